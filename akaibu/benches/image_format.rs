@@ -0,0 +1,49 @@
+use akaibu::resource::{ImageFormat, ResourceType};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use image::RgbaImage;
+
+/// Full HD CG with gradients and noise, roughly as hard to compress as
+/// typical event image
+fn sample_image() -> RgbaImage {
+    let mut seed = 0x1234_5678u32;
+    RgbaImage::from_fn(1920, 1080, |x, y| {
+        seed ^= seed << 13;
+        seed ^= seed >> 17;
+        seed ^= seed << 5;
+        let noise = (seed & 0xF) as u8;
+        image::Rgba([
+            (x / 8) as u8 ^ noise,
+            (y / 5) as u8 ^ noise,
+            ((x + y) / 12) as u8,
+            0xFF,
+        ])
+    })
+}
+
+fn write_resource(c: &mut Criterion) {
+    let image = sample_image();
+    let output = std::env::temp_dir()
+        .join(format!("akaibu_bench_{}.img", std::process::id()));
+    let mut group = c.benchmark_group("write_resource");
+    group.sample_size(10);
+    for format in &[ImageFormat::Png, ImageFormat::Bmp] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format.extension()),
+            format,
+            |b, format| {
+                b.iter(|| {
+                    ResourceType::RgbaImage {
+                        image: image.clone(),
+                    }
+                    .write_resource_as(&output, *format)
+                    .expect("Could not write image")
+                })
+            },
+        );
+        let _ = std::fs::remove_file(output.with_extension(format.extension()));
+    }
+    group.finish();
+}
+
+criterion_group!(benches, write_resource);
+criterion_main!(benches);
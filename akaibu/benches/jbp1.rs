@@ -0,0 +1,28 @@
+use akaibu::resource::ResourceMagic;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::path::PathBuf;
+
+/// 64x48 PB3 image, 12 JBP1 macroblocks of six 8x8 DCT blocks each
+fn sample_path() -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/pb3/input.pb3")
+}
+
+fn decode_blocks(c: &mut Criterion) {
+    let path = sample_path();
+    let buf = std::fs::read(&path).expect("Could not read sample");
+    let scheme = ResourceMagic::Pb3b.get_schemes().remove(0);
+    let mut group = c.benchmark_group("jbp1");
+    group.throughput(Throughput::Elements(12 * 6));
+    group.bench_function("pb3_decode", |b| {
+        b.iter(|| {
+            scheme
+                .convert_from_bytes(&path, buf.clone(), None)
+                .expect("Could not decode sample")
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, decode_blocks);
+criterion_main!(benches);
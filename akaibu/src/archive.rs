@@ -1,20 +1,119 @@
+use anyhow::Context;
 use bytes::Bytes;
 use itertools::Itertools;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use serde::{Deserialize, Serialize};
 use std::{
-    collections::BTreeMap,
+    collections::{btree_map, BTreeMap},
     ffi::OsStr,
     fmt::Debug,
-    fs::File,
     io::Write,
-    path::{Path, PathBuf},
+    path::{Component, Path, PathBuf},
 };
 
-use crate::resource::ResourceMagic;
+use crate::{
+    error::AkaibuError,
+    resource::{ImageFormat, ResourceMagic, ResourceType},
+    util::{self, text::display_name},
+};
 
 // Workaround until it is possible to return impl Trait in traits
+/// Opened archive. Entries are extracted from many threads at once through
+/// shared reference, so implementations read archive file with positioned
+/// reads of `RandomAccessFile` instead of seeking shared file cursor, and
+/// keep state built when opening archive, like index and keys, immutable.
 pub trait Archive: Sync + Send + Debug {
     fn extract(&self, entry: &FileEntry) -> anyhow::Result<FileContents>;
+    /// Write every entry under output path as stored, without conversion,
+    /// progress or manifest of [`crate::pipeline::extract_entries`].
+    /// Schemes with [`Archive::entry_location`] copy entries stored as is
+    /// through [`write_entries`], entries that have to be decrypted or
+    /// unpacked are held in memory whole while they are written.
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()>;
+    /// Get at most `len` first bytes of entry, used to sniff content type.
+    /// Archives storing entries as is should read only the prefix.
+    fn extract_prefix(
+        &self,
+        entry: &FileEntry,
+        len: usize,
+    ) -> anyhow::Result<FileContents> {
+        let file_contents = self.extract(entry)?;
+        let len = len.min(file_contents.contents.len());
+        Ok(FileContents {
+            contents: file_contents.contents.slice(..len),
+            type_hint: file_contents.type_hint,
+        })
+    }
+    /// Write entry to writer, returns number of bytes written. Entries with
+    /// [`Archive::entry_location`] are copied from archive file in chunks,
+    /// so they take constant memory whatever their size. Other entries are
+    /// extracted whole first.
+    fn extract_to(
+        &self,
+        entry: &FileEntry,
+        writer: &mut dyn Write,
+    ) -> anyhow::Result<u64> {
+        let (offset, size) = match self.entry_location(entry) {
+            Some(location) => location,
+            None => {
+                let file_contents = self.extract(entry)?;
+                writer.write_all(&file_contents.contents)?;
+                return Ok(file_contents.contents.len() as u64);
+            }
+        };
+        let mut buf = vec![0; size.min(crate::ONE_MB as u64) as usize];
+        let mut copied = 0;
+        while copied < size {
+            let len = (size - copied).min(buf.len() as u64) as usize;
+            self.read_raw(offset + copied, &mut buf[..len])?;
+            writer.write_all(&buf[..len])?;
+            copied += len as u64;
+        }
+        Ok(size)
+    }
+    /// Storage details of entry, empty when scheme does not expose them
+    fn entry_ext(&self, _entry: &FileEntry) -> FileEntryExt {
+        FileEntryExt::default()
+    }
+    /// Offset and size of entry data in archive file, so external tools can
+    /// read it directly. Only entries stored as is have location, schemes
+    /// decrypting or decompressing entry data return `None`.
+    fn entry_location(&self, _entry: &FileEntry) -> Option<(u64, u64)> {
+        None
+    }
+    /// Offset and size of bytes entry is decoded from by
+    /// [`Archive::decode_raw`]. Schemes returning it let neighbouring
+    /// entries be read from archive file in one go.
+    fn raw_location(&self, _entry: &FileEntry) -> Option<(u64, u64)> {
+        None
+    }
+    /// Offset and size of all bytes entry takes in archive file, checked by
+    /// [`crate::validate::validate_index`]. Schemes storing entries
+    /// encrypted or packed, or at offsets relative to data section, return
+    /// it next to their [`Archive::entry_location`].
+    fn stored_range(&self, entry: &FileEntry) -> Option<(u64, u64)> {
+        self.raw_location(entry)
+            .or_else(|| self.entry_location(entry))
+    }
+    /// Size of data [`Archive::stored_range`] offsets point into, size of
+    /// archive file unless archive is read from several files. `None` when
+    /// entries come from unrelated files and ranges can not be checked.
+    fn stored_size(&self, archive_path: &Path) -> anyhow::Result<Option<u64>> {
+        Ok(Some(std::fs::metadata(archive_path)?.len()))
+    }
+    /// Read bytes of archive file at given offset
+    fn read_raw(&self, _offset: u64, _buf: &mut [u8]) -> anyhow::Result<()> {
+        Err(AkaibuError::Unimplemented("Raw reads".to_string()).into())
+    }
+    /// Decrypt or decompress entry from bytes read at its
+    /// [`Archive::raw_location`]
+    fn decode_raw(
+        &self,
+        entry: &FileEntry,
+        _raw: Bytes,
+    ) -> anyhow::Result<FileContents> {
+        self.extract(entry)
+    }
 }
 
 // pub trait FileEntry: Debug {
@@ -43,8 +142,18 @@ impl FileContents {
         output_file_name: &Path,
         archive: Option<&Box<dyn Archive>>,
     ) -> anyhow::Result<()> {
-        if let Some(resource_type) = &self.type_hint {
-            let resource = resource_type
+        self.write_contents_as(output_file_name, archive, ImageFormat::Png)
+            .map(|_| ())
+    }
+    /// Contents converted with universal scheme of type hint, None for
+    /// entries written as stored
+    #[allow(clippy::borrowed_box)]
+    pub fn convert(
+        &self,
+        archive: Option<&Box<dyn Archive>>,
+    ) -> anyhow::Result<Option<ResourceType>> {
+        match &self.type_hint {
+            Some(resource_type) => resource_type
                 .get_schemes()
                 .get(0)
                 .expect("Expected universal scheme")
@@ -52,12 +161,93 @@ impl FileContents {
                     &PathBuf::new(),
                     self.contents.to_vec(),
                     archive,
-                )?;
-            resource.write_resource(&output_file_name)?;
-        } else {
-            File::create(output_file_name)?.write_all(&self.contents)?;
-        };
-        Ok(())
+                )
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+    /// Converted images are written in given format
+    #[allow(clippy::borrowed_box)]
+    pub fn write_contents_as(
+        &self,
+        output_file_name: &Path,
+        archive: Option<&Box<dyn Archive>>,
+        image_format: ImageFormat,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        match self.convert(archive)? {
+            Some(resource) => {
+                resource.write_resource_as(output_file_name, image_format)
+            }
+            None => {
+                util::write_atomic(output_file_name, &self.contents)?;
+                Ok(vec![output_file_name.to_path_buf()])
+            }
+        }
+    }
+}
+
+/// Folder on disk exposed as archive, so resources converted from loose
+/// files can look up files they reference the same way as inside archive.
+/// Entry paths are relative to root and matched case insensitively when
+/// exact path does not exist.
+#[derive(Debug, Clone)]
+pub struct DirectoryArchive {
+    root: PathBuf,
+}
+
+impl DirectoryArchive {
+    pub fn new(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+        }
+    }
+    /// Archive over folder containing given file
+    pub fn containing(file_path: &Path) -> anyhow::Result<Self> {
+        Ok(Self::new(
+            file_path.parent().context("Invalid path: At root dir")?,
+        ))
+    }
+    fn resolve(&self, full_path: &Path) -> Option<PathBuf> {
+        let path = self.root.join(full_path);
+        if path.is_file() {
+            return Some(path);
+        }
+        let file_name = full_path.file_name()?.to_str()?;
+        std::fs::read_dir(path.parent()?)
+            .ok()?
+            .filter_map(|dir_entry| dir_entry.ok())
+            .map(|dir_entry| dir_entry.path())
+            .find(|path| {
+                path.is_file()
+                    && path
+                        .file_name()
+                        .and_then(|name| name.to_str())
+                        .map_or(false, |name| {
+                            name.eq_ignore_ascii_case(file_name)
+                        })
+            })
+    }
+}
+
+impl Archive for DirectoryArchive {
+    fn extract(&self, entry: &FileEntry) -> anyhow::Result<FileContents> {
+        let path = self.resolve(&entry.full_path).ok_or_else(|| {
+            AkaibuError::Custom(format!(
+                "Could not find file: {:?}",
+                self.root.join(&entry.full_path)
+            ))
+        })?;
+        Ok(FileContents {
+            contents: Bytes::from(std::fs::read(path)?),
+            type_hint: None,
+        })
+    }
+    fn extract_all(&self, _output_path: &Path) -> anyhow::Result<()> {
+        Err(AkaibuError::Custom(format!(
+            "Files in {:?} are already extracted",
+            self.root
+        ))
+        .into())
     }
 }
 
@@ -69,6 +259,111 @@ pub struct FileEntry {
     pub file_size: u64,
 }
 
+impl FileEntry {
+    /// Entry named after last plain component of its path inside archive
+    pub fn new(full_path: PathBuf, file_offset: u64, file_size: u64) -> Self {
+        let file_name = normal_components(&full_path)
+            .last()
+            .unwrap_or_else(|| full_path.as_os_str())
+            .to_string_lossy()
+            .into_owned();
+        Self {
+            file_name,
+            full_path,
+            file_offset,
+            file_size,
+        }
+    }
+    /// Path entry is written to relative to output directory, see
+    /// [`output_path`]
+    pub fn output_path(&self) -> PathBuf {
+        output_path(&self.full_path)
+    }
+}
+
+/// Path entry stored under given name is written to relative to output
+/// directory. Root, `.` and `..` components of names stored in archive are
+/// dropped, so entries cannot be written outside of output directory.
+pub fn output_path(full_path: &Path) -> PathBuf {
+    normal_components(full_path).collect()
+}
+
+/// Write entries in parallel under their [`output_path`] through
+/// [`Archive::extract_to`], so entries stored as is are copied from archive
+/// file in chunks instead of being read whole
+pub(crate) fn write_entries(
+    archive: &dyn Archive,
+    entries: impl IntoIterator<Item = FileEntry>,
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    entries
+        .into_iter()
+        .collect::<Vec<FileEntry>>()
+        .par_iter()
+        .try_for_each(|entry| {
+            let output_file_name = output_path.join(entry.output_path());
+            std::fs::create_dir_all(
+                output_file_name
+                    .parent()
+                    .context("Could not get parent directory")?,
+            )?;
+            log::debug!(
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
+                entry
+            );
+            util::write_atomic_with(&output_file_name, |file| {
+                archive.extract_to(entry, file)
+            })?;
+            Ok(())
+        })
+}
+
+/// Entry as listed by [`crate::scheme::Scheme::list`], read from entry
+/// table without building directory tree
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileEntryMetadata {
+    pub full_path: PathBuf,
+    pub file_offset: u64,
+    pub file_size: u64,
+    pub ext: FileEntryExt,
+}
+
+impl FileEntryMetadata {
+    /// Metadata of entry of opened archive
+    pub fn of(archive: &dyn Archive, entry: &FileEntry) -> Self {
+        Self {
+            full_path: entry.full_path.clone(),
+            file_offset: entry.file_offset,
+            file_size: entry.file_size,
+            ext: archive.entry_ext(entry),
+        }
+    }
+}
+
+/// Names of path, without root, `.` and `..` components
+fn normal_components(path: &Path) -> impl Iterator<Item = &OsStr> {
+    path.components().filter_map(|component| match component {
+        Component::Normal(name) => Some(name),
+        _ => None,
+    })
+}
+
+/// How entry is stored inside archive.
+/// Helps with format research and debugging wrong output.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileEntryExt {
+    /// Decryption method applied to entry data
+    pub encryption: Option<String>,
+    /// Decompression method applied to entry data
+    pub compression: Option<String>,
+    /// Last modification time stored in archive, seconds since Unix epoch
+    pub modified: Option<u64>,
+    /// Name of archive whose entry of the same path this entry replaces,
+    /// see [`crate::overlay::OverlayArchive`]
+    pub overrides: Option<String>,
+}
+
 #[derive(Debug, Clone)]
 pub struct Directory {
     pub files: Vec<FileEntry>,
@@ -77,46 +372,79 @@ pub struct Directory {
 
 impl Directory {
     pub fn new(files: Vec<FileEntry>) -> Self {
+        Self::from_entries(files)
+    }
+    /// Tree of directories built from paths of entries. Only plain names
+    /// become directories, root, `.` and `..` components are skipped, so
+    /// every entry ends up inside tree. Names that are not valid UTF-8 are
+    /// converted lossily. Paths of entries are kept as they are.
+    pub fn from_entries(entries: impl IntoIterator<Item = FileEntry>) -> Self {
         let mut root_dir = Directory {
             files: Vec::new(),
             directories: BTreeMap::new(),
         };
-        for entry in files
+        for entry in entries
             .into_iter()
             .sorted_by(|a, b| a.full_path.cmp(&b.full_path))
         {
-            let dirs = entry.full_path.iter().collect::<Vec<&OsStr>>();
+            let names =
+                normal_components(&entry.full_path).collect::<Vec<&OsStr>>();
             let mut current = &mut root_dir;
-            if dirs.len() <= 1 {
-                current.files.push(entry);
-            } else {
-                for dir in &dirs[..dirs.len() - 1] {
+            if let Some((_, dirs)) = names.split_last() {
+                for dir in dirs {
                     current = current
                         .directories
-                        .entry(String::from(
-                            dir.to_str().expect("Not valid UTF-8"),
-                        ))
-                        .or_insert(Directory {
+                        .entry(dir.to_string_lossy().into_owned())
+                        .or_insert_with(|| Directory {
                             files: Vec::new(),
                             directories: BTreeMap::new(),
                         });
                 }
-                current.files.push(entry);
             }
+            current.files.push(entry);
         }
         root_dir
     }
     pub fn get_all_files<'a>(
         &'a self,
     ) -> Box<dyn Iterator<Item = &FileEntry> + 'a> {
-        Box::new(
-            self.files.iter().chain(
-                self.directories
-                    .values()
-                    .map(|directory| directory.get_all_files())
-                    .flatten(),
-            ),
-        )
+        Box::new(self.files())
+    }
+    /// Walk files of this directory and all subdirectories without
+    /// collecting them first
+    pub fn files(&self) -> Files<'_> {
+        Files {
+            stack: vec![(self.files.iter(), self.directories.values())],
+        }
+    }
+    /// Files whose full path starts with given path components. Only
+    /// directory matching prefix is walked.
+    pub fn files_with_prefix<'a>(
+        &'a self,
+        prefix: &Path,
+    ) -> impl Iterator<Item = &'a FileEntry> + 'a {
+        let dir_names = normal_components(prefix)
+            .map(|name| name.to_string_lossy().to_string())
+            .collect::<Vec<String>>();
+        // Prefix can also be path of single file
+        let prefix = prefix.to_path_buf();
+        let file = dir_names
+            .split_last()
+            .and_then(|(_, parent)| self.find_dir(parent))
+            .into_iter()
+            .flat_map(|parent| parent.files.iter())
+            .filter(move |entry| entry.full_path == prefix);
+        let files = self
+            .find_dir(&dir_names)
+            .map(|dir| dir.files())
+            .into_iter()
+            .flatten();
+        file.chain(files)
+    }
+    /// Find file entry by its full path inside archive
+    pub fn find_file(&self, full_path: &Path) -> Option<&FileEntry> {
+        self.files_with_prefix(full_path)
+            .find(|entry| entry.full_path == full_path)
     }
     pub fn find_dir(&self, dir_names: &[String]) -> Option<&Directory> {
         if dir_names.is_empty() {
@@ -129,6 +457,38 @@ impl Directory {
     }
 }
 
+/// Depth first iterator over files of directory and its subdirectories,
+/// files of directory come before files of its subdirectories
+#[derive(Debug, Clone)]
+pub struct Files<'a> {
+    stack: Vec<(
+        std::slice::Iter<'a, FileEntry>,
+        btree_map::Values<'a, String, Directory>,
+    )>,
+}
+
+impl<'a> Iterator for Files<'a> {
+    type Item = &'a FileEntry;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let (files, directories) = self.stack.last_mut()?;
+            if let Some(file) = files.next() {
+                return Some(file);
+            }
+            match directories.next() {
+                Some(directory) => self.stack.push((
+                    directory.files.iter(),
+                    directory.directories.values(),
+                )),
+                None => {
+                    self.stack.pop();
+                }
+            }
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct NavigableDirectory {
     root_dir: Directory,
@@ -144,6 +504,17 @@ impl NavigableDirectory {
     pub fn get_root_dir(&self) -> &Directory {
         &self.root_dir
     }
+    /// Walk every file of archive
+    pub fn files(&self) -> Files<'_> {
+        self.root_dir.files()
+    }
+    /// Files of archive whose full path starts with given path
+    pub fn files_with_prefix<'a>(
+        &'a self,
+        prefix: &Path,
+    ) -> impl Iterator<Item = &'a FileEntry> + 'a {
+        self.root_dir.files_with_prefix(prefix)
+    }
     pub fn get_current(&self) -> &Directory {
         self.root_dir
             .find_dir(&self.current)
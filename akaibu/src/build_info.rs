@@ -0,0 +1,146 @@
+use crate::{
+    magic::Archive, resource::ResourceMagic, scheme::registry::SchemeRegistry,
+    util, Resources,
+};
+use enum_iterator::IntoEnumIterator;
+use serde::Serialize;
+use std::fmt;
+
+macro_rules! enabled_features {
+    ($($feature:literal),* $(,)?) => {
+        [$((cfg!(feature = $feature), $feature)),*]
+            .iter()
+            .filter(|(enabled, _)| *enabled)
+            .map(|(_, feature)| *feature)
+            .collect::<Vec<&'static str>>()
+    };
+}
+
+/// Database file with its SHA-1
+#[derive(Debug, Clone, Serialize)]
+pub struct DatabaseFile {
+    pub path: String,
+    pub sha1: String,
+}
+
+/// Version and scheme database state of library, attached to issue reports
+/// so they can be reproduced with the same keys and formats
+#[derive(Debug, Clone, Serialize)]
+pub struct BuildInfo {
+    pub version: &'static str,
+    /// Format features library was built with
+    pub features: Vec<&'static str>,
+    pub archive_schemes: usize,
+    pub resource_schemes: usize,
+    /// Key tables and other resources embedded in library
+    pub embedded: Vec<DatabaseFile>,
+    /// Key tables read from config directory, merged with embedded ones
+    pub user: Vec<DatabaseFile>,
+}
+
+/// Version, enabled formats and hashes of scheme database files
+pub fn build_info() -> BuildInfo {
+    let mut embedded = Resources::iter()
+        .filter_map(|path| {
+            let file = Resources::get(&path)?;
+            Some(DatabaseFile {
+                path: path.to_string(),
+                sha1: sha1::Sha1::from(&file).digest().to_string(),
+            })
+        })
+        .collect::<Vec<DatabaseFile>>();
+    embedded.sort_by(|a, b| a.path.cmp(&b.path));
+    let registry = SchemeRegistry::global();
+    let user = registry
+        .user_tables()
+        .into_iter()
+        .filter_map(|path| {
+            let sha1 = util::sha1_file(&path).ok()?;
+            let path = registry
+                .config_dir()
+                .and_then(|config_dir| path.strip_prefix(config_dir).ok())
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            Some(DatabaseFile { path, sha1 })
+        })
+        .collect();
+    BuildInfo {
+        version: env!("CARGO_PKG_VERSION"),
+        features: enabled_features!(
+            "fmt-acv1",
+            "fmt-amusepac",
+            "fmt-buriko",
+            "fmt-cpz7",
+            "fmt-esc-arc2",
+            "fmt-gxp",
+            "fmt-iar",
+            "fmt-ikura",
+            "fmt-kaguya",
+            "fmt-link6",
+            "fmt-malie",
+            "fmt-mbl",
+            "fmt-moonstone-dat",
+            "fmt-nekopack",
+            "fmt-otomate-bin",
+            "fmt-packdat",
+            "fmt-paz",
+            "fmt-pf8",
+            "fmt-qlie",
+            "fmt-silky",
+            "fmt-tactics",
+            "fmt-willplus",
+            "fmt-ykc",
+            "fmt-ypf",
+            "fmt-akb",
+            "fmt-compressedbg",
+            "fmt-crxg",
+            "fmt-csx",
+            "fmt-dpng",
+            "fmt-escr",
+            "fmt-g00",
+            "fmt-gyu",
+            "fmt-mcg",
+            "fmt-pb2",
+            "fmt-pb3b",
+            "fmt-pgd",
+            "fmt-pna",
+            "fmt-prs",
+            "fmt-tlg",
+            "fmt-wip",
+            "fmt-ycg",
+            "fmt-ykg",
+        ),
+        archive_schemes: Archive::into_enum_iter()
+            .map(|archive| archive.get_schemes().len())
+            .sum(),
+        resource_schemes: ResourceMagic::into_enum_iter()
+            .map(|resource| resource.get_schemes().len())
+            .sum(),
+        embedded,
+        user,
+    }
+}
+
+impl fmt::Display for BuildInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "akaibu {}", self.version)?;
+        writeln!(f, "Features: {}", self.features.join(" "))?;
+        writeln!(
+            f,
+            "Schemes: {} archive, {} resource",
+            self.archive_schemes, self.resource_schemes
+        )?;
+        writeln!(f, "Embedded database:")?;
+        for file in &self.embedded {
+            writeln!(f, "  {} {}", file.sha1, file.path)?;
+        }
+        if !self.user.is_empty() {
+            writeln!(f, "User key tables:")?;
+            for file in &self.user {
+                writeln!(f, "  {} {}", file.sha1, file.path)?;
+            }
+        }
+        Ok(())
+    }
+}
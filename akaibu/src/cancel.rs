@@ -0,0 +1,71 @@
+use crate::error::AkaibuError;
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc, Arc,
+    },
+    time::Duration,
+};
+
+/// How often [`run_cancellable`] checks whether it was cancelled
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Flag shared between operation and whoever may want to stop it. Clones
+/// refer to the same flag.
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+    /// Error out when cancelled, for operations checking token between
+    /// their steps
+    pub fn check(&self) -> Result<(), AkaibuError> {
+        if self.is_cancelled() {
+            Err(AkaibuError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Run operation on worker thread and wait for its result, returning
+/// [`AkaibuError::Cancelled`] shortly after token is cancelled. Decoders do
+/// not check token themselves, so cancelled operation is left to finish in
+/// background and its result is dropped.
+pub fn run_cancellable<T, F>(
+    token: &CancellationToken,
+    operation: F,
+) -> anyhow::Result<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> anyhow::Result<T> + Send + 'static,
+{
+    token.check()?;
+    let (sender, receiver) = mpsc::channel();
+    std::thread::Builder::new()
+        .name("akaibu-worker".to_string())
+        .spawn(move || {
+            // Receiver is gone when operation was cancelled
+            let _ = sender.send(operation());
+        })?;
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(result) => return result,
+            Err(mpsc::RecvTimeoutError::Timeout) => token.check()?,
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err(AkaibuError::Custom(
+                    "Worker thread panicked".to_string(),
+                )
+                .into())
+            }
+        }
+    }
+}
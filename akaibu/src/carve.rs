@@ -0,0 +1,295 @@
+use crate::{magic::SNIFF_SIZE, resource::ResourceMagic, util};
+use positioned_io::{RandomAccessFile, ReadAt};
+use scroll::{Pread, BE, LE};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+};
+
+/// Bytes of file scanned at once
+const CHUNK_SIZE: usize = 1 << 20;
+/// First bytes of magics carved files can start with, other positions are
+/// not passed to magic detection at all
+const MAGIC_START: &[u8] = &[0x89, 0x87, b'O', b'R', b'T'];
+#[cfg(feature = "fmt-tlg")]
+/// Versions of TLG carved, `TLG` alone matches too much of random data
+const TLG_MAGICS: &[&[u8]] = &[
+    b"TLG5.0\x00raw\x1a",
+    b"TLG6.0\x00raw\x1a",
+    b"TLG0.0\x00sds\x1a",
+];
+#[cfg(feature = "fmt-tlg")]
+const TLG5_MAGIC: &[u8] = b"TLG5.0\x00raw\x1a";
+
+/// Resource found inside unrecognized file by its magic
+#[derive(Debug, Clone)]
+pub struct CarvedFile {
+    /// Numbered name file is written with, in order of offsets
+    pub file_name: String,
+    pub offset: u64,
+    pub size: u64,
+    pub resource: ResourceMagic,
+}
+
+/// Scan file for PNG, OGG, TLG and RIFF magics. File is read in chunks, so
+/// archives of any size can be scanned, and bytes of carved files with known
+/// size are skipped. Files whose end can not be found from their headers
+/// (TLG6 and wrapped TLG) end where next carved file starts.
+pub fn scan(file_path: &Path) -> anyhow::Result<Vec<CarvedFile>> {
+    let file = util::fs::open_archive_file(file_path)?;
+    let file_size = std::fs::metadata(file_path)?.len();
+    let mut reader = util::fs::open_shared(file_path)?;
+
+    let mut found: Vec<(u64, ResourceMagic, Option<u64>)> = Vec::new();
+    let mut chunk = vec![0; CHUNK_SIZE];
+    let mut window = Vec::with_capacity(CHUNK_SIZE + SNIFF_SIZE);
+    let mut window_offset = 0;
+    // First offset not covered by carved file
+    let mut next = 0;
+    loop {
+        let read = reader.read(&mut chunk)?;
+        window.extend_from_slice(&chunk[..read]);
+        // Magics crossing end of window are checked with next chunk
+        let scan_end = if read == 0 {
+            window.len()
+        } else {
+            window.len().saturating_sub(SNIFF_SIZE)
+        };
+        for pos in 0..scan_end {
+            let offset = window_offset + pos as u64;
+            if offset < next || !MAGIC_START.contains(&window[pos]) {
+                continue;
+            }
+            let head = &window[pos..window.len().min(pos + SNIFF_SIZE)];
+            let resource = ResourceMagic::parse_magic(head);
+            if let Some(size) = carved_size(&file, file_size, offset, &resource)
+            {
+                log::debug!(
+                    "Carved {:?} at {:#X}, size {:?}",
+                    resource,
+                    offset,
+                    size
+                );
+                if let Some(size) = size {
+                    next = offset + size;
+                }
+                found.push((offset, resource, size));
+            }
+        }
+        if read == 0 {
+            break;
+        }
+        let window_end = window_offset + window.len() as u64;
+        if next > window_end {
+            reader.seek(SeekFrom::Start(next))?;
+            window.clear();
+            window_offset = next;
+        } else {
+            window.drain(..scan_end);
+            window_offset += scan_end as u64;
+        }
+    }
+
+    let ends = found
+        .iter()
+        .skip(1)
+        .map(|(offset, _, _)| *offset)
+        .chain(std::iter::once(file_size))
+        .collect::<Vec<u64>>();
+    Ok(found
+        .into_iter()
+        .zip(ends)
+        .enumerate()
+        .map(|(i, ((offset, resource, size), end))| CarvedFile {
+            file_name: format!(
+                "{:05}.{}",
+                i,
+                extension(&file, offset, &resource)
+            ),
+            offset,
+            size: size.unwrap_or(end - offset),
+            resource,
+        })
+        .collect())
+}
+
+/// Write files found by `scan` into output directory as numbered files
+pub fn carve(
+    file_path: &Path,
+    output_path: &Path,
+) -> anyhow::Result<Vec<CarvedFile>> {
+    let carved = scan(file_path)?;
+    if carved.is_empty() {
+        return Ok(carved);
+    }
+    std::fs::create_dir_all(output_path)?;
+    let file = util::fs::open_archive_file(file_path)?;
+    for entry in &carved {
+        let mut buf = vec![0; entry.size as usize];
+        file.read_exact_at(entry.offset, &mut buf)?;
+        File::create(output_path.join(&entry.file_name))?.write_all(&buf)?;
+    }
+    Ok(carved)
+}
+
+/// Size of file starting at offset, None when resource is not carved or its
+/// headers are not valid, Some(None) when its end is not known
+fn carved_size(
+    file: &RandomAccessFile,
+    file_size: u64,
+    offset: u64,
+    resource: &ResourceMagic,
+) -> Option<Option<u64>> {
+    let size = match resource {
+        ResourceMagic::Png => Some(png_size(file, file_size, offset)?),
+        ResourceMagic::Ogg => Some(ogg_size(file, file_size, offset)?),
+        ResourceMagic::Wav | ResourceMagic::Riff => {
+            Some(riff_size(file, file_size, offset)?)
+        }
+        #[cfg(feature = "fmt-tlg")]
+        ResourceMagic::Tlg => {
+            let mut magic = [0; 11];
+            file.read_exact_at(offset, &mut magic).ok()?;
+            if !TLG_MAGICS.contains(&&magic[..]) {
+                return None;
+            }
+            tlg5_size(file, file_size, offset)
+        }
+        _ => return None,
+    };
+    Some(size)
+}
+
+/// Chunks are walked up to IEND
+fn png_size(
+    file: &RandomAccessFile,
+    file_size: u64,
+    offset: u64,
+) -> Option<u64> {
+    let mut pos = offset + 8;
+    loop {
+        let mut header = [0; 8];
+        file.read_exact_at(pos, &mut header).ok()?;
+        let length = header.pread_with::<u32>(0, BE).ok()? as u64;
+        let chunk_type = &header[4..];
+        if !chunk_type.iter().all(u8::is_ascii_alphabetic) {
+            return None;
+        }
+        // Length, type, data and CRC
+        pos += 12 + length;
+        if pos > file_size {
+            return None;
+        }
+        if chunk_type == b"IEND" {
+            return Some(pos - offset);
+        }
+    }
+}
+
+/// Pages are walked up to one marked as end of stream, or to first bytes
+/// that are not page
+fn ogg_size(
+    file: &RandomAccessFile,
+    file_size: u64,
+    offset: u64,
+) -> Option<u64> {
+    let mut pos = offset;
+    loop {
+        let mut header = [0; 27];
+        if file.read_exact_at(pos, &mut header).is_err()
+            || &header[..4] != b"OggS"
+            || header[4] != 0
+        {
+            return if pos > offset {
+                Some(pos - offset)
+            } else {
+                None
+            };
+        }
+        let header_type = header[5];
+        let mut segments = vec![0; header[26] as usize];
+        file.read_exact_at(pos + 27, &mut segments).ok()?;
+        let data_size = segments.iter().map(|&s| s as u64).sum::<u64>();
+        let end = pos + 27 + segments.len() as u64 + data_size;
+        if end > file_size {
+            return if pos > offset {
+                Some(pos - offset)
+            } else {
+                None
+            };
+        }
+        pos = end;
+        if header_type & 4 != 0 {
+            return Some(pos - offset);
+        }
+    }
+}
+
+/// Size stored in header, padded to even length like RIFF chunks
+fn riff_size(
+    file: &RandomAccessFile,
+    file_size: u64,
+    offset: u64,
+) -> Option<u64> {
+    let mut header = [0; 8];
+    file.read_exact_at(offset, &mut header).ok()?;
+    let size = header.pread_with::<u32>(4, LE).ok()? as u64;
+    if size < 4 {
+        return None;
+    }
+    let size = 8 + size;
+    if offset + size > file_size {
+        return None;
+    }
+    Some((size + size % 2).min(file_size - offset))
+}
+
+/// TLG5 blocks are walked like when decoding, end of other versions is not
+/// known
+#[cfg(feature = "fmt-tlg")]
+fn tlg5_size(
+    file: &RandomAccessFile,
+    file_size: u64,
+    offset: u64,
+) -> Option<u64> {
+    let mut header = [0; 24];
+    file.read_exact_at(offset, &mut header).ok()?;
+    if !header.starts_with(TLG5_MAGIC) {
+        return None;
+    }
+    let channels = header[11] as u64;
+    let height = header.pread_with::<u32>(16, LE).ok()? as u64;
+    let block_height = header.pread_with::<u32>(20, LE).ok()? as u64;
+    if channels == 0 || height == 0 || block_height == 0 {
+        return None;
+    }
+    let blocks = (height - 1) / block_height.min(height) + 1;
+    let mut pos = offset + 24 + blocks * 4;
+    for _ in 0..blocks * channels {
+        let mut block = [0; 5];
+        file.read_exact_at(pos, &mut block).ok()?;
+        pos += 5 + block.pread_with::<u32>(1, LE).ok()? as u64;
+        if pos > file_size {
+            return None;
+        }
+    }
+    Some(pos - offset)
+}
+
+/// Canonical extension of carved resource, RIFF ones by their form type
+fn extension(
+    file: &RandomAccessFile,
+    offset: u64,
+    resource: &ResourceMagic,
+) -> &'static str {
+    if let Some(extension) = resource.usual_extension() {
+        return extension;
+    }
+    let mut form = [0; 4];
+    match file.read_exact_at(offset + 8, &mut form) {
+        Ok(_) if &form == b"WEBP" => "webp",
+        Ok(_) if &form == b"AVI " => "avi",
+        _ => "riff",
+    }
+}
@@ -0,0 +1,226 @@
+use crate::{
+    archive::FileEntry,
+    error,
+    magic::{self, Archive},
+    manifest::{EntryOutput, EntryStatus, Manifest},
+    pipeline::{self, ArchiveHandle, ExtractOptions},
+    progress::ProgressTracker,
+    scheme::Scheme,
+    util::{self, text::display_name},
+};
+use anyhow::Context;
+use enum_iterator::IntoEnumIterator;
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// File name of summary written into output directory
+pub const SUMMARY_FILE_NAME: &str = "summary.json";
+
+/// Outcome of single archive of game directory
+#[derive(Debug, Clone, Serialize)]
+pub struct ArchiveSummary {
+    pub path: PathBuf,
+    pub scheme: Option<String>,
+    pub output_dir: Option<PathBuf>,
+    pub entries: usize,
+    /// Entries converted to common format, the rest is written as is
+    pub converted: usize,
+    pub failed: usize,
+    /// Why archive could not be opened
+    pub error: Option<String>,
+}
+
+/// Outcome of whole game directory dump
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DumpSummary {
+    pub akaibu_version: String,
+    /// Scheme picked for each archive type found
+    pub schemes: BTreeMap<String, String>,
+    pub archives: Vec<ArchiveSummary>,
+}
+
+impl DumpSummary {
+    pub fn write(&self, output_path: &Path) -> anyhow::Result<()> {
+        File::create(output_path)?
+            .write_all(&serde_json::to_vec_pretty(self)?)?;
+        Ok(())
+    }
+}
+
+/// Extract and convert every recognized archive under game directory into
+/// `<output>/<archive dir relative to game dir>/<archive stem>/`.
+/// Scheme is probed once per archive type and reused for other archives of
+/// the same engine, probing again only when it fails.
+pub fn dump_game(
+    game_dir: &Path,
+    output_dir: &Path,
+    options: &ExtractOptions,
+    mut on_archive: impl FnMut(&ArchiveSummary),
+) -> anyhow::Result<DumpSummary> {
+    let mut summary = DumpSummary {
+        akaibu_version: env!("CARGO_PKG_VERSION").to_string(),
+        ..Default::default()
+    };
+    let mut files = Vec::new();
+    util::walk_files(game_dir, &mut files)?;
+    files.sort();
+    for file in files {
+        let archive = match magic::sniff(&file) {
+            Ok(sniffed) => Archive::detect(&file, &sniffed),
+            Err(err) => {
+                log::debug!("Could not read {:?}: {}", file, err);
+                continue;
+            }
+        };
+        if let Archive::NotRecognized = archive {
+            continue;
+        }
+        let relative = file.strip_prefix(game_dir).unwrap_or(&file);
+        let archive_output_dir = output_dir
+            .join(relative.parent().unwrap_or_else(|| Path::new("")))
+            .join(relative.file_stem().context("Could not get file name")?);
+        let mut archive_summary = ArchiveSummary {
+            path: relative.to_path_buf(),
+            scheme: None,
+            output_dir: None,
+            entries: 0,
+            converted: 0,
+            failed: 0,
+            error: None,
+        };
+        match resolve_scheme(
+            &file,
+            &archive,
+            options.password.as_deref(),
+            &mut summary.schemes,
+        ) {
+            Ok((scheme, handle)) => {
+                archive_summary.scheme = Some(scheme.get_name());
+                archive_summary.entries = handle.dir().files().count();
+                // Images are stamped with file name of their own archive
+                let options = ExtractOptions {
+                    stamp_source: options.stamp_source.as_ref().and_then(
+                        |_| {
+                            relative.file_name().map(|name| {
+                                display_name(Path::new(name)).to_string()
+                            })
+                        },
+                    ),
+                    ..options.clone()
+                };
+                let (converted, failed) =
+                    dump_archive(&handle, &file, &archive_output_dir, &options);
+                archive_summary.converted = converted;
+                archive_summary.failed = failed;
+                archive_summary.output_dir = Some(archive_output_dir);
+            }
+            Err(err) => archive_summary.error = Some(err.to_string()),
+        }
+        on_archive(&archive_summary);
+        summary.archives.push(archive_summary);
+    }
+    Ok(summary)
+}
+
+/// Open archive with scheme already picked for its type, or probe every
+/// scheme and remember the best one
+fn resolve_scheme(
+    file: &Path,
+    archive: &Archive,
+    password: Option<&str>,
+    resolved: &mut BTreeMap<String, String>,
+) -> anyhow::Result<(Box<dyn Scheme>, ArchiveHandle)> {
+    let archive_name = format!("{:?}", archive);
+    if let Some(scheme_name) = resolved.get(&archive_name) {
+        let scheme = archive
+            .get_schemes()
+            .into_iter()
+            .find(|scheme| &scheme.get_name() == scheme_name);
+        if let Some(scheme) = scheme {
+            match ArchiveHandle::open_with_password(
+                scheme.as_ref(),
+                file,
+                password,
+            ) {
+                Ok(handle) => return Ok((scheme, handle)),
+                Err(err) => log::debug!(
+                    "{} could not open {:?}, probing: {}",
+                    scheme_name,
+                    file,
+                    err
+                ),
+            }
+        }
+    }
+    let best = crate::probe(file)?
+        .into_iter()
+        .find(|result| result.failure.is_none())
+        .context("No scheme could read archive")?;
+    let scheme = Archive::into_enum_iter()
+        .filter(|archive| format!("{:?}", archive) == best.archive)
+        .flat_map(|archive| archive.get_schemes())
+        .find(|scheme| scheme.get_name() == best.scheme)
+        .context("Probed scheme not found")?;
+    let handle =
+        ArchiveHandle::open_with_password(scheme.as_ref(), file, password)?;
+    resolved.insert(best.archive, best.scheme);
+    Ok((scheme, handle))
+}
+
+/// Extract archive the same way as single archive extraction, see
+/// [`pipeline::extract_entries`]. Broken entries do not stop the rest.
+/// Returns number of converted and failed entries.
+fn dump_archive(
+    handle: &ArchiveHandle,
+    archive_path: &Path,
+    output_dir: &Path,
+    options: &ExtractOptions,
+) -> (usize, usize) {
+    let manifest = Manifest::new(archive_path);
+    let entries = handle.dir().files().cloned().collect::<Vec<FileEntry>>();
+    let options = ExtractOptions {
+        keep_going: true,
+        ..options.clone()
+    };
+    if let Err(err) = pipeline::extract_entries(
+        handle.archive(),
+        &entries,
+        output_dir,
+        &ProgressTracker::new(&entries),
+        Some(&manifest),
+        &options,
+    ) {
+        // Failed entries were already logged one by one
+        if error::entry_failures(&err).is_none() {
+            log::error!("{:?}: {}", archive_path, error::describe(&err));
+        }
+    }
+    if options.mapping {
+        let mapping_path = manifest.default_mapping_path(output_dir);
+        if let Err(err) = manifest.write_mapping(&mapping_path) {
+            log::error!("Could not write mapping {:?}: {}", mapping_path, err);
+        }
+    }
+    let recorded = manifest.entries();
+    let converted = recorded
+        .iter()
+        .filter(|entry| {
+            matches!(
+                &entry.status,
+                EntryStatus::Extracted { output: Some(output), .. }
+                    if output.format != EntryOutput::STORED
+            )
+        })
+        .count();
+    // Entries never reached when extraction stopped count as failed too
+    let done = recorded
+        .iter()
+        .filter(|entry| !matches!(entry.status, EntryStatus::Failed { .. }))
+        .count();
+    (converted, entries.len().saturating_sub(done))
+}
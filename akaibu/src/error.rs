@@ -1,14 +1,90 @@
 use std::path::PathBuf;
 use thiserror::Error;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum AkaibuError {
     #[error("Unrecognized format: {0} {1:X?}")]
     UnrecognizedFormat(PathBuf, Vec<u8>),
     #[error("Unimplemented: {0}")]
     Unimplemented(String),
+    #[error("Missing {key_type} for {scheme}")]
+    MissingKey { scheme: String, key_type: String },
+    #[error("Invalid embedded resource {path}: {reason}")]
+    EmbeddedResource { path: String, reason: String },
+    #[error("Malformed compressed data: {0}")]
+    MalformedData(String),
+    #[error("Decompressed size {size} is over limit of {limit} bytes")]
+    OutputTooLarge { size: usize, limit: usize },
+    #[error("{scheme} version {found} found; supported: {expected}")]
+    UnsupportedVersion {
+        scheme: String,
+        found: String,
+        expected: String,
+    },
+    #[error("Password required for {scheme}")]
+    PasswordRequired { scheme: String },
+    #[error("Cancelled")]
+    Cancelled,
+    #[error(
+        "{0:?} is in use by another program, close the game and try again"
+    )]
+    FileInUse(PathBuf),
+    #[error("{} entries could not be extracted", .0.len())]
+    EntriesFailed(Vec<EntryFailure>),
     #[error("{0}")]
     Custom(String),
     #[error("Unknown error")]
     Unknown,
 }
+
+/// Entry skipped by extraction that keeps going after failures
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EntryFailure {
+    pub path: PathBuf,
+    pub reason: String,
+}
+
+/// Unsupported version found anywhere in error chain, so it can be reported
+/// even when wrapped in context
+pub fn unsupported_version(err: &anyhow::Error) -> Option<&AkaibuError> {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<AkaibuError>())
+        .find(|e| matches!(e, AkaibuError::UnsupportedVersion { .. }))
+}
+
+/// Whether operation failed only because it was cancelled
+pub fn is_cancelled(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<AkaibuError>())
+        .any(|e| matches!(e, AkaibuError::Cancelled))
+}
+
+/// Whether archive could not be opened without password or with the one
+/// given, so user can be asked for it
+pub fn password_required(err: &anyhow::Error) -> bool {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<AkaibuError>())
+        .any(|e| matches!(e, AkaibuError::PasswordRequired { .. }))
+}
+
+/// Entries that failed when extraction kept going past them, found anywhere
+/// in error chain
+pub fn entry_failures(err: &anyhow::Error) -> Option<&[EntryFailure]> {
+    err.chain()
+        .filter_map(|e| e.downcast_ref::<AkaibuError>())
+        .find_map(|e| match e {
+            AkaibuError::EntriesFailed(failures) => Some(failures.as_slice()),
+            _ => None,
+        })
+}
+
+/// Message shown to user, with unsupported version appended when outer
+/// context would hide it
+pub fn describe(err: &anyhow::Error) -> String {
+    match unsupported_version(err) {
+        Some(version) if version.to_string() != err.to_string() => {
+            format!("{}: {}", err, version)
+        }
+        _ => err.to_string(),
+    }
+}
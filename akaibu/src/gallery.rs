@@ -0,0 +1,338 @@
+use crate::{
+    archive::FileEntry,
+    error::AkaibuError,
+    pipeline::{ArchiveHandle, Converted, ExtractOptions},
+    resource::ResourceType,
+};
+use crc_any::CRC;
+use flate2::{write::ZlibEncoder, Compression};
+use image::{png::PngEncoder, ColorType, RgbaImage};
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// Single file bundling every converted image of archive, for quick review
+/// of CG sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GalleryFormat {
+    /// Zip of PNG images
+    Cbz,
+    /// PDF with one page per image
+    Pdf,
+}
+
+impl GalleryFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Cbz => "cbz",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+impl FromStr for GalleryFormat {
+    type Err = AkaibuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "cbz" => Ok(Self::Cbz),
+            "pdf" => Ok(Self::Pdf),
+            _ => Err(AkaibuError::Custom(format!(
+                "Unknown gallery format: {}, expected cbz or pdf",
+                s
+            ))),
+        }
+    }
+}
+
+/// Convert entries ordered by path and write every image into gallery.
+/// Entries that fail to convert or are not images are skipped.
+/// Returns number of pages written.
+pub fn write_gallery(
+    handle: &ArchiveHandle,
+    output_path: &Path,
+    format: GalleryFormat,
+    options: &ExtractOptions,
+) -> anyhow::Result<usize> {
+    let mut entries = handle.dir().files().collect::<Vec<&FileEntry>>();
+    entries.sort_by(|a, b| a.full_path.cmp(&b.full_path));
+    let pages = handle
+        .convert_entries(entries, options)
+        .filter_map(|converted| match converted {
+            Ok(converted) => Some(converted),
+            Err(err) => {
+                log::error!("Could not convert entry: {}", err);
+                None
+            }
+        })
+        .flat_map(|converted| {
+            let images = match converted.contents {
+                Converted::Resource(ResourceType::RgbaImage { image }) => {
+                    vec![image]
+                }
+                Converted::Resource(ResourceType::SpriteSheet { sprites }) => {
+                    sprites
+                }
+                Converted::Resource(ResourceType::Animation { mut frames })
+                    if !frames.is_empty() =>
+                {
+                    vec![frames.remove(0)]
+                }
+                _ => vec![],
+            };
+            let name = converted.entry.full_path.to_string_lossy().to_string();
+            images.into_iter().map(move |image| (name.clone(), image))
+        });
+
+    let mut writer =
+        OffsetWriter::new(BufWriter::new(File::create(output_path)?));
+    let page_count = match format {
+        GalleryFormat::Cbz => write_cbz(&mut writer, pages)?,
+        GalleryFormat::Pdf => write_pdf(&mut writer, pages)?,
+    };
+    writer.flush()?;
+    Ok(page_count)
+}
+
+/// Keeps track of bytes written, both formats store offsets of their parts
+#[derive(Debug)]
+struct OffsetWriter<W: Write> {
+    inner: W,
+    offset: u64,
+}
+
+impl<W: Write> OffsetWriter<W> {
+    fn new(inner: W) -> Self {
+        Self { inner, offset: 0 }
+    }
+}
+
+impl<W: Write> Write for OffsetWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.offset += written as u64;
+        Ok(written)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+fn encode_png(image: &RgbaImage) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    PngEncoder::new(&mut png).encode(
+        image,
+        image.width(),
+        image.height(),
+        ColorType::Rgba8,
+    )?;
+    Ok(png)
+}
+
+/// Pages are stored without compression, PNG data is already compressed
+fn write_cbz<W: Write>(
+    writer: &mut OffsetWriter<W>,
+    pages: impl Iterator<Item = (String, RgbaImage)>,
+) -> anyhow::Result<usize> {
+    let mut central_directory = Vec::new();
+    let mut count = 0;
+    for (index, (name, image)) in pages.enumerate() {
+        let png = encode_png(&image)?;
+        // Page number first so readers keep path order
+        let file_name = format!(
+            "{:04}_{}.png",
+            index,
+            name.replace(|c: char| c == '/' || c == '\\', "_")
+        );
+        let mut crc = CRC::crc32();
+        crc.digest(&png);
+        let crc = crc.get_crc() as u32;
+        let local_header_offset = writer.offset as u32;
+
+        let mut fields = Vec::new();
+        // Version needed, flags (UTF-8 names), method (stored), time, date
+        fields.extend_from_slice(&20u16.to_le_bytes());
+        fields.extend_from_slice(&0x0800u16.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+        fields.extend_from_slice(&0x21u16.to_le_bytes());
+        fields.extend_from_slice(&crc.to_le_bytes());
+        fields.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(png.len() as u32).to_le_bytes());
+        fields.extend_from_slice(&(file_name.len() as u16).to_le_bytes());
+        fields.extend_from_slice(&0u16.to_le_bytes());
+
+        writer.write_all(b"PK\x03\x04")?;
+        writer.write_all(&fields)?;
+        writer.write_all(file_name.as_bytes())?;
+        writer.write_all(&png)?;
+
+        central_directory.extend_from_slice(b"PK\x01\x02");
+        // Version made by
+        central_directory.extend_from_slice(&20u16.to_le_bytes());
+        central_directory.extend_from_slice(&fields);
+        // Comment length, disk, internal and external attributes
+        central_directory.extend_from_slice(&[0; 10]);
+        central_directory.extend_from_slice(&local_header_offset.to_le_bytes());
+        central_directory.extend_from_slice(file_name.as_bytes());
+        count += 1;
+    }
+    let central_directory_offset = writer.offset as u32;
+    writer.write_all(&central_directory)?;
+    writer.write_all(b"PK\x05\x06")?;
+    writer.write_all(&[0; 4])?;
+    writer.write_all(&(count as u16).to_le_bytes())?;
+    writer.write_all(&(count as u16).to_le_bytes())?;
+    writer.write_all(&(central_directory.len() as u32).to_le_bytes())?;
+    writer.write_all(&central_directory_offset.to_le_bytes())?;
+    writer.write_all(&0u16.to_le_bytes())?;
+    Ok(count)
+}
+
+/// Every image is own page of its size, with alpha kept as soft mask
+fn write_pdf<W: Write>(
+    writer: &mut OffsetWriter<W>,
+    pages: impl Iterator<Item = (String, RgbaImage)>,
+) -> anyhow::Result<usize> {
+    const CATALOG: usize = 1;
+    const PAGES: usize = 2;
+    // Offsets of objects, index is object number - 1
+    let mut offsets = vec![0; 2];
+    let mut page_ids = Vec::new();
+    writer.write_all(b"%PDF-1.4\n%\xE2\xE3\xCF\xD3\n")?;
+
+    for (_, image) in pages {
+        let (width, height) = image.dimensions();
+        let mut rgb = Vec::with_capacity(image.len() / 4 * 3);
+        let mut alpha = Vec::with_capacity(image.len() / 4);
+        for pixel in image.pixels() {
+            rgb.extend_from_slice(&pixel.0[..3]);
+            alpha.push(pixel.0[3]);
+        }
+        let first_id = offsets.len() + 1;
+        offsets.resize(offsets.len() + 4, 0);
+        let (image_id, mask_id, content_id, page_id) =
+            (first_id, first_id + 1, first_id + 2, first_id + 3);
+
+        let mask = deflate(&alpha)?;
+        write_object(
+            writer,
+            &mut offsets,
+            mask_id,
+            &format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceGray /BitsPerComponent 8 \
+                 /Filter /FlateDecode /Length {} >>",
+                width,
+                height,
+                mask.len()
+            ),
+            Some(&mask),
+        )?;
+        let rgb = deflate(&rgb)?;
+        write_object(
+            writer,
+            &mut offsets,
+            image_id,
+            &format!(
+                "<< /Type /XObject /Subtype /Image /Width {} /Height {} \
+                 /ColorSpace /DeviceRGB /BitsPerComponent 8 /SMask {} 0 R \
+                 /Filter /FlateDecode /Length {} >>",
+                width,
+                height,
+                mask_id,
+                rgb.len()
+            ),
+            Some(&rgb),
+        )?;
+        let content = format!("q {} 0 0 {} 0 0 cm /Im Do Q", width, height);
+        write_object(
+            writer,
+            &mut offsets,
+            content_id,
+            &format!("<< /Length {} >>", content.len()),
+            Some(content.as_bytes()),
+        )?;
+        write_object(
+            writer,
+            &mut offsets,
+            page_id,
+            &format!(
+                "<< /Type /Page /Parent {} 0 R /MediaBox [0 0 {} {}] \
+                 /Resources << /XObject << /Im {} 0 R >> >> \
+                 /Contents {} 0 R >>",
+                PAGES, width, height, image_id, content_id
+            ),
+            None,
+        )?;
+        page_ids.push(page_id);
+    }
+
+    let kids = page_ids
+        .iter()
+        .map(|id| format!("{} 0 R", id))
+        .collect::<Vec<String>>()
+        .join(" ");
+    write_object(
+        writer,
+        &mut offsets,
+        PAGES,
+        &format!(
+            "<< /Type /Pages /Kids [{}] /Count {} >>",
+            kids,
+            page_ids.len()
+        ),
+        None,
+    )?;
+    write_object(
+        writer,
+        &mut offsets,
+        CATALOG,
+        &format!("<< /Type /Catalog /Pages {} 0 R >>", PAGES),
+        None,
+    )?;
+
+    let xref_offset = writer.offset;
+    write!(
+        writer,
+        "xref\n0 {}\n0000000000 65535 f \n",
+        offsets.len() + 1
+    )?;
+    for offset in &offsets {
+        writeln!(writer, "{:010} 00000 n ", offset)?;
+    }
+    write!(
+        writer,
+        "trailer\n<< /Size {} /Root {} 0 R >>\nstartxref\n{}\n%%EOF\n",
+        offsets.len() + 1,
+        CATALOG,
+        xref_offset
+    )?;
+    Ok(page_ids.len())
+}
+
+fn write_object<W: Write>(
+    writer: &mut OffsetWriter<W>,
+    offsets: &mut [u64],
+    id: usize,
+    dictionary: &str,
+    stream: Option<&[u8]>,
+) -> io::Result<()> {
+    offsets[id - 1] = writer.offset;
+    write!(writer, "{} 0 obj\n{}\n", id, dictionary)?;
+    if let Some(stream) = stream {
+        writer.write_all(b"stream\n")?;
+        writer.write_all(stream)?;
+        writer.write_all(b"\nendstream\n")?;
+    }
+    writer.write_all(b"endobj\n")
+}
+
+fn deflate(buf: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    encoder.write_all(buf)?;
+    encoder.finish()
+}
@@ -0,0 +1,110 @@
+use crate::{archive::FileEntryMetadata, magic};
+use std::{
+    fs::OpenOptions,
+    io::{self, BufWriter, Cursor, Read, Write},
+    path::{Path, PathBuf},
+};
+use tempfile::TempDir;
+
+/// Argument naming standard input instead of file
+pub const STDIN_ARG: &str = "-";
+/// Name of spooled standard input, output directory of its entries
+const STDIN_NAME: &str = "stdin";
+/// Bytes of stream read to detect archive before it is listed or spooled
+const SNIFF_SIZE: u64 = 0x100;
+
+/// Where archive is read from. Schemes read archives at random offsets,
+/// so archive piped to standard input is first copied into temporary file.
+#[derive(Debug)]
+pub enum InputSource {
+    File(PathBuf),
+    Spooled(SpooledFile),
+    /// Entries of streamed archive, listed without spooling it,
+    /// see [`crate::scheme::Scheme::streams_index`]
+    Listed(Vec<FileEntryMetadata>),
+}
+
+impl InputSource {
+    /// `-` reads standard input, anything else is file path
+    pub fn open(arg: &Path) -> anyhow::Result<Self> {
+        if arg == Path::new(STDIN_ARG) {
+            let stdin = io::stdin();
+            let mut handle = stdin.lock();
+            Ok(Self::Spooled(SpooledFile::from_reader(
+                &mut handle,
+                STDIN_NAME,
+            )?))
+        } else {
+            Ok(Self::File(arg.to_path_buf()))
+        }
+    }
+    /// Like [`InputSource::open`], but archive piped to standard input is
+    /// listed while it is read if its scheme allows it
+    pub fn open_listed(arg: &Path) -> anyhow::Result<Self> {
+        if arg == Path::new(STDIN_ARG) {
+            let stdin = io::stdin();
+            let mut handle = stdin.lock();
+            Self::list_or_spool(&mut handle, STDIN_NAME)
+        } else {
+            Ok(Self::File(arg.to_path_buf()))
+        }
+    }
+    /// List archive of stream with entry table at start, spool anything else
+    pub fn list_or_spool(
+        reader: &mut impl Read,
+        name: &str,
+    ) -> anyhow::Result<Self> {
+        let mut head = Vec::with_capacity(SNIFF_SIZE as usize);
+        reader.take(SNIFF_SIZE).read_to_end(&mut head)?;
+        let archive = magic::Archive::parse(&head);
+        if archive.is_universal() {
+            if let Some(scheme) = archive
+                .get_schemes()
+                .into_iter()
+                .find(|scheme| scheme.streams_index())
+            {
+                log::debug!("Listing stream with {}", scheme.get_name());
+                return Ok(Self::Listed(
+                    scheme.list_stream(&mut Cursor::new(head).chain(reader))?,
+                ));
+            }
+        }
+        Ok(Self::Spooled(SpooledFile::from_reader(
+            &mut Cursor::new(head).chain(reader),
+            name,
+        )?))
+    }
+    /// Path schemes can open, `-` for already listed stream
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::File(path) => path,
+            Self::Spooled(spooled) => &spooled.path,
+            Self::Listed(_) => Path::new(STDIN_ARG),
+        }
+    }
+}
+
+/// Temporary copy of streamed archive, removed with its directory on drop.
+/// Every copy gets its own directory, so copy keeps name of stream.
+#[derive(Debug)]
+pub struct SpooledFile {
+    path: PathBuf,
+    _dir: TempDir,
+}
+
+impl SpooledFile {
+    pub fn from_reader(reader: &mut impl Read, name: &str) -> io::Result<Self> {
+        let dir = tempfile::Builder::new().prefix("akaibu_").tempdir()?;
+        let path = dir.path().join(name);
+        let mut writer = BufWriter::new(
+            OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?,
+        );
+        let size = io::copy(reader, &mut writer)?;
+        writer.flush()?;
+        log::debug!("Spooled {} bytes to {:?}", size, path);
+        Ok(Self { path, _dir: dir })
+    }
+}
@@ -0,0 +1,293 @@
+use crate::{
+    error::AkaibuError,
+    magic::{self, Archive},
+    resource::ResourceMagic,
+};
+use anyhow::Context;
+use serde::Serialize;
+use serde_json::{Map, Number, Value};
+use std::{fmt::Debug, path::Path, str::CharIndices};
+
+/// Header struct of format as parsed by its scheme
+#[derive(Debug, Clone, Serialize)]
+pub struct ParsedHeader {
+    /// Name of header struct
+    pub name: String,
+    pub fields: Value,
+}
+
+/// Headers of file read without extracting anything, printed by `inspect`
+/// command
+#[derive(Debug, Clone, Serialize)]
+pub struct HeaderReport {
+    /// Archive or resource type detected from magic or extension
+    pub format: String,
+    pub scheme: String,
+    pub headers: Vec<ParsedHeader>,
+}
+
+/// Header reported through its `Debug` implementation, so header structs
+/// only opt in instead of deriving serialization
+pub trait ReportHeader: Debug {
+    fn report(&self) -> ParsedHeader {
+        let debug = format!("{:?}", self);
+        ParsedHeader {
+            name: debug
+                .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .next()
+                .unwrap_or_default()
+                .to_string(),
+            fields: debug_to_json(&debug),
+        }
+    }
+}
+
+/// Parse headers of archive or resource with first scheme of its format.
+/// Headers are read before any game specific key is applied, so every
+/// scheme of format reports them the same.
+pub fn inspect(file_path: &Path) -> anyhow::Result<HeaderReport> {
+    let sniffed = magic::sniff(file_path)?;
+    let archive = Archive::detect(file_path, &sniffed);
+    if !matches!(archive, Archive::NotRecognized) {
+        let scheme = archive
+            .get_schemes()
+            .into_iter()
+            .next()
+            .context("Archive type has no schemes")?;
+        return Ok(HeaderReport {
+            format: format!("{:?}", archive),
+            scheme: scheme.get_name(),
+            headers: scheme.inspect(file_path)?,
+        });
+    }
+
+    let mut resource = ResourceMagic::parse_magic(&sniffed.head);
+    if let ResourceMagic::Unrecognized = resource {
+        resource = ResourceMagic::parse_file_extension(file_path);
+    }
+    let scheme =
+        resource.get_schemes().into_iter().next().ok_or_else(|| {
+            AkaibuError::UnrecognizedFormat(
+                file_path.to_path_buf(),
+                sniffed.head.iter().take(16).copied().collect(),
+            )
+        })?;
+    Ok(HeaderReport {
+        format: format!("{:?}", resource),
+        scheme: scheme.get_name(),
+        headers: scheme.inspect(file_path)?,
+    })
+}
+
+/// Rust `Debug` output turned into JSON. Structs and maps become objects,
+/// tuples and tuple structs arrays, `Some` its value and `None` null.
+/// Output that can not be parsed is kept as string.
+pub fn debug_to_json(debug: &str) -> Value {
+    let mut parser = DebugParser {
+        input: debug,
+        pos: 0,
+    };
+    match parser.value() {
+        Some(value) if parser.rest().trim().is_empty() => value,
+        _ => Value::String(debug.to_string()),
+    }
+}
+
+struct DebugParser<'a> {
+    input: &'a str,
+    pos: usize,
+}
+
+impl<'a> DebugParser<'a> {
+    fn rest(&self) -> &'a str {
+        &self.input[self.pos..]
+    }
+    fn peek(&mut self) -> Option<char> {
+        let rest = self.rest();
+        self.pos += rest.len() - rest.trim_start().len();
+        self.rest().chars().next()
+    }
+    fn eat(&mut self, c: char) -> bool {
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.pos += c.len_utf8();
+        }
+        eaten
+    }
+    fn value(&mut self) -> Option<Value> {
+        match self.peek()? {
+            '"' => self.quoted('"').map(Value::String),
+            '\'' => self.quoted('\'').map(Value::String),
+            '[' => {
+                self.pos += 1;
+                self.sequence(']').map(Value::Array)
+            }
+            '(' => {
+                self.pos += 1;
+                self.sequence(')').map(Value::Array)
+            }
+            '{' => {
+                self.pos += 1;
+                self.map()
+            }
+            c if c == '-' || c.is_ascii_digit() => self.number(),
+            c if c.is_alphabetic() || c == '_' => self.named(),
+            _ => None,
+        }
+    }
+    fn ident(&mut self) -> &'a str {
+        self.peek();
+        let rest = self.rest();
+        let len = rest
+            .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .unwrap_or(rest.len());
+        self.pos += len;
+        &rest[..len]
+    }
+    /// Struct, tuple struct, unit variant or keyword
+    fn named(&mut self) -> Option<Value> {
+        let name = self.ident();
+        match self.peek() {
+            Some('{') => {
+                self.pos += 1;
+                self.fields()
+            }
+            Some('(') => {
+                self.pos += 1;
+                let mut values = self.sequence(')')?;
+                Some(if values.len() == 1 {
+                    values.remove(0)
+                } else {
+                    Value::Array(values)
+                })
+            }
+            _ => Some(match name {
+                "None" => Value::Null,
+                "true" => Value::Bool(true),
+                "false" => Value::Bool(false),
+                _ => Value::String(name.to_string()),
+            }),
+        }
+    }
+    /// Values up to closing delimiter
+    fn sequence(&mut self, close: char) -> Option<Vec<Value>> {
+        let mut values = Vec::new();
+        loop {
+            if self.eat(close) {
+                return Some(values);
+            }
+            values.push(self.value()?);
+            if !self.eat(',') {
+                return Some(values).filter(|_| self.eat(close));
+            }
+        }
+    }
+    /// Struct fields up to closing brace
+    fn fields(&mut self) -> Option<Value> {
+        let mut object = Map::new();
+        loop {
+            if self.eat('}') {
+                return Some(Value::Object(object));
+            }
+            // Structs with non exhaustive Debug end with ..
+            if self.peek() == Some('.') && self.rest().starts_with("..") {
+                self.pos += 2;
+                continue;
+            }
+            let field = self.ident();
+            if field.is_empty() || !self.eat(':') {
+                return None;
+            }
+            object.insert(field.to_string(), self.value()?);
+            if !self.eat(',') {
+                return Some(Value::Object(object)).filter(|_| self.eat('}'));
+            }
+        }
+    }
+    /// Map entries up to closing brace, keys become strings
+    fn map(&mut self) -> Option<Value> {
+        let mut object = Map::new();
+        loop {
+            if self.eat('}') {
+                return Some(Value::Object(object));
+            }
+            let key = match self.value()? {
+                Value::String(key) => key,
+                key => key.to_string(),
+            };
+            if !self.eat(':') {
+                return None;
+            }
+            object.insert(key, self.value()?);
+            if !self.eat(',') {
+                return Some(Value::Object(object)).filter(|_| self.eat('}'));
+            }
+        }
+    }
+    fn number(&mut self) -> Option<Value> {
+        let rest = self.rest();
+        let len = rest
+            .char_indices()
+            .find(|&(i, c)| {
+                !(c.is_ascii_alphanumeric()
+                    || c == '.'
+                    || (i == 0 && c == '-')
+                    || ((c == '-' || c == '+')
+                        && rest[..i].ends_with(|e: char| e == 'e' || e == 'E')))
+            })
+            .map_or(rest.len(), |(i, _)| i);
+        let token = &rest[..len];
+        self.pos += len;
+        if let Ok(number) = token.parse::<i64>() {
+            Some(Value::from(number))
+        } else if let Ok(number) = token.parse::<u64>() {
+            Some(Value::from(number))
+        } else {
+            token
+                .parse::<f64>()
+                .ok()
+                .and_then(Number::from_f64)
+                .map(Value::Number)
+        }
+    }
+    /// String or char literal with Rust escapes
+    fn quoted(&mut self, quote: char) -> Option<String> {
+        let mut chars = self.rest().char_indices();
+        chars.next();
+        let mut string = String::new();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                c if c == quote => {
+                    self.pos += i + 1;
+                    return Some(string);
+                }
+                '\\' => string.push(unescape(&mut chars)?),
+                c => string.push(c),
+            }
+        }
+        None
+    }
+}
+
+fn unescape(chars: &mut CharIndices<'_>) -> Option<char> {
+    Some(match chars.next()?.1 {
+        'n' => '\n',
+        'r' => '\r',
+        't' => '\t',
+        '0' => '\0',
+        'u' => {
+            if chars.next()?.1 != '{' {
+                return None;
+            }
+            let mut code = 0;
+            loop {
+                match chars.next()?.1 {
+                    '}' => break,
+                    c => code = code * 16 + c.to_digit(16)?,
+                }
+            }
+            std::char::from_u32(code)?
+        }
+        c => c,
+    })
+}
@@ -0,0 +1,126 @@
+use crate::{error::AkaibuError, util::text};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+/// Name of key file looked up next to archive when none was supplied
+pub const COMPANION_KEY_FILE_NAME: &str = "key.dat";
+
+/// Per-install keys supplied from outside of archive.
+/// Either raw binary key dump or Windows registry export (*.reg)
+#[derive(Debug, Clone, Default)]
+pub struct KeyFile {
+    path: PathBuf,
+    raw: Vec<u8>,
+    values: HashMap<String, Vec<u8>>,
+}
+
+impl KeyFile {
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let raw = std::fs::read(path)?;
+        let values = if Self::is_registry_export(&raw) {
+            Self::parse_registry_export(&text::decode(&raw))?
+        } else {
+            HashMap::new()
+        };
+        log::debug!("Key file: {:?} values: {:?}", path, values.keys());
+        Ok(Self {
+            path: path.to_path_buf(),
+            raw,
+            values,
+        })
+    }
+    /// Use given key file or look for companion key file next to archive
+    pub fn load_or_companion(
+        key_file: Option<&Path>,
+        archive_path: &Path,
+    ) -> anyhow::Result<Self> {
+        match key_file {
+            Some(key_file) => Self::load(key_file),
+            None => {
+                let companion =
+                    archive_path.with_file_name(COMPANION_KEY_FILE_NAME);
+                if companion.is_file() {
+                    Self::load(&companion)
+                } else {
+                    Err(AkaibuError::Custom(format!(
+                        "Key file required for {:?}, supply it with --key-file or place {} next to archive",
+                        archive_path, COMPANION_KEY_FILE_NAME
+                    ))
+                    .into())
+                }
+            }
+        }
+    }
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    /// Whole key file contents
+    pub fn raw(&self) -> &[u8] {
+        &self.raw
+    }
+    /// Get binary registry value by case insensitive name
+    pub fn get(&self, name: &str) -> Option<&[u8]> {
+        self.values.get(&name.to_lowercase()).map(|v| v.as_slice())
+    }
+
+    fn is_registry_export(buf: &[u8]) -> bool {
+        let header = text::decode(&buf[..buf.len().min(0x40)]);
+        header.starts_with("Windows Registry Editor")
+            || header.starts_with("REGEDIT4")
+    }
+    fn parse_registry_export(
+        export: &str,
+    ) -> anyhow::Result<HashMap<String, Vec<u8>>> {
+        let mut values = HashMap::new();
+        let mut lines = export.lines();
+        while let Some(line) = lines.next() {
+            let mut line = line.trim().to_string();
+            // Long hex values are split with trailing backslash
+            while line.ends_with('\\') {
+                line.pop();
+                match lines.next() {
+                    Some(next) => line += next.trim(),
+                    None => break,
+                }
+            }
+            let (name, value) = match line.find("\"=") {
+                Some(i) if line.starts_with('"') => {
+                    (&line[1..i], &line[i + 2..])
+                }
+                _ => continue,
+            };
+            let value = if let Some(value) = value.strip_prefix("hex:") {
+                Self::parse_hex(value)?
+            } else if let Some(value) = value.strip_prefix("dword:") {
+                u32::from_str_radix(value, 16)
+                    .map_err(|_| {
+                        AkaibuError::Custom(format!(
+                            "Invalid dword value: {}",
+                            name
+                        ))
+                    })?
+                    .to_le_bytes()
+                    .to_vec()
+            } else {
+                value.trim_matches('"').as_bytes().to_vec()
+            };
+            values.insert(name.to_lowercase(), value);
+        }
+        Ok(values)
+    }
+    fn parse_hex(value: &str) -> anyhow::Result<Vec<u8>> {
+        value
+            .split(',')
+            .map(|b| b.trim())
+            .filter(|b| !b.is_empty())
+            .map(|b| {
+                u8::from_str_radix(b, 16).map_err(|_| {
+                    AkaibuError::Custom(format!("Invalid hex byte: {}", b))
+                        .into()
+                })
+            })
+            .collect()
+    }
+}
@@ -10,11 +10,33 @@
 extern crate positioned_io_preview as positioned_io;
 
 pub mod archive;
+pub mod build_info;
+pub mod cancel;
+pub mod carve;
+pub mod dump;
 pub mod error;
+pub mod gallery;
+pub mod input;
+pub mod inspect;
+pub mod key_file;
 pub mod magic;
+pub mod manifest;
+#[cfg(feature = "async")]
+pub mod nonblocking;
+pub mod overlay;
+pub mod patch;
+pub mod pipeline;
+pub mod probe;
+pub mod progress;
+mod readahead;
+pub mod report;
 pub mod resource;
 pub mod scheme;
 pub mod util;
+pub mod validate;
+
+pub use build_info::build_info;
+pub use probe::probe;
 
 use rust_embed::RustEmbed;
 
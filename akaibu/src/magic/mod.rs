@@ -1,24 +1,137 @@
-use crate::scheme::{self, Scheme};
+use crate::{scheme::Scheme, util};
 use enum_iterator::IntoEnumIterator;
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
 
-#[derive(Debug, IntoEnumIterator)]
+/// Maximum number of bytes sampled from start of file
+pub const SNIFF_SIZE: usize = 32;
+
+/// Magic found at fixed distance from end of file, for formats keeping
+/// their header in footer
+#[derive(Debug)]
+pub struct TailMagic {
+    /// Distance of magic start from end of file
+    pub offset_from_end: usize,
+    pub magic: &'static [u8],
+    pub archive: Archive,
+}
+
+/// Every registered footer magic, formats indexed from end of file add
+/// their entry here
+pub const TAIL_MAGICS: &[TailMagic] = &[
+    #[cfg(feature = "fmt-qlie")]
+    TailMagic {
+        offset_from_end: 0x1C,
+        magic: b"FilePackVer",
+        archive: Archive::QliePack,
+    },
+];
+
+/// Number of bytes sampled from end of file, enough to reach farthest
+/// registered footer magic
+pub const TAIL_WINDOW: usize = tail_window(TAIL_MAGICS);
+
+const fn tail_window(magics: &[TailMagic]) -> usize {
+    let mut window = 0;
+    let mut i = 0;
+    while i < magics.len() {
+        if magics[i].offset_from_end > window {
+            window = magics[i].offset_from_end;
+        }
+        i += 1;
+    }
+    window
+}
+
+/// Bytes sampled from start and end of file for format detection. Head is
+/// SNIFF_SIZE and tail TAIL_WINDOW bytes long, or shorter when file is
+/// smaller than that.
+#[derive(Debug, Clone, Default)]
+pub struct Sniffed {
+    pub head: Vec<u8>,
+    pub tail: Vec<u8>,
+}
+
+/// Read first and last bytes of file without failing on short files
+pub fn sniff(file_path: &Path) -> anyhow::Result<Sniffed> {
+    let mut file = util::fs::open_shared(file_path)?;
+    let file_size = file.metadata()?.len();
+
+    let head_size = file_size.min(SNIFF_SIZE as u64);
+    let mut head = Vec::with_capacity(head_size as usize);
+    (&mut file).take(head_size).read_to_end(&mut head)?;
+
+    let tail_size = file_size.min(TAIL_WINDOW as u64);
+    let mut tail = Vec::with_capacity(tail_size as usize);
+    file.seek(SeekFrom::Start(file_size - tail_size))?;
+    file.take(tail_size).read_to_end(&mut tail)?;
+
+    Ok(Sniffed { head, tail })
+}
+
+/// Detect archive type by magic at start of file, then by registered
+/// footer magics at its end
+pub fn detect_with_tail(head: &[u8], tail: &[u8]) -> Archive {
+    match Archive::parse(head) {
+        Archive::NotRecognized => Archive::parse_end(tail),
+        archive => archive,
+    }
+}
+
+#[derive(Debug, Clone, Copy, IntoEnumIterator)]
 pub enum Archive {
+    #[cfg(feature = "fmt-acv1")]
     Acv1,
+    #[cfg(feature = "fmt-cpz7")]
     Cpz7,
+    #[cfg(feature = "fmt-gxp")]
     Gxp,
+    #[cfg(feature = "fmt-pf8")]
     Pf8,
+    #[cfg(feature = "fmt-ypf")]
     Ypf,
+    #[cfg(feature = "fmt-buriko")]
     Buriko,
+    #[cfg(feature = "fmt-esc-arc2")]
     EscArc2,
+    #[cfg(feature = "fmt-malie")]
     Malie,
+    #[cfg(feature = "fmt-silky")]
     Silky,
+    #[cfg(feature = "fmt-silky")]
+    SilkyGrp,
+    #[cfg(feature = "fmt-iar")]
     Iar,
+    #[cfg(feature = "fmt-willplus")]
     WillplusArc,
+    #[cfg(feature = "fmt-qlie")]
     QliePack,
+    #[cfg(feature = "fmt-nekopack")]
     Nekopack,
+    #[cfg(feature = "fmt-amusepac")]
     AmusePac,
+    #[cfg(feature = "fmt-tactics")]
     TacticsArc,
+    #[cfg(feature = "fmt-link6")]
     Link6,
+    #[cfg(feature = "fmt-mbl")]
+    Mbl,
+    #[cfg(feature = "fmt-paz")]
+    Paz,
+    #[cfg(feature = "fmt-otomate-bin")]
+    OtomateBin,
+    #[cfg(feature = "fmt-packdat")]
+    Packdat,
+    #[cfg(feature = "fmt-moonstone-dat")]
+    MoonstoneDat,
+    #[cfg(feature = "fmt-kaguya")]
+    KaguyaArc,
+    #[cfg(feature = "fmt-ykc")]
+    Ykc,
+    #[cfg(feature = "fmt-ikura")]
+    IkuraGdl,
     NotRecognized,
 }
 
@@ -27,92 +140,274 @@ impl Archive {
     pub fn parse(buf: &[u8]) -> Self {
         match buf {
             // ACV1
+            #[cfg(feature = "fmt-acv1")]
             [0x41, 0x43, 0x56, 0x31, ..] => Self::Acv1,
             // CPZ7
+            #[cfg(feature = "fmt-cpz7")]
             [0x43, 0x50, 0x5A, 0x37, ..] => Self::Cpz7,
             // GXP\x00
+            #[cfg(feature = "fmt-gxp")]
             [0x47, 0x58, 0x50, 0x00, ..] => Self::Gxp,
             // pf8
+            #[cfg(feature = "fmt-pf8")]
             [0x70, 0x66, 0x38, ..] => Self::Pf8,
             // YFP\x00
+            #[cfg(feature = "fmt-ypf")]
             [0x59, 0x50, 0x46, 0x00, ..] => Self::Ypf,
             // BURIKO ARC20
+            #[cfg(feature = "fmt-buriko")]
             [0x42, 0x55, 0x52, 0x49, 0x4b, 0x4f, 0x20, 0x41, 0x52, 0x43, 0x32, 0x30, ..] => {
                 Self::Buriko
             }
+            // PackFile____
+            #[cfg(feature = "fmt-buriko")]
+            [0x50, 0x61, 0x63, 0x6b, 0x46, 0x69, 0x6c, 0x65, 0x20, 0x20, 0x20, 0x20, ..] => {
+                Self::Buriko
+            }
             // ESC-ARC2
+            #[cfg(feature = "fmt-esc-arc2")]
             [0x45, 0x53, 0x43, 0x2D, 0x41, 0x52, 0x43, 0x32, ..] => {
                 Self::EscArc2
             }
             // No magic but each game has only one archive so we can just hardcode first 4 bytes here
+            #[cfg(feature = "fmt-malie")]
             [0xc1, 0xf2, 0x5e, 0x79, ..] | [0x7f, 0x4d, 0x8f, 0xe9, ..] => {
                 Self::Malie
             }
             // iar
+            #[cfg(feature = "fmt-iar")]
             [0x69, 0x61, 0x72, 0x20, ..] => Self::Iar,
             // NEKOPACK
+            #[cfg(feature = "fmt-nekopack")]
             [0x4e, 0x45, 0x4b, 0x4f, 0x50, 0x41, 0x43, 0x4b, ..] => {
                 Self::Nekopack
             }
+            #[cfg(feature = "fmt-amusepac")]
             [0x50, 0x41, 0x43, 0x20, ..] => Self::AmusePac,
             // TACTICS_ARC_FILE
+            #[cfg(feature = "fmt-tactics")]
             [0x54, 0x41, 0x43, 0x54, 0x49, 0x43, 0x53, 0x5F, 0x41, 0x52, 0x43, 0x5F, 0x46, 0x49, 0x4C, 0x45, ..] => {
                 Self::TacticsArc
             }
             // LINK6\x00\x00
+            #[cfg(feature = "fmt-link6")]
             [0x4C, 0x49, 0x4E, 0x4B, 0x36, 0x00, 0x00, ..] => Self::Link6,
+            // PACKDAT.
+            #[cfg(feature = "fmt-packdat")]
+            [0x50, 0x41, 0x43, 0x4B, 0x44, 0x41, 0x54, 0x2E, ..] => {
+                Self::Packdat
+            }
+            // YKC001
+            #[cfg(feature = "fmt-ykc")]
+            [0x59, 0x4B, 0x43, 0x30, 0x30, 0x31, ..] => Self::Ykc,
+            // SM2MPX10
+            #[cfg(feature = "fmt-ikura")]
+            [0x53, 0x4D, 0x32, 0x4D, 0x50, 0x58, 0x31, 0x30, ..] => {
+                Self::IkuraGdl
+            }
             _ => Self::NotRecognized,
         }
     }
-    /// Parse last 32 bytes of file to detect archive type
+    /// Match last bytes of file against registered footer magics. Tail
+    /// shorter than magic distance from end never matches it.
     pub fn parse_end(buf: &[u8]) -> Self {
-        if &buf[buf.len() - 0x1C..buf.len() - 0x1C + 11] == b"FilePackVer" {
-            Self::QliePack
-        } else {
-            Self::NotRecognized
+        TAIL_MAGICS
+            .iter()
+            .find(|tail_magic| {
+                buf.len()
+                    .checked_sub(tail_magic.offset_from_end)
+                    .and_then(|start| buf.get(start..))
+                    .map_or(false, |footer| {
+                        footer.starts_with(tail_magic.magic)
+                    })
+            })
+            .map_or(Self::NotRecognized, |tail_magic| tail_magic.archive)
+    }
+    /// Detect archive type from sampled file start, end and file extension
+    pub fn detect(file_path: &Path, sniffed: &Sniffed) -> Self {
+        match detect_with_tail(&sniffed.head, &sniffed.tail) {
+            Self::NotRecognized => Self::parse_file_extension(file_path),
+            archive => archive,
+        }
+    }
+    /// Detect archive type by file extension for formats without magic
+    pub fn parse_file_extension(file_path: &Path) -> Self {
+        match file_path.extension() {
+            Some(extension) => match extension.to_str() {
+                Some(extension) => match extension.to_lowercase().as_str() {
+                    #[cfg(feature = "fmt-mbl")]
+                    "mbl" => Self::Mbl,
+                    #[cfg(feature = "fmt-paz")]
+                    "paz" => Self::Paz,
+                    #[cfg(feature = "fmt-silky")]
+                    "grp" => Self::SilkyGrp,
+                    // Otomate containers have no magic, other .bin files
+                    // are told apart by their sector table
+                    #[cfg(feature = "fmt-otomate-bin")]
+                    "bin"
+                        if crate::scheme::otomate_bin::is_otomate_bin(
+                            file_path,
+                        ) =>
+                    {
+                        Self::OtomateBin
+                    }
+                    // Many engines use .dat, Moonstone one is told by its
+                    // index
+                    #[cfg(feature = "fmt-moonstone-dat")]
+                    "dat"
+                        if crate::scheme::moonstone_dat::is_moonstone_dat(
+                            file_path,
+                        ) =>
+                    {
+                        Self::MoonstoneDat
+                    }
+                    #[cfg(feature = "fmt-kaguya")]
+                    "ari" => Self::KaguyaArc,
+                    // Other engines using .arc have magic, so archive with
+                    // index next to it is Kaguya one
+                    #[cfg(feature = "fmt-kaguya")]
+                    "arc"
+                        if crate::scheme::kaguya_arc::index_path(file_path)
+                            .is_some() =>
+                    {
+                        Self::KaguyaArc
+                    }
+                    _ => Self::NotRecognized,
+                },
+                None => Self::NotRecognized,
+            },
+            None => Self::NotRecognized,
         }
     }
     /// Is archive extraction scheme not game dependent
     pub fn is_universal(&self) -> bool {
         match self {
+            #[cfg(feature = "fmt-acv1")]
             Self::Acv1 => false,
+            #[cfg(feature = "fmt-cpz7")]
             Self::Cpz7 => false,
+            #[cfg(feature = "fmt-gxp")]
             Self::Gxp => true,
+            #[cfg(feature = "fmt-pf8")]
             Self::Pf8 => true,
+            #[cfg(feature = "fmt-ypf")]
             Self::Ypf => true,
+            #[cfg(feature = "fmt-buriko")]
             Self::Buriko => true,
+            #[cfg(feature = "fmt-esc-arc2")]
             Self::EscArc2 => true,
+            #[cfg(feature = "fmt-malie")]
             Self::Malie => false,
+            #[cfg(feature = "fmt-silky")]
             Self::Silky => true,
+            #[cfg(feature = "fmt-silky")]
+            Self::SilkyGrp => true,
+            #[cfg(feature = "fmt-iar")]
             Self::Iar => true,
+            #[cfg(feature = "fmt-willplus")]
             Self::WillplusArc => true,
+            #[cfg(feature = "fmt-qlie")]
             Self::QliePack => false,
+            #[cfg(feature = "fmt-nekopack")]
             Self::Nekopack => true,
+            #[cfg(feature = "fmt-amusepac")]
             Self::AmusePac => true,
+            #[cfg(feature = "fmt-tactics")]
             Self::TacticsArc => false,
+            #[cfg(feature = "fmt-link6")]
             Self::Link6 => true,
+            #[cfg(feature = "fmt-mbl")]
+            Self::Mbl => true,
+            #[cfg(feature = "fmt-paz")]
+            Self::Paz => true,
+            #[cfg(feature = "fmt-otomate-bin")]
+            Self::OtomateBin => true,
+            #[cfg(feature = "fmt-packdat")]
+            Self::Packdat => true,
+            #[cfg(feature = "fmt-moonstone-dat")]
+            Self::MoonstoneDat => true,
+            #[cfg(feature = "fmt-kaguya")]
+            Self::KaguyaArc => true,
+            #[cfg(feature = "fmt-ykc")]
+            Self::Ykc => true,
+            #[cfg(feature = "fmt-ikura")]
+            Self::IkuraGdl => true,
             Self::NotRecognized => false,
         }
     }
     /// Get list of all schemes for given archive type
     pub fn get_schemes(&self) -> Vec<Box<dyn Scheme>> {
         match self {
-            Self::Acv1 => scheme::acv1::Acv1Scheme::get_schemes(),
-            Self::Cpz7 => scheme::cpz7::Cpz7Scheme::get_schemes(),
-            Self::Gxp => scheme::gxp::GxpScheme::get_schemes(),
-            Self::Pf8 => scheme::pf8::Pf8Scheme::get_schemes(),
-            Self::Ypf => scheme::ypf::YpfScheme::get_schemes(),
-            Self::Buriko => scheme::buriko::BurikoScheme::get_schemes(),
-            Self::EscArc2 => scheme::esc_arc2::EscArc2Scheme::get_schemes(),
-            Self::Malie => scheme::malie::MalieScheme::get_schemes(),
-            Self::Silky => scheme::silky::SilkyScheme::get_schemes(),
-            Self::Iar => scheme::iar::IarScheme::get_schemes(),
-            Self::WillplusArc => scheme::willplus_arc::ArcScheme::get_schemes(),
-            Self::QliePack => scheme::qliepack::PackScheme::get_schemes(),
-            Self::Nekopack => scheme::nekopack::PackScheme::get_schemes(),
-            Self::AmusePac => scheme::amusepac::PacScheme::get_schemes(),
-            Self::TacticsArc => scheme::tactics_arc::ArcScheme::get_schemes(),
-            Self::Link6 => scheme::link6::Link6Scheme::get_schemes(),
+            #[cfg(feature = "fmt-acv1")]
+            Self::Acv1 => crate::scheme::acv1::Acv1Scheme::get_schemes(),
+            #[cfg(feature = "fmt-cpz7")]
+            Self::Cpz7 => crate::scheme::cpz7::Cpz7Scheme::get_schemes(),
+            #[cfg(feature = "fmt-gxp")]
+            Self::Gxp => crate::scheme::gxp::GxpScheme::get_schemes(),
+            #[cfg(feature = "fmt-pf8")]
+            Self::Pf8 => crate::scheme::pf8::Pf8Scheme::get_schemes(),
+            #[cfg(feature = "fmt-ypf")]
+            Self::Ypf => crate::scheme::ypf::YpfScheme::get_schemes(),
+            #[cfg(feature = "fmt-buriko")]
+            Self::Buriko => crate::scheme::buriko::BurikoScheme::get_schemes(),
+            #[cfg(feature = "fmt-esc-arc2")]
+            Self::EscArc2 => {
+                crate::scheme::esc_arc2::EscArc2Scheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-malie")]
+            Self::Malie => crate::scheme::malie::MalieScheme::get_schemes(),
+            #[cfg(feature = "fmt-silky")]
+            Self::Silky => crate::scheme::silky::SilkyScheme::get_schemes(),
+            #[cfg(feature = "fmt-silky")]
+            Self::SilkyGrp => {
+                crate::scheme::silky_grp::GrpScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-iar")]
+            Self::Iar => crate::scheme::iar::IarScheme::get_schemes(),
+            #[cfg(feature = "fmt-willplus")]
+            Self::WillplusArc => {
+                crate::scheme::willplus_arc::ArcScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-qlie")]
+            Self::QliePack => {
+                crate::scheme::qliepack::PackScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-nekopack")]
+            Self::Nekopack => {
+                crate::scheme::nekopack::PackScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-amusepac")]
+            Self::AmusePac => crate::scheme::amusepac::PacScheme::get_schemes(),
+            #[cfg(feature = "fmt-tactics")]
+            Self::TacticsArc => {
+                crate::scheme::tactics_arc::ArcScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-link6")]
+            Self::Link6 => crate::scheme::link6::Link6Scheme::get_schemes(),
+            #[cfg(feature = "fmt-mbl")]
+            Self::Mbl => crate::scheme::mbl::MblScheme::get_schemes(),
+            #[cfg(feature = "fmt-paz")]
+            Self::Paz => crate::scheme::paz::PazScheme::get_schemes(),
+            #[cfg(feature = "fmt-otomate-bin")]
+            Self::OtomateBin => {
+                crate::scheme::otomate_bin::OtomateBinScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-packdat")]
+            Self::Packdat => {
+                crate::scheme::packdat::PackdatScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-moonstone-dat")]
+            Self::MoonstoneDat => {
+                crate::scheme::moonstone_dat::MoonstoneDatScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-kaguya")]
+            Self::KaguyaArc => {
+                crate::scheme::kaguya_arc::KaguyaArcScheme::get_schemes()
+            }
+            #[cfg(feature = "fmt-ykc")]
+            Self::Ykc => crate::scheme::ykc::YkcScheme::get_schemes(),
+            #[cfg(feature = "fmt-ikura")]
+            Self::IkuraGdl => crate::scheme::ikura::IkuraScheme::get_schemes(),
             Self::NotRecognized => vec![],
         }
     }
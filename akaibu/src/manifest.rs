@@ -0,0 +1,380 @@
+use crate::{
+    archive::{FileEntry, FileEntryExt},
+    util::text::display_name,
+};
+use serde::{Deserialize, Serialize, Serializer};
+use std::{
+    collections::HashMap,
+    fs::{File, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Extension appended to archive file name for manifest written next to
+/// extracted files
+pub const MANIFEST_EXTENSION: &str = "manifest.json";
+/// Extension appended to manifest path for journal of entries recorded so
+/// far, removed once manifest is written
+pub const JOURNAL_EXTENSION: &str = "journal";
+/// Extension appended to archive file name for mapping of entries to files
+/// written for them
+pub const MAPPING_EXTENSION: &str = "mapping.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum EntryStatus {
+    Extracted {
+        bytes_written: u64,
+        /// SHA-1 of written file, of stored bytes for normalized text.
+        /// None when entry was converted or re-encoded
+        #[serde(default)]
+        sha1: Option<String>,
+        /// Files written for entry, None in manifests of older versions
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        output: Option<EntryOutput>,
+    },
+    Failed {
+        reason: String,
+    },
+    Skipped {
+        reason: String,
+    },
+}
+
+/// Files written for extracted entry and format they were written in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EntryOutput {
+    /// Written files relative to output directory, more than one for sprite
+    /// sheets and none for resources without contents
+    pub files: Vec<PathBuf>,
+    /// `stored` for entries written as is, otherwise extension of converted
+    /// files
+    pub format: String,
+    /// Width and height of first converted image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<(u32, u32)>,
+}
+
+impl EntryOutput {
+    pub const STORED: &'static str = "stored";
+
+    /// Entry written as is into single file
+    pub fn stored(file: PathBuf) -> Self {
+        Self {
+            files: vec![file],
+            format: Self::STORED.to_string(),
+            dimensions: None,
+        }
+    }
+}
+
+/// Entry extracted by previous run, as recorded in its manifest
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompletedEntry {
+    /// SHA-1 of written file
+    pub sha1: String,
+    /// File written for entry relative to output directory, which differs
+    /// from entry path when it was renamed. None in manifests of older
+    /// versions.
+    pub output: Option<PathBuf>,
+}
+
+/// Entry of mapping file, what was written for archive entry
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct MappingEntry {
+    /// Path inside archive
+    #[serde(flatten, with = "entry_path")]
+    pub path: PathBuf,
+    #[serde(flatten)]
+    pub output: EntryOutput,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    #[serde(flatten, with = "entry_path")]
+    pub path: PathBuf,
+    pub offset: u64,
+    pub size: u64,
+    #[serde(flatten)]
+    pub ext: FileEntryExt,
+    #[serde(flatten)]
+    pub status: EntryStatus,
+}
+
+/// Paths that are not valid UTF-8 are written decoded as SHIFT_JIS
+/// instead of failing whole manifest
+fn serialize_path<S: Serializer>(
+    path: &Path,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(&display_name(path))
+}
+
+/// Entry path flattened into `path` and `raw_path` fields. Manifests are
+/// always UTF-8, names that are not valid UTF-8 are written decoded as
+/// SHIFT_JIS for reading, with their original bytes escaped in `raw_path`,
+/// so they are read back as the same path and can be packed again.
+pub mod entry_path {
+    use crate::util::text::{display_name, escape_raw_name, unescape_raw_name};
+    use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+    use std::path::{Path, PathBuf};
+
+    #[derive(Serialize, Deserialize)]
+    struct EntryPath {
+        path: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        raw_path: Option<String>,
+    }
+
+    pub fn serialize<S: Serializer>(
+        path: &Path,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        EntryPath {
+            path: display_name(path).to_string(),
+            raw_path: escape_raw_name(path),
+        }
+        .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<PathBuf, D::Error> {
+        let entry_path = EntryPath::deserialize(deserializer)?;
+        match entry_path.raw_path {
+            Some(raw_path) => unescape_raw_name(&raw_path).ok_or_else(|| {
+                de::Error::custom(format!("Malformed raw_path: {}", raw_path))
+            }),
+            None => Ok(PathBuf::from(entry_path.path)),
+        }
+    }
+}
+
+/// Record of what happened to each archive entry during extraction.
+/// Entries can be recorded from multiple threads.
+#[derive(Debug)]
+pub struct Manifest {
+    archive: PathBuf,
+    entries: Mutex<Vec<ManifestEntry>>,
+    journal: Option<Journal>,
+}
+
+/// Entries appended as JSON lines while extracting, so interrupted
+/// extraction can be resumed
+#[derive(Debug)]
+struct Journal {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+#[derive(Debug, Serialize)]
+struct ManifestFile<'a> {
+    akaibu_version: &'a str,
+    #[serde(serialize_with = "serialize_path")]
+    archive: &'a Path,
+    entries: &'a [ManifestEntry],
+}
+
+#[derive(Debug, Serialize)]
+struct MappingFile<'a> {
+    akaibu_version: &'a str,
+    #[serde(serialize_with = "serialize_path")]
+    archive: &'a Path,
+    entries: &'a [MappingEntry],
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestFileOwned {
+    entries: Vec<ManifestEntry>,
+}
+
+impl Manifest {
+    pub fn new(archive: &Path) -> Self {
+        Self {
+            archive: archive.to_path_buf(),
+            entries: Mutex::new(Vec::new()),
+            journal: None,
+        }
+    }
+    /// Append every recorded entry to journal next to manifest path
+    pub fn with_journal(
+        mut self,
+        manifest_path: &Path,
+    ) -> anyhow::Result<Self> {
+        let path = Self::journal_path(manifest_path);
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        self.journal = Some(Journal {
+            path,
+            file: Mutex::new(file),
+        });
+        Ok(self)
+    }
+    pub fn record(
+        &self,
+        entry: &FileEntry,
+        ext: FileEntryExt,
+        status: EntryStatus,
+    ) {
+        let entry = ManifestEntry {
+            path: entry.full_path.clone(),
+            offset: entry.file_offset,
+            size: entry.file_size,
+            ext,
+            status,
+        };
+        if let Some(journal) = &self.journal {
+            if let Err(err) = journal.append(&entry) {
+                log::error!(
+                    "Could not write journal {:?}: {}",
+                    journal.path,
+                    err
+                );
+            }
+        }
+        self.entries
+            .lock()
+            .expect("Manifest lock poisoned")
+            .push(entry);
+    }
+    /// Recorded entries sorted by path
+    pub fn entries(&self) -> Vec<ManifestEntry> {
+        let mut entries =
+            self.entries.lock().expect("Manifest lock poisoned").clone();
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+        entries
+    }
+    /// Default manifest location for archive inside output directory
+    pub fn default_path(&self, output_dir: &Path) -> PathBuf {
+        self.path_next_to_output(output_dir, MANIFEST_EXTENSION)
+    }
+    /// Default mapping file location for archive inside output directory
+    pub fn default_mapping_path(&self, output_dir: &Path) -> PathBuf {
+        self.path_next_to_output(output_dir, MAPPING_EXTENSION)
+    }
+    fn path_next_to_output(
+        &self,
+        output_dir: &Path,
+        extension: &str,
+    ) -> PathBuf {
+        let mut file_name = self
+            .archive
+            .file_name()
+            .map(|name| name.to_os_string())
+            .unwrap_or_default();
+        file_name.push(".");
+        file_name.push(extension);
+        output_dir.join(file_name)
+    }
+    /// Files written for each extracted entry sorted by path, so edited
+    /// files can be matched back to entries they replace
+    pub fn mapping(&self) -> Vec<MappingEntry> {
+        self.entries()
+            .into_iter()
+            .filter_map(|entry| match entry.status {
+                EntryStatus::Extracted {
+                    output: Some(output),
+                    ..
+                } => Some(MappingEntry {
+                    path: entry.path,
+                    output,
+                }),
+                _ => None,
+            })
+            .collect()
+    }
+    pub fn write_mapping(&self, output_path: &Path) -> anyhow::Result<()> {
+        let entries = self.mapping();
+        let mapping = MappingFile {
+            akaibu_version: env!("CARGO_PKG_VERSION"),
+            archive: &self.archive,
+            entries: &entries,
+        };
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        File::create(output_path)?
+            .write_all(&serde_json::to_vec_pretty(&mapping)?)?;
+        Ok(())
+    }
+    pub fn write(&self, output_path: &Path) -> anyhow::Result<()> {
+        let entries = self.entries();
+        let manifest = ManifestFile {
+            akaibu_version: env!("CARGO_PKG_VERSION"),
+            archive: &self.archive,
+            entries: &entries,
+        };
+        if let Some(parent) = output_path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        File::create(output_path)?
+            .write_all(&serde_json::to_vec_pretty(&manifest)?)?;
+        if let Some(journal) = &self.journal {
+            std::fs::remove_file(&journal.path)?;
+        }
+        Ok(())
+    }
+    pub fn journal_path(manifest_path: &Path) -> PathBuf {
+        let mut path = manifest_path.as_os_str().to_os_string();
+        path.push(".");
+        path.push(JOURNAL_EXTENSION);
+        PathBuf::from(path)
+    }
+    /// Every entry extracted by previous run, read from manifest and
+    /// journal left by interrupted run. Missing files are ignored.
+    pub fn load_completed(
+        manifest_path: &Path,
+    ) -> anyhow::Result<HashMap<PathBuf, CompletedEntry>> {
+        let mut entries = Vec::new();
+        if manifest_path.is_file() {
+            let manifest: ManifestFileOwned =
+                serde_json::from_slice(&std::fs::read(manifest_path)?)?;
+            entries.extend(manifest.entries);
+        }
+        let journal_path = Self::journal_path(manifest_path);
+        if journal_path.is_file() {
+            for line in BufReader::new(File::open(&journal_path)?).lines() {
+                // Last line might be cut off by interruption
+                match serde_json::from_str::<ManifestEntry>(&line?) {
+                    Ok(entry) => entries.push(entry),
+                    Err(err) => log::debug!("Skipping journal line: {}", err),
+                }
+            }
+        }
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| match entry.status {
+                EntryStatus::Extracted {
+                    sha1: Some(sha1),
+                    output,
+                    ..
+                } => Some((
+                    entry.path,
+                    CompletedEntry {
+                        sha1,
+                        output: output
+                            .filter(|output| {
+                                output.format == EntryOutput::STORED
+                            })
+                            .and_then(|output| output.files.into_iter().next()),
+                    },
+                )),
+                _ => None,
+            })
+            .collect())
+    }
+}
+
+impl Journal {
+    fn append(&self, entry: &ManifestEntry) -> anyhow::Result<()> {
+        let mut line = serde_json::to_vec(entry)?;
+        line.push(b'\n');
+        let mut file = self.file.lock().expect("Journal lock poisoned");
+        file.write_all(&line)?;
+        file.flush()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,112 @@
+//! Async variants of opening and extracting archives, for async runtimes
+//! like tokio. Blocking work runs on rayon thread pool and is awaited
+//! through [`Blocking`], so runtime threads are never blocked on archive
+//! reads and many entries can be extracted concurrently. Futures do not
+//! depend on any runtime.
+
+use crate::{
+    archive::{Archive, FileContents, FileEntry, NavigableDirectory},
+    scheme::Scheme,
+};
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    path::PathBuf,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{Context, Poll, Waker},
+    thread,
+};
+
+/// Result of work running on rayon thread pool, resolved when it is done.
+/// Panic of work is resumed in task awaiting it.
+#[derive(Debug)]
+pub struct Blocking<T> {
+    state: Arc<Mutex<BlockingState<T>>>,
+}
+
+#[derive(Debug)]
+struct BlockingState<T> {
+    result: Option<thread::Result<T>>,
+    waker: Option<Waker>,
+}
+
+impl<T> Future for Blocking<T> {
+    type Output = T;
+
+    fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+        let mut state = self.state.lock().expect("Blocking lock poisoned");
+        match state.result.take() {
+            Some(Ok(result)) => Poll::Ready(result),
+            Some(Err(panic)) => panic::resume_unwind(panic),
+            None => {
+                state.waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+        }
+    }
+}
+
+/// Run blocking work on rayon thread pool
+pub fn spawn_blocking<T, F>(work: F) -> Blocking<T>
+where
+    T: Send + 'static,
+    F: FnOnce() -> T + Send + 'static,
+{
+    let state = Arc::new(Mutex::new(BlockingState {
+        result: None,
+        waker: None,
+    }));
+    let shared = Arc::clone(&state);
+    rayon::spawn(move || {
+        // Uncaught panic would abort whole process
+        let result = panic::catch_unwind(AssertUnwindSafe(work));
+        let mut state = shared.lock().expect("Blocking lock poisoned");
+        state.result = Some(result);
+        if let Some(waker) = state.waker.take() {
+            waker.wake();
+        }
+    });
+    Blocking { state }
+}
+
+/// Archive shared by extraction tasks, cheap to clone
+#[derive(Debug, Clone)]
+pub struct AsyncArchive {
+    archive: Arc<dyn Archive>,
+}
+
+impl AsyncArchive {
+    pub fn new(archive: Box<dyn Archive>) -> Self {
+        Self {
+            archive: Arc::from(archive),
+        }
+    }
+    /// Archive for blocking calls
+    pub fn archive(&self) -> &dyn Archive {
+        self.archive.as_ref()
+    }
+    /// Async variant of [`Archive::extract`]
+    pub fn extract(
+        &self,
+        entry: &FileEntry,
+    ) -> Blocking<anyhow::Result<FileContents>> {
+        let archive = Arc::clone(&self.archive);
+        let entry = entry.clone();
+        spawn_blocking(move || archive.extract(&entry))
+    }
+}
+
+/// Async variant of [`Scheme::extract`]. Scheme is cloned into work, so it
+/// must not borrow anything.
+pub fn open(
+    scheme: &(dyn Scheme + 'static),
+    file_path: impl Into<PathBuf>,
+) -> Blocking<anyhow::Result<(AsyncArchive, NavigableDirectory)>> {
+    let scheme = dyn_clone::clone_box(scheme);
+    let file_path = file_path.into();
+    spawn_blocking(move || {
+        let (archive, dir) = scheme.extract(&file_path)?;
+        Ok((AsyncArchive::new(archive), dir))
+    })
+}
@@ -0,0 +1,156 @@
+use crate::{
+    archive::{
+        Archive, Directory, FileContents, FileEntry, FileEntryExt,
+        NavigableDirectory,
+    },
+    util::{self, text::display_name},
+};
+use anyhow::Context;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::{
+    collections::HashSet,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Extension of original archive some installs keep next to updated one
+pub const BACKUP_EXTENSION: &str = "bak";
+
+/// Original archive kept next to given one as `<archive>.bak`, if it exists
+pub fn backup_path(file_path: &Path) -> Option<PathBuf> {
+    let mut backup = file_path.as_os_str().to_os_string();
+    backup.push(".");
+    backup.push(BACKUP_EXTENSION);
+    Some(PathBuf::from(backup)).filter(|backup| backup.is_file())
+}
+
+/// Two archives read as one, like updated archive over its original kept as
+/// backup. Entries of newer archive replace entries of the same path in base
+/// archive, remaining entries are read from base archive. Replaced entries
+/// are flagged in [`FileEntryExt::overrides`], so patches can be studied in
+/// manifest.
+#[derive(Debug)]
+pub struct OverlayArchive {
+    base: Box<dyn Archive>,
+    newer: Box<dyn Archive>,
+    base_name: String,
+    /// Merged entries, read from base archive when their path is in
+    /// `from_base`
+    entries: Vec<FileEntry>,
+    from_base: HashSet<PathBuf>,
+    overridden: HashSet<PathBuf>,
+}
+
+impl OverlayArchive {
+    /// Merge opened archives, `base_name` names base archive in manifest
+    pub fn merge(
+        base: (Box<dyn Archive>, NavigableDirectory),
+        newer: (Box<dyn Archive>, NavigableDirectory),
+        base_name: String,
+    ) -> (Self, NavigableDirectory) {
+        let (base, base_dir) = base;
+        let (newer, newer_dir) = newer;
+        let newer_paths = newer_dir
+            .files()
+            .map(|entry| entry.full_path.clone())
+            .collect::<HashSet<PathBuf>>();
+        let mut from_base = HashSet::new();
+        let mut overridden = HashSet::new();
+        for entry in base_dir.files() {
+            if newer_paths.contains(&entry.full_path) {
+                overridden.insert(entry.full_path.clone());
+            } else {
+                from_base.insert(entry.full_path.clone());
+            }
+        }
+        let entries = newer_dir
+            .files()
+            .chain(
+                base_dir
+                    .files()
+                    .filter(|entry| from_base.contains(&entry.full_path)),
+            )
+            .cloned()
+            .collect::<Vec<FileEntry>>();
+        log::debug!(
+            "Merged {} entries over {}, {} of them replaced",
+            newer_paths.len(),
+            base_name,
+            overridden.len()
+        );
+        let dir =
+            NavigableDirectory::new(Directory::from_entries(entries.clone()));
+        (
+            Self {
+                base,
+                newer,
+                base_name,
+                entries,
+                from_base,
+                overridden,
+            },
+            dir,
+        )
+    }
+    /// Whether entry replaces one of the same path in base archive
+    pub fn is_overridden(&self, entry: &FileEntry) -> bool {
+        self.overridden.contains(&entry.full_path)
+    }
+    fn source(&self, entry: &FileEntry) -> &dyn Archive {
+        if self.from_base.contains(&entry.full_path) {
+            self.base.as_ref()
+        } else {
+            self.newer.as_ref()
+        }
+    }
+}
+
+/// Locations are not exposed, as offsets of entries point into two different
+/// archive files
+impl Archive for OverlayArchive {
+    fn extract(&self, entry: &FileEntry) -> anyhow::Result<FileContents> {
+        self.source(entry).extract(entry)
+    }
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        self.entries.par_iter().try_for_each(|entry| {
+            let file_contents = self.extract(entry)?;
+            let output_file_name = output_path.join(entry.output_path());
+            std::fs::create_dir_all(
+                &output_file_name
+                    .parent()
+                    .context("Could not get parent directory")?,
+            )?;
+            log::debug!(
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
+                entry
+            );
+            util::write_atomic(&output_file_name, &file_contents.contents)
+        })
+    }
+    fn extract_prefix(
+        &self,
+        entry: &FileEntry,
+        len: usize,
+    ) -> anyhow::Result<FileContents> {
+        self.source(entry).extract_prefix(entry, len)
+    }
+    fn extract_to(
+        &self,
+        entry: &FileEntry,
+        writer: &mut dyn Write,
+    ) -> anyhow::Result<u64> {
+        self.source(entry).extract_to(entry, writer)
+    }
+    fn entry_ext(&self, entry: &FileEntry) -> FileEntryExt {
+        FileEntryExt {
+            overrides: Some(self.base_name.clone())
+                .filter(|_| self.is_overridden(entry)),
+            ..self.source(entry).entry_ext(entry)
+        }
+    }
+    /// Entries are read from archive and its backup
+    fn stored_size(&self, _archive_path: &Path) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+}
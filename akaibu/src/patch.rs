@@ -0,0 +1,215 @@
+use crate::{
+    archive::{Archive, NavigableDirectory},
+    error::AkaibuError,
+    manifest::{EntryStatus, ManifestEntry},
+    scheme::pack::PackInput,
+    util::{self, text::display_name},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// File describing patch bundle, written at its root next to patched files
+pub const BUNDLE_FILE_NAME: &str = "patch.json";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PatchKind {
+    /// Contents differ from entry written on extraction
+    Modified,
+    /// File not found in archive
+    Added,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PatchEntry {
+    /// Path inside archive, also path of file inside bundle
+    #[serde(flatten, with = "crate::manifest::entry_path")]
+    pub path: PathBuf,
+    pub kind: PatchKind,
+    /// SHA-1 of patched file
+    pub sha1: String,
+}
+
+/// Changed entries of archive, files themselves are stored under bundle
+/// directory by their path inside archive
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PatchBundle {
+    pub akaibu_version: String,
+    /// File name of archive bundle was made for
+    pub archive: PathBuf,
+    pub entries: Vec<PatchEntry>,
+    /// Entries converted on extraction, their edits can not be turned back
+    /// into archive format so they are left out
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub skipped: Vec<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestFile {
+    archive: PathBuf,
+    entries: Vec<ManifestEntry>,
+}
+
+impl PatchBundle {
+    pub fn read(bundle_dir: &Path) -> anyhow::Result<Self> {
+        let path = bundle_dir.join(BUNDLE_FILE_NAME);
+        let contents = std::fs::read(&path).map_err(|err| {
+            AkaibuError::Custom(format!(
+                "Could not read patch bundle {:?}: {}",
+                path, err
+            ))
+        })?;
+        Ok(serde_json::from_slice(&contents)?)
+    }
+}
+
+/// Compare files of extracted directory with SHA-1 recorded in its manifest
+/// and copy changed and new files into bundle directory
+pub fn create_bundle(
+    manifest_path: &Path,
+    extracted_dir: &Path,
+    bundle_dir: &Path,
+) -> anyhow::Result<PatchBundle> {
+    let manifest: ManifestFile =
+        serde_json::from_slice(&std::fs::read(manifest_path)?)?;
+    let mut files = Vec::new();
+    util::walk_files(extracted_dir, &mut files)?;
+    files.sort();
+
+    // Entries are matched by files written for them, names of stored
+    // entries may differ when extensions were fixed
+    let mut stored = HashMap::new();
+    let mut converted = HashMap::new();
+    for entry in &manifest.entries {
+        let (sha1, output) = match &entry.status {
+            EntryStatus::Extracted { sha1, output, .. } => (sha1, output),
+            _ => continue,
+        };
+        let outputs = output
+            .as_ref()
+            .map(|output| output.files.clone())
+            .unwrap_or_else(|| vec![entry.path.clone()]);
+        for file in outputs {
+            if let Some(sha1) = sha1 {
+                stored.insert(file, (entry.path.clone(), sha1.as_str()));
+            } else {
+                converted.insert(file, entry.path.clone());
+            }
+        }
+    }
+
+    let mut entries = Vec::new();
+    let mut skipped = Vec::new();
+    for file in files {
+        let relative = file.strip_prefix(extracted_dir)?.to_path_buf();
+        // Bundle may be made inside extracted directory
+        if is_akaibu_output(&relative, &manifest.archive)
+            || file.starts_with(bundle_dir)
+        {
+            continue;
+        }
+        let sha1 = util::sha1_file(&file)?;
+        let (path, kind) = match stored.get(&relative) {
+            Some((_, original)) if *original == sha1 => continue,
+            Some((path, _)) => (path.clone(), PatchKind::Modified),
+            None => match converted.get(&relative) {
+                Some(path) => {
+                    skipped.push(path.clone());
+                    continue;
+                }
+                None => (relative, PatchKind::Added),
+            },
+        };
+        log::debug!("Patching: {} {:?}", display_name(&path), kind);
+        let bundled = bundle_dir.join(&path);
+        if let Some(parent) = bundled.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::copy(&file, &bundled)?;
+        entries.push(PatchEntry { path, kind, sha1 });
+    }
+    skipped.sort();
+    skipped.dedup();
+
+    let bundle = PatchBundle {
+        akaibu_version: env!("CARGO_PKG_VERSION").to_string(),
+        archive: manifest
+            .archive
+            .file_name()
+            .map(PathBuf::from)
+            .unwrap_or_default(),
+        entries,
+        skipped,
+    };
+    std::fs::create_dir_all(bundle_dir)?;
+    File::create(bundle_dir.join(BUNDLE_FILE_NAME))?
+        .write_all(&serde_json::to_vec_pretty(&bundle)?)?;
+    Ok(bundle)
+}
+
+/// Manifest, mapping and journal written by akaibu next to extracted files
+fn is_akaibu_output(path: &Path, archive: &Path) -> bool {
+    let archive_name = match archive.file_name() {
+        Some(name) => name.to_string_lossy(),
+        None => return false,
+    };
+    path.parent() == Some(Path::new(""))
+        && path
+            .to_string_lossy()
+            .starts_with(&format!("{}.", archive_name))
+}
+
+/// Every entry of archive with files of bundle in place of entries they
+/// patch, ready to be written by pack writer. Bundle files are checked
+/// against SHA-1 recorded when bundle was made.
+#[allow(clippy::borrowed_box)]
+pub fn apply_bundle(
+    archive: &Box<dyn Archive>,
+    dir: &NavigableDirectory,
+    bundle_dir: &Path,
+) -> anyhow::Result<Vec<PackInput>> {
+    let bundle = PatchBundle::read(bundle_dir)?;
+    let mut patched = std::collections::BTreeMap::new();
+    for entry in &bundle.entries {
+        let file = bundle_dir.join(&entry.path);
+        if util::sha1_file(&file)? != entry.sha1 {
+            return Err(AkaibuError::Custom(format!(
+                "Patch file {} does not match bundle",
+                display_name(&entry.path)
+            ))
+            .into());
+        }
+        patched.insert(entry.path.clone(), (entry.kind, file));
+    }
+
+    let mut inputs = Vec::new();
+    for entry in dir.files() {
+        let contents = match patched.remove(&entry.full_path) {
+            Some((_, file)) => std::fs::read(file)?,
+            None => archive.extract(entry)?.contents.to_vec(),
+        };
+        inputs.push(PackInput {
+            full_path: entry.full_path.clone(),
+            contents,
+        });
+    }
+    // Whatever is left was not found in archive
+    for (path, (kind, file)) in patched {
+        if kind == PatchKind::Modified {
+            log::warn!(
+                "Entry {} not found in archive, adding it",
+                display_name(&path)
+            );
+        }
+        inputs.push(PackInput {
+            full_path: path,
+            contents: std::fs::read(file)?,
+        });
+    }
+    Ok(inputs)
+}
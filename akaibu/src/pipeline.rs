@@ -0,0 +1,915 @@
+use crate::{
+    archive::{self, Archive, DirectoryArchive, FileEntry, NavigableDirectory},
+    error::{self, AkaibuError, EntryFailure},
+    manifest::{CompletedEntry, EntryOutput, EntryStatus, Manifest},
+    progress::ProgressTracker,
+    readahead,
+    resource::{
+        ContentKind, ImageFormat, ImageMetadata, ResourceMagic, ResourceScheme,
+        ResourceType,
+    },
+    scheme::Scheme,
+    util::{self, script::ScriptEngine, text::display_name},
+};
+use anyhow::Context;
+use bytes::Bytes;
+use filetime::FileTime;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use std::{
+    collections::HashMap,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+/// Settings applied to every entry by [`extract_entries`]
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// Set modification time of extracted files to one stored in archive,
+    /// if scheme exposes it
+    pub preserve_timestamps: bool,
+    /// Skip entries stored in archive with less bytes than this
+    pub min_file_size: Option<u64>,
+    /// Skip entries stored in archive with more bytes than this
+    pub max_file_size: Option<u64>,
+    /// Entries written by previous run. Entries whose file in output
+    /// directory still matches its SHA-1 are not extracted again.
+    pub resume: HashMap<PathBuf, CompletedEntry>,
+    /// Format converted images are written in
+    pub image_format: ImageFormat,
+    /// Rename entries written as stored, like audio without extension or
+    /// with engine specific one, to canonical extension of their contents.
+    /// Entries without extension also get usual one of engine formats, or
+    /// `txt` when they look like text.
+    pub fix_extensions: bool,
+    /// Write mapping of entries to files written for them into output
+    /// directory of each archive dumped by [`crate::dump::dump_game`]
+    pub mapping: bool,
+    /// Password given to schemes of archives opened by
+    /// [`crate::dump::dump_game`]
+    pub password: Option<String>,
+    /// Write entries into one of [`TYPE_FOLDERS`] of output directory by
+    /// their detected type, keeping archive paths inside it
+    pub group_by_type: bool,
+    /// Embed loops of KiriKiri `.sli` files into OGG and WAV entries they
+    /// belong to, as Vorbis comments and `smpl` chunk
+    pub embed_loops: bool,
+    /// Remove loops stored in OGG and WAV entries, Vorbis loop comments and
+    /// `smpl` chunk, from sounds not getting loop of `.sli` file embedded
+    pub strip_loops: bool,
+    /// Decode SHIFT-JIS scripts written as stored to UTF-8 text, keeping
+    /// control codes of given engine intact
+    pub reencode_scripts: Option<ScriptEngine>,
+    /// Write text entries, ones starting with BOM or named like text files,
+    /// as UTF-8 without BOM and with LF line endings under their own name
+    pub normalize_text: bool,
+    /// Downscale converted images so their larger side is at most this
+    /// many pixels, for previews and galleries
+    pub max_dimension: Option<u32>,
+    /// Stamp converted PNG images with text chunks naming this source
+    /// archive, path of their entry and akaibu version, to trace files of
+    /// shared dumps. [`crate::dump::dump_game`] replaces it with file name
+    /// of each archive it extracts.
+    pub stamp_source: Option<String>,
+    /// Keep extracting remaining entries when one fails, failures are
+    /// returned together as [`AkaibuError::EntriesFailed`] at the end.
+    /// Cancellation still stops extraction right away.
+    pub keep_going: bool,
+}
+
+impl ExtractOptions {
+    /// Resource with images fitting [`Self::max_dimension`]
+    pub(crate) fn downscale(&self, resource: ResourceType) -> ResourceType {
+        match self.max_dimension {
+            Some(max_dimension) => resource.downscaled(max_dimension),
+            None => resource,
+        }
+    }
+    /// Metadata stamped into images converted from entry, None when
+    /// [`Self::stamp_source`] is not set
+    pub(crate) fn image_metadata(
+        &self,
+        entry: &FileEntry,
+    ) -> Option<ImageMetadata> {
+        self.stamp_source.as_ref().map(|archive| ImageMetadata {
+            archive: archive.clone(),
+            entry: display_name(&entry.full_path).to_string(),
+        })
+    }
+    /// Why entry should not be extracted, None if it should
+    pub(crate) fn skip_reason(&self, entry: &FileEntry) -> Option<String> {
+        match (self.min_file_size, self.max_file_size) {
+            (Some(min), _) if entry.file_size < min => {
+                Some(format!("Smaller than minimum file size {} bytes", min))
+            }
+            (_, Some(max)) if entry.file_size > max => {
+                Some(format!("Larger than maximum file size {} bytes", max))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Extract entries in parallel into output directory keeping archive paths.
+/// Outcome of each entry is recorded in manifest when given.
+#[allow(clippy::borrowed_box)]
+pub fn extract_entries(
+    archive: &Box<dyn Archive>,
+    entries: &[FileEntry],
+    output_path: &Path,
+    progress: &ProgressTracker,
+    manifest: Option<&Manifest>,
+    options: &ExtractOptions,
+) -> anyhow::Result<()> {
+    let loop_files = if options.embed_loops {
+        loop_files(entries.iter())
+    } else {
+        HashMap::new()
+    };
+    let process = |entry: &FileEntry, raw: Option<Bytes>| {
+        let ext = archive.entry_ext(entry);
+        if let Some(reason) = options.skip_reason(entry) {
+            log::debug!(
+                "Skipping: {} {}",
+                display_name(&entry.full_path),
+                reason
+            );
+            progress.entry_done(entry.file_size, 0);
+            if let Some(manifest) = manifest {
+                manifest.record(entry, ext, EntryStatus::Skipped { reason });
+            }
+            return Ok(());
+        }
+        if let Some((bytes_written, sha1, written)) =
+            already_extracted(entry, output_path, options)
+        {
+            log::debug!(
+                "Already extracted: {}",
+                display_name(&entry.full_path)
+            );
+            progress.entry_done(entry.file_size, 0);
+            if let Some(manifest) = manifest {
+                let status = EntryStatus::Extracted {
+                    bytes_written,
+                    sha1: Some(sha1),
+                    output: Some(EntryOutput::stored(written)),
+                };
+                manifest.record(entry, ext, status);
+            }
+            return Ok(());
+        }
+        let modified = ext.modified.filter(|_| options.preserve_timestamps);
+        let result = extract_entry(
+            archive,
+            entry,
+            output_path,
+            progress,
+            modified,
+            options,
+            raw,
+            loop_files.get(&loop_key(&entry.full_path)).copied(),
+        );
+        if let Some(manifest) = manifest {
+            let status = match &result {
+                Ok((bytes_written, sha1, output)) => EntryStatus::Extracted {
+                    bytes_written: *bytes_written,
+                    sha1: sha1.clone(),
+                    output: Some(output.clone()),
+                },
+                Err(err) => EntryStatus::Failed {
+                    reason: err.to_string(),
+                },
+            };
+            manifest.record(entry, ext, status);
+        }
+        result.map(|_| ())
+    };
+    let failures = Mutex::new(Vec::new());
+    let process =
+        |entry: &FileEntry, raw: Option<Bytes>| match process(entry, raw) {
+            Err(err) if options.keep_going && !error::is_cancelled(&err) => {
+                log::warn!(
+                    "Could not extract {}: {:#}",
+                    display_name(&entry.full_path),
+                    err
+                );
+                failures.lock().expect("Failures lock poisoned").push(
+                    EntryFailure {
+                        path: entry.full_path.clone(),
+                        reason: format!("{:#}", err),
+                    },
+                );
+                Ok(())
+            }
+            result => result,
+        };
+    // Entries stored next to each other are read in batches, which is much
+    // faster than seek per entry on hard drives
+    match readahead::plan(archive.as_ref(), entries) {
+        Some(batches) => {
+            log::debug!(
+                "Reading {} entries in {} batches",
+                entries.len(),
+                batches.len()
+            );
+            readahead::run(archive.as_ref(), &batches, process)
+        }
+        None => entries
+            .par_iter()
+            .try_for_each(|entry| process(entry, None)),
+    }?;
+    let mut failures = failures.into_inner().expect("Failures lock poisoned");
+    if failures.is_empty() {
+        return Ok(());
+    }
+    failures.sort_by(|a, b| a.path.cmp(&b.path));
+    Err(AkaibuError::EntriesFailed(failures).into())
+}
+
+/// Directory entries of archive are extracted into. With `subdir` every
+/// archive gets own `<output_path>/<archive_stem>/` folder, so entries of
+/// archives extracted into the same output directory do not collide.
+pub fn archive_output_dir(
+    output_path: &Path,
+    archive_path: &Path,
+    subdir: bool,
+) -> anyhow::Result<PathBuf> {
+    if !subdir {
+        return Ok(output_path.to_path_buf());
+    }
+    let stem = archive_path
+        .file_stem()
+        .context("Could not get archive file name")?;
+    Ok(output_path.join(stem))
+}
+
+/// Size, SHA-1 and path relative to output path of file written by previous
+/// run, if it matches manifest. File is looked up where manifest recorded
+/// it, manifests of older versions fall back to path of entry.
+fn already_extracted(
+    entry: &FileEntry,
+    output_path: &Path,
+    options: &ExtractOptions,
+) -> Option<(u64, String, PathBuf)> {
+    let completed = options.resume.get(&entry.full_path)?;
+    let sha1 = &completed.sha1;
+    // Recorded path is kept inside output path like names from archive
+    let written = if let Some(output) = &completed.output {
+        archive::output_path(output)
+    } else if options.group_by_type {
+        TYPE_FOLDERS
+            .iter()
+            .map(|folder| Path::new(folder).join(entry.output_path()))
+            .find(|written| output_path.join(written).is_file())?
+    } else {
+        entry.output_path()
+    };
+    let output_file_name = output_path.join(&written);
+    match util::sha1_file(&output_file_name) {
+        Ok(file_sha1) if &file_sha1 == sha1 => {
+            let bytes_written =
+                std::fs::metadata(&output_file_name).ok()?.len();
+            Some((bytes_written, file_sha1, written))
+        }
+        _ => None,
+    }
+}
+
+/// Folders of output directory entries are written into with
+/// [`ExtractOptions::group_by_type`]
+pub const TYPE_FOLDERS: [&str; 4] = ["images", "scripts", "audio", "other"];
+
+/// Folder of output directory entry is written into when grouping outputs
+/// by type. Converted resource decides it by its type, entry written as
+/// stored by its contents.
+pub(crate) fn type_folder(
+    resource: Option<&ResourceType>,
+    contents: &[u8],
+) -> &'static str {
+    match resource {
+        Some(ResourceType::RgbaImage { .. })
+        | Some(ResourceType::SpriteSheet { .. })
+        | Some(ResourceType::Animation { .. }) => "images",
+        Some(ResourceType::Text(_)) => "scripts",
+        Some(ResourceType::Other) | None => {
+            let magic = ResourceMagic::parse_magic(contents);
+            match magic {
+                ResourceMagic::Ogg
+                | ResourceMagic::Wav
+                | ResourceMagic::Adx => "audio",
+                _ => match ContentKind::sniff(&magic, contents) {
+                    ContentKind::Image => "images",
+                    ContentKind::Script => "scripts",
+                    ContentKind::Other => "other",
+                },
+            }
+        }
+    }
+}
+
+/// Path entry is written to, inside its type folder when grouping by type.
+/// Always inside output path, see [`FileEntry::output_path`].
+pub(crate) fn output_file_name(
+    output_path: &Path,
+    entry: &FileEntry,
+    folder: Option<&str>,
+) -> PathBuf {
+    let mut output_file_name = PathBuf::from(output_path);
+    if let Some(folder) = folder {
+        output_file_name.push(folder);
+    }
+    output_file_name.push(entry.output_path());
+    output_file_name
+}
+
+/// Script entry decoded to UTF-8 by [`ExtractOptions::reencode_scripts`],
+/// None for entries that are not SHIFT-JIS text
+pub(crate) fn reencode_script(
+    options: &ExtractOptions,
+    contents: &[u8],
+) -> anyhow::Result<Option<ResourceType>> {
+    let engine = match options.reencode_scripts {
+        Some(engine) => engine,
+        None => return Ok(None),
+    };
+    let magic = ResourceMagic::parse_magic(contents);
+    if !matches!(magic, ResourceMagic::Unrecognized)
+        || std::str::from_utf8(contents).is_ok()
+        || ContentKind::sniff(&magic, contents) != ContentKind::Script
+    {
+        return Ok(None);
+    }
+    Ok(Some(ResourceType::Text(util::script::reencode(
+        engine.tokenizer(),
+        contents,
+    )?)))
+}
+
+/// Whether entry is text normalized by [`ExtractOptions::normalize_text`].
+/// Text without BOM is recognized only in entries named like text files.
+fn normalizes_text(
+    options: &ExtractOptions,
+    entry: &FileEntry,
+    contents: &[u8],
+) -> bool {
+    if !options.normalize_text {
+        return false;
+    }
+    match ResourceMagic::parse_magic(contents) {
+        ResourceMagic::Text => true,
+        ResourceMagic::Unrecognized => {
+            matches!(
+                ResourceMagic::parse_file_extension(&entry.full_path),
+                ResourceMagic::Text
+            ) && util::text::looks_like_text(
+                &contents[..contents.len().min(CONTENT_SNIFF_SIZE)],
+            )
+        }
+        _ => false,
+    }
+}
+
+/// Lowercase path of sound `.sli` file belongs to
+pub(crate) fn loop_key(path: &Path) -> String {
+    path.to_string_lossy().to_lowercase()
+}
+
+/// KiriKiri `.sli` loop files by lowercase path of sound they belong to,
+/// which is their own path without `.sli`
+pub(crate) fn loop_files<'a>(
+    entries: impl Iterator<Item = &'a FileEntry>,
+) -> HashMap<String, &'a FileEntry> {
+    entries
+        .filter_map(|entry| {
+            let key = loop_key(&entry.full_path);
+            Some((key.strip_suffix(".sli")?.to_string(), entry))
+        })
+        .collect()
+}
+
+/// Sound with loop of `.sli` file embedded, unchanged contents when loop
+/// could not be read or sound is not OGG or WAV
+#[allow(clippy::borrowed_box)]
+pub(crate) fn embed_loop(
+    archive: &Box<dyn Archive>,
+    contents: Bytes,
+    loop_file: &FileEntry,
+) -> Bytes {
+    let embedded = archive.extract(loop_file).and_then(|sli| {
+        let loop_points =
+            util::audio::parse_sli(&util::text::decode(&sli.contents))?;
+        match ResourceMagic::parse_magic(&contents) {
+            ResourceMagic::Ogg => {
+                util::audio::set_ogg_loop(&contents, loop_points)
+            }
+            ResourceMagic::Wav => util::audio::set_wav_loop(
+                &contents,
+                util::audio::wav_sample_rate(&contents)?,
+                loop_points,
+            ),
+            resource => Err(AkaibuError::Unimplemented(format!(
+                "Loop of {:?}",
+                resource
+            ))
+            .into()),
+        }
+    });
+    match embedded {
+        Ok(embedded) => embedded.into(),
+        Err(err) => {
+            log::warn!(
+                "Could not embed loop of {}: {}",
+                display_name(&loop_file.full_path),
+                err
+            );
+            contents
+        }
+    }
+}
+
+/// Sound with loops removed, unchanged contents when it is not OGG or WAV
+/// or could not be parsed
+pub(crate) fn strip_loop(contents: Bytes, entry: &FileEntry) -> Bytes {
+    let stripped = match ResourceMagic::parse_magic(&contents) {
+        ResourceMagic::Ogg => util::audio::strip_ogg_loop(&contents),
+        ResourceMagic::Wav => util::audio::strip_wav_loop(&contents),
+        _ => return contents,
+    };
+    match stripped {
+        Ok(stripped) => stripped.into(),
+        Err(err) => {
+            log::warn!(
+                "Could not strip loop of {}: {}",
+                display_name(&entry.full_path),
+                err
+            );
+            contents
+        }
+    }
+}
+
+/// Sound written as stored with loop of its `.sli` file embedded, or with
+/// its own loops stripped when asked to
+#[allow(clippy::borrowed_box)]
+pub(crate) fn apply_loop(
+    archive: &Box<dyn Archive>,
+    entry: &FileEntry,
+    contents: Bytes,
+    loop_file: Option<&FileEntry>,
+    options: &ExtractOptions,
+) -> Bytes {
+    match loop_file {
+        Some(loop_file) => embed_loop(archive, contents, loop_file),
+        None if options.strip_loops => strip_loop(contents, entry),
+        None => contents,
+    }
+}
+
+/// Returns number of bytes written, SHA-1 of file written as is and files
+/// written relative to output path. Entry is decoded from `raw` bytes when
+/// they were already read from archive file.
+#[allow(clippy::borrowed_box, clippy::too_many_arguments)]
+fn extract_entry(
+    archive: &Box<dyn Archive>,
+    entry: &FileEntry,
+    output_path: &Path,
+    progress: &ProgressTracker,
+    modified: Option<u64>,
+    options: &ExtractOptions,
+    raw: Option<Bytes>,
+    loop_file: Option<&FileEntry>,
+) -> anyhow::Result<(u64, Option<String>, EntryOutput)> {
+    if let (None, None) = (&raw, loop_file) {
+        if let Some(prefix) = streamable_prefix(archive, entry, options)? {
+            return stream_entry(
+                archive,
+                entry,
+                output_path,
+                progress,
+                modified,
+                options,
+                &prefix,
+            );
+        }
+    }
+    let mut file_contents = match raw {
+        Some(raw) => archive.decode_raw(entry, raw)?,
+        None => archive.extract(entry)?,
+    };
+    let resource = match file_contents.convert(Some(archive))? {
+        Some(resource) => Some(options.downscale(resource)),
+        None => reencode_script(options, &file_contents.contents)?,
+    };
+    let mut stored_sha1 = None;
+    if resource.is_none() {
+        file_contents.contents =
+            if normalizes_text(options, entry, &file_contents.contents) {
+                stored_sha1 = Some(
+                    sha1::Sha1::from(&file_contents.contents)
+                        .digest()
+                        .to_string(),
+                );
+                util::text::normalize(&file_contents.contents).into()
+            } else {
+                apply_loop(
+                    archive,
+                    entry,
+                    file_contents.contents,
+                    loop_file,
+                    options,
+                )
+            };
+    }
+    let folder = Some(type_folder(resource.as_ref(), &file_contents.contents))
+        .filter(|_| options.group_by_type);
+    let mut output_file_name = output_file_name(output_path, entry, folder);
+    if options.fix_extensions && file_contents.type_hint.is_none() {
+        fix_extension(&mut output_file_name, &file_contents.contents);
+    }
+    std::fs::create_dir_all(
+        &output_file_name
+            .parent()
+            .context("Could not get parent directory")?,
+    )?;
+    log::debug!(
+        "Extracting resource: {} {:X?}",
+        display_name(&output_file_name),
+        entry
+    );
+    let bytes_written = file_contents.contents.len() as u64;
+    // Converted resources do not have single output file to verify, and
+    // re-encoded scripts differ from bytes extracted. Normalized text keeps
+    // hash of bytes stored in archive.
+    let sha1 = if resource.is_none() {
+        stored_sha1.or_else(|| {
+            Some(
+                sha1::Sha1::from(&file_contents.contents)
+                    .digest()
+                    .to_string(),
+            )
+        })
+    } else {
+        None
+    };
+    let output = match resource {
+        Some(resource) => write_converted(
+            resource,
+            &output_file_name,
+            output_path,
+            options.image_format,
+            options.image_metadata(entry).as_ref(),
+        )?,
+        None => {
+            util::write_atomic(&output_file_name, &file_contents.contents)?;
+            EntryOutput::stored(
+                output_file_name
+                    .strip_prefix(output_path)
+                    .unwrap_or(&output_file_name)
+                    .to_path_buf(),
+            )
+        }
+    };
+    finish_entry(entry, &output_file_name, progress, modified, bytes_written)?;
+    Ok((bytes_written, sha1, output))
+}
+
+/// Set modification time of written file and report entry as done
+fn finish_entry(
+    entry: &FileEntry,
+    output_file_name: &Path,
+    progress: &ProgressTracker,
+    modified: Option<u64>,
+    bytes_written: u64,
+) -> anyhow::Result<()> {
+    // Converted resources are written under different extension
+    if let (Some(modified), true) = (modified, output_file_name.is_file()) {
+        filetime::set_file_mtime(
+            output_file_name,
+            FileTime::from_unix_time(modified as i64, 0),
+        )?;
+    }
+    progress.entry_done(entry.file_size, bytes_written);
+    Ok(())
+}
+
+/// Entries larger than this are copied straight from archive file when
+/// they are stored as is and written as stored
+const STREAMING_THRESHOLD: u64 = 64 << 20;
+
+/// First bytes of large entry that can be written without holding it in
+/// memory, None when entry has to be decoded or converted first
+#[allow(clippy::borrowed_box)]
+fn streamable_prefix(
+    archive: &Box<dyn Archive>,
+    entry: &FileEntry,
+    options: &ExtractOptions,
+) -> anyhow::Result<Option<Vec<u8>>> {
+    if entry.file_size <= STREAMING_THRESHOLD {
+        return Ok(None);
+    }
+    let (offset, size) = match archive.entry_location(entry) {
+        Some(location) => location,
+        None => return Ok(None),
+    };
+    let mut prefix = vec![0; size.min(CONTENT_SNIFF_SIZE as u64) as usize];
+    archive.read_raw(offset, &mut prefix)?;
+    // Scripts are re-encoded and text normalized whole
+    if !matches!(reencode_script(options, &prefix), Ok(None))
+        || normalizes_text(options, entry, &prefix)
+    {
+        return Ok(None);
+    }
+    // Loops are stripped from whole sound
+    if options.strip_loops
+        && matches!(
+            ResourceMagic::parse_magic(&prefix),
+            ResourceMagic::Ogg | ResourceMagic::Wav
+        )
+    {
+        return Ok(None);
+    }
+    Ok(Some(prefix))
+}
+
+/// Write entry stored as is in chunks read from archive file, hashing them
+/// on the way. Its type folder and extension are decided by its prefix.
+#[allow(clippy::borrowed_box)]
+fn stream_entry(
+    archive: &Box<dyn Archive>,
+    entry: &FileEntry,
+    output_path: &Path,
+    progress: &ProgressTracker,
+    modified: Option<u64>,
+    options: &ExtractOptions,
+    prefix: &[u8],
+) -> anyhow::Result<(u64, Option<String>, EntryOutput)> {
+    let folder =
+        Some(type_folder(None, prefix)).filter(|_| options.group_by_type);
+    let mut output_file_name = output_file_name(output_path, entry, folder);
+    if options.fix_extensions {
+        fix_extension(&mut output_file_name, prefix);
+    }
+    std::fs::create_dir_all(
+        &output_file_name
+            .parent()
+            .context("Could not get parent directory")?,
+    )?;
+    log::debug!(
+        "Streaming resource: {} {:X?}",
+        display_name(&output_file_name),
+        entry
+    );
+    let (bytes_written, sha1) =
+        util::write_atomic_with(&output_file_name, |file| {
+            let mut writer = HashingWriter {
+                inner: file,
+                hasher: sha1::Sha1::new(),
+            };
+            let bytes_written = archive.extract_to(entry, &mut writer)?;
+            Ok((bytes_written, writer.hasher.digest().to_string()))
+        })?;
+    finish_entry(entry, &output_file_name, progress, modified, bytes_written)?;
+    let output = EntryOutput::stored(
+        output_file_name
+            .strip_prefix(output_path)
+            .unwrap_or(&output_file_name)
+            .to_path_buf(),
+    );
+    Ok((bytes_written, Some(sha1), output))
+}
+
+/// Writer computing SHA-1 of everything written through it
+struct HashingWriter<W> {
+    inner: W,
+    hasher: sha1::Sha1,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Write converted resource, returned files are relative to output path
+pub(crate) fn write_converted(
+    resource: ResourceType,
+    output_file_name: &Path,
+    output_path: &Path,
+    image_format: ImageFormat,
+    metadata: Option<&ImageMetadata>,
+) -> anyhow::Result<EntryOutput> {
+    let format = resource.output_format(image_format).to_string();
+    let dimensions = resource.dimensions();
+    let files = resource
+        .write_resource_stamped(output_file_name, image_format, metadata)?
+        .into_iter()
+        .map(|file| {
+            file.strip_prefix(output_path)
+                .map(Path::to_path_buf)
+                .unwrap_or(file)
+        })
+        .collect();
+    Ok(EntryOutput {
+        files,
+        format,
+        dimensions,
+    })
+}
+
+/// Replace extension of file written as stored with canonical one of its
+/// detected format. Names without known format are kept.
+fn fix_extension(output_file_name: &mut PathBuf, contents: &[u8]) {
+    let resource = ResourceMagic::parse_magic(contents);
+    let current = output_file_name
+        .extension()
+        .and_then(|current| current.to_str());
+    let extension = match (resource.extension(), current) {
+        (Some(extension), _) => extension,
+        // Engine formats and text only get extension when it is missing
+        (None, Some(_)) => return,
+        (None, None) => match resource.usual_extension() {
+            Some(extension) => extension,
+            None if util::text::looks_like_text(
+                &contents[..contents.len().min(CONTENT_SNIFF_SIZE)],
+            ) =>
+            {
+                "txt"
+            }
+            None => return,
+        },
+    };
+    let is_canonical = |current: &str| {
+        let current = current.to_ascii_lowercase();
+        current == extension
+            || matches!(
+                (extension, current.as_str()),
+                ("jpg", "jpeg") | ("mpg", "mpeg")
+            )
+    };
+    if current.map_or(false, is_canonical) {
+        return;
+    }
+    log::debug!(
+        "Renaming {} to .{}",
+        display_name(output_file_name.as_path()),
+        extension
+    );
+    output_file_name.set_extension(extension);
+}
+
+/// Number of bytes read from each entry to detect its content kind
+const CONTENT_SNIFF_SIZE: usize = 0x200;
+
+/// Keep only entries whose sniffed content is of given kind.
+/// Entries that could not be read are skipped.
+#[allow(clippy::borrowed_box)]
+pub fn filter_entries<'a>(
+    archive: &Box<dyn Archive>,
+    entries: impl IntoIterator<Item = &'a FileEntry>,
+    kind: ContentKind,
+) -> Vec<FileEntry> {
+    entries
+        .into_iter()
+        .collect::<Vec<&FileEntry>>()
+        .par_iter()
+        .filter(|entry| {
+            match archive.extract_prefix(entry, CONTENT_SNIFF_SIZE) {
+                Ok(file_contents) => {
+                    ContentKind::sniff(
+                        &file_contents.get_resource_type(),
+                        &file_contents.contents,
+                    ) == kind
+                }
+                Err(err) => {
+                    log::error!(
+                        "Could not read entry: {} {}",
+                        display_name(&entry.full_path),
+                        err
+                    );
+                    false
+                }
+            }
+        })
+        .map(|entry| (*entry).clone())
+        .collect()
+}
+
+/// Convert loose resource file. Files it references, like parent images,
+/// are looked up in the same folder through [`DirectoryArchive`].
+pub fn convert_file(
+    scheme: &dyn ResourceScheme,
+    file_path: &Path,
+) -> anyhow::Result<ResourceType> {
+    let buf = std::fs::read(file_path)?;
+    let archive: Box<dyn Archive> =
+        Box::new(DirectoryArchive::containing(file_path)?);
+    scheme.convert_from_bytes(file_path, buf, Some(&archive))
+}
+
+/// Opened archive with its entries, for library consumers that process
+/// entries in memory instead of writing them to output directory
+#[derive(Debug)]
+pub struct ArchiveHandle {
+    archive: Box<dyn Archive>,
+    dir: NavigableDirectory,
+}
+
+/// Entry contents after [`ArchiveHandle::convert`]
+#[derive(Debug, Clone)]
+pub enum Converted {
+    /// Entry recognized as resource and converted
+    Resource(ResourceType),
+    /// Entry of unknown type, as stored in archive after decryption
+    Raw(Bytes),
+}
+
+#[derive(Debug, Clone)]
+pub struct ConvertedEntry {
+    pub entry: FileEntry,
+    pub contents: Converted,
+}
+
+impl ArchiveHandle {
+    pub fn new(archive: Box<dyn Archive>, dir: NavigableDirectory) -> Self {
+        Self { archive, dir }
+    }
+    pub fn open(scheme: &dyn Scheme, file_path: &Path) -> anyhow::Result<Self> {
+        Self::open_with_password(scheme, file_path, None)
+    }
+    pub fn open_with_password(
+        scheme: &dyn Scheme,
+        file_path: &Path,
+        password: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let (archive, dir) = match password {
+            Some(password) => {
+                scheme.extract_with_password(file_path, password)?
+            }
+            None => scheme.extract(file_path)?,
+        };
+        Ok(Self::new(archive, dir))
+    }
+    #[allow(clippy::borrowed_box)]
+    pub fn archive(&self) -> &Box<dyn Archive> {
+        &self.archive
+    }
+    pub fn dir(&self) -> &NavigableDirectory {
+        &self.dir
+    }
+    /// Extract single entry and convert it when it is known resource
+    pub fn convert(&self, entry: &FileEntry) -> anyhow::Result<ConvertedEntry> {
+        let file_contents = self.archive.extract(entry)?;
+        let resource = file_contents.get_resource_type();
+        let contents = match resource.get_schemes().into_iter().next() {
+            Some(scheme) if resource.is_universal() => {
+                Converted::Resource(scheme.convert_from_bytes(
+                    &entry.full_path,
+                    file_contents.contents.to_vec(),
+                    Some(&self.archive),
+                )?)
+            }
+            _ => Converted::Raw(file_contents.contents),
+        };
+        Ok(ConvertedEntry {
+            entry: entry.clone(),
+            contents,
+        })
+    }
+    /// Lazily convert every entry passing size limits of options, one at a
+    /// time. Failure of one entry does not stop the iteration.
+    pub fn convert_all<'a>(
+        &'a self,
+        options: &'a ExtractOptions,
+    ) -> impl Iterator<Item = anyhow::Result<ConvertedEntry>> + 'a {
+        self.convert_entries(self.dir.files(), options)
+    }
+    /// Lazily convert given entries in their order, skipping ones outside
+    /// of size limits of options and downscaling images to their
+    /// [`ExtractOptions::max_dimension`]
+    pub fn convert_entries<'a>(
+        &'a self,
+        entries: impl IntoIterator<Item = &'a FileEntry> + 'a,
+        options: &'a ExtractOptions,
+    ) -> impl Iterator<Item = anyhow::Result<ConvertedEntry>> + 'a {
+        entries
+            .into_iter()
+            .filter(move |entry| options.skip_reason(entry).is_none())
+            .map(move |entry| {
+                let mut converted = self.convert(entry).with_context(|| {
+                    display_name(&entry.full_path).to_string()
+                })?;
+                converted.contents = match converted.contents {
+                    Converted::Resource(resource) => {
+                        Converted::Resource(options.downscale(resource))
+                    }
+                    raw => raw,
+                };
+                Ok(converted)
+            })
+    }
+}
@@ -0,0 +1,92 @@
+use crate::magic::{self, Archive};
+use enum_iterator::IntoEnumIterator;
+use serde::Serialize;
+use std::{mem::discriminant, path::Path};
+
+/// Confidence added when archive magic matches start or end of file
+const MAGIC_CONFIDENCE: f32 = 0.6;
+/// Confidence added when file extension is one used by archive type
+const EXTENSION_CONFIDENCE: f32 = 0.3;
+/// Confidence added when scheme could read archive index
+const INDEX_CONFIDENCE: f32 = 0.4;
+
+/// How likely is file to be read by single scheme
+#[derive(Debug, Clone, Serialize)]
+pub struct ProbeResult {
+    /// Archive type scheme belongs to
+    pub archive: String,
+    pub scheme: String,
+    /// From 0 (no sign of archive type) to 1 (magic matched, index read),
+    /// meaningless when scheme was not tested
+    pub confidence: f32,
+    /// Whether scheme tried to read index of file
+    pub tested: bool,
+    /// Number of entries in index, when it could be read
+    pub entries: Option<usize>,
+    /// Why scheme rejected file
+    pub failure: Option<String>,
+}
+
+/// Check file against every registered scheme and rank them by confidence.
+/// Magic of every archive type is checked regardless of file extension, so
+/// misnamed archives are still found. Only schemes whose archive magic or
+/// extension matches try to read index, others are reported as not tested
+/// and ranked last.
+pub fn probe(file_path: &Path) -> anyhow::Result<Vec<ProbeResult>> {
+    let sniffed = magic::sniff(file_path)?;
+    let magic = magic::detect_with_tail(&sniffed.head, &sniffed.tail);
+    let extension = Archive::parse_file_extension(file_path);
+
+    let mut results = Archive::into_enum_iter()
+        .filter(|archive| !matches!(archive, Archive::NotRecognized))
+        .flat_map(|archive| {
+            let is =
+                |other: &Archive| discriminant(&archive) == discriminant(other);
+            let mut confidence = 0.0;
+            if is(&magic) {
+                confidence += MAGIC_CONFIDENCE;
+            }
+            if is(&extension) {
+                confidence += EXTENSION_CONFIDENCE;
+            }
+            let matched = confidence > 0.0;
+            let archive_name = format!("{:?}", archive);
+            archive
+                .get_schemes()
+                .into_iter()
+                .map(|scheme| {
+                    let mut result = ProbeResult {
+                        archive: archive_name.clone(),
+                        scheme: scheme.get_name(),
+                        confidence,
+                        tested: matched,
+                        entries: None,
+                        failure: None,
+                    };
+                    if !matched {
+                        return result;
+                    }
+                    match scheme.extract(file_path) {
+                        Ok((_, dir)) => {
+                            result.confidence += INDEX_CONFIDENCE;
+                            result.entries = Some(dir.files().count());
+                        }
+                        Err(err) => result.failure = Some(err.to_string()),
+                    }
+                    result
+                })
+                .collect::<Vec<ProbeResult>>()
+        })
+        .collect::<Vec<ProbeResult>>();
+    for result in &mut results {
+        result.confidence = result.confidence.min(1.0);
+    }
+    results.sort_by(|a, b| {
+        b.tested.cmp(&a.tested).then_with(|| {
+            b.confidence
+                .partial_cmp(&a.confidence)
+                .unwrap_or(std::cmp::Ordering::Equal)
+        })
+    });
+    Ok(results)
+}
@@ -0,0 +1,115 @@
+use crate::archive::FileEntry;
+use std::{
+    fmt::Debug,
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant},
+};
+
+/// Snapshot of extraction progress
+#[derive(Debug, Clone, Copy)]
+pub struct Progress {
+    pub entries_done: u64,
+    pub entries_total: u64,
+    /// Bytes read from archive (compressed/encrypted entry data)
+    pub bytes_read: u64,
+    /// Sum of all entry sizes in archive
+    pub bytes_total: u64,
+    /// Bytes of extracted data handed to output
+    pub bytes_written: u64,
+    pub elapsed: Duration,
+}
+
+impl Progress {
+    /// Finished fraction in range 0.0..=1.0
+    pub fn fraction(&self) -> f32 {
+        if self.bytes_total != 0 {
+            self.bytes_read as f32 / self.bytes_total as f32
+        } else if self.entries_total != 0 {
+            self.entries_done as f32 / self.entries_total as f32
+        } else {
+            1.0
+        }
+    }
+    /// Archive read throughput in bytes per second
+    pub fn bytes_per_second(&self) -> f64 {
+        let secs = self.elapsed.as_secs_f64();
+        if secs > 0.0 {
+            self.bytes_read as f64 / secs
+        } else {
+            0.0
+        }
+    }
+    /// Estimated time left, None until anything was processed
+    pub fn eta(&self) -> Option<Duration> {
+        let fraction = self.fraction() as f64;
+        if fraction <= 0.0 {
+            return None;
+        }
+        let total = self.elapsed.as_secs_f64() / fraction;
+        Some(Duration::from_secs_f64(
+            (total - self.elapsed.as_secs_f64()).max(0.0),
+        ))
+    }
+}
+
+pub type ProgressCallback = Box<dyn Fn(&Progress) + Send + Sync>;
+
+/// Thread safe progress accumulator shared by extraction workers
+pub struct ProgressTracker {
+    entries_total: u64,
+    bytes_total: u64,
+    entries_done: AtomicU64,
+    bytes_read: AtomicU64,
+    bytes_written: AtomicU64,
+    start: Instant,
+    callback: Option<ProgressCallback>,
+}
+
+impl Debug for ProgressTracker {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ProgressTracker")
+            .field("progress", &self.progress())
+            .finish()
+    }
+}
+
+impl ProgressTracker {
+    pub fn new(entries: &[FileEntry]) -> Self {
+        Self {
+            entries_total: entries.len() as u64,
+            bytes_total: entries.iter().map(|entry| entry.file_size).sum(),
+            entries_done: AtomicU64::new(0),
+            bytes_read: AtomicU64::new(0),
+            bytes_written: AtomicU64::new(0),
+            start: Instant::now(),
+            callback: None,
+        }
+    }
+    /// Set function called after every finished entry
+    pub fn with_callback(
+        mut self,
+        callback: impl Fn(&Progress) + Send + Sync + 'static,
+    ) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+    pub fn entry_done(&self, bytes_read: u64, bytes_written: u64) {
+        self.entries_done.fetch_add(1, Ordering::Relaxed);
+        self.bytes_read.fetch_add(bytes_read, Ordering::Relaxed);
+        self.bytes_written
+            .fetch_add(bytes_written, Ordering::Relaxed);
+        if let Some(callback) = &self.callback {
+            callback(&self.progress());
+        }
+    }
+    pub fn progress(&self) -> Progress {
+        Progress {
+            entries_done: self.entries_done.load(Ordering::Relaxed),
+            entries_total: self.entries_total,
+            bytes_read: self.bytes_read.load(Ordering::Relaxed),
+            bytes_total: self.bytes_total,
+            bytes_written: self.bytes_written.load(Ordering::Relaxed),
+            elapsed: self.start.elapsed(),
+        }
+    }
+}
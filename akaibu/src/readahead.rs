@@ -0,0 +1,132 @@
+use crate::{
+    archive::{Archive, FileEntry},
+    util::text::display_name,
+};
+use bytes::{Bytes, BytesMut};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+
+/// Largest number of bytes read from archive file at once
+const MAX_BATCH_SIZE: u64 = 8 << 20;
+/// Largest gap between neighbouring entries read together with them
+const MAX_GAP: u64 = 64 << 10;
+
+/// Neighbouring entries read from archive file with single read
+#[derive(Debug)]
+pub(crate) struct Batch<'a> {
+    offset: u64,
+    size: u64,
+    /// Entries with offset of their bytes in batch and size
+    entries: Vec<(&'a FileEntry, u64, u64)>,
+}
+
+/// Group entries stored next to each other into batches read in one go, so
+/// thousands of small entries do not take a seek each. None when scheme can
+/// not decode entries from raw bytes or entries are scattered over archive
+/// file, as batches would then mostly read bytes nobody asked for.
+pub(crate) fn plan<'a>(
+    archive: &dyn Archive,
+    entries: &'a [FileEntry],
+) -> Option<Vec<Batch<'a>>> {
+    let mut located = entries
+        .iter()
+        .map(|entry| {
+            let (offset, size) = archive.raw_location(entry)?;
+            Some((entry, offset, size))
+        })
+        .collect::<Option<Vec<(&FileEntry, u64, u64)>>>()?;
+    located.sort_by_key(|&(_, offset, _)| offset);
+
+    let mut batches: Vec<Batch<'a>> = Vec::new();
+    let mut gaps = 0;
+    for (entry, offset, size) in located {
+        let end = offset.checked_add(size)?;
+        match batches.last_mut() {
+            Some(batch)
+                if offset <= batch.offset + batch.size + MAX_GAP
+                    && end.max(batch.offset + batch.size) - batch.offset
+                        <= MAX_BATCH_SIZE =>
+            {
+                gaps += offset.saturating_sub(batch.offset + batch.size);
+                batch.size = batch.size.max(end - batch.offset);
+                batch.entries.push((entry, offset - batch.offset, size));
+            }
+            _ => batches.push(Batch {
+                offset,
+                size,
+                entries: vec![(entry, 0, size)],
+            }),
+        }
+    }
+    let read = batches.iter().map(|batch| batch.size).sum::<u64>();
+    // Entries should take most of bytes read and share batches
+    if gaps * 8 > read || batches.len() * 2 > entries.len() {
+        return None;
+    }
+    Some(batches)
+}
+
+/// Read batches one after another, next one while entries of previous are
+/// processed in parallel. Entries of batch that could not be read are
+/// processed without raw bytes, extracted one by one.
+pub(crate) fn run<F>(
+    archive: &dyn Archive,
+    batches: &[Batch<'_>],
+    process: F,
+) -> anyhow::Result<()>
+where
+    F: Fn(&FileEntry, Option<Bytes>) -> anyhow::Result<()> + Sync,
+{
+    let read = |batch: &Batch<'_>| -> Option<anyhow::Result<Bytes>> {
+        // Only single entry can be larger than batch, it is left unread so
+        // it can be streamed from archive file
+        if batch.size > MAX_BATCH_SIZE {
+            return None;
+        }
+        let mut buf = BytesMut::with_capacity(batch.size as usize);
+        buf.resize(batch.size as usize, 0);
+        Some(
+            archive
+                .read_raw(batch.offset, &mut buf)
+                .map(|_| buf.freeze()),
+        )
+    };
+    let mut next = batches.first().and_then(read);
+    for (i, batch) in batches.iter().enumerate() {
+        let buf = match next.take() {
+            Some(Ok(buf)) => Some(buf),
+            Some(Err(err)) => {
+                log::warn!(
+                    "Could not read {} bytes at {:#X}, extracting {} entries \
+                     one by one: {}",
+                    batch.size,
+                    batch.offset,
+                    batch.entries.len(),
+                    err
+                );
+                None
+            }
+            None => None,
+        };
+        let (processed, read_next) = rayon::join(
+            || {
+                batch.entries.par_iter().try_for_each(
+                    |&(entry, start, size)| {
+                        log::trace!(
+                            "Read ahead: {} at {:#X}",
+                            display_name(&entry.full_path),
+                            batch.offset + start
+                        );
+                        let raw = buf.as_ref().map(|buf| {
+                            buf.slice(start as usize..(start + size) as usize)
+                        });
+                        process(entry, raw)
+                    },
+                )
+            },
+            || batches.get(i + 1).and_then(read),
+        );
+        processed?;
+        next = read_next;
+    }
+    Ok(())
+}
@@ -0,0 +1,81 @@
+use crate::error::AkaibuError;
+use serde::Serialize;
+use std::{
+    fs::File,
+    io::{Read, Write},
+    path::Path,
+};
+
+/// Number of bytes from start of archive included in report
+const HEADER_SAMPLE_SIZE: u64 = 0x40;
+
+/// Information about archive that could not be extracted because its key
+/// is not known. Meant to be attached to issue so key can be added.
+#[derive(Debug, Clone, Serialize)]
+pub struct MissingKeyReport {
+    pub akaibu_version: String,
+    pub scheme: String,
+    pub key_type: String,
+    pub file_name: String,
+    pub file_size: u64,
+    pub sha1: String,
+    pub header_sample: String,
+}
+
+impl MissingKeyReport {
+    /// Create report if extraction failed because of missing key
+    pub fn from_error(
+        file_path: &Path,
+        err: &anyhow::Error,
+    ) -> anyhow::Result<Option<Self>> {
+        let (scheme, key_type) = match err.downcast_ref::<AkaibuError>() {
+            Some(AkaibuError::MissingKey { scheme, key_type }) => {
+                (scheme.clone(), key_type.clone())
+            }
+            _ => return Ok(None),
+        };
+        let mut file = File::open(file_path)?;
+        let file_size = file.metadata()?.len();
+
+        let mut header = Vec::with_capacity(HEADER_SAMPLE_SIZE as usize);
+        (&mut file)
+            .take(HEADER_SAMPLE_SIZE)
+            .read_to_end(&mut header)?;
+        let mut hasher = sha1::Sha1::from(&header);
+        let mut buf = vec![0; crate::ONE_MB];
+        loop {
+            let read = file.read(&mut buf)?;
+            if read == 0 {
+                break;
+            }
+            hasher.update(&buf[..read]);
+        }
+
+        Ok(Some(Self {
+            akaibu_version: env!("CARGO_PKG_VERSION").to_string(),
+            scheme,
+            key_type,
+            file_name: file_path
+                .file_name()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            file_size,
+            sha1: hasher.digest().to_string(),
+            header_sample: header
+                .iter()
+                .map(|b| format!("{:02X}", b))
+                .collect::<Vec<String>>()
+                .join(" "),
+        }))
+    }
+}
+
+/// Write all collected reports as JSON array
+pub fn write_reports(
+    output_path: &Path,
+    reports: &[MissingKeyReport],
+) -> anyhow::Result<()> {
+    let mut file = File::create(output_path)?;
+    file.write_all(&serde_json::to_vec_pretty(reports)?)?;
+    Ok(())
+}
@@ -1,9 +1,35 @@
 use super::{ResourceScheme, ResourceType};
-use crate::{archive, error::AkaibuError, util::image::bitmap_to_png};
+use crate::{
+    archive::{self, Archive, DirectoryArchive, FileEntry},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::image::bitmap_to_png,
+};
 use anyhow::Context;
 use image::{buffer::ConvertBuffer, ImageBuffer, Pixel};
 use scroll::Pread;
-use std::{fs::File, io::Read, path::Path};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Pixels outside of frame are filled with fill color
+const FLAG_FILL: u32 = 0x8000_0000;
+/// Frame is stored without alpha channel
+const FLAG_24BPP: u32 = 0x4000_0000;
+/// Frame is stored as indices into palette kept in separate file
+const FLAG_INDEXED: u32 = 0x2000_0000;
+/// Palette of indexed frame is file with same name and this extension
+const PALETTE_EXTENSION: &str = "pal";
+/// 256 BGRA colors
+const PALETTE_SIZE: usize = 256 * 4;
+/// AKB+ header ends with name of frame it is drawn over
+const BASE_NAME_RANGE: std::ops::Range<usize> = 32..64;
+/// Limit of base frames chained by AKB+ files
+const MAX_BASE_DEPTH: usize = 8;
+
+type BgraImage = ImageBuffer<image::Bgra<u8>, Vec<u8>>;
 
 #[derive(Debug, Clone)]
 pub(crate) enum AkbScheme {
@@ -23,21 +49,42 @@ struct AkbHeader {
     bottom: u32,
 }
 
+impl ReportHeader for AkbHeader {}
+
+impl AkbHeader {
+    /// Frame with empty region holds no pixel data
+    fn is_empty_frame(&self) -> bool {
+        self.right == self.left || self.bottom == self.top
+    }
+    fn fill_pixel(&self) -> image::Bgra<u8> {
+        if self.compression & FLAG_FILL != 0 {
+            image::Bgra(self.fill.to_le_bytes())
+        } else {
+            image::Bgra([0; 4])
+        }
+    }
+}
+
 impl ResourceScheme for AkbScheme {
     fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
         let mut buf = Vec::with_capacity(1 << 20);
         let mut file = File::open(file_path)?;
         file.read_to_end(&mut buf)?;
-        self.from_bytes(buf)
+        self.from_bytes(buf, file_path, None)
     }
 
     fn convert_from_bytes(
         &self,
-        _file_path: &Path,
+        file_path: &Path,
         buf: Vec<u8>,
-        _archive: Option<&Box<dyn archive::Archive>>,
+        archive: Option<&Box<dyn archive::Archive>>,
     ) -> anyhow::Result<ResourceType> {
-        self.from_bytes(buf)
+        self.from_bytes(buf, file_path, archive)
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let buf = std::fs::read(file_path)?;
+        Ok(vec![buf.pread::<AkbHeader>(0)?.report()])
     }
 
     fn get_name(&self) -> String {
@@ -58,7 +105,27 @@ impl ResourceScheme for AkbScheme {
 }
 
 impl AkbScheme {
-    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+    #[allow(clippy::borrowed_box)]
+    fn from_bytes(
+        &self,
+        buf: Vec<u8>,
+        file_path: &Path,
+        archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        let image = Self::decode(&buf, file_path, archive, 0)?;
+        Ok(ResourceType::RgbaImage {
+            image: image.convert(),
+        })
+    }
+    /// AKB+ frames only store region that changed, rest of image is taken
+    /// from base frame they name
+    #[allow(clippy::borrowed_box)]
+    fn decode(
+        buf: &[u8],
+        file_path: &Path,
+        archive: Option<&Box<dyn archive::Archive>>,
+        depth: usize,
+    ) -> anyhow::Result<BgraImage> {
         let header = buf.pread::<AkbHeader>(0)?;
         let data_offset = match &header.magic {
             b"AKB " => 32,
@@ -71,26 +138,135 @@ impl AkbScheme {
                 .into())
             }
         };
-        let pixels = Self::transform(
-            bitmap_to_png(
-                Self::decompress(&buf[data_offset..], &header),
-                header.width as usize * 4,
-            ),
-            &header,
-            header.left as usize * 4
-                + header.top as usize * 4 * header.width as usize,
-        );
-        let mut image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
-            ImageBuffer::from_vec(
+        if header.left > header.right
+            || header.top > header.bottom
+            || header.right > header.width as u32
+            || header.bottom > header.height as u32
+        {
+            return Err(AkaibuError::Custom(format!(
+                "Invalid AKB frame {:?}",
+                header
+            ))
+            .into());
+        }
+        let mut image = if header.is_empty_frame() {
+            // Fill color only, nothing to decompress
+            ImageBuffer::from_pixel(
+                header.width as u32,
+                header.height as u32,
+                header.fill_pixel(),
+            )
+        } else {
+            let data = buf.get(data_offset..).context("Missing AKB data")?;
+            let pixels = if header.compression & FLAG_INDEXED != 0 {
+                let palette = extract_sibling(
+                    file_path,
+                    &file_path.with_extension(PALETTE_EXTENSION),
+                    archive,
+                )?;
+                Self::indexed_pixels(data, &palette, &header)?
+            } else {
+                Self::transform(
+                    bitmap_to_png(
+                        Self::decompress(data, &header),
+                        header.width as usize * 4,
+                    ),
+                    &header,
+                    header.left as usize * 4
+                        + header.top as usize * 4 * header.width as usize,
+                )
+            };
+            let mut image: BgraImage = ImageBuffer::from_vec(
                 header.width as u32,
                 header.height as u32,
                 pixels,
             )
             .context("Invalid image resolution")?;
-        Self::apply_filters(&mut image, &header);
-        Ok(ResourceType::RgbaImage {
-            image: image.convert(),
-        })
+            Self::apply_filters(&mut image, &header);
+            image
+        };
+        if let Some(base_name) = buf
+            .get(BASE_NAME_RANGE)
+            .filter(|_| data_offset == 64)
+            .and_then(|name| name.split(|b| *b == 0).next())
+            .filter(|name| !name.is_empty())
+        {
+            if depth == MAX_BASE_DEPTH {
+                return Err(AkaibuError::Custom(
+                    "Too many AKB base frames".to_string(),
+                )
+                .into());
+            }
+            let base_path =
+                file_path.with_file_name(&*String::from_utf8_lossy(base_name));
+            let base = Self::decode(
+                &extract_sibling(file_path, &base_path, archive)?,
+                &base_path,
+                archive,
+                depth + 1,
+            )?;
+            Self::draw_over_base(&mut image, &base, &header)?;
+        }
+        Ok(image)
+    }
+    /// Pixels outside of frame region are copied from base frame
+    fn draw_over_base(
+        image: &mut BgraImage,
+        base: &BgraImage,
+        akb: &AkbHeader,
+    ) -> anyhow::Result<()> {
+        if image.dimensions() != base.dimensions() {
+            return Err(AkaibuError::Custom(format!(
+                "AKB base frame size {:?} does not match {:?}",
+                base.dimensions(),
+                image.dimensions()
+            ))
+            .into());
+        }
+        for (x, y, pixel) in image.enumerate_pixels_mut() {
+            if !(akb.left..akb.right).contains(&x)
+                || !(akb.top..akb.bottom).contains(&y)
+            {
+                *pixel = *base.get_pixel(x, y);
+            }
+        }
+        Ok(())
+    }
+    /// Frame region holds one byte palette index per pixel, compressed the
+    /// same way as 32bpp frames and without line deltas
+    fn indexed_pixels(
+        buf: &[u8],
+        palette: &[u8],
+        akb: &AkbHeader,
+    ) -> anyhow::Result<Vec<u8>> {
+        if palette.len() < PALETTE_SIZE {
+            return Err(AkaibuError::Custom(format!(
+                "AKB palette too short: {} bytes",
+                palette.len()
+            ))
+            .into());
+        }
+        let width = akb.width as usize;
+        let region_width = (akb.right - akb.left) as usize;
+        let indices = bitmap_to_png(
+            Self::decompress3(
+                buf,
+                width * akb.height as usize,
+                region_width,
+                width - region_width,
+                akb.left as usize
+                    + (akb.height as usize - akb.bottom as usize) * width,
+            ),
+            width,
+        );
+        Ok(indices
+            .iter()
+            .flat_map(|index| {
+                palette[*index as usize * 4..*index as usize * 4 + 4]
+                    .iter()
+                    .copied()
+            })
+            .collect())
     }
     fn decompress(buf: &[u8], akb: &AkbHeader) -> Vec<u8> {
         let dest_len = akb.width as usize * akb.height as usize * 4;
@@ -101,7 +277,7 @@ impl AkbScheme {
             + (akb.height as usize - akb.bottom as usize)
                 * 4
                 * akb.width as usize;
-        if akb.compression & 0x40_00_00_00 == 0 {
+        if akb.compression & FLAG_24BPP == 0 {
             Self::decompress3(buf, dest_len, w_in, w_out, write_index)
         } else {
             Self::decompress2(buf, dest_len, w_in, w_out, write_index)
@@ -318,31 +494,55 @@ impl AkbScheme {
         image: &mut ImageBuffer<image::Bgra<u8>, Vec<u8>>,
         akb: &AkbHeader,
     ) {
-        if akb.compression & 0x40000000 != 0 {
-            let new_alpha = akb.compression as u8;
+        if akb.compression & FLAG_24BPP != 0 {
             for pixel in image.pixels_mut() {
-                pixel.apply_with_alpha(|c| c, |_| new_alpha);
+                pixel.apply_with_alpha(|c| c, |_| 0xFF);
             }
         }
-        if akb.compression & 0x80000000 != 0 {
-            let fill = akb.fill;
-            let b = fill as u8;
-            let g = (fill >> 8) as u8;
-            let r = (fill >> 16) as u8;
-            let a = (fill >> 24) as u8;
-            let height_range = akb.top..akb.bottom - 1;
+        if akb.compression & FLAG_FILL != 0 {
+            let fill = akb.fill_pixel();
+            let height_range = akb.top..akb.bottom;
             let width_range = akb.left..akb.right;
 
             for (width, height, pixel) in image.enumerate_pixels_mut() {
                 if !height_range.contains(&height)
                     || !width_range.contains(&width)
                 {
-                    pixel[0] = b;
-                    pixel[1] = g;
-                    pixel[2] = r;
-                    pixel[3] = a;
+                    *pixel = fill;
                 }
             }
         }
     }
 }
+
+/// Extract file referenced by image, from archive image came from or from
+/// folder of loose file
+#[allow(clippy::borrowed_box)]
+fn extract_sibling(
+    file_path: &Path,
+    full_path: &Path,
+    archive: Option<&Box<dyn archive::Archive>>,
+) -> anyhow::Result<Vec<u8>> {
+    let file_name = full_path
+        .file_name()
+        .context("Could not get file name")?
+        .to_string_lossy()
+        .to_string();
+    let contents = match archive {
+        Some(archive) => archive.extract(&FileEntry {
+            file_name,
+            full_path: full_path.to_path_buf(),
+            file_offset: 0,
+            file_size: 0,
+        })?,
+        None => {
+            DirectoryArchive::containing(file_path)?.extract(&FileEntry {
+                full_path: PathBuf::from(&file_name),
+                file_name,
+                file_offset: 0,
+                file_size: 0,
+            })?
+        }
+    };
+    Ok(contents.contents.to_vec())
+}
@@ -2,6 +2,7 @@ use super::{ResourceScheme, ResourceType};
 use crate::{
     archive,
     error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
     util::simd::{packuswb0, punpcklbw0},
 };
 use anyhow::Context;
@@ -29,6 +30,8 @@ struct BgHeader {
     unk4: u32,
 }
 
+impl ReportHeader for BgHeader {}
+
 impl ResourceScheme for BgScheme {
     fn convert(
         &self,
@@ -49,6 +52,11 @@ impl ResourceScheme for BgScheme {
         self.from_bytes(buf, file_path)
     }
 
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let buf = std::fs::read(file_path)?;
+        Ok(vec![buf.pread::<BgHeader>(0)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[CompressedBg] {}",
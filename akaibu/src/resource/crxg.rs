@@ -3,15 +3,20 @@ use crate::{
     archive,
     error::AkaibuError,
     util::{
+        bounds,
         image::{resolve_color_table, resolve_color_table_without_alpha},
         zlib_decompress,
     },
 };
 use anyhow::Context;
 use image::{buffer::ConvertBuffer, ImageBuffer};
-use scroll::Pread;
+use scroll::{Pread, LE};
 use std::{fs::File, io::Read, path::Path};
 
+const CRXG_MAGIC: &[u8] = b"CRXG";
+const CRXB_MAGIC: &[u8] = b"CRXB";
+const CRXJ_MAGIC: &[u8] = b"CRXJ";
+
 #[derive(Debug, Clone)]
 pub(crate) enum CrxgScheme {
     Universal,
@@ -68,8 +73,59 @@ impl CrxgScheme {
     fn from_bytes(
         &self,
         buf: Vec<u8>,
-        _file_path: &Path,
+        file_path: &Path,
+    ) -> anyhow::Result<ResourceType> {
+        match buf.get(..4) {
+            Some(CRXB_MAGIC) | Some(CRXJ_MAGIC) => {
+                self.animation(&buf, file_path)
+            }
+            _ => self.cell(buf),
+        }
+    }
+    /// Animation containers store offset table of CRXG cells
+    fn animation(
+        &self,
+        buf: &[u8],
+        file_path: &Path,
     ) -> anyhow::Result<ResourceType> {
+        let off = &mut 4;
+        let frame_count = buf.gread_with::<u32>(off, LE)? as usize;
+        // Checked before allocating, count comes from file
+        if frame_count > (buf.len() - *off) / 4 {
+            return Err(bounds::malformed("CRXG frame count").into());
+        }
+        let mut frame_offsets = Vec::with_capacity(frame_count);
+        for _ in 0..frame_count {
+            frame_offsets.push(buf.gread_with::<u32>(off, LE)? as usize);
+        }
+        let frames = frame_offsets
+            .iter()
+            .enumerate()
+            .map(|(i, frame_offset)| {
+                let frame_end =
+                    frame_offsets.get(i + 1).copied().unwrap_or(buf.len());
+                let cell = buf
+                    .get(*frame_offset..frame_end)
+                    .context("Out of bounds access")?;
+                if cell.get(..4) != Some(CRXG_MAGIC) {
+                    return Err(AkaibuError::Custom(format!(
+                        "Frame {} of {:?} is not CRXG cell",
+                        i, file_path
+                    ))
+                    .into());
+                }
+                match self.cell(cell.to_vec())? {
+                    ResourceType::RgbaImage { image } => Ok(image),
+                    _ => Err(AkaibuError::Custom(
+                        "Invalid CRXG cell".to_string(),
+                    )
+                    .into()),
+                }
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        Ok(ResourceType::Animation { frames })
+    }
+    fn cell(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
         let off = &mut 0;
         let header = buf.gread::<CrxgHeader>(off)?;
         let color_table = if header.has_alpha == 0x102 {
@@ -0,0 +1,113 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{archive::Archive, error::AkaibuError};
+use anyhow::Context;
+use scroll::{Pread, LE};
+use std::path::Path;
+
+/// Entis files start with `Entis\x1a\x00\x00`
+const MAGIC: &[u8] = b"Entis\x1a\x00\x00";
+/// Format description following file id, used to tell scripts from images
+const DESCRIPTION_OFFSET: usize = 0x10;
+const DESCRIPTION: &[u8] = b"Cotopha Image file";
+/// Sections follow fixed size file header
+const HEADER_SIZE: usize = 0x40;
+const CONST_STRING_SECTION: &[u8] = b"conststr";
+
+/// Cotopha (Entis) compiled scripts.
+/// Only constant string table is extracted, one string per line.
+#[derive(Debug, Clone)]
+pub(crate) enum CsxScheme {
+    Universal,
+}
+
+impl ResourceScheme for CsxScheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let buf = std::fs::read(file_path)?;
+        self.from_bytes(buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(buf)
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[CSX] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl CsxScheme {
+    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+        if !buf.starts_with(MAGIC)
+            || buf
+                .get(DESCRIPTION_OFFSET..)
+                .map_or(true, |desc| !desc.starts_with(DESCRIPTION))
+        {
+            return Err(AkaibuError::Custom(
+                "Not a Cotopha script".to_string(),
+            )
+            .into());
+        }
+        let section = find_section(&buf, CONST_STRING_SECTION)?;
+        let strings = read_const_strings(section)?;
+        Ok(ResourceType::Text(strings.join("\n")))
+    }
+}
+
+/// Sections are stored as 8 byte id, u64 size and data
+fn find_section<'a>(buf: &'a [u8], id: &[u8]) -> anyhow::Result<&'a [u8]> {
+    let mut off = HEADER_SIZE;
+    while off < buf.len() {
+        let section_id =
+            buf.get(off..off + 8).context("Out of bounds access")?;
+        let size = buf.pread_with::<u64>(off + 8, LE)? as usize;
+        off += 16;
+        let data = off
+            .checked_add(size)
+            .and_then(|end| buf.get(off..end))
+            .context("Section out of bounds")?;
+        if section_id == id {
+            return Ok(data);
+        }
+        off += size;
+    }
+    Err(AkaibuError::Custom(format!(
+        "Missing {} section",
+        String::from_utf8_lossy(id)
+    ))
+    .into())
+}
+
+/// Each string is u32 length in UTF-16 code units with UTF-16LE data,
+/// followed by list of code offsets referencing it
+fn read_const_strings(section: &[u8]) -> anyhow::Result<Vec<String>> {
+    let off = &mut 0;
+    let count = section.gread_with::<u32>(off, LE)?;
+    (0..count)
+        .map(|_| {
+            let length = section.gread_with::<u32>(off, LE)? as usize;
+            let units = (0..length)
+                .map(|_| section.gread_with::<u16>(off, LE))
+                .collect::<Result<Vec<u16>, _>>()?;
+            let reference_count = section.gread_with::<u32>(off, LE)? as usize;
+            *off += reference_count * 4;
+            Ok(String::from_utf16_lossy(&units))
+        })
+        .collect()
+}
@@ -1,4 +1,4 @@
-use crate::archive;
+use crate::{archive, util::bounds};
 
 use super::{ResourceScheme, ResourceType};
 use image::{ImageBuffer, RgbaImage};
@@ -19,6 +19,8 @@ struct DpngHeader {
     height: u32,
 }
 
+const ENTRY_SIZE: usize = 28;
+
 #[derive(Debug, Pread)]
 struct DpngEntry {
     left_offset: u32,
@@ -75,21 +77,53 @@ impl DpngScheme {
     ) -> anyhow::Result<ResourceType> {
         let off = &mut 0;
         let header = buf.gread_with::<DpngHeader>(off, LE)?;
+        // Checked before allocating, count comes from file
+        if header.entry_count as usize > (buf.len() - *off) / ENTRY_SIZE {
+            return Err(bounds::malformed("DPNG entry count").into());
+        }
         let mut entries = Vec::with_capacity(header.entry_count as usize);
         for _ in 0..header.entry_count {
             let entry = buf.gread_with::<DpngEntry>(off, LE)?;
             if entry.data_size > 0 {
+                let data = buf
+                    .get(*off..*off + entry.data_size as usize)
+                    .ok_or_else(|| bounds::malformed("DPNG entry size"))?;
                 let image = image::load_from_memory_with_format(
-                    &buf[*off..*off + entry.data_size as usize],
+                    data,
                     image::ImageFormat::Png,
                 )?
                 .to_rgba8();
+                let fits = |offset: u32, size: u32, image_size: u32, max| {
+                    size <= image_size
+                        && offset
+                            .checked_add(size)
+                            .map_or(false, |end| end <= max)
+                };
+                if !fits(
+                    entry.left_offset,
+                    entry.width,
+                    image.width(),
+                    header.width,
+                ) || !fits(
+                    entry.top_offset,
+                    entry.height,
+                    image.height(),
+                    header.height,
+                ) {
+                    return Err(
+                        bounds::malformed("DPNG entry placement").into()
+                    );
+                }
                 *off += entry.data_size as usize;
                 entries.push((entry, image));
             }
         }
+        let pixels = bounds::output_buffer(
+            header.width as usize * header.height as usize * 4,
+        )?;
         let mut combined_image: RgbaImage =
-            ImageBuffer::new(header.width, header.height);
+            ImageBuffer::from_raw(header.width, header.height, pixels)
+                .ok_or_else(|| bounds::malformed("DPNG image size"))?;
 
         for (entry, image) in entries {
             for x in 0..entry.width {
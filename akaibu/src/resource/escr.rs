@@ -0,0 +1,106 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{
+    archive::Archive,
+    error::AkaibuError,
+    util::{bounds, text},
+};
+use encoding_rs::SHIFT_JIS;
+use scroll::{Pread, LE};
+use std::path::Path;
+
+/// `ESCR1_00`
+const MAGIC: &[u8] = b"ESCR1_00";
+
+/// Escu:de compiled scripts, usually `.bin` entries of ESC-ARC2 archives.
+/// Header holds offsets of strings into string table stored after bytecode,
+/// bytecode refers to strings by their index. Strings are zero terminated
+/// SHIFT-JIS, they are written one per line in index order with line breaks
+/// inside them escaped as `\n`.
+#[derive(Debug, Clone)]
+pub(crate) enum EscrScheme {
+    Universal,
+}
+
+impl ResourceScheme for EscrScheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let buf = std::fs::read(file_path)?;
+        self.from_bytes(&buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(&buf)
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[ESCR] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl EscrScheme {
+    fn from_bytes(&self, buf: &[u8]) -> anyhow::Result<ResourceType> {
+        if !buf.starts_with(MAGIC) {
+            return Err(AkaibuError::Custom(
+                "Not a ESCR1_00 script".to_string(),
+            )
+            .into());
+        }
+        let off = &mut MAGIC.len();
+        let string_count = buf.gread_with::<u32>(off, LE)? as usize;
+        // Checked before allocating, count comes from file
+        if string_count > (buf.len() - *off) / 4 {
+            return Err(bounds::malformed("ESCR string count").into());
+        }
+        let offsets = (0..string_count)
+            .map(|_| buf.gread_with::<u32>(off, LE).map(|o| o as usize))
+            .collect::<Result<Vec<usize>, _>>()?;
+        let code_size = buf.gread_with::<u32>(off, LE)? as usize;
+        *off = off
+            .checked_add(code_size)
+            .filter(|end| *end <= buf.len())
+            .ok_or_else(|| bounds::malformed("ESCR bytecode size"))?;
+        let strings_size = buf.gread_with::<u32>(off, LE)? as usize;
+        let strings = off
+            .checked_add(strings_size)
+            .and_then(|end| buf.get(*off..end))
+            .ok_or_else(|| bounds::malformed("ESCR string table size"))?;
+        let lines = offsets
+            .into_iter()
+            .map(|offset| {
+                let string = strings
+                    .get(offset..)
+                    .ok_or_else(|| bounds::malformed("ESCR string offset"))?;
+                let end =
+                    string.iter().position(|b| *b == 0).unwrap_or(string.len());
+                Ok(decode_string(&string[..end]))
+            })
+            .collect::<Result<Vec<String>, AkaibuError>>()?;
+        Ok(ResourceType::Text(lines.join("\n")))
+    }
+}
+
+/// Undecodable bytes are replaced instead of failing whole script, so one
+/// broken string does not hide the rest
+fn decode_string(string: &[u8]) -> String {
+    let (decoded, _, had_errors) = SHIFT_JIS.decode(string);
+    if had_errors {
+        log::debug!("ESCR string is not valid SHIFT-JIS: {:X?}", string);
+    }
+    text::normalize_newlines(&decoded).replace('\n', "\\n")
+}
@@ -58,10 +58,11 @@ impl G00Scheme {
             0 => Self::version0(&buf[5..], header),
             1 => Self::version1(&buf[5..], header),
             2 => Self::version2(&buf[5..]),
-            _ => Err(AkaibuError::Custom(format!(
-                "Not supported version {}",
-                header.version
-            ))
+            _ => Err(AkaibuError::UnsupportedVersion {
+                scheme: "G00".to_string(),
+                found: header.version.to_string(),
+                expected: "0, 1, 2".to_string(),
+            }
             .into()),
         }
     }
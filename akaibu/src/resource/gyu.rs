@@ -2,24 +2,18 @@ use super::{ResourceScheme, ResourceType};
 use crate::{
     archive,
     error::AkaibuError,
-    util::{image::bitmap_to_png_with_padding, mt::Mt19937},
+    inspect::{ParsedHeader, ReportHeader},
+    scheme::registry::SchemeRegistry,
+    util::{bounds, image::bitmap_to_png_with_padding, mt::Mt19937},
 };
 use anyhow::Context;
 use image::{buffer::ConvertBuffer, ImageBuffer};
-use once_cell::sync::Lazy;
 use scroll::{Pread, BE, LE};
-use std::{collections::HashMap, fs::File, io::Read, path::Path};
-
-const SEEDS_PATH: &str = "gyu/seeds.json";
+use std::{
+    collections::HashMap, convert::TryFrom, fs::File, io::Read, path::Path,
+};
 
-static SEEDS_TABLE: Lazy<HashMap<String, Vec<u32>>> = Lazy::new(|| {
-    let seeds_table: HashMap<String, Vec<u32>> = serde_json::from_slice(
-        &crate::Resources::get(SEEDS_PATH)
-            .expect("Could not find file: gyu/seeds.json"),
-    )
-    .expect("Could not deserialize resource json");
-    seeds_table
-});
+pub(crate) const SEEDS_PATH: &str = "gyu/seeds.json";
 
 #[derive(Debug, Pread)]
 struct GyuHeader {
@@ -34,6 +28,8 @@ struct GyuHeader {
     color_table_size: u32,
 }
 
+impl ReportHeader for GyuHeader {}
+
 #[derive(Debug, Clone)]
 pub(crate) enum GyuScheme {
     DemonBusters,
@@ -73,6 +69,11 @@ impl ResourceScheme for GyuScheme {
     ) -> anyhow::Result<ResourceType> {
         self.from_bytes(buf, file_path)
     }
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let buf = std::fs::read(file_path)?;
+        Ok(vec![buf.pread::<GyuHeader>(0)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!("[GYU] {}",match self {
             Self::DemonBusters => "Demon Busters ~Ecchi na Ecchi na Demon Taiji~",
@@ -142,41 +143,45 @@ impl GyuScheme {
                 .to_str()
                 .context("Invalid string")?
                 .parse()?;
-            header.mt_seed = *seeds.get(id).context("Could not get mt_seed")?;
+            header.mt_seed =
+                *seeds.get(id).ok_or_else(|| self.missing_key("mt_seed"))?;
         }
-        let padded_width =
-            ((header.bpp / 8 * header.width + 3) & 0xFF_FF_FF_FC) as usize;
+        let width = header.width as usize;
+        let height = header.height as usize;
+        let row_size = (header.bpp as usize / 8)
+            .checked_mul(width)
+            .ok_or_else(|| bounds::malformed("image width too large"))?;
+        let padded_width = (row_size + 3) & !3;
+        let alpha_width = (width + 3) & !3;
         let data_offset = 36 + (header.color_table_size as usize * 4);
+        let data_end = data_offset + header.data_size as usize;
+        let alpha_end = data_end + header.alpha_channel_size as usize;
+        if alpha_end > buf.len() {
+            return Err(bounds::malformed("GYU data out of bounds").into());
+        }
         if (header.version & 0xFFFF_0000) != 0x0100_0000 {
-            decrypt_with_mt(
-                &mut buf[data_offset..data_offset + header.data_size as usize],
-                header.mt_seed,
-            );
+            decrypt_with_mt(&mut buf[data_offset..data_end], header.mt_seed);
         }
         let color_table = &buf[36..data_offset];
         let mut data = bitmap_to_png_with_padding(
             decompress(
-                &buf[data_offset..data_offset + header.data_size as usize],
-                padded_width * header.height as usize,
+                &buf[data_offset..data_end],
+                output_size(padded_width, height)?,
                 header.version,
             )?,
             padded_width,
-            padded_width - (header.bpp / 8 * header.width) as usize,
+            padded_width - row_size,
         );
         let alpha_channel = bitmap_to_png_with_padding(
             decompress0(
-                &buf[data_offset + header.data_size as usize
-                    ..data_offset
-                        + header.data_size as usize
-                        + header.alpha_channel_size as usize],
-                ((header.width + 3) & 0xFF_FF_FF_FC) as usize
-                    * header.height as usize,
-            ),
-            ((header.width + 3) & 0xFF_FF_FF_FC) as usize,
-            (((header.width + 3) & 0xFF_FF_FF_FC) - header.width) as usize,
+                &buf[data_end..alpha_end],
+                output_size(alpha_width, height)?,
+            )?,
+            alpha_width,
+            alpha_width - width,
         );
         if header.bpp == 8 && header.color_table_size != 0 {
-            data = resolve_color_table(&data, &color_table);
+            data = resolve_color_table(&data, &color_table)?;
         } else if header.bpp == 24 {
             data = add_alpha_channel(data);
         }
@@ -192,10 +197,18 @@ impl GyuScheme {
             image: image.convert(),
         })
     }
-    fn get_seeds(&self) -> anyhow::Result<&Vec<u32>> {
-        SEEDS_TABLE
-            .get(self.get_key())
-            .context(format!("Unsupported game key {}", self.get_key()))
+    fn get_seeds(&self) -> anyhow::Result<Vec<u32>> {
+        SchemeRegistry::global()
+            .load::<HashMap<String, Vec<u32>>>(SEEDS_PATH)?
+            .remove(self.get_key())
+            .ok_or_else(|| self.missing_key("mt_seed table"))
+    }
+    fn missing_key(&self, key_type: &str) -> anyhow::Error {
+        AkaibuError::MissingKey {
+            scheme: self.get_name(),
+            key_type: key_type.to_string(),
+        }
+        .into()
     }
     fn get_key(&self) -> &str {
         match self {
@@ -230,21 +243,33 @@ fn decompress(
 ) -> anyhow::Result<Vec<u8>> {
     let version = version & 0xFFFF_0000;
     Ok(match version {
-        0x0800_0000 => decompress3(&src[4..], dest_len)?,
-        0x0400_0000 | 0x0200_0000 => decompress0(src, dest_len),
+        0x0800_0000 => decompress3(
+            src.get(4..)
+                .ok_or_else(|| bounds::malformed("missing data"))?,
+            dest_len,
+        )?,
+        0x0400_0000 | 0x0200_0000 => decompress0(src, dest_len)?,
         0x0100_0000 => Vec::from(src),
         _ => {
-            return Err(AkaibuError::Custom(format!(
-                "Version not supported {}",
-                version
-            ))
+            return Err(AkaibuError::UnsupportedVersion {
+                scheme: "GYU".to_string(),
+                found: format!("{:#X}", version >> 24),
+                expected: "0x1, 0x2, 0x4, 0x8".to_string(),
+            }
             .into())
         }
     })
 }
 
+/// Size of decompressed buffer from image dimensions
+fn output_size(width: usize, height: usize) -> Result<usize, AkaibuError> {
+    width
+        .checked_mul(height)
+        .ok_or_else(|| bounds::malformed("image size overflow"))
+}
+
 fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
-    let mut dest = vec![0u8; dest_len];
+    let mut dest = bounds::output_buffer(dest_len)?;
     let mut src_index = 0;
     let mut di = 0;
     let mut c;
@@ -254,8 +279,7 @@ fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
 
     loop {
         if read_first {
-            *dest.get_mut(di).context("Out of bounds write")? =
-                *src.get(src_index).context("Out of bounds read")?;
+            bounds::write(&mut dest, di, bounds::read(src, src_index)?)?;
             di += 1;
             src_index += 1;
             read_first = false
@@ -263,7 +287,7 @@ fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
 
         d -= 1;
         if d == 0 {
-            dl = *src.get(src_index).context("Out of bounds read")?;
+            dl = bounds::read(src, src_index)?;
             src_index += 1;
             d = 8;
         }
@@ -278,7 +302,7 @@ fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
         let mut a = [0xFF; 4];
         d -= 1;
         if d == 0 {
-            dl = *src.get(src_index).context("Out of bounds read")?;
+            dl = bounds::read(src, src_index)?;
             src_index += 1;
             d = 8;
         }
@@ -296,7 +320,7 @@ fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             temp_a |= 0xFF_FF_E0_00;
             a = temp_a.to_le_bytes();
             if c == 0 {
-                let cl = src[src_index];
+                let cl = bounds::read(src, src_index)?;
                 c |= cl as u16;
                 src_index += 1;
                 if cl == 0 {
@@ -309,7 +333,7 @@ fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             c = 0;
             d -= 1;
             if d == 0 {
-                dl = *src.get(src_index).context("Out of bounds read")?;
+                dl = bounds::read(src, src_index)?;
                 src_index += 1;
                 d = 8;
             }
@@ -321,7 +345,7 @@ fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             }
             d -= 1;
             if d == 0 {
-                dl = *src.get(src_index).context("Out of bounds read")?;
+                dl = bounds::read(src, src_index)?;
                 src_index += 1;
                 d = 8;
             }
@@ -331,28 +355,29 @@ fn decompress3(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             if overflow {
                 c += 1;
             }
-            a[0] = *src.get(src_index).context("Out of bounds read")?;
+            a[0] = bounds::read(src, src_index)?;
             src_index += 1;
             c += 1;
         }
 
-        let mut si = di as i32;
-        si += a.pread_with::<i32>(0, LE)?;
+        let offset = a.pread_with::<i32>(0, LE)?;
+        let mut si = usize::try_from(di as i64 + offset as i64)
+            .map_err(|_| bounds::malformed("back reference before start"))?;
         c += 1;
         for _ in 0..c {
-            *dest.get_mut(di).context("Out of bounds write")? =
-                dest[si as usize];
+            let value = bounds::read(&dest, si)?;
+            bounds::write(&mut dest, di, value)?;
             di += 1;
             si += 1;
         }
     }
 }
 
-fn decompress0(buf: &[u8], dest_len: usize) -> Vec<u8> {
+fn decompress0(buf: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
     if buf.is_empty() {
-        return vec![];
+        return Ok(vec![]);
     }
-    let mut dest = vec![0u8; dest_len];
+    let mut dest = bounds::output_buffer(dest_len)?;
     let mut lookup_table = vec![0u8; 4096];
 
     let mut x = 0_u16;
@@ -362,14 +387,14 @@ fn decompress0(buf: &[u8], dest_len: usize) -> Vec<u8> {
     while bytes_read < buf.len() {
         x >>= 1;
         if (x & 0x100) == 0 {
-            x = buf[bytes_read] as u16;
+            x = bounds::read(buf, bytes_read)? as u16;
             bytes_read += 1;
             x |= 0xFF00;
         }
         if ((x & 0xFF) & 1) == 0 {
-            let bl = buf[bytes_read];
+            let bl = bounds::read(buf, bytes_read)?;
             bytes_read += 1;
-            let cl = buf[bytes_read];
+            let cl = bounds::read(buf, bytes_read)?;
             bytes_read += 1;
             let mut s = cl as u16;
             let mut d = s as u16;
@@ -388,7 +413,7 @@ fn decompress0(buf: &[u8], dest_len: usize) -> Vec<u8> {
                     s += 1;
                     c &= 0xFFF;
                     d = lookup_table[c as usize] as u16;
-                    dest[bytes_written] = d as u8;
+                    bounds::write(&mut dest, bytes_written, d as u8)?;
                     c = lookup_index;
                     bytes_written += 1;
                     lookup_index += 1;
@@ -399,9 +424,9 @@ fn decompress0(buf: &[u8], dest_len: usize) -> Vec<u8> {
                 }
             }
         } else {
-            let d = buf[bytes_read];
+            let d = bounds::read(buf, bytes_read)?;
             bytes_read += 1;
-            dest[bytes_written] = d;
+            bounds::write(&mut dest, bytes_written, d)?;
             bytes_written += 1;
             let c = lookup_index;
             lookup_index += 1;
@@ -409,11 +434,11 @@ fn decompress0(buf: &[u8], dest_len: usize) -> Vec<u8> {
             lookup_table[c as usize] = d;
         }
     }
-    dest
+    Ok(dest)
 }
 
 fn decrypt_with_mt(buf: &mut [u8], mt_seed: u32) {
-    if mt_seed != 0xFFFF_FFFF {
+    if mt_seed != 0xFFFF_FFFF && !buf.is_empty() {
         let mut mt = Mt19937::default();
         mt.seed_gyu(mt_seed);
         for _ in 0..10 {
@@ -424,13 +449,18 @@ fn decrypt_with_mt(buf: &mut [u8], mt_seed: u32) {
     }
 }
 
-fn resolve_color_table(buf: &[u8], color_table: &[u8]) -> Vec<u8> {
+fn resolve_color_table(
+    buf: &[u8],
+    color_table: &[u8],
+) -> Result<Vec<u8>, AkaibuError> {
     buf.iter()
-        .fold(Vec::with_capacity(buf.len() * 4), |mut v, b| {
+        .try_fold(Vec::with_capacity(buf.len() * 4), |mut v, b| {
             v.extend_from_slice(
-                &color_table[*b as usize * 4..*b as usize * 4 + 4],
+                color_table
+                    .get(*b as usize * 4..*b as usize * 4 + 4)
+                    .ok_or_else(|| bounds::malformed("color out of table"))?,
             );
-            v
+            Ok(v)
         })
 }
 
@@ -448,8 +478,8 @@ fn add_alpha_channel(buf: Vec<u8>) -> Vec<u8> {
 fn resolve_alpha_channel(buf: &mut [u8], alpha_channel: &[u8]) {
     if !alpha_channel.is_empty() {
         buf.chunks_exact_mut(4)
-            .enumerate()
-            .for_each(|(i, c)| c[3] = alpha_channel[i]);
+            .zip(alpha_channel)
+            .for_each(|(c, alpha)| c[3] = *alpha);
     } else {
         buf.chunks_exact_mut(4).for_each(|c| c[3] = 0xFF);
     }
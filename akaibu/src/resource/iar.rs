@@ -1,10 +1,33 @@
-use crate::{archive, error::AkaibuError, util::image::remove_bitmap_padding};
+use crate::{
+    archive::{self, Archive, DirectoryArchive, FileEntry},
+    error::AkaibuError,
+    util::{bounds, image::remove_bitmap_padding},
+};
 
 use super::{ResourceScheme, ResourceType};
 use anyhow::Context;
-use image::{buffer::ConvertBuffer, GrayImage, ImageBuffer};
-use scroll::Pread;
-use std::{fs::File, io::Read, path::Path};
+use image::{buffer::ConvertBuffer, GrayImage, ImageBuffer, Rgba, RgbaImage};
+use scroll::{Pread, LE};
+use std::{
+    fs::File,
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+/// Image data follows fixed size header
+const HEADER_SIZE: usize = 72;
+/// Bits of version telling pixel format
+const FORMAT_MASK: u32 = 0x3F;
+/// Entry is diff frame drawn over image it is based on
+const LAYER: u32 = 0x800;
+/// Version 3 layer pixels are alpha blended over base instead of
+/// replacing it
+const ALPHA_BLEND: u32 = 0x2000;
+/// Version 3 layer rows are stored from bottom to top
+const BOTTOM_UP: u32 = 0x4000;
+/// Layers can be based on other layers, longer chains are treated as
+/// malformed to not loop forever
+const MAX_LAYER_DEPTH: usize = 8;
 
 #[derive(Debug, Clone)]
 pub(crate) enum IarScheme {
@@ -19,8 +42,10 @@ struct IarHeader {
     unk1: u32,
     file_size: u32,
     unk2: u32,
-    unk3: u32,
-    unk4: u32,
+    /// Position of image origin, layers are placed by difference between
+    /// their origin and origin of their base
+    origin_x: i32,
+    origin_y: i32,
     width: u32,
     height: u32,
     unknown: [u8; 32],
@@ -31,16 +56,16 @@ impl ResourceScheme for IarScheme {
         let mut buf = Vec::with_capacity(1 << 20);
         let mut file = File::open(file_path)?;
         file.read_to_end(&mut buf)?;
-        self.from_bytes(buf)
+        self.from_bytes(file_path, buf, None)
     }
 
     fn convert_from_bytes(
         &self,
-        _file_path: &Path,
+        file_path: &Path,
         buf: Vec<u8>,
-        _archive: Option<&Box<dyn archive::Archive>>,
+        archive: Option<&Box<dyn archive::Archive>>,
     ) -> anyhow::Result<ResourceType> {
-        self.from_bytes(buf)
+        self.from_bytes(file_path, buf, archive)
     }
 
     fn get_name(&self) -> String {
@@ -61,62 +86,205 @@ impl ResourceScheme for IarScheme {
 }
 
 impl IarScheme {
-    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
-        let header = buf.pread::<IarHeader>(0)?;
-        let data = if header.version >> 24 == 1 {
-            decompress(&buf[72..], header.decompressed_file_size as usize)?
-        } else {
-            buf[72..].to_vec()
-        };
-        match header.version & 0xFFFF {
-            0x3C => {
-                let image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
-                    ImageBuffer::from_vec(
-                        header.width as u32,
-                        header.height as u32,
-                        data,
-                    )
-                    .context("Invalid image resolution")?;
-                Ok(ResourceType::RgbaImage {
-                    image: image.convert(),
-                })
-            }
-            0x1C => {
-                let data = remove_bitmap_padding(
+    #[allow(clippy::borrowed_box)]
+    fn from_bytes(
+        &self,
+        file_path: &Path,
+        buf: Vec<u8>,
+        archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        Ok(ResourceType::RgbaImage {
+            image: decode(&buf, file_path, archive, 0)?.0,
+        })
+    }
+}
+
+/// Decoded image with origin of its canvas. Layers are drawn over canvas of
+/// their base, so they keep origin of image at the bottom of the chain.
+#[allow(clippy::borrowed_box)]
+fn decode(
+    buf: &[u8],
+    file_path: &Path,
+    archive: Option<&Box<dyn archive::Archive>>,
+    depth: usize,
+) -> anyhow::Result<(RgbaImage, (i32, i32))> {
+    let header = buf.pread::<IarHeader>(0)?;
+    let data = buf.get(HEADER_SIZE..).context("Missing IAR image data")?;
+    let data = if header.version >> 24 == 1 {
+        decompress(data, header.decompressed_file_size as usize)?
+    } else {
+        data.to_vec()
+    };
+    if header.version & LAYER != 0 {
+        decode_layer(&header, &data, file_path, archive, depth)
+    } else {
+        Ok((
+            decode_pixels(&header, data)?,
+            (header.origin_x, header.origin_y),
+        ))
+    }
+}
+
+fn decode_pixels(
+    header: &IarHeader,
+    data: Vec<u8>,
+) -> anyhow::Result<RgbaImage> {
+    match header.version & 0xFFFF {
+        0x3C => {
+            let image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
+                ImageBuffer::from_vec(
+                    header.width as u32,
+                    header.height as u32,
                     data,
-                    header.decompressed_file_size as usize
-                        / header.height as usize,
-                    calculate_padding(header.width),
-                );
-                let image: ImageBuffer<image::Bgr<u8>, Vec<u8>> =
-                    ImageBuffer::from_vec(
-                        header.width as u32,
-                        header.height as u32,
-                        data,
-                    )
-                    .context("Invalid image resolution")?;
-                Ok(ResourceType::RgbaImage {
-                    image: image.convert(),
-                })
-            }
-            0x2 => {
-                let image: GrayImage = ImageBuffer::from_vec(
+                )
+                .context("Invalid image resolution")?;
+            Ok(image.convert())
+        }
+        0x1C => {
+            let data = remove_bitmap_padding(
+                data,
+                header.decompressed_file_size as usize / header.height as usize,
+                calculate_padding(header.width),
+            );
+            let image: ImageBuffer<image::Bgr<u8>, Vec<u8>> =
+                ImageBuffer::from_vec(
                     header.width as u32,
                     header.height as u32,
                     data,
                 )
                 .context("Invalid image resolution")?;
-                Ok(ResourceType::RgbaImage {
-                    image: image.convert(),
-                })
+            Ok(image.convert())
+        }
+        0x2 => {
+            let image: GrayImage = ImageBuffer::from_vec(
+                header.width as u32,
+                header.height as u32,
+                data,
+            )
+            .context("Invalid image resolution")?;
+            Ok(image.convert())
+        }
+        ver => Err(unsupported_version(ver)),
+    }
+}
+
+fn unsupported_version(version: u32) -> anyhow::Error {
+    AkaibuError::UnsupportedVersion {
+        scheme: "IAR image".to_string(),
+        found: format!("{:X}", version),
+        expected: "2, 1C, 3C".to_string(),
+    }
+    .into()
+}
+
+/// Draw diff frame over image it is based on. Data starts with id of base
+/// entry, then every row has count of chunks, each with number of skipped
+/// pixels, number of copied pixels and copied pixels.
+#[allow(clippy::borrowed_box)]
+fn decode_layer(
+    header: &IarHeader,
+    data: &[u8],
+    file_path: &Path,
+    archive: Option<&Box<dyn archive::Archive>>,
+    depth: usize,
+) -> anyhow::Result<(RgbaImage, (i32, i32))> {
+    if depth >= MAX_LAYER_DEPTH {
+        return Err(bounds::malformed("IAR layer chain too deep").into());
+    }
+    let pixel_size = match header.version & FORMAT_MASK {
+        0x3C => 4,
+        0x1C => 3,
+        0x2 => 1,
+        _ => return Err(unsupported_version(header.version & 0xFFFF)),
+    };
+    let off = &mut 0;
+    let base_id = data.gread_with::<u32>(off, LE)?;
+    let base = extract_base(base_id, file_path, archive)?;
+    let (mut image, origin) = decode(&base, file_path, archive, depth + 1)?;
+
+    let dx = origin.0 as i64 - header.origin_x as i64;
+    let dy = origin.1 as i64 - header.origin_y as i64;
+    let blend = header.version & ALPHA_BLEND != 0 && pixel_size == 4;
+    for row in 0..header.height {
+        let y = if header.version & BOTTOM_UP != 0 {
+            header.height - 1 - row
+        } else {
+            row
+        };
+        let y = dy + y as i64;
+        let chunk_count = data.gread_with::<u32>(off, LE)?;
+        let mut x = dx;
+        for _ in 0..chunk_count {
+            x += data.gread_with::<u32>(off, LE)? as i64;
+            let count = data.gread_with::<u32>(off, LE)? as usize;
+            let size = count
+                .checked_mul(pixel_size)
+                .ok_or_else(|| bounds::malformed("IAR layer chunk size"))?;
+            let pixels = data
+                .get(*off..)
+                .and_then(|pixels| pixels.get(..size))
+                .ok_or_else(|| bounds::malformed("IAR layer chunk"))?;
+            *off += size;
+            for pixel in pixels.chunks_exact(pixel_size) {
+                // Parts of layer outside of base are not drawn
+                if x >= 0
+                    && y >= 0
+                    && x < image.width() as i64
+                    && y < image.height() as i64
+                {
+                    let src = to_rgba(pixel);
+                    let dest = image.get_pixel_mut(x as u32, y as u32);
+                    *dest = if blend { blend_over(src, *dest) } else { src };
+                }
+                x += 1;
             }
-            ver => Err(AkaibuError::Custom(format!(
-                "Unsupported version {:X}",
-                ver
-            ))
-            .into()),
         }
     }
+    Ok((image, origin))
+}
+
+/// Layer pixel stored as BGRA, BGR or gray
+fn to_rgba(pixel: &[u8]) -> Rgba<u8> {
+    match *pixel {
+        [b, g, r, a] => Rgba([r, g, b, a]),
+        [b, g, r] => Rgba([r, g, b, 0xFF]),
+        [gray] => Rgba([gray, gray, gray, 0xFF]),
+        _ => unreachable!("Unexpected IAR pixel size"),
+    }
+}
+
+fn blend_over(src: Rgba<u8>, dest: Rgba<u8>) -> Rgba<u8> {
+    let alpha = src[3] as u32;
+    let mix = |s: u8, d: u8| {
+        ((s as u32 * alpha + d as u32 * (255 - alpha) + 127) / 255) as u8
+    };
+    Rgba([
+        mix(src[0], dest[0]),
+        mix(src[1], dest[1]),
+        mix(src[2], dest[2]),
+        (alpha + (dest[3] as u32 * (255 - alpha) + 127) / 255) as u8,
+    ])
+}
+
+/// Image layer is based on, from archive layer came from or from folder of
+/// loose file. Entries are named by their id.
+#[allow(clippy::borrowed_box)]
+fn extract_base(
+    id: u32,
+    file_path: &Path,
+    archive: Option<&Box<dyn archive::Archive>>,
+) -> anyhow::Result<Vec<u8>> {
+    let entry = FileEntry {
+        file_name: id.to_string(),
+        full_path: PathBuf::from(id.to_string()),
+        file_offset: 0,
+        file_size: 0,
+    };
+    let contents = match archive {
+        Some(archive) => archive.extract(&entry)?,
+        None => DirectoryArchive::containing(file_path)?.extract(&entry)?,
+    };
+    Ok(contents.contents.to_vec())
 }
 
 fn calculate_padding(width: u32) -> usize {
@@ -131,7 +299,7 @@ fn calculate_padding(width: u32) -> usize {
 fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
     let mut src_index = 0;
     let mut dest_index = 0;
-    let mut dest = vec![0; dest_len];
+    let mut dest = bounds::output_buffer(dest_len)?;
     let mut counter = 0u32;
     let mut s;
     let mut b;
@@ -140,23 +308,23 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
         'inner: loop {
             counter >>= 1;
             if counter <= 0xFFFF {
-                counter = src[src_index] as u32
-                    | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                src_index += 2;
+                counter = refill(src, &mut src_index)?;
             }
             if counter & 1 == 0 {
                 break 'inner;
             }
-            dest[dest_index] = src[src_index];
+            bounds::write(
+                &mut dest,
+                dest_index,
+                bounds::read(src, src_index)?,
+            )?;
             src_index += 1;
             dest_index += 1;
         }
 
         counter >>= 1;
         if counter <= 0xFFFF {
-            counter = src[src_index] as u32
-                | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-            src_index += 2;
+            counter = refill(src, &mut src_index)?;
         }
 
         if counter & 1 == 0 {
@@ -164,13 +332,11 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             b = 2;
             var_c = b;
             if counter <= 0xFFFF {
-                counter = src[src_index] as u32
-                    | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                src_index += 2;
+                counter = refill(src, &mut src_index)?;
             }
 
             if counter & 1 == 0 {
-                s = src[src_index] as u32 + 1;
+                s = bounds::read(src, src_index)? as u32 + 1;
                 src_index += 1;
                 if s == 256 {
                     return Ok(dest);
@@ -178,26 +344,22 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             } else {
                 counter >>= 1;
                 if counter <= 0xFFFF {
-                    counter = src[src_index] as u32
-                        | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                    src_index += 2;
+                    counter = refill(src, &mut src_index)?;
                 }
                 let mut d = (counter & 1) << 10;
                 counter >>= 1;
                 if counter <= 0xFFFF {
-                    counter = src[src_index] as u32
-                        | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                    src_index += 2;
+                    counter = refill(src, &mut src_index)?;
                 }
                 let a = (counter & 1) << 9;
                 counter >>= 1;
                 d |= a;
                 if counter <= 0xFFFF {
-                    counter = src[src_index] as u32
-                        | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                    src_index += 2;
+                    counter = refill(src, &mut src_index)?;
                 }
-                s = ((((counter & 1) << 8) | src[src_index] as u32) | d)
+                s = ((((counter & 1) << 8)
+                    | bounds::read(src, src_index)? as u32)
+                    | d)
                     .wrapping_add(256);
                 src_index += 1;
             }
@@ -205,71 +367,51 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             counter >>= 1;
             let mut d = 1;
             if counter <= 0xFFFF {
-                counter = src[src_index] as u32
-                    | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                src_index += 2;
+                counter = refill(src, &mut src_index)?;
             }
             s = counter;
             counter >>= 1;
             s &= d;
             if counter <= 0xFFFF {
-                counter = src[src_index] as u32
-                    | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                src_index += 2;
+                counter = refill(src, &mut src_index)?;
             }
             if counter & 1 == 0 {
                 counter >>= 1;
                 d = 513;
                 if counter <= 0xFFFF {
-                    counter = src[src_index] as u32
-                        | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                    src_index += 2;
+                    counter = refill(src, &mut src_index)?;
                 }
                 if counter & 1 == 0 {
                     counter >>= 1;
                     d = 1025;
                     if counter <= 0xFFFF {
-                        counter = src[src_index] as u32
-                            | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                        src_index += 2;
+                        counter = refill(src, &mut src_index)?;
                     }
                     let mut a = counter & 1;
                     counter >>= 1;
                     s = s.wrapping_add(s);
                     s |= a;
                     if counter <= 0xFFFF {
-                        counter = src[src_index] as u32
-                            | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                        src_index += 2;
+                        counter = refill(src, &mut src_index)?;
                     }
                     if counter & 1 == 0 {
                         counter >>= 1;
                         d = 2049;
                         if counter <= 0xFFFF {
-                            counter = src[src_index] as u32
-                                | ((src[src_index + 1] as u32 | 0xFFFF_FF00)
-                                    << 8);
-                            src_index += 2;
+                            counter = refill(src, &mut src_index)?;
                         }
                         a = counter & 1;
                         counter >>= 1;
                         s = s.wrapping_add(s);
                         s |= a;
                         if counter <= 0xFFFF {
-                            counter = src[src_index] as u32
-                                | ((src[src_index + 1] as u32 | 0xFFFF_FF00)
-                                    << 8);
-                            src_index += 2;
+                            counter = refill(src, &mut src_index)?;
                         }
                         if counter & 1 == 0 {
                             counter >>= 1;
                             d = 4097;
                             if counter <= 0xFFFF {
-                                counter = src[src_index] as u32
-                                    | ((src[src_index + 1] as u32
-                                        | 0xFFFF_FF00)
-                                        << 8);
-                                src_index += 2;
+                                counter = refill(src, &mut src_index)?;
                             }
                             s = s.wrapping_add(s);
                             s |= counter & 1;
@@ -277,15 +419,13 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
                     }
                 }
             }
-            s = (s << 8) | src[src_index] as u32;
+            s = (s << 8) | bounds::read(src, src_index)? as u32;
             src_index += 1;
             counter >>= 1;
             s = s.wrapping_add(d);
             let mut var_4 = src_index;
             if counter <= 0xFFFF {
-                counter = src[src_index] as u32
-                    | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                src_index += 2;
+                counter = refill(src, &mut src_index)?;
                 var_4 = src_index;
             }
 
@@ -293,28 +433,21 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             if counter & 1 == 0 {
                 counter >>= 1;
                 if counter <= 0xFFFF {
-                    counter = src[src_index] as u32
-                        | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                    src_index += 2;
+                    counter = refill(src, &mut src_index)?;
                     var_4 = src_index;
                 }
                 b = 4;
                 if counter & 1 == 0 {
                     counter >>= 1;
                     if counter <= 0xFFFF {
-                        counter = src[src_index] as u32
-                            | ((src[src_index + 1] as u32 | 0xFFFF_FF00) << 8);
-                        src_index += 2;
+                        counter = refill(src, &mut src_index)?;
                         var_4 = src_index;
                     }
                     b = 5;
                     if counter & 1 == 0 {
                         counter >>= 1;
                         if counter <= 0xFFFF {
-                            counter = src[src_index] as u32
-                                | ((src[src_index + 1] as u32 | 0xFFFF_FF00)
-                                    << 8);
-                            src_index += 2;
+                            counter = refill(src, &mut src_index)?;
                             var_4 = src_index;
                         }
                         b = 6;
@@ -322,29 +455,25 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
                             counter >>= 1;
                             let mut var_8 = counter;
                             if counter <= 0xFFFF {
-                                counter = src[src_index] as u32
-                                    | ((src[src_index + 1] as u32
-                                        | 0xFFFF_FF00)
-                                        << 8);
-                                src_index += 2;
+                                counter = refill(src, &mut src_index)?;
                                 var_8 = counter;
                                 var_4 = src_index;
                             }
                             if counter & 1 == 0 {
                                 let (a, second, third) =
-                                    some_fn(var_4, var_8, src);
+                                    some_fn(var_4, var_8, src)?;
                                 var_4 = second;
                                 var_8 = third;
                                 if a == 0 {
                                     let (mut d, second, third) =
-                                        some_fn(second, third, src);
+                                        some_fn(second, third, src)?;
                                     d <<= 2;
                                     let (mut a, second, third) =
-                                        some_fn(second, third, src);
+                                        some_fn(second, third, src)?;
                                     a = a.wrapping_add(a);
                                     d |= a;
                                     let (a, second, third) =
-                                        some_fn(second, third, src);
+                                        some_fn(second, third, src)?;
                                     var_4 = second;
                                     var_8 = third;
                                     src_index = var_4;
@@ -352,11 +481,11 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
                                     b = b.wrapping_add(9);
                                 } else {
                                     src_index = var_4 + 1;
-                                    b = src[src_index - 1] as u32 + 17;
+                                    b = bounds::read(src, var_4)? as u32 + 17;
                                 }
                             } else {
                                 let (a, second, third) =
-                                    some_fn(var_4, var_8, src);
+                                    some_fn(var_4, var_8, src)?;
                                 var_4 = second;
                                 var_8 = third;
                                 src_index = var_4;
@@ -374,10 +503,12 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
             var_c = b;
         }
 
-        let mut d = dest_index;
-        d -= s as usize;
+        let mut d = dest_index
+            .checked_sub(s as usize)
+            .ok_or_else(|| bounds::malformed("back reference before start"))?;
         for _ in 0..b {
-            dest[d + s as usize] = dest[d];
+            let byte = bounds::read(&dest, d)?;
+            bounds::write(&mut dest, d + s as usize, byte)?;
             d += 1;
         }
         if b != 0 {
@@ -387,13 +518,22 @@ fn decompress(src: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
     }
 }
 
-fn some_fn(mut var_4: usize, mut var_8: u32, src: &[u8]) -> (u32, usize, u32) {
+fn some_fn(
+    mut var_4: usize,
+    mut var_8: u32,
+    src: &[u8],
+) -> Result<(u32, usize, u32), AkaibuError> {
     var_8 >>= 1;
     if var_8 <= 0xFFFF {
-        let s =
-            src[var_4] as u32 | ((src[var_4 + 1] as u32 | 0xFFFF_FF00) << 8);
-        var_8 = s;
-        var_4 += 2;
+        var_8 = refill(src, &mut var_4)?;
     }
-    (var_8 & 1, var_4, var_8)
+    Ok((var_8 & 1, var_4, var_8))
+}
+
+/// Next 16 flag bits with sentinel in upper half
+fn refill(src: &[u8], src_index: &mut usize) -> Result<u32, AkaibuError> {
+    let counter = bounds::read(src, *src_index)? as u32
+        | ((bounds::read(src, *src_index + 1)? as u32 | 0xFFFF_FF00) << 8);
+    *src_index += 2;
+    Ok(counter)
 }
@@ -1,17 +1,7 @@
-use std::iter;
-
+use crate::error::AkaibuError;
 use anyhow::Context;
-use once_cell::sync::Lazy;
 use scroll::{Pread, LE};
 
-static LOOKUP_TABLE: Lazy<Vec<u8>> = Lazy::new(|| {
-    iter::repeat(0)
-        .take(256)
-        .chain(0..=255)
-        .chain(iter::repeat(255).take(256))
-        .collect::<Vec<u8>>()
-});
-
 struct Jbp1 {
     data_offset: u32,
     flags: u32,
@@ -54,7 +44,13 @@ impl Jbp1 {
                 x_block_size = 32;
                 y_block_size = 16;
             }
-            _ => unimplemented!(),
+            size => {
+                return Err(AkaibuError::MalformedData(format!(
+                    "Unknown JBP1 block size: {}",
+                    size
+                ))
+                .into())
+            }
         }
 
         let blocks_width = (width + x_block_size - 1) & !(x_block_size - 1);
@@ -78,42 +74,98 @@ impl Jbp1 {
     }
 }
 
-struct BitStream {
-    buf: Vec<u8>,
-    buffer: u8,
-    bits_available: u8,
+struct BitStream<'a> {
+    buf: &'a [u8],
+    /// Bits read ahead from buffer, next bit of stream is the lowest one
+    bits: u64,
+    bits_available: u32,
     off: usize,
 }
 
-impl BitStream {
-    fn new(buf: Vec<u8>) -> Self {
+impl<'a> BitStream<'a> {
+    fn new(buf: &'a [u8]) -> Self {
         Self {
             buf,
-            buffer: 0,
+            bits: 0,
             bits_available: 0,
             off: 0,
         }
     }
-    fn read(&mut self, bits: usize) -> anyhow::Result<u32> {
-        let mut ret: u32 = 0;
-        for _ in 0..bits {
-            if self.bits_available == 0 {
-                self.buffer = self.buf.gread(&mut self.off)?;
-                self.bits_available = 8;
+    /// Read ahead whole bytes while they fit
+    #[inline(always)]
+    fn refill(&mut self) {
+        while self.bits_available <= 56 {
+            match self.buf.get(self.off) {
+                Some(&byte) => {
+                    self.bits |= (byte as u64) << self.bits_available;
+                    self.bits_available += 8;
+                    self.off += 1;
+                }
+                None => break,
             }
-            ret <<= 1;
-            ret |= (self.buffer & 1) as u32;
-            self.buffer >>= 1;
-            self.bits_available -= 1;
         }
-        Ok(ret)
+    }
+    /// Next `count` bits in stream order without consuming them, None
+    /// when stream ends before them
+    #[inline(always)]
+    fn peek(&mut self, count: u32) -> Option<u32> {
+        if self.bits_available < count {
+            self.refill();
+            if self.bits_available < count {
+                return None;
+            }
+        }
+        Some((self.bits & ((1 << count) - 1)) as u32)
+    }
+    #[inline(always)]
+    fn consume(&mut self, count: u32) {
+        self.bits >>= count;
+        self.bits_available -= count;
+    }
+    #[inline(always)]
+    fn read_bit(&mut self) -> anyhow::Result<u32> {
+        let bit = self.peek(1).context("Out of bounds access")?;
+        self.consume(1);
+        Ok(bit)
+    }
+    /// Value of `count` bits, first bit read is the highest one
+    fn read(&mut self, count: u32) -> anyhow::Result<u32> {
+        if count == 0 || count > 32 {
+            let mut ret: u32 = 0;
+            for _ in 0..count {
+                ret = (ret << 1) | self.read_bit()?;
+            }
+            return Ok(ret);
+        }
+        let bits = self.peek(count).context("Out of bounds access")?;
+        self.consume(count);
+        Ok(bits.reverse_bits() >> (32 - count))
+    }
+    /// Value of `bit_count` bits, negative values are stored with
+    /// complemented bits like in JPEG. Arithmetic wraps as in original
+    /// decoder, so malformed counts do not panic.
+    fn read_signed(&mut self, bit_count: u32) -> anyhow::Result<u32> {
+        let x = self.read(bit_count)?;
+        Ok(if x < 1u32.wrapping_shl(bit_count.wrapping_sub(1)) {
+            x.wrapping_sub(1u32.wrapping_shl(bit_count)).wrapping_add(1)
+        } else {
+            x
+        })
     }
 }
 
+/// Bits of code looked up at once, longer codes are read bit by bit
+const LOOKUP_BITS: u32 = 8;
+/// Lookup entry of code longer than [`LOOKUP_BITS`]
+const UNRESOLVED: u32 = u32::MAX;
+
 struct Tree {
     neighbour: Vec<u32>,
     root: usize,
     input_size: usize,
+    /// Symbol in low half and code length in high half for each value of
+    /// next [`LOOKUP_BITS`] bits
+    lookup: Vec<u32>,
 }
 
 impl Tree {
@@ -165,21 +217,71 @@ impl Tree {
         }
         let root = size - 1;
         let input_size = input.len();
+        // Leaves reached within lookup bits fill every entry starting with
+        // their code. Children always have lower index than their parent.
+        let mut lookup = vec![UNRESOLVED; 1 << LOOKUP_BITS];
+        let mut pending = vec![(root as u32, 0u32, 0u32)];
+        while let Some((node, code, len)) = pending.pop() {
+            if node < input_size as u32 {
+                for rest in 0..1 << (LOOKUP_BITS - len) {
+                    lookup[(code | rest << len) as usize] = node | len << 16;
+                }
+            } else if len < LOOKUP_BITS {
+                let node = node as usize;
+                pending.push((neighbour[node], code, len + 1));
+                pending.push((neighbour[512 + node], code | 1 << len, len + 1));
+            }
+        }
         Self {
             neighbour,
             root,
             input_size,
+            lookup,
         }
     }
-    fn read(&self, bit_stream: &mut BitStream) -> anyhow::Result<u32> {
+    fn read(&self, bit_stream: &mut BitStream<'_>) -> anyhow::Result<u32> {
+        if let Some(bits) = bit_stream.peek(LOOKUP_BITS) {
+            let entry = self.lookup[bits as usize];
+            if entry != UNRESOLVED {
+                bit_stream.consume(entry >> 16);
+                return Ok(entry & 0xFFFF);
+            }
+        }
         let mut ret = self.root as u32;
         while ret >= self.input_size as u32 {
-            ret = self.neighbour[((bit_stream.read(1)? << 9) + ret) as usize];
+            ret =
+                self.neighbour[((bit_stream.read_bit()? << 9) + ret) as usize];
         }
         Ok(ret)
     }
 }
 
+/// Coefficient position of each AC coefficient in bit stream order, DC
+/// coefficient is stored separately
+const ZIGZAG: [usize; 63] = [
+    1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33, 40,
+    48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50, 43, 36,
+    29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31, 39, 46, 53, 60, 61,
+    54, 47, 55, 62, 63,
+];
+
+/// Quantization factors looked up by position in bit stream, so
+/// coefficients are dequantized when they are read instead of in IDCT
+struct QuantTable {
+    dc: i16,
+    ac: [i16; 63],
+}
+
+impl QuantTable {
+    fn new(quant: &[i16; 64]) -> Self {
+        let mut ac = [0; 63];
+        for (factor, &pos) in ac.iter_mut().zip(ZIGZAG.iter()) {
+            *factor = quant[pos];
+        }
+        Self { dc: quant[0], ac }
+    }
+}
+
 pub(crate) fn jbp1_decompress(buf: &[u8]) -> anyhow::Result<Vec<u8>> {
     let off = &mut 0;
     let jbp1 = Jbp1::new(buf)?;
@@ -197,31 +299,28 @@ pub(crate) fn jbp1_decompress(buf: &[u8]) -> anyhow::Result<Vec<u8>> {
         .context("Out of bounds access")?
         .to_vec();
     *off += 16;
-    tree_input.iter_mut().for_each(|b| *b += 1);
+    tree_input.iter_mut().for_each(|b| *b = b.wrapping_add(1));
 
-    let mut quant_y = vec![0i16; 128];
-    let mut quant_c = vec![0i16; 128];
+    let mut quant_y = [0i16; 64];
+    let mut quant_c = [0i16; 64];
     if jbp1.flags & 0x08000000 != 0 {
-        for val in quant_y.iter_mut().take(64) {
+        for val in quant_y.iter_mut() {
             *val = buf.gread::<u8>(off)? as i16;
         }
-        for val in quant_c.iter_mut().take(64) {
+        for val in quant_c.iter_mut() {
             *val = buf.gread::<u8>(off)? as i16;
         }
     }
 
     let mut bit_stream_1 = BitStream::new(
         buf.get(*off..*off + jbp1.bit_pool_size_1 as usize)
-            .context("Out of bounds access")?
-            .to_vec(),
+            .context("Out of bounds access")?,
     );
     *off += jbp1.bit_pool_size_1 as usize;
     let mut bit_stream_2 = BitStream::new(
         buf.get(*off..*off + jbp1.bit_pool_size_2 as usize)
-            .context("Out of bounce context")?
-            .to_vec(),
+            .context("Out of bounds access")?,
     );
-    *off += jbp1.bit_pool_size_2 as usize;
     let mut block_output = decode_blocks(
         &jbp1,
         tree_input,
@@ -229,8 +328,8 @@ pub(crate) fn jbp1_decompress(buf: &[u8]) -> anyhow::Result<Vec<u8>> {
         &mut bit_stream_2,
         &mut freq_dc,
         &mut freq_ac,
-        &mut quant_y,
-        &mut quant_c,
+        &QuantTable::new(&quant_y),
+        &QuantTable::new(&quant_c),
     )?;
 
     if jbp1.depth != 32 {
@@ -245,67 +344,49 @@ pub(crate) fn jbp1_decompress(buf: &[u8]) -> anyhow::Result<Vec<u8>> {
 fn decode_blocks(
     jbp1: &Jbp1,
     tree_input: &[u8],
-    bit_stream_1: &mut BitStream,
-    bit_stream_2: &mut BitStream,
+    bit_stream_1: &mut BitStream<'_>,
+    bit_stream_2: &mut BitStream<'_>,
     freq_dc: &mut [u32],
     freq_ac: &mut [u32],
-    quant_y: &mut [i16],
-    quant_c: &mut [i16],
+    quant_y: &QuantTable,
+    quant_c: &QuantTable,
 ) -> anyhow::Result<Vec<u8>> {
     let tree_dc = Tree::new(tree_input, freq_dc);
     let tree_ac = Tree::new(tree_input, freq_ac);
     let mut blocks =
         vec![
-            0;
+            0u32;
             jbp1.x_block_count as usize * jbp1.y_block_count as usize * 3 * 2
         ];
 
-    for i in 0..blocks.len() {
+    // DC coefficients are stored as differences to previous block
+    let mut previous = 0u32;
+    for block in blocks.iter_mut() {
         let bit_count = tree_dc.read(bit_stream_1)?;
-        let mut x = bit_stream_1.read(bit_count as usize)?;
-        if x < (1 << (bit_count - 1)) {
-            x = x - (1 << bit_count) + 1;
-        }
-        *blocks.get_mut(i).context("Out of bounds access")? = x;
-        if i != 0 {
-            *blocks.get_mut(i).context("Out of bounce access")? +=
-                *blocks.get(i - 1).context("Out of bounds context")?;
-        }
+        previous = previous.wrapping_add(bit_stream_1.read_signed(bit_count)?);
+        *block = previous;
     }
+    let block_stride = jbp1.block_stride as usize;
     let mut block_output =
         vec![0; jbp1.blocks_width as usize * jbp1.blocks_height as usize * 4];
-    let original_order = [
-        1, 8, 16, 9, 2, 3, 10, 17, 24, 32, 25, 18, 11, 4, 5, 12, 19, 26, 33,
-        40, 48, 41, 34, 27, 20, 13, 6, 7, 14, 21, 28, 35, 42, 49, 56, 57, 50,
-        43, 36, 29, 22, 15, 23, 30, 37, 44, 51, 58, 59, 52, 45, 38, 31, 39, 46,
-        53, 60, 61, 54, 47, 55, 62, 63, 0,
-    ];
 
+    // Four luma blocks of 16x16 macroblock, followed by its subsampled
+    // chroma blocks
+    let mut dct_table = [[0i16; 64]; 6];
     for y in 0..jbp1.y_block_count as usize {
-        let mut dst1 = y * jbp1.block_stride as usize * 16;
-        let mut dst2 = dst1 + jbp1.block_stride as usize * 9;
+        let mut dst1 = y * block_stride * 16;
+        let mut dst2 = dst1 + block_stride * 8;
 
         for x in 0..jbp1.x_block_count as usize {
-            let mut dct_table: Vec<Vec<i16>> = vec![
-                vec![0; 64],
-                vec![0; 64],
-                vec![0; 64],
-                vec![0; 64],
-                vec![0; 64],
-                vec![0; 64],
-            ];
-
-            for n in 0..6 {
-                *dct_table
-                    .get_mut(n)
-                    .context("Out of bounds access")?
-                    .get_mut(0)
-                    .context("Out of bounds access")? = *blocks
-                    .get((y * jbp1.x_block_count as usize + x) * 6 + n)
-                    .context("Out of bounds access")?
-                    as i16;
-
-                let mut i = 0;
+            let dc = &blocks[(y * jbp1.x_block_count as usize + x) * 6..];
+            for (n, coefficients) in dct_table.iter_mut().enumerate() {
+                let quant = if n < 4 { quant_y } else { quant_c };
+                *coefficients = [0; 64];
+                coefficients[0] = (dc[n] as i16).wrapping_mul(quant.dc);
+
+                // Position in zigzag order, skips wrap around as in
+                // original decoder
+                let mut i = 0u8;
                 while i < 63 {
                     let bit_count = tree_ac.read(bit_stream_2)?;
                     if bit_count == 15 {
@@ -313,76 +394,28 @@ fn decode_blocks(
                     }
                     if bit_count == 0 {
                         let mut tree_input_pos = 0;
-                        while bit_stream_2.read(1)? != 0 {
+                        while bit_stream_2.read_bit()? != 0 {
                             tree_input_pos += 1;
                         }
-                        i += tree_input
-                            .get(tree_input_pos)
-                            .context("Out of bounds access")?;
+                        i = i.wrapping_add(
+                            *tree_input
+                                .get(tree_input_pos)
+                                .context("Out of bounds access")?,
+                        );
                     } else {
-                        let mut x = bit_stream_2.read(bit_count as usize)?;
-                        if x < (1 << (bit_count - 1)) {
-                            x = x - (1 << bit_count) + 1;
-                        }
-                        *dct_table
-                            .get_mut(n)
-                            .context("Out of bounds access")?
-                            .get_mut(
-                                *original_order
-                                    .get(i as usize)
-                                    .context("Out of bounds access")?,
-                            )
-                            .context("Out of bounds access")? = x as i16;
+                        let value = bit_stream_2.read_signed(bit_count)? as i16;
+                        coefficients[ZIGZAG[i as usize]] =
+                            value.wrapping_mul(quant.ac[i as usize]);
                         i += 1;
                     }
                 }
+                idct(coefficients);
             }
-            dct(&mut dct_table[0], quant_y);
-            dct(&mut dct_table[1], quant_y);
-            dct(&mut dct_table[2], quant_y);
-            dct(&mut dct_table[3], quant_y);
-            dct(&mut dct_table[4], quant_c);
-            dct(&mut dct_table[5], quant_c);
-            ycc2rgb(
-                dst1,
-                dst1 + jbp1.block_stride as usize,
-                &dct_table[0],
-                &dct_table[4],
-                &dct_table[5],
-                0,
-                &mut block_output,
-                jbp1.block_stride as usize,
-            );
-            ycc2rgb(
-                dst1 + 32,
-                dst1 + jbp1.block_stride as usize + 32,
-                &dct_table[1],
-                &dct_table[4],
-                &dct_table[5],
-                4,
-                &mut block_output,
-                jbp1.block_stride as usize,
-            );
-            ycc2rgb(
-                dst2 - jbp1.block_stride as usize,
-                dst2,
-                &dct_table[2],
-                &dct_table[4],
-                &dct_table[5],
-                32,
-                &mut block_output,
-                jbp1.block_stride as usize,
-            );
-            ycc2rgb(
-                dst2 - jbp1.block_stride as usize + 32,
-                dst2 + 32,
-                &dct_table[3],
-                &dct_table[4],
-                &dct_table[5],
-                36,
-                &mut block_output,
-                jbp1.block_stride as usize,
-            );
+            let [y0, y1, y2, y3, cb, cr] = &dct_table;
+            ycc2rgb(dst1, y0, cb, cr, 0, &mut block_output, block_stride);
+            ycc2rgb(dst1 + 32, y1, cb, cr, 4, &mut block_output, block_stride);
+            ycc2rgb(dst2, y2, cb, cr, 32, &mut block_output, block_stride);
+            ycc2rgb(dst2 + 32, y3, cb, cr, 36, &mut block_output, block_stride);
 
             dst1 += 64;
             dst2 += 64;
@@ -391,190 +424,109 @@ fn decode_blocks(
     Ok(block_output)
 }
 
-#[allow(clippy::many_single_char_names)]
-fn dct(dct_table: &mut [i16], quant: &mut [i16]) {
-    let mut lp1 = &mut dct_table[..];
-    let mut lp2 = &mut quant[..];
-
-    let mut a: isize;
-    let mut b: isize;
-    let mut c: isize;
-    let mut d: isize;
-    let mut w: isize;
-    let mut x: isize;
-    let mut y: isize;
-    let mut z: isize;
-    let mut s: isize;
-    let mut t: isize;
-    let mut u: isize;
-    let mut v: isize;
-    let mut n: isize;
-
-    for _ in 0..8 {
-        if lp1[0x08] == 0
-            && lp1[0x10] == 0
-            && lp1[0x18] == 0
-            && lp1[0x20] == 0
-            && lp1[0x28] == 0
-            && lp1[0x30] == 0
-            && lp1[0x38] == 0
-        {
-            let val = lp1[0] * lp2[0];
-            lp1[0x00] = val;
-            lp1[0x08] = val;
-            lp1[0x10] = val;
-            lp1[0x18] = val;
-            lp1[0x20] = val;
-            lp1[0x28] = val;
-            lp1[0x30] = val;
-            lp1[0x38] = val;
-        } else {
-            c = (lp2[0x10] * lp1[0x10]) as isize;
-            d = (lp2[0x30] * lp1[0x30]) as isize;
-            x = ((c + d) * 35467) >> 16;
-            c = ((c * 50159) >> 16) + x;
-            d = ((d * -121094) >> 16) + x;
-            a = (lp1[0x00] * lp2[0x00]) as isize;
-            b = (lp1[0x20] * lp2[0x20]) as isize;
-            w = a + b + c;
-            x = a + b - c;
-            y = a - b + d;
-            z = a - b - d;
-
-            c = (lp1[0x38] * lp2[0x38]) as isize;
-            d = (lp1[0x28] * lp2[0x28]) as isize;
-            a = (lp1[0x18] * lp2[0x18]) as isize;
-            b = (lp1[0x08] * lp2[0x08]) as isize;
-            n = ((a + b + c + d) * 77062) >> 16;
-
-            u = n
-                + ((c * 19571) >> 16)
-                + (((c + a) * -128553) >> 16)
-                + (((c + b) * -58980) >> 16);
-            v = n
-                + ((d * 134553) >> 16)
-                + (((d + b) * -25570) >> 16)
-                + (((d + a) * -167963) >> 16);
-            t = n
-                + ((b * 98390) >> 16)
-                + (((d + b) * -25570) >> 16)
-                + (((c + b) * -58980) >> 16);
-            s = n
-                + ((a * 201373) >> 16)
-                + (((c + a) * -128553) >> 16)
-                + (((d + a) * -167963) >> 16);
-
-            lp1[0x00] = (w + t) as i16;
-            lp1[0x38] = (w - t) as i16;
-            lp1[0x08] = (y + s) as i16;
-            lp1[0x30] = (y - s) as i16;
-            lp1[0x10] = (z + v) as i16;
-            lp1[0x28] = (z - v) as i16;
-            lp1[0x18] = (x + u) as i16;
-            lp1[0x20] = (x - u) as i16;
+/// Even part of 8 point IDCT, taking inputs 0, 2, 4 and 6
+#[inline(always)]
+fn idct_even(a: i64, c: i64, b: i64, d: i64) -> [i64; 4] {
+    let x = ((c + d) * 35467) >> 16;
+    let c = ((c * 50159) >> 16) + x;
+    let d = ((d * -121094) >> 16) + x;
+    [a + b + c, a + b - c, a - b + d, a - b - d]
+}
+
+/// Odd part of 8 point IDCT, taking inputs 3, 1, 7 and 5
+#[inline(always)]
+fn idct_odd(a: i64, b: i64, c: i64, d: i64) -> [i64; 4] {
+    let n = ((a + b + c + d) * 77062) >> 16;
+    let ac = ((a + c) * -128553) >> 16;
+    let ad = ((a + d) * -167963) >> 16;
+    let bc = ((b + c) * -58980) >> 16;
+    let bd = ((b + d) * -25570) >> 16;
+    [
+        n + ((a * 201373) >> 16) + ac + ad,
+        n + ((b * 98390) >> 16) + bd + bc,
+        n + ((c * 19571) >> 16) + bc + ac,
+        n + ((d * 134553) >> 16) + bd + ad,
+    ]
+}
+
+/// Inverse DCT of dequantized block, columns first. Columns and rows
+/// holding only DC coefficient, the common case, skip the multiplications
+/// as they come out flat.
+fn idct(block: &mut [i16; 64]) {
+    for col in 0..8 {
+        if (1..8).all(|row| block[row * 8 + col] == 0) {
+            let dc = block[col];
+            for row in 1..8 {
+                block[row * 8 + col] = dc;
+            }
+            continue;
+        }
+        let at = |row: usize| block[row * 8 + col] as i64;
+        let [w, x, y, z] = idct_even(at(0), at(2), at(4), at(6));
+        let [s, t, u, v] = idct_odd(at(3), at(1), at(7), at(5));
+        let output = [w + t, y + s, z + v, x + u, x - u, z - v, y - s, w - t];
+        for (row, value) in output.iter().enumerate() {
+            block[row * 8 + col] = *value as i16;
         }
-        lp1 = &mut lp1[1..];
-        lp2 = &mut lp2[1..];
     }
 
-    lp1 = &mut dct_table[..];
-
-    for _ in 0..8 {
-        a = lp1[0] as isize;
-        c = lp1[2] as isize;
-        b = lp1[4] as isize;
-        d = lp1[6] as isize;
-        x = ((c + d) * 35467) >> 16;
-        c = ((c * 50159) >> 16) + x;
-        d = ((d * -121094) >> 16) + x;
-        w = a + b + c;
-        x = a + b - c;
-        y = a - b + d;
-        z = a - b - d;
-
-        d = lp1[5] as isize;
-        b = lp1[1] as isize;
-        c = lp1[7] as isize;
-        a = lp1[3] as isize;
-        n = ((a + b + c + d) * 77062) >> 16;
-
-        s = n
-            + ((a * 201373) >> 16)
-            + (((a + c) * -128553) >> 16)
-            + (((a + d) * -167963) >> 16);
-
-        t = n
-            + ((b * 98390) >> 16)
-            + (((b + d) * -25570) >> 16)
-            + (((b + c) * -58980) >> 16);
-
-        u = n
-            + ((c * 19571) >> 16)
-            + (((b + c) * -58980) >> 16)
-            + (((a + c) * -128553) >> 16);
-
-        v = n
-            + ((d * 134553) >> 16)
-            + (((b + d) * -25570) >> 16)
-            + (((a + d) * -167963) >> 16);
-
-        lp1[0] = ((w + t) >> 3) as i16;
-        lp1[7] = ((w - t) >> 3) as i16;
-        lp1[1] = ((y + s) >> 3) as i16;
-        lp1[6] = ((y - s) >> 3) as i16;
-        lp1[2] = ((z + v) >> 3) as i16;
-        lp1[5] = ((z - v) >> 3) as i16;
-        lp1[3] = ((x + u) >> 3) as i16;
-        lp1[4] = ((x - u) >> 3) as i16;
-
-        lp1 = &mut lp1[8..];
+    for row in block.chunks_exact_mut(8) {
+        if row[1..].iter().all(|&value| value == 0) {
+            let dc = row[0] >> 3;
+            row.iter_mut().for_each(|value| *value = dc);
+            continue;
+        }
+        let at = |col: usize| row[col] as i64;
+        let [w, x, y, z] = idct_even(at(0), at(2), at(4), at(6));
+        let [s, t, u, v] = idct_odd(at(3), at(1), at(7), at(5));
+        let output = [w + t, y + s, z + v, x + u, x - u, z - v, y - s, w - t];
+        for (value, output) in row.iter_mut().zip(output.iter()) {
+            *value = (output >> 3) as i16;
+        }
     }
 }
 
-#[allow(clippy::too_many_arguments)]
+/// Color component clamped to byte range
+#[inline(always)]
+fn clamp(value: i64) -> u8 {
+    value.max(0).min(255) as u8
+}
+
+/// Write 8x8 luma block with its quarter of subsampled chroma blocks as
+/// BGR pixels starting at `dst`, alpha is left as is
 fn ycc2rgb(
-    mut dc: usize,
-    mut ac: usize,
-    dct_y: &[i16],
-    dct_cb: &[i16],
-    dct_cr: &[i16],
-    mut cbcr_src: usize,
-    m_output: &mut [u8],
-    m_stride: usize,
+    dst: usize,
+    dct_y: &[i16; 64],
+    dct_cb: &[i16; 64],
+    dct_cr: &[i16; 64],
+    cbcr_src: usize,
+    output: &mut [u8],
+    stride: usize,
 ) {
-    let mut y_src = 0;
-    for _ in 0..4 {
-        for _ in 0..4 {
-            let cb = dct_cb[cbcr_src] as isize;
-            let cr = dct_cr[cbcr_src] as isize;
-            let r = ((cr * 0x166F0) >> 16) as usize;
-            let g = (((cb * 0x5810) >> 16) + ((cr * 0xB6C0) >> 16)) as usize;
-            let b = ((cb * 0x1C590) >> 16) as usize;
-            let c0 = dct_y[y_src] as usize + 0x180;
-            let c1 = dct_y[y_src + 1] as usize + 0x180;
-            let c8 = dct_y[y_src + 8] as usize + 0x180;
-            let c9 = dct_y[y_src + 9] as usize + 0x180;
-            m_output[dc] = LOOKUP_TABLE[c0 + b];
-            m_output[ac + 1 - m_stride] = LOOKUP_TABLE[c0 - g];
-            m_output[ac + 2 - m_stride] = LOOKUP_TABLE[c0 + r];
-            m_output[ac + 4 - m_stride] = LOOKUP_TABLE[c1 + b];
-            m_output[ac + 5 - m_stride] = LOOKUP_TABLE[c1 - g];
-            m_output[ac + 6 - m_stride] = LOOKUP_TABLE[c1 + r];
-            m_output[ac] = LOOKUP_TABLE[c8 + b];
-            m_output[ac + 1] = LOOKUP_TABLE[c8 - g];
-            m_output[ac + 2] = LOOKUP_TABLE[c8 + r];
-            m_output[ac + 4] = LOOKUP_TABLE[c9 + b];
-            m_output[ac + 5] = LOOKUP_TABLE[c9 - g];
-            m_output[ac + 6] = LOOKUP_TABLE[c9 + r];
-            y_src += 2;
-            dc += 8;
-            ac += 8;
-            cbcr_src += 1;
+    for row_pair in 0..4 {
+        let start = dst + row_pair * 2 * stride;
+        let (top, bottom) =
+            output[start..start + stride + 32].split_at_mut(stride);
+        let mut rows = [&mut top[..32], bottom];
+        let luma = &dct_y[row_pair * 16..row_pair * 16 + 16];
+        let chroma = cbcr_src + row_pair * 8;
+        for x in 0..4 {
+            let cb = dct_cb[chroma + x] as i64;
+            let cr = dct_cr[chroma + x] as i64;
+            let r = (cr * 0x166F0) >> 16;
+            let g = ((cb * 0x5810) >> 16) + ((cr * 0xB6C0) >> 16);
+            let b = (cb * 0x1C590) >> 16;
+            for (row, luma) in rows.iter_mut().zip(luma.chunks_exact(8)) {
+                let pixels = &mut row[x * 8..x * 8 + 8];
+                for (pixel, &y) in
+                    pixels.chunks_exact_mut(4).zip(&luma[x * 2..x * 2 + 2])
+                {
+                    let y = y as i64 + 0x80;
+                    pixel[0] = clamp(y + b);
+                    pixel[1] = clamp(y - g);
+                    pixel[2] = clamp(y + r);
+                }
+            }
         }
-        dc += m_stride * 2 - 32;
-        ac += m_stride * 2 - 32;
-
-        y_src += 8;
-        cbcr_src += 4;
     }
 }
@@ -0,0 +1,180 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{
+    archive,
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{bounds, zlib_decompress},
+};
+use anyhow::Context;
+use image::{buffer::ConvertBuffer, ImageBuffer};
+use scroll::{Pread, LE};
+use std::{fs::File, io::Read, path::Path};
+
+const HEADER_SIZE: usize = 0x18;
+const SUPPORTED_VERSION: u16 = 1;
+
+#[derive(Debug, Pread)]
+struct McgHeader {
+    magic: [u8; 4],
+    version: u16,
+    channels: u16,
+    width: u32,
+    height: u32,
+    data_size: u32,
+    unpacked_size: u32,
+}
+
+impl ReportHeader for McgHeader {}
+
+/// Moonstone images. Pixels are zlib compressed and stored as separate
+/// blue, green, red and optional alpha planes. Every row of plane starts
+/// with predictor byte telling from which neighbours its deltas are taken.
+#[derive(Debug, Clone)]
+pub(crate) enum McgScheme {
+    Universal,
+}
+
+impl ResourceScheme for McgScheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let mut buf = Vec::with_capacity(1 << 20);
+        let mut file = File::open(file_path)?;
+        file.read_to_end(&mut buf)?;
+        self.from_bytes(buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(buf)
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let buf = std::fs::read(file_path)?;
+        Ok(vec![buf.pread_with::<McgHeader>(0, LE)?.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[MCG] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl McgScheme {
+    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+        let header = buf.pread_with::<McgHeader>(0, LE)?;
+        log::debug!("MCG header: {:?}", header);
+        if header.version != SUPPORTED_VERSION {
+            return Err(AkaibuError::UnsupportedVersion {
+                scheme: "MCG".to_string(),
+                found: header.version.to_string(),
+                expected: SUPPORTED_VERSION.to_string(),
+            }
+            .into());
+        }
+        if header.channels != 3 && header.channels != 4 {
+            return Err(AkaibuError::Unimplemented(format!(
+                "MCG with {} channels",
+                header.channels
+            ))
+            .into());
+        }
+        let width = header.width as usize;
+        let height = header.height as usize;
+        let pixel_count = width
+            .checked_mul(height)
+            .ok_or_else(|| bounds::malformed("image size overflow"))?;
+        if pixel_count == 0 {
+            return Err(bounds::malformed("empty MCG image").into());
+        }
+        // Predictor byte precedes every row
+        let planes_size = (width + 1)
+            .checked_mul(height)
+            .and_then(|size| size.checked_mul(header.channels as usize))
+            .ok_or_else(|| bounds::malformed("image size overflow"))?;
+        if header.unpacked_size as usize != planes_size {
+            return Err(bounds::malformed("MCG unpacked size").into());
+        }
+        let mut pixels = bounds::output_buffer(
+            pixel_count
+                .checked_mul(4)
+                .ok_or_else(|| bounds::malformed("image size overflow"))?,
+        )?;
+        if header.channels == 3 {
+            pixels.iter_mut().skip(3).step_by(4).for_each(|a| *a = 0xFF);
+        }
+        let data = buf
+            .get(HEADER_SIZE..HEADER_SIZE + header.data_size as usize)
+            .ok_or_else(|| bounds::malformed("MCG data out of bounds"))?;
+        let planes = zlib_decompress(data)?;
+        let plane_size = planes_size / header.channels as usize;
+        if planes.len() != planes_size {
+            return Err(bounds::malformed("MCG planes size").into());
+        }
+
+        for (channel, plane) in planes.chunks_exact(plane_size).enumerate() {
+            let plane = unpredict_plane(plane, width, height)?;
+            pixels
+                .iter_mut()
+                .skip(channel)
+                .step_by(4)
+                .zip(plane)
+                .for_each(|(pixel, value)| *pixel = value);
+        }
+        let image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
+            ImageBuffer::from_vec(header.width, header.height, pixels)
+                .context("Invalid image resolution")?;
+        Ok(ResourceType::RgbaImage {
+            image: image.convert(),
+        })
+    }
+}
+
+/// Undo row predictors of single plane. Deltas are added to left
+/// neighbour (1), upper neighbour (2) or their average (3), with 0 outside
+/// of image. Rows with predictor 0 are stored as is.
+fn unpredict_plane(
+    plane: &[u8],
+    width: usize,
+    height: usize,
+) -> Result<Vec<u8>, AkaibuError> {
+    let mut dest = bounds::output_buffer(width * height)?;
+    for (y, row) in plane.chunks_exact(width + 1).take(height).enumerate() {
+        let predictor = row[0];
+        for (x, delta) in row[1..].iter().enumerate() {
+            let left = if x > 0 { dest[y * width + x - 1] } else { 0 };
+            let up = if y > 0 { dest[(y - 1) * width + x] } else { 0 };
+            let prediction = match predictor {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((left as u16 + up as u16) / 2) as u8,
+                _ => {
+                    return Err(AkaibuError::MalformedData(format!(
+                        "Invalid MCG row predictor {}",
+                        predictor
+                    )))
+                }
+            };
+            bounds::write(
+                &mut dest,
+                y * width + x,
+                prediction.wrapping_add(*delta),
+            )?;
+        }
+    }
+    Ok(dest)
+}
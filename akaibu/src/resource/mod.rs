@@ -1,47 +1,123 @@
+#[cfg(feature = "fmt-akb")]
 mod akb;
 mod common;
+#[cfg(feature = "fmt-compressedbg")]
 mod compressedbg;
+#[cfg(feature = "fmt-crxg")]
 mod crxg;
+#[cfg(feature = "fmt-csx")]
+mod csx;
+#[cfg(feature = "fmt-dpng")]
 mod dpng;
+#[cfg(feature = "fmt-escr")]
+mod escr;
+#[cfg(feature = "fmt-g00")]
 mod g00;
-mod gyu;
+#[cfg(feature = "fmt-gyu")]
+pub(crate) mod gyu;
+#[cfg(feature = "fmt-iar")]
 mod iar;
+#[cfg(feature = "fmt-pb3b")]
 mod jbp1;
+#[cfg(feature = "fmt-mcg")]
+mod mcg;
+#[cfg(feature = "fmt-pb2")]
+mod pb2;
+#[cfg(feature = "fmt-pb3b")]
 mod pb3b;
+#[cfg(feature = "fmt-pgd")]
 mod pgd;
+#[cfg(feature = "fmt-pna")]
 mod pna;
-mod tlg;
+#[cfg(feature = "fmt-prs")]
+mod prs;
+mod text;
+#[cfg(feature = "fmt-tlg")]
+pub mod tlg;
+#[cfg(feature = "fmt-wip")]
+mod wip;
+#[cfg(feature = "fmt-ycg")]
 mod ycg;
+#[cfg(feature = "fmt-ykg")]
+mod ykg;
 
-use crate::archive::Archive;
+use crate::{
+    archive::Archive, error::AkaibuError, inspect::ParsedHeader, util,
+};
 use anyhow::Context;
 use dyn_clone::DynClone;
 use enum_iterator::IntoEnumIterator;
-use image::RgbaImage;
-use scroll::{Pread, LE};
-use std::{fmt::Debug, fs::File, io::Write, path::Path};
+use image::{Pixel, Rgba, RgbaImage};
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator,
+    ParallelIterator,
+};
+#[cfg(feature = "fmt-gyu")]
+use scroll::LE;
+use scroll::{Pread, BE};
+use std::{
+    fmt::Debug,
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    time::Duration,
+};
+#[cfg(feature = "fmt-tlg")]
 use tlg::TlgScheme;
 
 #[derive(Debug, IntoEnumIterator, Clone)]
 pub enum ResourceMagic {
+    #[cfg(feature = "fmt-tlg")]
     Tlg,
+    #[cfg(feature = "fmt-pb3b")]
     Pb3b,
+    #[cfg(feature = "fmt-ycg")]
     Ycg,
+    #[cfg(feature = "fmt-akb")]
     Akb,
+    #[cfg(feature = "fmt-gyu")]
     Gyu,
+    #[cfg(feature = "fmt-gyu")]
     GyuUniversal,
+    #[cfg(feature = "fmt-g00")]
     G00,
+    #[cfg(feature = "fmt-iar")]
     Iar,
+    #[cfg(feature = "fmt-crxg")]
     Crxg,
+    #[cfg(feature = "fmt-pna")]
     Pna,
+    #[cfg(feature = "fmt-compressedbg")]
     CompressedBg,
+    #[cfg(feature = "fmt-dpng")]
     Dpng,
+    #[cfg(feature = "fmt-pgd")]
     Pgd,
+    #[cfg(feature = "fmt-prs")]
+    Prs,
+    #[cfg(feature = "fmt-csx")]
+    Csx,
+    #[cfg(feature = "fmt-wip")]
+    Wip,
+    #[cfg(feature = "fmt-escr")]
+    Escr,
+    #[cfg(feature = "fmt-mcg")]
+    Mcg,
+    #[cfg(feature = "fmt-pb2")]
+    Pb2,
+    #[cfg(feature = "fmt-ykg")]
+    Ykg,
+    Text,
 
     Png,
     Jpg,
     Bmp,
     Ico,
+    Ogg,
+    Wav,
+    Adx,
+    Mpeg,
     Riff,
     Unrecognized,
 }
@@ -54,6 +130,14 @@ pub trait ResourceScheme: Debug + Send + Sync + DynClone {
         buf: Vec<u8>,
         archive: Option<&Box<dyn Archive>>,
     ) -> anyhow::Result<ResourceType>;
+    /// Headers of resource parsed without converting it
+    fn inspect(&self, _file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        Err(AkaibuError::Unimplemented(format!(
+            "Header report for {}",
+            self.get_name()
+        ))
+        .into())
+    }
     fn get_name(&self) -> String;
     fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
     where
@@ -66,14 +150,19 @@ impl ResourceMagic {
     pub fn parse_magic(buf: &[u8]) -> Self {
         match buf {
             // TLG
+            #[cfg(feature = "fmt-tlg")]
             [84, 76, 71, ..] => Self::Tlg,
             // PB3B
+            #[cfg(feature = "fmt-pb3b")]
             [80, 66, 51, 66, ..] => Self::Pb3b,
             // YCG\x00
+            #[cfg(feature = "fmt-ycg")]
             [89, 67, 71, 0, ..] => Self::Ycg,
             // AKB or AKB+
+            #[cfg(feature = "fmt-akb")]
             [65, 75, 66, 32, ..] | [65, 75, 66, 43, ..] => Self::Akb,
             // GYU\x1a
+            #[cfg(feature = "fmt-gyu")]
             [71, 89, 85, 26, ..] => match buf.pread_with::<u32>(8, LE) {
                 Ok(mt_seed) => {
                     if mt_seed == 0 {
@@ -84,28 +173,71 @@ impl ResourceMagic {
                 }
                 Err(_) => Self::Unrecognized,
             },
-            // CRXG
-            [0x43, 0x52, 0x58, 0x47, ..] => Self::Crxg,
+            // CRXG | CRXB | CRXJ
+            #[cfg(feature = "fmt-crxg")]
+            [0x43, 0x52, 0x58, 0x47, ..]
+            | [0x43, 0x52, 0x58, 0x42, ..]
+            | [0x43, 0x52, 0x58, 0x4A, ..] => Self::Crxg,
             // PNAP | WPAP
+            #[cfg(feature = "fmt-pna")]
             [0x50, 0x4E, 0x41, 0x50, ..] | [0x57, 0x50, 0x41, 0x50, ..] => {
                 Self::Pna
             }
             // CompressedBG___\x00
+            #[cfg(feature = "fmt-compressedbg")]
             [0x43, 0x6f, 0x6d, 0x70, 0x72, 0x65, 0x73, 0x73, 0x65, 0x64, 0x42, 0x47, 0x5f, 0x5f, 0x5f, 0x0, ..] => {
                 Self::CompressedBg
             }
             // DPNG
+            #[cfg(feature = "fmt-dpng")]
             [0x44, 0x50, 0x4e, 0x47, ..] => Self::Dpng,
             // GE | PGD2 | PGD3
+            #[cfg(feature = "fmt-pgd")]
             [0x47, 0x45, ..]
             | [0x50, 0x47, 0x44, 0x32, ..]
             | [0x50, 0x47, 0x44, 0x33, ..] => Self::Pgd,
+            // YB with 3 or 4 bytes per pixel
+            #[cfg(feature = "fmt-prs")]
+            [0x59, 0x42, _, 3, ..] | [0x59, 0x42, _, 4, ..] => Self::Prs,
+            // Entis\x1a\x00\x00 with Cotopha format description
+            #[cfg(feature = "fmt-csx")]
+            [0x45, 0x6E, 0x74, 0x69, 0x73, 0x1A, 0x00, 0x00, _, _, _, _, _, _, _, _, 0x43, 0x6F, 0x74, 0x6F, 0x70, 0x68, 0x61, ..] => {
+                Self::Csx
+            }
+            // WIPF
+            #[cfg(feature = "fmt-wip")]
+            [0x57, 0x49, 0x50, 0x46, ..] => Self::Wip,
+            // \x00DLR
+            // ESCR1_00
+            #[cfg(feature = "fmt-escr")]
+            [0x45, 0x53, 0x43, 0x52, 0x31, 0x5F, 0x30, 0x30, ..] => Self::Escr,
+            // MCG\x20
+            #[cfg(feature = "fmt-mcg")]
+            [0x4D, 0x43, 0x47, 0x20, ..] => Self::Mcg,
+            // PB2A
+            #[cfg(feature = "fmt-pb2")]
+            [0x50, 0x42, 0x32, 0x41, ..] => Self::Pb2,
+            // YKG000
+            #[cfg(feature = "fmt-ykg")]
+            [0x59, 0x4B, 0x47, 0x30, 0x30, 0x30, ..] => Self::Ykg,
+            // UTF-8 BOM | UTF-16LE BOM | UTF-16BE BOM, binary data can start
+            // with these bytes too
+            [0xEF, 0xBB, 0xBF, ..] | [0xFF, 0xFE, ..] | [0xFE, 0xFF, ..]
+                if util::text::looks_like_text(buf) =>
+            {
+                Self::Text
+            }
 
             [137, 80, 78, 71, 13, 10, 26, 10, ..]
             | [135, 80, 78, 71, 13, 10, 26, 10, ..] => Self::Png,
             [255, 216, 255, ..] => Self::Jpg,
             [66, 77, ..] => Self::Bmp,
             [0, 0, 1, 0, ..] => Self::Ico,
+            [79, 103, 103, 83, ..] => Self::Ogg,
+            [82, 73, 70, 70, _, _, _, _, 87, 65, 86, 69, ..] => Self::Wav,
+            [128, 0, ..] if is_adx(buf) => Self::Adx,
+            // MPEG program stream pack header
+            [0, 0, 1, 186, ..] => Self::Mpeg,
             [82, 73, 70, 70, ..] => Self::Riff,
             _ => Self::Unrecognized,
         }
@@ -114,8 +246,22 @@ impl ResourceMagic {
         match file_path.extension() {
             Some(extension) => match extension.to_str() {
                 Some(extension) => match extension {
+                    #[cfg(feature = "fmt-g00")]
                     "g00" => Self::G00,
                     "png" => Self::Png,
+                    "ogg" => Self::Ogg,
+                    "wav" => Self::Wav,
+                    "adx" => Self::Adx,
+                    "mpg" | "mpeg" => Self::Mpeg,
+                    "txt" | "ks" | "csv" => Self::Text,
+                    #[cfg(feature = "fmt-csx")]
+                    "csx" => Self::Csx,
+                    #[cfg(feature = "fmt-mcg")]
+                    "mcg" => Self::Mcg,
+                    #[cfg(feature = "fmt-pb2")]
+                    "pb2" => Self::Pb2,
+                    #[cfg(feature = "fmt-ykg")]
+                    "ykg" => Self::Ykg,
                     _ => Self::Unrecognized,
                 },
                 None => Self::Unrecognized,
@@ -123,56 +269,220 @@ impl ResourceMagic {
             None => Self::Unrecognized,
         }
     }
+    /// Broad kind of content used to filter archive entries
+    pub fn content_kind(&self) -> ContentKind {
+        match self {
+            #[cfg(feature = "fmt-tlg")]
+            Self::Tlg => ContentKind::Image,
+            #[cfg(feature = "fmt-pb3b")]
+            Self::Pb3b => ContentKind::Image,
+            #[cfg(feature = "fmt-ycg")]
+            Self::Ycg => ContentKind::Image,
+            #[cfg(feature = "fmt-akb")]
+            Self::Akb => ContentKind::Image,
+            #[cfg(feature = "fmt-gyu")]
+            Self::Gyu => ContentKind::Image,
+            #[cfg(feature = "fmt-gyu")]
+            Self::GyuUniversal => ContentKind::Image,
+            #[cfg(feature = "fmt-g00")]
+            Self::G00 => ContentKind::Image,
+            #[cfg(feature = "fmt-iar")]
+            Self::Iar => ContentKind::Image,
+            #[cfg(feature = "fmt-crxg")]
+            Self::Crxg => ContentKind::Image,
+            #[cfg(feature = "fmt-pna")]
+            Self::Pna => ContentKind::Image,
+            #[cfg(feature = "fmt-compressedbg")]
+            Self::CompressedBg => ContentKind::Image,
+            #[cfg(feature = "fmt-dpng")]
+            Self::Dpng => ContentKind::Image,
+            #[cfg(feature = "fmt-pgd")]
+            Self::Pgd => ContentKind::Image,
+            #[cfg(feature = "fmt-prs")]
+            Self::Prs => ContentKind::Image,
+            #[cfg(feature = "fmt-wip")]
+            Self::Wip => ContentKind::Image,
+            #[cfg(feature = "fmt-mcg")]
+            Self::Mcg => ContentKind::Image,
+            #[cfg(feature = "fmt-pb2")]
+            Self::Pb2 => ContentKind::Image,
+            #[cfg(feature = "fmt-ykg")]
+            Self::Ykg => ContentKind::Image,
+            Self::Png | Self::Jpg | Self::Bmp | Self::Ico => ContentKind::Image,
+            #[cfg(feature = "fmt-csx")]
+            Self::Csx => ContentKind::Script,
+            #[cfg(feature = "fmt-escr")]
+            Self::Escr => ContentKind::Script,
+            Self::Text => ContentKind::Script,
+            Self::Ogg | Self::Wav | Self::Adx | Self::Mpeg => {
+                ContentKind::Other
+            }
+            Self::Riff | Self::Unrecognized => ContentKind::Other,
+        }
+    }
     pub fn is_universal(&self) -> bool {
         match self {
+            #[cfg(feature = "fmt-tlg")]
             Self::Tlg => true,
+            #[cfg(feature = "fmt-pb3b")]
             Self::Pb3b => true,
+            #[cfg(feature = "fmt-ycg")]
             Self::Ycg => true,
+            #[cfg(feature = "fmt-akb")]
             Self::Akb => true,
+            #[cfg(feature = "fmt-gyu")]
             Self::Gyu => false,
+            #[cfg(feature = "fmt-gyu")]
             Self::GyuUniversal => true,
+            #[cfg(feature = "fmt-g00")]
             Self::G00 => true,
+            #[cfg(feature = "fmt-iar")]
             Self::Iar => true,
+            #[cfg(feature = "fmt-crxg")]
             Self::Crxg => true,
+            #[cfg(feature = "fmt-pna")]
             Self::Pna => true,
+            #[cfg(feature = "fmt-compressedbg")]
             Self::CompressedBg => true,
+            #[cfg(feature = "fmt-dpng")]
             Self::Dpng => true,
+            #[cfg(feature = "fmt-pgd")]
             Self::Pgd => true,
+            #[cfg(feature = "fmt-prs")]
+            Self::Prs => true,
+            #[cfg(feature = "fmt-csx")]
+            Self::Csx => true,
+            #[cfg(feature = "fmt-wip")]
+            Self::Wip => true,
+            #[cfg(feature = "fmt-mcg")]
+            Self::Mcg => true,
+            #[cfg(feature = "fmt-pb2")]
+            Self::Pb2 => true,
+            #[cfg(feature = "fmt-ykg")]
+            Self::Ykg => true,
+            #[cfg(feature = "fmt-escr")]
+            Self::Escr => true,
+            Self::Text => true,
 
             Self::Png => true,
             Self::Jpg => true,
             Self::Bmp => true,
             Self::Ico => true,
+            Self::Ogg => true,
+            Self::Wav => true,
+            Self::Adx => true,
+            Self::Mpeg => true,
             Self::Riff => true,
             Self::Unrecognized => true,
         }
     }
     pub fn get_schemes(&self) -> Vec<Box<dyn ResourceScheme>> {
         match self {
+            #[cfg(feature = "fmt-tlg")]
             ResourceMagic::Tlg => TlgScheme::get_schemes(),
+            #[cfg(feature = "fmt-pb3b")]
             ResourceMagic::Pb3b => pb3b::Pb3bScheme::get_schemes(),
+            #[cfg(feature = "fmt-ycg")]
             ResourceMagic::Ycg => ycg::YcgScheme::get_schemes(),
+            #[cfg(feature = "fmt-akb")]
             ResourceMagic::Akb => akb::AkbScheme::get_schemes(),
+            #[cfg(feature = "fmt-gyu")]
             ResourceMagic::Gyu => gyu::GyuScheme::get_schemes(),
+            #[cfg(feature = "fmt-gyu")]
             ResourceMagic::GyuUniversal => {
                 vec![Box::new(gyu::GyuScheme::Universal)]
             }
+            #[cfg(feature = "fmt-g00")]
             ResourceMagic::G00 => g00::G00Scheme::get_schemes(),
+            #[cfg(feature = "fmt-iar")]
             ResourceMagic::Iar => iar::IarScheme::get_schemes(),
+            #[cfg(feature = "fmt-crxg")]
             ResourceMagic::Crxg => crxg::CrxgScheme::get_schemes(),
+            #[cfg(feature = "fmt-pna")]
             ResourceMagic::Pna => pna::PnaScheme::get_schemes(),
+            #[cfg(feature = "fmt-compressedbg")]
             ResourceMagic::CompressedBg => {
                 compressedbg::BgScheme::get_schemes()
             }
+            #[cfg(feature = "fmt-dpng")]
             ResourceMagic::Dpng => dpng::DpngScheme::get_schemes(),
+            #[cfg(feature = "fmt-pgd")]
             ResourceMagic::Pgd => pgd::PgdScheme::get_schemes(),
+            #[cfg(feature = "fmt-prs")]
+            ResourceMagic::Prs => prs::PrsScheme::get_schemes(),
+            #[cfg(feature = "fmt-csx")]
+            ResourceMagic::Csx => csx::CsxScheme::get_schemes(),
+            #[cfg(feature = "fmt-wip")]
+            ResourceMagic::Wip => wip::WipScheme::get_schemes(),
+            #[cfg(feature = "fmt-mcg")]
+            ResourceMagic::Mcg => mcg::McgScheme::get_schemes(),
+            #[cfg(feature = "fmt-pb2")]
+            ResourceMagic::Pb2 => pb2::Pb2Scheme::get_schemes(),
+            #[cfg(feature = "fmt-ykg")]
+            ResourceMagic::Ykg => ykg::YkgScheme::get_schemes(),
+            #[cfg(feature = "fmt-escr")]
+            ResourceMagic::Escr => escr::EscrScheme::get_schemes(),
+            ResourceMagic::Text => text::TextScheme::get_schemes(),
 
             Self::Png | Self::Jpg | Self::Bmp | Self::Ico | Self::Riff => {
                 vec![Box::new(common::Common(format!("{:?}", self)))]
             }
+            // Audio and video are written as stored
+            Self::Ogg | Self::Wav | Self::Adx | Self::Mpeg => vec![],
             ResourceMagic::Unrecognized => vec![],
         }
     }
+    /// Canonical extension of formats written as stored in archive
+    pub fn extension(&self) -> Option<&'static str> {
+        match self {
+            Self::Png => Some("png"),
+            Self::Jpg => Some("jpg"),
+            Self::Bmp => Some("bmp"),
+            Self::Ico => Some("ico"),
+            Self::Ogg => Some("ogg"),
+            Self::Wav => Some("wav"),
+            Self::Adx => Some("adx"),
+            Self::Mpeg => Some("mpg"),
+            _ => None,
+        }
+    }
+    /// Extension files of format usually have. Unlike [`Self::extension`]
+    /// it also covers engine formats, whose files are named differently
+    /// between games, so it is only given to entries stored without one.
+    pub fn usual_extension(&self) -> Option<&'static str> {
+        match self {
+            #[cfg(feature = "fmt-tlg")]
+            Self::Tlg => Some("tlg"),
+            #[cfg(feature = "fmt-pb3b")]
+            Self::Pb3b => Some("pb3"),
+            #[cfg(feature = "fmt-ycg")]
+            Self::Ycg => Some("ycg"),
+            #[cfg(feature = "fmt-akb")]
+            Self::Akb => Some("akb"),
+            #[cfg(feature = "fmt-gyu")]
+            Self::Gyu | Self::GyuUniversal => Some("gyu"),
+            #[cfg(feature = "fmt-crxg")]
+            Self::Crxg => Some("crx"),
+            #[cfg(feature = "fmt-pna")]
+            Self::Pna => Some("pna"),
+            #[cfg(feature = "fmt-pgd")]
+            Self::Pgd => Some("pgd"),
+            #[cfg(feature = "fmt-csx")]
+            Self::Csx => Some("csx"),
+            #[cfg(feature = "fmt-wip")]
+            Self::Wip => Some("wip"),
+            #[cfg(feature = "fmt-escr")]
+            Self::Escr => Some("bin"),
+            #[cfg(feature = "fmt-mcg")]
+            Self::Mcg => Some("mcg"),
+            #[cfg(feature = "fmt-pb2")]
+            Self::Pb2 => Some("pb2"),
+            #[cfg(feature = "fmt-ykg")]
+            Self::Ykg => Some("ykg"),
+            Self::Text => Some("txt"),
+            resource => resource.extension(),
+        }
+    }
     pub fn get_all_schemes() -> Vec<Box<dyn ResourceScheme>> {
         ResourceMagic::into_enum_iter()
             .map(|arc| arc.get_schemes())
@@ -181,54 +491,327 @@ impl ResourceMagic {
     }
 }
 
+/// CRI ADX header ends with `(c)CRI` right before audio data, header size
+/// is stored after `0x80 0x00` magic
+fn is_adx(buf: &[u8]) -> bool {
+    const COPYRIGHT: &[u8] = b"(c)CRI";
+    match buf.pread_with::<u16>(2, BE) {
+        Ok(header_size) => {
+            let end = header_size as usize + 4;
+            end >= COPYRIGHT.len()
+                && buf.get(end - COPYRIGHT.len()..end) == Some(COPYRIGHT)
+        }
+        Err(_) => false,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentKind {
+    Image,
+    Script,
+    Other,
+}
+
+impl ContentKind {
+    /// Detect kind of content from first bytes of file
+    pub fn sniff(resource: &ResourceMagic, buf: &[u8]) -> Self {
+        match resource {
+            ResourceMagic::Unrecognized if util::text::looks_like_text(buf) => {
+                Self::Script
+            }
+            resource => resource.content_kind(),
+        }
+    }
+}
+
+impl FromStr for ContentKind {
+    type Err = AkaibuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "image" | "images" => Ok(Self::Image),
+            "script" | "scripts" => Ok(Self::Script),
+            "other" => Ok(Self::Other),
+            _ => Err(AkaibuError::Custom(format!(
+                "Unknown content kind: {}, expected images, scripts or other",
+                s
+            ))),
+        }
+    }
+}
+
+/// Format converted images are written in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Png,
+    /// Uncompressed, much faster to write than PNG
+    Bmp,
+}
+
+impl ImageFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Png => "png",
+            Self::Bmp => "bmp",
+        }
+    }
+    pub fn save(
+        &self,
+        image: &RgbaImage,
+        file_name: &Path,
+    ) -> anyhow::Result<()> {
+        self.save_with_metadata(image, file_name, None)
+    }
+    /// Save image with metadata stamped into it, BMP can not hold it and is
+    /// written without
+    pub fn save_with_metadata(
+        &self,
+        image: &RgbaImage,
+        file_name: &Path,
+        metadata: Option<&ImageMetadata>,
+    ) -> anyhow::Result<()> {
+        match (self, metadata) {
+            (Self::Png, None) => image.save(file_name)?,
+            (Self::Png, Some(metadata)) => {
+                let png = util::image::encode_png_with_text(
+                    image,
+                    &metadata.text_chunks(),
+                )?;
+                File::create(file_name)?.write_all(&png)?;
+            }
+            (Self::Bmp, _) => {
+                let mut writer = BufWriter::new(File::create(file_name)?);
+                util::image::write_bmp(image, &mut writer)?;
+                writer.flush()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Default for ImageFormat {
+    fn default() -> Self {
+        Self::Png
+    }
+}
+
+impl FromStr for ImageFormat {
+    type Err = AkaibuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "png" => Ok(Self::Png),
+            "bmp" => Ok(Self::Bmp),
+            _ => Err(AkaibuError::Custom(format!(
+                "Unknown image format: {}, expected png or bmp",
+                s
+            ))),
+        }
+    }
+}
+
+/// Provenance stamped into converted PNG images, so files of shared dumps
+/// can be traced back to entry they were converted from
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImageMetadata {
+    /// Name of source archive
+    pub archive: String,
+    /// Path of entry inside archive
+    pub entry: String,
+}
+
+impl ImageMetadata {
+    /// PNG keyword and text of each field, akaibu version is written as
+    /// standard `Software` keyword
+    pub fn text_chunks(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("Software", format!("akaibu {}", env!("CARGO_PKG_VERSION"))),
+            ("Archive", self.archive.clone()),
+            ("Entry", self.entry.clone()),
+        ]
+    }
+}
+
 #[derive(Debug, Clone)]
 pub enum ResourceType {
     SpriteSheet { sprites: Vec<RgbaImage> },
+    Animation { frames: Vec<RgbaImage> },
     RgbaImage { image: RgbaImage },
     Text(String),
     Other,
 }
 
 impl ResourceType {
-    pub fn write_resource(self, file_name: &Path) -> anyhow::Result<()> {
+    pub fn write_resource(
+        self,
+        file_name: &Path,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        self.write_resource_as(file_name, ImageFormat::Png)
+    }
+    /// Write resource with images in given format. Returns paths of written
+    /// files, as extension is replaced and sprites get own files. Sprites
+    /// and frames are saved in parallel, paths are returned in their order.
+    pub fn write_resource_as(
+        self,
+        file_name: &Path,
+        image_format: ImageFormat,
+    ) -> anyhow::Result<Vec<PathBuf>> {
+        self.write_resource_stamped(file_name, image_format, None)
+    }
+    /// Same as [`Self::write_resource_as`], stamping metadata into every
+    /// written image
+    pub fn write_resource_stamped(
+        self,
+        file_name: &Path,
+        image_format: ImageFormat,
+        metadata: Option<&ImageMetadata>,
+    ) -> anyhow::Result<Vec<PathBuf>> {
         match self {
             ResourceType::RgbaImage { image } => {
                 let mut new_file_name = file_name.to_path_buf();
-                new_file_name.set_extension("png");
-                image.save(new_file_name)?;
-                Ok(())
+                new_file_name.set_extension(image_format.extension());
+                image_format.save_with_metadata(
+                    &image,
+                    &new_file_name,
+                    metadata,
+                )?;
+                Ok(vec![new_file_name])
             }
             ResourceType::Text(s) => {
                 let mut new_file_name = file_name.to_path_buf();
                 new_file_name.set_extension("txt");
-                File::create(new_file_name)?.write_all(s.as_bytes())?;
-                Ok(())
+                File::create(&new_file_name)?.write_all(s.as_bytes())?;
+                Ok(vec![new_file_name])
             }
-            ResourceType::Other => Ok(()),
-            ResourceType::SpriteSheet { mut sprites } => {
+            ResourceType::Other => Ok(vec![]),
+            ResourceType::SpriteSheet { mut sprites }
+            | ResourceType::Animation {
+                frames: mut sprites,
+            } => {
                 if sprites.len() == 1 {
                     let image = sprites.remove(0);
                     let mut new_file_name = file_name.to_path_buf();
-                    new_file_name.set_extension("png");
-                    image.save(new_file_name)?;
-                } else {
-                    for (i, sprite) in sprites.iter().enumerate() {
+                    new_file_name.set_extension(image_format.extension());
+                    image_format.save_with_metadata(
+                        &image,
+                        &new_file_name,
+                        metadata,
+                    )?;
+                    return Ok(vec![new_file_name]);
+                }
+                let stem = file_name
+                    .file_stem()
+                    .context("Could not get file name")?
+                    .to_str()
+                    .context("Not valid UTF-8")?;
+                let written = (0..sprites.len())
+                    .map(|i| {
                         let mut new_file_name = file_name.to_path_buf();
-                        new_file_name.set_file_name(format!(
-                            "{}_{}",
-                            new_file_name
-                                .file_stem()
-                                .context("Could not get file name")?
-                                .to_str()
-                                .context("Not valid UTF-8")?,
-                            i
-                        ));
-                        new_file_name.set_extension("png");
-                        sprite.save(&new_file_name)?;
-                    }
+                        new_file_name.set_file_name(format!("{}_{}", stem, i));
+                        new_file_name.set_extension(image_format.extension());
+                        new_file_name
+                    })
+                    .collect::<Vec<PathBuf>>();
+                sprites.par_iter().zip(written.par_iter()).try_for_each(
+                    |(sprite, new_file_name)| {
+                        image_format.save_with_metadata(
+                            sprite,
+                            new_file_name,
+                            metadata,
+                        )
+                    },
+                )?;
+                Ok(written)
+            }
+        }
+    }
+    /// Resource with every image downscaled so its larger side is at most
+    /// `max_dimension`, for previews and thumbnails. Smaller images and
+    /// resources that are not images are kept as they are.
+    pub fn downscaled(self, max_dimension: u32) -> Self {
+        let downscale_all = |images: Vec<RgbaImage>| -> Vec<RgbaImage> {
+            images
+                .into_par_iter()
+                .map(|image| util::image::downscale(image, max_dimension))
+                .collect()
+        };
+        match self {
+            ResourceType::RgbaImage { image } => ResourceType::RgbaImage {
+                image: util::image::downscale(image, max_dimension),
+            },
+            ResourceType::SpriteSheet { sprites } => {
+                ResourceType::SpriteSheet {
+                    sprites: downscale_all(sprites),
                 }
-                Ok(())
             }
+            ResourceType::Animation { frames } => ResourceType::Animation {
+                frames: downscale_all(frames),
+            },
+            ResourceType::Text(_) | ResourceType::Other => self,
+        }
+    }
+    /// Width and height of first image, None for non image resources
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match self {
+            ResourceType::SpriteSheet { sprites: images }
+            | ResourceType::Animation { frames: images } => {
+                images.first().map(|image| image.dimensions())
+            }
+            ResourceType::RgbaImage { image } => Some(image.dimensions()),
+            ResourceType::Text(_) | ResourceType::Other => None,
+        }
+    }
+    /// Format resource is written in by [`Self::write_resource_as`],
+    /// `none` when nothing is written
+    pub fn output_format(&self, image_format: ImageFormat) -> &'static str {
+        match self {
+            ResourceType::SpriteSheet { .. }
+            | ResourceType::Animation { .. }
+            | ResourceType::RgbaImage { .. } => image_format.extension(),
+            ResourceType::Text(_) => "txt",
+            ResourceType::Other => "none",
+        }
+    }
+}
+
+/// Properties of converted resource, used to verify scheme selection
+#[derive(Debug, Clone)]
+pub struct ConvertedMeta {
+    /// Name of scheme that converted resource
+    pub scheme: String,
+    /// Detected resource format
+    pub format: String,
+    /// Width and height of first image, None for non image resources
+    pub dimensions: Option<(u32, u32)>,
+    /// Number of sprites or animation frames
+    pub frames: usize,
+    /// Bits per pixel of decoded image
+    pub bits_per_pixel: Option<u8>,
+    pub decode_time: Duration,
+}
+
+impl ConvertedMeta {
+    pub fn new(
+        scheme: &dyn ResourceScheme,
+        format: &ResourceMagic,
+        resource: &ResourceType,
+        decode_time: Duration,
+    ) -> Self {
+        let images = match resource {
+            ResourceType::SpriteSheet { sprites }
+            | ResourceType::Animation { frames: sprites } => &sprites[..],
+            ResourceType::RgbaImage { image } => std::slice::from_ref(image),
+            ResourceType::Text(_) | ResourceType::Other => &[],
+        };
+        let first = images.first();
+        Self {
+            scheme: scheme.get_name(),
+            format: format!("{:?}", format),
+            dimensions: first.map(|image| image.dimensions()),
+            frames: images.len(),
+            bits_per_pixel: first
+                .map(|_| <Rgba<u8> as Pixel>::CHANNEL_COUNT * 8),
+            decode_time,
         }
     }
 }
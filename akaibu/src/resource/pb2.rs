@@ -0,0 +1,158 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{
+    archive,
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{bounds, lzss_decompress},
+};
+use anyhow::Context;
+use image::{buffer::ConvertBuffer, ImageBuffer};
+use scroll::{Pread, LE};
+use std::{fs::File, io::Read, path::Path};
+
+#[derive(Debug, Pread)]
+struct Pb2Header {
+    magic: [u8; 4],
+    file_size: u32,
+    main_type: u16,
+    width: u16,
+    height: u16,
+    depth: u16,
+    data_offset: u32,
+}
+
+impl ReportHeader for Pb2Header {}
+
+/// Kaguya images used before PB3. Header is not encrypted and holds type
+/// before dimensions. Type 1 stores blue, green, red and optional alpha as
+/// separate LZSS packed planes, type 2 packs interleaved pixels of rows
+/// stored bottom-up into single LZSS stream. Every packed block is preceded
+/// by its size.
+#[derive(Debug, Clone)]
+pub(crate) enum Pb2Scheme {
+    Universal,
+}
+
+impl ResourceScheme for Pb2Scheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let mut buf = Vec::with_capacity(1 << 20);
+        let mut file = File::open(file_path)?;
+        file.read_to_end(&mut buf)?;
+        self.from_bytes(buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(buf)
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let buf = std::fs::read(file_path)?;
+        Ok(vec![buf.pread_with::<Pb2Header>(0, LE)?.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[PB2] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl Pb2Scheme {
+    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+        let header = buf.pread_with::<Pb2Header>(0, LE)?;
+        log::debug!("PB2 header: {:?}", header);
+        let channels = match header.depth {
+            24 => 3,
+            32 => 4,
+            _ => {
+                return Err(AkaibuError::Unimplemented(format!(
+                    "PB2 with {} bits per pixel",
+                    header.depth
+                ))
+                .into())
+            }
+        };
+        let width = header.width as usize;
+        let height = header.height as usize;
+        let pixel_count = width * height;
+        if pixel_count == 0 {
+            return Err(bounds::malformed("empty PB2 image").into());
+        }
+        let off = &mut (header.data_offset as usize);
+        let mut pixels = bounds::output_buffer(pixel_count * 4)?;
+        if channels == 3 {
+            pixels.iter_mut().skip(3).step_by(4).for_each(|a| *a = 0xFF);
+        }
+        match header.main_type {
+            1 => {
+                for channel in 0..channels {
+                    let plane = read_packed(&buf, off, pixel_count)?;
+                    pixels
+                        .iter_mut()
+                        .skip(channel)
+                        .step_by(4)
+                        .zip(plane)
+                        .for_each(|(pixel, value)| *pixel = value);
+                }
+            }
+            2 => {
+                let row_size = width * channels;
+                let data = read_packed(&buf, off, row_size * height)?;
+                for (y, row) in data.chunks_exact(row_size).rev().enumerate() {
+                    for (x, pixel) in row.chunks_exact(channels).enumerate() {
+                        let dest = (y * width + x) * 4;
+                        pixels[dest..dest + channels].copy_from_slice(pixel);
+                    }
+                }
+            }
+            _ => {
+                return Err(AkaibuError::UnsupportedVersion {
+                    scheme: "PB2".to_string(),
+                    found: header.main_type.to_string(),
+                    expected: "1, 2".to_string(),
+                }
+                .into())
+            }
+        }
+        let image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
+            ImageBuffer::from_vec(
+                header.width as u32,
+                header.height as u32,
+                pixels,
+            )
+            .context("Invalid image resolution")?;
+        Ok(ResourceType::RgbaImage {
+            image: image.convert(),
+        })
+    }
+}
+
+/// Size prefixed LZSS block unpacking to given size
+fn read_packed(
+    buf: &[u8],
+    off: &mut usize,
+    unpacked_size: usize,
+) -> anyhow::Result<Vec<u8>> {
+    let packed_size = buf.gread_with::<u32>(off, LE)? as usize;
+    let packed = buf
+        .get(*off..)
+        .and_then(|data| data.get(..packed_size))
+        .ok_or_else(|| bounds::malformed("PB2 data out of bounds"))?;
+    *off += packed_size;
+    lzss_decompress(packed, unpacked_size)
+}
@@ -1,5 +1,9 @@
 use super::{jbp1::jbp1_decompress, ResourceScheme, ResourceType};
-use crate::{archive, error::AkaibuError};
+use crate::{
+    archive,
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+};
 use anyhow::Context;
 use image::{buffer::ConvertBuffer, ImageBuffer, RgbaImage};
 use scroll::{Pread, LE};
@@ -19,6 +23,8 @@ struct Header {
     depth: u16,
 }
 
+impl ReportHeader for Header {}
+
 impl ResourceScheme for Pb3bScheme {
     fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
         let mut buf = Vec::with_capacity(1 << 20);
@@ -34,6 +40,12 @@ impl ResourceScheme for Pb3bScheme {
     ) -> anyhow::Result<ResourceType> {
         self.from_bytes(buf)
     }
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = std::fs::read(file_path)?;
+        Self::decrypt(&mut buf)?;
+        Ok(vec![buf.pread_with::<Header>(0x18, LE)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[PB3B] {}",
@@ -61,10 +73,11 @@ impl Pb3bScheme {
             5 => Self::decode_v5(&mut buf, &header),
             6 => Self::decode_v6(&mut buf, &header),
             _ => {
-                return Err(AkaibuError::Unimplemented(format!(
-                    "PB3 version {} is not supported",
-                    header.main_type
-                ))
+                return Err(AkaibuError::UnsupportedVersion {
+                    scheme: "PB3".to_string(),
+                    found: header.main_type.to_string(),
+                    expected: "1, 2, 3, 5, 6".to_string(),
+                }
                 .into())
             }
         }?;
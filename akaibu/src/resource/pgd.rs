@@ -1,5 +1,5 @@
 use crate::{
-    archive::{self, FileEntry},
+    archive::{self, Archive, DirectoryArchive, FileEntry},
     error::AkaibuError,
     util::simd::{packuswb0, paddw, psrlw, psubb, punpcklbw0},
 };
@@ -121,10 +121,11 @@ fn ge_image(buf: Vec<u8>) -> anyhow::Result<(Vec<u8>, u32, u32)> {
     let off = &mut 0;
     let header = buf.gread::<GeHeader>(off)?;
     if header.version != 3 {
-        return Err(AkaibuError::Custom(format!(
-            "Unsupported version for GE image {}",
-            header.version
-        ))
+        return Err(AkaibuError::UnsupportedVersion {
+            scheme: "PGD GE image".to_string(),
+            found: header.version.to_string(),
+            expected: "3".to_string(),
+        }
         .into());
     }
 
@@ -140,8 +141,8 @@ fn ge_image(buf: Vec<u8>) -> anyhow::Result<(Vec<u8>, u32, u32)> {
     Ok((pixel_data, header.width, header.height))
 }
 
-// TODO: Add possibility for getting parent image from archive/file system so formats like this,
-// expecting child image layering on top of parent image work.
+/// Child image layered on top of parent image looked up in archive,
+/// or in the same folder when converting loose file
 fn pgd3_image(
     buf: Vec<u8>,
     archive: Option<&Box<dyn archive::Archive>>,
@@ -160,40 +161,19 @@ fn pgd3_image(
     )?
     .to_uppercase();
 
+    let parent_entry = FileEntry {
+        file_name: parent_name.clone(),
+        full_path: parent_name.into(),
+        file_offset: 0,
+        file_size: 0,
+    };
     let parent = match archive {
-        Some(archive) => ge_image(
-            archive
-                .extract(&FileEntry {
-                    file_name: parent_name.clone(),
-                    full_path: parent_name.clone().into(),
-                    file_offset: 0,
-                    file_size: 0,
-                })?
-                .contents
-                .to_vec(),
-        )?,
+        Some(archive) => archive.extract(&parent_entry)?,
         None => {
-            let mut path = file_path
-                .parent()
-                .context("Invalid path: At root dir")?
-                .to_path_buf();
-            path.push(&parent_name);
-            match File::open(path) {
-                Ok(mut file) => {
-                    let mut buf = Vec::with_capacity(1 << 20);
-                    file.read_to_end(&mut buf)?;
-                    ge_image(buf)?
-                }
-                Err(_) => {
-                    return Err(AkaibuError::Custom(format!(
-                        "Could not find parent file: {}",
-                        parent_name
-                    ))
-                    .into())
-                }
-            }
+            DirectoryArchive::containing(file_path)?.extract(&parent_entry)?
         }
     };
+    let parent = ge_image(parent.contents.to_vec())?;
 
     let mut parent_image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
         ImageBuffer::from_vec(parent.1, parent.2, parent.0)
@@ -1,4 +1,4 @@
-use crate::archive;
+use crate::{archive, util::bounds};
 
 use super::{ResourceScheme, ResourceType};
 use libwebp_image::webp_load_from_memory;
@@ -19,6 +19,8 @@ struct PnaHeader {
     entry_count: u32,
 }
 
+const ENTRY_SIZE: usize = 40;
+
 #[derive(Debug, Pread)]
 struct PnaEntry {
     typ: u32,
@@ -78,6 +80,10 @@ impl PnaScheme {
     ) -> anyhow::Result<ResourceType> {
         let off = &mut 0;
         let header = buf.gread_with::<PnaHeader>(off, LE)?;
+        // Checked before allocating, count comes from file
+        if header.entry_count as usize > (buf.len() - *off) / ENTRY_SIZE {
+            return Err(bounds::malformed("PNA entry count").into());
+        }
         let mut entries = Vec::with_capacity(header.entry_count as usize);
         for _ in 0..header.entry_count {
             let entry = buf.gread_with::<PnaEntry>(off, LE)?;
@@ -88,12 +94,15 @@ impl PnaScheme {
         let mut images = Vec::with_capacity(header.entry_count as usize);
         for entry in entries.iter() {
             let size = entry.size as usize;
+            let data = buf
+                .get(*off..*off + size)
+                .ok_or_else(|| bounds::malformed("PNA entry size"))?;
             let image = match &header.magic {
                 b"PNAP" => image::load_from_memory_with_format(
-                    &buf[*off..*off + size],
+                    data,
                     image::ImageFormat::Png,
                 )?,
-                b"WPAP" => webp_load_from_memory(&buf[*off..*off + size])?,
+                b"WPAP" => webp_load_from_memory(data)?,
                 _ => {
                     return Err(crate::error::AkaibuError::Custom(format!(
                         "Unsupported format {} {:X?}",
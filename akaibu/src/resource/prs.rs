@@ -0,0 +1,173 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{archive, error::AkaibuError, util::bounds};
+use anyhow::Context;
+use image::{buffer::ConvertBuffer, ImageBuffer};
+use scroll::{Pread, LE};
+use std::{fs::File, io::Read, path::Path};
+
+const PRS_HEADER_SIZE: usize = 0x10;
+
+#[derive(Debug, Clone)]
+pub(crate) enum PrsScheme {
+    Universal,
+}
+
+#[derive(Debug, Pread)]
+struct PrsHeader {
+    magic: [u8; 2],
+    flags: u8,
+    bytes_per_pixel: u8,
+    packed_size: u32,
+    unk0: u32,
+    width: u16,
+    height: u16,
+}
+
+impl ResourceScheme for PrsScheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let mut buf = Vec::with_capacity(1 << 20);
+        let mut file = File::open(file_path)?;
+        file.read_to_end(&mut buf)?;
+        self.from_bytes(buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(buf)
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[PRS] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl PrsScheme {
+    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+        let header = buf.pread_with::<PrsHeader>(0, LE)?;
+        log::debug!("Header: {:#?}", header);
+        let bytes_per_pixel = header.bytes_per_pixel as usize;
+        if bytes_per_pixel != 3 && bytes_per_pixel != 4 {
+            return Err(AkaibuError::Unimplemented(format!(
+                "Unsupported PRS bytes per pixel: {}",
+                bytes_per_pixel
+            ))
+            .into());
+        }
+        let mut pixels = decompress(
+            buf.get(PRS_HEADER_SIZE..).context("Out of bounds access")?,
+            header.width as usize * header.height as usize * bytes_per_pixel,
+        )?;
+        if header.flags & 0x80 != 0 {
+            for i in bytes_per_pixel..pixels.len() {
+                let prev = pixels[i - bytes_per_pixel];
+                pixels[i] = pixels[i].wrapping_add(prev);
+            }
+        }
+        if bytes_per_pixel == 4 {
+            let image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
+                ImageBuffer::from_vec(
+                    header.width as u32,
+                    header.height as u32,
+                    pixels,
+                )
+                .context("Invalid image resolution")?;
+            Ok(ResourceType::RgbaImage {
+                image: image.convert(),
+            })
+        } else {
+            let image: ImageBuffer<image::Bgr<u8>, Vec<u8>> =
+                ImageBuffer::from_vec(
+                    header.width as u32,
+                    header.height as u32,
+                    pixels,
+                )
+                .context("Invalid image resolution")?;
+            Ok(ResourceType::RgbaImage {
+                image: image.convert(),
+            })
+        }
+    }
+}
+
+/// LZ77 variant used by Marble engine
+pub(crate) fn decompress(
+    src: &[u8],
+    dest_len: usize,
+) -> anyhow::Result<Vec<u8>> {
+    let mut dest = bounds::output_buffer(dest_len)?;
+    let src_off = &mut 0;
+    let mut dest_off = 0;
+    let mut bit = 0u8;
+    let mut control = 0u8;
+    while dest_off < dest_len {
+        bit >>= 1;
+        if bit == 0 {
+            control = src.gread::<u8>(src_off)?;
+            bit = 0x80;
+        }
+        if control & bit == 0 {
+            dest[dest_off] = src.gread::<u8>(src_off)?;
+            dest_off += 1;
+            continue;
+        }
+        let b = src.gread::<u8>(src_off)? as usize;
+        let (mut offset, count) = if b & 0x80 != 0 {
+            let low = src.gread::<u8>(src_off)? as usize;
+            let offset = ((b & 0x3F) << 8) | low;
+            if b & 0x40 != 0 {
+                let count = match src.gread::<u8>(src_off)? {
+                    0xFE => 0x400,
+                    0xFF => 0x1000,
+                    x => x as usize + 3,
+                };
+                (offset, count)
+            } else {
+                (offset >> 4, (offset & 0xF) + 3)
+            }
+        } else {
+            let count = b >> 2;
+            if b & 3 == 3 {
+                // Uncompressed run
+                let count = count + 9;
+                let run = src
+                    .get(*src_off..*src_off + count)
+                    .context("Out of bounds access")?;
+                dest.get_mut(dest_off..dest_off + count)
+                    .context("Out of bounds access")?
+                    .copy_from_slice(run);
+                *src_off += count;
+                dest_off += count;
+                continue;
+            }
+            (count, (b & 3) + 2)
+        };
+        offset += 1;
+        if offset > dest_off || dest_off + count > dest_len {
+            return Err(AkaibuError::Custom(
+                "Invalid PRS back reference".to_string(),
+            )
+            .into());
+        }
+        for _ in 0..count {
+            dest[dest_off] = dest[dest_off - offset];
+            dest_off += 1;
+        }
+    }
+    Ok(dest)
+}
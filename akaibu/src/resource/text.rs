@@ -0,0 +1,48 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{archive, util::text};
+use std::{fs::File, io::Read, path::Path};
+
+#[derive(Debug, Clone)]
+pub(crate) enum TextScheme {
+    Universal,
+}
+
+impl ResourceScheme for TextScheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let mut buf = Vec::with_capacity(1 << 20);
+        let mut file = File::open(file_path)?;
+        file.read_to_end(&mut buf)?;
+        self.from_bytes(buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(buf)
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[TEXT] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl TextScheme {
+    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+        Ok(ResourceType::Text(text::normalize(&buf)))
+    }
+}
@@ -1,14 +1,58 @@
 use super::{ResourceScheme, ResourceType};
-use crate::{archive, error::AkaibuError};
-use scroll::Pread;
-use std::{fs::File, io::Read, path::Path};
+use crate::{archive, error::AkaibuError, util::bounds};
+use anyhow::Context;
+use image::RgbaImage;
+use scroll::{Pread, LE};
+use std::{fs::File, io::Read, path::Path, str::FromStr};
 use tlg_rs::formats::{tlg0::Tlg0, tlg6::Tlg6};
 
+const TLG5_MAGIC: &[u8] = b"TLG5.0\x00raw\x1a";
+/// LZSS dictionary is shared by every block and channel of image
+const TLG5_DICTIONARY_SIZE: usize = 4096;
+/// Rows of block written by encoder, same as KiriKiri uses
+const TLG5_BLOCK_HEIGHT: usize = 4;
+const TLG5_MIN_MATCH: usize = 3;
+const TLG5_MAX_MATCH: usize = 18 + 0xFF;
+const TLG5_HASH_SIZE: usize = 1 << 13;
+
 #[derive(Debug, Clone)]
-pub(crate) enum TlgScheme {
+pub enum TlgScheme {
     Universal,
 }
 
+/// Version of TLG written by [`TlgScheme::encode`]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TlgVersion {
+    Tlg5,
+}
+
+impl FromStr for TlgVersion {
+    type Err = AkaibuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "5" | "tlg5" => Ok(Self::Tlg5),
+            _ => Err(AkaibuError::Custom(format!(
+                "Unsupported TLG version: {}, only tlg5 can be written",
+                s
+            ))),
+        }
+    }
+}
+
+impl TlgScheme {
+    /// Encode image as TLG, so edited images can be packed back into
+    /// archive. Alpha channel is written only when image is not opaque.
+    pub fn encode(
+        image: &RgbaImage,
+        version: TlgVersion,
+    ) -> anyhow::Result<Vec<u8>> {
+        match version {
+            TlgVersion::Tlg5 => encode_tlg5(image),
+        }
+    }
+}
+
 impl ResourceScheme for TlgScheme {
     fn convert(&self, file_path: &Path) -> anyhow::Result<super::ResourceType> {
         let mut buf = Vec::with_capacity(1 << 20);
@@ -46,14 +90,341 @@ impl ResourceScheme for TlgScheme {
 fn parse_tlg(buf: Vec<u8>) -> anyhow::Result<ResourceType> {
     let image = match buf.pread::<u8>(3)? - 0x30 {
         0 => Tlg0::from_bytes(&buf)?.to_rgba_image()?,
+        5 => decode_tlg5(&buf)?,
         6 => Tlg6::from_bytes(&buf)?.to_rgba_image()?,
         ver => {
-            return Err(AkaibuError::Unimplemented(format!(
-                "Version: {} is not supported",
-                ver
-            ))
+            return Err(AkaibuError::UnsupportedVersion {
+                scheme: "TLG".to_string(),
+                found: ver.to_string(),
+                expected: "0, 5, 6".to_string(),
+            }
             .into())
         }
     };
     Ok(ResourceType::RgbaImage { image })
 }
+
+/// Image is split into blocks of rows, each channel of block is stored raw
+/// or LZSS compressed. Values are deltas from left and upper pixel, with
+/// blue and red also relative to green.
+fn decode_tlg5(buf: &[u8]) -> anyhow::Result<RgbaImage> {
+    if !buf.starts_with(TLG5_MAGIC) {
+        return Err(
+            AkaibuError::Custom("Invalid TLG5 header".to_string()).into()
+        );
+    }
+    let mut off = TLG5_MAGIC.len();
+    let channels = buf.gread::<u8>(&mut off)? as usize;
+    let width = buf.gread_with::<u32>(&mut off, LE)? as usize;
+    let height = buf.gread_with::<u32>(&mut off, LE)? as usize;
+    let block_height = buf.gread_with::<u32>(&mut off, LE)? as usize;
+    if channels != 1 && channels != 3 && channels != 4 {
+        return Err(AkaibuError::Unimplemented(format!(
+            "TLG5 with {} channels is not supported, expected 1, 3 or 4",
+            channels
+        ))
+        .into());
+    }
+    if width == 0 || height == 0 || block_height == 0 {
+        return Err(bounds::malformed("TLG5 image size").into());
+    }
+    let block_height = block_height.min(height);
+    // Sizes of blocks, not needed when reading them in order
+    off += ((height - 1) / block_height + 1) * 4;
+
+    let mut image = bounds::output_buffer(width * height * 4)?;
+    let mut blocks = (0..channels)
+        .map(|_| bounds::output_buffer(width * block_height))
+        .collect::<Result<Vec<Vec<u8>>, AkaibuError>>()?;
+    let mut dictionary = [0; TLG5_DICTIONARY_SIZE];
+    let mut dictionary_index = 0;
+    // Channel values of row above, updated in place
+    let mut previous_row = vec![[0u8; 4]; width];
+    for block_y in (0..height).step_by(block_height) {
+        let rows = block_height.min(height - block_y);
+        for block in blocks.iter_mut() {
+            let mark = buf.gread::<u8>(&mut off)?;
+            let size = buf.gread_with::<u32>(&mut off, LE)? as usize;
+            let data = buf
+                .get(off..off + size)
+                .context("TLG5 block out of bounds")?;
+            off += size;
+            if mark == 0 {
+                tlg5_decompress(
+                    data,
+                    &mut block[..width * rows],
+                    &mut dictionary,
+                    &mut dictionary_index,
+                )?;
+            } else {
+                let len = data.len().min(block.len());
+                block[..len].copy_from_slice(&data[..len]);
+            }
+        }
+        for y in 0..rows {
+            let mut left = [0u8; 4];
+            for (x, color) in previous_row.iter_mut().enumerate() {
+                let mut delta = [0u8; 4];
+                for (channel, block) in blocks.iter().enumerate() {
+                    delta[channel] = block[y * width + x];
+                }
+                if channels >= 3 {
+                    delta[0] = delta[0].wrapping_add(delta[1]);
+                    delta[2] = delta[2].wrapping_add(delta[1]);
+                }
+                // Unused channels stay zero
+                for ((left, value), delta) in
+                    left.iter_mut().zip(color.iter_mut()).zip(&delta)
+                {
+                    *left = left.wrapping_add(*delta);
+                    *value = value.wrapping_add(*left);
+                }
+                let pixel = match channels {
+                    1 => [color[0], color[0], color[0], 0xFF],
+                    3 => [color[2], color[1], color[0], 0xFF],
+                    _ => [color[2], color[1], color[0], color[3]],
+                };
+                let index = ((block_y + y) * width + x) * 4;
+                image[index..index + 4].copy_from_slice(&pixel);
+            }
+        }
+    }
+    RgbaImage::from_raw(width as u32, height as u32, image)
+        .context("Invalid image resolution")
+}
+
+/// Dictionary and its position carry over between calls, so matches can
+/// reach data of previous channels and blocks
+fn tlg5_decompress(
+    src: &[u8],
+    dest: &mut [u8],
+    dictionary: &mut [u8; TLG5_DICTIONARY_SIZE],
+    dictionary_index: &mut usize,
+) -> Result<(), AkaibuError> {
+    let mut flags = 0u32;
+    let mut src_index = 0;
+    let mut dest_index = 0;
+    while src_index < src.len() {
+        flags >>= 1;
+        if flags & 0x100 == 0 {
+            flags = bounds::read(src, src_index)? as u32 | 0xFF00;
+            src_index += 1;
+        }
+        if flags & 1 != 0 {
+            let low = bounds::read(src, src_index)? as usize;
+            let high = bounds::read(src, src_index + 1)? as usize;
+            src_index += 2;
+            let mut position = low | (high & 0xF) << 8;
+            let mut length = (high >> 4) + 3;
+            if length == 18 {
+                length += bounds::read(src, src_index)? as usize;
+                src_index += 1;
+            }
+            for _ in 0..length {
+                let value = dictionary[position];
+                bounds::write(dest, dest_index, value)?;
+                dest_index += 1;
+                dictionary[*dictionary_index] = value;
+                *dictionary_index =
+                    (*dictionary_index + 1) % TLG5_DICTIONARY_SIZE;
+                position = (position + 1) % TLG5_DICTIONARY_SIZE;
+            }
+        } else {
+            let value = bounds::read(src, src_index)?;
+            src_index += 1;
+            bounds::write(dest, dest_index, value)?;
+            dest_index += 1;
+            dictionary[*dictionary_index] = value;
+            *dictionary_index = (*dictionary_index + 1) % TLG5_DICTIONARY_SIZE;
+        }
+    }
+    Ok(())
+}
+
+fn encode_tlg5(image: &RgbaImage) -> anyhow::Result<Vec<u8>> {
+    let width = image.width() as usize;
+    let height = image.height() as usize;
+    if width == 0 || height == 0 {
+        return Err(AkaibuError::Custom(
+            "Can not encode empty image as TLG5".to_string(),
+        )
+        .into());
+    }
+    let channels = if image.pixels().all(|pixel| pixel[3] == 0xFF) {
+        3
+    } else {
+        4
+    };
+    let block_count = (height - 1) / TLG5_BLOCK_HEIGHT + 1;
+
+    let mut blocks = Vec::with_capacity(block_count);
+    let mut compressor = Tlg5Compressor::new();
+    // Channel values of row above, in B, G, R, A order
+    let mut previous_row = vec![[0u8; 4]; width];
+    for block_y in (0..height).step_by(TLG5_BLOCK_HEIGHT) {
+        let rows = TLG5_BLOCK_HEIGHT.min(height - block_y);
+        let mut channel_data = vec![Vec::with_capacity(width * rows); channels];
+        for y in block_y..block_y + rows {
+            // Difference from upper pixel of previous pixel in row
+            let mut left = [0u8; 4];
+            for (x, color) in previous_row.iter_mut().enumerate() {
+                let pixel = image.get_pixel(x as u32, y as u32);
+                let value = [pixel[2], pixel[1], pixel[0], pixel[3]];
+                let mut delta = [0u8; 4];
+                for channel in 0..4 {
+                    let up = value[channel].wrapping_sub(color[channel]);
+                    delta[channel] = up.wrapping_sub(left[channel]);
+                    left[channel] = up;
+                }
+                delta[0] = delta[0].wrapping_sub(delta[1]);
+                delta[2] = delta[2].wrapping_sub(delta[1]);
+                for (data, delta) in channel_data.iter_mut().zip(&delta) {
+                    data.push(*delta);
+                }
+                *color = value;
+            }
+        }
+        let mut block = Vec::new();
+        for data in channel_data.iter() {
+            // Compressed data is kept only when it is smaller, raw data does
+            // not go through dictionary
+            let mut candidate = compressor.clone();
+            let compressed = candidate.compress(data);
+            if compressed.len() < data.len() {
+                compressor = candidate;
+                block.push(0);
+                block.extend_from_slice(
+                    &(compressed.len() as u32).to_le_bytes(),
+                );
+                block.extend_from_slice(&compressed);
+            } else {
+                block.push(1);
+                block.extend_from_slice(&(data.len() as u32).to_le_bytes());
+                block.extend_from_slice(data);
+            }
+        }
+        blocks.push(block);
+    }
+
+    let mut buf = Vec::with_capacity(
+        TLG5_MAGIC.len()
+            + 13
+            + blocks.iter().map(|b| 4 + b.len()).sum::<usize>(),
+    );
+    buf.extend_from_slice(TLG5_MAGIC);
+    buf.push(channels as u8);
+    buf.extend_from_slice(&(width as u32).to_le_bytes());
+    buf.extend_from_slice(&(height as u32).to_le_bytes());
+    buf.extend_from_slice(&(TLG5_BLOCK_HEIGHT as u32).to_le_bytes());
+    for block in blocks.iter() {
+        buf.extend_from_slice(&(block.len() as u32).to_le_bytes());
+    }
+    for block in blocks.iter() {
+        buf.extend_from_slice(block);
+    }
+    Ok(buf)
+}
+
+/// Greedy LZSS encoder keeping same dictionary as [`tlg5_decompress`].
+/// Candidate matches are found by hash of their first three bytes and
+/// checked against dictionary, so stale hash entries are harmless.
+#[derive(Clone)]
+struct Tlg5Compressor {
+    dictionary: [u8; TLG5_DICTIONARY_SIZE],
+    dictionary_index: usize,
+    /// Dictionary position of last sequence with given hash
+    positions: Vec<Option<u16>>,
+}
+
+impl Tlg5Compressor {
+    fn new() -> Self {
+        Self {
+            dictionary: [0; TLG5_DICTIONARY_SIZE],
+            dictionary_index: 0,
+            positions: vec![None; TLG5_HASH_SIZE],
+        }
+    }
+
+    fn compress(&mut self, src: &[u8]) -> Vec<u8> {
+        let mut dest = Vec::with_capacity(src.len() + src.len() / 8 + 1);
+        let mut flags_index = 0;
+        let mut flag_bit = 8;
+        let mut index = 0;
+        while index < src.len() {
+            if flag_bit == 8 {
+                flags_index = dest.len();
+                dest.push(0);
+                flag_bit = 0;
+            }
+            let (position, length) = self.find_match(&src[index..]);
+            if length >= TLG5_MIN_MATCH {
+                dest[flags_index] |= 1 << flag_bit;
+                let nibble = (length - TLG5_MIN_MATCH).min(0xF);
+                dest.push(position as u8);
+                dest.push((position >> 8) as u8 | (nibble << 4) as u8);
+                if nibble == 0xF {
+                    dest.push((length - 18) as u8);
+                }
+            } else {
+                dest.push(src[index]);
+            }
+            let length = length.max(1);
+            for offset in 0..length {
+                self.push(&src[index + offset..]);
+            }
+            index += length;
+            flag_bit += 1;
+        }
+        dest
+    }
+
+    /// Longest match for start of `src`, length 0 when there is none
+    fn find_match(&self, src: &[u8]) -> (usize, usize) {
+        if src.len() < TLG5_MIN_MATCH {
+            return (0, 0);
+        }
+        let position = match self.positions[Self::hash(src)] {
+            Some(position) => position as usize,
+            None => return (0, 0),
+        };
+        let max_length = src.len().min(TLG5_MAX_MATCH);
+        let mut length = 0;
+        while length < max_length {
+            let dictionary_position =
+                (position + length) % TLG5_DICTIONARY_SIZE;
+            // Decoder copies byte by byte, so match can read bytes it has
+            // just written
+            let written = (dictionary_position + TLG5_DICTIONARY_SIZE
+                - self.dictionary_index)
+                % TLG5_DICTIONARY_SIZE;
+            let value = if written < length {
+                src[written]
+            } else {
+                self.dictionary[dictionary_position]
+            };
+            if value != src[length] {
+                break;
+            }
+            length += 1;
+        }
+        (position, length)
+    }
+
+    /// Write first byte of `src` into dictionary, remembering position of
+    /// sequence starting with it
+    fn push(&mut self, src: &[u8]) {
+        if src.len() >= TLG5_MIN_MATCH {
+            self.positions[Self::hash(src)] =
+                Some(self.dictionary_index as u16);
+        }
+        self.dictionary[self.dictionary_index] = src[0];
+        self.dictionary_index =
+            (self.dictionary_index + 1) % TLG5_DICTIONARY_SIZE;
+    }
+
+    fn hash(src: &[u8]) -> usize {
+        let value =
+            (src[0] as usize) << 16 | (src[1] as usize) << 8 | src[2] as usize;
+        value.wrapping_mul(2_654_435_761) >> 11 & (TLG5_HASH_SIZE - 1)
+    }
+}
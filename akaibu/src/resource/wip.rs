@@ -0,0 +1,196 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{
+    archive,
+    error::AkaibuError,
+    util::{bounds, image::resolve_color_table},
+};
+use anyhow::Context;
+use image::{buffer::ConvertBuffer, ImageBuffer, RgbaImage};
+use scroll::{Pread, LE};
+use std::{fs::File, io::Read, path::Path};
+
+/// Magic, frame count and bits per pixel
+const HEADER_SIZE: usize = 8;
+const FRAME_SIZE: usize = 0x18;
+/// BGRX palette of 8bpp frames, stored before frame data
+const PALETTE_SIZE: usize = 0x400;
+const WINDOW_SIZE: usize = 0x1000;
+
+#[derive(Debug, Pread)]
+struct WipFrame {
+    width: u32,
+    height: u32,
+    offset_x: i32,
+    offset_y: i32,
+    layer: u32,
+    data_size: u32,
+}
+
+/// ExHibit images. Every frame is LZSS compressed, 8bpp frames use own
+/// palette and 24bpp frames are stored as separate blue, green and red
+/// planes. Alpha is kept in separate mask image.
+#[derive(Debug, Clone)]
+pub(crate) enum WipScheme {
+    Universal,
+}
+
+impl ResourceScheme for WipScheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let mut buf = Vec::with_capacity(1 << 20);
+        let mut file = File::open(file_path)?;
+        file.read_to_end(&mut buf)?;
+        self.from_bytes(buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(buf)
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[WIP] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl WipScheme {
+    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+        let frame_count = buf.pread_with::<u16>(4, LE)? as usize;
+        let bpp = buf.pread_with::<u16>(6, LE)?;
+        if bpp != 8 && bpp != 24 {
+            return Err(AkaibuError::Unimplemented(format!(
+                "WIP with {} bits per pixel",
+                bpp
+            ))
+            .into());
+        }
+        let frames = (0..frame_count)
+            .map(|i| {
+                buf.pread_with::<WipFrame>(HEADER_SIZE + i * FRAME_SIZE, LE)
+            })
+            .collect::<Result<Vec<WipFrame>, scroll::Error>>()?;
+        log::debug!("WIP {}bpp frames: {:?}", bpp, frames);
+
+        let mut off = HEADER_SIZE + frame_count * FRAME_SIZE;
+        let mut images = Vec::with_capacity(frame_count);
+        for frame in &frames {
+            let palette = if bpp == 8 {
+                let palette = buf
+                    .get(off..off + PALETTE_SIZE)
+                    .ok_or_else(|| bounds::malformed("WIP palette"))?;
+                off += PALETTE_SIZE;
+                Some(palette)
+            } else {
+                None
+            };
+            let data = buf
+                .get(off..off + frame.data_size as usize)
+                .ok_or_else(|| bounds::malformed("WIP frame data"))?;
+            off += frame.data_size as usize;
+            images.push(decode_frame(frame, palette, data)?);
+        }
+        if images.len() == 1 {
+            Ok(ResourceType::RgbaImage {
+                image: images.remove(0),
+            })
+        } else {
+            Ok(ResourceType::SpriteSheet { sprites: images })
+        }
+    }
+}
+
+fn decode_frame(
+    frame: &WipFrame,
+    palette: Option<&[u8]>,
+    data: &[u8],
+) -> anyhow::Result<RgbaImage> {
+    let pixel_count = (frame.width as usize)
+        .checked_mul(frame.height as usize)
+        .ok_or_else(|| bounds::malformed("image size overflow"))?;
+    let pixels = match palette {
+        Some(palette) => {
+            let indices = decompress(data, pixel_count)?;
+            let mut pixels = resolve_color_table(&indices, palette);
+            // Palette alpha byte is unused
+            pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 0xFF);
+            pixels
+        }
+        None => {
+            let planes_size = pixel_count
+                .checked_mul(3)
+                .ok_or_else(|| bounds::malformed("image size overflow"))?;
+            let planes = decompress(data, planes_size)?;
+            let (blue, rest) = planes.split_at(pixel_count);
+            let (green, red) = rest.split_at(pixel_count);
+            let mut pixels = Vec::with_capacity(pixel_count * 4);
+            for ((b, g), r) in blue.iter().zip(green).zip(red) {
+                pixels.extend_from_slice(&[*b, *g, *r, 0xFF]);
+            }
+            pixels
+        }
+    };
+    let image: ImageBuffer<image::Bgra<u8>, Vec<u8>> =
+        ImageBuffer::from_vec(frame.width, frame.height, pixels)
+            .context("Invalid image resolution")?;
+    Ok(image.convert())
+}
+
+/// LZSS with 4KB window starting at position 1. Set control bit marks
+/// literal byte, otherwise 12-bit window position and 4-bit length follow.
+fn decompress(src: &[u8], dest_len: usize) -> Result<Vec<u8>, AkaibuError> {
+    let mut dest = bounds::output_buffer(dest_len)?;
+    let mut window = [0u8; WINDOW_SIZE];
+    let mut window_pos = 1;
+    let mut i = 0;
+    let mut j = 0;
+    while j < dest_len {
+        let control = bounds::read(src, i)?;
+        i += 1;
+        for bit in 0..8 {
+            if j >= dest_len {
+                break;
+            }
+            if control & (1 << bit) != 0 {
+                let b = bounds::read(src, i)?;
+                i += 1;
+                bounds::write(&mut dest, j, b)?;
+                j += 1;
+                window[window_pos] = b;
+                window_pos = (window_pos + 1) & (WINDOW_SIZE - 1);
+            } else {
+                let hi = bounds::read(src, i)? as usize;
+                let lo = bounds::read(src, i + 1)? as usize;
+                i += 2;
+                let mut offset = (hi << 4) | (lo >> 4);
+                let count = (lo & 0xF) + 2;
+                for _ in 0..count {
+                    if j >= dest_len {
+                        break;
+                    }
+                    let b = window[offset];
+                    offset = (offset + 1) & (WINDOW_SIZE - 1);
+                    bounds::write(&mut dest, j, b)?;
+                    j += 1;
+                    window[window_pos] = b;
+                    window_pos = (window_pos + 1) & (WINDOW_SIZE - 1);
+                }
+            }
+        }
+    }
+    Ok(dest)
+}
@@ -70,10 +70,11 @@ impl YcgScheme {
                     image: image.convert(),
                 })
             }
-            _ => Err(AkaibuError::Unimplemented(format!(
-                "Unsupported YCG version {}",
-                version,
-            ))
+            _ => Err(AkaibuError::UnsupportedVersion {
+                scheme: "YCG".to_string(),
+                found: version.to_string(),
+                expected: "1".to_string(),
+            }
             .into()),
         }
     }
@@ -0,0 +1,97 @@
+use super::{ResourceScheme, ResourceType};
+use crate::{
+    archive,
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::bounds,
+};
+use scroll::{Pread, LE};
+use std::{fs::File, io::Read, path::Path};
+
+const PNG_SIGNATURE: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+#[derive(Debug, Pread)]
+struct YkgHeader {
+    magic: [u8; 8],
+    header_size: u32,
+    reserved: [u32; 7],
+    data_offset: u32,
+    data_size: u32,
+}
+
+impl ReportHeader for YkgHeader {}
+
+/// Xuse and Terios images. Header points to PNG stored after it, with
+/// `PNG` of its signature displaced to `GNP` so viewers do not pick it up.
+#[derive(Debug, Clone)]
+pub(crate) enum YkgScheme {
+    Universal,
+}
+
+impl ResourceScheme for YkgScheme {
+    fn convert(&self, file_path: &Path) -> anyhow::Result<ResourceType> {
+        let mut buf = Vec::with_capacity(1 << 20);
+        let mut file = File::open(file_path)?;
+        file.read_to_end(&mut buf)?;
+        self.from_bytes(buf)
+    }
+
+    fn convert_from_bytes(
+        &self,
+        _file_path: &Path,
+        buf: Vec<u8>,
+        _archive: Option<&Box<dyn archive::Archive>>,
+    ) -> anyhow::Result<ResourceType> {
+        self.from_bytes(buf)
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let buf = std::fs::read(file_path)?;
+        Ok(vec![buf.pread_with::<YkgHeader>(0, LE)?.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[YKG] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn ResourceScheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl YkgScheme {
+    fn from_bytes(&self, buf: Vec<u8>) -> anyhow::Result<ResourceType> {
+        let header = buf.pread_with::<YkgHeader>(0, LE)?;
+        log::debug!("YKG header: {:?}", header);
+        let mut data = buf
+            .get(header.data_offset as usize..)
+            .and_then(|data| data.get(..header.data_size as usize))
+            .ok_or_else(|| bounds::malformed("YKG data out of bounds"))?
+            .to_vec();
+        if data.get(1..4) == Some(&b"GNP"[..]) {
+            data[1..4].copy_from_slice(b"PNG");
+        }
+        if !data.starts_with(PNG_SIGNATURE) {
+            return Err(AkaibuError::Unimplemented(format!(
+                "YKG with data {:02X?}",
+                &data[..data.len().min(4)]
+            ))
+            .into());
+        }
+        Ok(ResourceType::RgbaImage {
+            image: image::load_from_memory_with_format(
+                &data,
+                image::ImageFormat::Png,
+            )?
+            .to_rgba8(),
+        })
+    }
+}
@@ -1,7 +1,8 @@
 use super::Scheme;
 use crate::{
     archive::{self, FileContents},
-    util::{crc64, zlib_decompress},
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, crc64, text::display_name, zlib_decompress},
 };
 use anyhow::Context;
 use bytes::{Bytes, BytesMut};
@@ -41,10 +42,8 @@ impl Scheme for Acv1Scheme {
         sjis_file_names.lines().for_each(|l| {
             hashes.insert(crc64(&SHIFT_JIS.encode(&l).0), l);
         });
-        let mut buf = vec![0; 4];
-        let file = RandomAccessFile::open(file_path)?;
-        file.read_exact_at(4, &mut buf)?;
-        let entries_count = buf.pread_with::<u32>(0, LE)? ^ MASTER_KEY;
+        let file = util::fs::open_archive_file(file_path)?;
+        let entries_count = read_header(&file)?.entry_count;
         let mut buf = vec![0; 4 + entries_count as usize * 21];
         file.read_exact_at(8, &mut buf)?;
 
@@ -62,6 +61,11 @@ impl Scheme for Acv1Scheme {
             navigable_dir,
         ))
     }
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        Ok(vec![
+            read_header(&util::fs::open_archive_file(file_path)?)?.report()
+        ])
+    }
     fn get_name(&self) -> String {
         format!(
             "[ACV1] {}",
@@ -103,6 +107,24 @@ impl Acv1Scheme {
     }
 }
 
+#[derive(Debug, Pread)]
+struct Acv1Header {
+    magic: [u8; 4],
+    /// Stored XORed with [`MASTER_KEY`]
+    entry_count: u32,
+}
+
+impl ReportHeader for Acv1Header {}
+
+/// Header at start of archive with entry count decrypted
+fn read_header(file: &RandomAccessFile) -> anyhow::Result<Acv1Header> {
+    let mut buf = vec![0; 8];
+    file.read_exact_at(0, &mut buf)?;
+    let mut header = buf.pread_with::<Acv1Header>(0, LE)?;
+    header.entry_count ^= MASTER_KEY;
+    Ok(header)
+}
+
 #[derive(Debug)]
 struct Acv1Archive {
     file: RandomAccessFile,
@@ -128,15 +150,15 @@ impl archive::Archive for Acv1Archive {
         self.archive.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -148,28 +170,17 @@ impl archive::Archive for Acv1Archive {
 
 impl Acv1Archive {
     fn new_root_dir(entries: &[Acv1Entry]) -> archive::Directory {
-        archive::Directory::new(
+        archive::Directory::from_entries(
             entries
                 .iter()
                 .filter(|entry| entry.extractable)
                 .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: String::from(
-                            entry
-                                .full_path
-                                .file_name()
-                                .expect("No file name")
-                                .to_str()
-                                .expect("Not valid UTF-8"),
-                        ),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
+                    archive::FileEntry::new(
+                        entry.full_path.clone(),
+                        entry.file_offset as u64,
+                        entry.file_size as u64,
+                    )
+                }),
         )
     }
     fn extract(&self, entry: &Acv1Entry) -> anyhow::Result<FileContents> {
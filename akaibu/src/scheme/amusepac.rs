@@ -1,11 +1,14 @@
 use super::Scheme;
-use crate::archive::{self, FileContents};
+use crate::{
+    archive::{self, FileContents},
+    inspect::{ParsedHeader, ReportHeader},
+    util,
+};
 use anyhow::Context;
 use bytes::BytesMut;
 use positioned_io_preview::{RandomAccessFile, ReadAt};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{ctx, Pread, LE};
-use std::{fs::File, io::Write, path::PathBuf};
+use std::path::PathBuf;
 
 #[derive(Debug, Clone)]
 pub enum PacScheme {
@@ -21,7 +24,7 @@ impl Scheme for PacScheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 14];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
         let header = buf.pread_with::<PacHeader>(0, LE)?;
         log::debug!("Header: {:#?}", header);
@@ -41,6 +44,15 @@ impl Scheme for PacScheme {
         Ok((Box::new(PacArchive { file, file_entries }), navigable_dir))
     }
 
+    fn inspect(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 14];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<PacHeader>(0, LE)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[AMUSE PAC] {}",
@@ -76,49 +88,50 @@ impl archive::Archive for PacArchive {
             .context("File not found")?
     }
 
-    fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
-        self.file_entries.par_iter().try_for_each(|entry| {
-            let file_contents = self.extract(entry)?;
-            let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
-            std::fs::create_dir_all(
-                &output_file_name
-                    .parent()
-                    .context("Could not get parent directory")?,
-            )?;
-            log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
-                entry
-            );
-            File::create(output_file_name)?
-                .write_all(&file_contents.contents)?;
-            Ok(())
+    fn extract_prefix(
+        &self,
+        entry: &archive::FileEntry,
+        len: usize,
+    ) -> anyhow::Result<archive::FileContents> {
+        let len = len.min(entry.file_size as usize);
+        let mut buf = BytesMut::with_capacity(len);
+        buf.resize(len, 0);
+        self.file.read_exact_at(entry.file_offset, &mut buf)?;
+        Ok(FileContents {
+            contents: buf.freeze(),
+            type_hint: None,
         })
     }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset, e.file_size as u64))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.file_entries).files().cloned(),
+            output_path,
+        )
+    }
 }
 
 impl PacArchive {
     fn new_root_dir(entries: &[PacFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: entry
-                            .full_path
-                            .to_str()
-                            .expect("Not valid UTF-8")
-                            .to_string(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &PacFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -139,6 +152,8 @@ struct PacHeader {
     entries_count: u32,
 }
 
+impl ReportHeader for PacHeader {}
+
 #[derive(Debug)]
 struct PacFileEntry {
     file_size: u32,
@@ -1,16 +1,26 @@
-use super::Scheme;
-use crate::archive::{self, FileContents};
+use super::{pack::PackInput, Scheme};
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    resource::ResourceMagic,
+    util::{self, bounds, text::display_name},
+};
 use anyhow::Context;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use encoding_rs::SHIFT_JIS;
 use positioned_io::{RandomAccessFile, ReadAt};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{ctx, Pread, LE};
 use std::{convert::TryInto, fs::File, io::Write, path::PathBuf};
 
-const BURIKO_ENTRY_SIZE: usize = 0x80;
-const BURIKO_ENTRY_NAME_SIZE: usize = 0x60;
+pub mod writer;
+
 const SOUND_FILE_MAGIC: &[u8] = b"bw  ";
+const PACKFILE_MAGIC: &[u8] = b"PackFile    ";
+const DSC_MAGIC: &[u8] = b"DSC FORMAT 1.00\0";
+const DSC_HEADER_SIZE: usize = 0x20;
+const DSC_CODE_COUNT: usize = 0x200;
 
 #[derive(Debug, Clone)]
 pub enum BurikoScheme {
@@ -26,13 +36,14 @@ impl Scheme for BurikoScheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 16];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
 
         let header = buf.pread::<BurikoHeader>(0)?;
         log::debug!("Header: {:#?}", header);
 
-        let mut buf = vec![0; header.entry_count as usize * BURIKO_ENTRY_SIZE];
+        let mut buf =
+            vec![0; header.entry_count as usize * header.layout.entry_size()];
         file.read_exact_at(16, &mut buf)?;
         let archive = buf.pread_with::<Buriko>(0, header)?;
         log::debug!("Archive: {:#?}", archive);
@@ -42,6 +53,27 @@ impl Scheme for BurikoScheme {
         Ok((Box::new(BurikoArchive { file, archive }), navigable_dir))
     }
 
+    fn inspect(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 16];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread::<BurikoHeader>(0)?.report()])
+    }
+
+    fn can_pack(&self) -> bool {
+        true
+    }
+
+    fn write_archive(
+        &self,
+        inputs: Vec<PackInput>,
+        mut output: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        writer::write_arc20(inputs, &mut output)
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[BURIKO] {}",
@@ -78,19 +110,38 @@ impl archive::Archive for BurikoArchive {
             .context("File not found")?
     }
 
+    fn raw_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        Some((
+            self.archive.header.file_contents_offset + entry.file_offset,
+            entry.file_size,
+        ))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn decode_raw(
+        &self,
+        _entry: &archive::FileEntry,
+        raw: Bytes,
+    ) -> anyhow::Result<FileContents> {
+        Self::decode(raw)
+    }
+
     fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
         self.archive.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -102,28 +153,13 @@ impl archive::Archive for BurikoArchive {
 
 impl BurikoArchive {
     fn new_root_dir(entries: &[BurikoFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: String::from(
-                            entry
-                                .full_path
-                                .file_name()
-                                .expect("No file name")
-                                .to_str()
-                                .expect("Not valid UTF-8"),
-                        ),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &BurikoFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -132,12 +168,26 @@ impl BurikoArchive {
             self.archive.header.file_contents_offset + entry.file_offset as u64,
             &mut buf,
         )?;
-        if buf.get(4..8).context("Out of bounds access")? == SOUND_FILE_MAGIC {
-            buf = buf.split_off(0x40);
-        }
+        Self::decode(buf.freeze())
+    }
+    /// Sound entries start with header not part of audio data, scripts and
+    /// images may be packed with DSC
+    fn decode(raw: Bytes) -> anyhow::Result<FileContents> {
+        let contents = if raw.starts_with(DSC_MAGIC) {
+            Bytes::from(dsc_decompress(&raw)?)
+        } else if raw.get(4..8) == Some(SOUND_FILE_MAGIC) {
+            raw.slice(0x40..)
+        } else {
+            raw
+        };
+        let type_hint = match ResourceMagic::parse_magic(&contents) {
+            #[cfg(feature = "fmt-compressedbg")]
+            ResourceMagic::CompressedBg => Some(ResourceMagic::CompressedBg),
+            _ => None,
+        };
         Ok(FileContents {
-            contents: buf.freeze(),
-            type_hint: None,
+            contents,
+            type_hint,
         })
     }
 }
@@ -158,7 +208,7 @@ impl<'a> ctx::TryFromCtx<'a, BurikoHeader> for Buriko {
         let off = &mut 0;
         let mut file_entries = Vec::with_capacity(header.entry_count as usize);
         for _ in 0..header.entry_count {
-            file_entries.push(buf.gread(off)?);
+            file_entries.push(buf.gread_with(off, header.layout)?);
         }
         Ok((
             Self {
@@ -170,14 +220,40 @@ impl<'a> ctx::TryFromCtx<'a, BurikoHeader> for Buriko {
     }
 }
 
+/// Index layout, older `PackFile` archives still used for system images
+/// have shorter entries
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Layout {
+    PackFile,
+    Arc20,
+}
+
+impl Layout {
+    fn entry_size(self) -> usize {
+        match self {
+            Self::PackFile => 0x20,
+            Self::Arc20 => 0x80,
+        }
+    }
+    fn name_size(self) -> usize {
+        match self {
+            Self::PackFile => 0x10,
+            Self::Arc20 => 0x60,
+        }
+    }
+}
+
 #[derive(Debug, Copy, Clone)]
 struct BurikoHeader {
     magic: [u8; 10],
     version: u16,
+    layout: Layout,
     entry_count: u32,
     file_contents_offset: u64,
 }
 
+impl ReportHeader for BurikoHeader {}
+
 impl<'a> ctx::TryFromCtx<'a, scroll::Endian> for BurikoHeader {
     type Error = anyhow::Error;
 
@@ -189,20 +265,27 @@ impl<'a> ctx::TryFromCtx<'a, scroll::Endian> for BurikoHeader {
         let magic: [u8; 10] =
             buf.get(0..10).context("Out of bounds access")?.try_into()?;
         *off += magic.len();
-        let version = String::from_utf8(
-            buf.get(*off..*off + 2)
-                .context("Out of bounds access")?
-                .to_vec(),
-        )?
-        .parse()?;
+        let (version, layout) = if buf.starts_with(PACKFILE_MAGIC) {
+            // No version digits, magic is padded with spaces instead
+            (0, Layout::PackFile)
+        } else {
+            let version = String::from_utf8(
+                buf.get(*off..*off + 2)
+                    .context("Out of bounds access")?
+                    .to_vec(),
+            )?
+            .parse()?;
+            (version, Layout::Arc20)
+        };
         *off += 2;
         let entry_count = buf.gread_with::<u32>(off, LE)?;
         let file_contents_offset =
-            0x10 + entry_count as u64 * BURIKO_ENTRY_SIZE as u64;
+            0x10 + entry_count as u64 * layout.entry_size() as u64;
         Ok((
             Self {
                 magic,
                 version,
+                layout,
                 entry_count,
                 file_contents_offset,
             },
@@ -216,21 +299,21 @@ struct BurikoFileEntry {
     full_path: PathBuf,
     file_offset: u32,
     file_size: u32,
-    unknown: [u8; 18],
+    unknown: Vec<u8>,
 }
 
-impl<'a> ctx::TryFromCtx<'a, scroll::Endian> for BurikoFileEntry {
+impl<'a> ctx::TryFromCtx<'a, Layout> for BurikoFileEntry {
     type Error = anyhow::Error;
 
     fn try_from_ctx(
         buf: &'a [u8],
-        _: scroll::Endian,
+        layout: Layout,
     ) -> Result<(Self, usize), Self::Error> {
         let off = &mut 0;
         let full_path = PathBuf::from(
             SHIFT_JIS
                 .decode(
-                    buf.get(*off..*off + BURIKO_ENTRY_NAME_SIZE)
+                    buf.get(*off..*off + layout.name_size())
                         .context("Out of bounds access")?
                         .split(|b| *b == 0)
                         .next()
@@ -239,14 +322,13 @@ impl<'a> ctx::TryFromCtx<'a, scroll::Endian> for BurikoFileEntry {
                 .0
                 .to_string(),
         );
-        *off += BURIKO_ENTRY_NAME_SIZE;
+        *off += layout.name_size();
         let file_offset = buf.gread_with::<u32>(off, LE)?;
         let file_size = buf.gread_with::<u32>(off, LE)?;
-        let unknown: [u8; 18] = buf
-            .get(*off..*off + 18)
+        let unknown = buf
+            .get(*off..layout.entry_size())
             .context("Out of bounds access")?
-            .try_into()?;
-        *off += unknown.len();
+            .to_vec();
         Ok((
             Self {
                 full_path,
@@ -254,7 +336,139 @@ impl<'a> ctx::TryFromCtx<'a, scroll::Endian> for BurikoFileEntry {
                 file_size,
                 unknown,
             },
-            BURIKO_ENTRY_SIZE,
+            layout.entry_size(),
         ))
     }
 }
+
+/// Huffman coded LZ used for scripts and images. Code lengths are xored
+/// with PRNG seeded by key from header.
+fn dsc_decompress(src: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let magic = (src.pread_with::<u16>(0, LE)? as u32) << 16;
+    let mut key = src.pread_with::<u32>(0x10, LE)?;
+    let dest_size = src.pread_with::<u32>(0x14, LE)? as usize;
+    let code_count = src.pread_with::<u32>(0x18, LE)?;
+    let mut dest = bounds::output_buffer(dest_size)?;
+
+    let mut codes = Vec::with_capacity(DSC_CODE_COUNT);
+    for code in 0..DSC_CODE_COUNT {
+        let depth = bounds::read(src, DSC_HEADER_SIZE + code)?
+            .wrapping_sub(dsc_next_key(&mut key, magic));
+        if depth != 0 {
+            codes.push((depth, code as u16));
+        }
+    }
+    codes.sort_unstable();
+    let tree = dsc_huffman_tree(&codes)?;
+
+    let mut bits = MsbBitStream::new(src, DSC_HEADER_SIZE + DSC_CODE_COUNT);
+    let mut dest_index = 0;
+    for _ in 0..code_count {
+        let mut node = 0;
+        let code = loop {
+            node = match tree.get(node) {
+                Some(DscNode::Parent(left, right)) => {
+                    if bits.read(1)? == 0 {
+                        *left
+                    } else {
+                        *right
+                    }
+                }
+                Some(DscNode::Leaf(code)) => break *code,
+                None => return Err(bounds::malformed("DSC code tree").into()),
+            };
+        };
+        if code < 0x100 {
+            bounds::write(&mut dest, dest_index, code as u8)?;
+            dest_index += 1;
+        } else {
+            let offset = bits.read(12)? as usize + 2;
+            let count = (code & 0xFF) as usize + 2;
+            let start = dest_index
+                .checked_sub(offset)
+                .ok_or_else(|| bounds::malformed("DSC back reference"))?;
+            for i in 0..count {
+                let byte = bounds::read(&dest, start + i)?;
+                bounds::write(&mut dest, dest_index + i, byte)?;
+            }
+            dest_index += count;
+        }
+    }
+    Ok(dest)
+}
+
+fn dsc_next_key(key: &mut u32, magic: u32) -> u8 {
+    let v0 = 20021u32.wrapping_mul(*key & 0xFFFF);
+    let v1 = (magic | (*key >> 16))
+        .wrapping_mul(20021)
+        .wrapping_add(key.wrapping_mul(346))
+        .wrapping_add(v0 >> 16)
+        & 0xFFFF;
+    *key = (v1 << 16).wrapping_add(v0 & 0xFFFF).wrapping_add(1);
+    v1 as u8
+}
+
+#[derive(Debug, Clone, Copy)]
+enum DscNode {
+    Parent(usize, usize),
+    Leaf(u16),
+}
+
+/// Canonical tree from codes sorted by depth, each depth first places its
+/// leaves and then splits remaining nodes into children
+fn dsc_huffman_tree(codes: &[(u8, u16)]) -> Result<Vec<DscNode>, AkaibuError> {
+    let mut tree = vec![DscNode::Leaf(0)];
+    let mut depth_nodes = vec![0];
+    let mut n = 0;
+    let mut depth = 0;
+    while n < codes.len() {
+        if depth_nodes.is_empty() {
+            return Err(bounds::malformed("DSC code lengths"));
+        }
+        let mut nodes = depth_nodes.iter();
+        while n < codes.len() && codes[n].0 == depth {
+            let node = nodes
+                .next()
+                .ok_or_else(|| bounds::malformed("DSC code lengths"))?;
+            tree[*node] = DscNode::Leaf(codes[n].1);
+            n += 1;
+        }
+        let mut children = Vec::new();
+        for node in nodes {
+            let left = tree.len();
+            tree.push(DscNode::Leaf(0));
+            tree.push(DscNode::Leaf(0));
+            tree[*node] = DscNode::Parent(left, left + 1);
+            children.extend_from_slice(&[left, left + 1]);
+        }
+        depth_nodes = children;
+        depth += 1;
+    }
+    Ok(tree)
+}
+
+/// Reads bits starting from most significant bit of each byte
+struct MsbBitStream<'a> {
+    buf: &'a [u8],
+    off: usize,
+    bit: u8,
+}
+
+impl<'a> MsbBitStream<'a> {
+    fn new(buf: &'a [u8], off: usize) -> Self {
+        Self { buf, off, bit: 0 }
+    }
+    fn read(&mut self, bits: usize) -> Result<u32, AkaibuError> {
+        let mut ret = 0;
+        for _ in 0..bits {
+            let byte = bounds::read(self.buf, self.off)?;
+            ret = (ret << 1) | ((byte >> (7 - self.bit)) & 1) as u32;
+            self.bit += 1;
+            if self.bit == 8 {
+                self.bit = 0;
+                self.off += 1;
+            }
+        }
+        Ok(ret)
+    }
+}
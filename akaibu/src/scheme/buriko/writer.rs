@@ -0,0 +1,69 @@
+use super::Layout;
+pub use crate::scheme::pack::{read_directory, PackInput};
+use crate::{error::AkaibuError, scheme::pack};
+use encoding_rs::SHIFT_JIS;
+use std::{convert::TryFrom, io::Write};
+
+const HEADER_SIZE: usize = 0x10;
+
+/// Write BURIKO ARC20 archive with entries stored in order of inputs right
+/// after index. Entries are written as given, DSC packing and header of
+/// sound entries removed on extraction are not restored. Archive has no
+/// directories, so every input has to be plain file name.
+pub fn write_arc20(
+    inputs: Vec<PackInput>,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    let layout = Layout::Arc20;
+    let mut header =
+        Vec::with_capacity(HEADER_SIZE + inputs.len() * layout.entry_size());
+    header.extend_from_slice(b"BURIKO ARC20");
+    header.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    // Offsets are relative to data following index
+    let mut offset = 0u64;
+    for input in &inputs {
+        let name = encode_name(&pack::path_name(&input.full_path, "/")?)?;
+        let file_offset = u32::try_from(offset).map_err(|_| {
+            AkaibuError::Custom(format!(
+                "Archive is too large to hold {:?}",
+                input.full_path
+            ))
+        })?;
+        let entry_start = header.len();
+        header.extend_from_slice(&name);
+        header.resize(entry_start + layout.name_size(), 0);
+        header.extend_from_slice(&file_offset.to_le_bytes());
+        header.extend_from_slice(&(input.contents.len() as u32).to_le_bytes());
+        header.resize(entry_start + layout.entry_size(), 0);
+        offset += input.contents.len() as u64;
+    }
+    output.write_all(&header)?;
+    for input in &inputs {
+        output.write_all(&input.contents)?;
+    }
+    Ok(())
+}
+
+/// SHIFT-JIS name, zero terminated within name field
+fn encode_name(name: &str) -> anyhow::Result<Vec<u8>> {
+    if name.contains('/') {
+        return Err(AkaibuError::Custom(format!(
+            "BURIKO ARC20 entries can not be in directories: {}",
+            name
+        ))
+        .into());
+    }
+    let (encoded, _, had_errors) = SHIFT_JIS.encode(name);
+    if had_errors
+        || encoded.is_empty()
+        || encoded.len() >= Layout::Arc20.name_size()
+    {
+        return Err(AkaibuError::Custom(format!(
+            "Name {:?} does not fit into {} bytes of SHIFT-JIS",
+            name,
+            Layout::Arc20.name_size() - 1
+        ))
+        .into());
+    }
+    Ok(encoded.into_owned())
+}
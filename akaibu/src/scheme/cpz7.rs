@@ -1,7 +1,9 @@
-use super::Scheme;
+use super::{registry::SchemeRegistry, Scheme};
 use crate::{
     archive::{self, FileContents},
-    util::md5,
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, md5, text::display_name},
 };
 use anyhow::Context;
 use bytes::{Buf, BufMut, Bytes, BytesMut};
@@ -16,6 +18,12 @@ use std::{
     path::{Path, PathBuf},
 };
 
+pub(crate) const KEYS_PATHS: [&str; 3] = [
+    "cpz7/aoitori.json",
+    "cpz7/realive.json",
+    "cpz7/seishun.json",
+];
+
 /// Used to decrypt header fields
 const HEADER_KEYS: [u32; 12] = [
     0xFE3A53DA, 0x37F298E8, 0x7A6F3A2D, 0x43DE7C1A, 0xCC65F416, 0xD016A93D,
@@ -46,10 +54,8 @@ impl Scheme for Cpz7Scheme {
         file_path: &Path,
     ) -> anyhow::Result<(Box<dyn archive::Archive>, archive::NavigableDirectory)>
     {
-        let mut buf = vec![0; 68];
-        let file = RandomAccessFile::open(file_path)?;
-        file.read_exact_at(4, &mut buf)?;
-        let cpz_header = buf.pread::<Cpz7Header>(0)?;
+        let file = util::fs::open_archive_file(file_path)?;
+        let cpz_header = read_header(&file)?;
 
         let mut buf = vec![
             0;
@@ -59,16 +65,26 @@ impl Scheme for Cpz7Scheme {
         ];
         file.read_exact_at(72, &mut buf)?;
         let all_game_keys = self.get_game_keys()?;
-        let game_keys = *all_game_keys
-            .get(
-                file_path
-                    .file_name()
-                    .context("Could not get file name")?
-                    .to_str()
-                    .context("Could not parse OsStr to str")?,
-            )
-            .unwrap_or(&[0, 0, 0, 0]);
-        let archive = buf.pread_with::<Cpz7>(0, (cpz_header, &game_keys))?;
+        let known_keys = all_game_keys.get(
+            file_path
+                .file_name()
+                .context("Could not get file name")?
+                .to_str()
+                .context("Could not parse OsStr to str")?,
+        );
+        let game_keys = *known_keys.unwrap_or(&[0, 0, 0, 0]);
+        // Archives not listed in keys file are tried with empty keys,
+        // failure in that case is most likely caused by wrong keys
+        let archive = buf
+            .pread_with::<Cpz7>(0, (cpz_header, &game_keys))
+            .map_err(|err| match known_keys {
+                Some(_) => err,
+                None => AkaibuError::MissingKey {
+                    scheme: self.get_name(),
+                    key_type: "game keys".to_string(),
+                }
+                .into(),
+            })?;
         log::debug!("Archive: {:#?}", archive.file_data.values());
 
         let root_dir = Cpz7Archive::new_root_dir(&archive);
@@ -82,6 +98,11 @@ impl Scheme for Cpz7Scheme {
             navigable_dir,
         ))
     }
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        Ok(vec![
+            read_header(&util::fs::open_archive_file(file_path)?)?.report()
+        ])
+    }
     fn get_name(&self) -> String {
         format!(
             "[CPZ7] {}",
@@ -106,23 +127,11 @@ impl Scheme for Cpz7Scheme {
 
 impl Cpz7Scheme {
     fn get_game_keys(&self) -> anyhow::Result<HashMap<String, [u32; 4]>> {
-        Ok(match self {
-            Cpz7Scheme::AoiTori => serde_json::from_slice(
-                &crate::Resources::get("cpz7/aoitori.json").context(
-                    format!("Could not find file: {}", "cpz7/aoitori.json"),
-                )?,
-            )?,
-            Cpz7Scheme::Realive => serde_json::from_slice(
-                &crate::Resources::get("cpz7/realive.json").context(
-                    format!("Could not find file: {}", "cpz7/realive.json"),
-                )?,
-            )?,
-            Cpz7Scheme::SeishunFragile => serde_json::from_slice(
-                &crate::Resources::get("cpz7/seishun.json").context(
-                    format!("Could not find file: {}", "cpz7/seishun.json"),
-                )?,
-            )?,
-        })
+        Ok(SchemeRegistry::global().load(match self {
+            Cpz7Scheme::AoiTori => KEYS_PATHS[0],
+            Cpz7Scheme::Realive => KEYS_PATHS[1],
+            Cpz7Scheme::SeishunFragile => KEYS_PATHS[2],
+        })?)
     }
 }
 
@@ -147,6 +156,22 @@ impl archive::Archive for Cpz7Archive {
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_data
+            .values()
+            .flatten()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| {
+                let header = &self.archive.header;
+                let raw_file_data_off = header.archive_data_size as u64
+                    + header.file_data_size as u64
+                    + header.encryption_data_size as u64
+                    + 0x48;
+                (raw_file_data_off + e.file_offset as u64, e.file_size as u64)
+            })
+    }
+
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
         // TODO parallelize that
         self.archive
@@ -156,15 +181,15 @@ impl archive::Archive for Cpz7Archive {
             .try_for_each(|entry| {
                 let file_contents = self.extract(entry)?;
                 let mut output_file_name = PathBuf::from(output_path);
-                output_file_name.push(&entry.full_path);
+                output_file_name.push(archive::output_path(&entry.full_path));
                 std::fs::create_dir_all(
                     &output_file_name
                         .parent()
                         .context("Could not get parent directory")?,
                 )?;
                 log::debug!(
-                    "Extracting resource: {:?} {:X?}",
-                    output_file_name,
+                    "Extracting resource: {} {:X?}",
+                    display_name(&output_file_name),
                     entry
                 );
                 File::create(output_file_name)?
@@ -176,29 +201,14 @@ impl archive::Archive for Cpz7Archive {
 
 impl Cpz7Archive {
     fn new_root_dir(archive: &Cpz7) -> archive::Directory {
-        archive::Directory::new(
-            archive
-                .file_data
-                .values()
-                .flatten()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: String::from(
-                            entry
-                                .full_path
-                                .file_name()
-                                .expect("No file name")
-                                .to_str()
-                                .expect("Not valid UTF-8"),
-                        ),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
+        archive::Directory::from_entries(
+            archive.file_data.values().flatten().map(|entry| {
+                archive::FileEntry::new(
+                    entry.full_path.clone(),
+                    entry.file_offset as u64,
+                    entry.file_size as u64,
+                )
+            }),
         )
     }
     fn extract(&self, entry: &FileEntry) -> anyhow::Result<FileContents> {
@@ -338,6 +348,13 @@ impl<'a> ctx::TryFromCtx<'a, (Cpz7Header, &[u32; 4])> for Cpz7 {
     }
 }
 
+/// Header following magic, with fields decrypted by fixed keys
+fn read_header(file: &RandomAccessFile) -> anyhow::Result<Cpz7Header> {
+    let mut buf = vec![0; 68];
+    file.read_exact_at(4, &mut buf)?;
+    buf.pread::<Cpz7Header>(0)
+}
+
 #[derive(Debug, Copy, Clone)]
 struct Cpz7Header {
     archive_data_entry_count: u32,
@@ -353,6 +370,8 @@ struct Cpz7Header {
     header_checksum: u32,
 }
 
+impl ReportHeader for Cpz7Header {}
+
 impl<'a> ctx::TryFromCtx<'a, scroll::Endian> for Cpz7Header {
     type Error = anyhow::Error;
     fn try_from_ctx(
@@ -1,18 +1,22 @@
-use crate::archive::{self, FileContents};
+use crate::{
+    archive::{self, FileContents},
+    inspect::{ParsedHeader, ReportHeader},
+    util,
+};
 
-use super::Scheme;
+use super::{pack::PackInput, Scheme};
 use anyhow::Context;
 use bytes::BytesMut;
 use encoding_rs::SHIFT_JIS;
 use positioned_io::{RandomAccessFile, ReadAt};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{ctx, Pread, LE};
 use std::{
-    fs::File,
     io::Write,
     path::{Path, PathBuf},
 };
 
+pub mod writer;
+
 const KEY: u32 = 0x65AC9365;
 const FILE_ENTRY_SIZE: usize = 12;
 
@@ -30,7 +34,7 @@ impl Scheme for EscArc2Scheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 20];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
 
         let header = buf.pread::<EscArc2Header>(0)?;
@@ -61,6 +65,24 @@ impl Scheme for EscArc2Scheme {
         Ok((Box::new(EscArc2Archive { file, archive }), navigable_dir))
     }
 
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 20];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread::<EscArc2Header>(0)?.report()])
+    }
+
+    fn can_pack(&self) -> bool {
+        true
+    }
+
+    fn write_archive(
+        &self,
+        inputs: Vec<PackInput>,
+        mut output: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        writer::write_esc_arc2(inputs, &mut output)
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[EscArc2] {}",
@@ -96,47 +118,38 @@ impl archive::Archive for EscArc2Archive {
             .context("File not found")?
     }
 
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset as u64, e.file_size as u64))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
-        self.archive.file_entries.par_iter().try_for_each(
-            |entry| -> Result<(), anyhow::Error> {
-                let file_contents = self.extract(entry)?;
-                let mut output_file_name = PathBuf::from(output_path);
-                output_file_name.push(&entry.full_path);
-                std::fs::create_dir_all(
-                    &output_file_name
-                        .parent()
-                        .context("Could not get parent directory")?,
-                )?;
-                log::debug!(
-                    "Extracting resource: {:?} {:X?}",
-                    output_file_name,
-                    entry
-                );
-                File::create(output_file_name)?
-                    .write_all(&file_contents.contents)?;
-                Ok(())
-            },
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.archive.file_entries)
+                .files()
+                .cloned(),
+            output_path,
         )
     }
 }
 
 impl EscArc2Archive {
     fn new_root_dir(entries: &[EscArc2FileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: entry.file_name.clone(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(
         &self,
@@ -166,6 +179,9 @@ struct EscArc2Header {
     file_name_table_size: u32,
 }
 
+/// Fields decrypted from header following magic
+impl ReportHeader for EscArc2Header {}
+
 impl<'a> ctx::TryFromCtx<'a, ()> for EscArc2Header {
     type Error = anyhow::Error;
 
@@ -211,13 +227,30 @@ impl EscArc2Header {
             file_name_table_size,
         }
     }
+    /// Header fields following magic encrypted with key derived from seed,
+    /// with key of first index entry
+    fn encrypt_header(
+        seed: u32,
+        file_count: u32,
+        file_name_table_size: u32,
+    ) -> ([u32; 3], u32) {
+        let count_key = next_entry_key(seed);
+        let file_entry_key = next_entry_key(count_key);
+        (
+            [
+                seed,
+                file_count ^ count_key,
+                file_name_table_size ^ file_entry_key,
+            ],
+            file_entry_key,
+        )
+    }
 }
 
 #[derive(Debug)]
 struct EscArc2FileEntry {
     file_offset: u32,
     file_size: u32,
-    file_name: String,
     full_path: PathBuf,
 }
 
@@ -247,17 +280,10 @@ impl<'a> ctx::TryFromCtx<'a, &[u8]> for EscArc2FileEntry {
                 .to_string()
                 .replace("\\", "/"),
         );
-        let file_name = full_path
-            .file_name()
-            .context("Could not get file name")?
-            .to_str()
-            .context("Not valid UTF-8")?
-            .to_string();
         Ok((
             Self {
                 file_offset,
                 file_size,
-                file_name,
                 full_path,
             },
             *off,
@@ -271,18 +297,7 @@ fn decrypt_file_entries(
     file_name_table: &[u8],
 ) -> anyhow::Result<Vec<EscArc2FileEntry>> {
     file_entries.chunks_exact_mut(4).for_each(|chunk| {
-        file_entry_key ^= KEY;
-        let mut d = file_entry_key.wrapping_add(file_entry_key);
-        d ^= file_entry_key;
-        let mut c = file_entry_key;
-        c >>= 1;
-        d = d.wrapping_add(d);
-        c ^= file_entry_key;
-        d = d.wrapping_add(d);
-        c >>= 3;
-        d = d.wrapping_add(d);
-        c ^= d;
-        file_entry_key ^= c;
+        file_entry_key = next_entry_key(file_entry_key);
         chunk[0] ^= file_entry_key as u8;
         chunk[1] ^= (file_entry_key >> 8) as u8;
         chunk[2] ^= (file_entry_key >> 16) as u8;
@@ -295,3 +310,19 @@ fn decrypt_file_entries(
             Ok(v)
         })
 }
+
+/// Key of next 4 bytes of index, also used to encrypt header
+fn next_entry_key(mut key: u32) -> u32 {
+    key ^= KEY;
+    let mut d = key.wrapping_add(key);
+    d ^= key;
+    let mut c = key;
+    c >>= 1;
+    d = d.wrapping_add(d);
+    c ^= key;
+    d = d.wrapping_add(d);
+    c >>= 3;
+    d = d.wrapping_add(d);
+    c ^= d;
+    key ^ c
+}
@@ -0,0 +1,68 @@
+use super::{next_entry_key, EscArc2Header, FILE_ENTRY_SIZE};
+pub use crate::scheme::pack::{read_directory, PackInput};
+use crate::{error::AkaibuError, scheme::pack};
+use encoding_rs::SHIFT_JIS;
+use std::{convert::TryFrom, io::Write};
+
+const HEADER_SIZE: usize = 20;
+/// Header is encrypted with key derived from this seed. Any seed decrypts
+/// to the same header, fixed one keeps output the same for the same inputs.
+const SEED: u32 = 0x4553_4332;
+
+/// Write ESC-ARC2 archive with entries stored in order of inputs right
+/// after name table. Names are stored as SHIFT-JIS with `\` separators.
+pub fn write_esc_arc2(
+    inputs: Vec<PackInput>,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    let mut name_table = Vec::new();
+    let mut name_offsets = Vec::with_capacity(inputs.len());
+    for input in &inputs {
+        let name = pack::path_name(&input.full_path, "\\")?;
+        let (encoded, _, had_errors) = SHIFT_JIS.encode(&name);
+        if had_errors {
+            return Err(AkaibuError::Custom(format!(
+                "Name {:?} can not be encoded as SHIFT-JIS",
+                name
+            ))
+            .into());
+        }
+        name_offsets.push(name_table.len() as u32);
+        name_table.extend_from_slice(&encoded);
+        name_table.push(0);
+    }
+
+    let (header, mut entry_key) = EscArc2Header::encrypt_header(
+        SEED,
+        inputs.len() as u32,
+        name_table.len() as u32,
+    );
+    let mut offset = (HEADER_SIZE
+        + inputs.len() * FILE_ENTRY_SIZE
+        + name_table.len()) as u64;
+    let mut entries = Vec::with_capacity(inputs.len() * FILE_ENTRY_SIZE);
+    for (input, name_offset) in inputs.iter().zip(name_offsets) {
+        let file_offset = u32::try_from(offset).map_err(|_| {
+            AkaibuError::Custom(format!(
+                "Archive is too large to hold {:?}",
+                input.full_path
+            ))
+        })?;
+        for value in &[name_offset, file_offset, input.contents.len() as u32] {
+            entry_key = next_entry_key(entry_key);
+            entries.extend_from_slice(&(value ^ entry_key).to_le_bytes());
+        }
+        offset += input.contents.len() as u64;
+    }
+
+    output.write_all(b"ESC-ARC2")?;
+    for value in &header {
+        output.write_all(&value.to_le_bytes())?;
+    }
+    output.write_all(&entries)?;
+    output.write_all(&name_table)?;
+    for input in &inputs {
+        output.write_all(&input.contents)?;
+    }
+    Ok(())
+}
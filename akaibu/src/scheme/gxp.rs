@@ -1,5 +1,9 @@
-use super::Scheme;
-use crate::archive::{self, FileContents};
+use super::{pack::PackInput, Scheme};
+use crate::{
+    archive::{self, FileContents},
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name},
+};
 use anyhow::Context;
 use bytes::BytesMut;
 use positioned_io::{RandomAccessFile, ReadAt};
@@ -11,6 +15,8 @@ use std::{
     path::{Path, PathBuf},
 };
 
+pub mod writer;
+
 const PASSWORD: &[u8] = &[
     0x40, 0x21, 0x28, 0x38, 0xA6, 0x6E, 0x43, 0xA5, 0x40, 0x21, 0x28, 0x38,
     0xA6, 0x43, 0xA5, 0x64, 0x3E, 0x65, 0x24, 0x20, 0x46, 0x6E, 0x74,
@@ -28,7 +34,7 @@ impl Scheme for GxpScheme {
     ) -> anyhow::Result<(Box<dyn archive::Archive>, archive::NavigableDirectory)>
     {
         let mut buf = vec![0; 48];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
         let header = buf.pread::<GxpHeader>(0)?;
         log::debug!("Header: {:#?}", header);
@@ -42,6 +48,24 @@ impl Scheme for GxpScheme {
         let navigable_dir = archive::NavigableDirectory::new(root_dir);
         Ok((Box::new(GxpArchive { file, archive }), navigable_dir))
     }
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 48];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread::<GxpHeader>(0)?.report()])
+    }
+
+    fn can_pack(&self) -> bool {
+        true
+    }
+
+    fn write_archive(
+        &self,
+        inputs: Vec<PackInput>,
+        mut output: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        writer::write_gxp(inputs, &mut output)
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[GXP] {}",
@@ -76,19 +100,31 @@ impl archive::Archive for GxpArchive {
             .map(|e| self.extract(e))
             .context("File not found")?
     }
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| {
+                (
+                    self.archive.header.raw_file_data_offset + e.file_offset,
+                    e.file_size,
+                )
+            })
+    }
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
         self.archive.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -100,28 +136,13 @@ impl archive::Archive for GxpArchive {
 
 impl GxpArchive {
     fn new_root_dir(entries: &[GxpFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: String::from(
-                            entry
-                                .full_path
-                                .file_name()
-                                .expect("No file name")
-                                .to_str()
-                                .expect("Not valid UTF-8"),
-                        ),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset,
+                entry.file_size,
+            )
+        }))
     }
     fn extract(&self, entry: &GxpFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -129,8 +150,7 @@ impl GxpArchive {
         let buf_len = buf.len();
 
         self.file.read_exact_at(
-            self.archive.header.raw_file_data_offset as u64
-                + entry.file_offset as u64,
+            self.archive.header.raw_file_data_offset + entry.file_offset,
             &mut buf,
         )?;
         xor_data_with_password(&mut buf, buf_len, 0)?;
@@ -179,25 +199,54 @@ struct GxpHeader {
     unk5: u32,
     file_entries_count: u32,
     file_entries_size: u32,
-    raw_file_data_size: u32,
-    unk6: u32,
-    raw_file_data_offset: u32,
-    unk7: u32,
+    raw_file_data_size: u64,
+    raw_file_data_offset: u64,
+}
+
+impl ReportHeader for GxpHeader {}
+
+impl GxpHeader {
+    /// Archives with more than 4GB of data store entry sizes in 64 bits,
+    /// smaller ones keep unrelated value in upper half
+    fn has_wide_entries(&self) -> bool {
+        self.raw_file_data_size > u32::MAX as u64
+    }
 }
 
 #[derive(Debug)]
 struct GxpFileEntry {
     entry_size: u32,
-    file_size: u32,
-    unk1: u32,
+    file_size: u64,
     file_name_utf16_len: u32,
     unk2: u32,
     unk3: u32,
-    file_offset: u32,
-    unk4: u32,
+    /// Relative to raw file data, always stored in 64 bits
+    file_offset: u64,
     full_path: PathBuf,
 }
 
+impl GxpFileEntry {
+    /// Fields following entry size, same in plain and ciphered index
+    fn read_fields(
+        buf: &[u8],
+        off: &mut usize,
+        header: &GxpHeader,
+    ) -> anyhow::Result<(u64, u32, u32, u32, u64)> {
+        let file_size = buf.gread_with::<u32>(off, LE)? as u64;
+        let file_size_high = buf.gread_with::<u32>(off, LE)? as u64;
+        let file_size = if header.has_wide_entries() {
+            file_size | file_size_high << 32
+        } else {
+            file_size
+        };
+        let file_name_utf16_len = buf.gread_with::<u32>(off, LE)?;
+        let unk2 = buf.gread_with::<u32>(off, LE)?;
+        let unk3 = buf.gread_with::<u32>(off, LE)?;
+        let file_offset = buf.gread_with::<u64>(off, LE)?;
+        Ok((file_size, file_name_utf16_len, unk2, unk3, file_offset))
+    }
+}
+
 impl<'a> ctx::TryFromCtx<'a, &GxpHeader> for GxpFileEntry {
     type Error = anyhow::Error;
     fn try_from_ctx(
@@ -220,13 +269,8 @@ impl<'a> ctx::TryFromCtx<'a, &GxpHeader> for GxpFileEntry {
                 4,
             )?;
 
-            let file_size = entry_data.gread_with::<u32>(off, LE)?;
-            let unk1 = entry_data.gread_with::<u32>(off, LE)?;
-            let file_name_utf16_len = entry_data.gread_with::<u32>(off, LE)?;
-            let unk2 = entry_data.gread_with::<u32>(off, LE)?;
-            let unk3 = entry_data.gread_with::<u32>(off, LE)?;
-            let file_offset = entry_data.gread_with::<u32>(off, LE)?;
-            let unk4 = entry_data.gread_with::<u32>(off, LE)?;
+            let (file_size, file_name_utf16_len, unk2, unk3, file_offset) =
+                GxpFileEntry::read_fields(&entry_data, off, header)?;
             let utf16_string: Vec<u16> = entry_data
                 .get(*off..*off + entry_size as usize - 0x20)
                 .context("Out of bounds access")?
@@ -239,25 +283,18 @@ impl<'a> ctx::TryFromCtx<'a, &GxpHeader> for GxpFileEntry {
                 GxpFileEntry {
                     entry_size,
                     file_size,
-                    unk1,
                     file_name_utf16_len,
                     unk2,
                     unk3,
                     file_offset,
-                    unk4,
                     full_path,
                 },
                 entry_size as usize,
             ))
         } else {
             let entry_size = buf.gread_with::<u32>(off, LE)?;
-            let file_size = buf.gread_with::<u32>(off, LE)?;
-            let unk1 = buf.gread_with::<u32>(off, LE)?;
-            let file_name_utf16_len = buf.gread_with::<u32>(off, LE)?;
-            let unk2 = buf.gread_with::<u32>(off, LE)?;
-            let unk3 = buf.gread_with::<u32>(off, LE)?;
-            let file_offset = buf.gread_with::<u32>(off, LE)?;
-            let unk4 = buf.gread_with::<u32>(off, LE)?;
+            let (file_size, file_name_utf16_len, unk2, unk3, file_offset) =
+                GxpFileEntry::read_fields(buf, off, header)?;
             let utf16_string: Vec<u16> = buf
                 .get(*off..*off + file_name_utf16_len as usize * 2)
                 .context("Out of bounds access")?
@@ -270,12 +307,10 @@ impl<'a> ctx::TryFromCtx<'a, &GxpHeader> for GxpFileEntry {
                 GxpFileEntry {
                     entry_size,
                     file_size,
-                    unk1,
                     file_name_utf16_len,
                     unk2,
                     unk3,
                     file_offset,
-                    unk4,
                     full_path,
                 },
                 entry_size as usize,
@@ -0,0 +1,58 @@
+use super::xor_data_with_password;
+use crate::scheme::pack;
+pub use crate::scheme::pack::{read_directory, PackInput};
+use std::io::Write;
+
+const HEADER_SIZE: usize = 48;
+/// Entry size and fields preceding name
+const ENTRY_FIELDS_SIZE: usize = 0x20;
+
+/// Write GXP archive with ciphered index, entries are stored in order of
+/// inputs right after it. Entry sizes are always written in 64 bits, so
+/// archives over 4GB of data are read back too.
+pub fn write_gxp(
+    inputs: Vec<PackInput>,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    let mut index = Vec::new();
+    let mut offset = 0u64;
+    for input in &inputs {
+        let name = pack::path_name(&input.full_path, "/")?
+            .encode_utf16()
+            .chain(std::iter::once(0))
+            .collect::<Vec<u16>>();
+        let entry_size = ENTRY_FIELDS_SIZE + name.len() * 2;
+        let mut entry = Vec::with_capacity(entry_size);
+        entry.extend_from_slice(&(entry_size as u32).to_le_bytes());
+        entry.extend_from_slice(&(input.contents.len() as u64).to_le_bytes());
+        entry.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        entry.extend_from_slice(&[0; 8]);
+        entry.extend_from_slice(&offset.to_le_bytes());
+        for c in name {
+            entry.extend_from_slice(&c.to_le_bytes());
+        }
+        xor_data_with_password(&mut entry, entry_size, 0)?;
+        index.extend_from_slice(&entry);
+        offset += input.contents.len() as u64;
+    }
+
+    let mut header = Vec::with_capacity(HEADER_SIZE);
+    header.extend_from_slice(b"GXP\0");
+    header.resize(0x14, 0);
+    // Index is ciphered
+    header.extend_from_slice(&1u32.to_le_bytes());
+    header.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    header.extend_from_slice(&(index.len() as u32).to_le_bytes());
+    header.extend_from_slice(&offset.to_le_bytes());
+    header
+        .extend_from_slice(&((HEADER_SIZE + index.len()) as u64).to_le_bytes());
+    output.write_all(&header)?;
+    output.write_all(&index)?;
+    for input in inputs {
+        let mut contents = input.contents;
+        let len = contents.len();
+        xor_data_with_password(&mut contents, len, 0)?;
+        output.write_all(&contents)?;
+    }
+    Ok(())
+}
@@ -1,7 +1,9 @@
 use super::Scheme;
 use crate::{
     archive::{self, FileContents},
+    inspect::{ParsedHeader, ReportHeader},
     resource::ResourceMagic,
+    util::{self, bounds, text::display_name},
 };
 use anyhow::Context;
 use bytes::BytesMut;
@@ -24,10 +26,15 @@ impl Scheme for IarScheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 28];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(4, &mut buf)?;
         let header = buf.pread::<IarHeader>(0)?;
         log::debug!("Header: {:#?}", header);
+        // Checked before allocating, count comes from file
+        let index_size = header.entry_count as u64 * 8;
+        if 32 + index_size > std::fs::metadata(file_path)?.len() {
+            return Err(bounds::malformed("IAR entry count").into());
+        }
         let mut file_entries = Vec::with_capacity(header.entry_count as usize);
 
         let mut entry_index_table = vec![0; header.entry_count as usize * 8];
@@ -53,6 +60,12 @@ impl Scheme for IarScheme {
         Ok((Box::new(IarArchive { file, archive }), navigable_dir))
     }
 
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 28];
+        util::fs::open_archive_file(file_path)?.read_exact_at(4, &mut buf)?;
+        Ok(vec![buf.pread::<IarHeader>(0)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[IAR] {}",
@@ -84,11 +97,21 @@ impl archive::Archive for IarArchive {
         self.archive
             .file_entries
             .iter()
-            .find(|e| e.file_offset == entry.file_offset)
+            // Layers look up images they are based on by id
+            .find(|e| entry.full_path == Path::new(&e.id.to_string()))
             .map(|e| self.extract(e))
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| entry.full_path == Path::new(&e.id.to_string()))
+            // Entry data follows its 72 byte header
+            .map(|e| (e.file_offset, e.file_size as u64 + 72))
+    }
+
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
         self.archive.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
@@ -100,8 +123,8 @@ impl archive::Archive for IarArchive {
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             file_contents.write_contents(&output_file_name, None)?;
@@ -112,21 +135,13 @@ impl archive::Archive for IarArchive {
 
 impl IarArchive {
     fn new_root_dir(entries: &[IarFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: entry.id.to_string(),
-                        full_path: PathBuf::from(entry.id.to_string()),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                PathBuf::from(entry.id.to_string()),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &IarFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize + 72);
@@ -156,6 +171,8 @@ struct IarHeader {
     entry_count2: u32,
 }
 
+impl ReportHeader for IarHeader {}
+
 #[derive(Debug)]
 struct IarFileEntry {
     version: u32,
@@ -177,8 +194,6 @@ impl IarFileEntry {
         match self.version & 0xFFFF {
             // This just concatenates two images into one, those images are already extracted no need to double
             0x103C | 0x101C => true,
-            // TODO those modify existing image, have to refactor some stuff
-            0x83C | 0x81C => true,
             _ => false,
         }
     }
@@ -0,0 +1,220 @@
+use super::{pack::PackInput, Scheme};
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util,
+};
+use anyhow::Context;
+use bytes::{Bytes, BytesMut};
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use scroll::{Pread, LE};
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+pub mod writer;
+
+const MAGIC: &[u8; 8] = b"SM2MPX10";
+const HEADER_SIZE: usize = 0x20;
+const ENTRY_SIZE: usize = 0x14;
+const NAME_SIZE: usize = 12;
+
+#[derive(Debug, Pread)]
+struct MpxHeader {
+    magic: [u8; 8],
+    entry_count: u32,
+    /// End of index, where data of first entry starts
+    index_end: u32,
+    /// Not read on extraction
+    reserved: [u8; 16],
+}
+
+impl ReportHeader for MpxHeader {}
+
+/// Ikura GDL SM2MPX10 containers. Index right after header holds zero
+/// padded SHIFT-JIS name, offset and size of each entry, entries are not
+/// compressed or encrypted and there are no directories.
+#[derive(Debug, Clone)]
+pub enum IkuraScheme {
+    Universal,
+}
+
+impl Scheme for IkuraScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let file_size = std::fs::metadata(file_path)?.len();
+        let mut buf = vec![0; HEADER_SIZE];
+        file.read_exact_at(0, &mut buf)?;
+        let header = buf.pread_with::<MpxHeader>(0, LE)?;
+        log::debug!("Header: {:#?}", header);
+
+        let index_size = header.entry_count as u64 * ENTRY_SIZE as u64;
+        if HEADER_SIZE as u64 + index_size > file_size {
+            return Err(AkaibuError::Custom(format!(
+                "Invalid SM2MPX10 entry count: {}",
+                header.entry_count
+            ))
+            .into());
+        }
+        let mut index = vec![0; index_size as usize];
+        file.read_exact_at(HEADER_SIZE as u64, &mut index)?;
+        let file_entries = index
+            .chunks_exact(ENTRY_SIZE)
+            .map(|record| MpxFileEntry::parse(record, file_size))
+            .collect::<anyhow::Result<Vec<MpxFileEntry>>>()?;
+        log::debug!("Entries: {:#?}", file_entries);
+
+        let root_dir = MpxArchive::new_root_dir(&file_entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((Box::new(MpxArchive { file, file_entries }), navigable_dir))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; HEADER_SIZE];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<MpxHeader>(0, LE)?.report()])
+    }
+
+    fn can_pack(&self) -> bool {
+        true
+    }
+
+    fn write_archive(
+        &self,
+        inputs: Vec<PackInput>,
+        mut output: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        writer::write_mpx(inputs, &mut output)
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[IKURA GDL] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+#[derive(Debug)]
+struct MpxArchive {
+    file: RandomAccessFile,
+    file_entries: Vec<MpxFileEntry>,
+}
+
+impl archive::Archive for MpxArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        Some((entry.file_offset, entry.file_size))
+    }
+
+    fn raw_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        Some((entry.file_offset, entry.file_size))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn decode_raw(
+        &self,
+        _entry: &archive::FileEntry,
+        raw: Bytes,
+    ) -> anyhow::Result<FileContents> {
+        Ok(FileContents {
+            contents: raw,
+            type_hint: None,
+        })
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.file_entries).files().cloned(),
+            output_path,
+        )
+    }
+}
+
+impl MpxArchive {
+    fn new_root_dir(entries: &[MpxFileEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    fn extract(&self, entry: &MpxFileEntry) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::with_capacity(entry.file_size as usize);
+        buf.resize(entry.file_size as usize, 0);
+        self.file
+            .read_exact_at(entry.file_offset as u64, &mut buf)?;
+        Ok(FileContents {
+            contents: buf.freeze(),
+            type_hint: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct MpxFileEntry {
+    full_path: PathBuf,
+    file_offset: u32,
+    file_size: u32,
+}
+
+impl MpxFileEntry {
+    /// Name is not terminated when it takes all 12 bytes
+    fn parse(record: &[u8], archive_size: u64) -> anyhow::Result<Self> {
+        let name = record[..NAME_SIZE]
+            .split(|b| *b == 0)
+            .next()
+            .filter(|name| !name.is_empty())
+            .context("Invalid SM2MPX10 entry name")?;
+        let file_name = SHIFT_JIS.decode(name).0.to_string();
+        let mut off = NAME_SIZE;
+        let file_offset = record.gread_with::<u32>(&mut off, LE)?;
+        let file_size = record.gread_with::<u32>(&mut off, LE)?;
+        if file_offset as u64 + file_size as u64 > archive_size {
+            return Err(AkaibuError::Custom(format!(
+                "SM2MPX10 entry {} out of bounds",
+                file_name
+            ))
+            .into());
+        }
+        Ok(Self {
+            full_path: PathBuf::from(&file_name),
+            file_offset,
+            file_size,
+        })
+    }
+}
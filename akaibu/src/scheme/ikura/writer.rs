@@ -0,0 +1,66 @@
+use super::{ENTRY_SIZE, HEADER_SIZE, MAGIC, NAME_SIZE};
+use crate::error::AkaibuError;
+pub use crate::scheme::pack::{read_directory, PackInput};
+use encoding_rs::SHIFT_JIS;
+use std::{convert::TryFrom, io::Write, path::Path};
+
+/// Write SM2MPX10 archive with entries stored in order of inputs right
+/// after index. Archive has no directories, so every input has to be plain
+/// file name fitting into 12 bytes of SHIFT-JIS.
+pub fn write_mpx(
+    inputs: Vec<PackInput>,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    let names = inputs
+        .iter()
+        .map(|input| encode_name(&input.full_path))
+        .collect::<anyhow::Result<Vec<Vec<u8>>>>()?;
+    let index_end = HEADER_SIZE + inputs.len() * ENTRY_SIZE;
+
+    let mut header = Vec::with_capacity(index_end);
+    header.extend_from_slice(MAGIC);
+    header.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    header.extend_from_slice(&(index_end as u32).to_le_bytes());
+    header.resize(HEADER_SIZE, 0);
+    let mut offset = index_end as u64;
+    for (input, name) in inputs.iter().zip(&names) {
+        let file_offset = u32::try_from(offset).map_err(|_| {
+            AkaibuError::Custom(format!(
+                "Archive is too large to hold {:?}",
+                input.full_path
+            ))
+        })?;
+        header.extend_from_slice(name);
+        header.resize(header.len() + NAME_SIZE - name.len(), 0);
+        header.extend_from_slice(&file_offset.to_le_bytes());
+        header.extend_from_slice(&(input.contents.len() as u32).to_le_bytes());
+        offset += input.contents.len() as u64;
+    }
+    output.write_all(&header)?;
+    for input in &inputs {
+        output.write_all(&input.contents)?;
+    }
+    Ok(())
+}
+
+/// SHIFT-JIS file name, entries can not be put into directories
+fn encode_name(full_path: &Path) -> anyhow::Result<Vec<u8>> {
+    let name = full_path
+        .to_str()
+        .filter(|name| !name.contains(&['/', '\\'][..]))
+        .ok_or_else(|| {
+            AkaibuError::Custom(format!(
+                "SM2MPX10 entries can not be in directories: {:?}",
+                full_path
+            ))
+        })?;
+    let (encoded, _, had_errors) = SHIFT_JIS.encode(name);
+    if had_errors || encoded.is_empty() || encoded.len() > NAME_SIZE {
+        return Err(AkaibuError::Custom(format!(
+            "Name {:?} does not fit into {} bytes of SHIFT-JIS",
+            name, NAME_SIZE
+        ))
+        .into());
+    }
+    Ok(encoded.into_owned())
+}
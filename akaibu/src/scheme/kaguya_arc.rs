@@ -0,0 +1,284 @@
+use super::Scheme;
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, lzss_decompress, text::display_name},
+};
+use anyhow::Context;
+use bytes::{Bytes, BytesMut};
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use scroll::{Pread, LE};
+use std::path::{Path, PathBuf};
+
+const INDEX_EXTENSION: &str = "ari";
+const DATA_EXTENSION: &str = "arc";
+const MODE_PACKED: u16 = 2;
+
+/// Index file of Kaguya archive, stored next to data file with the same
+/// stem. Extension case is kept as in data file name when possible.
+pub(crate) fn index_path(file_path: &Path) -> Option<PathBuf> {
+    sibling(file_path, INDEX_EXTENSION)
+}
+
+fn sibling(file_path: &Path, extension: &str) -> Option<PathBuf> {
+    [extension.to_string(), extension.to_uppercase()]
+        .iter()
+        .map(|extension| file_path.with_extension(extension))
+        .find(|path| path.is_file())
+}
+
+/// Atelier Kaguya data archives. Index is kept in `.ari` file next to
+/// `.arc` data. Every index record is name length, name with inverted
+/// bytes, u16 mode and stored size, followed by unpacked size for entries
+/// packed with LZSS (mode 2). Data file repeats the record before each
+/// entry, so entry offsets follow from record and entry sizes.
+#[derive(Debug, Clone)]
+pub enum KaguyaArcScheme {
+    Universal,
+}
+
+impl Scheme for KaguyaArcScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let (data_path, index_path) = archive_paths(file_path)?;
+        let index = std::fs::read(&index_path)?;
+        let file_entries = parse_index(&index)?;
+        let file = util::fs::open_archive_file(&data_path)?;
+        let file_size = std::fs::metadata(&data_path)?.len();
+        if let Some(entry) = file_entries.iter().find(|entry| {
+            entry.file_offset + entry.file_size as u64 > file_size
+        }) {
+            return Err(AkaibuError::Custom(format!(
+                "Entry {} is out of archive bounds",
+                display_name(&entry.full_path)
+            ))
+            .into());
+        }
+        log::debug!("Entries: {:#?}", file_entries);
+
+        let root_dir = KaguyaArcArchive::new_root_dir(&file_entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((
+            Box::new(KaguyaArcArchive { file, file_entries }),
+            navigable_dir,
+        ))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let (_, index_path) = archive_paths(file_path)?;
+        let file_entries = parse_index(&std::fs::read(&index_path)?)?;
+        Ok(vec![AriIndex {
+            entry_count: file_entries.len(),
+            packed_count: file_entries
+                .iter()
+                .filter(|entry| entry.unpacked_size.is_some())
+                .count(),
+        }
+        .report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[KAGUYA ARC] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+/// Data and index file of archive opened through either of them
+fn archive_paths(file_path: &Path) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let is_index = file_path.extension().map_or(false, |extension| {
+        extension.eq_ignore_ascii_case(INDEX_EXTENSION)
+    });
+    let data_path = if is_index {
+        sibling(file_path, DATA_EXTENSION).with_context(|| {
+            format!("Could not find Kaguya data file for {:?}", file_path)
+        })?
+    } else {
+        file_path.to_path_buf()
+    };
+    let index_path = index_path(&data_path).with_context(|| {
+        format!("Could not find Kaguya index for {:?}", data_path)
+    })?;
+    Ok((data_path, index_path))
+}
+
+/// Index has no header of its own, its records are summarized instead
+#[derive(Debug)]
+struct AriIndex {
+    entry_count: usize,
+    packed_count: usize,
+}
+
+impl ReportHeader for AriIndex {}
+
+fn parse_index(index: &[u8]) -> anyhow::Result<Vec<KaguyaFileEntry>> {
+    let off = &mut 0;
+    let mut data_offset = 0u64;
+    let mut entries = Vec::new();
+    while *off < index.len() {
+        let record_start = *off;
+        let name_len = index.gread_with::<u32>(off, LE)? as usize;
+        let name = index
+            .get(*off..)
+            .and_then(|name| name.get(..name_len))
+            .context("Out of bounds access")?
+            .iter()
+            .map(|b| !b)
+            .collect::<Vec<u8>>();
+        *off += name_len;
+        let name = SHIFT_JIS.decode(&name).0.replace("\\", "/");
+        if name.is_empty() {
+            return Err(
+                AkaibuError::Custom("Empty entry name".to_string()).into()
+            );
+        }
+        let mode = index.gread_with::<u16>(off, LE)?;
+        let file_size = index.gread_with::<u32>(off, LE)?;
+        let unpacked_size = match mode {
+            0 | 1 => None,
+            MODE_PACKED => Some(index.gread_with::<u32>(off, LE)?),
+            _ => {
+                return Err(AkaibuError::Unimplemented(format!(
+                    "Kaguya ARC entry mode {}",
+                    mode
+                ))
+                .into())
+            }
+        };
+        data_offset += (*off - record_start) as u64;
+        let full_path = PathBuf::from(name);
+        entries.push(KaguyaFileEntry {
+            full_path,
+            file_offset: data_offset,
+            file_size,
+            unpacked_size,
+        });
+        data_offset += file_size as u64;
+    }
+    Ok(entries)
+}
+
+#[derive(Debug)]
+struct KaguyaArcArchive {
+    file: RandomAccessFile,
+    file_entries: Vec<KaguyaFileEntry>,
+}
+
+impl archive::Archive for KaguyaArcArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn entry_ext(&self, entry: &archive::FileEntry) -> archive::FileEntryExt {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .filter(|e| e.unpacked_size.is_some())
+            .map(|_| archive::FileEntryExt {
+                compression: Some("LZSS".to_string()),
+                ..Default::default()
+            })
+            .unwrap_or_default()
+    }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .filter(|e| e.unpacked_size.is_none())
+            .map(|e| (e.file_offset, e.file_size as u64))
+    }
+
+    fn raw_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        Some((entry.file_offset, entry.file_size))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn decode_raw(
+        &self,
+        entry: &archive::FileEntry,
+        raw: Bytes,
+    ) -> anyhow::Result<FileContents> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| Self::decode(e, raw))
+            .context("File not found")?
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.file_entries).files().cloned(),
+            output_path,
+        )
+    }
+}
+
+impl KaguyaArcArchive {
+    fn new_root_dir(entries: &[KaguyaFileEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    fn extract(&self, entry: &KaguyaFileEntry) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::with_capacity(entry.file_size as usize);
+        buf.resize(entry.file_size as usize, 0);
+        self.file.read_exact_at(entry.file_offset, &mut buf)?;
+        Self::decode(entry, buf.freeze())
+    }
+    fn decode(
+        entry: &KaguyaFileEntry,
+        raw: Bytes,
+    ) -> anyhow::Result<FileContents> {
+        let contents = match entry.unpacked_size {
+            Some(unpacked_size) => {
+                Bytes::from(lzss_decompress(&raw, unpacked_size as usize)?)
+            }
+            None => raw,
+        };
+        Ok(FileContents {
+            contents,
+            type_hint: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct KaguyaFileEntry {
+    full_path: PathBuf,
+    file_offset: u64,
+    file_size: u32,
+    unpacked_size: Option<u32>,
+}
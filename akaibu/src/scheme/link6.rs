@@ -2,6 +2,8 @@ use super::Scheme;
 use crate::{
     archive::{self, FileContents},
     error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name},
 };
 use anyhow::Context;
 use bytes::BytesMut;
@@ -37,7 +39,7 @@ impl Scheme for Link6Scheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 8 + 256];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
         let header = buf.pread::<Link6Header>(0)?;
         log::debug!("Header: {:#?}", header);
@@ -78,7 +80,7 @@ impl Scheme for Link6Scheme {
             let metadata = std::fs::metadata(&params_file_path)?;
 
             let mut params_buf = vec![0; metadata.len() as usize];
-            let params_file = RandomAccessFile::open(params_file_path)?;
+            let params_file = util::fs::open_archive_file(&params_file_path)?;
             params_file.read_exact_at(0, &mut params_buf)?;
 
             Some(extract_key_from_params(&params_buf)?)
@@ -96,6 +98,15 @@ impl Scheme for Link6Scheme {
         ))
     }
 
+    fn inspect(
+        &self,
+        file_path: &path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 8 + 256];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread::<Link6Header>(0)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[LINK6] {}",
@@ -132,19 +143,26 @@ impl archive::Archive for Link6Archive {
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset, e.file_size as u64))
+    }
+
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
         self.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -156,25 +174,13 @@ impl archive::Archive for Link6Archive {
 
 impl Link6Archive {
     fn new_root_dir(entries: &[Link6FileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset;
-                    let file_size = entry.file_size;
-                    archive::FileEntry {
-                        file_name: entry
-                            .full_path
-                            .to_str()
-                            .expect("Not valid UTF-8")
-                            .to_string(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size: file_size as u64,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &Link6FileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size);
@@ -205,6 +211,8 @@ struct Link6Header {
     name: String,
 }
 
+impl ReportHeader for Link6Header {}
+
 impl<'a> ctx::TryFromCtx<'a, ()> for Link6Header {
     type Error = anyhow::Error;
 
@@ -298,10 +306,11 @@ fn extract_key_from_params(buf: &[u8]) -> anyhow::Result<Vec<u8>> {
         }
         _ => {
             let version = &buf[..0x11];
-            return Err(AkaibuError::Custom(format!(
-                "Unsupported version of params.dat file {:?}",
-                String::from_utf8_lossy(version)
-            ))
+            return Err(AkaibuError::UnsupportedVersion {
+                scheme: "LINK6 params.dat".to_string(),
+                found: String::from_utf8_lossy(version).into_owned(),
+                expected: "[SCR-PARAMS]v05.4 to v05.7".to_string(),
+            }
             .into());
         }
     })
@@ -1,7 +1,9 @@
-use super::Scheme;
+use super::{registry::SchemeRegistry, Scheme};
 use crate::{
     archive::{self, FileContents},
     error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name},
 };
 use anyhow::Context;
 use bytes::{BufMut, Bytes, BytesMut};
@@ -11,7 +13,7 @@ use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{ctx, Pread, LE};
 use std::{collections::HashMap, fs::File, io::Write, path::PathBuf};
 
-const KEYS_PATH: &str = "malie/keys.json";
+pub(crate) const KEYS_PATH: &str = "malie/keys.json";
 const MAGIC: &[u8] = b"LIBP";
 
 #[derive(Debug, Clone)]
@@ -28,24 +30,10 @@ impl Scheme for MalieScheme {
         Box<dyn crate::archive::Archive>,
         crate::archive::NavigableDirectory,
     )> {
-        let camellia =
-            CamelliaCipher::new(&self.get_game_key()?).map_err(|_| {
-                AkaibuError::Custom("Invalid Camellia key length".to_owned())
-            })?;
-        let mut buf = vec![0; 16];
-        let file = RandomAccessFile::open(file_path)?;
-        file.read_exact_at(0, &mut buf)?;
-        decrypt(&mut buf, 0, &camellia)?;
-
-        let header = buf.pread::<MalieHeader>(0)?;
+        let camellia = self.get_cipher()?;
+        let file = util::fs::open_archive_file(file_path)?;
+        let header = read_header(&file, &camellia)?;
         log::debug!("Header: {:#?}", header);
-        if header.magic != MAGIC {
-            return Err(AkaibuError::Custom(format!(
-                "Invalid magic valie for malie archive: {:X?}",
-                header.magic
-            ))
-            .into());
-        }
         let size = ((header.entry_count * 8 + header.unk2) * 4) as usize;
         let file_data_offset = ((((header.entry_count * 8 + header.unk2) * 4
             + 0x10)
@@ -123,6 +111,21 @@ impl Scheme for MalieScheme {
         ))
     }
 
+    /// Header is encrypted with game key, so keys of every title are tried
+    fn inspect(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let header = [self.clone(), Self::HaruUso, Self::NatsuUso]
+            .iter()
+            .find_map(|scheme| {
+                read_header(&file, &scheme.get_cipher().ok()?).ok()
+            })
+            .context("No Malie key decrypts archive header")?;
+        Ok(vec![header.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[MALIE] {}",
@@ -142,20 +145,20 @@ impl Scheme for MalieScheme {
 }
 
 impl MalieScheme {
+    fn get_cipher(&self) -> anyhow::Result<CamelliaCipher> {
+        Ok(CamelliaCipher::new(&self.get_game_key()?).map_err(|_| {
+            AkaibuError::Custom("Invalid Camellia key length".to_owned())
+        })?)
+    }
     fn get_game_key(&self) -> anyhow::Result<Vec<u8>> {
-        let keys: HashMap<String, Vec<u8>> = serde_json::from_slice(
-            &crate::Resources::get(KEYS_PATH).context(format!(
-                "Could not find embedded resource: {}",
-                KEYS_PATH
-            ))?,
-        )?;
+        let mut keys: HashMap<String, Vec<u8>> =
+            SchemeRegistry::global().load(KEYS_PATH)?;
         Ok(keys
-            .get(match self {
+            .remove(match self {
                 Self::HaruUso => "HaruUso",
                 Self::NatsuUso => "NatsuUso",
             })
-            .context("Malie key not found")?
-            .clone())
+            .context("Malie key not found")?)
     }
 }
 
@@ -180,20 +183,33 @@ impl archive::Archive for MalieArchive {
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| {
+                (
+                    (e.file_offset + self.file_data_offset) << 10,
+                    align_size(e.file_size as usize) as u64,
+                )
+            })
+    }
+
     fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
         self.archive.file_entries.par_iter().try_for_each(
             |entry| -> Result<(), anyhow::Error> {
                 let file_contents = self.extract(entry)?;
                 let mut output_file_name = PathBuf::from(output_path);
-                output_file_name.push(&entry.full_path);
+                output_file_name.push(archive::output_path(&entry.full_path));
                 std::fs::create_dir_all(
                     &output_file_name
                         .parent()
                         .context("Could not get parent directory")?,
                 )?;
                 log::debug!(
-                    "Extracting resource: {:?} {:X?}",
-                    output_file_name,
+                    "Extracting resource: {} {:X?}",
+                    display_name(&output_file_name),
                     entry
                 );
                 File::create(output_file_name)?
@@ -206,21 +222,13 @@ impl archive::Archive for MalieArchive {
 
 impl MalieArchive {
     fn new_root_dir(entries: &[MalieEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: entry.file_name.clone(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &MalieEntry) -> anyhow::Result<FileContents> {
         let aligned = align_size(entry.file_size as usize);
@@ -252,6 +260,27 @@ struct MalieHeader {
     unk3: u32,
 }
 
+impl ReportHeader for MalieHeader {}
+
+/// First block of archive decrypted with given cipher
+fn read_header(
+    file: &RandomAccessFile,
+    camellia: &CamelliaCipher,
+) -> anyhow::Result<MalieHeader> {
+    let mut buf = vec![0; 16];
+    file.read_exact_at(0, &mut buf)?;
+    decrypt(&mut buf, 0, camellia)?;
+    let header = buf.pread::<MalieHeader>(0)?;
+    if header.magic != MAGIC {
+        return Err(AkaibuError::Custom(format!(
+            "Invalid magic valie for malie archive: {:X?}",
+            header.magic
+        ))
+        .into());
+    }
+    Ok(header)
+}
+
 #[derive(Debug)]
 struct MalieEntry {
     id: usize,
@@ -0,0 +1,291 @@
+use super::Scheme;
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name},
+};
+use anyhow::Context;
+use bytes::BytesMut;
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use scroll::{ctx, Pread, LE};
+use std::path::{Path, PathBuf};
+
+const MBL_V1_NAME_SIZE: u32 = 0x10;
+const MBL_V1_INDEX_OFFSET: u64 = 4;
+const MBL_V2_INDEX_OFFSET: u64 = 8;
+
+#[derive(Debug, Clone)]
+pub enum MblScheme {
+    Universal,
+}
+
+impl Scheme for MblScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let archive =
+            Self::read_archive(&file, std::fs::metadata(file_path)?.len())?;
+        log::debug!("Archive: {:#?}", archive);
+
+        let root_dir = MblArchive::new_root_dir(&archive.file_entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((Box::new(MblArchive { file, archive }), navigable_dir))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let archive =
+            Self::read_archive(&file, std::fs::metadata(file_path)?.len())?;
+        Ok(vec![archive.header.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[MBL] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+impl MblScheme {
+    /// Version is not stored, it is told by which index layout fits
+    /// archive
+    fn read_archive(
+        file: &RandomAccessFile,
+        file_size: u64,
+    ) -> anyhow::Result<Mbl> {
+        let mut buf = vec![0; 8];
+        file.read_exact_at(0, &mut buf)?;
+
+        let entry_count = buf.pread_with::<u32>(0, LE)?;
+        let name_size = buf.pread_with::<u32>(4, LE)?;
+
+        // Version 2 stores name size in header, version 1 has fixed size names
+        match name_size {
+            1..=0xFF => Self::read_index(
+                file,
+                file_size,
+                MblHeader {
+                    version: 2,
+                    entry_count,
+                    name_size,
+                },
+                MBL_V2_INDEX_OFFSET,
+            )
+            .or_else(|err| {
+                log::debug!("Not MBL version 2 archive: {}", err);
+                Self::read_index(
+                    file,
+                    file_size,
+                    MblHeader {
+                        version: 1,
+                        entry_count,
+                        name_size: MBL_V1_NAME_SIZE,
+                    },
+                    MBL_V1_INDEX_OFFSET,
+                )
+            }),
+            _ => Self::read_index(
+                file,
+                file_size,
+                MblHeader {
+                    version: 1,
+                    entry_count,
+                    name_size: MBL_V1_NAME_SIZE,
+                },
+                MBL_V1_INDEX_OFFSET,
+            ),
+        }
+    }
+    fn read_index(
+        file: &RandomAccessFile,
+        file_size: u64,
+        header: MblHeader,
+        index_offset: u64,
+    ) -> anyhow::Result<Mbl> {
+        let index_size =
+            (header.name_size as u64 + 8) * header.entry_count as u64;
+        if index_offset + index_size > file_size {
+            return Err(AkaibuError::Custom(format!(
+                "Invalid MBL index size: {}",
+                index_size
+            ))
+            .into());
+        }
+        let mut buf = vec![0; index_size as usize];
+        file.read_exact_at(index_offset, &mut buf)?;
+        let archive = buf.pread_with::<Mbl>(0, header)?;
+        if let Some(entry) = archive.file_entries.iter().find(|entry| {
+            entry.file_offset as u64 + entry.file_size as u64 > file_size
+        }) {
+            return Err(AkaibuError::Custom(format!(
+                "Entry {} is out of archive bounds",
+                display_name(&entry.full_path)
+            ))
+            .into());
+        }
+        Ok(archive)
+    }
+}
+
+#[derive(Debug)]
+struct MblArchive {
+    file: RandomAccessFile,
+    archive: Mbl,
+}
+
+impl archive::Archive for MblArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset as u64, e.file_size as u64))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.archive.file_entries)
+                .files()
+                .cloned(),
+            output_path,
+        )
+    }
+}
+
+impl MblArchive {
+    fn new_root_dir(entries: &[MblFileEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    fn extract(&self, entry: &MblFileEntry) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::with_capacity(entry.file_size as usize);
+        buf.resize(entry.file_size as usize, 0);
+        self.file
+            .read_exact_at(entry.file_offset as u64, &mut buf)?;
+        Ok(FileContents {
+            contents: buf.freeze(),
+            type_hint: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct Mbl {
+    header: MblHeader,
+    file_entries: Vec<MblFileEntry>,
+}
+
+impl<'a> ctx::TryFromCtx<'a, MblHeader> for Mbl {
+    type Error = anyhow::Error;
+
+    fn try_from_ctx(
+        buf: &'a [u8],
+        header: MblHeader,
+    ) -> Result<(Self, usize), Self::Error> {
+        let off = &mut 0;
+        let mut file_entries = Vec::with_capacity(header.entry_count as usize);
+        for _ in 0..header.entry_count {
+            file_entries.push(buf.gread_with(off, header.name_size)?);
+        }
+        Ok((
+            Self {
+                header,
+                file_entries,
+            },
+            *off,
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct MblHeader {
+    version: u32,
+    entry_count: u32,
+    name_size: u32,
+}
+
+impl ReportHeader for MblHeader {}
+
+#[derive(Debug)]
+struct MblFileEntry {
+    full_path: PathBuf,
+    file_offset: u32,
+    file_size: u32,
+}
+
+impl<'a> ctx::TryFromCtx<'a, u32> for MblFileEntry {
+    type Error = anyhow::Error;
+
+    fn try_from_ctx(
+        buf: &'a [u8],
+        name_size: u32,
+    ) -> Result<(Self, usize), Self::Error> {
+        let off = &mut 0;
+        let name = SHIFT_JIS
+            .decode(
+                buf.get(*off..*off + name_size as usize)
+                    .context("Out of bounds access")?
+                    .split(|b| *b == 0)
+                    .next()
+                    .context("Could not split")?,
+            )
+            .0
+            .replace("\\", "/");
+        if name.is_empty() {
+            return Err(
+                AkaibuError::Custom("Empty entry name".to_string()).into()
+            );
+        }
+        *off += name_size as usize;
+        let full_path = PathBuf::from(name);
+        let file_offset = buf.gread_with::<u32>(off, LE)?;
+        let file_size = buf.gread_with::<u32>(off, LE)?;
+        Ok((
+            Self {
+                full_path,
+                file_offset,
+                file_size,
+            },
+            *off,
+        ))
+    }
+}
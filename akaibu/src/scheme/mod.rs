@@ -1,30 +1,165 @@
-use crate::archive;
+use crate::{
+    archive, error::AkaibuError, inspect::ParsedHeader, key_file::KeyFile, util,
+};
 use archive::NavigableDirectory;
 use dyn_clone::DynClone;
-use std::{fmt::Debug, path::Path};
+use pack::PackInput;
+use std::{
+    fmt::Debug,
+    io::{Read, Write},
+    path::Path,
+};
 
+#[cfg(feature = "fmt-acv1")]
 pub mod acv1;
+#[cfg(feature = "fmt-amusepac")]
 pub mod amusepac;
+#[cfg(feature = "fmt-buriko")]
 pub mod buriko;
+#[cfg(feature = "fmt-cpz7")]
 pub mod cpz7;
+#[cfg(feature = "fmt-esc-arc2")]
 pub mod esc_arc2;
+#[cfg(feature = "fmt-gxp")]
 pub mod gxp;
+#[cfg(feature = "fmt-iar")]
 pub mod iar;
+#[cfg(feature = "fmt-ikura")]
+pub mod ikura;
+#[cfg(feature = "fmt-kaguya")]
+pub mod kaguya_arc;
+#[cfg(feature = "fmt-link6")]
 pub mod link6;
+#[cfg(feature = "fmt-malie")]
 pub mod malie;
+#[cfg(feature = "fmt-mbl")]
+pub mod mbl;
+#[cfg(feature = "fmt-moonstone-dat")]
+pub mod moonstone_dat;
+#[cfg(feature = "fmt-nekopack")]
 pub mod nekopack;
+#[cfg(feature = "fmt-otomate-bin")]
+pub mod otomate_bin;
+#[cfg(feature = "fmt-packdat")]
+pub mod packdat;
+#[cfg(feature = "fmt-paz")]
+pub mod paz;
+#[cfg(feature = "fmt-pf8")]
 pub mod pf8;
+#[cfg(feature = "fmt-qlie")]
 pub mod qliepack;
+#[cfg(feature = "fmt-silky")]
 pub mod silky;
+#[cfg(feature = "fmt-silky")]
+pub mod silky_grp;
+#[cfg(feature = "fmt-tactics")]
 pub mod tactics_arc;
+#[cfg(feature = "fmt-willplus")]
 pub mod willplus_arc;
+#[cfg(feature = "fmt-ykc")]
+pub mod ykc;
+#[cfg(feature = "fmt-ypf")]
 pub mod ypf;
 
-pub trait Scheme: Debug + Send + DynClone {
+pub mod pack;
+pub mod registry;
+
+pub trait Scheme: Debug + Send + Sync + DynClone {
     fn extract(
         &self,
         file_path: &Path,
     ) -> anyhow::Result<(Box<dyn archive::Archive>, NavigableDirectory)>;
+    /// Extract using per-install key supplied from outside of archive.
+    /// Schemes that do not need external key ignore it.
+    fn extract_with_key(
+        &self,
+        file_path: &Path,
+        _key_file: &KeyFile,
+    ) -> anyhow::Result<(Box<dyn archive::Archive>, NavigableDirectory)> {
+        self.extract(file_path)
+    }
+    /// Extract using password given by user. Schemes that can not use
+    /// password ignore it, the ones that need it return
+    /// [`crate::error::AkaibuError::PasswordRequired`] when it is missing or
+    /// wrong.
+    fn extract_with_password(
+        &self,
+        file_path: &Path,
+        _password: &str,
+    ) -> anyhow::Result<(Box<dyn archive::Archive>, NavigableDirectory)> {
+        self.extract(file_path)
+    }
+    /// Entries of archive without building directory tree, for listing
+    /// huge archives. Default opens archive with [`Scheme::extract`],
+    /// schemes override it to read only entry table.
+    fn list(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<Vec<archive::FileEntryMetadata>> {
+        let (archive, dir) = self.extract(file_path)?;
+        Ok(dir
+            .files()
+            .map(|entry| {
+                archive::FileEntryMetadata::of(archive.as_ref(), entry)
+            })
+            .collect())
+    }
+    /// Can scheme list archive with [`Scheme::list_stream`], true for
+    /// formats with entry table at start of archive
+    fn streams_index(&self) -> bool {
+        false
+    }
+    /// Entries of archive read front to back, e.g. from pipe, without
+    /// copying archive into temporary file
+    fn list_stream(
+        &self,
+        _reader: &mut dyn Read,
+    ) -> anyhow::Result<Vec<archive::FileEntryMetadata>> {
+        Err(AkaibuError::Unimplemented(format!(
+            "Listing stream for {}",
+            self.get_name()
+        ))
+        .into())
+    }
+    /// Does scheme require key file to extract archive
+    fn needs_key_file(&self) -> bool {
+        false
+    }
+    /// Headers of archive parsed without extracting entries
+    fn inspect(&self, _file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        Err(AkaibuError::Unimplemented(format!(
+            "Header report for {}",
+            self.get_name()
+        ))
+        .into())
+    }
+    /// Can scheme write archives with [`Scheme::write_archive`]
+    fn can_pack(&self) -> bool {
+        false
+    }
+    /// Write archive holding given files, e.g. to rebuild archive with
+    /// edited entries. Files are stored as given, conversion and
+    /// decompression done on extraction are not reverted.
+    fn write_archive(
+        &self,
+        _inputs: Vec<PackInput>,
+        _output: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        Err(
+            AkaibuError::Unimplemented(format!("Packing {}", self.get_name()))
+                .into(),
+        )
+    }
+    /// Pack every file under directory into archive written at output
+    /// path, returns number of packed files
+    fn pack(&self, input_dir: &Path, output: &Path) -> anyhow::Result<usize> {
+        let inputs = pack::read_directory(input_dir)?;
+        let count = inputs.len();
+        let mut buf = Vec::new();
+        self.write_archive(inputs, &mut buf)?;
+        util::write_atomic(output, &buf)?;
+        Ok(count)
+    }
     fn get_name(&self) -> String;
     fn get_schemes() -> Vec<Box<dyn Scheme>>
     where
@@ -0,0 +1,226 @@
+use super::Scheme;
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name},
+};
+use anyhow::Context;
+use bytes::BytesMut;
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use scroll::{ctx, Pread, LE};
+use std::path::{Path, PathBuf};
+
+const NAME_SIZE: usize = 0x20;
+const ENTRY_SIZE: usize = NAME_SIZE + 8;
+const INDEX_OFFSET: u64 = 4;
+
+/// Moonstone archives have no magic. Entry count is followed by fixed size
+/// entries with SHIFT-JIS name, offset and size, entries are stored as is.
+#[derive(Debug, Clone)]
+pub enum MoonstoneDatScheme {
+    Universal,
+}
+
+impl Scheme for MoonstoneDatScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let file_size = std::fs::metadata(file_path)?.len();
+        let file_entries = read_index(&file, file_size)?;
+        log::debug!("Entries: {:#?}", file_entries);
+
+        let root_dir = MoonstoneDatArchive::new_root_dir(&file_entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((
+            Box::new(MoonstoneDatArchive { file, file_entries }),
+            navigable_dir,
+        ))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; INDEX_OFFSET as usize];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<MoonstoneHeader>(0, LE)?.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[MOONSTONE DAT] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+/// Archive has no magic, so `.dat` file is taken for Moonstone one only
+/// when its index fits in file and every entry lies after index
+pub(crate) fn is_moonstone_dat(file_path: &Path) -> bool {
+    util::fs::open_archive_file(file_path)
+        .and_then(|file| read_index(&file, std::fs::metadata(file_path)?.len()))
+        .is_ok()
+}
+
+fn read_index(
+    file: &RandomAccessFile,
+    file_size: u64,
+) -> anyhow::Result<Vec<MoonstoneFileEntry>> {
+    let mut buf = vec![0; INDEX_OFFSET as usize];
+    file.read_exact_at(0, &mut buf)?;
+    let entry_count =
+        buf.pread_with::<MoonstoneHeader>(0, LE)?.entry_count as u64;
+    let index_end = INDEX_OFFSET + entry_count * ENTRY_SIZE as u64;
+    if entry_count == 0 || index_end > file_size {
+        return Err(AkaibuError::Custom(format!(
+            "Invalid Moonstone DAT entry count: {}",
+            entry_count
+        ))
+        .into());
+    }
+    let mut buf = vec![0; (index_end - INDEX_OFFSET) as usize];
+    file.read_exact_at(INDEX_OFFSET, &mut buf)?;
+    let off = &mut 0;
+    let file_entries = (0..entry_count)
+        .map(|_| buf.gread::<MoonstoneFileEntry>(off))
+        .collect::<anyhow::Result<Vec<MoonstoneFileEntry>>>()?;
+    if let Some(entry) = file_entries.iter().find(|entry| {
+        (entry.file_offset as u64) < index_end
+            || entry.file_offset as u64 + entry.file_size as u64 > file_size
+    }) {
+        return Err(AkaibuError::Custom(format!(
+            "Entry {} is out of archive bounds",
+            display_name(&entry.full_path)
+        ))
+        .into());
+    }
+    Ok(file_entries)
+}
+
+#[derive(Debug, Pread)]
+struct MoonstoneHeader {
+    entry_count: u32,
+}
+
+impl ReportHeader for MoonstoneHeader {}
+
+#[derive(Debug)]
+struct MoonstoneDatArchive {
+    file: RandomAccessFile,
+    file_entries: Vec<MoonstoneFileEntry>,
+}
+
+impl archive::Archive for MoonstoneDatArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset as u64, e.file_size as u64))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.file_entries).files().cloned(),
+            output_path,
+        )
+    }
+}
+
+impl MoonstoneDatArchive {
+    fn new_root_dir(entries: &[MoonstoneFileEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    fn extract(
+        &self,
+        entry: &MoonstoneFileEntry,
+    ) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::with_capacity(entry.file_size as usize);
+        buf.resize(entry.file_size as usize, 0);
+        self.file
+            .read_exact_at(entry.file_offset as u64, &mut buf)?;
+        Ok(FileContents {
+            contents: buf.freeze(),
+            type_hint: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct MoonstoneFileEntry {
+    full_path: PathBuf,
+    file_offset: u32,
+    file_size: u32,
+}
+
+impl<'a> ctx::TryFromCtx<'a, ()> for MoonstoneFileEntry {
+    type Error = anyhow::Error;
+
+    fn try_from_ctx(
+        buf: &'a [u8],
+        _: (),
+    ) -> Result<(Self, usize), Self::Error> {
+        let off = &mut 0;
+        let name = SHIFT_JIS
+            .decode(
+                buf.get(..NAME_SIZE)
+                    .context("Out of bounds access")?
+                    .split(|b| *b == 0)
+                    .next()
+                    .context("Could not split")?,
+            )
+            .0
+            .replace("\\", "/");
+        if name.is_empty() {
+            return Err(
+                AkaibuError::Custom("Empty entry name".to_string()).into()
+            );
+        }
+        *off += NAME_SIZE;
+        let full_path = PathBuf::from(name);
+        let file_offset = buf.gread_with::<u32>(off, LE)?;
+        let file_size = buf.gread_with::<u32>(off, LE)?;
+        Ok((
+            Self {
+                full_path,
+                file_offset,
+                file_size,
+            },
+            *off,
+        ))
+    }
+}
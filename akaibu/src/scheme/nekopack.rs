@@ -1,6 +1,8 @@
 use crate::{
     archive::{self, FileContents},
-    util::zlib_decompress,
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name, zlib_decompress},
 };
 use anyhow::Context;
 use bytes::{Bytes, BytesMut};
@@ -26,25 +28,49 @@ impl Scheme for PackScheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 14];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
         let header = buf.pread_with::<PackHeader>(0, LE)?;
         log::debug!("Header: {:#?}", header);
+        let file_size = std::fs::metadata(file_path)?.len();
 
         let mut file_entries = Vec::new();
         let off = &mut 0;
 
-        let mut buf = vec![0; header.entries_size as usize - 4];
+        let entries_size = (header.entries_size as usize)
+            .checked_sub(4)
+            .context("Invalid NEKOPACK index size")?;
+        let mut buf = vec![0; entries_size];
         file.read_exact_at(14, &mut buf)?;
-        while *off < header.entries_size as usize - 4 {
-            file_entries.push(buf.gread(off)?);
+        let scrambled_names = header.has_scrambled_names();
+        while *off < entries_size {
+            file_entries.push(buf.gread_with(off, scrambled_names)?);
         }
+        validate_entries(&file_entries, file_size).map_err(|err| {
+            if scrambled_names {
+                AkaibuError::Custom(format!(
+                    "Could not unscramble NEKOPACK entry names: {}",
+                    err
+                ))
+            } else {
+                err
+            }
+        })?;
 
         let root_dir = PackArchive::new_root_dir(&file_entries);
         let navigable_dir = archive::NavigableDirectory::new(root_dir);
         Ok((Box::new(PackArchive { file, file_entries }), navigable_dir))
     }
 
+    fn inspect(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 14];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<PackHeader>(0, LE)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[NEKOPACK ARC] {}",
@@ -80,19 +106,26 @@ impl archive::Archive for PackArchive {
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset, e.file_size as u64))
+    }
+
     fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
         self.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -104,25 +137,13 @@ impl archive::Archive for PackArchive {
 
 impl PackArchive {
     fn new_root_dir(entries: &[PackFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: entry
-                            .full_path
-                            .to_str()
-                            .expect("Not valid UTF-8")
-                            .to_string(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &PackFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -145,30 +166,44 @@ struct PackHeader {
     entries_size: u32,
 }
 
+impl ReportHeader for PackHeader {}
+
+impl PackHeader {
+    /// Version 1 archives scramble entry names
+    fn has_scrambled_names(&self) -> bool {
+        self.version[0] == b'1'
+    }
+}
+
 #[derive(Debug)]
 struct PackFileEntry {
     file_size: u32,
     file_offset: u64,
     full_path: PathBuf,
+    /// Name could not be decoded as SHIFT-JIS
+    malformed_name: bool,
 }
 
-impl<'a> ctx::TryFromCtx<'a, ()> for PackFileEntry {
+impl<'a> ctx::TryFromCtx<'a, bool> for PackFileEntry {
     type Error = anyhow::Error;
 
     fn try_from_ctx(
         buf: &'a [u8],
-        _ctx: (),
+        scrambled_name: bool,
     ) -> Result<(Self, usize), Self::Error> {
         let off = &mut 0;
         let name_size = buf.gread_with::<u32>(off, LE)? as usize;
-        let full_path = PathBuf::from(
-            SHIFT_JIS
-                .decode(&buf[*off..*off + name_size - 1])
-                .0
-                .replace("\\", "/"),
-        );
-        let file_name_sum: u32 =
-            buf[*off..*off + name_size].iter().map(|b| *b as u32).sum();
+        let mut name = buf
+            .get(*off..*off + name_size)
+            .context("Out of bounds read")?
+            .to_vec();
+        if scrambled_name {
+            unscramble_name(&mut name);
+        }
+        let (full_path, _, malformed_name) = SHIFT_JIS
+            .decode(name.split(|b| *b == 0).next().context("Could not split")?);
+        let full_path = PathBuf::from(full_path.replace("\\", "/"));
+        let file_name_sum: u32 = name.iter().map(|b| *b as u32).sum();
         *off += name_size;
         let file_offset =
             (buf.gread_with::<u32>(off, LE)? ^ file_name_sum) as u64;
@@ -178,12 +213,50 @@ impl<'a> ctx::TryFromCtx<'a, ()> for PackFileEntry {
                 file_size,
                 file_offset,
                 full_path,
+                malformed_name,
             },
             *off,
         ))
     }
 }
 
+/// Name is xored with key derived from its length, including terminating
+/// zero, rotated after every byte
+fn unscramble_name(name: &mut [u8]) {
+    let mut key = ((name.len() >> 3) as u8).wrapping_add(34);
+    name.iter_mut().for_each(|b| {
+        *b ^= key;
+        key = key.rotate_left(3);
+    });
+}
+
+/// Wrong name key shows up as garbage names and offsets, since offsets are
+/// xored with name checksum
+fn validate_entries(
+    entries: &[PackFileEntry],
+    file_size: u64,
+) -> Result<(), AkaibuError> {
+    entries.iter().try_for_each(|entry| {
+        let name = entry.full_path.to_string_lossy();
+        if entry.malformed_name
+            || name.is_empty()
+            || name.chars().any(char::is_control)
+        {
+            Err(AkaibuError::Custom(format!(
+                "Invalid entry name {:?}",
+                entry.full_path
+            )))
+        } else if entry.file_offset + entry.file_size as u64 > file_size {
+            Err(AkaibuError::Custom(format!(
+                "Entry {} is out of archive bounds",
+                display_name(&entry.full_path)
+            )))
+        } else {
+            Ok(())
+        }
+    })
+}
+
 fn decompress(src: &mut [u8]) -> anyhow::Result<Bytes> {
     let mut s = ((src.len() >> 3) as u8).wrapping_add(34);
     if src.len() > 32 {
@@ -0,0 +1,244 @@
+use super::Scheme;
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{text::display_name, volume::MultiVolumeFile},
+};
+use anyhow::Context;
+use bytes::BytesMut;
+use positioned_io::ReadAt;
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use scroll::{Pread, LE};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Entries start at sector boundary
+const SECTOR_SIZE: u64 = 2048;
+/// Sector offset and size of each entry
+const ENTRY_SIZE: usize = 8;
+/// Upper bound of index size, so unrelated `.bin` files with garbage entry
+/// count are rejected before reading index
+const MAX_INDEX_SIZE: u64 = 1 << 20;
+
+/// Containers used by Rejet and Otomate PSP/Vita ports. Index is just entry
+/// count and table of sector offsets and sizes, names are not stored.
+/// Larger containers are split into `.b01`, `.b02`... volumes.
+#[derive(Debug, Clone)]
+pub enum OtomateBinScheme {
+    Universal,
+}
+
+impl Scheme for OtomateBinScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let file = MultiVolumeFile::open(file_path)?;
+        let archive = Bin::parse(&file)?;
+        log::debug!(
+            "Volumes: {}, Archive: {:#?}",
+            file.volume_count(),
+            archive
+        );
+
+        let root_dir = BinArchive::new_root_dir(&archive.entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((Box::new(BinArchive { file, archive }), navigable_dir))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 4];
+        MultiVolumeFile::open(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<BinHeader>(0, LE)?.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[OTOMATE BIN] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+#[derive(Debug)]
+struct BinArchive {
+    file: MultiVolumeFile,
+    archive: Bin,
+}
+
+impl archive::Archive for BinArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        self.archive.entries.par_iter().try_for_each(|entry| {
+            let file_contents = self.extract(entry)?;
+            let mut output_file_name = PathBuf::from(output_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
+            std::fs::create_dir_all(
+                &output_file_name
+                    .parent()
+                    .context("Could not get parent directory")?,
+            )?;
+            log::debug!(
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
+                entry
+            );
+            File::create(output_file_name)?
+                .write_all(&file_contents.contents)?;
+            Ok(())
+        })
+    }
+
+    fn stored_size(&self, _archive_path: &Path) -> anyhow::Result<Option<u64>> {
+        Ok(Some(self.file.size()))
+    }
+}
+
+impl BinArchive {
+    fn new_root_dir(entries: &[BinEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset,
+                entry.file_size,
+            )
+        }))
+    }
+    fn extract(&self, entry: &BinEntry) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::with_capacity(entry.file_size as usize);
+        buf.resize(entry.file_size as usize, 0);
+        self.file.read_exact_at(entry.file_offset, &mut buf)?;
+        Ok(FileContents {
+            contents: buf.freeze(),
+            type_hint: None,
+        })
+    }
+}
+
+#[derive(Debug, Pread)]
+struct BinHeader {
+    entry_count: u32,
+}
+
+impl ReportHeader for BinHeader {}
+
+#[derive(Debug)]
+struct Bin {
+    entries: Vec<BinEntry>,
+}
+
+impl Bin {
+    fn parse(file: &MultiVolumeFile) -> anyhow::Result<Self> {
+        let entries = read_index(file)?
+            .into_iter()
+            .enumerate()
+            .map(|(i, (file_offset, file_size))| BinEntry {
+                full_path: PathBuf::from(format!(
+                    "{:04}.{}",
+                    i,
+                    guess_extension(file, file_offset, file_size)
+                )),
+                file_offset,
+                file_size,
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+}
+
+/// Format has no magic, so `.bin` file is taken for container only when its
+/// table is valid, see [`read_index`]
+pub(crate) fn is_otomate_bin(file_path: &Path) -> bool {
+    MultiVolumeFile::open(file_path)
+        .and_then(|file| read_index(&file))
+        .is_ok()
+}
+
+/// Offsets and sizes of entries. Table is accepted only when it ends before
+/// first entry and every entry starts at sector, is in order and inside of
+/// volumes.
+fn read_index(file: &MultiVolumeFile) -> anyhow::Result<Vec<(u64, u64)>> {
+    let invalid = || AkaibuError::Custom("Invalid BIN index".to_string());
+    let mut buf = vec![0; 4];
+    file.read_exact_at(0, &mut buf)?;
+    let entry_count = buf.pread_with::<BinHeader>(0, LE)?.entry_count as u64;
+    let index_end = 4 + entry_count * ENTRY_SIZE as u64;
+    if entry_count == 0 || index_end > MAX_INDEX_SIZE || index_end > file.size()
+    {
+        return Err(invalid().into());
+    }
+    let mut index = vec![0; (index_end - 4) as usize];
+    file.read_exact_at(4, &mut index)?;
+
+    let mut data_end = index_end;
+    index
+        .chunks_exact(ENTRY_SIZE)
+        .map(|entry| -> anyhow::Result<(u64, u64)> {
+            // Offset is stored in sectors
+            let file_offset =
+                entry.pread_with::<u32>(0, LE)? as u64 * SECTOR_SIZE;
+            let file_size = entry.pread_with::<u32>(4, LE)? as u64;
+            if file_offset < data_end || file_offset + file_size > file.size() {
+                return Err(invalid().into());
+            }
+            data_end = file_offset + file_size;
+            Ok((file_offset, file_size))
+        })
+        .collect()
+}
+
+/// Names are not stored, so extension is picked from entry magic
+fn guess_extension(
+    file: &MultiVolumeFile,
+    offset: u64,
+    size: u64,
+) -> &'static str {
+    let mut magic = [0; 4];
+    if size < magic.len() as u64
+        || file.read_exact_at(offset, &mut magic).is_err()
+    {
+        return "bin";
+    }
+    match &magic {
+        [0x89, b'P', b'N', b'G'] => "png",
+        b"OggS" => "ogg",
+        b"RIFF" => "wav",
+        b"MIG." => "gim",
+        b"PSMF" => "pmf",
+        _ => "bin",
+    }
+}
+
+#[derive(Debug)]
+struct BinEntry {
+    file_offset: u64,
+    file_size: u64,
+    full_path: PathBuf,
+}
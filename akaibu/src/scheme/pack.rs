@@ -0,0 +1,102 @@
+use super::Scheme;
+use crate::{error::AkaibuError, util};
+use std::{
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+/// File written into archive
+#[derive(Debug, Clone)]
+pub struct PackInput {
+    /// Path inside archive
+    pub full_path: PathBuf,
+    pub contents: Vec<u8>,
+}
+
+/// Every file under directory, with paths relative to it
+pub fn read_directory(dir: &Path) -> anyhow::Result<Vec<PackInput>> {
+    let mut files = Vec::new();
+    util::walk_files(dir, &mut files)?;
+    files.sort();
+    files
+        .into_iter()
+        .map(|path| {
+            Ok(PackInput {
+                full_path: path.strip_prefix(dir)?.to_path_buf(),
+                contents: std::fs::read(&path)?,
+            })
+        })
+        .collect()
+}
+
+/// Path inside archive as text, with directories split by given separator
+pub(crate) fn path_name(
+    full_path: &Path,
+    separator: &str,
+) -> anyhow::Result<String> {
+    let name = full_path.to_str().ok_or_else(|| {
+        AkaibuError::Custom(format!("Not valid UTF-8: {:?}", full_path))
+    })?;
+    Ok(name.replace(&['/', '\\'][..], separator))
+}
+
+/// Archive format directory can be packed into
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PackFormat {
+    #[cfg(feature = "fmt-pf8")]
+    Pf8,
+    #[cfg(feature = "fmt-gxp")]
+    Gxp,
+    #[cfg(feature = "fmt-buriko")]
+    Buriko,
+    #[cfg(feature = "fmt-esc-arc2")]
+    EscArc2,
+    #[cfg(feature = "fmt-ikura")]
+    IkuraGdl,
+}
+
+impl PackFormat {
+    /// Scheme writing archives of format
+    // Match has no arms when no pack format is enabled
+    #[allow(unreachable_code, unused_variables)]
+    pub fn scheme(self) -> Box<dyn Scheme> {
+        let archive: crate::magic::Archive = match self {
+            #[cfg(feature = "fmt-pf8")]
+            Self::Pf8 => crate::magic::Archive::Pf8,
+            #[cfg(feature = "fmt-gxp")]
+            Self::Gxp => crate::magic::Archive::Gxp,
+            #[cfg(feature = "fmt-buriko")]
+            Self::Buriko => crate::magic::Archive::Buriko,
+            #[cfg(feature = "fmt-esc-arc2")]
+            Self::EscArc2 => crate::magic::Archive::EscArc2,
+            #[cfg(feature = "fmt-ikura")]
+            Self::IkuraGdl => crate::magic::Archive::IkuraGdl,
+        };
+        // Every scheme of format writes the same archive
+        archive.get_schemes().remove(0)
+    }
+}
+
+impl FromStr for PackFormat {
+    type Err = AkaibuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            #[cfg(feature = "fmt-pf8")]
+            "pf8" => Ok(Self::Pf8),
+            #[cfg(feature = "fmt-gxp")]
+            "gxp" => Ok(Self::Gxp),
+            #[cfg(feature = "fmt-buriko")]
+            "buriko" | "bgi" => Ok(Self::Buriko),
+            #[cfg(feature = "fmt-esc-arc2")]
+            "esc-arc2" | "escarc2" => Ok(Self::EscArc2),
+            #[cfg(feature = "fmt-ikura")]
+            "gdl" | "ikura" => Ok(Self::IkuraGdl),
+            _ => Err(AkaibuError::Custom(format!(
+                "Unknown pack format: {}, expected pf8, gxp, buriko, \
+                 esc-arc2 or gdl",
+                s
+            ))),
+        }
+    }
+}
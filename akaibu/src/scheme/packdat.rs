@@ -0,0 +1,275 @@
+use super::Scheme;
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name, zlib_decompress},
+};
+use anyhow::Context;
+use bytes::{Bytes, BytesMut};
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use scroll::{Pread, LE};
+use std::path::{Path, PathBuf};
+
+/// Magic, entry count and total size of entries
+const HEADER_SIZE: u64 = 16;
+const ENTRY_SIZE: usize = 48;
+const NAME_SIZE: usize = 32;
+/// Entry data is xored with key derived from its size
+const FLAG_ENCRYPTED: u32 = 0x1_0000;
+/// Entry data is zlib stream after decryption
+const FLAG_COMPRESSED: u32 = 0x1;
+
+/// PACKDAT archives used by Winters and Clochette titles
+#[derive(Debug, Clone)]
+pub enum PackdatScheme {
+    Universal,
+}
+
+impl Scheme for PackdatScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let mut buf = vec![0; HEADER_SIZE as usize];
+        let file = util::fs::open_archive_file(file_path)?;
+        file.read_exact_at(0, &mut buf)?;
+        let file_size = std::fs::metadata(file_path)?.len();
+
+        let entry_count =
+            buf.pread_with::<PackdatHeader>(0, LE)?.entry_count as u64;
+        let index_size = entry_count * ENTRY_SIZE as u64;
+        if HEADER_SIZE + index_size > file_size {
+            return Err(AkaibuError::Custom(format!(
+                "Invalid PACKDAT entry count: {}",
+                entry_count
+            ))
+            .into());
+        }
+        let mut index = vec![0; index_size as usize];
+        file.read_exact_at(HEADER_SIZE, &mut index)?;
+        let archive = Packdat {
+            entries: parse_index(&index, file_size)?,
+        };
+        log::debug!("Archive: {:#?}", archive);
+
+        let root_dir = PackdatArchive::new_root_dir(&archive.entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((Box::new(PackdatArchive { file, archive }), navigable_dir))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; HEADER_SIZE as usize];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<PackdatHeader>(0, LE)?.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[PACKDAT] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+#[derive(Debug, Pread)]
+struct PackdatHeader {
+    magic: [u8; 8],
+    entry_count: u32,
+    /// Total size of entries
+    data_size: u32,
+}
+
+impl ReportHeader for PackdatHeader {}
+
+#[derive(Debug)]
+struct PackdatArchive {
+    file: RandomAccessFile,
+    archive: Packdat,
+}
+
+impl archive::Archive for PackdatArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset as u64, e.file_size as u64))
+    }
+
+    fn entry_ext(&self, entry: &archive::FileEntry) -> archive::FileEntryExt {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| archive::FileEntryExt {
+                encryption: if e.flags & FLAG_ENCRYPTED != 0 {
+                    Some("XOR size key".to_string())
+                } else {
+                    None
+                },
+                compression: if e.flags & FLAG_COMPRESSED != 0 {
+                    Some("zlib".to_string())
+                } else {
+                    None
+                },
+                modified: None,
+                overrides: None,
+            })
+            .unwrap_or_default()
+    }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .filter(|e| e.flags & (FLAG_ENCRYPTED | FLAG_COMPRESSED) == 0)
+            .map(|e| (e.file_offset as u64, e.file_size as u64))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.archive.entries).files().cloned(),
+            output_path,
+        )
+    }
+}
+
+impl PackdatArchive {
+    fn new_root_dir(entries: &[PackdatEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    fn extract(&self, entry: &PackdatEntry) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::with_capacity(entry.file_size as usize);
+        buf.resize(entry.file_size as usize, 0);
+        self.file
+            .read_exact_at(entry.file_offset as u64, &mut buf)?;
+        if entry.flags & FLAG_ENCRYPTED != 0 {
+            decrypt(&mut buf);
+        }
+        let contents = if entry.flags & FLAG_COMPRESSED != 0 {
+            let contents = zlib_decompress(&buf)?;
+            if contents.len() != entry.unpacked_size as usize {
+                return Err(AkaibuError::Custom(format!(
+                    "Unpacked size of {:?} is {}, expected {}",
+                    entry.full_path,
+                    contents.len(),
+                    entry.unpacked_size
+                ))
+                .into());
+            }
+            Bytes::from(contents)
+        } else {
+            buf.freeze()
+        };
+        Ok(FileContents {
+            contents,
+            type_hint: None,
+        })
+    }
+}
+
+/// Each 32-bit word is xored with key derived from number of words, key is
+/// rotated by decrypted word after each one. Trailing bytes are left as is.
+/// Follows `Decrypt` of GARbro `ArcPACKDAT.cs`, which xors word in place
+/// and then takes rotation from it.
+fn decrypt(buf: &mut [u8]) {
+    let word_count = (buf.len() / 4) as u32;
+    let mut key = (word_count << ((word_count & 7) + 8)) ^ word_count;
+    buf.chunks_exact_mut(4).for_each(|word| {
+        let value =
+            u32::from_le_bytes([word[0], word[1], word[2], word[3]]) ^ key;
+        word.copy_from_slice(&value.to_le_bytes());
+        key = key.rotate_left(value % 24);
+    });
+}
+
+/// Each entry has zero padded SHIFT-JIS name, offset, flags, unpacked size
+/// and stored size
+fn parse_index(
+    index: &[u8],
+    archive_size: u64,
+) -> anyhow::Result<Vec<PackdatEntry>> {
+    index
+        .chunks_exact(ENTRY_SIZE)
+        .map(|entry| {
+            let name = entry[..NAME_SIZE]
+                .split(|b| *b == 0)
+                .next()
+                .filter(|name| !name.is_empty())
+                .context("Invalid PACKDAT entry name")?;
+            let full_path =
+                PathBuf::from(SHIFT_JIS.decode(name).0.replace("\\", "/"));
+            let mut off = NAME_SIZE;
+            let file_offset = entry.gread_with::<u32>(&mut off, LE)?;
+            let flags = entry.gread_with::<u32>(&mut off, LE)?;
+            let unpacked_size = entry.gread_with::<u32>(&mut off, LE)?;
+            let file_size = entry.gread_with::<u32>(&mut off, LE)?;
+            if file_offset as u64 + file_size as u64 > archive_size {
+                return Err(AkaibuError::Custom(format!(
+                    "PACKDAT entry {} out of bounds",
+                    display_name(&full_path)
+                ))
+                .into());
+            }
+            Ok(PackdatEntry {
+                full_path,
+                file_offset,
+                file_size,
+                unpacked_size,
+                flags,
+            })
+        })
+        .collect()
+}
+
+#[derive(Debug)]
+struct Packdat {
+    entries: Vec<PackdatEntry>,
+}
+
+#[derive(Debug)]
+struct PackdatEntry {
+    file_offset: u32,
+    file_size: u32,
+    unpacked_size: u32,
+    flags: u32,
+    full_path: PathBuf,
+}
@@ -0,0 +1,397 @@
+use super::Scheme;
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    key_file::KeyFile,
+    util::{self, bounds, text::display_name, zlib_decompress},
+};
+use anyhow::Context;
+use blowfish::{
+    cipher::{generic_array::GenericArray, BlockCipher, NewBlockCipher},
+    BlowfishLE,
+};
+use bytes::BytesMut;
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use scroll::{ctx, Pread, LE};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+const INDEX_OFFSET: u64 = 4;
+const KEY_SIZE: usize = 0x20;
+
+#[derive(Debug, Clone)]
+pub enum PazScheme {
+    Universal,
+}
+
+impl Scheme for PazScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let key_file = KeyFile::load_or_companion(None, file_path)?;
+        self.extract_with_key(file_path, &key_file)
+    }
+
+    fn extract_with_key(
+        &self,
+        file_path: &Path,
+        key_file: &KeyFile,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let (index_key, data_key) = Self::get_keys(file_path, key_file)?;
+        let index_cipher = new_cipher(&index_key)?;
+        let data_cipher = new_cipher(&data_key)?;
+
+        let file = util::fs::open_archive_file(file_path)?;
+        let file_size = std::fs::metadata(file_path)?.len();
+
+        let PazHeader {
+            index_size,
+            xor_key,
+        } = read_header(&file)?;
+        if index_size & 7 != 0 || INDEX_OFFSET + index_size as u64 > file_size {
+            return Err(AkaibuError::Custom(format!(
+                "Invalid PAZ index size: {}",
+                index_size
+            ))
+            .into());
+        }
+        let mut buf = vec![0; index_size as usize];
+        file.read_exact_at(INDEX_OFFSET, &mut buf)?;
+        decrypt(&mut buf, &index_cipher);
+        xor(&mut buf, xor_key);
+
+        let archive = buf.pread::<Paz>(0)?;
+        log::debug!("Archive: {:#?}", archive);
+        if let Some(entry) = archive.file_entries.iter().find(|entry| {
+            entry.file_offset + entry.aligned_size as u64 > file_size
+        }) {
+            return Err(AkaibuError::Custom(format!(
+                "Entry {} is out of archive bounds, wrong key?",
+                display_name(&entry.full_path)
+            ))
+            .into());
+        }
+
+        let root_dir = PazArchive::new_root_dir(&archive.file_entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((
+            Box::new(PazArchive {
+                file,
+                archive,
+                xor_key,
+                data_cipher,
+            }),
+            navigable_dir,
+        ))
+    }
+
+    /// Index size is read without keys, index itself is encrypted
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        Ok(vec![
+            read_header(&util::fs::open_archive_file(file_path)?)?.report()
+        ])
+    }
+
+    fn needs_key_file(&self) -> bool {
+        true
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[PAZ] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+#[derive(Debug)]
+struct PazHeader {
+    index_size: u32,
+    /// Top byte of stored index size, xored over index size and index
+    xor_key: u8,
+}
+
+impl ReportHeader for PazHeader {}
+
+/// Index size with its xor key removed
+fn read_header(file: &RandomAccessFile) -> anyhow::Result<PazHeader> {
+    let mut buf = vec![0; INDEX_OFFSET as usize];
+    file.read_exact_at(0, &mut buf)?;
+    let mut index_size = buf.pread_with::<u32>(0, LE)?;
+    let xor_key = (index_size >> 24) as u8;
+    if xor_key != 0 {
+        index_size ^= u32::from_le_bytes([xor_key; 4]);
+    }
+    Ok(PazHeader {
+        index_size,
+        xor_key,
+    })
+}
+
+impl PazScheme {
+    /// Keys are looked up by archive name first (`bg.index`, `bg.data`),
+    /// then shared `index`/`data` values. Raw key dump holds index key
+    /// followed by data key.
+    fn get_keys(
+        file_path: &Path,
+        key_file: &KeyFile,
+    ) -> anyhow::Result<(Vec<u8>, Vec<u8>)> {
+        let arc_name = file_path
+            .file_stem()
+            .context("Could not get file name")?
+            .to_string_lossy()
+            .to_lowercase();
+        let named = |kind: &str| {
+            key_file
+                .get(&format!("{}.{}", arc_name, kind))
+                .or_else(|| key_file.get(kind))
+                .map(|key| key.to_vec())
+        };
+        if let (Some(index_key), Some(data_key)) =
+            (named("index"), named("data"))
+        {
+            return Ok((index_key, data_key));
+        }
+        let raw = key_file.raw();
+        if raw.len() == KEY_SIZE * 2 {
+            Ok((raw[..KEY_SIZE].to_vec(), raw[KEY_SIZE..].to_vec()))
+        } else {
+            Err(AkaibuError::Custom(format!(
+                "Could not find PAZ keys for {:?} in key file {:?}",
+                arc_name,
+                key_file.path()
+            ))
+            .into())
+        }
+    }
+}
+
+struct PazArchive {
+    file: RandomAccessFile,
+    archive: Paz,
+    xor_key: u8,
+    data_cipher: BlowfishLE,
+}
+
+impl std::fmt::Debug for PazArchive {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("PazArchive")
+            .field("file", &self.file)
+            .field("archive", &self.archive)
+            .field("xor_key", &self.xor_key)
+            .finish()
+    }
+}
+
+impl archive::Archive for PazArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset, e.aligned_size as u64))
+    }
+
+    fn entry_ext(&self, entry: &archive::FileEntry) -> archive::FileEntryExt {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| archive::FileEntryExt {
+                encryption: Some(if self.xor_key != 0 {
+                    format!("Blowfish, XOR {:#04X}", self.xor_key)
+                } else {
+                    "Blowfish".to_string()
+                }),
+                compression: if e.is_packed {
+                    Some("zlib".to_string())
+                } else {
+                    None
+                },
+                modified: None,
+                overrides: None,
+            })
+            .unwrap_or_default()
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        self.archive.file_entries.par_iter().try_for_each(|entry| {
+            let file_contents = self.extract(entry)?;
+            let mut output_file_name = PathBuf::from(output_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
+            std::fs::create_dir_all(
+                &output_file_name
+                    .parent()
+                    .context("Could not get parent directory")?,
+            )?;
+            log::debug!(
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
+                entry
+            );
+            File::create(output_file_name)?
+                .write_all(&file_contents.contents)?;
+            Ok(())
+        })
+    }
+}
+
+impl PazArchive {
+    fn new_root_dir(entries: &[PazFileEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    fn extract(&self, entry: &PazFileEntry) -> anyhow::Result<FileContents> {
+        let mut buf = vec![0; entry.aligned_size as usize];
+        self.file.read_exact_at(entry.file_offset, &mut buf)?;
+        xor(&mut buf, self.xor_key);
+        decrypt(&mut buf, &self.data_cipher);
+        buf.truncate(entry.file_size as usize);
+        if entry.is_packed {
+            buf = zlib_decompress(&buf)?;
+        }
+        Ok(FileContents {
+            contents: BytesMut::from(&buf[..]).freeze(),
+            type_hint: None,
+        })
+    }
+}
+
+fn new_cipher(key: &[u8]) -> anyhow::Result<BlowfishLE> {
+    BlowfishLE::new_varkey(key).map_err(|_| {
+        AkaibuError::Custom("Invalid Blowfish key length".to_string()).into()
+    })
+}
+
+fn decrypt(buf: &mut [u8], cipher: &BlowfishLE) {
+    buf.chunks_exact_mut(8).for_each(|block| {
+        cipher.decrypt_block(GenericArray::from_mut_slice(block))
+    });
+}
+
+fn xor(buf: &mut [u8], key: u8) {
+    if key != 0 {
+        buf.iter_mut().for_each(|b| *b ^= key);
+    }
+}
+
+#[derive(Debug)]
+struct Paz {
+    entry_count: u32,
+    file_entries: Vec<PazFileEntry>,
+}
+
+impl<'a> ctx::TryFromCtx<'a, ()> for Paz {
+    type Error = anyhow::Error;
+
+    fn try_from_ctx(
+        buf: &'a [u8],
+        _: (),
+    ) -> Result<(Self, usize), Self::Error> {
+        let off = &mut 0;
+        let entry_count = buf.gread_with::<u32>(off, LE)?;
+        // Checked before allocating, count comes from file
+        if entry_count as usize > (buf.len() - *off) / MIN_ENTRY_SIZE {
+            return Err(bounds::malformed("PAZ entry count").into());
+        }
+        let mut file_entries = Vec::with_capacity(entry_count as usize);
+        for _ in 0..entry_count {
+            file_entries.push(buf.gread(off)?);
+        }
+        Ok((
+            Self {
+                entry_count,
+                file_entries,
+            },
+            *off,
+        ))
+    }
+}
+
+/// Name terminator, offset and four sizes and flags
+const MIN_ENTRY_SIZE: usize = 1 + 8 + 4 * 4;
+
+#[derive(Debug)]
+struct PazFileEntry {
+    full_path: PathBuf,
+    file_offset: u64,
+    unpacked_size: u32,
+    file_size: u32,
+    aligned_size: u32,
+    is_packed: bool,
+}
+
+impl<'a> ctx::TryFromCtx<'a, ()> for PazFileEntry {
+    type Error = anyhow::Error;
+
+    fn try_from_ctx(
+        buf: &'a [u8],
+        _: (),
+    ) -> Result<(Self, usize), Self::Error> {
+        let off = &mut 0;
+        let name_bytes = buf
+            .get(*off..)
+            .context("Out of bounds access")?
+            .split(|b| *b == 0)
+            .next()
+            .context("Could not split")?;
+        let name = SHIFT_JIS.decode(name_bytes).0.replace("\\", "/");
+        *off += name_bytes.len() + 1;
+        let full_path = PathBuf::from(name);
+        let file_offset = buf.gread_with::<u64>(off, LE)?;
+        let unpacked_size = buf.gread_with::<u32>(off, LE)?;
+        let file_size = buf.gread_with::<u32>(off, LE)?;
+        let aligned_size = buf.gread_with::<u32>(off, LE)?;
+        let is_packed = buf.gread_with::<u32>(off, LE)? != 0;
+        Ok((
+            Self {
+                full_path,
+                file_offset,
+                unpacked_size,
+                file_size,
+                aligned_size,
+                is_packed,
+            },
+            *off,
+        ))
+    }
+}
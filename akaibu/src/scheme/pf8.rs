@@ -1,16 +1,22 @@
-use super::Scheme;
-use crate::archive::{self, FileContents};
+use super::{pack::PackInput, Scheme};
+use crate::{
+    archive::{self, FileContents},
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name},
+};
 use anyhow::Context;
-use bytes::BytesMut;
+use bytes::{Bytes, BytesMut};
 use positioned_io::{RandomAccessFile, ReadAt};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{ctx, Pread, LE};
 use std::{
     fs::File,
-    io::Write,
+    io::{Read, Write},
     path::{Path, PathBuf},
 };
 
+pub mod writer;
+
 #[derive(Debug, Clone)]
 pub enum Pf8Scheme {
     Universal,
@@ -25,7 +31,7 @@ impl Scheme for Pf8Scheme {
         archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 11];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
 
         let header = buf.pread::<Pf8Header>(0)?;
@@ -51,6 +57,54 @@ impl Scheme for Pf8Scheme {
             navigable_dir,
         ))
     }
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 11];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread::<Pf8Header>(0)?.report()])
+    }
+
+    fn streams_index(&self) -> bool {
+        true
+    }
+    fn list_stream(
+        &self,
+        reader: &mut dyn Read,
+    ) -> anyhow::Result<Vec<archive::FileEntryMetadata>> {
+        let mut buf = vec![0; 11];
+        reader.read_exact(&mut buf)?;
+        let header = buf.pread::<Pf8Header>(0)?;
+
+        let index_size = header
+            .archive_data_size
+            .checked_sub(4)
+            .context("Index size out of bounds")?;
+        let mut buf = Vec::new();
+        reader.take(index_size as u64).read_to_end(&mut buf)?;
+        let archive = buf.pread_with::<Pf8>(0, header)?;
+        Ok(archive
+            .file_entries
+            .iter()
+            .map(|entry| archive::FileEntryMetadata {
+                full_path: entry.full_path.clone(),
+                file_offset: entry.file_offset as u64,
+                file_size: entry.file_size as u64,
+                ext: Pf8Archive::ext(),
+            })
+            .collect())
+    }
+
+    fn can_pack(&self) -> bool {
+        true
+    }
+
+    fn write_archive(
+        &self,
+        inputs: Vec<PackInput>,
+        mut output: &mut dyn Write,
+    ) -> anyhow::Result<()> {
+        writer::write_pf8(inputs, &mut output)
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[PF8] {}",
@@ -87,19 +141,58 @@ impl archive::Archive for Pf8Archive {
             .context("File not found")?
     }
 
+    fn entry_ext(&self, _entry: &archive::FileEntry) -> archive::FileEntryExt {
+        Self::ext()
+    }
+
+    fn extract_prefix(
+        &self,
+        entry: &archive::FileEntry,
+        len: usize,
+    ) -> anyhow::Result<FileContents> {
+        let mut buf = vec![0; len.min(entry.file_size as usize)];
+        self.file.read_exact_at(entry.file_offset, &mut buf)?;
+        self.decrypt_file(&mut buf)?;
+        Ok(FileContents {
+            contents: BytesMut::from(&buf[..]).freeze(),
+            type_hint: None,
+        })
+    }
+
+    fn raw_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        Some((entry.file_offset, entry.file_size))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn decode_raw(
+        &self,
+        _entry: &archive::FileEntry,
+        raw: Bytes,
+    ) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::from(&raw[..]);
+        self.decrypt_file(&mut buf)?;
+        Ok(FileContents {
+            contents: buf.freeze(),
+            type_hint: None,
+        })
+    }
+
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
         self.archive.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -110,29 +203,22 @@ impl archive::Archive for Pf8Archive {
 }
 
 impl Pf8Archive {
+    fn ext() -> archive::FileEntryExt {
+        archive::FileEntryExt {
+            encryption: Some("SHA-1 XOR".to_string()),
+            compression: None,
+            modified: None,
+            overrides: None,
+        }
+    }
     fn new_root_dir(entries: &[Pf8FileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: String::from(
-                            entry
-                                .full_path
-                                .file_name()
-                                .expect("No file name")
-                                .to_str()
-                                .expect("Not valid UTF-8"),
-                        ),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &Pf8FileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -193,6 +279,8 @@ struct Pf8Header {
     file_entries_count: u32,
 }
 
+impl ReportHeader for Pf8Header {}
+
 #[derive(Debug)]
 struct Pf8FileEntry {
     file_name_size: u32,
@@ -0,0 +1,53 @@
+pub use crate::scheme::pack::{read_directory, PackInput};
+use crate::{error::AkaibuError, scheme::pack};
+use std::{convert::TryFrom, io::Write};
+
+/// Magic and index size preceding index
+const HEADER_SIZE: usize = 7;
+
+/// Write PF8 archive with entries stored in order of inputs right after
+/// index. Entries are xored with SHA-1 of index, which starts with entry
+/// count and is hashed after offsets of entries are filled in.
+pub fn write_pf8(
+    inputs: Vec<PackInput>,
+    output: &mut impl Write,
+) -> anyhow::Result<()> {
+    let names = inputs
+        .iter()
+        .map(|input| pack::path_name(&input.full_path, "\\"))
+        .collect::<anyhow::Result<Vec<String>>>()?;
+    let index_size =
+        4 + names.iter().map(|name| name.len() + 16).sum::<usize>();
+
+    let mut index = Vec::with_capacity(index_size);
+    index.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    let mut offset = (HEADER_SIZE + index_size) as u64;
+    for (input, name) in inputs.iter().zip(&names) {
+        let file_offset = u32::try_from(offset).map_err(|_| {
+            AkaibuError::Custom(format!(
+                "Archive is too large to hold {:?}",
+                input.full_path
+            ))
+        })?;
+        index.extend_from_slice(&(name.len() as u32).to_le_bytes());
+        index.extend_from_slice(name.as_bytes());
+        // Unknown, zero in game archives
+        index.extend_from_slice(&0u32.to_le_bytes());
+        index.extend_from_slice(&file_offset.to_le_bytes());
+        index.extend_from_slice(&(input.contents.len() as u32).to_le_bytes());
+        offset += input.contents.len() as u64;
+    }
+    let key = sha1::Sha1::from(&index).digest().bytes();
+
+    output.write_all(b"pf8")?;
+    output.write_all(&(index.len() as u32).to_le_bytes())?;
+    output.write_all(&index)?;
+    for input in inputs {
+        let mut contents = input.contents;
+        for (i, b) in contents.iter_mut().enumerate() {
+            *b ^= key[i % key.len()];
+        }
+        output.write_all(&contents)?;
+    }
+    Ok(())
+}
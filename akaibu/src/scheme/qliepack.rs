@@ -2,10 +2,12 @@ use std::{
     collections::HashMap, convert::TryInto, fs::File, io::Write, path::PathBuf,
 };
 
-use super::Scheme;
+use super::{registry::SchemeRegistry, Scheme};
 use crate::{
     archive::{self, Archive, FileContents, NavigableDirectory},
     error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, bounds, text::display_name},
 };
 use anyhow::Context;
 use bytes::BytesMut;
@@ -33,106 +35,28 @@ static BYTE_BUF: Lazy<[u8; 256]> = Lazy::new(|| {
     dest
 });
 
-const KEYS_PATH: &str = "qlie/keys.json";
+pub(crate) const KEYS_PATH: &str = "qlie/keys.json";
 
-static KEYS: Lazy<HashMap<String, HashMap<String, Vec<u32>>>> =
-    Lazy::new(|| {
-        let keys = serde_json::from_slice(
-            &crate::Resources::get(KEYS_PATH)
-                .expect("Could not find file: qlie/keys.json"),
-        )
-        .expect("Could not deserialize resource json");
-        keys
-    });
+type KeyTable = HashMap<String, HashMap<String, Vec<u32>>>;
 
 impl Scheme for PackScheme {
     fn extract(
         &self,
         file_path: &std::path::Path,
     ) -> anyhow::Result<(Box<dyn Archive>, NavigableDirectory)> {
-        let mut buf = vec![0; 0x440];
-        let metadata = std::fs::metadata(&file_path)?;
-        let file = RandomAccessFile::open(file_path)?;
-        file.read_exact_at(metadata.len() - 0x440, &mut buf)?;
-        let header = buf.pread_with::<PackHeader>(0x440 - 0x1C, LE)?;
-
-        if &header.magic != b"FilePackVer"
-            || (&header.version != b"3.0" && &header.version != b"3.1")
-        {
-            return Err(AkaibuError::Custom(format!(
-                "Unsupported archive: {} version: {}",
-                String::from_utf8_lossy(&header.magic),
-                String::from_utf8_lossy(&header.version)
-            ))
-            .into());
-        }
-
-        let header2 = buf.pread_with::<PackHeader2>(0, LE)?;
-        let header2_data = &buf[0x24..];
-        log::debug!("Header: {:#?}", header);
-
-        let decrypt_key = if &header.version == b"3.0" {
-            generate_decrypt_key(&header2_data[..0x100])?
-        } else {
-            generate_decrypt_key3_1(&header2_data[..0x100])?
-        };
-
-        let mut buf2 = vec![0; header2.hash_data_size as usize];
-        file.read_exact_at(
-            metadata.len() - 0x440 - header2.hash_data_size as u64,
-            &mut buf2,
-        )?;
-
-        let hash_data_header = buf2.pread::<HashDataHeader>(0)?;
-        if &hash_data_header.magic != b"HashVer"
-            || (&hash_data_header.version != b"1.3"
-                && &hash_data_header.version != b"1.4")
-        {
-            return Err(AkaibuError::Custom(format!(
-                "Unsupported hash data: {} version: {}",
-                String::from_utf8_lossy(&hash_data_header.magic),
-                String::from_utf8_lossy(&hash_data_header.version)
-            ))
-            .into());
-        }
-        let hash_data = if let Some(is_compressed) = hash_data_header.compressed
-        {
-            if is_compressed == 1 {
-                decompress(&decrypt_with_decrypt_key(&buf2[0x44..], 0x428)?)?
-            } else {
-                decrypt_with_decrypt_key(&buf2[0x44..], 0x428)?
-            }
-        } else {
-            decompress(&decrypt_with_decrypt_key(&buf2[0x20..], 0x428)?)?
-        };
-        let entries = if &hash_data_header.version == b"1.4" {
-            parse_hash_data1_4(&hash_data, hash_data_header.iter_count)?
-        } else {
-            parse_hash_data(&hash_data, hash_data_header.iter_count)?
-        };
-        let hash_data_version = if &hash_data_header.version == b"1.4" {
-            14
-        } else {
-            13
-        };
-
-        let mut entry_data = vec![
-            0;
-            (metadata.len() as usize
-                - 0x440
-                - header2.hash_data_size as usize)
-                - header.entry_data_offset as usize
-        ];
-        file.read_exact_at(header.entry_data_offset as u64, &mut entry_data)?;
-        let file_entries =
-            parse_entry_data(&entry_data, entries, hash_data_version)?;
-        log::debug!("{:#?}", file_entries);
+        let PackIndex {
+            file,
+            header,
+            decrypt_key,
+            file_entries,
+        } = read_index(file_path)?;
 
         let root_dir = PackArchive::new_root_dir(&file_entries);
         let navigable_dir = archive::NavigableDirectory::new(root_dir);
 
-        let keys = KEYS
-            .get(match self {
+        let keys = SchemeRegistry::global()
+            .load::<KeyTable>(KEYS_PATH)?
+            .remove(match self {
                 Self::KoikenOtome => "KoikenOtome",
                 Self::KoikenOtomeFD => "KoikenOtomeFD",
                 Self::Biman3 => "Biman3",
@@ -141,14 +65,14 @@ impl Scheme for PackScheme {
                 Self::Biman2 => "Biman2",
                 Self::Biman2_5 => "Biman2_5",
             })
-            .context(format!("Could not find keys for {:?}", self))?;
+            .ok_or_else(|| self.missing_key("KEY1/KEY2"))?;
         let key1 = keys
             .get("KEY1")
-            .context("Could not find KEY1 on keys file")?
+            .ok_or_else(|| self.missing_key("KEY1"))?
             .clone();
         let key2 = keys
             .get("KEY2")
-            .context("Could not find KEY2 on keys file")?
+            .ok_or_else(|| self.missing_key("KEY2"))?
             .clone();
 
         let key_file_entry = file_entries.get(0).context("Empty archive")?;
@@ -175,6 +99,54 @@ impl Scheme for PackScheme {
         ))
     }
 
+    /// Only entry table is read, keys are not loaded and key file entry is
+    /// not decrypted
+    fn list(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<archive::FileEntryMetadata>> {
+        let index = read_index(file_path)?;
+        Ok(index
+            .file_entries
+            .iter()
+            .map(|entry| archive::FileEntryMetadata {
+                full_path: entry.full_path.clone(),
+                file_offset: entry.file_offset,
+                file_size: entry.file_size as u64,
+                ext: entry.ext(&index.header.version),
+            })
+            .collect())
+    }
+
+    fn inspect(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 0x440];
+        let header_offset = std::fs::metadata(&file_path)?
+            .len()
+            .checked_sub(0x440)
+            .context("File too small for QLIE header")?;
+        let file = util::fs::open_archive_file(file_path)?;
+        file.read_exact_at(header_offset, &mut buf)?;
+        let header = buf.pread_with::<PackHeader>(0x440 - 0x1C, LE)?;
+        let header2 = buf.pread_with::<PackHeader2>(0, LE)?;
+
+        let mut buf2 = vec![0; header2.hash_data_size as usize];
+        file.read_exact_at(
+            header_offset
+                .checked_sub(header2.hash_data_size as u64)
+                .context("Hash data out of bounds")?,
+            &mut buf2,
+        )?;
+        let hash_data_header = buf2.pread::<HashDataHeader>(0)?;
+        Ok(vec![
+            header.report(),
+            header2.report(),
+            hash_data_header.report(),
+        ])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[QLIE PACK] {}",
@@ -210,6 +182,16 @@ impl Scheme for PackScheme {
     }
 }
 
+impl PackScheme {
+    fn missing_key(&self, key_type: &str) -> anyhow::Error {
+        AkaibuError::MissingKey {
+            scheme: self.get_name(),
+            key_type: key_type.to_string(),
+        }
+        .into()
+    }
+}
+
 #[derive(Debug)]
 struct PackArchive {
     file: RandomAccessFile,
@@ -233,19 +215,27 @@ impl archive::Archive for PackArchive {
             .context("File not found")?
     }
 
+    fn entry_ext(&self, entry: &archive::FileEntry) -> archive::FileEntryExt {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| e.ext(&self.header.version))
+            .unwrap_or_default()
+    }
+
     fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
         self.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -257,28 +247,13 @@ impl archive::Archive for PackArchive {
 
 impl PackArchive {
     fn new_root_dir(entries: &[PackFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: String::from(
-                            entry
-                                .full_path
-                                .file_name()
-                                .expect("No file name")
-                                .to_str()
-                                .expect("Not valid UTF-8"),
-                        ),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &PackFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -286,23 +261,19 @@ impl PackArchive {
 
         self.file.read_exact_at(entry.file_offset, &mut buf)?;
 
-        if &self.header.version == b"3.1" {
-            if entry.unk1 == 1 {
-                decrypt_key_file3_1(
-                    &mut buf,
-                    &entry.file_name,
-                    self.decrypt_key,
-                )?;
-            } else if entry.unk1 == 2 {
-                decrypt_file3_1(
-                    &mut buf,
-                    &entry.file_name,
-                    self.decrypt_key,
-                    &self.decrypt_buf,
-                )?;
-            }
-        } else {
-            if entry.unk1 == 4 {
+        match entry.encryption(&self.header.version) {
+            Some(Encryption::KeyFile3_1) => decrypt_key_file3_1(
+                &mut buf,
+                &entry.file_name,
+                self.decrypt_key,
+            )?,
+            Some(Encryption::File3_1) => decrypt_file3_1(
+                &mut buf,
+                &entry.file_name,
+                self.decrypt_key,
+                &self.decrypt_buf,
+            )?,
+            Some(Encryption::Prng) => {
                 let mut prng = Prng::init_prng(
                     &entry.file_name,
                     entry.file_size,
@@ -311,8 +282,9 @@ impl PackArchive {
                 );
                 prng.decrypt(&mut buf)?;
             }
+            None => (),
         }
-        if entry.unk0 != 0 {
+        if entry.is_compressed() {
             buf = BytesMut::from(&decompress(&buf)?[..]);
         }
 
@@ -323,6 +295,23 @@ impl PackArchive {
     }
 }
 
+#[derive(Debug, Clone, Copy)]
+enum Encryption {
+    KeyFile3_1,
+    File3_1,
+    Prng,
+}
+
+impl Encryption {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::KeyFile3_1 => "QLIE 3.1 key file",
+            Self::File3_1 => "QLIE 3.1 file",
+            Self::Prng => "QLIE PRNG",
+        }
+    }
+}
+
 #[derive(Debug, Pread)]
 struct PackHeader {
     magic: [u8; 11],
@@ -333,12 +322,16 @@ struct PackHeader {
     unk3: u32,
 }
 
+impl ReportHeader for PackHeader {}
+
 #[derive(Debug, Pread)]
 struct PackHeader2 {
     key: [u8; 32],
     hash_data_size: u32,
 }
 
+impl ReportHeader for PackHeader2 {}
+
 #[derive(Debug)]
 struct HashDataHeader {
     magic: [u8; 7],
@@ -352,6 +345,8 @@ struct HashDataHeader {
     compressed: Option<u32>,
 }
 
+impl ReportHeader for HashDataHeader {}
+
 impl<'a> ctx::TryFromCtx<'a, ()> for HashDataHeader {
     type Error = anyhow::Error;
 
@@ -467,6 +462,36 @@ struct PackFileEntry {
     file_name: Vec<u8>,
 }
 
+impl PackFileEntry {
+    fn is_compressed(&self) -> bool {
+        self.unk0 != 0
+    }
+    /// Encryption is selected by unk1 flag, meaning depends on version
+    fn encryption(&self, version: &[u8; 3]) -> Option<Encryption> {
+        match (version, self.unk1) {
+            (b"3.1", 1) => Some(Encryption::KeyFile3_1),
+            (b"3.1", 2) => Some(Encryption::File3_1),
+            (b"3.1", _) => None,
+            (_, 4) => Some(Encryption::Prng),
+            _ => None,
+        }
+    }
+    fn ext(&self, version: &[u8; 3]) -> archive::FileEntryExt {
+        archive::FileEntryExt {
+            encryption: self
+                .encryption(version)
+                .map(|encryption| encryption.name().to_string()),
+            compression: if self.is_compressed() {
+                Some("QLIE LZ".to_string())
+            } else {
+                None
+            },
+            modified: None,
+            overrides: None,
+        }
+    }
+}
+
 impl<'a> ctx::TryFromCtx<'a, (&'a PackEntry, u32)> for PackFileEntry {
     type Error = anyhow::Error;
 
@@ -506,6 +531,111 @@ impl<'a> ctx::TryFromCtx<'a, (&'a PackEntry, u32)> for PackFileEntry {
     }
 }
 
+/// Archive file with its headers and entry table
+struct PackIndex {
+    file: RandomAccessFile,
+    header: PackHeader,
+    decrypt_key: u32,
+    file_entries: Vec<PackFileEntry>,
+}
+
+/// Read headers and entry table stored at end of archive
+fn read_index(file_path: &std::path::Path) -> anyhow::Result<PackIndex> {
+    let mut buf = vec![0; 0x440];
+    let metadata = std::fs::metadata(&file_path)?;
+    let file = util::fs::open_archive_file(file_path)?;
+    file.read_exact_at(metadata.len() - 0x440, &mut buf)?;
+    let header = buf.pread_with::<PackHeader>(0x440 - 0x1C, LE)?;
+
+    if &header.magic != b"FilePackVer" {
+        return Err(AkaibuError::Custom(format!(
+            "Unsupported archive: {}",
+            String::from_utf8_lossy(&header.magic),
+        ))
+        .into());
+    }
+    if &header.version != b"3.0" && &header.version != b"3.1" {
+        return Err(AkaibuError::UnsupportedVersion {
+            scheme: "QLIE FilePack".to_string(),
+            found: String::from_utf8_lossy(&header.version).into_owned(),
+            expected: "3.0, 3.1".to_string(),
+        }
+        .into());
+    }
+
+    let header2 = buf.pread_with::<PackHeader2>(0, LE)?;
+    let header2_data = &buf[0x24..];
+    log::debug!("Header: {:#?}", header);
+
+    let decrypt_key = if &header.version == b"3.0" {
+        generate_decrypt_key(&header2_data[..0x100])?
+    } else {
+        generate_decrypt_key3_1(&header2_data[..0x100])?
+    };
+
+    let mut buf2 = vec![0; header2.hash_data_size as usize];
+    file.read_exact_at(
+        metadata.len() - 0x440 - header2.hash_data_size as u64,
+        &mut buf2,
+    )?;
+
+    let hash_data_header = buf2.pread::<HashDataHeader>(0)?;
+    if &hash_data_header.magic != b"HashVer" {
+        return Err(AkaibuError::Custom(format!(
+            "Unsupported hash data: {}",
+            String::from_utf8_lossy(&hash_data_header.magic),
+        ))
+        .into());
+    }
+    if &hash_data_header.version != b"1.3"
+        && &hash_data_header.version != b"1.4"
+    {
+        return Err(AkaibuError::UnsupportedVersion {
+            scheme: "QLIE hash data".to_string(),
+            found: String::from_utf8_lossy(&hash_data_header.version)
+                .into_owned(),
+            expected: "1.3, 1.4".to_string(),
+        }
+        .into());
+    }
+    let hash_data = if let Some(is_compressed) = hash_data_header.compressed {
+        if is_compressed == 1 {
+            decompress(&decrypt_with_decrypt_key(&buf2[0x44..], 0x428)?)?
+        } else {
+            decrypt_with_decrypt_key(&buf2[0x44..], 0x428)?
+        }
+    } else {
+        decompress(&decrypt_with_decrypt_key(&buf2[0x20..], 0x428)?)?
+    };
+    let entries = if &hash_data_header.version == b"1.4" {
+        parse_hash_data1_4(&hash_data, hash_data_header.iter_count)?
+    } else {
+        parse_hash_data(&hash_data, hash_data_header.iter_count)?
+    };
+    let hash_data_version = if &hash_data_header.version == b"1.4" {
+        14
+    } else {
+        13
+    };
+
+    let mut entry_data =
+        vec![
+            0;
+            (metadata.len() as usize - 0x440 - header2.hash_data_size as usize)
+                - header.entry_data_offset as usize
+        ];
+    file.read_exact_at(header.entry_data_offset as u64, &mut entry_data)?;
+    let file_entries =
+        parse_entry_data(&entry_data, entries, hash_data_version)?;
+    log::debug!("{:#?}", file_entries);
+    Ok(PackIndex {
+        file,
+        header,
+        decrypt_key,
+        file_entries,
+    })
+}
+
 fn generate_decrypt_key(src: &[u8]) -> anyhow::Result<u32> {
     let mut mm0 = [0u8; 8];
     let mut mm2 = [0u8; 8];
@@ -569,16 +699,17 @@ fn decrypt_with_decrypt_key(
 }
 
 fn decompress(src: &[u8]) -> anyhow::Result<Vec<u8>> {
-    if &src[0..4] != b"1PC\xFF" {
+    let magic = src.get(0..4).unwrap_or(src);
+    if magic != b"1PC\xFF" {
         return Err(AkaibuError::Custom(format!(
             "Invalid decompress magic {:?}",
-            &src[0..4]
+            magic
         ))
         .into());
     }
     let val4 = src.pread_with::<u32>(4, LE)?;
     let dest_size = src.pread_with::<u32>(8, LE)? as usize;
-    let mut dest = vec![0; dest_size];
+    let mut dest = bounds::output_buffer(dest_size)?;
 
     let index = &mut 12;
     let mut dest_index = 0;
@@ -597,11 +728,15 @@ fn decompress(src: &[u8]) -> anyhow::Result<Vec<u8>> {
             if b > 0xFF {
                 break;
             }
-            let mut d = byte + 1;
+            let mut d = byte as u32 + 1;
             while d != 0 {
-                cur_buf[b as usize] = src.gread::<u8>(index)?;
+                bounds::write(&mut cur_buf, b as usize, src.gread(index)?)?;
                 if b != cur_buf[b as usize] as u32 {
-                    some_buf2[b as usize] = src.gread::<u8>(index)?;
+                    bounds::write(
+                        &mut some_buf2,
+                        b as usize,
+                        src.gread(index)?,
+                    )?;
                 }
                 b += 1;
                 d -= 1;
@@ -622,7 +757,7 @@ fn decompress(src: &[u8]) -> anyhow::Result<Vec<u8>> {
         loop {
             if counter != 0 {
                 counter -= 1;
-                b = some_buf3[counter] as u32;
+                b = bounds::read(&some_buf3, counter)? as u32;
             } else {
                 if val_c == 0 {
                     break;
@@ -631,12 +766,12 @@ fn decompress(src: &[u8]) -> anyhow::Result<Vec<u8>> {
                 b = src.gread::<u8>(index)? as u32;
             }
             if b == cur_buf[b as usize] as u32 {
-                dest[dest_index] = b as u8;
+                bounds::write(&mut dest, dest_index, b as u8)?;
                 dest_index += 1;
             } else {
-                some_buf3[counter] = some_buf2[b as usize];
+                bounds::write(&mut some_buf3, counter, some_buf2[b as usize])?;
                 counter += 1;
-                some_buf3[counter] = cur_buf[b as usize];
+                bounds::write(&mut some_buf3, counter, cur_buf[b as usize])?;
                 counter += 1;
             }
         }
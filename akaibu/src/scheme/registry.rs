@@ -0,0 +1,198 @@
+use crate::error::AkaibuError;
+use once_cell::sync::Lazy;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::RwLock,
+};
+
+static REGISTRY: Lazy<SchemeRegistry> =
+    Lazy::new(|| SchemeRegistry::new(SchemeRegistry::default_config_dir()));
+
+/// Key tables used by schemes. Embedded tables are merged with tables put
+/// into config directory under the same path, e.g. `malie/keys.json`, so
+/// keys for new titles can be added without rebuilding. Tables are read on
+/// first use and kept until [`SchemeRegistry::reload`].
+#[derive(Debug)]
+pub struct SchemeRegistry {
+    config_dir: Option<PathBuf>,
+    tables: RwLock<HashMap<String, Value>>,
+}
+
+impl SchemeRegistry {
+    pub fn new(config_dir: Option<PathBuf>) -> Self {
+        Self {
+            config_dir,
+            tables: RwLock::new(HashMap::new()),
+        }
+    }
+    /// Registry used by schemes
+    pub fn global() -> &'static Self {
+        &REGISTRY
+    }
+    /// `$XDG_CONFIG_HOME/akaibu/keys` or `%APPDATA%\akaibu\keys`
+    pub fn default_config_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+            .or_else(|| {
+                std::env::var_os("HOME")
+                    .map(|home| PathBuf::from(home).join(".config"))
+            })
+            .map(|dir| dir.join("akaibu").join("keys"))
+    }
+    pub fn config_dir(&self) -> Option<&Path> {
+        self.config_dir.as_deref()
+    }
+    /// JSON files in config directory, sorted by path
+    pub fn user_tables(&self) -> Vec<PathBuf> {
+        let mut user_tables = match &self.config_dir {
+            Some(config_dir) if config_dir.is_dir() => json_files(config_dir),
+            _ => return Vec::new(),
+        };
+        user_tables.sort();
+        user_tables
+    }
+    /// Load every key table of enabled formats merged with user tables and
+    /// check it has the type its scheme expects. Returns errors of all
+    /// tables that failed, schemes using them fail with the same error
+    /// when used while other schemes keep working.
+    pub fn validate(&self) -> Vec<AkaibuError> {
+        crate::util::embedded::key_tables()
+            .into_iter()
+            .filter_map(|(path, check)| {
+                self.table(path)
+                    .and_then(|table| {
+                        check(table).map_err(|err| {
+                            AkaibuError::EmbeddedResource {
+                                path: path.to_string(),
+                                reason: err.to_string(),
+                            }
+                        })
+                    })
+                    .err()
+            })
+            .collect()
+    }
+    /// Drop loaded tables, so next use reads them again, and check that
+    /// every JSON file in config directory parses and key tables merged
+    /// with them are valid, see [`SchemeRegistry::validate`].
+    /// Returns number of user key tables found.
+    pub fn reload(&self) -> Result<usize, AkaibuError> {
+        self.tables
+            .write()
+            .expect("Key table lock poisoned")
+            .clear();
+        let config_dir = match &self.config_dir {
+            Some(config_dir) if config_dir.is_dir() => config_dir,
+            _ => return first_error(self.validate()).map(|_| 0),
+        };
+        let user_tables = json_files(config_dir);
+        for path in &user_tables {
+            read_user_table(path)?;
+        }
+        first_error(self.validate())?;
+        log::info!(
+            "Reloaded key tables, {} found in {:?}",
+            user_tables.len(),
+            config_dir
+        );
+        Ok(user_tables.len())
+    }
+    /// Deserialize embedded table at given path merged with user table
+    pub(crate) fn load<T: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<T, AkaibuError> {
+        serde_json::from_value(self.table(path)?).map_err(|err| {
+            AkaibuError::EmbeddedResource {
+                path: path.to_string(),
+                reason: err.to_string(),
+            }
+        })
+    }
+    /// Merged table at given path, read on first use
+    fn table(&self, path: &str) -> Result<Value, AkaibuError> {
+        let cached = self
+            .tables
+            .read()
+            .expect("Key table lock poisoned")
+            .get(path)
+            .cloned();
+        let table = match cached {
+            Some(table) => table,
+            None => {
+                let table = self.read_table(path)?;
+                self.tables
+                    .write()
+                    .expect("Key table lock poisoned")
+                    .insert(path.to_string(), table.clone());
+                table
+            }
+        };
+        Ok(table)
+    }
+    /// Entries of user table replace embedded entries with the same name
+    fn read_table(&self, path: &str) -> Result<Value, AkaibuError> {
+        let mut table = crate::util::embedded::load_json::<Value>(path)?;
+        let user_path = self
+            .config_dir
+            .as_ref()
+            .map(|config_dir| config_dir.join(path))
+            .filter(|user_path| user_path.is_file());
+        if let Some(user_path) = user_path {
+            let user_table = read_user_table(&user_path).map_err(|err| {
+                AkaibuError::EmbeddedResource {
+                    path: path.to_string(),
+                    reason: err.to_string(),
+                }
+            })?;
+            match (&mut table, user_table) {
+                (Value::Object(table), Value::Object(user_table)) => {
+                    table.extend(user_table)
+                }
+                (_, user_table) => table = user_table,
+            }
+        }
+        Ok(table)
+    }
+}
+
+fn first_error(errors: Vec<AkaibuError>) -> Result<(), AkaibuError> {
+    errors.into_iter().next().map_or(Ok(()), Err)
+}
+
+fn read_user_table(path: &Path) -> Result<Value, AkaibuError> {
+    std::fs::read(path)
+        .map_err(|err| err.to_string())
+        .and_then(|file| {
+            serde_json::from_slice(&file).map_err(|err| err.to_string())
+        })
+        .map_err(|reason| {
+            AkaibuError::Custom(format!(
+                "Invalid key table {:?}: {}",
+                path, reason
+            ))
+        })
+}
+
+/// JSON files in directory and its subdirectories
+fn json_files(dir: &Path) -> Vec<PathBuf> {
+    std::fs::read_dir(dir)
+        .into_iter()
+        .flatten()
+        .flatten()
+        .map(|entry| entry.path())
+        .flat_map(|path| {
+            if path.is_dir() {
+                json_files(&path)
+            } else if path.extension().map_or(false, |ext| ext == "json") {
+                vec![path]
+            } else {
+                vec![]
+            }
+        })
+        .collect()
+}
@@ -1,17 +1,16 @@
-use crate::archive::{self, FileContents};
+use crate::{
+    archive::{self, FileContents},
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, bounds},
+};
 
 use super::Scheme;
 use anyhow::Context;
 use bytes::{Bytes, BytesMut};
 use encoding_rs::SHIFT_JIS;
 use positioned_io::{RandomAccessFile, ReadAt};
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{ctx, Pread, BE, LE};
-use std::{
-    fs::File,
-    io::Write,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 
 #[derive(Debug, Clone)]
 pub enum SilkyScheme {
@@ -27,9 +26,10 @@ impl Scheme for SilkyScheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 4];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
-        let entries_size = buf.pread_with::<u32>(0, LE)? as usize;
+        let entries_size =
+            buf.pread_with::<SilkyHeader>(0, LE)?.entries_size as usize;
 
         let mut buf = vec![0; entries_size];
         file.read_exact_at(4, &mut buf)?;
@@ -47,6 +47,12 @@ impl Scheme for SilkyScheme {
         Ok((Box::new(SilkyArchive { file, archive }), navigable_dir))
     }
 
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 4];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<SilkyHeader>(0, LE)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[SILKY] {}",
@@ -64,6 +70,13 @@ impl Scheme for SilkyScheme {
     }
 }
 
+#[derive(Debug, Pread)]
+struct SilkyHeader {
+    entries_size: u32,
+}
+
+impl ReportHeader for SilkyHeader {}
+
 #[derive(Debug)]
 struct SilkyArchive {
     file: RandomAccessFile,
@@ -83,52 +96,53 @@ impl archive::Archive for SilkyArchive {
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset, e.file_size as u64))
+    }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            // Compressed entries are larger once unpacked
+            .filter(|e| e.uncompressed_file_size <= e.file_size)
+            .map(|e| (e.file_offset as u64, e.file_size as u64))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
-        self.archive.entries.par_iter().try_for_each(|entry| {
-            let file_contents = self.extract(entry)?;
-            let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
-            std::fs::create_dir_all(
-                &output_file_name
-                    .parent()
-                    .context("Could not get parent directory")?,
-            )?;
-            log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
-                entry
-            );
-            File::create(output_file_name)?
-                .write_all(&file_contents.contents)?;
-            Ok(())
-        })
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.archive.entries).files().cloned(),
+            output_path,
+        )
     }
 }
 
 impl SilkyArchive {
     fn new_root_dir(entries: &[SilkyEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: entry.file_name.clone(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &SilkyEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
         buf.resize(entry.file_size as usize, 0);
         self.file.read_exact_at(entry.file_offset, &mut buf)?;
         let contents = if entry.uncompressed_file_size > entry.file_size {
-            decompress(&buf, entry.uncompressed_file_size as usize)
+            decompress(&buf, entry.uncompressed_file_size as usize)?
         } else {
             buf.freeze()
         };
@@ -149,7 +163,6 @@ struct SilkyEntry {
     file_offset: u64,
     file_size: u32,
     uncompressed_file_size: u32,
-    file_name: String,
     full_path: PathBuf,
 }
 
@@ -184,7 +197,6 @@ impl<'a> ctx::TryFromCtx<'a, ()> for SilkyEntry {
                 file_offset,
                 file_size,
                 uncompressed_file_size,
-                file_name,
                 full_path,
             },
             *off,
@@ -192,8 +204,9 @@ impl<'a> ctx::TryFromCtx<'a, ()> for SilkyEntry {
     }
 }
 
-fn decompress(buf: &[u8], dest_len: usize) -> Bytes {
-    let mut dest = vec![0u8; dest_len];
+/// LZSS shared with older Silky's containers
+pub(super) fn decompress(buf: &[u8], dest_len: usize) -> anyhow::Result<Bytes> {
+    let mut dest = bounds::output_buffer(dest_len)?;
     let mut lookup_table = vec![0u8; 4096];
 
     let mut x = 0_u16;
@@ -203,14 +216,14 @@ fn decompress(buf: &[u8], dest_len: usize) -> Bytes {
     while bytes_read < buf.len() {
         x >>= 1;
         if (x & 0x100) == 0 {
-            x = buf[bytes_read] as u16;
+            x = read_byte(buf, bytes_read)? as u16;
             bytes_read += 1;
             x |= 0xFF00;
         }
         if ((x & 0xFF) & 1) == 0 {
-            let bl = buf[bytes_read];
+            let bl = read_byte(buf, bytes_read)?;
             bytes_read += 1;
-            let cl = buf[bytes_read];
+            let cl = read_byte(buf, bytes_read)?;
             bytes_read += 1;
             let mut s = cl as u16;
             let mut d = s as u16;
@@ -229,7 +242,9 @@ fn decompress(buf: &[u8], dest_len: usize) -> Bytes {
                     s += 1;
                     c &= 0xFFF;
                     d = lookup_table[c as usize] as u16;
-                    dest[bytes_written] = d as u8;
+                    *dest
+                        .get_mut(bytes_written)
+                        .context("Decompressed data overflow")? = d as u8;
                     c = lookup_index;
                     bytes_written += 1;
                     lookup_index += 1;
@@ -240,9 +255,11 @@ fn decompress(buf: &[u8], dest_len: usize) -> Bytes {
                 }
             }
         } else {
-            let d = buf[bytes_read];
+            let d = read_byte(buf, bytes_read)?;
             bytes_read += 1;
-            dest[bytes_written] = d;
+            *dest
+                .get_mut(bytes_written)
+                .context("Decompressed data overflow")? = d;
             bytes_written += 1;
             let c = lookup_index;
             lookup_index += 1;
@@ -250,5 +267,9 @@ fn decompress(buf: &[u8], dest_len: usize) -> Bytes {
             lookup_table[c as usize] = d;
         }
     }
-    Bytes::from(dest)
+    Ok(Bytes::from(dest))
+}
+
+fn read_byte(buf: &[u8], index: usize) -> anyhow::Result<u8> {
+    buf.get(index).copied().context("Compressed data truncated")
 }
@@ -0,0 +1,252 @@
+use super::{silky, Scheme};
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, text::display_name},
+};
+use anyhow::Context;
+use bytes::BytesMut;
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
+use scroll::{Pread, LE};
+use std::{
+    fs::File,
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+/// Size of unpacked size field preceding each entry data
+const ENTRY_HEADER_SIZE: u32 = 4;
+
+/// Containers used by older Silky's titles (Ankh, Aquarium).
+/// Entry count is followed by offset table with one extra offset pointing
+/// to zero terminated SHIFT-JIS names stored at the end of file.
+#[derive(Debug, Clone)]
+pub enum GrpScheme {
+    Universal,
+}
+
+impl Scheme for GrpScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let file_size = std::fs::metadata(file_path)?.len();
+        let offsets = read_offsets(&file, file_size)?;
+
+        let names_offset = offsets[offsets.len() - 1] as u64;
+        let mut buf = vec![0; (file_size - names_offset) as usize];
+        file.read_exact_at(names_offset, &mut buf)?;
+        let mut names = buf.split(|b| *b == 0);
+
+        let entries = offsets
+            .windows(2)
+            .map(|w| {
+                let full_path = PathBuf::from(
+                    SHIFT_JIS
+                        .decode(names.next().context("Missing entry name")?)
+                        .0
+                        .replace("\\", "/"),
+                );
+                Ok(GrpEntry {
+                    full_path,
+                    file_offset: w[0] as u64,
+                    file_size: w[1] - w[0],
+                })
+            })
+            .collect::<anyhow::Result<Vec<GrpEntry>>>()?;
+        let archive = Grp { entries };
+        log::debug!("Archive: {:#?}", archive);
+
+        let root_dir = GrpArchive::new_root_dir(&archive.entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((Box::new(GrpArchive { file, archive }), navigable_dir))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let offsets = read_offsets(&file, std::fs::metadata(file_path)?.len())?;
+        Ok(vec![GrpHeader {
+            entry_count: offsets.len() as u32 - 1,
+            names_offset: offsets[offsets.len() - 1],
+        }
+        .report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[SILKY GRP] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+#[derive(Debug)]
+struct GrpArchive {
+    file: RandomAccessFile,
+    archive: Grp,
+}
+
+impl archive::Archive for GrpArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn entry_ext(&self, entry: &archive::FileEntry) -> archive::FileEntryExt {
+        self.archive
+            .entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .and_then(|e| self.unpacked_size(e).ok())
+            .map(|(packed_size, unpacked_size)| archive::FileEntryExt {
+                compression: if unpacked_size != packed_size {
+                    Some("LZSS".to_string())
+                } else {
+                    None
+                },
+                ..Default::default()
+            })
+            .unwrap_or_default()
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        self.archive.entries.par_iter().try_for_each(|entry| {
+            let file_contents = self.extract(entry)?;
+            let mut output_file_name = PathBuf::from(output_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
+            std::fs::create_dir_all(
+                &output_file_name
+                    .parent()
+                    .context("Could not get parent directory")?,
+            )?;
+            log::debug!(
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
+                entry
+            );
+            File::create(output_file_name)?
+                .write_all(&file_contents.contents)?;
+            Ok(())
+        })
+    }
+}
+
+impl GrpArchive {
+    fn new_root_dir(entries: &[GrpEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    /// Returns size of stored data and size after decompression
+    fn unpacked_size(&self, entry: &GrpEntry) -> anyhow::Result<(u32, u32)> {
+        let packed_size = entry
+            .file_size
+            .checked_sub(ENTRY_HEADER_SIZE)
+            .context("GRP entry too small")?;
+        let mut buf = vec![0; ENTRY_HEADER_SIZE as usize];
+        self.file.read_exact_at(entry.file_offset, &mut buf)?;
+        Ok((packed_size, buf.pread_with::<u32>(0, LE)?))
+    }
+    fn extract(&self, entry: &GrpEntry) -> anyhow::Result<FileContents> {
+        let (packed_size, unpacked_size) = self.unpacked_size(entry)?;
+        let mut buf = BytesMut::with_capacity(packed_size as usize);
+        buf.resize(packed_size as usize, 0);
+        self.file.read_exact_at(
+            entry.file_offset + ENTRY_HEADER_SIZE as u64,
+            &mut buf,
+        )?;
+        // Entries that did not shrink are stored as is
+        let contents = if unpacked_size != packed_size {
+            silky::decompress(&buf, unpacked_size as usize)?
+        } else {
+            buf.freeze()
+        };
+        Ok(FileContents {
+            contents,
+            type_hint: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct GrpHeader {
+    entry_count: u32,
+    /// Extra offset past last entry
+    names_offset: u32,
+}
+
+impl ReportHeader for GrpHeader {}
+
+/// Validated offset table, including trailing names offset
+fn read_offsets(
+    file: &RandomAccessFile,
+    file_size: u64,
+) -> anyhow::Result<Vec<u32>> {
+    let mut buf = vec![0; 4];
+    file.read_exact_at(0, &mut buf)?;
+
+    let entry_count = buf.pread_with::<u32>(0, LE)?;
+    let table_size = (entry_count as u64 + 1) * 4;
+    if entry_count == 0 || 4 + table_size > file_size {
+        return Err(AkaibuError::Custom(format!(
+            "Invalid GRP entry count: {}",
+            entry_count
+        ))
+        .into());
+    }
+    let mut buf = vec![0; table_size as usize];
+    file.read_exact_at(4, &mut buf)?;
+    let offsets = (0..=entry_count as usize)
+        .map(|i| buf.pread_with::<u32>(i * 4, LE))
+        .collect::<Result<Vec<u32>, _>>()?;
+    if offsets[0] as u64 != 4 + table_size
+        || offsets.windows(2).any(|w| w[0] > w[1])
+        || offsets[entry_count as usize] as u64 > file_size
+    {
+        return Err(AkaibuError::Custom(
+            "Invalid GRP offset table".to_string(),
+        )
+        .into());
+    }
+
+    Ok(offsets)
+}
+
+#[derive(Debug)]
+struct Grp {
+    entries: Vec<GrpEntry>,
+}
+
+#[derive(Debug)]
+struct GrpEntry {
+    file_offset: u64,
+    file_size: u32,
+    full_path: PathBuf,
+}
@@ -1,9 +1,13 @@
-use super::Scheme;
-use crate::archive::{self, FileContents};
+use super::{registry::SchemeRegistry, Scheme};
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, bounds, text::display_name},
+};
 use anyhow::Context;
 use bytes::BytesMut;
 use encoding_rs::SHIFT_JIS;
-use once_cell::sync::Lazy;
 use positioned_io::{RandomAccessFile, ReadAt};
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{Pread, LE};
@@ -18,16 +22,7 @@ pub enum ArcScheme {
     Oshioki,
 }
 
-const KEYS_PATH: &str = "tactics_arc/keys.json";
-
-static KEYS: Lazy<HashMap<String, String>> = Lazy::new(|| {
-    let keys = serde_json::from_slice(
-        &crate::Resources::get(KEYS_PATH)
-            .expect("Could not find file: tactics_arc/keys.json"),
-    )
-    .expect("Could not deserialize resource json");
-    keys
-});
+pub(crate) const KEYS_PATH: &str = "tactics_arc/keys.json";
 
 impl Scheme for ArcScheme {
     fn extract(
@@ -37,50 +32,20 @@ impl Scheme for ArcScheme {
         Box<dyn crate::archive::Archive>,
         crate::archive::NavigableDirectory,
     )> {
-        let metadata = std::fs::metadata(&file_path)?;
-        let mut buf = vec![0; 20];
-        let file = RandomAccessFile::open(file_path)?;
-        let mut cur_file_offset = 16;
-
-        let mut file_entries = Vec::new();
-
-        while cur_file_offset < metadata.len() {
-            file.read_exact_at(cur_file_offset, &mut buf)?;
-
-            let file_size = buf.pread_with::<u32>(0, LE)? as u64;
-            let decompressed_file_size = buf.pread_with::<u32>(4, LE)? as usize;
-            let name_size = buf.pread_with::<u32>(8, LE)? as usize;
-
-            let mut file_name_buf = vec![0; name_size];
-            cur_file_offset += 20;
-            file.read_exact_at(cur_file_offset, &mut file_name_buf)?;
-
-            cur_file_offset += name_size as u64;
-
-            if name_size > 0 {
-                file_entries.push(ArcFileEntry {
-                    file_size,
-                    decompressed_file_size,
-                    file_offset: cur_file_offset,
-                    full_path: PathBuf::from(
-                        SHIFT_JIS.decode(&file_name_buf).0.replace("\\", "/"),
-                    ),
-                });
-            }
-
-            cur_file_offset += file_size as u64
-        }
+        let file = util::fs::open_archive_file(file_path)?;
+        let file_entries =
+            read_entries(&file, std::fs::metadata(&file_path)?.len())?;
         let root_dir = ArcArchive::new_root_dir(&file_entries);
         let navigable_dir = archive::NavigableDirectory::new(root_dir);
-        let xor_key = KEYS
-            .get(match self {
+        let xor_key = SchemeRegistry::global()
+            .load::<HashMap<String, String>>(KEYS_PATH)?
+            .remove(match self {
                 // ArcScheme::Maou1 => "Maou1",
                 ArcScheme::Maou2 => "Maou2",
                 ArcScheme::Maou2FD => "Maou2FD",
                 ArcScheme::Oshioki => "Oshioki",
             })
             .context(format!("Could not find key for {:?}", self))?
-            .clone()
             .into_bytes();
         Ok((
             Box::new(ArcArchive {
@@ -92,6 +57,24 @@ impl Scheme for ArcScheme {
         ))
     }
 
+    /// Entries are walked without the key, only their data is xored
+    fn inspect(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let file_entries =
+            read_entries(&file, std::fs::metadata(&file_path)?.len())?;
+        Ok(vec![ArcIndex {
+            entry_count: file_entries.len(),
+            unpacked_size: file_entries
+                .iter()
+                .map(|e| e.decompressed_file_size as u64)
+                .sum(),
+        }
+        .report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[TACTICS_ARC_FILE] {}",
@@ -139,19 +122,26 @@ impl archive::Archive for ArcArchive {
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| (e.file_offset, e.file_size))
+    }
+
     fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
         self.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -163,25 +153,13 @@ impl archive::Archive for ArcArchive {
 
 impl ArcArchive {
     fn new_root_dir(entries: &[ArcFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset;
-                    let file_size = entry.file_size;
-                    archive::FileEntry {
-                        file_name: entry
-                            .full_path
-                            .to_str()
-                            .expect("Not valid UTF-8")
-                            .to_string(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset,
+                entry.file_size,
+            )
+        }))
     }
     fn extract(&self, entry: &ArcFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -194,12 +172,61 @@ impl ArcArchive {
             .for_each(|(b, k)| *b ^= k);
         Ok(FileContents {
             // contents: bytes::Bytes::copy_from_slice(&buf[4..]),
-            contents: bytes::Bytes::from(decompress(&buf)),
+            contents: bytes::Bytes::from(decompress(&buf)?),
             type_hint: None,
         })
     }
 }
 
+/// Summary of entry headers, archive has no index
+#[derive(Debug)]
+struct ArcIndex {
+    entry_count: usize,
+    unpacked_size: u64,
+}
+
+impl ReportHeader for ArcIndex {}
+
+/// Walks entry headers following the 16 byte magic, entries without name are
+/// skipped
+fn read_entries(
+    file: &RandomAccessFile,
+    file_size: u64,
+) -> anyhow::Result<Vec<ArcFileEntry>> {
+    let mut buf = vec![0; 20];
+    let mut cur_file_offset = 16;
+
+    let mut file_entries = Vec::new();
+
+    while cur_file_offset < file_size {
+        file.read_exact_at(cur_file_offset, &mut buf)?;
+
+        let file_size = buf.pread_with::<u32>(0, LE)? as u64;
+        let decompressed_file_size = buf.pread_with::<u32>(4, LE)? as usize;
+        let name_size = buf.pread_with::<u32>(8, LE)? as usize;
+
+        let mut file_name_buf = vec![0; name_size];
+        cur_file_offset += 20;
+        file.read_exact_at(cur_file_offset, &mut file_name_buf)?;
+
+        cur_file_offset += name_size as u64;
+
+        if name_size > 0 {
+            file_entries.push(ArcFileEntry {
+                file_size,
+                decompressed_file_size,
+                file_offset: cur_file_offset,
+                full_path: PathBuf::from(
+                    SHIFT_JIS.decode(&file_name_buf).0.replace("\\", "/"),
+                ),
+            });
+        }
+
+        cur_file_offset += file_size as u64
+    }
+    Ok(file_entries)
+}
+
 #[derive(Debug)]
 struct ArcFileEntry {
     file_size: u64,
@@ -288,7 +315,7 @@ const DECOMPRESS_TABLE: &[u16] = &[
     0x2001, 0x0F0B, 0x1040, 0x2040,
 ];
 
-fn decompress(src: &[u8]) -> Vec<u8> {
+fn decompress(src: &[u8]) -> Result<Vec<u8>, AkaibuError> {
     let mut decompressed_size = 0;
     let mut src_index = 0;
     let mut dest_index = 0;
@@ -296,16 +323,19 @@ fn decompress(src: &[u8]) -> Vec<u8> {
 
     let mut i = 0;
     while b >= 0x80 {
-        b = src[src_index];
+        if i >= 64 {
+            return Err(bounds::malformed("decompressed size too long"));
+        }
+        b = bounds::read(src, src_index)?;
         src_index += 1;
-        decompressed_size |= ((b as u32 & 0x7F) << i) as usize;
+        decompressed_size |= ((b as u64 & 0x7F) << i) as usize;
         i += 7;
     }
 
-    let mut dest = vec![0u8; decompressed_size];
+    let mut dest = bounds::output_buffer(decompressed_size)?;
 
     while dest_index < decompressed_size {
-        b = src[src_index];
+        b = bounds::read(src, src_index)?;
         src_index += 1;
         if (b & 3) != 0 {
             let offset_length =
@@ -313,7 +343,7 @@ fn decompress(src: &[u8]) -> Vec<u8> {
             let mut offset = 0u32;
             let mut i = 0;
             while i < offset_length {
-                offset |= (src[src_index] as u32) << i;
+                offset |= (bounds::read(src, src_index)? as u32) << i;
                 src_index += 1;
                 i += 8;
             }
@@ -322,10 +352,13 @@ fn decompress(src: &[u8]) -> Vec<u8> {
 
             let offset = offset as usize;
             let count = (DECOMPRESS_TABLE[b as usize] as u8) as usize;
-            dest.copy_within(
-                dest_index - offset..dest_index - offset + count,
-                dest_index,
-            );
+            let start = dest_index.checked_sub(offset).ok_or_else(|| {
+                bounds::malformed("back reference before start")
+            })?;
+            if dest_index + count > dest.len() {
+                return Err(bounds::malformed("write past end of output"));
+            }
+            dest.copy_within(start..start + count, dest_index);
             dest_index += count as usize;
         } else {
             let mut count = (b as u32 >> 2) + 1;
@@ -334,17 +367,24 @@ fn decompress(src: &[u8]) -> Vec<u8> {
                 count = 0;
                 let mut i = 0;
                 while i < count_length {
-                    count |= (src[src_index] as u32) << i;
+                    count |= (bounds::read(src, src_index)? as u32) << i;
                     src_index += 1;
                     i += 8;
                 }
-                count += 1;
+                count = count.checked_add(1).ok_or_else(|| {
+                    bounds::malformed("literal length overflow")
+                })?;
             }
-            dest[dest_index..dest_index + count as usize]
-                .copy_from_slice(&src[src_index..src_index + count as usize]);
-            src_index += count as usize;
-            dest_index += count as usize;
+            let count = count as usize;
+            let literal = src
+                .get(src_index..src_index + count)
+                .ok_or_else(|| bounds::malformed("read past end of input"))?;
+            dest.get_mut(dest_index..dest_index + count)
+                .ok_or_else(|| bounds::malformed("write past end of output"))?
+                .copy_from_slice(literal);
+            src_index += count;
+            dest_index += count;
         }
     }
-    dest
+    Ok(dest)
 }
@@ -1,16 +1,15 @@
-use std::{
-    fs::File,
-    io::Write,
-    path::{Path, PathBuf},
-};
+use std::path::{Path, PathBuf};
 
 use super::Scheme;
-use crate::archive::{self, FileContents, NavigableDirectory};
+use crate::{
+    archive::{self, FileContents, NavigableDirectory},
+    inspect::{ParsedHeader, ReportHeader},
+    util,
+};
 use anyhow::Context;
 use bytes::BytesMut;
 use positioned_io::RandomAccessFile;
 use positioned_io_preview::ReadAt;
-use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
 use scroll::{ctx, Pread, LE};
 
 #[derive(Debug, Clone)]
@@ -25,7 +24,7 @@ impl Scheme for ArcScheme {
     ) -> anyhow::Result<(Box<dyn crate::archive::Archive>, NavigableDirectory)>
     {
         let mut buf = vec![0; 8];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
         let header = buf.pread_with::<ArcHeader>(0, LE)?;
         log::debug!("Header: {:#?}", header);
@@ -51,6 +50,12 @@ impl Scheme for ArcScheme {
         ))
     }
 
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 8];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<ArcHeader>(0, LE)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[WILLPLUS ARC] {}",
@@ -87,49 +92,40 @@ impl archive::Archive for ArcArchive {
             .context("File not found")?
     }
 
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| {
+                (
+                    8 + self.header.entries_size as u64 + e.file_offset,
+                    e.file_size as u64,
+                )
+            })
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
     fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
-        self.file_entries.par_iter().try_for_each(|entry| {
-            let file_contents = self.extract(entry)?;
-            let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
-            std::fs::create_dir_all(
-                &output_file_name
-                    .parent()
-                    .context("Could not get parent directory")?,
-            )?;
-            log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
-                entry
-            );
-            File::create(output_file_name)?
-                .write_all(&file_contents.contents)?;
-            Ok(())
-        })
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.file_entries).files().cloned(),
+            output_path,
+        )
     }
 }
 
 impl ArcArchive {
     fn new_root_dir(entries: &[ArcFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: entry
-                            .full_path
-                            .to_str()
-                            .expect("Not valid UTF-8")
-                            .to_string(),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &ArcFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -152,6 +148,8 @@ struct ArcHeader {
     entries_size: u32,
 }
 
+impl ReportHeader for ArcHeader {}
+
 #[derive(Debug)]
 struct ArcFileEntry {
     file_size: u32,
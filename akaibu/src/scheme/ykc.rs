@@ -0,0 +1,207 @@
+use super::Scheme;
+use crate::{
+    archive::{self, FileContents},
+    error::AkaibuError,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, bounds},
+};
+use anyhow::Context;
+use bytes::{Bytes, BytesMut};
+use encoding_rs::SHIFT_JIS;
+use positioned_io::{RandomAccessFile, ReadAt};
+use scroll::{Pread, LE};
+use std::path::{Path, PathBuf};
+
+const HEADER_SIZE: usize = 0x18;
+const FILE_ENTRY_SIZE: usize = 0x14;
+
+#[derive(Debug, Pread)]
+struct YkcHeader {
+    magic: [u8; 8],
+    header_size: u32,
+    reserved: u32,
+    index_offset: u32,
+    index_size: u32,
+}
+
+impl ReportHeader for YkcHeader {}
+
+/// Xuse and Terios YKC001 archives. Index at the end of file holds name
+/// offset and length, data offset and size of each entry. Names are
+/// stored as SHIFT-JIS strings elsewhere in file.
+#[derive(Debug, Clone)]
+pub enum YkcScheme {
+    Universal,
+}
+
+impl Scheme for YkcScheme {
+    fn extract(
+        &self,
+        file_path: &Path,
+    ) -> anyhow::Result<(
+        Box<dyn crate::archive::Archive>,
+        crate::archive::NavigableDirectory,
+    )> {
+        let file = util::fs::open_archive_file(file_path)?;
+        let mut buf = vec![0; HEADER_SIZE];
+        file.read_exact_at(0, &mut buf)?;
+        let header = buf.pread_with::<YkcHeader>(0, LE)?;
+        log::debug!("Header: {:#?}", header);
+
+        // Sizes are checked against file before allocating buffers for them
+        let archive_size = std::fs::metadata(file_path)?.len();
+        if header.index_offset as u64 + header.index_size as u64 > archive_size
+        {
+            return Err(bounds::malformed("YKC index out of archive").into());
+        }
+        let mut index = vec![0; header.index_size as usize];
+        file.read_exact_at(header.index_offset as u64, &mut index)?;
+        let file_entries = index
+            .chunks_exact(FILE_ENTRY_SIZE)
+            .map(|record| YkcFileEntry::read(&file, record, archive_size))
+            .collect::<anyhow::Result<Vec<YkcFileEntry>>>()?;
+        log::debug!("Entries: {:#?}", file_entries);
+
+        let root_dir = YkcArchive::new_root_dir(&file_entries);
+        let navigable_dir = archive::NavigableDirectory::new(root_dir);
+        Ok((Box::new(YkcArchive { file, file_entries }), navigable_dir))
+    }
+
+    fn inspect(&self, file_path: &Path) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; HEADER_SIZE];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread_with::<YkcHeader>(0, LE)?.report()])
+    }
+
+    fn get_name(&self) -> String {
+        format!(
+            "[YKC] {}",
+            match self {
+                Self::Universal => "Universal",
+            }
+        )
+    }
+
+    fn get_schemes() -> Vec<Box<dyn Scheme>>
+    where
+        Self: Sized,
+    {
+        vec![Box::new(Self::Universal)]
+    }
+}
+
+#[derive(Debug)]
+struct YkcArchive {
+    file: RandomAccessFile,
+    file_entries: Vec<YkcFileEntry>,
+}
+
+impl archive::Archive for YkcArchive {
+    fn extract(
+        &self,
+        entry: &archive::FileEntry,
+    ) -> anyhow::Result<FileContents> {
+        self.file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| self.extract(e))
+            .context("File not found")?
+    }
+
+    fn entry_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        Some((entry.file_offset, entry.file_size))
+    }
+
+    fn raw_location(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        Some((entry.file_offset, entry.file_size))
+    }
+
+    fn read_raw(&self, offset: u64, buf: &mut [u8]) -> anyhow::Result<()> {
+        Ok(self.file.read_exact_at(offset, buf)?)
+    }
+
+    fn decode_raw(
+        &self,
+        _entry: &archive::FileEntry,
+        raw: Bytes,
+    ) -> anyhow::Result<FileContents> {
+        Ok(FileContents {
+            contents: raw,
+            type_hint: None,
+        })
+    }
+
+    fn extract_all(&self, output_path: &Path) -> anyhow::Result<()> {
+        archive::write_entries(
+            self,
+            Self::new_root_dir(&self.file_entries).files().cloned(),
+            output_path,
+        )
+    }
+}
+
+impl YkcArchive {
+    fn new_root_dir(entries: &[YkcFileEntry]) -> archive::Directory {
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
+    }
+    fn extract(&self, entry: &YkcFileEntry) -> anyhow::Result<FileContents> {
+        let mut buf = BytesMut::with_capacity(entry.file_size as usize);
+        buf.resize(entry.file_size as usize, 0);
+        self.file
+            .read_exact_at(entry.file_offset as u64, &mut buf)?;
+        Ok(FileContents {
+            contents: buf.freeze(),
+            type_hint: None,
+        })
+    }
+}
+
+#[derive(Debug)]
+struct YkcFileEntry {
+    full_path: PathBuf,
+    file_offset: u32,
+    file_size: u32,
+}
+
+impl YkcFileEntry {
+    /// Index record with name read from its offset in archive file
+    fn read(
+        file: &RandomAccessFile,
+        record: &[u8],
+        archive_size: u64,
+    ) -> anyhow::Result<Self> {
+        let off = &mut 0;
+        let name_offset = record.gread_with::<u32>(off, LE)?;
+        let name_size = record.gread_with::<u32>(off, LE)?;
+        let file_offset = record.gread_with::<u32>(off, LE)?;
+        let file_size = record.gread_with::<u32>(off, LE)?;
+        if name_offset as u64 + name_size as u64 > archive_size
+            || file_offset as u64 + file_size as u64 > archive_size
+        {
+            return Err(bounds::malformed("YKC entry out of archive").into());
+        }
+        let mut name = vec![0; name_size as usize];
+        file.read_exact_at(name_offset as u64, &mut name)?;
+        let name = name.split(|b| *b == 0).next().unwrap_or_default();
+        let full_path =
+            PathBuf::from(SHIFT_JIS.decode(name).0.replace("\\", "/"));
+        if full_path.file_name().is_none() {
+            return Err(AkaibuError::Custom(format!(
+                "Entry at {:#X} has no name",
+                file_offset
+            ))
+            .into());
+        }
+        Ok(Self {
+            full_path,
+            file_offset,
+            file_size,
+        })
+    }
+}
@@ -1,5 +1,9 @@
 use crate::{archive, error::AkaibuError, scheme::Scheme};
-use crate::{archive::FileContents, util::zlib_decompress};
+use crate::{
+    archive::FileContents,
+    inspect::{ParsedHeader, ReportHeader},
+    util::{self, embedded, text::display_name, zlib_decompress},
+};
 use anyhow::Context;
 use bytes::Bytes;
 use bytes::BytesMut;
@@ -12,6 +16,9 @@ use std::fs::File;
 use std::io::Write;
 use std::{collections::HashMap, path::PathBuf};
 
+pub(crate) const DECRYPT_NAME_TABLES_PATH: &str =
+    "ypf/decrypt_name_tables.json";
+
 #[derive(Debug, Clone)]
 pub enum YpfScheme {
     Universal,
@@ -26,7 +33,7 @@ impl Scheme for YpfScheme {
         crate::archive::NavigableDirectory,
     )> {
         let mut buf = vec![0; 32];
-        let file = RandomAccessFile::open(file_path)?;
+        let file = util::fs::open_archive_file(file_path)?;
         file.read_exact_at(0, &mut buf)?;
 
         let header = buf.pread::<YpfHeader>(0)?;
@@ -46,6 +53,15 @@ impl Scheme for YpfScheme {
         Ok((Box::new(YpfArchive { file, archive }), navigable_dir))
     }
 
+    fn inspect(
+        &self,
+        file_path: &std::path::Path,
+    ) -> anyhow::Result<Vec<ParsedHeader>> {
+        let mut buf = vec![0; 32];
+        util::fs::open_archive_file(file_path)?.read_exact_at(0, &mut buf)?;
+        Ok(vec![buf.pread::<YpfHeader>(0)?.report()])
+    }
+
     fn get_name(&self) -> String {
         format!(
             "[YPF] {}",
@@ -82,19 +98,34 @@ impl archive::Archive for YpfArchive {
             .context("File not found")?
     }
 
+    fn stored_range(&self, entry: &archive::FileEntry) -> Option<(u64, u64)> {
+        self.archive
+            .file_entries
+            .iter()
+            .find(|e| e.full_path == entry.full_path)
+            .map(|e| {
+                let stored_size = if e.flags == 1 {
+                    e.compressed_file_size
+                } else {
+                    e.file_size
+                };
+                (e.file_offset, stored_size as u64)
+            })
+    }
+
     fn extract_all(&self, output_path: &std::path::Path) -> anyhow::Result<()> {
         self.archive.file_entries.par_iter().try_for_each(|entry| {
             let file_contents = self.extract(entry)?;
             let mut output_file_name = PathBuf::from(output_path);
-            output_file_name.push(&entry.full_path);
+            output_file_name.push(archive::output_path(&entry.full_path));
             std::fs::create_dir_all(
                 &output_file_name
                     .parent()
                     .context("Could not get parent directory")?,
             )?;
             log::debug!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_name,
+                "Extracting resource: {} {:X?}",
+                display_name(&output_file_name),
                 entry
             );
             File::create(output_file_name)?
@@ -106,28 +137,13 @@ impl archive::Archive for YpfArchive {
 
 impl YpfArchive {
     fn new_root_dir(entries: &[YpfFileEntry]) -> archive::Directory {
-        archive::Directory::new(
-            entries
-                .iter()
-                .map(|entry| {
-                    let file_offset = entry.file_offset as u64;
-                    let file_size = entry.file_size as u64;
-                    archive::FileEntry {
-                        file_name: String::from(
-                            entry
-                                .full_path
-                                .file_name()
-                                .expect("No file name")
-                                .to_str()
-                                .expect("Not valid UTF-8"),
-                        ),
-                        full_path: entry.full_path.clone(),
-                        file_offset,
-                        file_size,
-                    }
-                })
-                .collect(),
-        )
+        archive::Directory::from_entries(entries.iter().map(|entry| {
+            archive::FileEntry::new(
+                entry.full_path.clone(),
+                entry.file_offset as u64,
+                entry.file_size as u64,
+            )
+        }))
     }
     fn extract(&self, entry: &YpfFileEntry) -> anyhow::Result<FileContents> {
         let mut buf = BytesMut::with_capacity(entry.file_size as usize);
@@ -185,6 +201,8 @@ struct YpfHeader {
     padding: [u8; 16],
 }
 
+impl ReportHeader for YpfHeader {}
+
 #[derive(Debug)]
 struct YpfFileEntry {
     unk0: u32,
@@ -249,19 +267,24 @@ fn get_name_size(
 }
 
 fn get_decrypt_name_table(archive_version: u32) -> anyhow::Result<Vec<u8>> {
-    let decrypt_name_tables: HashMap<u32, Vec<u8>> = serde_json::from_slice(
-        &crate::Resources::get("ypf/decrypt_name_tables.json").context(
-            format!("Could not find file: {}", "ypf/decrypt_name_tables.json"),
-        )?,
-    )?;
+    let decrypt_name_tables: HashMap<u32, Vec<u8>> =
+        embedded::load_json(DECRYPT_NAME_TABLES_PATH)?;
     Ok(match decrypt_name_tables.get(&archive_version) {
         Some(table) => table.clone(),
         None => {
-            return Err(AkaibuError::Unimplemented(format!(
-                "Unsupported YPF archive version: {}",
-                archive_version
-            ))
-            .into())
+            let mut versions =
+                decrypt_name_tables.keys().copied().collect::<Vec<u32>>();
+            versions.sort_unstable();
+            return Err(AkaibuError::UnsupportedVersion {
+                scheme: "YPF".to_string(),
+                found: archive_version.to_string(),
+                expected: versions
+                    .iter()
+                    .map(|v| v.to_string())
+                    .collect::<Vec<String>>()
+                    .join(", "),
+            }
+            .into());
         }
     })
 }
@@ -0,0 +1,380 @@
+use crate::error::AkaibuError;
+use anyhow::Context;
+use crc_any::CRC;
+use scroll::{Pread, LE};
+
+const RIFF_HEADER_SIZE: usize = 0xC;
+const SMPL_CHUNK_ID: &[u8] = b"smpl";
+const OGG_PAGE_HEADER_SIZE: usize = 27;
+const OGG_CONTINUED_PACKET: u8 = 1;
+const VORBIS_COMMENT_HEADER: &[u8] = b"\x03vorbis";
+/// Vorbis comments holding loop, `LOOPEND` is written by some tools instead
+/// of `LOOPLENGTH`
+const VORBIS_LOOP_COMMENTS: &[&str] = &["LOOPSTART", "LOOPLENGTH", "LOOPEND"];
+
+/// Loop region in sample frames, end is exclusive
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LoopPoints {
+    pub start: u32,
+    pub end: u32,
+}
+
+/// Build WAV `smpl` chunk with single forward infinite loop
+pub fn smpl_chunk(sample_rate: u32, loop_points: LoopPoints) -> Vec<u8> {
+    let sample_period = if sample_rate != 0 {
+        1_000_000_000 / sample_rate
+    } else {
+        0
+    };
+    let fields: [u32; 15] = [
+        0,             // manufacturer
+        0,             // product
+        sample_period, // in nanoseconds
+        60,            // MIDI unity note
+        0,             // MIDI pitch fraction
+        0,             // SMPTE format
+        0,             // SMPTE offset
+        1,             // loop count
+        0,             // sampler data size
+        0,             // cue point id
+        0,             // loop type, forward
+        loop_points.start,
+        loop_points.end.saturating_sub(1),
+        0, // fraction
+        0, // play count, infinite
+    ];
+    let mut chunk = Vec::with_capacity(8 + fields.len() * 4);
+    chunk.extend_from_slice(SMPL_CHUNK_ID);
+    chunk.extend_from_slice(&((fields.len() * 4) as u32).to_le_bytes());
+    fields
+        .iter()
+        .for_each(|field| chunk.extend_from_slice(&field.to_le_bytes()));
+    chunk
+}
+
+/// Vorbis comments understood by most players and engines
+pub fn vorbis_loop_comments(loop_points: LoopPoints) -> Vec<(String, String)> {
+    vec![
+        ("LOOPSTART".to_string(), loop_points.start.to_string()),
+        (
+            "LOOPLENGTH".to_string(),
+            loop_points
+                .end
+                .saturating_sub(loop_points.start)
+                .to_string(),
+        ),
+    ]
+}
+
+/// Append `smpl` chunk to RIFF WAVE file, replacing existing one
+pub fn set_wav_loop(
+    wav: &[u8],
+    sample_rate: u32,
+    loop_points: LoopPoints,
+) -> anyhow::Result<Vec<u8>> {
+    let mut wav = strip_wav_loop(wav)?;
+    wav.extend_from_slice(&smpl_chunk(sample_rate, loop_points));
+    update_riff_size(&mut wav);
+    Ok(wav)
+}
+
+/// Remove `smpl` chunks from RIFF WAVE file
+pub fn strip_wav_loop(wav: &[u8]) -> anyhow::Result<Vec<u8>> {
+    if wav.get(..4) != Some(&b"RIFF"[..])
+        || wav.get(8..12) != Some(&b"WAVE"[..])
+    {
+        return Err(
+            AkaibuError::Custom("Not RIFF WAVE file".to_string()).into()
+        );
+    }
+    let mut ret = wav[..RIFF_HEADER_SIZE].to_vec();
+    let mut off = RIFF_HEADER_SIZE;
+    while off + 8 <= wav.len() {
+        let chunk_start = off;
+        let chunk_id = &wav[off..off + 4];
+        let chunk_size = wav.pread_with::<u32>(off + 4, LE)? as usize;
+        // Chunks are word aligned
+        let chunk_end =
+            (off + 8 + chunk_size + (chunk_size & 1)).min(wav.len());
+        off = chunk_end;
+        if chunk_id != SMPL_CHUNK_ID {
+            ret.extend_from_slice(&wav[chunk_start..chunk_end]);
+        }
+    }
+    update_riff_size(&mut ret);
+    Ok(ret)
+}
+
+fn update_riff_size(wav: &mut [u8]) {
+    let riff_size = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+}
+
+/// Sample rate from `fmt ` chunk of RIFF WAVE file
+pub fn wav_sample_rate(wav: &[u8]) -> anyhow::Result<u32> {
+    let mut off = RIFF_HEADER_SIZE;
+    while off + 8 <= wav.len() {
+        let chunk_size = wav.pread_with::<u32>(off + 4, LE)? as usize;
+        if &wav[off..off + 4] == b"fmt " {
+            return Ok(wav.pread_with::<u32>(off + 12, LE)?);
+        }
+        off += 8 + chunk_size + (chunk_size & 1);
+    }
+    Err(AkaibuError::Custom("WAV without fmt chunk".to_string()).into())
+}
+
+/// Loop of KiriKiri `.sli` file stored next to sound it belongs to. Newer
+/// files describe jumps between positions as `Link { From=...; To=...; }`,
+/// first link jumping backwards is the loop. Older ones have single
+/// `LoopStart=` and `LoopLength=` pair. Positions are in sample frames.
+pub fn parse_sli(text: &str) -> anyhow::Result<LoopPoints> {
+    let field = |s: &str, name: &str| -> Option<u32> {
+        let value = &s[s.find(name)? + name.len()..];
+        let end = value
+            .find(|c: char| !c.is_ascii_digit())
+            .unwrap_or_else(|| value.len());
+        value[..end].parse::<u32>().ok()
+    };
+    let link = text
+        .split("Link")
+        .skip(1)
+        .filter_map(|link| {
+            let link = &link[..link.find('}')?];
+            Some((field(link, "From=")?, field(link, "To=")?))
+        })
+        .find(|(from, to)| from > to);
+    if let Some((from, to)) = link {
+        return Ok(LoopPoints {
+            start: to,
+            end: from,
+        });
+    }
+    match (field(text, "LoopStart="), field(text, "LoopLength=")) {
+        (Some(start), Some(length)) if length > 0 => Ok(LoopPoints {
+            start,
+            end: start.saturating_add(length),
+        }),
+        _ => Err(AkaibuError::Custom(
+            "No backward link or loop in SLI file".to_string(),
+        )
+        .into()),
+    }
+}
+
+/// Ogg page with header fields that are rewritten
+#[derive(Debug)]
+struct OggPage<'a> {
+    header_type: u8,
+    granule: u64,
+    serial: u32,
+    lacing: &'a [u8],
+    data: &'a [u8],
+}
+
+/// Ogg checksum, CRC-32 with polynomial 0x04C11DB7 neither reflected nor
+/// inverted, over page with checksum field zeroed
+fn ogg_crc(page: &[u8]) -> u32 {
+    let mut crc = CRC::create_crc(0x04C1_1DB7, 32, 0, 0, false);
+    crc.digest(page);
+    crc.get_crc() as u32
+}
+
+fn read_ogg_pages(ogg: &[u8]) -> anyhow::Result<Vec<OggPage<'_>>> {
+    let mut pages = Vec::new();
+    let mut off = 0;
+    while off < ogg.len() {
+        let header = ogg
+            .get(off..off + OGG_PAGE_HEADER_SIZE)
+            .context("Ogg page header out of bounds")?;
+        if &header[..4] != b"OggS" || header[4] != 0 {
+            return Err(AkaibuError::Custom(format!(
+                "No Ogg page at {:#X}",
+                off
+            ))
+            .into());
+        }
+        let segments = header[26] as usize;
+        let lacing_start = off + OGG_PAGE_HEADER_SIZE;
+        let lacing = ogg
+            .get(lacing_start..lacing_start + segments)
+            .context("Ogg lacing values out of bounds")?;
+        let data_start = lacing_start + segments;
+        let data_size = lacing.iter().map(|&l| l as usize).sum::<usize>();
+        let data = ogg
+            .get(data_start..data_start + data_size)
+            .context("Ogg page data out of bounds")?;
+        pages.push(OggPage {
+            header_type: header[5],
+            granule: header.pread_with::<u64>(6, LE)?,
+            serial: header.pread_with::<u32>(14, LE)?,
+            lacing,
+            data,
+        });
+        off = data_start + data_size;
+    }
+    Ok(pages)
+}
+
+fn write_ogg_page(out: &mut Vec<u8>, page: &OggPage<'_>, sequence: u32) {
+    let start = out.len();
+    out.extend_from_slice(b"OggS\0");
+    out.push(page.header_type);
+    out.extend_from_slice(&page.granule.to_le_bytes());
+    out.extend_from_slice(&page.serial.to_le_bytes());
+    out.extend_from_slice(&sequence.to_le_bytes());
+    out.extend_from_slice(&[0; 4]);
+    out.push(page.lacing.len() as u8);
+    out.extend_from_slice(page.lacing);
+    out.extend_from_slice(page.data);
+    let crc = ogg_crc(&out[start..]);
+    out[start + 22..start + 26].copy_from_slice(&crc.to_le_bytes());
+}
+
+/// Packets of pages up to and including one where given number of packets
+/// is complete, with number of pages read
+fn read_ogg_packets(
+    pages: &[OggPage<'_>],
+    count: usize,
+) -> anyhow::Result<(Vec<Vec<u8>>, usize)> {
+    let mut packets = vec![Vec::new()];
+    for (i, page) in pages.iter().enumerate() {
+        let mut off = 0;
+        for (j, &lace) in page.lacing.iter().enumerate() {
+            let packet = packets.last_mut().context("No Ogg packet")?;
+            packet.extend_from_slice(&page.data[off..off + lace as usize]);
+            off += lace as usize;
+            if lace < 255 {
+                if packets.len() == count {
+                    if j + 1 < page.lacing.len() {
+                        return Err(AkaibuError::Custom(
+                            "Ogg page mixes headers with other packets"
+                                .to_string(),
+                        )
+                        .into());
+                    }
+                    return Ok((packets, i + 1));
+                }
+                packets.push(Vec::new());
+            }
+        }
+    }
+    Err(AkaibuError::Custom("Ogg headers are incomplete".to_string()).into())
+}
+
+/// Vorbis comment header without comments of removed names and with added
+/// comments appended
+fn replace_vorbis_comments(
+    packet: &[u8],
+    removed: &[&str],
+    added: &[(String, String)],
+) -> anyhow::Result<Vec<u8>> {
+    if !packet.starts_with(VORBIS_COMMENT_HEADER) {
+        return Err(AkaibuError::Custom(
+            "Second Ogg packet is not Vorbis comment header".to_string(),
+        )
+        .into());
+    }
+    let off = &mut VORBIS_COMMENT_HEADER.len();
+    let vendor_size = packet.gread_with::<u32>(off, LE)? as usize;
+    let vendor = packet
+        .get(*off..*off + vendor_size)
+        .context("Vorbis vendor out of bounds")?;
+    *off += vendor_size;
+    let count = packet.gread_with::<u32>(off, LE)?;
+    let mut kept = Vec::with_capacity(count as usize);
+    for _ in 0..count {
+        let size = packet.gread_with::<u32>(off, LE)? as usize;
+        let comment = packet
+            .get(*off..*off + size)
+            .context("Vorbis comment out of bounds")?;
+        *off += size;
+        let name = comment.split(|&b| b == b'=').next().unwrap_or_default();
+        if !removed
+            .iter()
+            .any(|removed| removed.as_bytes().eq_ignore_ascii_case(name))
+        {
+            kept.push(comment.to_vec());
+        }
+    }
+    kept.extend(
+        added
+            .iter()
+            .map(|(name, value)| format!("{}={}", name, value).into_bytes()),
+    );
+
+    let mut ret = VORBIS_COMMENT_HEADER.to_vec();
+    ret.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    ret.extend_from_slice(vendor);
+    ret.extend_from_slice(&(kept.len() as u32).to_le_bytes());
+    for comment in kept {
+        ret.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        ret.extend_from_slice(&comment);
+    }
+    // Framing bit
+    ret.push(1);
+    Ok(ret)
+}
+
+/// Set `LOOPSTART` and `LOOPLENGTH` comments of Ogg Vorbis file
+pub fn set_ogg_loop(
+    ogg: &[u8],
+    loop_points: LoopPoints,
+) -> anyhow::Result<Vec<u8>> {
+    rewrite_ogg_comments(ogg, &vorbis_loop_comments(loop_points))
+}
+
+/// Remove loop comments from Ogg Vorbis file
+pub fn strip_ogg_loop(ogg: &[u8]) -> anyhow::Result<Vec<u8>> {
+    rewrite_ogg_comments(ogg, &[])
+}
+
+/// Replace loop comments of Ogg Vorbis file with given ones. Comment and
+/// setup headers are laid out into new pages, pages after them keep their
+/// contents and are renumbered.
+fn rewrite_ogg_comments(
+    ogg: &[u8],
+    loop_comments: &[(String, String)],
+) -> anyhow::Result<Vec<u8>> {
+    let pages = read_ogg_pages(ogg)?;
+    let first = pages.first().context("Ogg without pages")?;
+    // Identification header has first page to itself
+    let (mut packets, header_pages) =
+        read_ogg_packets(&pages[1..], 2).context("Ogg Vorbis headers")?;
+    packets[0] = replace_vorbis_comments(
+        &packets[0],
+        VORBIS_LOOP_COMMENTS,
+        loop_comments,
+    )?;
+
+    let mut lacing = Vec::new();
+    let mut data = Vec::new();
+    for packet in &packets {
+        lacing.extend(std::iter::repeat(255).take(packet.len() / 255));
+        lacing.push((packet.len() % 255) as u8);
+        data.extend_from_slice(packet);
+    }
+    let mut ret = Vec::with_capacity(ogg.len() + 64);
+    write_ogg_page(&mut ret, first, 0);
+    let mut sequence = 1;
+    let mut data_off = 0;
+    let mut continued = false;
+    for lacing in lacing.chunks(255) {
+        let size = lacing.iter().map(|&l| l as usize).sum::<usize>();
+        let page = OggPage {
+            header_type: if continued { OGG_CONTINUED_PACKET } else { 0 },
+            granule: 0,
+            serial: first.serial,
+            lacing,
+            data: &data[data_off..data_off + size],
+        };
+        write_ogg_page(&mut ret, &page, sequence);
+        data_off += size;
+        sequence += 1;
+        continued = lacing.last() == Some(&255);
+    }
+    for page in &pages[1 + header_pages..] {
+        write_ogg_page(&mut ret, page, sequence);
+        sequence += 1;
+    }
+    Ok(ret)
+}
@@ -0,0 +1,49 @@
+use crate::error::AkaibuError;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Default limit of single decompressed buffer
+pub const DEFAULT_MAX_OUTPUT_SIZE: usize = 1 << 30;
+
+static MAX_OUTPUT_SIZE: AtomicUsize = AtomicUsize::new(DEFAULT_MAX_OUTPUT_SIZE);
+
+/// Limit size of buffers allocated from sizes stored in file headers
+pub fn set_max_output_size(size: usize) {
+    MAX_OUTPUT_SIZE.store(size, Ordering::Relaxed);
+}
+
+pub fn max_output_size() -> usize {
+    MAX_OUTPUT_SIZE.load(Ordering::Relaxed)
+}
+
+/// Zeroed buffer for decompressed data, fails when size read from file
+/// header is over configured limit
+pub fn output_buffer(size: usize) -> Result<Vec<u8>, AkaibuError> {
+    let limit = max_output_size();
+    if size > limit {
+        return Err(AkaibuError::OutputTooLarge { size, limit });
+    }
+    Ok(vec![0; size])
+}
+
+pub(crate) fn malformed(what: &str) -> AkaibuError {
+    AkaibuError::MalformedData(what.to_string())
+}
+
+/// Read byte at index of compressed data
+pub(crate) fn read(src: &[u8], index: usize) -> Result<u8, AkaibuError> {
+    src.get(index)
+        .copied()
+        .ok_or_else(|| malformed("read past end of input"))
+}
+
+/// Write byte at index of decompressed data
+pub(crate) fn write(
+    dest: &mut [u8],
+    index: usize,
+    value: u8,
+) -> Result<(), AkaibuError> {
+    *dest
+        .get_mut(index)
+        .ok_or_else(|| malformed("write past end of output"))? = value;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+use crate::error::AkaibuError;
+#[cfg(feature = "fmt-gyu")]
+use crate::resource::gyu;
+#[cfg(feature = "fmt-cpz7")]
+use crate::scheme::cpz7;
+#[cfg(feature = "fmt-malie")]
+use crate::scheme::malie;
+#[cfg(feature = "fmt-qlie")]
+use crate::scheme::qliepack;
+#[cfg(feature = "fmt-tactics")]
+use crate::scheme::tactics_arc;
+#[cfg(feature = "fmt-ypf")]
+use crate::scheme::ypf;
+use serde::de::DeserializeOwned;
+use serde_json::Value;
+
+/// Load and deserialize JSON file embedded in crate resources
+pub(crate) fn load_json<T: DeserializeOwned>(
+    path: &str,
+) -> Result<T, AkaibuError> {
+    let file = crate::Resources::get(path).ok_or_else(|| {
+        AkaibuError::EmbeddedResource {
+            path: path.to_string(),
+            reason: "file not found".to_string(),
+        }
+    })?;
+    serde_json::from_slice(&file).map_err(|err| AkaibuError::EmbeddedResource {
+        path: path.to_string(),
+        reason: err.to_string(),
+    })
+}
+
+/// Check that table deserializes into type its scheme expects
+type TableCheck = fn(Value) -> Result<(), serde_json::Error>;
+
+fn check<T: DeserializeOwned>(table: Value) -> Result<(), serde_json::Error> {
+    serde_json::from_value::<T>(table).map(drop)
+}
+
+/// Paths of key tables of enabled formats with check of type their scheme
+/// expects
+pub(crate) fn key_tables() -> Vec<(&'static str, TableCheck)> {
+    #[allow(unused_imports)]
+    use std::collections::HashMap;
+
+    #[allow(unused_mut)]
+    let mut tables: Vec<(&'static str, TableCheck)> = Vec::new();
+    #[cfg(feature = "fmt-gyu")]
+    tables.push((gyu::SEEDS_PATH, check::<HashMap<String, Vec<u32>>>));
+    #[cfg(feature = "fmt-malie")]
+    tables.push((malie::KEYS_PATH, check::<HashMap<String, Vec<u8>>>));
+    #[cfg(feature = "fmt-qlie")]
+    tables.push((
+        qliepack::KEYS_PATH,
+        check::<HashMap<String, HashMap<String, Vec<u32>>>>,
+    ));
+    #[cfg(feature = "fmt-tactics")]
+    tables.push((tactics_arc::KEYS_PATH, check::<HashMap<String, String>>));
+    #[cfg(feature = "fmt-ypf")]
+    tables.push((
+        ypf::DECRYPT_NAME_TABLES_PATH,
+        check::<HashMap<u32, Vec<u8>>>,
+    ));
+    #[cfg(feature = "fmt-cpz7")]
+    tables.extend(
+        cpz7::KEYS_PATHS.iter().map(|path| {
+            (*path, check::<HashMap<String, [u32; 4]>> as TableCheck)
+        }),
+    );
+    tables
+}
@@ -0,0 +1,83 @@
+use crate::{error::AkaibuError, util::text::display_name};
+use positioned_io::RandomAccessFile;
+use std::{
+    fs::{File, OpenOptions},
+    io,
+    path::Path,
+    time::Duration,
+};
+
+/// Times opening file held by another process is tried again before giving
+/// up, games often keep archives open only while loading them
+const LOCKED_RETRIES: u32 = 3;
+const LOCKED_RETRY_DELAY: Duration = Duration::from_millis(250);
+
+/// Open archive for positioned reads, see [`open_shared`]
+pub fn open_archive_file(path: &Path) -> anyhow::Result<RandomAccessFile> {
+    Ok(RandomAccessFile::try_new(open_shared(path)?)?)
+}
+
+/// Open file read only, letting other processes keep reading, writing and
+/// deleting it. File locked by process that does not share it, like running
+/// game on Windows, is tried again for short while and then reported as
+/// [`AkaibuError::FileInUse`].
+pub fn open_shared(path: &Path) -> anyhow::Result<File> {
+    let mut retries = 0;
+    loop {
+        match shared_options().open(path) {
+            Ok(file) => return Ok(file),
+            Err(err) if is_locked(&err) && retries < LOCKED_RETRIES => {
+                retries += 1;
+                log::debug!(
+                    "{} is locked, trying again: {}",
+                    display_name(path),
+                    err
+                );
+                std::thread::sleep(LOCKED_RETRY_DELAY);
+            }
+            Err(err) if is_locked(&err) => {
+                return Err(AkaibuError::FileInUse(path.to_path_buf()).into())
+            }
+            Err(err) => return Err(err.into()),
+        }
+    }
+}
+
+#[cfg(windows)]
+fn shared_options() -> OpenOptions {
+    use std::os::windows::fs::OpenOptionsExt;
+
+    const FILE_SHARE_READ: u32 = 0x1;
+    const FILE_SHARE_WRITE: u32 = 0x2;
+    const FILE_SHARE_DELETE: u32 = 0x4;
+
+    let mut options = OpenOptions::new();
+    options
+        .read(true)
+        .share_mode(FILE_SHARE_READ | FILE_SHARE_WRITE | FILE_SHARE_DELETE);
+    options
+}
+
+#[cfg(not(windows))]
+fn shared_options() -> OpenOptions {
+    let mut options = OpenOptions::new();
+    options.read(true);
+    options
+}
+
+/// Sharing or lock violation, only reported by Windows
+#[cfg(windows)]
+fn is_locked(err: &io::Error) -> bool {
+    const ERROR_SHARING_VIOLATION: i32 = 32;
+    const ERROR_LOCK_VIOLATION: i32 = 33;
+
+    matches!(
+        err.raw_os_error(),
+        Some(ERROR_SHARING_VIOLATION) | Some(ERROR_LOCK_VIOLATION)
+    )
+}
+
+#[cfg(not(windows))]
+fn is_locked(_err: &io::Error) -> bool {
+    false
+}
@@ -1,3 +1,5 @@
+use crc_any::CRC;
+
 pub fn bitmap_to_png(buf: Vec<u8>, width_in_bytes: usize) -> Vec<u8> {
     buf.chunks_exact(width_in_bytes)
         .rev()
@@ -64,3 +66,121 @@ pub fn resolve_color_table_without_alpha(
         },
     )
 }
+
+/// Length of PNG signature and IHDR chunk, text chunks are placed after them
+const PNG_HEADER_SIZE: usize = 8 + 4 + 4 + 13 + 4;
+
+/// Encode PNG with text chunk for each keyword and text after its header.
+/// ASCII text is written as `tEXt`, anything else as UTF-8 `iTXt`, as
+/// `tEXt` only holds Latin-1.
+pub fn encode_png_with_text(
+    image: &image::RgbaImage,
+    text: &[(&str, String)],
+) -> anyhow::Result<Vec<u8>> {
+    let mut png = Vec::new();
+    image::png::PngEncoder::new(&mut png).encode(
+        image,
+        image.width(),
+        image.height(),
+        image::ColorType::Rgba8,
+    )?;
+    let mut chunks = Vec::new();
+    for (keyword, text) in text {
+        let mut data = keyword.as_bytes().to_vec();
+        data.push(0);
+        let chunk_type = if text.bytes().all(|b| b.is_ascii()) {
+            b"tEXt"
+        } else {
+            // Not compressed, without language tag and translated keyword
+            data.extend_from_slice(&[0, 0, 0, 0]);
+            b"iTXt"
+        };
+        data.extend_from_slice(text.as_bytes());
+        chunks.extend_from_slice(&(data.len() as u32).to_be_bytes());
+        let start = chunks.len();
+        chunks.extend_from_slice(chunk_type);
+        chunks.extend_from_slice(&data);
+        let mut crc = CRC::crc32();
+        crc.digest(&chunks[start..]);
+        chunks.extend_from_slice(&(crc.get_crc() as u32).to_be_bytes());
+    }
+    let mut stamped = Vec::with_capacity(png.len() + chunks.len());
+    stamped.extend_from_slice(&png[..PNG_HEADER_SIZE]);
+    stamped.extend_from_slice(&chunks);
+    stamped.extend_from_slice(&png[PNG_HEADER_SIZE..]);
+    Ok(stamped)
+}
+
+const BMP_FILE_HEADER_SIZE: u32 = 14;
+/// BITMAPV4HEADER, needed for alpha channel mask
+const BMP_INFO_HEADER_SIZE: u32 = 108;
+const BI_BITFIELDS: u32 = 3;
+
+/// Write uncompressed 32bpp BMP with alpha. Rows are stored top-down, so
+/// pixels are only swizzled to BGRA without any encoding.
+pub fn write_bmp(
+    image: &image::RgbaImage,
+    writer: &mut impl std::io::Write,
+) -> std::io::Result<()> {
+    let (width, height) = image.dimensions();
+    let pixel_data_offset = BMP_FILE_HEADER_SIZE + BMP_INFO_HEADER_SIZE;
+    let pixel_data_size = image.as_raw().len() as u32;
+
+    let mut header = Vec::with_capacity(pixel_data_offset as usize);
+    header.extend_from_slice(b"BM");
+    header.extend_from_slice(
+        &(pixel_data_offset + pixel_data_size).to_le_bytes(),
+    );
+    header.extend_from_slice(&[0; 4]);
+    header.extend_from_slice(&pixel_data_offset.to_le_bytes());
+    header.extend_from_slice(&BMP_INFO_HEADER_SIZE.to_le_bytes());
+    header.extend_from_slice(&(width as i32).to_le_bytes());
+    // Negative height marks top-down rows
+    header.extend_from_slice(&(-(height as i32)).to_le_bytes());
+    header.extend_from_slice(&1u16.to_le_bytes());
+    header.extend_from_slice(&32u16.to_le_bytes());
+    header.extend_from_slice(&BI_BITFIELDS.to_le_bytes());
+    header.extend_from_slice(&pixel_data_size.to_le_bytes());
+    // Resolution, palette size and important colors
+    header.extend_from_slice(&[0; 16]);
+    // Red, green, blue and alpha masks of BGRA pixel
+    for mask in &[0x00FF_0000u32, 0x0000_FF00, 0x0000_00FF, 0xFF00_0000] {
+        header.extend_from_slice(&mask.to_le_bytes());
+    }
+    // sRGB color space, endpoints and gamma are unused
+    header.extend_from_slice(b"BGRs");
+    header.resize(pixel_data_offset as usize, 0);
+    writer.write_all(&header)?;
+
+    let mut pixels = image.as_raw().clone();
+    pixels
+        .chunks_exact_mut(4)
+        .for_each(|pixel| pixel.swap(0, 2));
+    writer.write_all(&pixels)
+}
+
+/// Downscale image with triangle filter so its larger side is at most
+/// `max_dimension`, keeping aspect ratio. Smaller images are returned as
+/// they are.
+pub fn downscale(
+    image: image::RgbaImage,
+    max_dimension: u32,
+) -> image::RgbaImage {
+    let max_dimension = max_dimension.max(1);
+    let (width, height) = image.dimensions();
+    let larger = width.max(height);
+    if larger <= max_dimension {
+        return image;
+    }
+    let scale = |side: u32| {
+        ((side as u64 * max_dimension as u64 + larger as u64 / 2)
+            / larger as u64)
+            .max(1) as u32
+    };
+    image::imageops::resize(
+        &image,
+        scale(width),
+        scale(height),
+        image::imageops::FilterType::Triangle,
+    )
+}
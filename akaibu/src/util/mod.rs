@@ -1,7 +1,14 @@
+pub mod audio;
+pub mod bounds;
+pub mod embedded;
+pub mod fs;
 pub mod image;
 pub mod md5;
 pub mod mt;
+pub mod script;
 pub mod simd;
+pub mod text;
+pub mod volume;
 
 pub fn crc64(buf: &[u8]) -> u64 {
     use crc_any::CRC;
@@ -21,6 +28,118 @@ pub fn zlib_decompress(buf: &[u8]) -> anyhow::Result<Vec<u8>> {
     Ok(ret)
 }
 
+/// Common LZSS with 0x1000 byte window starting at 0xFEE. Flag bits are
+/// read from lowest, set bit is literal byte, otherwise two bytes hold
+/// 12 bit window position and 4 bit length minus 3.
+pub fn lzss_decompress(buf: &[u8], dest_len: usize) -> anyhow::Result<Vec<u8>> {
+    const WINDOW_SIZE: usize = 0x1000;
+
+    let mut dest = bounds::output_buffer(dest_len)?;
+    let mut window = [0u8; WINDOW_SIZE];
+    let mut window_pos = 0xFEE;
+    let mut src = 0;
+    let mut dst = 0;
+    let mut flags = 0u16;
+    while dst < dest_len && src < buf.len() {
+        flags >>= 1;
+        if flags & 0x100 == 0 {
+            flags = bounds::read(buf, src)? as u16 | 0xFF00;
+            src += 1;
+        }
+        if flags & 1 != 0 {
+            let b = bounds::read(buf, src)?;
+            src += 1;
+            bounds::write(&mut dest, dst, b)?;
+            dst += 1;
+            window[window_pos] = b;
+            window_pos = (window_pos + 1) % WINDOW_SIZE;
+        } else {
+            let lo = bounds::read(buf, src)? as usize;
+            let hi = bounds::read(buf, src + 1)? as usize;
+            src += 2;
+            let pos = lo | (hi & 0xF0) << 4;
+            for i in 0..(hi & 0x0F) + 3 {
+                if dst >= dest_len {
+                    break;
+                }
+                let b = window[(pos + i) % WINDOW_SIZE];
+                dest[dst] = b;
+                dst += 1;
+                window[window_pos] = b;
+                window_pos = (window_pos + 1) % WINDOW_SIZE;
+            }
+        }
+    }
+    if dst != dest_len {
+        return Err(bounds::malformed("LZSS data shorter than expected").into());
+    }
+    Ok(dest)
+}
+
 pub fn md5(buf: &[u8]) -> [u8; 16] {
     md5::compute(&buf, [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476])
 }
+
+/// Hex encoded SHA-1 of file contents
+pub fn sha1_file(path: &std::path::Path) -> anyhow::Result<String> {
+    use std::io::Read;
+
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = sha1::Sha1::new();
+    let mut buf = vec![0; crate::ONE_MB];
+    loop {
+        let read = file.read(&mut buf)?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hasher.digest().to_string())
+}
+
+/// Write file under temporary name and rename it when done, so interrupted
+/// write never leaves partial file under final name
+pub fn write_atomic(path: &std::path::Path, buf: &[u8]) -> anyhow::Result<()> {
+    use std::io::Write;
+
+    write_atomic_with(path, |file| Ok(file.write_all(buf)?))
+}
+
+/// Like [`write_atomic`], with contents written to file by given function,
+/// so they do not have to be in memory at once. Temporary file is removed
+/// when writing fails
+pub fn write_atomic_with<T>(
+    path: &std::path::Path,
+    write: impl FnOnce(&mut std::fs::File) -> anyhow::Result<T>,
+) -> anyhow::Result<T> {
+    let mut tmp_path = path.as_os_str().to_os_string();
+    tmp_path.push(".part");
+    let written = std::fs::File::create(&tmp_path)
+        .map_err(anyhow::Error::from)
+        .and_then(|mut file| write(&mut file))
+        .and_then(|written| {
+            std::fs::rename(&tmp_path, path)?;
+            Ok(written)
+        });
+    if written.is_err() {
+        // Partial file is of no use, do not leave it next to output
+        let _ = std::fs::remove_file(&tmp_path);
+    }
+    written
+}
+
+/// All files under directory and its subdirectories
+pub fn walk_files(
+    dir: &std::path::Path,
+    files: &mut Vec<std::path::PathBuf>,
+) -> anyhow::Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            walk_files(&path, files)?;
+        } else if path.is_file() {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
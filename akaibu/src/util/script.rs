@@ -0,0 +1,190 @@
+use crate::{error::AkaibuError, util::text};
+use encoding_rs::SHIFT_JIS;
+use std::{fmt::Debug, str::FromStr};
+
+/// Piece of SHIFT-JIS script
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Token<'a> {
+    /// Text shown to player
+    Text(&'a [u8]),
+    /// Engine control code, has to stay exactly as stored
+    Code(&'a [u8]),
+}
+
+/// Finds control codes of one engine in SHIFT-JIS script. Codes are looked
+/// for only at character boundaries, so second byte of double byte
+/// character is never taken for `\`, `[` or `|` of control code.
+pub trait ScriptTokenizer: Debug + Send + Sync {
+    /// Length of control code at start of buffer, 0 when there is none
+    fn code_len(&self, buf: &[u8]) -> usize;
+}
+
+/// Script without control codes
+#[derive(Debug, Clone, Copy)]
+pub struct PlainTokenizer;
+
+impl ScriptTokenizer for PlainTokenizer {
+    fn code_len(&self, _buf: &[u8]) -> usize {
+        0
+    }
+}
+
+/// BGI text with `%` format codes like `%N` and tags in angle brackets,
+/// like ruby `<r漢字|かんじ>`
+#[derive(Debug, Clone, Copy)]
+pub struct BurikoTokenizer;
+
+impl ScriptTokenizer for BurikoTokenizer {
+    fn code_len(&self, buf: &[u8]) -> usize {
+        match buf {
+            [b'%', c, ..] if c.is_ascii_alphanumeric() => 2,
+            [b'<', ..] => enclosed_len(buf, b'>'),
+            _ => 0,
+        }
+    }
+}
+
+/// Silky text with `\` escapes followed by letter and optional number, like
+/// `\n` or `\w30`, and commands in square brackets
+#[derive(Debug, Clone, Copy)]
+pub struct SilkyTokenizer;
+
+impl ScriptTokenizer for SilkyTokenizer {
+    fn code_len(&self, buf: &[u8]) -> usize {
+        match buf {
+            [b'\\', c, rest @ ..] if c.is_ascii_alphabetic() => {
+                2 + rest.iter().take_while(|b| b.is_ascii_digit()).count()
+            }
+            [b'[', ..] => enclosed_len(buf, b']'),
+            _ => 0,
+        }
+    }
+}
+
+/// Length of code from its first byte up to and including closing byte on
+/// the same line, 0 when it is not closed
+fn enclosed_len(buf: &[u8], close: u8) -> usize {
+    let mut off = 1;
+    while off < buf.len() {
+        match buf[off] {
+            b if b == close => return off + 1,
+            b'\r' | b'\n' => return 0,
+            lead => off += char_len(lead),
+        }
+    }
+    0
+}
+
+/// Length of SHIFT-JIS character starting with given byte
+fn char_len(lead: u8) -> usize {
+    match lead {
+        0x81..=0x9F | 0xE0..=0xFC => 2,
+        _ => 1,
+    }
+}
+
+/// Split SHIFT-JIS script into text and control codes of engine
+pub fn tokenize<'a>(
+    tokenizer: &dyn ScriptTokenizer,
+    buf: &'a [u8],
+) -> Vec<Token<'a>> {
+    let mut tokens = Vec::new();
+    let mut text_start = 0;
+    let mut off = 0;
+    while off < buf.len() {
+        let code_len = tokenizer.code_len(&buf[off..]).min(buf.len() - off);
+        if code_len == 0 {
+            off = (off + char_len(buf[off])).min(buf.len());
+            continue;
+        }
+        if text_start < off {
+            tokens.push(Token::Text(&buf[text_start..off]));
+        }
+        tokens.push(Token::Code(&buf[off..off + code_len]));
+        off += code_len;
+        text_start = off;
+    }
+    if text_start < buf.len() {
+        tokens.push(Token::Text(&buf[text_start..]));
+    }
+    tokens
+}
+
+/// Decode SHIFT-JIS script to UTF-8 with LF line endings. Text characters
+/// missing from SHIFT-JIS are replaced, control codes must decode to text
+/// encoding back to the same bytes, so script can be encoded again for
+/// engine without breaking them.
+pub fn reencode(
+    tokenizer: &dyn ScriptTokenizer,
+    buf: &[u8],
+) -> anyhow::Result<String> {
+    let mut ret = String::with_capacity(buf.len() * 3 / 2);
+    let mut off = 0;
+    for token in tokenize(tokenizer, buf) {
+        match token {
+            Token::Text(bytes) => {
+                let (decoded, had_errors) =
+                    SHIFT_JIS.decode_without_bom_handling(bytes);
+                if had_errors {
+                    log::warn!(
+                        "Replaced characters missing from SHIFT-JIS near {:#X}",
+                        off
+                    );
+                }
+                ret.push_str(&text::normalize_newlines(&decoded));
+                off += bytes.len();
+            }
+            Token::Code(bytes) => {
+                let decoded = SHIFT_JIS
+                    .decode_without_bom_handling_and_without_replacement(bytes)
+                    .filter(|decoded| {
+                        SHIFT_JIS.encode(decoded).0.as_ref() == bytes
+                    })
+                    .ok_or_else(|| {
+                        AkaibuError::Custom(format!(
+                            "Control code {:02X?} at {:#X} would change when \
+                             re-encoded",
+                            bytes, off
+                        ))
+                    })?;
+                ret.push_str(&decoded);
+                off += bytes.len();
+            }
+        }
+    }
+    Ok(ret)
+}
+
+/// Engine whose control codes are kept when scripts are re-encoded
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptEngine {
+    Plain,
+    Buriko,
+    Silky,
+}
+
+impl ScriptEngine {
+    pub fn tokenizer(&self) -> &'static dyn ScriptTokenizer {
+        match self {
+            Self::Plain => &PlainTokenizer,
+            Self::Buriko => &BurikoTokenizer,
+            Self::Silky => &SilkyTokenizer,
+        }
+    }
+}
+
+impl FromStr for ScriptEngine {
+    type Err = AkaibuError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "plain" => Ok(Self::Plain),
+            "buriko" | "bgi" => Ok(Self::Buriko),
+            "silky" => Ok(Self::Silky),
+            _ => Err(AkaibuError::Custom(format!(
+                "Unknown script engine: {}, expected plain, buriko or silky",
+                s
+            ))),
+        }
+    }
+}
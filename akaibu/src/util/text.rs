@@ -0,0 +1,201 @@
+use encoding_rs::{SHIFT_JIS, UTF_16BE, UTF_16LE};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+const UTF8_BOM: &[u8] = &[0xEF, 0xBB, 0xBF];
+const UTF16LE_BOM: &[u8] = &[0xFF, 0xFE];
+const UTF16BE_BOM: &[u8] = &[0xFE, 0xFF];
+
+/// Check if buffer starts with any of the supported byte order marks
+pub fn has_bom(buf: &[u8]) -> bool {
+    buf.starts_with(UTF8_BOM)
+        || buf.starts_with(UTF16LE_BOM)
+        || buf.starts_with(UTF16BE_BOM)
+}
+
+/// Decode text to UTF-8 stripping BOM if present.
+/// Text without BOM is treated as UTF-8 if valid, SHIFT_JIS otherwise.
+pub fn decode(buf: &[u8]) -> String {
+    if let Some(rest) = buf.strip_prefix(UTF8_BOM) {
+        String::from_utf8_lossy(rest).into_owned()
+    } else if let Some(rest) = buf.strip_prefix(UTF16LE_BOM) {
+        UTF_16LE.decode_without_bom_handling(rest).0.into_owned()
+    } else if let Some(rest) = buf.strip_prefix(UTF16BE_BOM) {
+        UTF_16BE.decode_without_bom_handling(rest).0.into_owned()
+    } else {
+        match std::str::from_utf8(buf) {
+            Ok(s) => s.to_string(),
+            Err(_) => SHIFT_JIS.decode_without_bom_handling(buf).0.into_owned(),
+        }
+    }
+}
+
+/// Control characters not expected in text, EOF marker is kept by some
+/// old editors
+fn is_control(c: u32) -> bool {
+    c < 0x20 && !matches!(c as u8, b'\t' | b'\n' | b'\r' | 0x1A)
+}
+
+/// Check if text after BOM decodes in encoding BOM names, without control
+/// characters
+fn bom_text_is_valid(buf: &[u8]) -> bool {
+    let (rest, big_endian) = if let Some(rest) = buf.strip_prefix(UTF16LE_BOM) {
+        (rest, false)
+    } else if let Some(rest) = buf.strip_prefix(UTF16BE_BOM) {
+        (rest, true)
+    } else {
+        let rest = &buf[UTF8_BOM.len()..];
+        return !rest.iter().any(|b| is_control(*b as u32))
+            && std::str::from_utf8(rest)
+                .map_or_else(|err| err.error_len().is_none(), |_| true);
+    };
+    let units = rest
+        .chunks_exact(2)
+        .map(|c| {
+            if big_endian {
+                u16::from_be_bytes([c[0], c[1]])
+            } else {
+                u16::from_le_bytes([c[0], c[1]])
+            }
+        })
+        .collect::<Vec<u16>>();
+    let chars = char::decode_utf16(units).collect::<Vec<_>>();
+    chars.iter().enumerate().all(|(i, c)| match c {
+        Ok(c) => !is_control(*c as u32),
+        // Only high surrogate of last character is left
+        Err(err) => {
+            i + 1 == chars.len()
+                && (0xD800..0xDC00).contains(&err.unpaired_surrogate())
+        }
+    })
+}
+
+/// Guess if buffer is text. Text with BOM must decode in encoding it names,
+/// text without BOM is guessed to be UTF-8 or SHIFT_JIS.
+/// Last character may be cut off when buffer is only prefix of file.
+pub fn looks_like_text(buf: &[u8]) -> bool {
+    if buf.is_empty() {
+        return false;
+    }
+    if has_bom(buf) {
+        return bom_text_is_valid(buf);
+    }
+    if buf.iter().any(|b| is_control(*b as u32)) {
+        return false;
+    }
+    match std::str::from_utf8(buf) {
+        Ok(_) => return true,
+        // Only last character is incomplete
+        Err(err) if err.error_len().is_none() => return true,
+        Err(_) => (),
+    }
+    [buf, &buf[..buf.len() - 1]].iter().any(|buf| {
+        SHIFT_JIS
+            .decode_without_bom_handling_and_without_replacement(buf)
+            .is_some()
+    })
+}
+
+/// Convert CRLF and lone CR line endings to LF
+pub fn normalize_newlines(s: &str) -> String {
+    s.replace("\r\n", "\n").replace('\r', "\n")
+}
+
+/// Decode text to UTF-8 without BOM and with LF line endings
+pub fn normalize(buf: &[u8]) -> String {
+    normalize_newlines(&decode(buf))
+}
+
+/// Entry name or path shown in logs, manifests and error messages. Unlike
+/// `{:?}` it is printed without quotes and escapes, names that are not
+/// valid UTF-8 are decoded as SHIFT_JIS.
+#[derive(Debug, Clone, Copy)]
+pub struct DisplayName<'a>(&'a Path);
+
+pub fn display_name(path: &Path) -> DisplayName<'_> {
+    DisplayName(path)
+}
+
+impl fmt::Display for DisplayName<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.0.to_str() {
+            Some(name) => f.write_str(name),
+            None => f.write_str(&decode_os_name(self.0)),
+        }
+    }
+}
+
+#[cfg(unix)]
+fn decode_os_name(path: &Path) -> String {
+    use std::os::unix::ffi::OsStrExt;
+
+    decode(path.as_os_str().as_bytes())
+}
+
+#[cfg(not(unix))]
+fn decode_os_name(path: &Path) -> String {
+    path.to_string_lossy().into_owned()
+}
+
+/// Original bytes of name that is not valid UTF-8, with `%` and bytes
+/// outside printable ASCII written as `%XX`, so they survive UTF-8 files
+/// next to name decoded by [`display_name`]. None for UTF-8 names.
+pub fn escape_raw_name(path: &Path) -> Option<String> {
+    if path.to_str().is_some() {
+        return None;
+    }
+    let mut escaped = String::new();
+    for &b in os_name_bytes(path).iter() {
+        if (b.is_ascii_graphic() || b == b' ') && b != b'%' {
+            escaped.push(b as char);
+        } else {
+            escaped.push_str(&format!("%{:02X}", b));
+        }
+    }
+    Some(escaped)
+}
+
+/// Name with original bytes escaped by [`escape_raw_name`], None when
+/// escape is malformed
+pub fn unescape_raw_name(escaped: &str) -> Option<PathBuf> {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut rest = escaped.as_bytes();
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+    Some(path_from_bytes(bytes))
+}
+
+#[cfg(unix)]
+fn os_name_bytes(path: &Path) -> Vec<u8> {
+    use std::os::unix::ffi::OsStrExt;
+
+    path.as_os_str().as_bytes().to_vec()
+}
+
+#[cfg(not(unix))]
+fn os_name_bytes(path: &Path) -> Vec<u8> {
+    path.to_string_lossy().into_owned().into_bytes()
+}
+
+#[cfg(unix)]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt};
+
+    PathBuf::from(OsString::from_vec(bytes))
+}
+
+/// Paths are not raw bytes here, name is decoded like [`display_name`]
+#[cfg(not(unix))]
+fn path_from_bytes(bytes: Vec<u8>) -> PathBuf {
+    PathBuf::from(decode(&bytes))
+}
@@ -0,0 +1,82 @@
+use anyhow::Context;
+use positioned_io::{RandomAccessFile, ReadAt};
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+/// Highest volume number looked for after first volume
+const MAX_VOLUMES: usize = 99;
+
+/// Archive split into several files, read as one contiguous file.
+/// Volumes after the first one share its name with numbered extension
+/// `.b01`, `.b02` and so on.
+#[derive(Debug)]
+pub struct MultiVolumeFile {
+    volumes: Vec<Volume>,
+    size: u64,
+}
+
+#[derive(Debug)]
+struct Volume {
+    /// Offset of first byte of volume in joined file
+    start: u64,
+    size: u64,
+    file: RandomAccessFile,
+}
+
+impl MultiVolumeFile {
+    /// Open first volume and every volume following it
+    pub fn open(first_volume: &Path) -> anyhow::Result<Self> {
+        let mut volumes = Vec::new();
+        let mut size = 0;
+        for path in std::iter::once(first_volume.to_path_buf())
+            .chain(Self::volume_paths(first_volume)?)
+        {
+            let volume_size = std::fs::metadata(&path)?.len();
+            volumes.push(Volume {
+                start: size,
+                size: volume_size,
+                file: super::fs::open_archive_file(&path)?,
+            });
+            size += volume_size;
+        }
+        Ok(Self { volumes, size })
+    }
+    /// Existing volumes after first one, stops at first missing number
+    fn volume_paths(first_volume: &Path) -> anyhow::Result<Vec<PathBuf>> {
+        let stem = first_volume
+            .file_stem()
+            .context("Could not get file name")?
+            .to_string_lossy()
+            .to_string();
+        Ok((1..=MAX_VOLUMES)
+            .map(|i| first_volume.with_file_name(format!("{}.b{:02}", stem, i)))
+            .take_while(|path| path.is_file())
+            .collect())
+    }
+    /// Size of all volumes joined
+    pub fn size(&self) -> u64 {
+        self.size
+    }
+    pub fn volume_count(&self) -> usize {
+        self.volumes.len()
+    }
+}
+
+impl ReadAt for MultiVolumeFile {
+    /// Reads stop at end of volume, so read crossing volumes is split by
+    /// `read_exact_at` into read from each of them
+    fn read_at(&self, pos: u64, buf: &mut [u8]) -> io::Result<usize> {
+        match self.volumes.iter().find(|volume| {
+            pos >= volume.start && pos < volume.start + volume.size
+        }) {
+            Some(volume) => {
+                let len =
+                    buf.len().min((volume.start + volume.size - pos) as usize);
+                volume.file.read_at(pos - volume.start, &mut buf[..len])
+            }
+            None => Ok(0),
+        }
+    }
+}
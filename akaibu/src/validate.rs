@@ -0,0 +1,115 @@
+use crate::archive::{Archive, FileEntry};
+use std::{fmt, path::PathBuf};
+
+/// Entry whose stored data does not fit archive file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IndexProblem {
+    /// Data reaches past end of archive file
+    OutOfBounds {
+        path: PathBuf,
+        offset: u64,
+        size: u64,
+    },
+    /// Data shares bytes with data of another entry
+    Overlap {
+        first: PathBuf,
+        second: PathBuf,
+        offset: u64,
+        size: u64,
+    },
+}
+
+impl fmt::Display for IndexProblem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::OutOfBounds { path, offset, size } => write!(
+                f,
+                "{:?} at {:#X} with size {:#X} ends past end of archive",
+                path, offset, size
+            ),
+            Self::Overlap {
+                first,
+                second,
+                offset,
+                size,
+            } => write!(
+                f,
+                "{:?} and {:?} share {:#X} bytes at {:#X}",
+                first, second, size, offset
+            ),
+        }
+    }
+}
+
+/// Outcome of checking parsed index against archive file
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexReport {
+    pub archive_size: u64,
+    /// Entries whose stored range was checked
+    pub checked: usize,
+    /// Entries of schemes that do not expose where their data is stored
+    pub unchecked: usize,
+    pub problems: Vec<IndexProblem>,
+}
+
+impl IndexReport {
+    pub fn is_valid(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Check stored range of every entry against size of archive file and
+/// ranges of other entries. Entries pointing at the very same bytes are
+/// not reported, as some archives store identical files once.
+#[allow(clippy::borrowed_box)]
+pub fn validate_index(
+    archive: &Box<dyn Archive>,
+    entries: &[FileEntry],
+    archive_size: u64,
+) -> IndexReport {
+    let mut problems = Vec::new();
+    let mut ranges = Vec::new();
+    for entry in entries {
+        let (offset, size) = match archive.stored_range(entry) {
+            Some(range) => range,
+            None => continue,
+        };
+        match offset.checked_add(size) {
+            Some(end) if end <= archive_size => {
+                ranges.push((offset, end, &entry.full_path))
+            }
+            _ => problems.push(IndexProblem::OutOfBounds {
+                path: entry.full_path.clone(),
+                offset,
+                size,
+            }),
+        }
+    }
+    let checked = ranges.len() + problems.len();
+
+    ranges.sort();
+    // Entry reaching furthest so far, every overlap includes it
+    let mut furthest: Option<(u64, u64, &PathBuf)> = None;
+    for &(offset, end, path) in ranges.iter().filter(|(o, e, _)| o < e) {
+        if let Some((last_offset, last_end, last_path)) = furthest {
+            if offset < last_end && (offset, end) != (last_offset, last_end) {
+                problems.push(IndexProblem::Overlap {
+                    first: last_path.clone(),
+                    second: path.clone(),
+                    offset,
+                    size: end.min(last_end) - offset,
+                });
+            }
+            if end <= last_end {
+                continue;
+            }
+        }
+        furthest = Some((offset, end, path));
+    }
+    IndexReport {
+        archive_size,
+        checked,
+        unchecked: entries.len() - checked,
+        problems,
+    }
+}
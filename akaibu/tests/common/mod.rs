@@ -0,0 +1,169 @@
+// Every test binary uses only part of the helpers
+#![allow(dead_code)]
+
+use akaibu::{
+    archive::FileEntry,
+    magic,
+    resource::{ResourceMagic, ResourceType},
+    scheme::Scheme,
+};
+use image::RgbaImage;
+use std::{
+    collections::BTreeSet,
+    path::{Path, PathBuf},
+};
+
+/// Path of file inside tests/fixtures
+pub fn fixture(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(path)
+}
+
+/// Convert resource file with scheme picked by its magic
+pub fn convert(file_path: &Path) -> RgbaImage {
+    let sniffed = magic::sniff(file_path).expect("Could not read fixture");
+    let resource = ResourceMagic::parse_magic(&sniffed.head);
+    assert!(
+        resource.is_universal(),
+        "{:?} detected as non universal {:?}",
+        file_path,
+        resource
+    );
+    let scheme = resource.get_schemes().remove(0);
+    into_image(
+        scheme
+            .convert(file_path)
+            .expect("Could not convert fixture"),
+        file_path,
+    )
+}
+
+/// Compare image with golden PNG pixel by pixel
+pub fn assert_image_eq(image: &RgbaImage, golden_path: &Path) {
+    let golden = image::open(golden_path)
+        .unwrap_or_else(|err| panic!("{:?}: {}", golden_path, err))
+        .to_rgba8();
+    assert_eq!(
+        image.dimensions(),
+        golden.dimensions(),
+        "Resolution differs from {:?}",
+        golden_path
+    );
+    if let Some((x, y, pixel)) = image
+        .enumerate_pixels()
+        .find(|(x, y, pixel)| golden.get_pixel(*x, *y) != *pixel)
+    {
+        panic!(
+            "Pixel {}x{} is {:?}, expected {:?} from {:?}",
+            x,
+            y,
+            pixel,
+            golden.get_pixel(x, y),
+            golden_path
+        );
+    }
+}
+
+/// Extract archive with detected universal scheme and compare every entry
+/// with expected directory. Entries with known resource type are converted
+/// and compared with `<entry path>.png`, all other entries byte by byte.
+pub fn assert_archive_matches(archive_path: &Path, expected_dir: &Path) {
+    let sniffed = magic::sniff(archive_path).expect("Could not read fixture");
+    let archive_magic = magic::Archive::detect(archive_path, &sniffed);
+    assert!(
+        archive_magic.is_universal(),
+        "{:?} detected as non universal {:?}",
+        archive_path,
+        archive_magic
+    );
+    assert_archive_matches_with(
+        archive_magic.get_schemes()[0].as_ref(),
+        archive_path,
+        expected_dir,
+    );
+}
+
+/// Same as [`assert_archive_matches`] with given scheme, for formats that
+/// can not be detected or are game specific
+pub fn assert_archive_matches_with(
+    scheme: &dyn Scheme,
+    archive_path: &Path,
+    expected_dir: &Path,
+) {
+    let (archive, dir) = scheme
+        .extract(archive_path)
+        .expect("Could not open fixture archive");
+    let entries = dir
+        .get_root_dir()
+        .get_all_files()
+        .cloned()
+        .collect::<Vec<FileEntry>>();
+
+    let mut checked = BTreeSet::new();
+    for entry in &entries {
+        let contents = archive
+            .extract(entry)
+            .unwrap_or_else(|err| panic!("{:?}: {}", entry.full_path, err));
+        let resource = contents.get_resource_type();
+        let schemes = resource.get_schemes();
+        if resource.is_universal() && !schemes.is_empty() {
+            let mut golden_path = expected_dir.join(&entry.full_path);
+            golden_path.set_file_name(format!("{}.png", entry.file_name));
+            let image = into_image(
+                schemes[0]
+                    .convert_from_bytes(
+                        &entry.full_path,
+                        contents.contents.to_vec(),
+                        Some(&archive),
+                    )
+                    .unwrap_or_else(|err| {
+                        panic!("{:?}: {}", entry.full_path, err)
+                    }),
+                &entry.full_path,
+            );
+            assert_image_eq(&image, &golden_path);
+            checked.insert(golden_path);
+        } else {
+            let expected_path = expected_dir.join(&entry.full_path);
+            let expected = std::fs::read(&expected_path)
+                .unwrap_or_else(|err| panic!("{:?}: {}", expected_path, err));
+            assert!(
+                contents.contents[..] == expected[..],
+                "Entry {:?} differs from {:?}",
+                entry.full_path,
+                expected_path
+            );
+            checked.insert(expected_path);
+        }
+    }
+    assert_eq!(
+        checked,
+        list_files(expected_dir),
+        "Archive entries do not match expected files"
+    );
+}
+
+fn into_image(resource: ResourceType, file_path: &Path) -> RgbaImage {
+    match resource {
+        ResourceType::RgbaImage { image } => image,
+        ResourceType::SpriteSheet { mut sprites } if sprites.len() == 1 => {
+            sprites.remove(0)
+        }
+        _ => panic!("{:?} was not converted to single image", file_path),
+    }
+}
+
+fn list_files(dir: &Path) -> BTreeSet<PathBuf> {
+    std::fs::read_dir(dir)
+        .unwrap_or_else(|err| panic!("{:?}: {}", dir, err))
+        .map(|entry| entry.expect("Could not read directory").path())
+        .flat_map(|path| {
+            if path.is_dir() {
+                list_files(&path)
+            } else {
+                std::iter::once(path).collect()
+            }
+        })
+        .collect()
+}
@@ -0,0 +1,74 @@
+use akaibu::{scheme::registry::SchemeRegistry, Resources};
+
+#[test]
+fn key_tables_deserialize() {
+    let errors = SchemeRegistry::new(None).validate();
+    assert!(errors.is_empty(), "Invalid key tables: {:#?}", errors);
+}
+
+#[test]
+fn json_resources_are_valid() {
+    Resources::iter()
+        .filter(|path| path.ends_with(".json"))
+        .for_each(|path| {
+            let file = Resources::get(&path).expect("Listed resource missing");
+            if let Err(err) = serde_json::from_slice::<serde_json::Value>(&file)
+            {
+                panic!("{}: {}", path, err);
+            }
+        });
+}
+
+#[test]
+fn user_key_tables_are_reloaded() {
+    let config_dir = std::env::temp_dir()
+        .join(format!("akaibu_keys_{}", std::process::id()));
+    let table_path = config_dir.join("malie").join("keys.json");
+    std::fs::create_dir_all(table_path.parent().unwrap()).unwrap();
+    let registry = SchemeRegistry::new(Some(config_dir.clone()));
+    assert_eq!(registry.reload().unwrap(), 0);
+
+    std::fs::write(&table_path, br#"{"NewTitle": [1, 2, 3, 4]}"#).unwrap();
+    assert_eq!(registry.reload().unwrap(), 1);
+
+    std::fs::write(&table_path, b"{").unwrap();
+    let result = registry.reload();
+    std::fs::remove_dir_all(&config_dir).unwrap();
+    assert!(result.is_err(), "Broken key table was accepted");
+}
+
+#[test]
+#[cfg(feature = "fmt-malie")]
+fn user_key_table_of_wrong_type_fails_validation() {
+    use akaibu::error::AkaibuError;
+
+    let config_dir = std::env::temp_dir()
+        .join(format!("akaibu_keys_wrong_type_{}", std::process::id()));
+    let registry = SchemeRegistry::new(Some(config_dir.clone()));
+    assert!(registry.validate().is_empty());
+
+    // Parses as JSON, but keys of malie are bytes
+    let table_path = config_dir.join("malie").join("keys.json");
+    std::fs::create_dir_all(table_path.parent().unwrap()).unwrap();
+    std::fs::write(&table_path, br#"{"NewTitle": "key"}"#).unwrap();
+    let reloaded = registry.reload();
+    let validated = registry.validate();
+    std::fs::remove_dir_all(&config_dir).unwrap();
+
+    assert!(reloaded.is_err(), "Key table of wrong type was reloaded");
+    // Only broken table is reported, other schemes keep their tables
+    assert!(matches!(
+        validated.as_slice(),
+        [AkaibuError::EmbeddedResource { path, .. }] if path == "malie/keys.json"
+    ));
+}
+
+#[test]
+fn build_info_hashes_embedded_resources() {
+    let info = akaibu::build_info();
+    assert_eq!(info.embedded.len(), Resources::iter().count());
+    assert!(info
+        .embedded
+        .iter()
+        .all(|file| file.sha1.len() == 40 && !file.path.is_empty()));
+}
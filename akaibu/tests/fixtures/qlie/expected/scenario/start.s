@@ -0,0 +1 @@
+^bg,file:title
@@ -0,0 +1,729 @@
+mod common;
+
+use common::{assert_image_eq, convert, fixture};
+
+#[test]
+#[cfg(feature = "fmt-akb")]
+fn akb_universal() {
+    assert_image_eq(
+        &convert(&fixture("akb/input.akb")),
+        &fixture("akb/expected.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-akb")]
+fn akb_fill_only() {
+    assert_image_eq(
+        &convert(&fixture("akb/fill.akb")),
+        &fixture("akb/fill.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-akb")]
+fn akb_external_palette() {
+    assert_image_eq(
+        &convert(&fixture("akb/indexed.akb")),
+        &fixture("akb/indexed.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-akb")]
+fn akb_inter_frame() {
+    assert_image_eq(
+        &convert(&fixture("akb/frame.akb")),
+        &fixture("akb/frame.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-akb")]
+fn akb_written_as_bmp() {
+    use akaibu::resource::{ImageFormat, ResourceType};
+
+    let output = std::env::temp_dir()
+        .join(format!("akaibu_bmp_{}.akb", std::process::id()));
+    ResourceType::RgbaImage {
+        image: convert(&fixture("akb/input.akb")),
+    }
+    .write_resource_as(&output, ImageFormat::Bmp)
+    .unwrap();
+    let written = std::fs::read(output.with_extension("bmp")).unwrap();
+    std::fs::remove_file(output.with_extension("bmp")).unwrap();
+    assert_eq!(written, std::fs::read(fixture("akb/expected.bmp")).unwrap());
+}
+
+#[test]
+fn sprite_sheet_paths_in_sprite_order() {
+    use akaibu::resource::ResourceType;
+    use image::{Rgba, RgbaImage};
+
+    let output = std::env::temp_dir()
+        .join(format!("akaibu_sprites_{}", std::process::id()));
+    std::fs::create_dir_all(&output).unwrap();
+    let sprites = (0..8u8)
+        .map(|i| RgbaImage::from_pixel(1 + i as u32, 2, Rgba([i, 0, 0, 255])))
+        .collect::<Vec<RgbaImage>>();
+    let written = ResourceType::SpriteSheet {
+        sprites: sprites.clone(),
+    }
+    .write_resource(&output.join("sheet.pna"))
+    .unwrap();
+    let read = written
+        .iter()
+        .map(|path| image::open(path).unwrap().to_rgba8())
+        .collect::<Vec<RgbaImage>>();
+    std::fs::remove_dir_all(&output).unwrap();
+
+    assert_eq!(
+        written,
+        (0..8)
+            .map(|i| output.join(format!("sheet_{}.png", i)))
+            .collect::<Vec<_>>()
+    );
+    assert_eq!(read, sprites);
+}
+
+#[test]
+#[cfg(feature = "fmt-compressedbg")]
+fn compressedbg_universal() {
+    assert_image_eq(
+        &convert(&fixture("compressedbg/input.bgi")),
+        &fixture("compressedbg/expected.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-compressedbg")]
+fn compressedbg_cancellable() {
+    use akaibu::{
+        cancel::{self, CancellationToken},
+        error, pipeline,
+        resource::ResourceMagic,
+    };
+
+    let convert_with = |token: &CancellationToken| {
+        cancel::run_cancellable(token, || {
+            pipeline::convert_file(
+                ResourceMagic::CompressedBg.get_schemes()[0].as_ref(),
+                &fixture("compressedbg/input.bgi"),
+            )
+        })
+    };
+    assert!(convert_with(&CancellationToken::new()).is_ok());
+
+    let token = CancellationToken::new();
+    token.cancel();
+    let err = convert_with(&token).expect_err("Cancelled conversion ran");
+    assert!(error::is_cancelled(&err), "Unexpected error: {:?}", err);
+}
+
+#[test]
+#[cfg(feature = "fmt-tlg")]
+fn tlg6_universal() {
+    assert_image_eq(
+        &convert(&fixture("tlg/input.tlg")),
+        &fixture("tlg/expected.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-tlg")]
+fn tlg5_channels() {
+    for name in &["tlg5_gray", "tlg5_rgb", "tlg5_rgba"] {
+        assert_image_eq(
+            &convert(&fixture(&format!("tlg/{}.tlg", name))),
+            &fixture(&format!("tlg/{}.png", name)),
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-tlg")]
+fn tlg5_two_channels_unsupported() {
+    use akaibu::{error::AkaibuError, resource::ResourceMagic};
+
+    let err = ResourceMagic::Tlg
+        .get_schemes()
+        .remove(0)
+        .convert(&fixture("tlg/tlg5_two_channels.tlg"))
+        .expect_err("Two channel TLG5 was converted");
+    assert!(
+        matches!(
+            err.downcast_ref::<AkaibuError>(),
+            Some(AkaibuError::Unimplemented(_))
+        ),
+        "Unexpected error: {:?}",
+        err
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-mcg")]
+fn mcg_universal() {
+    assert_image_eq(
+        &convert(&fixture("mcg/input.mcg")),
+        &fixture("mcg/expected.png"),
+    );
+    assert_image_eq(&convert(&fixture("mcg/rgb.mcg")), &fixture("mcg/rgb.png"));
+}
+
+#[test]
+#[cfg(feature = "fmt-pb3b")]
+fn pb3_jbp1_universal() {
+    assert_image_eq(
+        &convert(&fixture("pb3/input.pb3")),
+        &fixture("pb3/expected.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-pb3b")]
+fn pb3_jbp1_malformed() {
+    use akaibu::resource::ResourceMagic;
+
+    assert_rejected(ResourceMagic::Pb3b, "malformed/pb3_block_size.pb3", false);
+}
+
+#[test]
+#[cfg(feature = "fmt-pb2")]
+fn pb2_universal() {
+    assert_image_eq(
+        &convert(&fixture("pb2/input.pb2")),
+        &fixture("pb2/expected.png"),
+    );
+    assert_image_eq(&convert(&fixture("pb2/rgb.pb2")), &fixture("pb2/rgb.png"));
+}
+
+#[test]
+#[cfg(feature = "fmt-ykg")]
+fn ykg_universal() {
+    assert_image_eq(
+        &convert(&fixture("ykg/input.ykg")),
+        &fixture("ykg/expected.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-tlg")]
+fn tlg_unknown_version_reported() {
+    use akaibu::{error::AkaibuError, resource::ResourceMagic};
+    use std::path::Path;
+
+    let err = ResourceMagic::Tlg
+        .get_schemes()
+        .remove(0)
+        .convert_from_bytes(
+            Path::new("unknown.tlg"),
+            b"TLG7.0\x00raw\x1a".to_vec(),
+            None,
+        )
+        .expect_err("Unknown TLG version was converted");
+    match err.downcast_ref::<AkaibuError>() {
+        Some(AkaibuError::UnsupportedVersion {
+            scheme,
+            found,
+            expected,
+        }) => {
+            assert_eq!(scheme, "TLG");
+            assert_eq!(found, "7");
+            assert_eq!(expected, "0, 5, 6");
+        }
+        _ => panic!("Unexpected error: {:?}", err),
+    }
+    assert_eq!(
+        akaibu::error::describe(&err.context("Could not convert")),
+        "Could not convert: TLG version 7 found; supported: 0, 5, 6"
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-wip")]
+fn wip_planar_truecolor() {
+    assert_image_eq(
+        &convert(&fixture("wip/input.wip")),
+        &fixture("wip/expected.png"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-wip")]
+fn wip_paletted_frames() {
+    use akaibu::resource::{ResourceMagic, ResourceType};
+
+    let file_path = fixture("wip/frames.wip");
+    let scheme = ResourceMagic::Wip.get_schemes().remove(0);
+    match scheme
+        .convert(&file_path)
+        .expect("Could not convert fixture")
+    {
+        ResourceType::SpriteSheet { sprites } => {
+            assert_eq!(sprites.len(), 2);
+            for (i, sprite) in sprites.iter().enumerate() {
+                assert_image_eq(
+                    sprite,
+                    &fixture(&format!("wip/frames_{}.png", i)),
+                );
+            }
+        }
+        _ => panic!("{:?} was not converted to frames", file_path),
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-escr")]
+fn escr_strings_in_index_order() {
+    use akaibu::{
+        error::AkaibuError,
+        resource::{ResourceMagic, ResourceType},
+    };
+    use std::path::Path;
+
+    let script = |offsets: &[u32], strings: &[u8]| {
+        let mut buf = b"ESCR1_00".to_vec();
+        buf.extend_from_slice(&(offsets.len() as u32).to_le_bytes());
+        for offset in offsets {
+            buf.extend_from_slice(&offset.to_le_bytes());
+        }
+        let code = [0x01, 0x00, 0x00, 0x00, 0x02, 0x00, 0x00, 0x00];
+        buf.extend_from_slice(&(code.len() as u32).to_le_bytes());
+        buf.extend_from_slice(&code);
+        buf.extend_from_slice(&(strings.len() as u32).to_le_bytes());
+        buf.extend_from_slice(strings);
+        buf
+    };
+    let convert = |buf: Vec<u8>| {
+        let magic = ResourceMagic::parse_magic(&buf);
+        assert!(matches!(magic, ResourceMagic::Escr));
+        magic.get_schemes().remove(0).convert_from_bytes(
+            Path::new("script.bin"),
+            buf,
+            None,
+        )
+    };
+    // "あ" and "い" in SHIFT-JIS, second line break is inside string
+    let strings = b"\x82\xa0\0line\r\nbreak\0\x82\xa2\0";
+    match convert(script(&[15, 0, 3], strings)).unwrap() {
+        ResourceType::Text(text) => {
+            assert_eq!(text, "い\nあ\nline\\nbreak")
+        }
+        _ => panic!("ESCR script was not converted to text"),
+    }
+
+    let err = convert(script(&[0, 64], strings)).unwrap_err();
+    assert!(matches!(
+        err.downcast_ref::<AkaibuError>(),
+        Some(AkaibuError::MalformedData(_))
+    ));
+}
+
+#[test]
+#[cfg(feature = "fmt-csx")]
+fn csx_const_strings_in_table_order() {
+    use akaibu::resource::{ResourceMagic, ResourceType};
+    use std::path::Path;
+
+    let section = |id: &[u8; 8], data: &[u8]| {
+        let mut buf = id.to_vec();
+        buf.extend_from_slice(&(data.len() as u64).to_le_bytes());
+        buf.extend_from_slice(data);
+        buf
+    };
+    let script = |sections: &[&[u8]]| {
+        let mut buf = b"Entis\x1a\x00\x00".to_vec();
+        buf.resize(0x10, 0);
+        buf.extend_from_slice(b"Cotopha Image file");
+        buf.resize(0x40, 0);
+        for section in sections {
+            buf.extend_from_slice(section);
+        }
+        buf
+    };
+    let convert = |buf: Vec<u8>| {
+        let magic = ResourceMagic::parse_magic(&buf);
+        assert!(matches!(magic, ResourceMagic::Csx));
+        magic.get_schemes().remove(0).convert_from_bytes(
+            Path::new("script.csx"),
+            buf,
+            None,
+        )
+    };
+    let mut strings = 3u32.to_le_bytes().to_vec();
+    for (string, references) in &[("こんにちは", 2u32), ("", 0), ("end", 1)]
+    {
+        let units = string.encode_utf16().collect::<Vec<u16>>();
+        strings.extend_from_slice(&(units.len() as u32).to_le_bytes());
+        units
+            .iter()
+            .for_each(|unit| strings.extend_from_slice(&unit.to_le_bytes()));
+        strings.extend_from_slice(&references.to_le_bytes());
+        (0..*references).for_each(|reference| {
+            strings.extend_from_slice(&(reference * 0x10).to_le_bytes())
+        });
+    }
+    // Code section before string table must be skipped
+    let code = section(b"image   ", &[0xAA; 12]);
+    match convert(script(&[&code, &section(b"conststr", &strings)])).unwrap() {
+        ResourceType::Text(text) => assert_eq!(text, "こんにちは\n\nend"),
+        _ => panic!("CSX script was not converted to text"),
+    }
+
+    assert!(convert(script(&[&code])).is_err());
+    let mut truncated = section(b"conststr", &strings);
+    truncated.truncate(truncated.len() - 3);
+    assert!(convert(script(&[&code, &truncated])).is_err());
+}
+
+/// Converting damaged file must fail with error instead of panicking
+#[cfg(any(feature = "fmt-gyu", feature = "fmt-iar"))]
+fn assert_rejected(
+    resource: akaibu::resource::ResourceMagic,
+    path: &str,
+    too_large: bool,
+) {
+    use akaibu::error::AkaibuError;
+
+    let err = resource
+        .get_schemes()
+        .remove(0)
+        .convert(&fixture(path))
+        .expect_err("Malformed fixture was converted");
+    match err.downcast_ref::<AkaibuError>() {
+        Some(AkaibuError::OutputTooLarge { .. }) if too_large => (),
+        Some(AkaibuError::MalformedData(_)) if !too_large => (),
+        _ => panic!("{} failed with unexpected error: {:?}", path, err),
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-gyu")]
+fn gyu_malformed() {
+    use akaibu::resource::ResourceMagic;
+
+    assert_rejected(
+        ResourceMagic::GyuUniversal,
+        "malformed/gyu_huge.gyu",
+        true,
+    );
+    for path in &["malformed/gyu_truncated.gyu", "malformed/gyu_overflow.gyu"] {
+        assert_rejected(ResourceMagic::GyuUniversal, path, false);
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-iar")]
+fn iar_layers() {
+    use akaibu::resource::{ResourceMagic, ResourceType};
+
+    // Layer 2 is based on layer 1, which is based on image 0
+    for layer in &["1", "2"] {
+        let resource = ResourceMagic::Iar.get_schemes()[0]
+            .convert(&fixture(&format!("iar/{}", layer)))
+            .expect("Could not convert layer");
+        match resource {
+            ResourceType::RgbaImage { image } => {
+                assert_image_eq(&image, &fixture(&format!("iar/{}.png", layer)))
+            }
+            _ => panic!("Unexpected resource type"),
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-iar")]
+fn iar_malformed() {
+    use akaibu::resource::ResourceMagic;
+
+    assert_rejected(ResourceMagic::Iar, "malformed/iar_huge.iar", true);
+    assert_rejected(ResourceMagic::Iar, "malformed/iar_truncated.iar", false);
+}
+
+/// Ogg page with zeroed checksum, which is not verified when reading
+fn ogg_page(header_type: u8, sequence: u32, packets: &[&[u8]]) -> Vec<u8> {
+    let mut page = b"OggS\0".to_vec();
+    page.push(header_type);
+    page.extend_from_slice(&[0; 8]);
+    page.extend_from_slice(&7u32.to_le_bytes());
+    page.extend_from_slice(&sequence.to_le_bytes());
+    page.extend_from_slice(&[0; 4]);
+    page.push(packets.len() as u8);
+    page.extend(packets.iter().map(|packet| packet.len() as u8));
+    packets
+        .iter()
+        .for_each(|packet| page.extend_from_slice(packet));
+    page
+}
+
+#[test]
+fn sli_loop_embedded_into_ogg() {
+    use akaibu::util::audio::{parse_sli, set_ogg_loop, LoopPoints};
+
+    let sli = "#2.00\n# Sound Loop Information (utf-16)\n\
+               Label { Position=0000000000000000000; Name=start; }\n\
+               Link { From=0000000000000001000; To=0000000000000000441; \
+               Smooth=False; Condition=no; RefValue=0; CondVar=0; }\n";
+    let loop_points = parse_sli(sli).unwrap();
+    assert_eq!(
+        loop_points,
+        LoopPoints {
+            start: 441,
+            end: 1000
+        }
+    );
+    assert_eq!(
+        parse_sli("LoopLength=1000 LoopStart=200").unwrap(),
+        LoopPoints {
+            start: 200,
+            end: 1200
+        }
+    );
+
+    let mut comment = b"\x03vorbis".to_vec();
+    comment.extend_from_slice(&4u32.to_le_bytes());
+    comment.extend_from_slice(b"test");
+    comment.extend_from_slice(&1u32.to_le_bytes());
+    comment.extend_from_slice(&11u32.to_le_bytes());
+    comment.extend_from_slice(b"LOOPSTART=5");
+    comment.push(1);
+    let mut ogg = ogg_page(2, 0, &[&[1; 30]]);
+    ogg.extend(ogg_page(0, 1, &[&comment, b"\x05vorbis setup"]));
+    ogg.extend(ogg_page(4, 2, &[b"abc"]));
+
+    let looped = set_ogg_loop(&ogg, loop_points).unwrap();
+    let count = |needle: &[u8]| {
+        looped
+            .windows(needle.len())
+            .filter(|window| *window == needle)
+            .count()
+    };
+    assert_eq!(count(b"LOOPSTART="), 1);
+    assert_eq!(count(b"LOOPSTART=441"), 1);
+    assert_eq!(count(b"LOOPLENGTH=559"), 1);
+    assert_eq!(count(b"\x05vorbis setup"), 1);
+    assert!(looped.ends_with(b"abc"));
+    // Setting same loop again gives same file
+    assert_eq!(set_ogg_loop(&looped, loop_points).unwrap(), looped);
+}
+
+#[test]
+fn loops_stripped_from_ogg_and_wav() {
+    use akaibu::util::audio::{
+        set_ogg_loop, set_wav_loop, strip_ogg_loop, strip_wav_loop, LoopPoints,
+    };
+
+    let loop_points = LoopPoints {
+        start: 441,
+        end: 1000,
+    };
+    let contains = |buf: &[u8], needle: &[u8]| {
+        buf.windows(needle.len()).any(|window| window == needle)
+    };
+
+    let mut comment = b"\x03vorbis".to_vec();
+    comment.extend_from_slice(&4u32.to_le_bytes());
+    comment.extend_from_slice(b"test");
+    comment.extend_from_slice(&2u32.to_le_bytes());
+    comment.extend_from_slice(&9u32.to_le_bytes());
+    comment.extend_from_slice(b"LOOPEND=5");
+    comment.extend_from_slice(&10u32.to_le_bytes());
+    comment.extend_from_slice(b"TITLE=song");
+    comment.push(1);
+    let mut ogg = ogg_page(2, 0, &[&[1; 30]]);
+    ogg.extend(ogg_page(0, 1, &[&comment, b"\x05vorbis setup"]));
+    ogg.extend(ogg_page(4, 2, &[b"abc"]));
+
+    let stripped =
+        strip_ogg_loop(&set_ogg_loop(&ogg, loop_points).unwrap()).unwrap();
+    assert!(!contains(&stripped, b"LOOP"));
+    assert!(contains(&stripped, b"TITLE=song"));
+    assert!(stripped.ends_with(b"abc"));
+
+    let mut wav = b"RIFF\0\0\0\0WAVEfmt ".to_vec();
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&[1, 0, 1, 0]);
+    wav.extend_from_slice(&44100u32.to_le_bytes());
+    wav.extend_from_slice(&[0; 8]);
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&3u32.to_le_bytes());
+    wav.extend_from_slice(&[1, 2, 3, 0]);
+    let riff_size = (wav.len() - 8) as u32;
+    wav[4..8].copy_from_slice(&riff_size.to_le_bytes());
+
+    let looped = set_wav_loop(&wav, 44100, loop_points).unwrap();
+    assert!(contains(&looped, b"smpl"));
+    assert_eq!(strip_wav_loop(&looped).unwrap(), wav);
+}
+
+#[test]
+fn script_control_codes_survive_reencoding() {
+    use akaibu::util::script::{reencode, tokenize, ScriptEngine, Token};
+    use encoding_rs::SHIFT_JIS;
+
+    let sjis = |s: &str| SHIFT_JIS.encode(s).0.into_owned();
+    // Second byte of 表 is backslash, which must not start Silky code
+    let silky = sjis("表n\\w30[voice 12]\r\n");
+    assert_eq!(
+        tokenize(ScriptEngine::Silky.tokenizer(), &silky),
+        [
+            Token::Text(&sjis("表n")),
+            Token::Code(b"\\w30"),
+            Token::Code(b"[voice 12]"),
+            Token::Text(b"\r\n"),
+        ]
+    );
+    assert_eq!(
+        reencode(ScriptEngine::Silky.tokenizer(), &silky).unwrap(),
+        "表n\\w30[voice 12]\n"
+    );
+
+    let buriko = sjis("%Nこれは<r漢字|かんじ>です 1 < 2");
+    assert_eq!(
+        tokenize(ScriptEngine::Buriko.tokenizer(), &buriko),
+        [
+            Token::Code(b"%N"),
+            Token::Text(&sjis("これは")),
+            Token::Code(&sjis("<r漢字|かんじ>")),
+            Token::Text(&sjis("です 1 < 2")),
+        ]
+    );
+    assert_eq!(
+        reencode(ScriptEngine::Buriko.tokenizer(), &buriko).unwrap(),
+        "%Nこれは<r漢字|かんじ>です 1 < 2"
+    );
+    // Code with byte not in SHIFT-JIS fails instead of being replaced
+    assert!(
+        reencode(ScriptEngine::Buriko.tokenizer(), b"<r\x85\x40|a>").is_err()
+    );
+}
+
+#[test]
+fn downscaled_images_fit_max_dimension() {
+    use akaibu::resource::ResourceType;
+    use image::RgbaImage;
+
+    let resource = ResourceType::SpriteSheet {
+        sprites: vec![RgbaImage::new(400, 100), RgbaImage::new(30, 60)],
+    }
+    .downscaled(200);
+    let dimensions = match &resource {
+        ResourceType::SpriteSheet { sprites } => sprites
+            .iter()
+            .map(|sprite| sprite.dimensions())
+            .collect::<Vec<(u32, u32)>>(),
+        _ => panic!("Expected sprite sheet"),
+    };
+    assert_eq!(dimensions, vec![(200, 50), (30, 60)]);
+    let image = ResourceType::RgbaImage {
+        image: RgbaImage::new(1, 1000),
+    };
+    assert_eq!(image.downscaled(10).dimensions(), Some((1, 10)));
+}
+
+#[test]
+#[cfg(feature = "fmt-tlg")]
+fn tlg5_encoded_image_round_trips() {
+    use akaibu::resource::{
+        tlg::{TlgScheme, TlgVersion},
+        ResourceScheme, ResourceType,
+    };
+    use image::{Rgba, RgbaImage};
+    use std::path::Path;
+
+    // Large enough for several blocks and LZSS matches, odd height leaves
+    // last block short
+    let opaque = RgbaImage::from_fn(37, 23, |x, y| {
+        Rgba([(x * 7) as u8, (y * 11) as u8, ((x ^ y) * 3) as u8, 0xFF])
+    });
+    let translucent = RgbaImage::from_fn(16, 9, |x, y| {
+        Rgba([(x * 16) as u8, 0x40, (y * 28) as u8, (x * y) as u8])
+    });
+    for image in [opaque, translucent].iter() {
+        let encoded = TlgScheme::encode(image, TlgVersion::Tlg5).unwrap();
+        assert!(encoded.starts_with(b"TLG5.0\x00raw\x1a"));
+        let decoded = TlgScheme::get_schemes()[0]
+            .convert_from_bytes(Path::new("image.tlg"), encoded, None)
+            .unwrap();
+        match decoded {
+            ResourceType::RgbaImage { image: decoded } => {
+                assert_eq!(&decoded, image)
+            }
+            _ => panic!("Expected image"),
+        }
+    }
+}
+
+#[test]
+fn converted_png_stamped_with_metadata() {
+    use akaibu::resource::{ImageFormat, ImageMetadata, ResourceType};
+    use image::{Rgba, RgbaImage};
+
+    let output = std::env::temp_dir()
+        .join(format!("akaibu_stamped_{}.tlg", std::process::id()));
+    let image = RgbaImage::from_pixel(3, 2, Rgba([1, 2, 3, 4]));
+    let written = ResourceType::RgbaImage {
+        image: image.clone(),
+    }
+    .write_resource_stamped(
+        &output,
+        ImageFormat::Png,
+        Some(&ImageMetadata {
+            archive: "data.xp3".to_string(),
+            entry: "bg/背景.tlg".to_string(),
+        }),
+    )
+    .unwrap();
+    let png = std::fs::read(&written[0]).unwrap();
+    let read = image::load_from_memory(&png).unwrap().to_rgba8();
+    std::fs::remove_file(&written[0]).unwrap();
+    let contains = |needle: &[u8]| {
+        png.windows(needle.len()).any(|window| window == needle)
+    };
+    assert!(contains(b"tEXtArchive\0data.xp3"));
+    assert!(contains("iTXtEntry\0\0\0\0\0bg/背景.tlg".as_bytes()));
+    assert!(contains(b"tEXtSoftware\0akaibu "));
+    assert_eq!(read, image);
+}
+
+#[test]
+fn bom_confirmed_before_text_detected() {
+    use akaibu::resource::ResourceMagic;
+
+    let utf16le = |s: &str| {
+        let mut buf = vec![0xFF, 0xFE];
+        s.encode_utf16()
+            .for_each(|unit| buf.extend_from_slice(&unit.to_le_bytes()));
+        buf
+    };
+    let text = utf16le("テキスト\r\n");
+    assert!(matches!(
+        ResourceMagic::parse_magic(&text),
+        ResourceMagic::Text
+    ));
+    // Prefix cut inside surrogate pair of last character
+    let mut cut = utf16le("text 🎵");
+    cut.truncate(cut.len() - 2);
+    assert!(matches!(
+        ResourceMagic::parse_magic(&cut),
+        ResourceMagic::Text
+    ));
+    assert!(matches!(
+        ResourceMagic::parse_magic(b"\xEF\xBB\xBFtext"),
+        ResourceMagic::Text
+    ));
+
+    // Binary data starting with BOM bytes
+    assert!(!matches!(
+        ResourceMagic::parse_magic(&[0xFF, 0xFE, 0x01, 0x00, 0x02, 0x00]),
+        ResourceMagic::Text
+    ));
+    assert!(!matches!(
+        ResourceMagic::parse_magic(&[0xFE, 0xFF, 0xDC, 0x00, 0x00, 0x41]),
+        ResourceMagic::Text
+    ));
+    assert!(!matches!(
+        ResourceMagic::parse_magic(b"\xEF\xBB\xBF\xC0\x80binary"),
+        ResourceMagic::Text
+    ));
+}
@@ -0,0 +1,2180 @@
+mod common;
+
+use akaibu::{
+    magic,
+    pipeline::{ArchiveHandle, Converted, ConvertedEntry, ExtractOptions},
+    resource::ResourceType,
+};
+use common::{assert_archive_matches, assert_archive_matches_with, fixture};
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-akb"))]
+fn pf8_universal() {
+    assert_archive_matches(&fixture("pf8/input.pf8"), &fixture("pf8/expected"));
+}
+
+#[test]
+#[cfg(all(feature = "fmt-gxp", feature = "fmt-compressedbg"))]
+fn gxp_universal() {
+    assert_archive_matches(&fixture("gxp/input.gxp"), &fixture("gxp/expected"));
+}
+
+#[test]
+#[cfg(all(feature = "fmt-gxp", feature = "fmt-compressedbg"))]
+fn gxp_wide_entries() {
+    assert_archive_matches(&fixture("gxp/wide.gxp"), &fixture("gxp/expected"));
+}
+
+#[test]
+#[cfg(all(feature = "fmt-buriko", feature = "fmt-compressedbg"))]
+fn buriko_packfile_dsc() {
+    assert_archive_matches(
+        &fixture("buriko/input.arc"),
+        &fixture("buriko/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-nekopack")]
+fn nekopack_v1_scrambled_names() {
+    assert_archive_matches(
+        &fixture("nekopack/input.dat"),
+        &fixture("nekopack/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-nekopack")]
+fn nekopack_v1_wrong_name_key() {
+    let scheme = magic::Archive::Nekopack.get_schemes().remove(0);
+    assert!(scheme.extract(&fixture("nekopack/wrong_key.dat")).is_err());
+}
+
+#[test]
+#[cfg(feature = "fmt-otomate-bin")]
+fn otomate_bin_split_volumes() {
+    assert_archive_matches(
+        &fixture("otomate_bin/input.bin"),
+        &fixture("otomate_bin/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-otomate-bin")]
+fn otomate_bin_stored_size_spans_volumes() {
+    let path = fixture("otomate_bin/input.bin");
+    let (archive, _) = magic::Archive::OtomateBin.get_schemes()[0]
+        .extract(&path)
+        .expect("Could not open fixture archive");
+    let volumes_size = ["input.bin", "input.b01"]
+        .iter()
+        .map(|name| {
+            std::fs::metadata(fixture(&format!("otomate_bin/{}", name)))
+                .unwrap()
+                .len()
+        })
+        .sum::<u64>();
+    assert_eq!(archive.stored_size(&path).unwrap(), Some(volumes_size));
+}
+
+#[test]
+#[cfg(feature = "fmt-otomate-bin")]
+fn otomate_bin_detected_by_sector_table() {
+    let path = fixture("otomate_bin/input.bin");
+    let sniffed = magic::sniff(&path).unwrap();
+    assert!(matches!(
+        magic::Archive::detect(&path, &sniffed),
+        magic::Archive::OtomateBin
+    ));
+
+    // Second entry starts before end of first one
+    let mut unordered = vec![0; 4096];
+    unordered[..20].copy_from_slice(&[
+        2, 0, 0, 0, 1, 0, 0, 0, 0, 4, 0, 0, 0, 0, 0, 0, 16, 0, 0, 0,
+    ]);
+    for (name, contents) in [
+        ("text", b"Not an Otomate container\r\n".to_vec()),
+        ("unordered", unordered),
+    ]
+    .iter()
+    {
+        let path = std::env::temp_dir().join(format!(
+            "akaibu_not_otomate_{}_{}.bin",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let sniffed = magic::sniff(&path).unwrap();
+        let detected = magic::Archive::detect(&path, &sniffed);
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            matches!(detected, magic::Archive::NotRecognized),
+            "{} detected as {:?}",
+            name,
+            detected
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-packdat")]
+fn packdat_universal() {
+    assert_archive_matches(
+        &fixture("packdat/input.dat"),
+        &fixture("packdat/expected"),
+    );
+}
+
+#[test]
+#[cfg(all(feature = "fmt-moonstone-dat", feature = "fmt-mcg"))]
+fn moonstone_dat_universal() {
+    assert_archive_matches(
+        &fixture("moonstone/input.dat"),
+        &fixture("moonstone/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-moonstone-dat")]
+fn moonstone_dat_detected_by_index() {
+    let path = fixture("moonstone/input.dat");
+    let sniffed = magic::sniff(&path).unwrap();
+    assert!(matches!(
+        magic::Archive::detect(&path, &sniffed),
+        magic::Archive::MoonstoneDat
+    ));
+
+    // Text file and table with entry pointing into index are not archives
+    let mut overlapping = vec![1, 0, 0, 0];
+    overlapping.extend_from_slice(&[b'a'; 0x20]);
+    overlapping.extend_from_slice(&[0, 0, 0, 0, 4, 0, 0, 0]);
+    for (name, contents) in [
+        ("text", b"Not a Moonstone archive\r\n".to_vec()),
+        ("overlapping", overlapping),
+    ]
+    .iter()
+    {
+        let path = std::env::temp_dir().join(format!(
+            "akaibu_not_moonstone_{}_{}.dat",
+            name,
+            std::process::id()
+        ));
+        std::fs::write(&path, contents).unwrap();
+        let sniffed = magic::sniff(&path).unwrap();
+        let detected = magic::Archive::detect(&path, &sniffed);
+        std::fs::remove_file(&path).unwrap();
+        assert!(
+            matches!(detected, magic::Archive::NotRecognized),
+            "{} detected as {:?}",
+            name,
+            detected
+        );
+    }
+}
+
+#[test]
+#[cfg(all(feature = "fmt-kaguya", feature = "fmt-pb2"))]
+fn kaguya_arc_universal() {
+    assert_archive_matches(
+        &fixture("kaguya/data.arc"),
+        &fixture("kaguya/expected"),
+    );
+}
+
+#[test]
+#[cfg(all(feature = "fmt-ykc", feature = "fmt-ykg"))]
+fn ykc_universal() {
+    assert_archive_matches(&fixture("ykc/input.ykc"), &fixture("ykc/expected"));
+}
+
+#[test]
+#[cfg(feature = "fmt-ykc")]
+fn ykc_malformed() {
+    use akaibu::scheme::{ykc::YkcScheme, Scheme};
+
+    // Index and name sizes larger than archive fail before allocating them
+    for path in &[
+        "malformed/ykc_huge_index.ykc",
+        "malformed/ykc_huge_name.ykc",
+    ] {
+        assert!(
+            YkcScheme::Universal.extract(&fixture(path)).is_err(),
+            "{} was accepted",
+            path
+        );
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-ikura")]
+fn ikura_gdl_universal() {
+    assert_archive_matches(
+        &fixture("ikura/input.dat"),
+        &fixture("ikura/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-amusepac")]
+fn amusepac_universal() {
+    assert_archive_matches(
+        &fixture("amusepac/input.pac"),
+        &fixture("amusepac/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-link6")]
+fn link6_universal() {
+    assert_archive_matches(
+        &fixture("link6/input.lnk"),
+        &fixture("link6/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-ypf")]
+fn ypf_v500_zlib() {
+    assert_archive_matches(&fixture("ypf/input.ypf"), &fixture("ypf/expected"));
+}
+
+#[test]
+#[cfg(feature = "fmt-silky")]
+fn silky_grp_lzss() {
+    assert_archive_matches(
+        &fixture("silky_grp/input.grp"),
+        &fixture("silky_grp/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-paz")]
+fn paz_companion_key() {
+    assert_archive_matches(&fixture("paz/input.paz"), &fixture("paz/expected"));
+}
+
+#[test]
+#[cfg(feature = "fmt-silky")]
+fn silky_arc_lzss() {
+    assert_archive_matches_with(
+        &akaibu::scheme::silky::SilkyScheme::Universal,
+        &fixture("silky/input.arc"),
+        &fixture("silky/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-willplus")]
+fn willplus_arc_universal() {
+    assert_archive_matches_with(
+        &akaibu::scheme::willplus_arc::ArcScheme::Universal,
+        &fixture("willplus/input.arc"),
+        &fixture("willplus/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-tactics")]
+fn tactics_arc_maou2() {
+    assert_archive_matches_with(
+        &akaibu::scheme::tactics_arc::ArcScheme::Maou2,
+        &fixture("tactics/input.arc"),
+        &fixture("tactics/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-acv1")]
+fn acv1_shukugar1() {
+    assert_archive_matches_with(
+        &akaibu::scheme::acv1::Acv1Scheme::Shukugar1,
+        &fixture("acv1/input.bin"),
+        &fixture("acv1/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-cpz7")]
+fn cpz7_aoi_tori() {
+    assert_archive_matches_with(
+        &akaibu::scheme::cpz7::Cpz7Scheme::AoiTori,
+        &fixture("cpz7/input.cpz"),
+        &fixture("cpz7/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-malie")]
+fn malie_haru_uso() {
+    assert_archive_matches_with(
+        &akaibu::scheme::malie::MalieScheme::HaruUso,
+        &fixture("malie/input.dat"),
+        &fixture("malie/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-qlie")]
+fn qlie_3_1_universal() {
+    let path = fixture("qlie/input.pack");
+    let sniffed = magic::sniff(&path).unwrap();
+    assert!(matches!(
+        magic::Archive::detect(&path, &sniffed),
+        magic::Archive::QliePack
+    ));
+    assert_archive_matches_with(
+        &akaibu::scheme::qliepack::PackScheme::UniversalVer31,
+        &path,
+        &fixture("qlie/expected"),
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-ikura")]
+fn extract_to_streams_stored_entries() {
+    let scheme = magic::Archive::IkuraGdl.get_schemes().remove(0);
+    let (archive, dir) = scheme
+        .extract(&fixture("ikura/input.dat"))
+        .expect("Could not open fixture archive");
+    for entry in dir.files() {
+        assert!(archive.entry_location(entry).is_some());
+        let mut streamed = Vec::new();
+        let written = archive
+            .extract_to(entry, &mut streamed)
+            .expect("Could not stream entry");
+        let extracted = archive.extract(entry).expect("Could not extract");
+        assert_eq!(written, streamed.len() as u64);
+        assert_eq!(streamed, extracted.contents.as_ref());
+    }
+}
+
+#[test]
+#[cfg(all(feature = "async", feature = "fmt-ikura"))]
+fn async_extraction_matches_blocking() {
+    use akaibu::nonblocking;
+    use std::{
+        future::Future,
+        sync::Arc,
+        task::{Context, Poll, Wake, Waker},
+        thread::{self, Thread},
+    };
+
+    struct ThreadWaker(Thread);
+    impl Wake for ThreadWaker {
+        fn wake(self: Arc<Self>) {
+            self.0.unpark();
+        }
+    }
+    // Minimal executor, futures do not need any runtime
+    fn block_on<F: Future>(future: F) -> F::Output {
+        let mut future = Box::pin(future);
+        let waker: Waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    let scheme = magic::Archive::IkuraGdl.get_schemes().remove(0);
+    let (archive, dir) = block_on(nonblocking::open(
+        scheme.as_ref(),
+        fixture("ikura/input.dat"),
+    ))
+    .expect("Could not open fixture archive");
+    // Every entry is extracted concurrently before any is awaited
+    let pending = dir
+        .files()
+        .map(|entry| (entry, archive.extract(entry)))
+        .collect::<Vec<_>>();
+    assert!(!pending.is_empty());
+    for (entry, extracted) in pending {
+        let extracted = block_on(extracted).expect("Could not extract");
+        let expected = archive.archive().extract(entry).unwrap();
+        assert_eq!(extracted.contents, expected.contents);
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-packdat")]
+fn packdat_stored_entry_location() {
+    let archive_path = fixture("packdat/input.dat");
+    let raw = std::fs::read(&archive_path).expect("Could not read fixture");
+    let scheme = magic::Archive::Packdat.get_schemes().remove(0);
+    let (archive, dir) = scheme
+        .extract(&archive_path)
+        .expect("Could not open fixture archive");
+    let (mut stored, mut transformed) = (0, 0);
+    for entry in dir.get_root_dir().get_all_files() {
+        match archive.entry_location(entry) {
+            Some((offset, len)) => {
+                let contents = archive.extract(entry).unwrap();
+                let data = &raw[offset as usize..(offset + len) as usize];
+                assert_eq!(data, &contents.contents[..], "{:?}", entry);
+                stored += 1;
+            }
+            None => transformed += 1,
+        }
+    }
+    assert!(stored > 0 && transformed > 0);
+}
+
+#[test]
+fn directory_from_entries_keeps_paths_inside_tree() {
+    use akaibu::archive::{Directory, FileEntry};
+    use std::path::{Path, PathBuf};
+
+    let dir = Directory::from_entries(
+        ["/abs/a.txt", "cg/../b.txt", "./cg/c.png", "cg/d.png"]
+            .iter()
+            .map(|path| FileEntry::new(PathBuf::from(path), 0, 0)),
+    );
+    fn names(dir: &Directory) -> Vec<&str> {
+        dir.files
+            .iter()
+            .map(|entry| entry.file_name.as_str())
+            .collect()
+    }
+    assert_eq!(
+        dir.directories.keys().collect::<Vec<&String>>(),
+        vec!["abs", "cg"]
+    );
+    assert_eq!(names(&dir.directories["abs"]), vec!["a.txt"]);
+    let cg = &dir.directories["cg"];
+    assert_eq!(names(cg), vec!["c.png", "b.txt", "d.png"]);
+    assert!(cg.directories.is_empty());
+    assert_eq!(
+        dir.find_file(Path::new("cg/../b.txt"))
+            .map(|entry| entry.file_offset),
+        Some(0)
+    );
+    assert_eq!(
+        FileEntry::new(PathBuf::from("dir/.."), 0, 0).file_name,
+        "dir"
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-pf8")]
+fn navigable_dir_prefix_iteration() {
+    use akaibu::archive::FileEntry;
+    use std::path::{Path, PathBuf};
+
+    let scheme = magic::Archive::Pf8.get_schemes().remove(0);
+    let (_, dir) = scheme
+        .extract(&fixture("pf8/input.pf8"))
+        .expect("Could not open fixture archive");
+    let paths = |files: &mut dyn Iterator<Item = &FileEntry>| {
+        files
+            .map(|entry| entry.full_path.clone())
+            .collect::<Vec<PathBuf>>()
+    };
+    assert_eq!(
+        paths(&mut dir.files()),
+        vec![
+            PathBuf::from("readme.txt"),
+            PathBuf::from("image/sample.akb")
+        ]
+    );
+    assert_eq!(
+        paths(&mut dir.files_with_prefix(Path::new("image"))),
+        vec![PathBuf::from("image/sample.akb")]
+    );
+    assert_eq!(
+        paths(&mut dir.files_with_prefix(Path::new("image/sample.akb"))),
+        vec![PathBuf::from("image/sample.akb")]
+    );
+    assert!(dir.files_with_prefix(Path::new("bg")).next().is_none());
+}
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-akb"))]
+fn pf8_convert_all_in_memory() {
+    let scheme = magic::Archive::Pf8.get_schemes().remove(0);
+    let handle =
+        ArchiveHandle::open(scheme.as_ref(), &fixture("pf8/input.pf8"))
+            .expect("Could not open fixture archive");
+    let converted = handle
+        .convert_all(&ExtractOptions::default())
+        .collect::<anyhow::Result<Vec<ConvertedEntry>>>()
+        .expect("Could not convert entries");
+    assert_eq!(converted.len(), 2);
+    for converted in converted {
+        match (converted.entry.file_name.as_str(), converted.contents) {
+            (
+                "sample.akb",
+                Converted::Resource(ResourceType::RgbaImage { .. }),
+            ) => {}
+            ("readme.txt", Converted::Raw(contents)) => assert_eq!(
+                contents[..],
+                std::fs::read(fixture("pf8/expected/readme.txt")).unwrap()[..]
+            ),
+            (name, contents) => panic!("Unexpected {} {:?}", name, contents),
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-pf8")]
+fn pf8_ignores_password() {
+    use akaibu::error::{self, AkaibuError};
+
+    let scheme = magic::Archive::Pf8.get_schemes().remove(0);
+    let handle = ArchiveHandle::open_with_password(
+        scheme.as_ref(),
+        &fixture("pf8/input.pf8"),
+        Some("secret"),
+    )
+    .expect("Password not used by scheme was not ignored");
+    assert_eq!(handle.dir().files().count(), 2);
+
+    let err = anyhow::Error::from(AkaibuError::PasswordRequired {
+        scheme: scheme.get_name(),
+    })
+    .context("Could not open archive");
+    assert!(error::password_required(&err));
+}
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-akb"))]
+fn pf8_gallery_export() {
+    use akaibu::gallery::{self, GalleryFormat};
+
+    let scheme = magic::Archive::Pf8.get_schemes().remove(0);
+    let handle =
+        ArchiveHandle::open(scheme.as_ref(), &fixture("pf8/input.pf8"))
+            .expect("Could not open fixture archive");
+    for format in &[GalleryFormat::Cbz, GalleryFormat::Pdf] {
+        let output_path = std::env::temp_dir().join(format!(
+            "akaibu_gallery_{}.{}",
+            std::process::id(),
+            format.extension()
+        ));
+        let pages = gallery::write_gallery(
+            &handle,
+            &output_path,
+            *format,
+            &ExtractOptions::default(),
+        )
+        .expect("Could not write gallery");
+        let contents = std::fs::read(&output_path).unwrap();
+        std::fs::remove_file(&output_path).unwrap();
+        // Only sample.akb is image, readme.txt is skipped
+        assert_eq!(pages, 1);
+        match format {
+            GalleryFormat::Cbz => {
+                assert!(contents.starts_with(b"PK\x03\x04"));
+                assert!(contents
+                    .windows(25)
+                    .any(|name| name == b"0000_image_sample.akb.png"));
+            }
+            GalleryFormat::Pdf => {
+                assert!(contents.starts_with(b"%PDF-1.4"));
+                assert!(contents.ends_with(b"%%EOF\n"));
+            }
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-pf8")]
+fn probe_ranks_matching_scheme_first() {
+    let results = akaibu::probe(&fixture("pf8/input.pf8"))
+        .expect("Could not probe fixture");
+    let best = &results[0];
+    assert_eq!(best.archive, "Pf8");
+    assert_eq!(best.entries, Some(2));
+    assert!(best.failure.is_none(), "{:?}", best.failure);
+    assert!(results[1..].iter().all(|r| r.confidence < best.confidence));
+    assert!(results
+        .iter()
+        .filter(|r| !r.tested)
+        .all(|r| r.failure.is_none()));
+    assert!(results.iter().skip_while(|r| r.tested).all(|r| !r.tested));
+}
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-akb", feature = "fmt-packdat"))]
+fn dump_game_directory() {
+    use akaibu::dump;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_dump_{}", std::process::id()));
+    let game_dir = root.join("game");
+    let output_dir = root.join("output");
+    std::fs::create_dir_all(game_dir.join("data")).unwrap();
+    std::fs::copy(fixture("pf8/input.pf8"), game_dir.join("data/input.pf8"))
+        .unwrap();
+    std::fs::copy(fixture("packdat/input.dat"), game_dir.join("input.dat"))
+        .unwrap();
+    std::fs::write(game_dir.join("readme.txt"), b"not an archive").unwrap();
+
+    let mut reported = 0;
+    let summary = dump::dump_game(
+        &game_dir,
+        &output_dir,
+        &ExtractOptions::default(),
+        |_| reported += 1,
+    )
+    .expect("Could not dump game directory");
+    let sample_written =
+        output_dir.join("data/input/image/sample.png").is_file();
+    let script_written = output_dir.join("input/script/start.txt").is_file();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(reported, 2);
+    assert_eq!(summary.archives.len(), 2);
+    assert!(summary
+        .archives
+        .iter()
+        .all(|archive| archive.error.is_none() && archive.failed == 0));
+    assert_eq!(
+        summary.schemes.keys().collect::<Vec<&String>>(),
+        vec!["Packdat", "Pf8"]
+    );
+    assert!(sample_written, "Converted image was not written");
+    assert!(script_written, "Entry was not written");
+}
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-akb"))]
+fn dump_writes_mapping() {
+    use akaibu::dump;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_mapping_{}", std::process::id()));
+    let game_dir = root.join("game");
+    let output_dir = root.join("output");
+    std::fs::create_dir_all(&game_dir).unwrap();
+    std::fs::copy(fixture("pf8/input.pf8"), game_dir.join("input.pf8"))
+        .unwrap();
+
+    dump::dump_game(
+        &game_dir,
+        &output_dir,
+        &ExtractOptions {
+            mapping: true,
+            ..Default::default()
+        },
+        |_| (),
+    )
+    .expect("Could not dump game directory");
+    let mapping: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(output_dir.join("input/input.pf8.mapping.json"))
+            .expect("Mapping was not written"),
+    )
+    .unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(
+        mapping["entries"],
+        serde_json::json!([
+            {
+                "path": "image/sample.akb",
+                "files": ["image/sample.png"],
+                "format": "png",
+                "dimensions": [3, 3]
+            },
+            {
+                "path": "readme.txt",
+                "files": ["readme.txt"],
+                "format": "stored"
+            }
+        ])
+    );
+}
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-akb"))]
+fn dump_downscales_like_extraction() {
+    use akaibu::dump;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_dump_options_{}", std::process::id()));
+    let game_dir = root.join("game");
+    let output_dir = root.join("output");
+    std::fs::create_dir_all(&game_dir).unwrap();
+    std::fs::copy(fixture("pf8/input.pf8"), game_dir.join("input.pf8"))
+        .unwrap();
+
+    let summary = dump::dump_game(
+        &game_dir,
+        &output_dir,
+        &ExtractOptions {
+            mapping: true,
+            max_dimension: Some(2),
+            ..Default::default()
+        },
+        |_| (),
+    )
+    .expect("Could not dump game directory");
+    let mapping: serde_json::Value = serde_json::from_slice(
+        &std::fs::read(output_dir.join("input/input.pf8.mapping.json"))
+            .expect("Mapping was not written"),
+    )
+    .unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(summary.archives[0].converted, 1);
+    assert_eq!(summary.archives[0].failed, 0);
+    assert_eq!(
+        mapping["entries"][0]["dimensions"],
+        serde_json::json!([2, 2])
+    );
+}
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-akb"))]
+fn spooled_input_archive() {
+    use akaibu::input::{InputSource, SpooledFile};
+
+    let archive = std::fs::read(fixture("pf8/input.pf8")).unwrap();
+    let input = InputSource::Spooled(
+        SpooledFile::from_reader(&mut &archive[..], "test")
+            .expect("Could not spool archive"),
+    );
+    let spooled_path = input.path().to_path_buf();
+    assert_eq!(spooled_path.file_name().unwrap(), "test");
+    // Second copy of the same stream does not reuse first one
+    let second = SpooledFile::from_reader(&mut &archive[..], "test")
+        .expect("Could not spool archive");
+    assert_archive_matches(&spooled_path, &fixture("pf8/expected"));
+    drop(input);
+    assert!(!spooled_path.exists(), "Spooled file was not removed");
+    drop(second);
+}
+
+#[test]
+#[cfg(all(feature = "fmt-pf8", feature = "fmt-gxp"))]
+fn streamed_input_listing() {
+    use akaibu::input::InputSource;
+
+    let archive = std::fs::read(fixture("pf8/input.pf8")).unwrap();
+    let mut listed = match InputSource::list_or_spool(&mut &archive[..], "test")
+        .expect("Could not list stream")
+    {
+        InputSource::Listed(entries) => entries,
+        input => panic!("PF8 stream was not listed: {:?}", input),
+    };
+    let scheme = magic::Archive::Pf8.get_schemes().remove(0);
+    let mut expected = scheme.list(&fixture("pf8/input.pf8")).unwrap();
+    // Stream is listed in entry table order
+    listed.sort_by(|a, b| a.full_path.cmp(&b.full_path));
+    expected.sort_by(|a, b| a.full_path.cmp(&b.full_path));
+    assert_eq!(listed, expected);
+
+    // GXP entry table is not read front to back
+    let archive = std::fs::read(fixture("gxp/input.gxp")).unwrap();
+    let input = InputSource::list_or_spool(&mut &archive[..], "test")
+        .expect("Could not spool stream");
+    assert!(matches!(input, InputSource::Spooled(_)));
+    assert_eq!(std::fs::read(input.path()).unwrap(), archive);
+}
+
+/// Names of files written for entries of given name and contents with
+/// extensions fixed
+fn extract_with_fixed_extensions(
+    test_name: &str,
+    files: &[(&str, &[u8])],
+) -> Vec<String> {
+    use akaibu::{
+        archive::{Archive, DirectoryArchive, FileEntry},
+        pipeline::extract_entries,
+        progress::ProgressTracker,
+    };
+    use std::path::PathBuf;
+
+    let root = std::env::temp_dir().join(format!(
+        "akaibu_{}_{}",
+        test_name,
+        std::process::id()
+    ));
+    let input_dir = root.join("input");
+    let output_dir = root.join("output");
+    std::fs::create_dir_all(&input_dir).unwrap();
+    let entries = files
+        .iter()
+        .map(|(name, contents)| {
+            std::fs::write(input_dir.join(name), contents).unwrap();
+            FileEntry {
+                file_name: name.to_string(),
+                full_path: PathBuf::from(name),
+                file_offset: 0,
+                file_size: contents.len() as u64,
+            }
+        })
+        .collect::<Vec<FileEntry>>();
+    let archive: Box<dyn Archive> = Box::new(DirectoryArchive::new(&input_dir));
+    extract_entries(
+        &archive,
+        &entries,
+        &output_dir,
+        &ProgressTracker::new(&entries),
+        None,
+        &ExtractOptions {
+            fix_extensions: true,
+            ..Default::default()
+        },
+    )
+    .expect("Could not extract entries");
+    let mut written = std::fs::read_dir(&output_dir)
+        .unwrap()
+        .map(|entry| entry.unwrap().file_name().into_string().unwrap())
+        .collect::<Vec<String>>();
+    written.sort();
+    std::fs::remove_dir_all(&root).unwrap();
+    written
+}
+
+#[test]
+fn fix_extensions_of_stored_entries() {
+    let written = extract_with_fixed_extensions(
+        "fix_extensions",
+        &[
+            ("bgm01", b"OggS\0\x02"),
+            ("voice.bin", b"RIFF\x04\0\0\0WAVEfmt "),
+            ("se.snd", b"\x80\0\0\x0A\0\0\0\0(c)CRI"),
+            ("movie.mpeg", b"\0\0\x01\xBA\x44"),
+            ("readme.txt", b"plain text"),
+        ],
+    );
+    assert_eq!(
+        written,
+        vec![
+            "bgm01.ogg",
+            "movie.mpeg",
+            "readme.txt",
+            "se.adx",
+            "voice.wav"
+        ]
+    );
+}
+
+#[test]
+fn fix_extensions_of_extensionless_entries() {
+    let text: &[u8] = b"@bg storage=title\r\n";
+    let tlg: &[u8] = b"TLG6.0\0raw\x1a";
+    let written = extract_with_fixed_extensions(
+        "fix_missing_extensions",
+        &[
+            ("script", text),
+            ("scenario.ks", text),
+            ("blob", b"\x01\x02\x03\x04"),
+            ("bg01", tlg),
+            ("bg02.dat", tlg),
+        ],
+    );
+    // Extensions of engine formats are only added when missing
+    #[cfg(feature = "fmt-tlg")]
+    let bg01 = "bg01.tlg";
+    #[cfg(not(feature = "fmt-tlg"))]
+    let bg01 = "bg01";
+    assert_eq!(
+        written,
+        vec![bg01, "bg02.dat", "blob", "scenario.ks", "script.txt"]
+    );
+}
+
+#[test]
+fn text_entries_normalized_when_asked() {
+    use akaibu::{
+        archive::{Archive, DirectoryArchive, FileEntry},
+        manifest::{EntryStatus, Manifest},
+        pipeline::extract_entries,
+        progress::ProgressTracker,
+    };
+    use std::path::PathBuf;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_normalize_text_{}", std::process::id()));
+    let input_dir = root.join("input");
+    std::fs::create_dir_all(&input_dir).unwrap();
+    let inputs: [(&str, &[u8]); 3] = [
+        ("bom.dat", b"\xEF\xBB\xBFfirst\r\nsecond\rthird"),
+        ("script.txt", b"first\r\nsecond"),
+        ("blob", b"first\r\nsecond"),
+    ];
+    let entries = inputs
+        .iter()
+        .map(|(name, contents)| {
+            std::fs::write(input_dir.join(name), contents).unwrap();
+            FileEntry {
+                file_name: name.to_string(),
+                full_path: PathBuf::from(name),
+                file_offset: 0,
+                file_size: contents.len() as u64,
+            }
+        })
+        .collect::<Vec<FileEntry>>();
+    let archive: Box<dyn Archive> = Box::new(DirectoryArchive::new(&input_dir));
+    let extract = |normalize_text| {
+        let output_dir = root.join(format!("output_{}", normalize_text));
+        let manifest = Manifest::new(&input_dir);
+        extract_entries(
+            &archive,
+            &entries,
+            &output_dir,
+            &ProgressTracker::new(&entries),
+            Some(&manifest),
+            &ExtractOptions {
+                normalize_text,
+                ..Default::default()
+            },
+        )
+        .expect("Could not extract entries");
+        let hashes = manifest
+            .entries()
+            .into_iter()
+            .map(|entry| match entry.status {
+                EntryStatus::Extracted { sha1, .. } => sha1,
+                status => panic!("Entry not extracted: {:?}", status),
+            })
+            .collect::<Vec<Option<String>>>();
+        let written = inputs
+            .iter()
+            .map(|(name, _)| std::fs::read(output_dir.join(name)).unwrap())
+            .collect::<Vec<Vec<u8>>>();
+        (written, hashes)
+    };
+    let (normalized, normalized_hashes) = extract(true);
+    let (kept, kept_hashes) = extract(false);
+    std::fs::remove_dir_all(&root).unwrap();
+
+    // Only entries recognized as text are normalized
+    assert_eq!(
+        normalized,
+        vec![
+            b"first\nsecond\nthird".to_vec(),
+            b"first\nsecond".to_vec(),
+            b"first\r\nsecond".to_vec(),
+        ]
+    );
+    assert_eq!(
+        kept,
+        inputs
+            .iter()
+            .map(|(_, contents)| contents.to_vec())
+            .collect::<Vec<Vec<u8>>>()
+    );
+    // Manifest records hash of stored bytes either way
+    assert_eq!(normalized_hashes, kept_hashes);
+}
+
+#[test]
+fn resume_finds_entries_renamed_by_fix_extensions() {
+    use akaibu::{
+        archive::{Archive, DirectoryArchive, FileEntry},
+        manifest::Manifest,
+        pipeline::extract_entries,
+        progress::ProgressTracker,
+    };
+    use std::path::PathBuf;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_resume_renamed_{}", std::process::id()));
+    let input_dir = root.join("input");
+    let output_dir = root.join("output");
+    std::fs::create_dir_all(&input_dir).unwrap();
+    std::fs::write(input_dir.join("bgm01"), b"OggS\0first").unwrap();
+    let entries = vec![FileEntry {
+        file_name: "bgm01".to_string(),
+        full_path: PathBuf::from("bgm01"),
+        file_offset: 0,
+        file_size: 10,
+    }];
+    let archive: Box<dyn Archive> = Box::new(DirectoryArchive::new(&input_dir));
+    let manifest_path = root.join("manifest.json");
+    let extract = |resume| {
+        let manifest = Manifest::new(&input_dir);
+        extract_entries(
+            &archive,
+            &entries,
+            &output_dir,
+            &ProgressTracker::new(&entries),
+            Some(&manifest),
+            &ExtractOptions {
+                fix_extensions: true,
+                resume,
+                ..Default::default()
+            },
+        )
+        .expect("Could not extract entries");
+        manifest.write(&manifest_path).unwrap();
+    };
+
+    extract(Default::default());
+    let completed = Manifest::load_completed(&manifest_path).unwrap();
+    assert_eq!(
+        completed[&PathBuf::from("bgm01")].output,
+        Some(PathBuf::from("bgm01.ogg"))
+    );
+    // Entry that is skipped keeps file written by first run
+    std::fs::write(input_dir.join("bgm01"), b"OggS\0again").unwrap();
+    extract(completed);
+    let written = std::fs::read(output_dir.join("bgm01.ogg")).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(written, b"OggS\0first");
+}
+
+#[test]
+fn group_entries_by_type() {
+    use akaibu::{
+        archive::{Archive, DirectoryArchive, FileEntry},
+        pipeline::extract_entries,
+        progress::ProgressTracker,
+    };
+    use std::path::{Path, PathBuf};
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_group_by_type_{}", std::process::id()));
+    let input_dir = root.join("input");
+    let output_dir = root.join("output");
+    std::fs::create_dir_all(input_dir.join("bgm")).unwrap();
+    let files: &[(&str, &[u8])] = &[
+        ("bgm/title", b"OggS\0\x02"),
+        ("voice.wav", b"RIFF\x04\0\0\0WAVEfmt "),
+        ("cg.png", b"\x89PNG\r\n\x1a\n\0\0\0\x0DIHDR"),
+        ("start.txt", b"plain text"),
+        ("data.bin", b"\0\x01\x02\x03\xFF\xFE"),
+    ];
+    let entries = files
+        .iter()
+        .map(|(name, contents)| {
+            std::fs::write(input_dir.join(name), contents).unwrap();
+            FileEntry {
+                file_name: name.rsplit('/').next().unwrap().to_string(),
+                full_path: PathBuf::from(name),
+                file_offset: 0,
+                file_size: contents.len() as u64,
+            }
+        })
+        .collect::<Vec<FileEntry>>();
+    let archive: Box<dyn Archive> = Box::new(DirectoryArchive::new(&input_dir));
+    extract_entries(
+        &archive,
+        &entries,
+        &output_dir,
+        &ProgressTracker::new(&entries),
+        None,
+        &ExtractOptions {
+            group_by_type: true,
+            ..Default::default()
+        },
+    )
+    .expect("Could not extract entries");
+    let written = [
+        "audio/bgm/title",
+        "audio/voice.wav",
+        "images/cg.png",
+        "scripts/start.txt",
+        "other/data.bin",
+    ]
+    .iter()
+    .filter(|path| output_dir.join(Path::new(path)).is_file())
+    .count();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(written, files.len());
+}
+
+#[test]
+fn keep_going_reports_all_failed_entries() {
+    use akaibu::{
+        archive::{Archive, DirectoryArchive, FileEntry},
+        error,
+        pipeline::extract_entries,
+        progress::ProgressTracker,
+    };
+    use std::path::PathBuf;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_keep_going_{}", std::process::id()));
+    let input_dir = root.join("input");
+    let output_dir = root.join("output");
+    std::fs::create_dir_all(&input_dir).unwrap();
+    // Missing files can not be extracted from directory archive
+    let files: &[(&str, Option<&[u8]>)] = &[
+        ("a.txt", Some(b"first")),
+        ("missing2.bin", None),
+        ("b.txt", Some(b"second")),
+        ("missing1.bin", None),
+        ("c.txt", Some(b"third")),
+    ];
+    let entries = files
+        .iter()
+        .map(|(name, contents)| {
+            if let Some(contents) = contents {
+                std::fs::write(input_dir.join(name), contents).unwrap();
+            }
+            FileEntry {
+                file_name: name.to_string(),
+                full_path: PathBuf::from(name),
+                file_offset: 0,
+                file_size: contents.map_or(1, |contents| contents.len()) as u64,
+            }
+        })
+        .collect::<Vec<FileEntry>>();
+    let archive: Box<dyn Archive> = Box::new(DirectoryArchive::new(&input_dir));
+    let extract = |keep_going| {
+        extract_entries(
+            &archive,
+            &entries,
+            &output_dir,
+            &ProgressTracker::new(&entries),
+            None,
+            &ExtractOptions {
+                keep_going,
+                ..Default::default()
+            },
+        )
+        .unwrap_err()
+    };
+    let stopped = extract(false);
+    let err = extract(true);
+    let failures = error::entry_failures(&err)
+        .expect("Failures not aggregated")
+        .iter()
+        .map(|failure| failure.path.clone())
+        .collect::<Vec<PathBuf>>();
+    let written = ["a.txt", "b.txt", "c.txt"]
+        .iter()
+        .filter(|name| output_dir.join(name).is_file())
+        .count();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert!(error::entry_failures(&stopped).is_none());
+    assert_eq!(
+        failures,
+        vec![PathBuf::from("missing1.bin"), PathBuf::from("missing2.bin")]
+    );
+    assert_eq!(written, 3);
+}
+
+#[test]
+fn entries_stay_inside_output_dir() {
+    use akaibu::{
+        archive::{Archive, DirectoryArchive, FileEntry},
+        pipeline::extract_entries,
+        progress::ProgressTracker,
+    };
+    use std::path::PathBuf;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_output_escape_{}", std::process::id()));
+    let input_dir = root.join("input").join("archive");
+    let output_dir = root.join("output").join("archive");
+    std::fs::create_dir_all(&input_dir).unwrap();
+    // Directory archive resolves `..` on disk, as archive with such name
+    // stored in its index would
+    std::fs::write(root.join("input").join("escape.txt"), b"escape").unwrap();
+    let entries = vec![FileEntry::new(PathBuf::from("../escape.txt"), 0, 6)];
+    let archive: Box<dyn Archive> = Box::new(DirectoryArchive::new(&input_dir));
+    extract_entries(
+        &archive,
+        &entries,
+        &output_dir,
+        &ProgressTracker::new(&entries),
+        None,
+        &ExtractOptions::default(),
+    )
+    .unwrap();
+    let inside = output_dir.join("escape.txt").is_file();
+    let outside = root.join("output").join("escape.txt").exists();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert!(inside);
+    assert!(!outside);
+}
+
+#[test]
+#[cfg(feature = "fmt-qlie")]
+fn qlie_list_matches_opened_archive() {
+    use akaibu::{
+        archive::FileEntryMetadata,
+        scheme::{qliepack::PackScheme, Scheme},
+    };
+
+    let path = fixture("qlie/input.pack");
+    let scheme = PackScheme::UniversalVer31;
+    let listed = scheme.list(&path);
+    let opened = scheme.extract(&path).map(|(archive, dir)| {
+        dir.files()
+            .map(|entry| FileEntryMetadata::of(archive.as_ref(), entry))
+            .collect::<Vec<FileEntryMetadata>>()
+    });
+
+    let sorted = |mut entries: Vec<FileEntryMetadata>| {
+        entries.sort_by(|a, b| a.full_path.cmp(&b.full_path));
+        entries
+    };
+    let listed = sorted(listed.expect("Could not list archive"));
+    assert_eq!(listed.len(), 4);
+    assert_eq!(listed, sorted(opened.expect("Could not open archive")));
+}
+
+#[test]
+#[cfg(feature = "fmt-ikura")]
+fn ikura_gdl_round_trip() {
+    use akaibu::scheme::{
+        ikura::{
+            writer::{self, PackInput},
+            IkuraScheme,
+        },
+        Scheme,
+    };
+    use std::path::PathBuf;
+
+    let input = |full_path: &str, contents: Vec<u8>| PackInput {
+        full_path: PathBuf::from(full_path),
+        contents,
+    };
+    let inputs = vec![
+        input("START.ISF", vec![0x12, 0x34, 0x00, 0xFF]),
+        input("empty.txt", Vec::new()),
+        input("BG01.GGD", (0..1001u32).map(|i| (i % 251) as u8).collect()),
+    ];
+    let path = std::env::temp_dir()
+        .join(format!("akaibu_ikura_{}.dat", std::process::id()));
+    writer::write_mpx(
+        inputs.clone(),
+        &mut std::fs::File::create(&path).unwrap(),
+    )
+    .expect("Could not write archive");
+
+    let sniffed = magic::sniff(&path).unwrap();
+    let detected = magic::Archive::detect(&path, &sniffed);
+    let extracted =
+        IkuraScheme::Universal.extract(&path).map(|(archive, dir)| {
+            inputs
+                .iter()
+                .map(|input| {
+                    let entry = dir
+                        .get_root_dir()
+                        .find_file(&input.full_path)
+                        .expect("Entry not found");
+                    archive.extract(entry).unwrap().contents.to_vec()
+                })
+                .collect::<Vec<Vec<u8>>>()
+        });
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(matches!(detected, magic::Archive::IkuraGdl));
+    let extracted = extracted.expect("Could not open written archive");
+    for (input, contents) in inputs.iter().zip(extracted) {
+        assert_eq!(input.contents, contents, "{:?}", input.full_path);
+    }
+    // Names longer than 12 bytes or in directories can not be stored
+    for full_path in &["LONG_NAME.TXT2", "bg/BG01.GGD"] {
+        assert!(writer::write_mpx(
+            vec![input(full_path, Vec::new())],
+            &mut Vec::new()
+        )
+        .is_err());
+    }
+}
+
+/// Archive written by scheme of format must be detected as that format and
+/// give back the same entries
+fn assert_pack_round_trip(
+    format: akaibu::scheme::pack::PackFormat,
+    inputs: &[akaibu::scheme::pack::PackInput],
+) {
+    let scheme = format.scheme();
+    let mut buf = Vec::new();
+    scheme
+        .write_archive(inputs.to_vec(), &mut buf)
+        .unwrap_or_else(|err| panic!("{:?}: {}", format, err));
+    let path = std::env::temp_dir().join(format!(
+        "akaibu_pack_{:?}_{}",
+        format,
+        std::process::id()
+    ));
+    std::fs::write(&path, &buf).unwrap();
+    let sniffed = magic::sniff(&path).unwrap();
+    let detected = magic::Archive::detect(&path, &sniffed);
+    let extracted = scheme.extract(&path).map(|(archive, dir)| {
+        inputs
+            .iter()
+            .map(|input| {
+                let entry = dir
+                    .get_root_dir()
+                    .find_file(&input.full_path)
+                    .unwrap_or_else(|| {
+                        panic!("{:?}: {:?} not found", format, input.full_path)
+                    });
+                archive.extract(entry).unwrap().contents.to_vec()
+            })
+            .collect::<Vec<Vec<u8>>>()
+    });
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        detected.get_schemes()[0].get_name(),
+        scheme.get_name(),
+        "{:?}",
+        format
+    );
+    let extracted =
+        extracted.unwrap_or_else(|err| panic!("{:?}: {}", format, err));
+    for (input, contents) in inputs.iter().zip(extracted) {
+        assert_eq!(
+            input.contents, contents,
+            "{:?}: {:?}",
+            format, input.full_path
+        );
+    }
+}
+
+#[test]
+fn pack_round_trip() {
+    use akaibu::scheme::pack::{PackFormat, PackInput};
+    use std::path::PathBuf;
+
+    let input = |full_path: &str, contents: Vec<u8>| PackInput {
+        full_path: PathBuf::from(full_path),
+        contents,
+    };
+    let image = (0..1001u32).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+    let script = "Ｈｅｌｌｏ\n".as_bytes().to_vec();
+    let nested = vec![
+        input("bg/BG01.png", image.clone()),
+        input("script/start.txt", script.clone()),
+        input("empty.dat", Vec::new()),
+    ];
+    #[cfg(feature = "fmt-pf8")]
+    assert_pack_round_trip(PackFormat::Pf8, &nested);
+    #[cfg(feature = "fmt-gxp")]
+    assert_pack_round_trip(PackFormat::Gxp, &nested);
+    #[cfg(feature = "fmt-esc-arc2")]
+    assert_pack_round_trip(PackFormat::EscArc2, &nested);
+    #[cfg(feature = "fmt-buriko")]
+    {
+        let flat = vec![
+            input("BG01", image),
+            input("start", script),
+            input("empty", Vec::new()),
+        ];
+        assert_pack_round_trip(PackFormat::Buriko, &flat);
+        // Names are not split into directories
+        assert!(PackFormat::Buriko
+            .scheme()
+            .write_archive(nested, &mut Vec::new())
+            .is_err());
+    }
+}
+
+/// MBL archive with entries stored after index. Version 1 has 16 byte
+/// names, version 2 stores name size in header.
+#[cfg(feature = "fmt-mbl")]
+fn mbl_archive(
+    version: u32,
+    name_size: usize,
+    entries: &[(&str, Vec<u8>)],
+) -> Vec<u8> {
+    let mut buf = (entries.len() as u32).to_le_bytes().to_vec();
+    if version == 2 {
+        buf.extend_from_slice(&(name_size as u32).to_le_bytes());
+    }
+    let mut offset = buf.len() + entries.len() * (name_size + 8);
+    for (name, contents) in entries {
+        let mut name = name.as_bytes().to_vec();
+        name.resize(name_size, 0);
+        buf.extend_from_slice(&name);
+        buf.extend_from_slice(&(offset as u32).to_le_bytes());
+        buf.extend_from_slice(&(contents.len() as u32).to_le_bytes());
+        offset += contents.len();
+    }
+    for (_, contents) in entries {
+        buf.extend_from_slice(contents);
+    }
+    buf
+}
+
+#[test]
+#[cfg(feature = "fmt-mbl")]
+fn mbl_round_trip() {
+    use std::path::Path;
+
+    let entries = [
+        (
+            "bg\\BG01.bmp",
+            (0..1001u32).map(|i| (i % 251) as u8).collect(),
+        ),
+        ("start.txt", b"Hello\n".to_vec()),
+        ("empty.dat", Vec::new()),
+    ];
+    for &(version, name_size) in &[(1, 0x10), (2, 0x20)] {
+        let root = std::env::temp_dir().join(format!(
+            "akaibu_mbl_v{}_{}",
+            version,
+            std::process::id()
+        ));
+        std::fs::create_dir_all(&root).unwrap();
+        let path = root.join("input.mbl");
+        std::fs::write(&path, mbl_archive(version, name_size, &entries))
+            .unwrap();
+        let sniffed = magic::sniff(&path).unwrap();
+        let detected = magic::Archive::detect(&path, &sniffed);
+        let extracted =
+            detected.get_schemes()[0]
+                .extract(&path)
+                .map(|(archive, dir)| {
+                    let output_dir = root.join("output");
+                    archive
+                        .extract_all(&output_dir)
+                        .expect("Could not extract archive");
+                    entries
+                        .iter()
+                        .map(|(name, _)| {
+                            let full_path = name.replace('\\', "/");
+                            let entry = dir
+                                .get_root_dir()
+                                .find_file(Path::new(&full_path))
+                                .expect("Entry not found");
+                            (
+                                archive
+                                    .extract(entry)
+                                    .unwrap()
+                                    .contents
+                                    .to_vec(),
+                                std::fs::read(output_dir.join(&full_path)),
+                            )
+                        })
+                        .collect::<Vec<_>>()
+                });
+        std::fs::remove_dir_all(&root).unwrap();
+
+        assert!(matches!(detected, magic::Archive::Mbl));
+        let extracted =
+            extracted.unwrap_or_else(|err| panic!("MBL v{}: {}", version, err));
+        for ((name, contents), (extracted, written)) in
+            entries.iter().zip(extracted)
+        {
+            assert_eq!(contents, &extracted, "MBL v{}: {}", version, name);
+            assert_eq!(
+                contents,
+                &written.expect("Entry was not written"),
+                "MBL v{}: {}",
+                version,
+                name
+            );
+        }
+    }
+}
+
+#[test]
+#[cfg(feature = "fmt-ikura")]
+fn validate_index_reports_overlap() {
+    use akaibu::{
+        archive::FileEntry,
+        scheme::ikura::writer::{self, PackInput},
+        validate::{validate_index, IndexProblem},
+    };
+    use std::{convert::TryInto, path::PathBuf};
+
+    let open = |path: &std::path::Path| {
+        let (archive, dir) = magic::Archive::IkuraGdl.get_schemes()[0]
+            .extract(path)
+            .expect("Could not open archive");
+        let entries = dir.files().cloned().collect::<Vec<FileEntry>>();
+        let size = std::fs::metadata(path).unwrap().len();
+        validate_index(&archive, &entries, size)
+    };
+    let report = open(&fixture("ikura/input.dat"));
+    assert!(report.is_valid(), "{:?}", report.problems);
+    assert_eq!((report.checked, report.unchecked), (2, 0));
+
+    let mut buf = Vec::new();
+    writer::write_mpx(
+        vec![
+            PackInput {
+                full_path: PathBuf::from("a.txt"),
+                contents: vec![1; 0x10],
+            },
+            PackInput {
+                full_path: PathBuf::from("b.txt"),
+                contents: vec![2; 0x10],
+            },
+        ],
+        &mut buf,
+    )
+    .unwrap();
+    // Move second entry 8 bytes back into data of first one
+    let record = 0x20 + 0x14 + 12;
+    let offset =
+        u32::from_le_bytes(buf[record..record + 4].try_into().unwrap());
+    buf[record..record + 4].copy_from_slice(&(offset - 8).to_le_bytes());
+    let path = std::env::temp_dir()
+        .join(format!("akaibu_overlap_{}.dat", std::process::id()));
+    std::fs::write(&path, &buf).unwrap();
+    let report = open(&path);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(
+        report.problems,
+        vec![IndexProblem::Overlap {
+            first: PathBuf::from("a.txt"),
+            second: PathBuf::from("b.txt"),
+            offset: offset as u64 - 8,
+            size: 8,
+        }]
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-ikura")]
+fn overlay_prefers_newer_entries() {
+    use akaibu::{
+        archive::{Archive, FileEntry},
+        overlay::{self, OverlayArchive},
+        scheme::ikura::writer::{self, PackInput},
+    };
+    use std::path::{Path, PathBuf};
+
+    let write = |path: &Path, entries: &[(&str, u8)]| {
+        let mut buf = Vec::new();
+        writer::write_mpx(
+            entries
+                .iter()
+                .map(|&(name, byte)| PackInput {
+                    full_path: PathBuf::from(name),
+                    contents: vec![byte; 0x10],
+                })
+                .collect(),
+            &mut buf,
+        )
+        .unwrap();
+        std::fs::write(path, &buf).unwrap();
+    };
+    let path = std::env::temp_dir()
+        .join(format!("akaibu_overlay_{}.dat", std::process::id()));
+    write(&path, &[("b.txt", 3), ("c.txt", 4)]);
+    assert!(overlay::backup_path(&path).is_none());
+    let backup = path.with_extension("dat.bak");
+    write(&backup, &[("a.txt", 1), ("b.txt", 2)]);
+    assert_eq!(overlay::backup_path(&path), Some(backup.clone()));
+
+    let scheme = magic::Archive::IkuraGdl.get_schemes().remove(0);
+    let (archive, dir) = OverlayArchive::merge(
+        scheme.extract(&backup).unwrap(),
+        scheme.extract(&path).unwrap(),
+        "overlay.dat.bak".to_string(),
+    );
+    let entries = dir.files().cloned().collect::<Vec<FileEntry>>();
+    let merged = entries
+        .iter()
+        .map(|entry| {
+            let contents = archive.extract(entry).unwrap().contents;
+            let overrides = archive.entry_ext(entry).overrides;
+            (entry.file_name.as_str(), contents[0], overrides)
+        })
+        .collect::<Vec<(&str, u8, Option<String>)>>();
+    drop(archive);
+    std::fs::remove_file(&path).unwrap();
+    std::fs::remove_file(&backup).unwrap();
+    assert_eq!(
+        merged,
+        vec![
+            ("a.txt", 1, None),
+            ("b.txt", 3, Some("overlay.dat.bak".to_string())),
+            ("c.txt", 4, None),
+        ]
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-qlie")]
+fn qlie_detected_by_tail_magic() {
+    let mut tail = vec![0u8; magic::TAIL_WINDOW];
+    let start = tail.len() - 0x1C;
+    tail[start..start + 11].copy_from_slice(b"FilePackVer");
+    assert!(matches!(
+        magic::detect_with_tail(&[0; 4], &tail),
+        magic::Archive::QliePack
+    ));
+    // Tail of file shorter than footer can not hold magic
+    assert!(matches!(
+        magic::detect_with_tail(&[0; 4], &tail[start + 1..]),
+        magic::Archive::NotRecognized
+    ));
+}
+
+#[test]
+#[cfg(unix)]
+fn manifest_keeps_raw_entry_names() {
+    use akaibu::{
+        archive::{FileEntry, FileEntryExt},
+        manifest::{EntryStatus, Manifest},
+    };
+    use std::{ffi::OsString, os::unix::ffi::OsStringExt, path::PathBuf};
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_raw_names_{}", std::process::id()));
+    let manifest_path = root.join("data.arc.manifest.json");
+    // "あ" in SHIFT_JIS
+    let raw_path =
+        PathBuf::from(OsString::from_vec(b"bg/\x82\xa0.png".to_vec()));
+    let manifest = Manifest::new(&root.join("data.arc"));
+    for path in [raw_path.clone(), PathBuf::from("bg/100%.png")] {
+        manifest.record(
+            &FileEntry {
+                file_name: String::new(),
+                full_path: path,
+                file_offset: 0,
+                file_size: 4,
+            },
+            FileEntryExt::default(),
+            EntryStatus::Extracted {
+                bytes_written: 4,
+                sha1: Some("sha1".to_string()),
+                output: None,
+            },
+        );
+    }
+    manifest.write(&manifest_path).unwrap();
+    let written = std::fs::read_to_string(&manifest_path).unwrap();
+    let completed = Manifest::load_completed(&manifest_path).unwrap();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert!(written.contains("\"path\": \"bg/あ.png\""));
+    assert!(written.contains("\"raw_path\": \"bg/%82%A0.png\""));
+    assert_eq!(written.matches("raw_path").count(), 1);
+    assert!(completed.contains_key(&raw_path));
+    assert!(completed.contains_key(&PathBuf::from("bg/100%.png")));
+}
+
+#[test]
+#[cfg(feature = "fmt-pf8")]
+fn pf8_patch_bundle() {
+    use akaibu::{
+        manifest::Manifest,
+        patch::{self, PatchKind},
+        pipeline,
+        progress::ProgressTracker,
+    };
+    use std::path::Path;
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_patch_{}", std::process::id()));
+    let extracted_dir = root.join("extracted");
+    let bundle_dir = root.join("bundle");
+    let original_path = fixture("pf8/input.pf8");
+    let scheme = magic::Archive::Pf8.get_schemes().remove(0);
+    let original = ArchiveHandle::open(scheme.as_ref(), &original_path)
+        .expect("Could not open archive");
+    let entries = original.dir().files().cloned().collect::<Vec<_>>();
+    let manifest = Manifest::new(&original_path);
+    pipeline::extract_entries(
+        original.archive(),
+        &entries,
+        &extracted_dir,
+        &ProgressTracker::new(&entries),
+        Some(&manifest),
+        &ExtractOptions::default(),
+    )
+    .expect("Could not extract archive");
+    let manifest_path = manifest.default_path(&extracted_dir);
+    manifest.write(&manifest_path).unwrap();
+
+    std::fs::write(extracted_dir.join("readme.txt"), b"patched readme")
+        .unwrap();
+    std::fs::write(extracted_dir.join("credits.txt"), b"credits").unwrap();
+    let bundle =
+        patch::create_bundle(&manifest_path, &extracted_dir, &bundle_dir)
+            .expect("Could not create bundle");
+    let inputs =
+        patch::apply_bundle(original.archive(), original.dir(), &bundle_dir)
+            .expect("Could not apply bundle");
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(
+        bundle
+            .entries
+            .iter()
+            .map(|entry| (entry.path.to_string_lossy().to_string(), entry.kind))
+            .collect::<Vec<_>>(),
+        vec![
+            ("credits.txt".to_string(), PatchKind::Added),
+            ("readme.txt".to_string(), PatchKind::Modified),
+        ]
+    );
+    let contents = |path: &str| {
+        inputs
+            .iter()
+            .find(|input| input.full_path == Path::new(path))
+            .map(|input| input.contents.clone())
+            .expect("Entry not found")
+    };
+    let image = original
+        .dir()
+        .get_root_dir()
+        .find_file(Path::new("image/sample.akb"))
+        .expect("Entry not found");
+    assert_eq!(inputs.len(), 3);
+    assert_eq!(contents("readme.txt"), b"patched readme");
+    assert_eq!(contents("credits.txt"), b"credits");
+    assert_eq!(
+        contents("image/sample.akb"),
+        original.archive().extract(image).unwrap().contents.to_vec()
+    );
+}
+
+#[test]
+#[cfg(unix)]
+fn shift_jis_entry_name_displayed() {
+    use akaibu::util::text::display_name;
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt, path::Path};
+
+    // 背景 in SHIFT_JIS, as left by schemes keeping raw names
+    let path = Path::new(OsStr::from_bytes(b"cg/\x94w\x8ci.png"));
+    assert_eq!(display_name(path).to_string(), "cg/背景.png");
+    assert_eq!(
+        display_name(Path::new("cg/背景.png")).to_string(),
+        "cg/背景.png"
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-pf8")]
+fn pf8_inspect_headers() {
+    let report = akaibu::inspect::inspect(&fixture("pf8/input.pf8"))
+        .expect("Could not inspect PF8 archive");
+    assert_eq!(report.format, "Pf8");
+    assert_eq!(report.headers[0].name, "Pf8Header");
+    assert_eq!(report.headers[0].fields["file_entries_count"], 2);
+}
+
+#[test]
+#[cfg(all(
+    feature = "fmt-acv1",
+    feature = "fmt-amusepac",
+    feature = "fmt-kaguya",
+    feature = "fmt-moonstone-dat",
+    feature = "fmt-otomate-bin",
+    feature = "fmt-packdat",
+    feature = "fmt-silky",
+    feature = "fmt-tactics",
+    feature = "fmt-willplus",
+))]
+fn archive_inspect_entry_counts() {
+    use akaibu::scheme::*;
+    let schemes: [(&dyn Scheme, &str, &str, usize); 9] = [
+        (
+            &acv1::Acv1Scheme::Shukugar1,
+            "acv1/input.bin",
+            "entry_count",
+            3,
+        ),
+        (
+            &amusepac::PacScheme::Universal,
+            "amusepac/input.pac",
+            "entries_count",
+            3,
+        ),
+        (
+            &kaguya_arc::KaguyaArcScheme::Universal,
+            "kaguya/data.arc",
+            "entry_count",
+            2,
+        ),
+        (
+            &moonstone_dat::MoonstoneDatScheme::Universal,
+            "moonstone/input.dat",
+            "entry_count",
+            2,
+        ),
+        (
+            &otomate_bin::OtomateBinScheme::Universal,
+            "otomate_bin/input.bin",
+            "entry_count",
+            2,
+        ),
+        (
+            &packdat::PackdatScheme::Universal,
+            "packdat/input.dat",
+            "entry_count",
+            3,
+        ),
+        (
+            &silky_grp::GrpScheme::Universal,
+            "silky_grp/input.grp",
+            "entry_count",
+            3,
+        ),
+        (
+            &tactics_arc::ArcScheme::Maou2,
+            "tactics/input.arc",
+            "entry_count",
+            3,
+        ),
+        (
+            &willplus_arc::ArcScheme::Universal,
+            "willplus/input.arc",
+            "entry_count",
+            3,
+        ),
+    ];
+    for (scheme, archive, field, entry_count) in schemes {
+        let headers = scheme
+            .inspect(&fixture(archive))
+            .unwrap_or_else(|e| panic!("Could not inspect {}: {}", archive, e));
+        assert_eq!(headers[0].fields[field], entry_count, "{}", archive);
+    }
+}
+
+/// Headers of encrypted indexes are still reported without a key
+#[test]
+#[cfg(all(feature = "fmt-malie", feature = "fmt-paz"))]
+fn archive_inspect_without_key() {
+    use akaibu::scheme::*;
+    let schemes: [(&dyn Scheme, &str); 2] = [
+        (&malie::MalieScheme::NatsuUso, "malie/input.dat"),
+        (&paz::PazScheme::Universal, "paz/input.paz"),
+    ];
+    for (scheme, archive) in schemes {
+        let headers = scheme
+            .inspect(&fixture(archive))
+            .unwrap_or_else(|e| panic!("Could not inspect {}: {}", archive, e));
+        assert!(!headers.is_empty(), "{}", archive);
+    }
+}
+
+#[test]
+fn debug_output_to_json() {
+    let json = akaibu::inspect::debug_to_json(
+        r#"Header { magic: [80, 70], name: Some("a\"b"), offset: None }"#,
+    );
+    assert_eq!(
+        json,
+        serde_json::json!({"magic": [80, 70], "name": "a\"b", "offset": null})
+    );
+}
+
+/// Entries decoded from bytes read at their raw location must match
+/// entries extracted one by one
+fn assert_raw_decode_matches(archive_path: &std::path::Path) {
+    let sniffed = magic::sniff(archive_path).expect("Could not read fixture");
+    let scheme = magic::Archive::detect(archive_path, &sniffed)
+        .get_schemes()
+        .remove(0);
+    let handle = ArchiveHandle::open(scheme.as_ref(), archive_path)
+        .expect("Could not open fixture archive");
+    for entry in handle.dir().files() {
+        let (offset, size) = handle
+            .archive()
+            .raw_location(entry)
+            .expect("Scheme does not expose raw location");
+        let mut raw = vec![0; size as usize];
+        handle.archive().read_raw(offset, &mut raw).unwrap();
+        let decoded = handle
+            .archive()
+            .decode_raw(entry, raw.into())
+            .unwrap_or_else(|err| panic!("{:?}: {}", entry.full_path, err));
+        let extracted = handle.archive().extract(entry).unwrap();
+        assert_eq!(
+            decoded.contents, extracted.contents,
+            "{:?} decoded differently from raw bytes",
+            entry.full_path
+        );
+    }
+}
+
+#[test]
+fn read_ahead_decodes_raw_entries() {
+    #[cfg(feature = "fmt-pf8")]
+    assert_raw_decode_matches(&fixture("pf8/input.pf8"));
+    #[cfg(feature = "fmt-buriko")]
+    assert_raw_decode_matches(&fixture("buriko/input.arc"));
+    #[cfg(feature = "fmt-kaguya")]
+    assert_raw_decode_matches(&fixture("kaguya/data.arc"));
+    #[cfg(feature = "fmt-ykc")]
+    assert_raw_decode_matches(&fixture("ykc/input.ykc"));
+}
+
+/// Entries extracted from many threads sharing one archive at once must
+/// match entries extracted one by one
+fn assert_concurrent_extract_matches(archive_path: &std::path::Path) {
+    let sniffed = magic::sniff(archive_path).expect("Could not read fixture");
+    let scheme = magic::Archive::detect(archive_path, &sniffed)
+        .get_schemes()
+        .remove(0);
+    assert_concurrent_extract_matches_with(scheme.as_ref(), archive_path);
+}
+
+/// Same as [`assert_concurrent_extract_matches`] with given scheme
+fn assert_concurrent_extract_matches_with(
+    scheme: &dyn akaibu::scheme::Scheme,
+    archive_path: &std::path::Path,
+) {
+    use std::sync::Arc;
+
+    const THREADS: usize = 8;
+
+    let (archive, dir) = scheme
+        .extract(archive_path)
+        .expect("Could not open fixture archive");
+    let entries = dir.files().cloned().collect::<Vec<_>>();
+    let expected = entries
+        .iter()
+        .map(|entry| archive.extract(entry).unwrap().contents)
+        .collect::<Vec<_>>();
+    let archive = Arc::new(archive);
+    let entries = Arc::new(entries);
+    let threads = (0..THREADS)
+        .map(|thread| {
+            let archive = Arc::clone(&archive);
+            let entries = Arc::clone(&entries);
+            // Every thread starts at different entry, so same entries are
+            // read at the same time by some threads and not by others
+            std::thread::spawn(move || {
+                (0..entries.len())
+                    .map(|i| (i + thread) % entries.len())
+                    .map(|i| {
+                        (i, archive.extract(&entries[i]).unwrap().contents)
+                    })
+                    .collect::<Vec<_>>()
+            })
+        })
+        .collect::<Vec<_>>();
+    for thread in threads {
+        for (i, contents) in thread.join().expect("Extracting thread panicked")
+        {
+            assert_eq!(
+                contents, expected[i],
+                "{:?} of {:?} differs when extracted concurrently",
+                entries[i].full_path, archive_path
+            );
+        }
+    }
+}
+
+#[test]
+fn concurrent_extract_matches_sequential() {
+    #[cfg(feature = "fmt-pf8")]
+    assert_concurrent_extract_matches(&fixture("pf8/input.pf8"));
+    #[cfg(feature = "fmt-gxp")]
+    assert_concurrent_extract_matches(&fixture("gxp/input.gxp"));
+    #[cfg(feature = "fmt-gxp")]
+    assert_concurrent_extract_matches(&fixture("gxp/wide.gxp"));
+    #[cfg(feature = "fmt-buriko")]
+    assert_concurrent_extract_matches(&fixture("buriko/input.arc"));
+    #[cfg(feature = "fmt-nekopack")]
+    assert_concurrent_extract_matches(&fixture("nekopack/input.dat"));
+    #[cfg(feature = "fmt-otomate-bin")]
+    assert_concurrent_extract_matches(&fixture("otomate_bin/input.bin"));
+    #[cfg(feature = "fmt-packdat")]
+    assert_concurrent_extract_matches(&fixture("packdat/input.dat"));
+    #[cfg(feature = "fmt-moonstone-dat")]
+    assert_concurrent_extract_matches(&fixture("moonstone/input.dat"));
+    #[cfg(feature = "fmt-kaguya")]
+    assert_concurrent_extract_matches(&fixture("kaguya/data.arc"));
+    #[cfg(feature = "fmt-ykc")]
+    assert_concurrent_extract_matches(&fixture("ykc/input.ykc"));
+    #[cfg(feature = "fmt-ikura")]
+    assert_concurrent_extract_matches(&fixture("ikura/input.dat"));
+    #[cfg(feature = "fmt-amusepac")]
+    assert_concurrent_extract_matches(&fixture("amusepac/input.pac"));
+    #[cfg(feature = "fmt-link6")]
+    assert_concurrent_extract_matches(&fixture("link6/input.lnk"));
+    #[cfg(feature = "fmt-ypf")]
+    assert_concurrent_extract_matches(&fixture("ypf/input.ypf"));
+    #[cfg(feature = "fmt-silky")]
+    assert_concurrent_extract_matches(&fixture("silky_grp/input.grp"));
+    #[cfg(feature = "fmt-paz")]
+    assert_concurrent_extract_matches(&fixture("paz/input.paz"));
+    #[cfg(feature = "fmt-silky")]
+    assert_concurrent_extract_matches_with(
+        &akaibu::scheme::silky::SilkyScheme::Universal,
+        &fixture("silky/input.arc"),
+    );
+    #[cfg(feature = "fmt-willplus")]
+    assert_concurrent_extract_matches_with(
+        &akaibu::scheme::willplus_arc::ArcScheme::Universal,
+        &fixture("willplus/input.arc"),
+    );
+    #[cfg(feature = "fmt-tactics")]
+    assert_concurrent_extract_matches_with(
+        &akaibu::scheme::tactics_arc::ArcScheme::Maou2,
+        &fixture("tactics/input.arc"),
+    );
+    #[cfg(feature = "fmt-acv1")]
+    assert_concurrent_extract_matches_with(
+        &akaibu::scheme::acv1::Acv1Scheme::Shukugar1,
+        &fixture("acv1/input.bin"),
+    );
+    #[cfg(feature = "fmt-cpz7")]
+    assert_concurrent_extract_matches_with(
+        &akaibu::scheme::cpz7::Cpz7Scheme::AoiTori,
+        &fixture("cpz7/input.cpz"),
+    );
+    #[cfg(feature = "fmt-malie")]
+    assert_concurrent_extract_matches_with(
+        &akaibu::scheme::malie::MalieScheme::HaruUso,
+        &fixture("malie/input.dat"),
+    );
+    #[cfg(feature = "fmt-qlie")]
+    assert_concurrent_extract_matches_with(
+        &akaibu::scheme::qliepack::PackScheme::UniversalVer31,
+        &fixture("qlie/input.pack"),
+    );
+}
+
+/// Formats without fixtures are written into temporary files first
+#[test]
+#[cfg(any(feature = "fmt-mbl", feature = "fmt-esc-arc2"))]
+fn concurrent_extract_matches_sequential_written() {
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_concurrent_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let data = (0..1001u32).map(|i| (i % 251) as u8).collect::<Vec<u8>>();
+    #[cfg(feature = "fmt-mbl")]
+    {
+        let path = root.join("input.mbl");
+        std::fs::write(
+            &path,
+            mbl_archive(
+                2,
+                0x20,
+                &[
+                    ("bg\\BG01.bmp", data.clone()),
+                    ("start.txt", b"Hello\n".to_vec()),
+                ],
+            ),
+        )
+        .unwrap();
+        assert_concurrent_extract_matches(&path);
+    }
+    #[cfg(feature = "fmt-esc-arc2")]
+    {
+        use akaibu::scheme::pack::{PackFormat, PackInput};
+        use std::path::PathBuf;
+
+        let path = root.join("input.bin");
+        let mut buf = Vec::new();
+        PackFormat::EscArc2
+            .scheme()
+            .write_archive(
+                vec![
+                    PackInput {
+                        full_path: PathBuf::from("bg/BG01.png"),
+                        contents: data,
+                    },
+                    PackInput {
+                        full_path: PathBuf::from("script/start.txt"),
+                        contents: b"Hello\n".to_vec(),
+                    },
+                ],
+                &mut buf,
+            )
+            .unwrap();
+        std::fs::write(&path, buf).unwrap();
+        assert_concurrent_extract_matches(&path);
+    }
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+#[cfg(feature = "fmt-pf8")]
+fn read_ahead_extracts_contiguous_entries() {
+    use akaibu::{pipeline::extract_entries, progress::ProgressTracker};
+
+    let output_dir = std::env::temp_dir()
+        .join(format!("akaibu_read_ahead_{}", std::process::id()));
+    let scheme = magic::Archive::Pf8.get_schemes().remove(0);
+    let handle =
+        ArchiveHandle::open(scheme.as_ref(), &fixture("pf8/input.pf8"))
+            .expect("Could not open fixture archive");
+    let entries = handle.dir().files().cloned().collect::<Vec<_>>();
+    extract_entries(
+        handle.archive(),
+        &entries,
+        &output_dir,
+        &ProgressTracker::new(&entries),
+        None,
+        &ExtractOptions::default(),
+    )
+    .expect("Could not extract entries");
+    let readme = std::fs::read(output_dir.join("readme.txt"));
+    std::fs::remove_dir_all(&output_dir).unwrap();
+
+    assert_eq!(
+        readme.expect("Entry was not written"),
+        std::fs::read(fixture("pf8/expected/readme.txt")).unwrap()
+    );
+}
+
+#[test]
+#[cfg(feature = "fmt-tlg")]
+fn carve_resources_by_magic() {
+    let png = std::fs::read(fixture("tlg/tlg5_rgb.png")).unwrap();
+    let tlg5 = std::fs::read(fixture("tlg/tlg5_rgba.tlg")).unwrap();
+    let tlg6 = std::fs::read(fixture("tlg/input.tlg")).unwrap();
+    let mut wav = b"RIFF".to_vec();
+    wav.extend_from_slice(&12u32.to_le_bytes());
+    wav.extend_from_slice(b"WAVEdata\0\0\0\0");
+    // Single page marked as first and last of stream
+    let mut ogg = b"OggS\0\x06".to_vec();
+    ogg.extend_from_slice(&[0; 20]);
+    ogg.extend_from_slice(&[1, 3]);
+    ogg.extend_from_slice(b"abc");
+
+    let mut blob = vec![0; 100];
+    let mut expected = Vec::new();
+    for file in [&png, &wav, &tlg5, &ogg, &tlg6].iter() {
+        blob.extend_from_slice(file);
+        blob.extend_from_slice(&[0; 37]);
+        expected.push(file.to_vec());
+    }
+    // TLG6 end is not known, it takes rest of file
+    expected.last_mut().unwrap().extend_from_slice(&[0; 37]);
+
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_carve_{}", std::process::id()));
+    let blob_path = root.join("unknown.bin");
+    let output_dir = root.join("out");
+    std::fs::create_dir_all(&root).unwrap();
+    std::fs::write(&blob_path, &blob).unwrap();
+    let carved = akaibu::carve::carve(&blob_path, &output_dir)
+        .expect("Could not carve blob");
+    let written = carved
+        .iter()
+        .map(|file| std::fs::read(output_dir.join(&file.file_name)).unwrap())
+        .collect::<Vec<Vec<u8>>>();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(
+        carved
+            .iter()
+            .map(|file| file.file_name.as_str())
+            .collect::<Vec<&str>>(),
+        [
+            "00000.png",
+            "00001.wav",
+            "00002.tlg",
+            "00003.ogg",
+            "00004.tlg"
+        ]
+    );
+    assert_eq!(written, expected);
+}
+
+#[test]
+fn write_atomic_removes_partial_file() {
+    let root = std::env::temp_dir()
+        .join(format!("akaibu_atomic_{}", std::process::id()));
+    std::fs::create_dir_all(&root).unwrap();
+    let output = root.join("entry.png");
+    let result = akaibu::util::write_atomic_with(&output, |file| {
+        use std::io::Write;
+        file.write_all(b"\x89PNG")?;
+        anyhow::bail!("Entry cut short")
+    });
+    let leftover = std::fs::read_dir(&root).unwrap().count();
+    std::fs::remove_dir_all(&root).unwrap();
+
+    assert!(result.is_err());
+    assert_eq!(leftover, 0);
+}
@@ -0,0 +1,183 @@
+use akaibu::{archive::FileEntry, util::text::display_name};
+use anyhow::Context;
+use rayon::prelude::*;
+use regex::{Regex, RegexBuilder};
+use std::path::Path;
+
+/// Entries selected by --include and --exclude patterns, matched against
+/// path inside archive with `/` separators
+#[derive(Debug, Clone)]
+pub(crate) struct EntryFilter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+#[derive(Debug, Clone)]
+struct Pattern {
+    regex: Regex,
+    /// Glob without `/` is matched against file name only, so `*.png`
+    /// selects images in every directory
+    file_name_only: bool,
+}
+
+impl EntryFilter {
+    /// Patterns are globs unless `regex` is set
+    pub(crate) fn new(
+        include: &[String],
+        exclude: &[String],
+        regex: bool,
+    ) -> anyhow::Result<Self> {
+        let parse = |patterns: &[String]| {
+            patterns
+                .iter()
+                .map(|pattern| Pattern::new(pattern, regex))
+                .collect::<anyhow::Result<Vec<Pattern>>>()
+        };
+        Ok(Self {
+            include: parse(include)?,
+            exclude: parse(exclude)?,
+        })
+    }
+    pub(crate) fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+    fn matches(&self, path: &Path) -> bool {
+        let path = display_name(path).to_string().replace('\\', "/");
+        (self.include.is_empty()
+            || self.include.iter().any(|pattern| pattern.matches(&path)))
+            && !self.exclude.iter().any(|pattern| pattern.matches(&path))
+    }
+    /// Entries passing filter, in original order
+    pub(crate) fn apply(&self, entries: Vec<FileEntry>) -> Vec<FileEntry> {
+        entries
+            .into_par_iter()
+            .filter(|entry| self.matches(&entry.full_path))
+            .collect()
+    }
+}
+
+impl Pattern {
+    fn new(pattern: &str, is_regex: bool) -> anyhow::Result<Self> {
+        let (source, file_name_only) = if is_regex {
+            (pattern.to_string(), false)
+        } else {
+            (glob_to_regex(pattern), !pattern.contains('/'))
+        };
+        let regex = RegexBuilder::new(&source)
+            // Engines mix case of names freely
+            .case_insensitive(!is_regex)
+            .build()
+            .with_context(|| format!("Invalid pattern: {}", pattern))?;
+        Ok(Self {
+            regex,
+            file_name_only,
+        })
+    }
+    fn matches(&self, path: &str) -> bool {
+        if self.file_name_only {
+            let file_name = path.rsplit('/').next().unwrap_or(path);
+            self.regex.is_match(file_name)
+        } else {
+            self.regex.is_match(path)
+        }
+    }
+}
+
+/// Anchored regex of glob. `*` and `?` do not cross `/`, `**` does and
+/// `[...]` is kept as character class.
+fn glob_to_regex(glob: &str) -> String {
+    let mut regex = String::from("^");
+    let mut chars = glob.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '*' if chars.peek() == Some(&'*') => {
+                chars.next();
+                // `**/` also matches no directory at all
+                if chars.peek() == Some(&'/') {
+                    chars.next();
+                    regex.push_str("(?:.*/)?");
+                } else {
+                    regex.push_str(".*");
+                }
+            }
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            '[' => {
+                regex.push('[');
+                if chars.peek() == Some(&'!') {
+                    chars.next();
+                    regex.push('^');
+                }
+                for c in chars.by_ref() {
+                    if c == ']' {
+                        break;
+                    }
+                    if c == '\\' || c == '[' {
+                        regex.push('\\');
+                    }
+                    regex.push(c);
+                }
+                regex.push(']');
+            }
+            c => regex.push_str(&regex::escape(&c.to_string())),
+        }
+    }
+    regex.push('$');
+    regex
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glob_matches(glob: &str, path: &str) -> bool {
+        Regex::new(&glob_to_regex(glob)).unwrap().is_match(path)
+    }
+
+    #[test]
+    fn star_does_not_cross_directories() {
+        assert_eq!(glob_to_regex("bg/*.png"), r"^bg/[^/]*\.png$");
+        assert!(glob_matches("bg/*.png", "bg/BG01.png"));
+        assert!(!glob_matches("bg/*.png", "bg/sub/BG01.png"));
+        assert!(glob_matches("bg/BG0?.png", "bg/BG01.png"));
+        assert!(!glob_matches("bg/BG0?.png", "bg/BG0/.png"));
+    }
+
+    #[test]
+    fn double_star_crosses_directories() {
+        assert!(glob_matches("**/*.png", "BG01.png"));
+        assert!(glob_matches("**/*.png", "cg/ev/BG01.png"));
+        assert!(glob_matches("cg/**", "cg/ev/BG01.png"));
+        assert!(!glob_matches("cg/**/*.png", "bg/cg/BG01.png"));
+    }
+
+    #[test]
+    fn character_classes() {
+        assert!(glob_matches("ev[0-9].png", "ev1.png"));
+        assert!(!glob_matches("ev[!0-9].png", "ev1.png"));
+        assert!(glob_matches("ev[!0-9].png", "evA.png"));
+        assert_eq!(glob_to_regex(r"[\[]"), r"^[\\\[]$");
+    }
+
+    #[test]
+    fn special_characters_are_escaped() {
+        assert!(glob_matches("a+b (1).txt", "a+b (1).txt"));
+        assert!(!glob_matches("a.txt", "abtxt"));
+    }
+
+    #[test]
+    fn glob_without_slash_matches_file_name() {
+        let filter =
+            EntryFilter::new(&["*.PNG".to_string()], &[], false).unwrap();
+        assert!(filter.matches(Path::new("cg/ev/BG01.png")));
+        let filter = EntryFilter::new(
+            &["cg/**".to_string()],
+            &["*.txt".to_string()],
+            false,
+        )
+        .unwrap();
+        assert!(filter.matches(Path::new("cg/ev/BG01.png")));
+        assert!(!filter.matches(Path::new("cg/readme.txt")));
+        assert!(!filter.matches(Path::new("bg/BG01.png")));
+    }
+}
@@ -7,30 +7,78 @@
     missing_debug_implementations
 )]
 
+mod filter;
+mod table;
+
 use akaibu::{
-    archive::FileEntry,
-    magic::Archive,
-    resource::{ResourceMagic, ResourceScheme},
-    scheme::Scheme,
+    archive::{self, FileEntry},
+    carve,
+    dump::{self, ArchiveSummary},
+    error::{self, AkaibuError, EntryFailure},
+    gallery::{self, GalleryFormat},
+    input::InputSource,
+    inspect,
+    key_file::KeyFile,
+    magic::{self, Archive},
+    manifest::Manifest,
+    overlay::{self, OverlayArchive},
+    patch,
+    pipeline::{self, ArchiveHandle, ExtractOptions},
+    progress::ProgressTracker,
+    report::{self, MissingKeyReport},
+    resource::{
+        tlg::{TlgScheme, TlgVersion},
+        ContentKind, ImageFormat, ResourceMagic, ResourceScheme, ResourceType,
+    },
+    scheme::{pack::PackFormat, registry::SchemeRegistry, Scheme},
+    util::{
+        self, bounds,
+        script::ScriptEngine,
+        text::{self, display_name},
+    },
+    validate,
 };
 use anyhow::Context;
 use colored::*;
-use indicatif::{ParallelProgressIterator, ProgressBar, ProgressStyle};
+use filter::EntryFilter;
+use indicatif::{
+    HumanBytes, ParallelProgressIterator, ProgressBar, ProgressStyle,
+};
 use rayon::prelude::*;
 use std::{
-    fs::File,
-    io::{Read, Seek, SeekFrom},
+    collections::{BTreeMap, HashMap},
+    io::Write,
     path::{Path, PathBuf},
+    sync::Mutex,
 };
-use structopt::StructOpt;
+use structopt::{clap::AppSettings, StructOpt};
+use table::{Align, Table};
 
 #[derive(StructOpt, Debug)]
-#[structopt()]
+#[structopt(
+    setting = AppSettings::SubcommandsNegateReqs,
+    setting = AppSettings::DisableVersion
+)]
 struct Opt {
-    /// Files to process
-    #[structopt(required = true, name = "ARCHIVES", parse(from_os_str))]
+    #[structopt(subcommand)]
+    command: Option<Command>,
+
+    /// Files to process, - reads archive from standard input
+    #[structopt(
+        required_unless = "version",
+        name = "ARCHIVES",
+        parse(from_os_str)
+    )]
     files: Vec<PathBuf>,
 
+    /// Prints version information
+    #[structopt(short = "V", long)]
+    version: bool,
+
+    /// With --version also print enabled formats, number of schemes and hashes of key databases, attach it to issue reports
+    #[structopt(long)]
+    verbose: bool,
+
     /// Directory to output extracted files
     #[structopt(
         short = "o",
@@ -43,47 +91,423 @@ struct Opt {
     /// Convert resource files to commonly used formats only one try of resource can converted at the time
     #[structopt(short, long)]
     convert: bool,
+
+    /// Encode PNG images back into TLG files of given version into output directory, e.g. to pack edited images into archive. Only tlg5 can be written
+    #[structopt(long)]
+    to_tlg: Option<TlgVersion>,
+
+    /// Write text entries and resources as UTF-8 without BOM and with LF line endings instead of their original bytes
+    #[structopt(long)]
+    normalize_text: bool,
+
+    /// Key file for archives that need per-install key (raw key dump or registry export). Defaults to key.dat next to archive
+    #[structopt(long, parse(from_os_str))]
+    key_file: Option<PathBuf>,
+
+    /// Password for archives protected by user supplied password
+    #[structopt(long)]
+    password: Option<String>,
+
+    /// Write report about archives that could not be extracted because of missing key to given JSON file. Attach it to issue to request support
+    #[structopt(long, parse(from_os_str))]
+    report: Option<PathBuf>,
+
+    /// Extract only entries of given kind detected by their contents: images, scripts or other
+    #[structopt(long)]
+    only: Option<ContentKind>,
+
+    /// Write manifest with outcome and storage details of each entry next to extracted files
+    #[structopt(long)]
+    manifest: bool,
+
+    /// Write mapping of each entry to files written for it, with format and dimensions, for re-importing edited files
+    #[structopt(long)]
+    mapping: bool,
+
+    /// Open original archive kept next to each archive as <archive>.bak too and extract both merged, entries of updated archive replace original ones and are flagged in manifest
+    #[structopt(long)]
+    merge_backup: bool,
+
+    /// Set modification time of extracted files to one stored in archive, when format stores it
+    #[structopt(long)]
+    preserve_timestamps: bool,
+
+    /// Skip entries smaller than given size in bytes, K, M and G suffixes are accepted
+    #[structopt(long, parse(try_from_str = parse_size))]
+    min_file_size: Option<u64>,
+
+    /// Skip entries larger than given size in bytes, K, M and G suffixes are accepted
+    #[structopt(long, parse(try_from_str = parse_size))]
+    max_file_size: Option<u64>,
+
+    /// Refuse to decompress resources whose stored size is over given limit, K, M and G suffixes are accepted. Defaults to 1G
+    #[structopt(long, parse(try_from_str = parse_size))]
+    max_output_size: Option<u64>,
+
+    /// Extract all archives directly into output directory instead of subdirectory named after each archive
+    #[structopt(long)]
+    no_subdirs: bool,
+
+    /// Rename entries written as stored, like audio without extension, to canonical extension of their detected format. Entries without extension also get one of detected engine format, like tlg, or txt for text
+    #[structopt(long)]
+    fix_extensions: bool,
+
+    /// Write entries into images, scripts, audio or other folder of output directory by their detected type, keeping archive paths inside it
+    #[structopt(long)]
+    group_by_type: bool,
+
+    /// Embed loops of KiriKiri .sli files into OGG and WAV entries they belong to, as LOOPSTART and LOOPLENGTH comments and smpl chunk
+    #[structopt(long)]
+    embed_loops: bool,
+
+    /// Remove LOOPSTART and LOOPLENGTH comments from OGG and smpl chunk from WAV entries written as stored, for players that would loop them forever. Sounds getting loop of --embed-loops are not stripped
+    #[structopt(long)]
+    strip_loops: bool,
+
+    /// When no scheme recognizes archive, scan it for PNG, OGG, TLG and RIFF files and write them as numbered files instead of asking for scheme
+    #[structopt(long)]
+    carve: bool,
+
+    /// Decode SHIFT-JIS scripts to UTF-8 text, keeping control codes of given engine intact: plain, buriko or silky
+    #[structopt(long)]
+    reencode_scripts: Option<ScriptEngine>,
+
+    /// Write name of source archive, path of entry and akaibu version into text chunks of converted PNG images, to trace files of shared dumps
+    #[structopt(long)]
+    stamp_metadata: bool,
+
+    /// Extract only entries whose path inside archive matches glob, can be given multiple times. Glob without / matches file name in any directory, ** crosses directories
+    #[structopt(long, number_of_values = 1)]
+    include: Vec<String>,
+
+    /// Skip entries whose path inside archive matches glob, can be given multiple times
+    #[structopt(long, number_of_values = 1)]
+    exclude: Vec<String>,
+
+    /// Treat --include and --exclude patterns as case sensitive regular expressions instead of globs
+    #[structopt(long)]
+    regex: bool,
+
+    /// Keep extracting remaining entries when one fails and print all failures at the end, instead of stopping at first one
+    #[structopt(long)]
+    keep_going: bool,
+
+    /// Format of converted images: png or bmp. BMP is written uncompressed, much faster for large image sets
+    #[structopt(long, default_value = "png")]
+    image_format: ImageFormat,
+
+    /// Downscale converted images so their larger side is at most given number of pixels, for previews and lighter galleries
+    #[structopt(long)]
+    max_dimension: Option<u32>,
+
+    /// Instead of extracting, bundle all converted images of each archive into single cbz or pdf file in output directory
+    #[structopt(long)]
+    gallery: Option<GalleryFormat>,
+
+    /// List entries of archives with their size, offset, type and storage details instead of extracting them
+    #[structopt(long)]
+    list: bool,
+
+    /// Print --list output as JSON array of archives with their entries, for piping into other tools
+    #[structopt(long, conflicts_with = "csv")]
+    json: bool,
+
+    /// Print --list output as CSV with one row per entry, for piping into other tools
+    #[structopt(long)]
+    csv: bool,
+
+    /// Show number and size of entries of archives grouped by extension instead of extracting them
+    #[structopt(long)]
+    stats: bool,
+
+    /// Print tables without colors
+    #[structopt(long)]
+    no_color: bool,
+
+    /// Continue interrupted extraction, files matching checksum recorded in manifest are not extracted again
+    #[structopt(long)]
+    resume: bool,
+
+    /// Before extracting, check that data of every entry lies inside archive and does not overlap other entries, and refuse to extract archive with broken index
+    #[structopt(long)]
+    strict: bool,
+}
+
+/// Parse size like `512`, `64K` or `10M`
+fn parse_size(size: &str) -> Result<u64, AkaibuError> {
+    let size = size.trim();
+    let (number, multiplier) =
+        match size.chars().last().map(|c| c.to_ascii_uppercase()) {
+            Some('K') => (&size[..size.len() - 1], 1 << 10),
+            Some('M') => (&size[..size.len() - 1], 1 << 20),
+            Some('G') => (&size[..size.len() - 1], 1 << 30),
+            _ => (size, 1),
+        };
+    number
+        .trim()
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(multiplier))
+        .ok_or_else(|| AkaibuError::Custom(format!("Invalid size: {}", size)))
+}
+
+#[derive(StructOpt, Debug)]
+enum Command {
+    /// List entries of archives, same as --list. - reads archive from standard input
+    List {
+        /// Archives to list
+        #[structopt(required = true, parse(from_os_str))]
+        archives: Vec<PathBuf>,
+        /// Print as JSON array of archives with their entries
+        #[structopt(long, conflicts_with = "csv")]
+        json: bool,
+        /// Print as CSV with one row per entry
+        #[structopt(long)]
+        csv: bool,
+    },
+    /// Write single extracted entry to stdout without converting it
+    Cat {
+        /// Archive to read entry from
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+        /// Path of entry inside archive
+        #[structopt(parse(from_os_str))]
+        entry: PathBuf,
+    },
+    /// Rank every scheme by how likely it is to read given file
+    Probe {
+        /// File to check
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+        /// Show schemes that were not tested too
+        #[structopt(long)]
+        all: bool,
+    },
+    /// Print parsed headers of archive or resource as JSON without extracting anything
+    Inspect {
+        /// File to inspect
+        #[structopt(parse(from_os_str))]
+        file: PathBuf,
+    },
+    /// Extract and convert every archive found in game directory into output directory, with summary.json of outcome
+    Dump {
+        /// Game install directory
+        #[structopt(parse(from_os_str))]
+        game_dir: PathBuf,
+    },
+    /// Pack directory into archive of given format: pf8, gxp, buriko, esc-arc2 or gdl. Files are stored as they are, e.g. to rebuild archive with edited entries. Buriko and gdl archives have no directories, gdl names are limited to 12 bytes
+    Pack {
+        /// Format of archive to write
+        format: PackFormat,
+        /// Directory with files to pack
+        #[structopt(parse(from_os_str))]
+        input_dir: PathBuf,
+        /// Archive to write
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+    },
+    /// Copy files changed since extraction into patch bundle, comparing them with checksums of manifest written by --manifest
+    MakePatch {
+        /// Manifest of extracted archive
+        #[structopt(parse(from_os_str))]
+        manifest: PathBuf,
+        /// Directory with extracted and modified files
+        #[structopt(parse(from_os_str))]
+        extracted_dir: PathBuf,
+        /// Directory to write bundle into
+        #[structopt(parse(from_os_str))]
+        bundle: PathBuf,
+    },
+    /// Rebuild archive of format supported by pack with files of patch bundle replacing its entries
+    ApplyPatch {
+        /// Patch bundle made by make-patch
+        #[structopt(parse(from_os_str))]
+        bundle: PathBuf,
+        /// Original archive
+        #[structopt(parse(from_os_str))]
+        archive: PathBuf,
+        /// Patched archive to write
+        #[structopt(parse(from_os_str))]
+        output: PathBuf,
+    },
 }
 
 fn main() {
     env_logger::init();
-    let opt = Opt::from_args();
+    let mut opt = Opt::from_args();
+    if opt.version {
+        if opt.verbose {
+            print!("{}", akaibu::build_info());
+        } else {
+            println!("akaibu_cli {}", env!("CARGO_PKG_VERSION"));
+        }
+        return;
+    }
+    if let Some(size) = opt.max_output_size {
+        bounds::set_max_output_size(size as usize);
+    }
+    if opt.no_color {
+        colored::control::set_override(false);
+    }
+    // Broken key table is reported before any archive is opened, only
+    // schemes using it fail
+    for err in SchemeRegistry::global().validate() {
+        log::warn!("{}, schemes using it are disabled", err);
+    }
+    // `list` is spelled out form of --list, its archives are opened the same
+    // way so - is read the same too
+    if let Some(Command::List {
+        archives,
+        json,
+        csv,
+    }) = &mut opt.command
+    {
+        opt.files = std::mem::take(archives);
+        opt.list = true;
+        opt.json |= *json;
+        opt.csv |= *csv;
+        opt.command = None;
+    }
+    // Checked after `list` is rewritten, so its format options count too
+    if (opt.json || opt.csv) && !opt.list {
+        structopt::clap::Error::with_description(
+            "--json and --csv require --list",
+            structopt::clap::ErrorKind::MissingRequiredArgument,
+        )
+        .exit();
+    }
+    if opt.json && opt.csv {
+        structopt::clap::Error::with_description(
+            "--json can not be used with --csv",
+            structopt::clap::ErrorKind::ArgumentConflict,
+        )
+        .exit();
+    }
+    // Archive piped only to be listed is listed while it is read, when its
+    // scheme allows it
+    let streamed = opt.command.is_none()
+        && (opt.list || opt.stats)
+        && !opt.convert
+        && opt.to_tlg.is_none()
+        && opt.key_file.is_none()
+        && opt.password.is_none()
+        && !opt.merge_backup;
+    // Spooled copies of standard input live until archives are processed
+    let inputs = match opt
+        .files
+        .iter()
+        .map(|file| {
+            if streamed {
+                InputSource::open_listed(file)
+            } else {
+                InputSource::open(file)
+            }
+        })
+        .collect::<anyhow::Result<Vec<InputSource>>>()
+    {
+        Ok(inputs) => inputs,
+        Err(err) => {
+            log::error!("Could not read input: {}", err);
+            return;
+        }
+    };
+    opt.files = inputs
+        .iter()
+        .map(|input| input.path().to_path_buf())
+        .collect();
+    let reports = Mutex::new(Vec::new());
 
-    match if opt.convert {
-        convert_resource(&opt)
-    } else {
-        extract_archive(&opt)
+    match match &opt.command {
+        Some(Command::List { .. }) => unreachable!("Replaced by --list"),
+        Some(Command::Cat { archive, entry }) => {
+            cat_entry(&opt, archive, entry)
+        }
+        Some(Command::Probe { file, all }) => probe_file(file, *all),
+        Some(Command::Inspect { file }) => inspect_file(file),
+        Some(Command::Dump { game_dir }) => dump_game(&opt, game_dir),
+        Some(Command::Pack {
+            format,
+            input_dir,
+            archive,
+        }) => pack_archive(*format, input_dir, archive),
+        Some(Command::MakePatch {
+            manifest,
+            extracted_dir,
+            bundle,
+        }) => make_patch(manifest, extracted_dir, bundle),
+        Some(Command::ApplyPatch {
+            bundle,
+            archive,
+            output,
+        }) => apply_patch(&opt, bundle, archive, output),
+        None if opt.convert => convert_resource(&opt, &reports),
+        None if opt.to_tlg.is_some() => encode_tlg(&opt),
+        None if opt.list || opt.stats => list_archives(&opt, &inputs),
+        None => extract_archive(&opt, &reports),
     } {
         Ok(_) => (),
-        Err(err) => log::error!("Error while extracting: {}", err),
+        Err(err) => {
+            log::error!("Error while extracting: {}", error::describe(&err))
+        }
+    }
+
+    if let Some(report_path) = &opt.report {
+        let reports = reports.into_inner().expect("Report lock poisoned");
+        match report::write_reports(report_path, &reports) {
+            Ok(_) if !reports.is_empty() => eprintln!(
+                "{}",
+                format!(
+                    "Missing key report for {} file(s) written to {:?}",
+                    reports.len(),
+                    report_path
+                )
+                .yellow()
+            ),
+            Ok(_) => (),
+            Err(err) => log::error!("Could not write report: {}", err),
+        }
+    }
+}
+
+/// Remember failed file if it failed because of missing key
+fn record_missing_key(
+    reports: &Mutex<Vec<MissingKeyReport>>,
+    file: &Path,
+    err: &anyhow::Error,
+) {
+    match MissingKeyReport::from_error(file, err) {
+        Ok(Some(report)) => {
+            reports.lock().expect("Report lock poisoned").push(report)
+        }
+        Ok(None) => (),
+        Err(err) => {
+            log::error!("Could not create report for {:?}: {}", file, err)
+        }
     }
 }
 
-fn convert_resource(opt: &Opt) -> anyhow::Result<()> {
+fn convert_resource(
+    opt: &Opt,
+    reports: &Mutex<Vec<MissingKeyReport>>,
+) -> anyhow::Result<()> {
     let not_universal = opt.files.iter().find(|f| {
-        let mut magic = vec![0; 16];
-        File::open(&f)
+        let sniffed = magic::sniff(&f)
             .map_err(|e| {
-                log::error!("Could not find file: {:?}. {}", f, e);
+                log::error!("Could not read file: {:?}. {}", f, e);
                 e
             })
-            .expect("Could not open file")
-            .read_exact(&mut magic)
             .expect("Could not read file");
-        let resource = ResourceMagic::parse_magic(&magic);
+        let resource = ResourceMagic::parse_magic(&sniffed.head);
         !resource.is_universal()
     });
     let scheme = if let Some(file) = not_universal {
-        let mut magic = vec![0; 16];
-        File::open(&file)?.read_exact(&mut magic)?;
-        let resource = ResourceMagic::parse_magic(&magic);
+        let resource = ResourceMagic::parse_magic(&magic::sniff(&file)?.head);
         let mut schemes = resource.get_schemes();
         schemes.remove(prompt_for_resource_scheme(&schemes, &file))
     } else {
         let file = opt.files.get(0).expect("Could not get first file");
-        let mut magic = vec![0; 16];
-        File::open(&file)?.read_exact(&mut magic)?;
-        let mut resource = ResourceMagic::parse_magic(&magic);
+        let mut resource =
+            ResourceMagic::parse_magic(&magic::sniff(&file)?.head);
         if let ResourceMagic::Unrecognized = resource {
             resource = ResourceMagic::parse_file_extension(&file);
         }
@@ -96,8 +520,9 @@ fn convert_resource(opt: &Opt) -> anyhow::Result<()> {
             let mut schemes = ResourceMagic::get_all_schemes();
             schemes.remove(prompt_for_resource_scheme(&schemes, &file))
         } else {
-            let mut schemes = resource.get_schemes();
-            schemes.remove(0)
+            resource.get_schemes().into_iter().next().with_context(|| {
+                format!("{:?} files are not converted", resource)
+            })?
         }
     };
 
@@ -112,91 +537,881 @@ fn convert_resource(opt: &Opt) -> anyhow::Result<()> {
         .filter(|file| file.is_file())
         .try_for_each(|file| {
             log::debug!("Converting: {:?}", file);
-            match scheme.convert(&file) {
-                Ok(resource) => resource.write_resource(file),
+            match pipeline::convert_file(scheme.as_ref(), &file) {
+                Ok(ResourceType::Text(_)) if !opt.normalize_text => {
+                    log::debug!("Keeping original text: {:?}", file);
+                    let output_path = file.with_extension("txt");
+                    if &output_path != file {
+                        std::fs::copy(file, &output_path)?;
+                    }
+                    Ok(())
+                }
+                Ok(resource) => resource
+                    .write_resource_as(file, opt.image_format)
+                    .map(|_| ()),
                 Err(err) => {
-                    log::error!("Error while converting: {:?} {}", file, err);
+                    log::error!(
+                        "Error while converting: {:?} {}",
+                        file,
+                        error::describe(&err)
+                    );
+                    record_missing_key(reports, file, &err);
                     Ok(())
                 }
             }
         })
 }
 
-fn extract_archive(opt: &Opt) -> anyhow::Result<()> {
-    opt.files
-        .iter()
+fn encode_tlg(opt: &Opt) -> anyhow::Result<()> {
+    let version = opt.to_tlg.context("TLG version not given")?;
+    std::fs::create_dir_all(&opt.output_dir)?;
+    let progress_bar =
+        init_progressbar("Encoding...".to_string(), opt.files.len() as u64);
+    let encoded = opt
+        .files
+        .par_iter()
+        .progress_with(progress_bar)
         .filter(|file| file.is_file())
-        .try_for_each(|file| {
-            let mut magic = vec![0; 32];
-            File::open(&file)?.read_exact(&mut magic)?;
-
-            let mut archive_magic = Archive::parse(&magic);
-            if let Archive::NotRecognized = archive_magic {
-                let mut magic = vec![0; 32];
-                let mut f = File::open(&file)?;
-                f.seek(SeekFrom::End(-32))?;
-                f.read_exact(&mut magic)?;
-                archive_magic = Archive::parse_end(&magic);
-            };
-            log::debug!("Archive: {:?}", archive_magic);
-            let schemes = if let Archive::NotRecognized = archive_magic {
-                println!(
-                    "{}",
-                    "Archive type could not be guessed. Please enter scheme manually:"
-                        .yellow()
+        .map(|file| match encode_tlg_file(opt, file, version) {
+            Ok(_) => 1,
+            Err(err) => {
+                log::error!(
+                    "Error while encoding: {:?} {}",
+                    file,
+                    error::describe(&err)
                 );
-                Archive::get_all_schemes()
-            } else {
-                archive_magic.get_schemes()
+                0
+            }
+        })
+        .sum::<usize>();
+    println!(
+        "{}",
+        format!("Encoded {} image(s) into {:?}", encoded, opt.output_dir)
+            .green()
+    );
+    Ok(())
+}
+
+fn encode_tlg_file(
+    opt: &Opt,
+    file: &Path,
+    version: TlgVersion,
+) -> anyhow::Result<()> {
+    log::debug!("Encoding: {:?}", file);
+    let image = image::open(file)?.to_rgba8();
+    let file_name = file.file_name().context("Could not get file name")?;
+    let output_path = opt
+        .output_dir
+        .join(Path::new(file_name).with_extension("tlg"));
+    util::write_atomic(&output_path, &TlgScheme::encode(&image, version)?)
+}
+
+fn extract_archive(
+    opt: &Opt,
+    reports: &Mutex<Vec<MissingKeyReport>>,
+) -> anyhow::Result<()> {
+    // Unrecognized archives are carved without asking for scheme
+    let (carved, files): (Vec<&PathBuf>, Vec<&PathBuf>) = opt
+        .files
+        .iter()
+        .filter(|file| file.is_file() && !is_merged_backup(opt, file))
+        .partition(|file| opt.carve && is_unrecognized(file));
+    // Ask for all schemes before any progress bar is drawn
+    let selected = files
+        .into_iter()
+        .filter_map(|file| match select_archive_scheme(file) {
+            Ok(scheme) => Some((file, scheme)),
+            Err(err) => {
+                log::error!("{:?}: {}", file, error::describe(&err));
+                None
+            }
+        })
+        .collect::<Vec<_>>();
+
+    let filter = EntryFilter::new(&opt.include, &opt.exclude, opt.regex)?;
+    let mut failed = 0;
+    let mut rejected = Vec::new();
+    selected.into_iter().try_for_each(|(file, scheme)| {
+        let (archive, dir) = match open_with_scheme(opt, scheme.as_ref(), &file)
+        {
+            Ok(archive) => archive,
+            Err(err) => {
+                log::error!("{:?}: {}", file, error::describe(&err));
+                record_missing_key(reports, file, &err);
+                return Ok(());
+            }
+        };
+        if opt.strict {
+            let entries = dir.files().cloned().collect::<Vec<FileEntry>>();
+            if let Err(err) = validate_archive(file, &archive, &entries) {
+                log::error!("{}", error::describe(&err));
+                rejected.push(file);
+                return Ok(());
+            }
+        }
+        if let Some(format) = opt.gallery {
+            return write_gallery(opt, file, archive, dir, format);
+        }
+        let files = dir.files().cloned().collect::<Vec<FileEntry>>();
+        // Names are matched first, so --only reads only entries that passed
+        let files = if filter.is_empty() {
+            files
+        } else {
+            let total = files.len();
+            let files = filter.apply(files);
+            log::info!(
+                "{:?}: {} of {} entries match",
+                file,
+                files.len(),
+                total
+            );
+            files
+        };
+        let files = match opt.only {
+            Some(kind) => pipeline::filter_entries(&archive, &files, kind),
+            None => files,
+        };
+        let progress_bar = init_progressbar(
+            format!("Extracting: {:?}", file),
+            files.len() as u64,
+        );
+        let bar = progress_bar.clone();
+        let progress =
+            ProgressTracker::new(&files).with_callback(move |progress| {
+                bar.set_position(progress.entries_done);
+                bar.set_message(format!(
+                    "{}/s",
+                    HumanBytes(progress.bytes_per_second() as u64)
+                ));
+            });
+
+        let output_dir = pipeline::archive_output_dir(
+            &opt.output_dir,
+            file,
+            !opt.no_subdirs,
+        )?;
+        let manifest = Manifest::new(&file);
+        let manifest_path = manifest.default_path(&output_dir);
+        let resume = if opt.resume {
+            Manifest::load_completed(&manifest_path)?
+        } else {
+            HashMap::new()
+        };
+        let manifest = if opt.manifest || opt.resume {
+            Some(manifest.with_journal(&manifest_path)?)
+        } else if opt.mapping {
+            Some(manifest)
+        } else {
+            None
+        };
+        let result = pipeline::extract_entries(
+            &archive,
+            &files,
+            &output_dir,
+            &progress,
+            manifest.as_ref(),
+            &ExtractOptions {
+                preserve_timestamps: opt.preserve_timestamps,
+                min_file_size: opt.min_file_size,
+                max_file_size: opt.max_file_size,
+                resume,
+                image_format: opt.image_format,
+                fix_extensions: opt.fix_extensions,
+                mapping: opt.mapping,
+                password: opt.password.clone(),
+                group_by_type: opt.group_by_type,
+                embed_loops: opt.embed_loops,
+                strip_loops: opt.strip_loops,
+                reencode_scripts: opt.reencode_scripts,
+                normalize_text: opt.normalize_text,
+                max_dimension: opt.max_dimension,
+                stamp_source: stamp_source(opt, file),
+                keep_going: opt.keep_going,
+            },
+        );
+        if let Some(manifest) = &manifest {
+            if opt.manifest || opt.resume {
+                manifest.write(&manifest_path)?;
+            }
+            if opt.mapping {
+                manifest.write_mapping(
+                    &manifest.default_mapping_path(&output_dir),
+                )?;
+            }
+        }
+        if let Err(err) = result {
+            let failures = match error::entry_failures(&err) {
+                Some(failures) if opt.keep_going => failures,
+                _ => return Err(err),
             };
+            progress_bar.finish();
+            print_entry_failures(file, failures);
+            failed += failures.len();
+            return Ok(());
+        }
+        progress_bar.finish();
+        Ok(())
+    })?;
 
-            let scheme = if archive_magic.is_universal() {
-                schemes.get(0).context("Scheme list is empty")?
+    carved
+        .into_iter()
+        .try_for_each(|file| carve_archive(opt, file))?;
+    if !rejected.is_empty() {
+        anyhow::bail!(
+            "{} archive(s) failed strict check and were not extracted: {:?}",
+            rejected.len(),
+            rejected
+        );
+    }
+    if failed > 0 {
+        anyhow::bail!("{} entries could not be extracted", failed);
+    }
+    Ok(())
+}
+
+/// Table of entries skipped with --keep-going
+fn print_entry_failures(file: &Path, failures: &[EntryFailure]) {
+    println!(
+        "{}",
+        format!("{} entries of {:?} failed:", failures.len(), file).red()
+    );
+    let mut table =
+        Table::new(&[("PATH", Align::Left), ("REASON", Align::Left)]);
+    for failure in failures {
+        table.add_row(vec![
+            display_name(&failure.path).to_string(),
+            failure.reason.clone(),
+        ]);
+    }
+    print!("{}", table);
+}
+
+/// Name stamped into converted images with --stamp-metadata
+fn stamp_source(opt: &Opt, file: &Path) -> Option<String> {
+    file.file_name()
+        .map(|name| display_name(Path::new(name)).to_string())
+        .filter(|_| opt.stamp_metadata)
+}
+
+/// Print every problem of archive index and fail before anything is
+/// written, instead of erroring on first bad entry midway through extraction
+#[allow(clippy::borrowed_box)]
+fn validate_archive(
+    file: &Path,
+    archive: &Box<dyn archive::Archive>,
+    entries: &[FileEntry],
+) -> anyhow::Result<()> {
+    let archive_size = match archive.stored_size(file)? {
+        Some(size) => size,
+        None => {
+            eprintln!(
+                "{}",
+                format!(
+                    "{:?}: entries are read from several files, they are not checked",
+                    file
+                )
+                .yellow()
+            );
+            return Ok(());
+        }
+    };
+    let report = validate::validate_index(archive, entries, archive_size);
+    if report.unchecked > 0 {
+        eprintln!(
+            "{}",
+            format!(
+                "{:?}: location of {} entries is not known, they are not checked",
+                file, report.unchecked
+            )
+            .yellow()
+        );
+    }
+    if report.is_valid() {
+        return Ok(());
+    }
+    for problem in &report.problems {
+        eprintln!("{}", format!("{:?}: {}", file, problem).red());
+    }
+    Err(AkaibuError::Custom(format!(
+        "{:?}: index failed strict check with {} problem(s) in {} checked \
+         entries, archive size {:#X}",
+        file,
+        report.problems.len(),
+        report.checked,
+        report.archive_size
+    ))
+    .into())
+}
+
+fn is_unrecognized(file: &Path) -> bool {
+    match magic::sniff(file) {
+        Ok(sniffed) => {
+            matches!(Archive::detect(file, &sniffed), Archive::NotRecognized)
+        }
+        Err(_) => false,
+    }
+}
+
+/// Last resort for archives no scheme reads, resources found by their magic
+/// are written as numbered files
+fn carve_archive(opt: &Opt, file: &Path) -> anyhow::Result<()> {
+    let output_dir =
+        pipeline::archive_output_dir(&opt.output_dir, file, !opt.no_subdirs)?;
+    let carved = carve::carve(file, &output_dir)?;
+    if carved.is_empty() {
+        eprintln!(
+            "{}",
+            format!("{:?}: no known resources found to carve", file).yellow()
+        );
+    } else {
+        println!(
+            "{}",
+            format!(
+                "Carved {} file(s) from {:?} into {:?}",
+                carved.len(),
+                file,
+                output_dir
+            )
+            .green()
+        );
+    }
+    Ok(())
+}
+
+/// Write images of archive ordered by path into `<output>/<archive_stem>.<format>`
+fn write_gallery(
+    opt: &Opt,
+    file: &Path,
+    archive: Box<dyn archive::Archive>,
+    dir: archive::NavigableDirectory,
+    format: GalleryFormat,
+) -> anyhow::Result<()> {
+    std::fs::create_dir_all(&opt.output_dir)?;
+    let output_path = opt.output_dir.join(format!(
+        "{}.{}",
+        file.file_stem()
+            .context("Could not get file name")?
+            .to_string_lossy(),
+        format.extension()
+    ));
+    let handle = ArchiveHandle::new(archive, dir);
+    let pages = gallery::write_gallery(
+        &handle,
+        &output_path,
+        format,
+        &ExtractOptions {
+            min_file_size: opt.min_file_size,
+            max_file_size: opt.max_file_size,
+            max_dimension: opt.max_dimension,
+            ..Default::default()
+        },
+    )?;
+    println!(
+        "{}",
+        format!("Written {} image(s) to {:?}", pages, output_path).green()
+    );
+    Ok(())
+}
+
+fn cat_entry(
+    opt: &Opt,
+    archive_path: &Path,
+    entry_path: &Path,
+) -> anyhow::Result<()> {
+    let input = InputSource::open(archive_path)?;
+    let (archive, dir) = open_archive(opt, input.path())?;
+    let entry_path = PathBuf::from(
+        entry_path
+            .to_string_lossy()
+            .replace("\\", "/")
+            .trim_start_matches('/'),
+    );
+    let entry =
+        dir.get_root_dir().find_file(&entry_path).with_context(|| {
+            format!("Could not find entry: {}", display_name(&entry_path))
+        })?;
+    let stdout = std::io::stdout();
+    let mut handle = stdout.lock();
+    archive.extract_to(entry, &mut handle)?;
+    handle.flush()?;
+    Ok(())
+}
+
+fn dump_game(opt: &Opt, game_dir: &Path) -> anyhow::Result<()> {
+    let progress_bar = ProgressBar::new_spinner();
+    progress_bar.set_prefix(format!("Dumping: {:?}", game_dir));
+    let summary = dump::dump_game(
+        game_dir,
+        &opt.output_dir,
+        &ExtractOptions {
+            preserve_timestamps: opt.preserve_timestamps,
+            min_file_size: opt.min_file_size,
+            max_file_size: opt.max_file_size,
+            image_format: opt.image_format,
+            fix_extensions: opt.fix_extensions,
+            mapping: opt.mapping,
+            password: opt.password.clone(),
+            group_by_type: opt.group_by_type,
+            embed_loops: opt.embed_loops,
+            strip_loops: opt.strip_loops,
+            reencode_scripts: opt.reencode_scripts,
+            normalize_text: opt.normalize_text,
+            max_dimension: opt.max_dimension,
+            stamp_source: stamp_source(opt, game_dir),
+            ..Default::default()
+        },
+        |archive| {
+            progress_bar.println(format_archive_summary(archive));
+            progress_bar.tick();
+        },
+    )?;
+    progress_bar.finish_and_clear();
+    std::fs::create_dir_all(&opt.output_dir)?;
+    let summary_path = opt.output_dir.join(dump::SUMMARY_FILE_NAME);
+    summary.write(&summary_path)?;
+
+    let mut table =
+        Table::new(&[("ARCHIVE TYPE", Align::Left), ("SCHEME", Align::Left)]);
+    for (archive, scheme) in &summary.schemes {
+        table.add_row(vec![archive.clone(), scheme.clone()]);
+    }
+    print!("{}", table);
+    println!(
+        "{}",
+        format!(
+            "Dumped {} archive(s), summary written to {:?}",
+            summary.archives.len(),
+            summary_path
+        )
+        .green()
+    );
+    Ok(())
+}
+
+fn format_archive_summary(archive: &ArchiveSummary) -> String {
+    match &archive.error {
+        Some(err) => format!("{:?}: {}", archive.path, err).red().to_string(),
+        None if archive.failed > 0 => format!(
+            "{:?}: {} entries, {} converted, {} failed",
+            archive.path, archive.entries, archive.converted, archive.failed
+        )
+        .yellow()
+        .to_string(),
+        None => format!(
+            "{:?}: {} entries, {} converted",
+            archive.path, archive.entries, archive.converted
+        ),
+    }
+}
+
+fn pack_archive(
+    format: PackFormat,
+    input_dir: &Path,
+    archive_path: &Path,
+) -> anyhow::Result<()> {
+    let count = format.scheme().pack(input_dir, archive_path)?;
+    println!(
+        "{}",
+        format!("Packed {} file(s) into {:?}", count, archive_path).green()
+    );
+    Ok(())
+}
+
+fn make_patch(
+    manifest_path: &Path,
+    extracted_dir: &Path,
+    bundle_dir: &Path,
+) -> anyhow::Result<()> {
+    let bundle =
+        patch::create_bundle(manifest_path, extracted_dir, bundle_dir)?;
+    for path in &bundle.skipped {
+        eprintln!(
+            "{}",
+            format!(
+                "Skipped converted entry {}, it can not be written back",
+                display_name(path)
+            )
+            .yellow()
+        );
+    }
+    println!(
+        "{}",
+        format!(
+            "Bundled {} changed file(s) into {:?}",
+            bundle.entries.len(),
+            bundle_dir
+        )
+        .green()
+    );
+    Ok(())
+}
+
+fn apply_patch(
+    opt: &Opt,
+    bundle_dir: &Path,
+    archive_path: &Path,
+    output_path: &Path,
+) -> anyhow::Result<()> {
+    // Bundle is read from original archive while output is written
+    if output_path.exists()
+        && std::fs::canonicalize(output_path)?
+            == std::fs::canonicalize(archive_path)?
+    {
+        return Err(AkaibuError::Custom(format!(
+            "Output {:?} is the archive being patched, write it elsewhere",
+            output_path
+        ))
+        .into());
+    }
+    let scheme = select_archive_scheme(archive_path)?;
+    if !scheme.can_pack() {
+        let writable = magic::Archive::get_all_schemes()
+            .iter()
+            .filter(|scheme| scheme.can_pack())
+            .map(|scheme| scheme.get_name())
+            .collect::<Vec<String>>();
+        return Err(AkaibuError::Custom(format!(
+            "{} archives can not be rebuilt, supported schemes: {}",
+            scheme.get_name(),
+            writable.join(", ")
+        ))
+        .into());
+    }
+    let (archive, dir) = open_with_scheme(opt, scheme.as_ref(), archive_path)?;
+    let inputs = patch::apply_bundle(&archive, &dir, bundle_dir)?;
+    let count = inputs.len();
+    util::write_atomic_with(output_path, |file| {
+        let mut output = std::io::BufWriter::new(file);
+        scheme.write_archive(inputs, &mut output)?;
+        Ok(output.flush()?)
+    })?;
+    println!(
+        "{}",
+        format!("Wrote {} entries into {:?}", count, output_path).green()
+    );
+    Ok(())
+}
+
+fn probe_file(file: &Path, all: bool) -> anyhow::Result<()> {
+    let input = InputSource::open(file)?;
+    let results = akaibu::probe(input.path())?;
+    let mut table = Table::new(&[
+        ("CONFIDENCE", Align::Right),
+        ("ENTRIES", Align::Right),
+        ("SCHEME", Align::Left),
+        ("FAILURE", Align::Left),
+    ]);
+    for result in results.iter().filter(|r| all || r.tested) {
+        let cells = vec![
+            if result.tested {
+                format!("{:.2}", result.confidence)
             } else {
-                schemes
-                    .get(prompt_for_archive_scheme(&schemes, &file))
-                    .context("Could no get scheme from scheme list")?
-            };
-            log::debug!("Scheme {:?}", scheme);
+                "not tested".to_string()
+            },
+            result
+                .entries
+                .map(|entries| entries.to_string())
+                .unwrap_or_default(),
+            result.scheme.clone(),
+            result.failure.clone().unwrap_or_default(),
+        ];
+        if result.tested && result.failure.is_none() {
+            table.add_colored_row(cells, Color::Green);
+        } else {
+            table.add_row(cells);
+        }
+    }
+    print!("{}", table);
+    Ok(())
+}
 
-            let (archive, dir) = match scheme.extract(&file) {
-                Ok(archive) => archive,
-                Err(err) => {
-                    log::error!("{:?}: {}", file, err);
-                    return Ok(());
-                }
-            };
-            let files = dir
-                .get_root_dir()
-                .get_all_files()
-                .cloned()
-                .collect::<Vec<FileEntry>>();
-            let progress_bar = init_progressbar(
-                format!("Extracting: {:?}", file),
-                files.len() as u64,
+fn inspect_file(file: &Path) -> anyhow::Result<()> {
+    let input = InputSource::open(file)?;
+    let report = inspect::inspect(input.path())?;
+    println!("{}", serde_json::to_string_pretty(&report)?);
+    Ok(())
+}
+
+/// Print entries or per extension statistics of every archive
+fn list_archives(opt: &Opt, inputs: &[InputSource]) -> anyhow::Result<()> {
+    if opt.json || opt.csv {
+        return print_entry_list(opt, inputs);
+    }
+    for input in inputs.iter().filter(|input| is_listable(input)) {
+        let file = input.path();
+        let entries = match list_entries(opt, input) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::error!("{:?}: {}", file, error::describe(&err));
+                continue;
+            }
+        };
+        println!("{}", format!("{:?}", file).bold());
+        if opt.list {
+            let mut table = Table::new(&[
+                ("PATH", Align::Left),
+                ("SIZE", Align::Right),
+                ("OFFSET", Align::Right),
+                ("TYPE", Align::Left),
+                ("COMPRESSION", Align::Left),
+                ("ENCRYPTION", Align::Left),
+            ]);
+            for entry in entries.iter() {
+                table.add_row(vec![
+                    display_name(&entry.full_path).to_string(),
+                    table::human_size(entry.file_size),
+                    format!("{:#X}", entry.file_offset),
+                    entry_type(&entry.full_path),
+                    entry.ext.compression.clone().unwrap_or_default(),
+                    entry.ext.encryption.clone().unwrap_or_default(),
+                ]);
+            }
+            print!("{}", table);
+        }
+        if opt.stats {
+            let mut by_extension = BTreeMap::<String, (u64, u64)>::new();
+            for entry in entries.iter() {
+                let extension = entry
+                    .full_path
+                    .extension()
+                    .map(|extension| extension.to_string_lossy().to_lowercase())
+                    .unwrap_or_default();
+                let stats = by_extension.entry(extension).or_default();
+                stats.0 += 1;
+                stats.1 += entry.file_size;
+            }
+            let mut table = Table::new(&[
+                ("EXTENSION", Align::Left),
+                ("FILES", Align::Right),
+                ("SIZE", Align::Right),
+            ]);
+            let (mut files, mut size) = (0, 0);
+            for (extension, (count, bytes)) in &by_extension {
+                files += count;
+                size += bytes;
+                table.add_row(vec![
+                    extension.clone(),
+                    count.to_string(),
+                    table::human_size(*bytes),
+                ]);
+            }
+            table.add_colored_row(
+                vec![
+                    "total".to_string(),
+                    files.to_string(),
+                    table::human_size(size),
+                ],
+                Color::Cyan,
             );
+            print!("{}", table);
+        }
+    }
+    Ok(())
+}
 
-            files
-                .par_iter()
-                .progress_with(progress_bar)
-                .try_for_each(|entry| {
-                    let file_contents = archive.extract(entry)?;
-                    let mut output_file_name = PathBuf::from(&opt.output_dir);
-                    output_file_name.push(&entry.full_path);
-                    std::fs::create_dir_all(
-                        &output_file_name
-                            .parent()
-                            .context("Could not get parent directory")?,
-                    )?;
-                    log::debug!(
-                        "Extracting resource: {:?} {:X?}",
-                        output_file_name,
-                        entry
-                    );
-                    file_contents.write_contents(&output_file_name, Some(&archive))?;
-                    Ok(())
-                })
-        })
+/// Entries of every archive as single JSON document or CSV table with
+/// exact sizes and offsets. Archives that can not be listed are only
+/// logged, so output stays parseable.
+fn print_entry_list(opt: &Opt, inputs: &[InputSource]) -> anyhow::Result<()> {
+    let mut archives = Vec::new();
+    let mut csv =
+        String::from("archive,path,size,offset,type,compression,encryption\n");
+    for input in inputs.iter().filter(|input| is_listable(input)) {
+        let file = input.path();
+        let entries = match list_entries(opt, input) {
+            Ok(entries) => entries,
+            Err(err) => {
+                log::error!("{:?}: {}", file, error::describe(&err));
+                continue;
+            }
+        };
+        let archive = display_name(file).to_string();
+        if opt.csv {
+            for entry in entries.iter() {
+                let cells = [
+                    archive.clone(),
+                    display_name(&entry.full_path).to_string(),
+                    entry.file_size.to_string(),
+                    entry.file_offset.to_string(),
+                    entry_type(&entry.full_path),
+                    entry.ext.compression.clone().unwrap_or_default(),
+                    entry.ext.encryption.clone().unwrap_or_default(),
+                ];
+                let row = cells
+                    .iter()
+                    .map(|cell| csv_field(cell))
+                    .collect::<Vec<String>>();
+                csv.push_str(&row.join(","));
+                csv.push('\n');
+            }
+            continue;
+        }
+        let entries = entries
+            .iter()
+            .map(|entry| {
+                let mut value = serde_json::json!({
+                    "path": display_name(&entry.full_path).to_string(),
+                    "size": entry.file_size,
+                    "offset": entry.file_offset,
+                    "type": entry_type(&entry.full_path),
+                    "compression": entry.ext.compression,
+                    "encryption": entry.ext.encryption,
+                });
+                // Names that are not UTF-8 keep original bytes like in
+                // manifests
+                if let Some(raw_path) = text::escape_raw_name(&entry.full_path)
+                {
+                    value["raw_path"] = raw_path.into();
+                }
+                value
+            })
+            .collect::<Vec<serde_json::Value>>();
+        archives.push(serde_json::json!({
+            "archive": archive,
+            "entries": entries,
+        }));
+    }
+    let mut stdout = std::io::stdout();
+    if opt.csv {
+        stdout.write_all(csv.as_bytes())?;
+    } else {
+        serde_json::to_writer_pretty(&mut stdout, &archives)?;
+        writeln!(stdout)?;
+    }
+    Ok(())
+}
+
+/// Format entry is expected to be in by its extension, empty when it is not
+/// known resource format
+fn entry_type(path: &Path) -> String {
+    match ResourceMagic::parse_file_extension(path) {
+        ResourceMagic::Unrecognized => String::new(),
+        magic => format!("{:?}", magic).to_lowercase(),
+    }
+}
+
+/// Cell of CSV row, quoted when it contains separator, quote or line break
+fn csv_field(cell: &str) -> String {
+    if cell.contains(|c| matches!(c, ',' | '"' | '\n' | '\r')) {
+        format!("\"{}\"", cell.replace('"', "\"\""))
+    } else {
+        cell.to_string()
+    }
+}
+
+/// Input is archive listed from stream or file, directories are skipped
+fn is_listable(input: &InputSource) -> bool {
+    matches!(input, InputSource::Listed(_)) || input.path().is_file()
+}
+
+/// Entries of archive read by scheme without opening whole archive, unless
+/// it needs key, password or merged backup
+fn list_entries(
+    opt: &Opt,
+    input: &InputSource,
+) -> anyhow::Result<Vec<archive::FileEntryMetadata>> {
+    let file = match input {
+        InputSource::Listed(entries) => return Ok(entries.clone()),
+        input => input.path(),
+    };
+    let scheme = select_archive_scheme(file)?;
+    if scheme.needs_key_file() || opt.password.is_some() || opt.merge_backup {
+        let (archive, dir) = open_with_scheme(opt, scheme.as_ref(), file)?;
+        return Ok(dir
+            .files()
+            .map(|entry| {
+                archive::FileEntryMetadata::of(archive.as_ref(), entry)
+            })
+            .collect());
+    }
+    scheme.list(file)
+}
+
+/// Detect archive type and open it with universal or user selected scheme
+fn open_archive(
+    opt: &Opt,
+    file: &Path,
+) -> anyhow::Result<(Box<dyn archive::Archive>, archive::NavigableDirectory)> {
+    let scheme = select_archive_scheme(file)?;
+    open_with_scheme(opt, scheme.as_ref(), file)
+}
+
+/// Detect archive type and pick universal scheme or ask user for one
+fn select_archive_scheme(file: &Path) -> anyhow::Result<Box<dyn Scheme>> {
+    let archive_magic = Archive::detect(&file, &magic::sniff(&file)?);
+    log::debug!("Archive: {:?}", archive_magic);
+    let mut schemes = if let Archive::NotRecognized = archive_magic {
+        eprintln!(
+            "{}",
+            "Archive type could not be guessed. Please enter scheme manually:"
+                .yellow()
+        );
+        Archive::get_all_schemes()
+    } else {
+        archive_magic.get_schemes()
+    };
+
+    let index = if archive_magic.is_universal() {
+        0
+    } else {
+        prompt_for_archive_scheme(&schemes, &file)
+    };
+    if index >= schemes.len() {
+        return Err(AkaibuError::Custom(
+            "Could not get scheme from scheme list".to_string(),
+        )
+        .into());
+    }
+    let scheme = schemes.remove(index);
+    log::debug!("Scheme {:?}", scheme);
+    Ok(scheme)
+}
+
+fn open_with_scheme(
+    opt: &Opt,
+    scheme: &dyn Scheme,
+    file: &Path,
+) -> anyhow::Result<(Box<dyn archive::Archive>, archive::NavigableDirectory)> {
+    let opened = open_single(opt, scheme, file)?;
+    let backup = match overlay::backup_path(file).filter(|_| opt.merge_backup) {
+        Some(backup) => backup,
+        None => return Ok(opened),
+    };
+    log::info!("Merging over original archive {}", display_name(&backup));
+    let base = open_single(opt, scheme, &backup)?;
+    let base_name = backup
+        .file_name()
+        .map_or_else(String::new, |name| name.to_string_lossy().into_owned());
+    let (archive, dir) = OverlayArchive::merge(base, opened, base_name);
+    Ok((Box::new(archive), dir))
+}
+
+/// Original archive merged by --merge-backup into updated archive also given
+fn is_merged_backup(opt: &Opt, file: &Path) -> bool {
+    opt.merge_backup
+        && file
+            .extension()
+            .map_or(false, |extension| extension == overlay::BACKUP_EXTENSION)
+        && opt
+            .files
+            .iter()
+            .any(|other| overlay::backup_path(other).as_deref() == Some(file))
+}
+
+fn open_single(
+    opt: &Opt,
+    scheme: &dyn Scheme,
+    file: &Path,
+) -> anyhow::Result<(Box<dyn archive::Archive>, archive::NavigableDirectory)> {
+    if scheme.needs_key_file() {
+        let key_file =
+            KeyFile::load_or_companion(opt.key_file.as_deref(), &file)?;
+        scheme.extract_with_key(&file, &key_file)
+    } else if let Some(password) = &opt.password {
+        scheme.extract_with_password(&file, password)
+    } else {
+        scheme.extract(&file)
+    }
 }
 
 fn prompt_for_archive_scheme(
@@ -216,8 +1431,9 @@ fn prompt_for_archive_scheme(
                 v
             },
         );
+    // Prompt goes to stderr so it does not mix with piped output
+    eprint!("{}", msg);
     input::<usize>()
-        .repeat_msg(msg)
         .err("Invalid input value".red())
         .inside_err(
             0..schemes.len(),
@@ -243,8 +1459,9 @@ fn prompt_for_resource_scheme(
                 v
             },
         );
+    // Prompt goes to stderr so it does not mix with piped output
+    eprint!("{}", msg);
     input::<usize>()
-        .repeat_msg(msg)
         .err("Invalid input value".red())
         .inside_err(
             0..schemes.len(),
@@ -254,11 +1471,10 @@ fn prompt_for_resource_scheme(
 }
 
 fn init_progressbar(prefix: String, size: u64) -> ProgressBar {
-    let progress_bar = ProgressBar::new(size).with_style(
-        ProgressStyle::default_bar().template(
-            " {spinner} {prefix} {wide_bar:} {pos:>6}/{len:6} ETA:[{eta}]",
-        ),
-    );
+    let progress_bar = ProgressBar::new(size)
+        .with_style(ProgressStyle::default_bar().template(
+        " {spinner} {prefix} {wide_bar:} {pos:>6}/{len:6} {msg} ETA:[{eta}]",
+    ));
     progress_bar.set_prefix(prefix);
     progress_bar
 }
@@ -0,0 +1,126 @@
+use colored::{Color, Colorize};
+use std::fmt;
+
+/// Space between columns
+const COLUMN_GAP: &str = "  ";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Align {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone)]
+struct Column {
+    header: String,
+    align: Align,
+}
+
+/// Text table with columns aligned by display width, so rows with
+/// Japanese entry names stay aligned in terminal
+#[derive(Debug, Clone)]
+pub(crate) struct Table {
+    columns: Vec<Column>,
+    rows: Vec<(Vec<String>, Option<Color>)>,
+}
+
+impl Table {
+    pub(crate) fn new(columns: &[(&str, Align)]) -> Self {
+        Self {
+            columns: columns
+                .iter()
+                .map(|(header, align)| Column {
+                    header: header.to_string(),
+                    align: *align,
+                })
+                .collect(),
+            rows: Vec::new(),
+        }
+    }
+    pub(crate) fn add_row(&mut self, cells: Vec<String>) {
+        self.rows.push((cells, None));
+    }
+    /// Row printed in given color unless colors are disabled
+    pub(crate) fn add_colored_row(&mut self, cells: Vec<String>, color: Color) {
+        self.rows.push((cells, Some(color)));
+    }
+    fn widths(&self) -> Vec<usize> {
+        self.columns
+            .iter()
+            .enumerate()
+            .map(|(i, column)| {
+                self.rows
+                    .iter()
+                    .filter_map(|(cells, _)| cells.get(i))
+                    .map(|cell| display_width(cell))
+                    .chain(std::iter::once(display_width(&column.header)))
+                    .max()
+                    .unwrap_or_default()
+            })
+            .collect()
+    }
+    fn format_line(&self, cells: &[String], widths: &[usize]) -> String {
+        let last = self.columns.len().saturating_sub(1);
+        self.columns
+            .iter()
+            .zip(widths)
+            .enumerate()
+            .map(|(i, (column, width))| {
+                let cell = cells.get(i).map(String::as_str).unwrap_or_default();
+                let padding = " ".repeat(width - display_width(cell));
+                match column.align {
+                    // Last column is not padded to avoid trailing spaces
+                    Align::Left if i == last => cell.to_string(),
+                    Align::Left => format!("{}{}", cell, padding),
+                    Align::Right => format!("{}{}", padding, cell),
+                }
+            })
+            .collect::<Vec<String>>()
+            .join(COLUMN_GAP)
+    }
+}
+
+impl fmt::Display for Table {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let widths = self.widths();
+        let headers = self
+            .columns
+            .iter()
+            .map(|column| column.header.clone())
+            .collect::<Vec<String>>();
+        writeln!(f, "{}", self.format_line(&headers, &widths).bold())?;
+        for (cells, color) in &self.rows {
+            let line = self.format_line(cells, &widths);
+            match color {
+                Some(color) => writeln!(f, "{}", line.color(*color))?,
+                None => writeln!(f, "{}", line)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Number of terminal columns text takes, East Asian wide characters
+/// take two
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0x303E
+            | 0x3041..=0x33FF
+            | 0x3400..=0x4DBF
+            | 0x4E00..=0x9FFF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6 => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Size in bytes as human readable string
+pub(crate) fn human_size(bytes: u64) -> String {
+    bytesize::to_string(bytes, false)
+}
@@ -0,0 +1,83 @@
+/* C interface of akaibu, see src/lib.rs of akaibu_ffi for details.
+ * Strings are NUL terminated UTF-8. Handles are released by their _free
+ * function, everything borrowed from handle is valid until it is freed.
+ * Message of last failure on calling thread is returned by
+ * akaibu_last_error. */
+#ifndef AKAIBU_H
+#define AKAIBU_H
+
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define AKAIBU_OK 0
+#define AKAIBU_ERROR (-1)
+#define AKAIBU_BUFFER_TOO_SMALL (-2)
+
+typedef struct AkaibuArchive AkaibuArchive;
+typedef struct AkaibuResource AkaibuResource;
+
+typedef struct AkaibuEntry {
+    /* Path inside archive with / separators, names that are not UTF-8 decoded
+     * as SHIFT_JIS, owned by archive */
+    const char *path;
+    /* Size of entry as stored in archive */
+    uint64_t size;
+    /* Offset of entry data in archive */
+    uint64_t offset;
+} AkaibuEntry;
+
+typedef enum AkaibuResourceKind {
+    AKAIBU_RESOURCE_IMAGE = 0,
+    AKAIBU_RESOURCE_SPRITE_SHEET = 1,
+    AKAIBU_RESOURCE_ANIMATION = 2,
+    AKAIBU_RESOURCE_TEXT = 3,
+    AKAIBU_RESOURCE_RAW = 4,
+    AKAIBU_RESOURCE_EMPTY = 5,
+} AkaibuResourceKind;
+
+typedef struct AkaibuImage {
+    uint32_t width;
+    uint32_t height;
+    /* RGBA, 8 bits per channel, rows top to bottom without padding */
+    const uint8_t *pixels;
+    size_t len;
+} AkaibuImage;
+
+const char *akaibu_last_error(void);
+const char *akaibu_version(void);
+
+/* scheme and key_file may be NULL, returns NULL on failure */
+AkaibuArchive *akaibu_open(const char *path, const char *scheme,
+                           const char *key_file);
+void akaibu_archive_free(AkaibuArchive *archive);
+size_t akaibu_entry_count(const AkaibuArchive *archive);
+int32_t akaibu_entry(const AkaibuArchive *archive, size_t index,
+                     AkaibuEntry *entry);
+/* Index of entry or AKAIBU_ERROR, path is matched like AkaibuEntry.path */
+int64_t akaibu_find_entry(const AkaibuArchive *archive, const char *path);
+/* Returns AKAIBU_BUFFER_TOO_SMALL with required size in written when
+ * buffer_len is too small */
+int32_t akaibu_extract(const AkaibuArchive *archive, size_t index,
+                       uint8_t *buffer, size_t buffer_len, size_t *written);
+
+/* Return NULL on failure */
+AkaibuResource *akaibu_convert(const AkaibuArchive *archive, size_t index);
+AkaibuResource *akaibu_convert_file(const char *path);
+void akaibu_resource_free(AkaibuResource *resource);
+AkaibuResourceKind akaibu_resource_kind(const AkaibuResource *resource);
+size_t akaibu_resource_image_count(const AkaibuResource *resource);
+int32_t akaibu_resource_image(const AkaibuResource *resource, size_t index,
+                              AkaibuImage *image);
+/* Text or raw contents, not NUL terminated, NULL for images */
+const uint8_t *akaibu_resource_data(const AkaibuResource *resource,
+                                    size_t *len);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif
@@ -0,0 +1,260 @@
+use crate::{
+    guard, handle_arg, resource::AkaibuResource, str_arg,
+    AKAIBU_BUFFER_TOO_SMALL, AKAIBU_ERROR, AKAIBU_OK,
+};
+use akaibu::{
+    archive::FileEntry, pipeline::ArchiveHandle, util::text::display_name,
+};
+use anyhow::Context;
+use std::{ffi::CString, os::raw::c_char, path::Path, ptr};
+
+/// Opened archive, entries are addressed by their index from 0 to
+/// [`akaibu_entry_count`], in order they are listed by scheme
+#[derive(Debug)]
+pub struct AkaibuArchive {
+    handle: ArchiveHandle,
+    entries: Vec<FileEntry>,
+    /// Paths of entries, borrowed by [`AkaibuEntry`]
+    paths: Vec<CString>,
+}
+
+/// Entry of archive, path is owned by archive and valid until it is freed
+#[repr(C)]
+#[derive(Debug)]
+pub struct AkaibuEntry {
+    /// Path inside archive, with `/` separators
+    pub path: *const c_char,
+    /// Size of entry as stored in archive
+    pub size: u64,
+    /// Offset of entry data in archive
+    pub offset: u64,
+}
+
+/// Path given to C callers, names that are not valid UTF-8 are decoded as
+/// SHIFT_JIS like in manifests and separators are `/`
+fn c_path(path: &Path) -> String {
+    display_name(path).to_string().replace('\\', "/")
+}
+
+impl AkaibuArchive {
+    pub(crate) fn new(handle: ArchiveHandle) -> anyhow::Result<Self> {
+        let entries = handle.dir().files().cloned().collect::<Vec<FileEntry>>();
+        let paths = entries
+            .iter()
+            .map(|entry| {
+                CString::new(c_path(&entry.full_path))
+                    .context("Entry path contains NUL byte")
+            })
+            .collect::<anyhow::Result<Vec<CString>>>()?;
+        Ok(Self {
+            handle,
+            entries,
+            paths,
+        })
+    }
+    fn entry(&self, index: usize) -> anyhow::Result<&FileEntry> {
+        self.entries.get(index).with_context(|| {
+            format!(
+                "Entry index {} out of range, archive has {} entries",
+                index,
+                self.entries.len()
+            )
+        })
+    }
+}
+
+/// Release archive, null is ignored
+///
+/// # Safety
+/// `archive` has to be null or handle returned by `akaibu_open` that was
+/// not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_archive_free(archive: *mut AkaibuArchive) {
+    if !archive.is_null() {
+        drop(Box::from_raw(archive));
+    }
+}
+
+/// Number of entries in archive, 0 for null handle
+///
+/// # Safety
+/// `archive` has to be null or valid handle
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_entry_count(
+    archive: *const AkaibuArchive,
+) -> usize {
+    archive.as_ref().map_or(0, |archive| archive.entries.len())
+}
+
+/// Write entry at index into `entry`
+///
+/// # Safety
+/// `archive` has to be valid handle and `entry` point to writable
+/// [`AkaibuEntry`]
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_entry(
+    archive: *const AkaibuArchive,
+    index: usize,
+    entry: *mut AkaibuEntry,
+) -> i32 {
+    guard(AKAIBU_ERROR, || {
+        let archive = handle_arg(archive)?;
+        let file_entry = archive.entry(index)?;
+        let entry = entry.as_mut().context("Entry is null")?;
+        *entry = AkaibuEntry {
+            path: archive.paths[index].as_ptr(),
+            size: file_entry.file_size,
+            offset: file_entry.file_offset,
+        };
+        Ok(AKAIBU_OK)
+    })
+}
+
+/// Index of entry with given path inside archive, `AKAIBU_ERROR` when
+/// there is none
+///
+/// # Safety
+/// `archive` has to be valid handle and `path` NUL terminated string
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_find_entry(
+    archive: *const AkaibuArchive,
+    path: *const c_char,
+) -> i64 {
+    guard(AKAIBU_ERROR as i64, || {
+        let archive = handle_arg(archive)?;
+        let path = str_arg(path, "path")?.context("path is null")?;
+        // Compared the same way path is given by `akaibu_entry`
+        let normalized = c_path(Path::new(path));
+        let index = archive
+            .paths
+            .iter()
+            .position(|entry_path| {
+                entry_path.as_bytes() == normalized.as_bytes()
+            })
+            .with_context(|| format!("Entry not found: {}", path))?;
+        Ok(index as i64)
+    })
+}
+
+/// Copy entry contents after decryption into caller buffer. Size of
+/// contents is written to `written`, when it is larger than `buffer_len`
+/// nothing is copied and `AKAIBU_BUFFER_TOO_SMALL` is returned, so size can
+/// be queried with null buffer of length 0 first.
+///
+/// # Safety
+/// `archive` has to be valid handle, `buffer` point to `buffer_len`
+/// writable bytes or be null when it is 0, and `written` point to writable
+/// `size_t`
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_extract(
+    archive: *const AkaibuArchive,
+    index: usize,
+    buffer: *mut u8,
+    buffer_len: usize,
+    written: *mut usize,
+) -> i32 {
+    guard(AKAIBU_ERROR, || {
+        let archive = handle_arg(archive)?;
+        let written = written.as_mut().context("written is null")?;
+        let contents = archive
+            .handle
+            .archive()
+            .extract(archive.entry(index)?)?
+            .contents;
+        *written = contents.len();
+        if contents.len() > buffer_len {
+            return Ok(AKAIBU_BUFFER_TOO_SMALL);
+        }
+        if !contents.is_empty() {
+            if buffer.is_null() {
+                anyhow::bail!("buffer is null");
+            }
+            ptr::copy_nonoverlapping(contents.as_ptr(), buffer, contents.len());
+        }
+        Ok(AKAIBU_OK)
+    })
+}
+
+/// Extract entry and convert it when it is known resource, entries of
+/// unknown type are returned as raw resource. Returns null on failure.
+///
+/// # Safety
+/// `archive` has to be valid handle
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_convert(
+    archive: *const AkaibuArchive,
+    index: usize,
+) -> *mut AkaibuResource {
+    guard(ptr::null_mut(), || {
+        let archive = handle_arg(archive)?;
+        let converted = archive.handle.convert(archive.entry(index)?)?;
+        Ok(Box::into_raw(Box::new(AkaibuResource::from_converted(
+            converted.contents,
+        ))))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akaibu::archive::{Directory, DirectoryArchive, NavigableDirectory};
+    use std::{ffi::CStr, path::PathBuf};
+
+    fn archive(paths: Vec<PathBuf>) -> AkaibuArchive {
+        let root_dir = Directory::from_entries(
+            paths.into_iter().map(|path| FileEntry::new(path, 0, 0)),
+        );
+        AkaibuArchive::new(ArchiveHandle::new(
+            Box::new(DirectoryArchive::new(Path::new("."))),
+            NavigableDirectory::new(root_dir),
+        ))
+        .unwrap()
+    }
+
+    /// Every path returned by `akaibu_entry` finds its own entry
+    fn assert_paths_found(archive: &AkaibuArchive) {
+        for index in 0..archive.entries.len() {
+            let mut entry = AkaibuEntry {
+                path: ptr::null(),
+                size: 0,
+                offset: 0,
+            };
+            unsafe {
+                assert_eq!(akaibu_entry(archive, index, &mut entry), AKAIBU_OK);
+                assert_eq!(
+                    akaibu_find_entry(archive, entry.path),
+                    index as i64,
+                    "{:?}",
+                    CStr::from_ptr(entry.path)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn find_entry_by_path_of_entry() {
+        let archive = archive(vec![
+            PathBuf::from("bg/BG01.png"),
+            PathBuf::from("script\\start.txt"),
+        ]);
+        assert_paths_found(&archive);
+        let path = CString::new("script\\start.txt").unwrap();
+        let found = unsafe { akaibu_find_entry(&archive, path.as_ptr()) };
+        assert_eq!(
+            archive.paths[found as usize].to_str(),
+            Ok("script/start.txt")
+        );
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn find_entry_with_shift_jis_name() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        // 背景.png in SHIFT_JIS
+        let name = OsStr::from_bytes(b"\x94\x77\x8C\x69.png");
+        let archive = archive(vec![PathBuf::from(name)]);
+        assert_eq!(archive.paths[0].to_str(), Ok("背景.png"));
+        assert_paths_found(&archive);
+    }
+}
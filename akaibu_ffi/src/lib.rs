@@ -0,0 +1,194 @@
+#![deny(
+    rust_2018_idioms,
+    unreachable_pub,
+    unused_imports,
+    unused_mut,
+    missing_debug_implementations
+)]
+
+//! C ABI of akaibu, for tools written in other languages. Archives and
+//! converted resources are passed as opaque handles released by their
+//! `_free` function. Functions returning handle return null on failure,
+//! functions returning status return one of `AKAIBU_` codes, and message of
+//! last failure on calling thread is available from [`akaibu_last_error`].
+//! Strings are NUL terminated UTF-8.
+
+pub mod archive;
+pub mod resource;
+
+use akaibu::{
+    key_file::KeyFile,
+    magic,
+    pipeline::{self, ArchiveHandle},
+    resource::ResourceMagic,
+};
+use anyhow::Context;
+use archive::AkaibuArchive;
+use resource::AkaibuResource;
+use std::{
+    cell::RefCell,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    ptr,
+};
+
+/// Call succeeded
+pub const AKAIBU_OK: i32 = 0;
+/// Call failed, see [`akaibu_last_error`]
+pub const AKAIBU_ERROR: i32 = -1;
+/// Caller provided buffer is too small, required size was written back
+pub const AKAIBU_BUFFER_TOO_SMALL: i32 = -2;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = RefCell::new(None);
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " "))
+        .expect("NUL bytes were replaced");
+    LAST_ERROR.with(|last_error| *last_error.borrow_mut() = Some(message));
+}
+
+/// Run body of exported function, errors and panics are stored as last
+/// error and `failed` is returned instead. Panics must not unwind into
+/// foreign caller.
+pub(crate) fn guard<T>(
+    failed: T,
+    body: impl FnOnce() -> anyhow::Result<T>,
+) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(body)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            set_last_error(format!("{:#}", err));
+            failed
+        }
+        Err(panic) => {
+            let message = panic
+                .downcast_ref::<&str>()
+                .map(|message| message.to_string())
+                .or_else(|| panic.downcast_ref::<String>().cloned())
+                .unwrap_or_else(|| "Unknown panic".to_string());
+            set_last_error(format!("Panicked: {}", message));
+            failed
+        }
+    }
+}
+
+/// String argument, None when pointer is null
+///
+/// # Safety
+/// Pointer has to be null or point to NUL terminated string
+pub(crate) unsafe fn str_arg<'a>(
+    arg: *const c_char,
+    name: &str,
+) -> anyhow::Result<Option<&'a str>> {
+    if arg.is_null() {
+        return Ok(None);
+    }
+    CStr::from_ptr(arg)
+        .to_str()
+        .map(Some)
+        .with_context(|| format!("{} is not valid UTF-8", name))
+}
+
+/// Reference behind handle, failing on null handle
+///
+/// # Safety
+/// Pointer has to be null or handle that was not freed yet
+pub(crate) unsafe fn handle_arg<'a, T>(
+    handle: *const T,
+) -> anyhow::Result<&'a T> {
+    handle.as_ref().context("Handle is null")
+}
+
+/// Message of last failed call on calling thread, null when none failed.
+/// It stays valid until next failed call on the same thread.
+#[no_mangle]
+pub extern "C" fn akaibu_last_error() -> *const c_char {
+    LAST_ERROR.with(|last_error| {
+        last_error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Version of library, to check it matches header caller was built with
+#[no_mangle]
+pub extern "C" fn akaibu_version() -> *const c_char {
+    concat!(env!("CARGO_PKG_VERSION"), "\0").as_ptr() as *const c_char
+}
+
+/// Open archive. Scheme is detected from archive contents unless given by
+/// name, `key_file` is used by archives that need per-install key. Returns
+/// null on failure.
+///
+/// # Safety
+/// `path` has to be NUL terminated string, `scheme` and `key_file` too or
+/// null.
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_open(
+    path: *const c_char,
+    scheme: *const c_char,
+    key_file: *const c_char,
+) -> *mut AkaibuArchive {
+    guard(ptr::null_mut(), || {
+        let path =
+            PathBuf::from(str_arg(path, "path")?.context("path is null")?);
+        let scheme_name = str_arg(scheme, "scheme")?;
+        let key_file = str_arg(key_file, "key_file")?
+            .map(|key_file| KeyFile::load(Path::new(key_file)))
+            .transpose()?;
+        let sniffed = magic::sniff(&path)?;
+        let scheme = magic::Archive::detect(&path, &sniffed)
+            .get_schemes()
+            .into_iter()
+            .find(|scheme| {
+                scheme_name.map_or(true, |name| scheme.get_name() == name)
+            })
+            .with_context(|| match scheme_name {
+                Some(name) => {
+                    format!("Unknown scheme for {:?}: {}", path, name)
+                }
+                None => format!("Archive type of {:?} not recognized", path),
+            })?;
+        let (archive, dir) = match &key_file {
+            Some(key_file) => scheme.extract_with_key(&path, key_file)?,
+            None => scheme.extract(&path)?,
+        };
+        let archive = AkaibuArchive::new(ArchiveHandle::new(archive, dir))?;
+        Ok(Box::into_raw(Box::new(archive)))
+    })
+}
+
+/// Convert loose resource file, returns null on failure
+///
+/// # Safety
+/// `path` has to be NUL terminated string
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_convert_file(
+    path: *const c_char,
+) -> *mut AkaibuResource {
+    guard(ptr::null_mut(), || {
+        let path =
+            PathBuf::from(str_arg(path, "path")?.context("path is null")?);
+        let sniffed = magic::sniff(&path)?;
+        let mut resource_magic = ResourceMagic::parse_magic(&sniffed.head);
+        if let ResourceMagic::Unrecognized = resource_magic {
+            resource_magic = ResourceMagic::parse_file_extension(&path);
+        }
+        let scheme = resource_magic
+            .get_schemes()
+            .into_iter()
+            .next()
+            .with_context(|| {
+                format!("Resource type of {:?} not recognized", path)
+            })?;
+        let resource = pipeline::convert_file(scheme.as_ref(), &path)?;
+        Ok(Box::into_raw(Box::new(AkaibuResource::from_resource(
+            resource,
+        ))))
+    })
+}
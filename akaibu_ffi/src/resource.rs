@@ -0,0 +1,169 @@
+use crate::{guard, handle_arg, AKAIBU_ERROR, AKAIBU_OK};
+use akaibu::{pipeline::Converted, resource::ResourceType};
+use anyhow::Context;
+use image::RgbaImage;
+use std::ptr;
+
+/// What converted resource holds
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AkaibuResourceKind {
+    /// Single image
+    Image = 0,
+    /// Images of sprite sheet
+    SpriteSheet = 1,
+    /// Frames of animation
+    Animation = 2,
+    /// Script decoded to UTF-8 text
+    Text = 3,
+    /// Entry of unknown type, as stored in archive after decryption
+    Raw = 4,
+    /// Resource without contents
+    Empty = 5,
+}
+
+/// Converted resource, images and data are owned by it and valid until it
+/// is freed
+#[derive(Debug)]
+pub struct AkaibuResource {
+    kind: AkaibuResourceKind,
+    images: Vec<RgbaImage>,
+    /// UTF-8 text or raw entry contents
+    data: Vec<u8>,
+}
+
+/// Image of resource, pixels are RGBA with 8 bits per channel and rows
+/// stored top to bottom without padding
+#[repr(C)]
+#[derive(Debug)]
+pub struct AkaibuImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: *const u8,
+    /// Number of bytes of pixels, `width * height * 4`
+    pub len: usize,
+}
+
+impl AkaibuResource {
+    pub(crate) fn from_resource(resource: ResourceType) -> Self {
+        let (kind, images, data) = match resource {
+            ResourceType::RgbaImage { image } => {
+                (AkaibuResourceKind::Image, vec![image], vec![])
+            }
+            ResourceType::SpriteSheet { sprites } => {
+                (AkaibuResourceKind::SpriteSheet, sprites, vec![])
+            }
+            ResourceType::Animation { frames } => {
+                (AkaibuResourceKind::Animation, frames, vec![])
+            }
+            ResourceType::Text(text) => {
+                (AkaibuResourceKind::Text, vec![], text.into_bytes())
+            }
+            ResourceType::Other => (AkaibuResourceKind::Empty, vec![], vec![]),
+        };
+        Self { kind, images, data }
+    }
+    pub(crate) fn from_converted(converted: Converted) -> Self {
+        match converted {
+            Converted::Resource(resource) => Self::from_resource(resource),
+            Converted::Raw(contents) => Self {
+                kind: AkaibuResourceKind::Raw,
+                images: vec![],
+                data: contents.to_vec(),
+            },
+        }
+    }
+}
+
+/// Release resource, null is ignored
+///
+/// # Safety
+/// `resource` has to be null or handle returned by `akaibu_convert` or
+/// `akaibu_convert_file` that was not freed yet
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_resource_free(resource: *mut AkaibuResource) {
+    if !resource.is_null() {
+        drop(Box::from_raw(resource));
+    }
+}
+
+/// Kind of resource, `Empty` for null handle
+///
+/// # Safety
+/// `resource` has to be null or valid handle
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_resource_kind(
+    resource: *const AkaibuResource,
+) -> AkaibuResourceKind {
+    resource
+        .as_ref()
+        .map_or(AkaibuResourceKind::Empty, |resource| resource.kind)
+}
+
+/// Number of images of resource, 0 for text and raw resources
+///
+/// # Safety
+/// `resource` has to be null or valid handle
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_resource_image_count(
+    resource: *const AkaibuResource,
+) -> usize {
+    resource
+        .as_ref()
+        .map_or(0, |resource| resource.images.len())
+}
+
+/// Write image at index into `image`
+///
+/// # Safety
+/// `resource` has to be valid handle and `image` point to writable
+/// [`AkaibuImage`]
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_resource_image(
+    resource: *const AkaibuResource,
+    index: usize,
+    image: *mut AkaibuImage,
+) -> i32 {
+    guard(AKAIBU_ERROR, || {
+        let resource = handle_arg(resource)?;
+        let source = resource.images.get(index).with_context(|| {
+            format!(
+                "Image index {} out of range, resource has {} images",
+                index,
+                resource.images.len()
+            )
+        })?;
+        let image = image.as_mut().context("image is null")?;
+        *image = AkaibuImage {
+            width: source.width(),
+            height: source.height(),
+            pixels: source.as_raw().as_ptr(),
+            len: source.as_raw().len(),
+        };
+        Ok(AKAIBU_OK)
+    })
+}
+
+/// UTF-8 text of text resource or contents of raw resource, not NUL
+/// terminated. Its length is written to `len`, null is returned for other
+/// kinds.
+///
+/// # Safety
+/// `resource` has to be valid handle and `len` point to writable `size_t`
+#[no_mangle]
+pub unsafe extern "C" fn akaibu_resource_data(
+    resource: *const AkaibuResource,
+    len: *mut usize,
+) -> *const u8 {
+    guard(ptr::null(), || {
+        let resource = handle_arg(resource)?;
+        let len = len.as_mut().context("len is null")?;
+        *len = resource.data.len();
+        Ok(match resource.kind {
+            AkaibuResourceKind::Text | AkaibuResourceKind::Raw => {
+                resource.data.as_ptr()
+            }
+            _ => ptr::null(),
+        })
+    })
+}
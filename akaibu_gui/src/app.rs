@@ -1,22 +1,20 @@
 use crate::{
+    i18n::{self, Language},
+    logic::jobs::JobManager,
     message::Message,
     ui::{
-        archive::ArchiveContent, content::Content, resource::ResourceContent,
-        resource_scheme::ResourceSchemeContent, scheme::SchemeContent,
+        content::Content,
+        tabs::{Tab, Tabs},
     },
     update, Opt,
 };
-use akaibu::{magic, resource::ResourceMagic};
-use iced::{executor, Application, Clipboard, Command};
-use std::{
-    fs::File,
-    io::{Read, Seek, SeekFrom},
-};
+use akaibu::scheme::registry::SchemeRegistry;
+use iced::{executor, Application, Clipboard, Command, Subscription};
 use structopt::StructOpt;
 
 pub(crate) struct App {
-    pub(crate) opt: Opt,
-    pub(crate) content: Content,
+    pub(crate) tabs: Tabs,
+    pub(crate) jobs: JobManager,
 }
 
 impl Application for App {
@@ -26,111 +24,51 @@ impl Application for App {
 
     fn new(_flags: Self::Flags) -> (Self, Command<Message>) {
         let opt = Opt::from_args();
+        i18n::set_language(opt.lang.unwrap_or_else(Language::from_env));
 
-        let mut magic = vec![0; 32];
-        File::open(&opt.file)
-            .expect("Could not open file")
-            .read_exact(&mut magic)
-            .expect("Could not read file");
-        let mut archive = magic::Archive::parse(&magic);
-        if let magic::Archive::NotRecognized = archive {
-            let mut magic = vec![0; 32];
-            let mut f = File::open(&opt.file).expect("Could not open file");
-            f.seek(SeekFrom::End(-32)).expect("Could not seek file");
-            f.read_exact(&mut magic).expect("Could not read file");
-            archive = magic::Archive::parse_end(&magic);
-        };
-
-        if let magic::Archive::NotRecognized = archive {
-            let mut resource = ResourceMagic::parse_magic(&magic);
-            if let ResourceMagic::Unrecognized = resource {
-                resource = ResourceMagic::parse_file_extension(&opt.file);
-            }
-            if let ResourceMagic::Unrecognized = resource {
-                return (
-                    Self {
-                        opt,
-                        content: Content::SchemeView(SchemeContent::new(
-                            magic::Archive::get_all_schemes(),
-                            "Archive type could not be guessed. Please enter scheme manually:"
-                                .to_string(),
-                        )),
-                    },
-                    Command::none(),
-                );
-            } else {
-                let file_name = opt.file.clone();
-                if resource.is_universal() {
-                    let resource = resource
-                        .get_schemes()
-                        .get(0)
-                        .expect("Expected universal scheme")
-                        .convert(&file_name)
-                        .expect("Could not convert resource");
-                    return (
-                        Self {
-                            opt,
-                            content: Content::ResourceView(
-                                ResourceContent::new(resource, file_name),
-                            ),
-                        },
-                        Command::none(),
-                    );
-                } else {
-                    return (
-                        Self {
-                            opt,
-                            content: Content::ResourceSchemeView(
-                                ResourceSchemeContent::new(
-                                    resource.get_schemes(),
-                                    "Select convert scheme:".to_string(),
-                                    file_name,
-                                ),
-                            ),
-                        },
-                        Command::none(),
-                    );
-                }
-            }
-        }
-
-        let schemes = archive.get_schemes();
-
-        if archive.is_universal() {
-            let scheme = schemes.get(0).expect("Expected universal scheme");
-            let (archive, dir) =
-                scheme.extract(&opt.file).expect("Could not extract");
-            (
-                Self {
-                    opt,
-                    content: Content::ArchiveView(Box::new(
-                        ArchiveContent::new(archive, dir),
-                    )),
-                },
-                Command::none(),
-            )
-        } else {
-            (
-                Self {
-                    opt,
-                    content: Content::SchemeView(SchemeContent::new(
-                        schemes,
-                        "Select extract scheme:".to_string(),
-                    )),
-                },
-                Command::none(),
-            )
+        let mut tabs: Vec<Tab> = opt
+            .files
+            .iter()
+            .map(|file| {
+                let content = Content::open(file).expect("Could not open file");
+                Tab::new(file.clone(), content)
+            })
+            .collect();
+        let mut jobs = JobManager::new();
+        let mut commands = tabs
+            .iter_mut()
+            .map(|tab| update::convert_opened_resource(tab, &mut jobs))
+            .collect::<Vec<_>>();
+        // Broken key tables are reported before any archive is opened, only
+        // schemes using them fail
+        for err in SchemeRegistry::global().validate() {
+            log::warn!("{}", err);
+            commands.push(Command::perform(
+                async move { err.to_string() },
+                Message::Error,
+            ));
         }
+        (
+            Self {
+                tabs: Tabs::new(tabs),
+                jobs,
+            },
+            Command::batch(commands),
+        )
     }
     fn title(&self) -> String {
-        format!("Akaibu {}", env!("CARGO_PKG_VERSION"))
+        format!(
+            "Akaibu {} - {}",
+            env!("CARGO_PKG_VERSION"),
+            self.tabs.active().title()
+        )
     }
     fn update(
         &mut self,
         message: Self::Message,
-        _clipboard: &mut Clipboard,
+        clipboard: &mut Clipboard,
     ) -> Command<Message> {
-        match update::handle_message(self, message) {
+        match update::handle_message(self, message, clipboard) {
             Ok(command) => command,
             Err(err) => {
                 log::error!("{:?}", err);
@@ -138,7 +76,10 @@ impl Application for App {
             }
         }
     }
+    fn subscription(&self) -> Subscription<Message> {
+        self.jobs.subscription()
+    }
     fn view(&mut self) -> iced::Element<'_, Self::Message> {
-        self.content.view()
+        self.tabs.view()
     }
 }
@@ -0,0 +1,106 @@
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::atomic::{AtomicU8, Ordering},
+};
+
+static ENGLISH: Lazy<HashMap<String, String>> =
+    Lazy::new(|| load_translation("i18n/en.json"));
+static JAPANESE: Lazy<HashMap<String, String>> =
+    Lazy::new(|| load_translation("i18n/ja.json"));
+
+static LANGUAGE: AtomicU8 = AtomicU8::new(Language::English as u8);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Language {
+    English,
+    Japanese,
+}
+
+impl Language {
+    /// Guess language from system locale environment variables
+    pub(crate) fn from_env() -> Self {
+        let is_japanese = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|var| std::env::var(var).ok())
+            .find(|locale| !locale.is_empty())
+            .map(|locale| locale.starts_with("ja"))
+            .unwrap_or(false);
+        if is_japanese {
+            Self::Japanese
+        } else {
+            Self::English
+        }
+    }
+    pub(crate) fn other(self) -> Self {
+        match self {
+            Self::English => Self::Japanese,
+            Self::Japanese => Self::English,
+        }
+    }
+    fn translation(self) -> &'static HashMap<String, String> {
+        match self {
+            Self::English => &ENGLISH,
+            Self::Japanese => &JAPANESE,
+        }
+    }
+}
+
+impl FromStr for Language {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "en" | "english" => Ok(Self::English),
+            "ja" | "japanese" => Ok(Self::Japanese),
+            _ => Err(format!("Unsupported language: {}, expected en or ja", s)),
+        }
+    }
+}
+
+pub(crate) fn language() -> Language {
+    match LANGUAGE.load(Ordering::Relaxed) {
+        l if l == Language::Japanese as u8 => Language::Japanese,
+        _ => Language::English,
+    }
+}
+
+pub(crate) fn set_language(language: Language) {
+    LANGUAGE.store(language as u8, Ordering::Relaxed);
+}
+
+/// Get interface string in current language.
+/// Falls back to English and then to key itself when translation is missing.
+pub(crate) fn tr(key: &str) -> String {
+    language()
+        .translation()
+        .get(key)
+        .or_else(|| ENGLISH.get(key))
+        .cloned()
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Get interface string with `{}` placeholders replaced by args in order
+pub(crate) fn tr_args(key: &str, args: &[String]) -> String {
+    let template = tr(key);
+    let mut args = args.iter();
+    let mut parts = template.split("{}");
+    let mut text = parts.next().unwrap_or_default().to_string();
+    for part in parts {
+        if let Some(arg) = args.next() {
+            text.push_str(arg);
+        }
+        text.push_str(part);
+    }
+    text
+}
+
+fn load_translation(path: &str) -> HashMap<String, String> {
+    crate::Resources::get(path)
+        .and_then(|file| serde_json::from_slice(&file).ok())
+        .unwrap_or_else(|| {
+            log::error!("Could not load translation: {}", path);
+            HashMap::new()
+        })
+}
@@ -1,15 +1,14 @@
-use crate::ui::resource::ConvertFormat;
+use crate::{i18n, ui::resource::ConvertFormat};
 use akaibu::{archive::Archive, archive::FileEntry, resource::ResourceType};
 use anyhow::Context;
 use image::ImageFormat;
 use std::{
-    fs::File,
-    io::Write,
     path::{Path, PathBuf},
     sync::Arc,
 };
 
-pub async fn convert_resource(
+/// Convert entry and write it next to archive
+pub fn convert_resource_next_to(
     archive: Arc<Box<dyn Archive>>,
     entry: FileEntry,
     file_path: PathBuf,
@@ -19,7 +18,7 @@ pub async fn convert_resource(
     log::info!("Converting resource {:?}", resource_magic);
     let mut converted_path = file_path;
     converted_path.set_file_name(&entry.file_name);
-    write_resource(
+    let written = write_resource(
         resource_magic
             .get_schemes()
             .get(0)
@@ -32,7 +31,7 @@ pub async fn convert_resource(
         &entry,
         &converted_path,
     )?;
-    Ok(converted_path)
+    Ok(written.into_iter().next().unwrap_or(converted_path))
 }
 
 #[allow(clippy::borrowed_box)]
@@ -44,9 +43,13 @@ pub fn convert_resource_blocking(
     let file_contents = archive.extract(&entry)?;
     let resource_magic = file_contents.get_resource_type();
     log::info!("Converting resource {:?}", resource_magic);
-    let mut converted_path = file_path.to_path_buf();
-    converted_path.set_file_name(&entry.file_name);
-    write_resource_entry(
+    let converted_path = file_path.join(entry.output_path());
+    std::fs::create_dir_all(
+        converted_path
+            .parent()
+            .context("Could not get parent directory")?,
+    )?;
+    let written = write_resource(
         resource_magic
             .get_schemes()
             .get(0)
@@ -57,58 +60,25 @@ pub fn convert_resource_blocking(
                 Some(&archive),
             )?,
         &entry,
-        file_path,
+        &converted_path,
     )?;
-    Ok(converted_path)
+    Ok(written.into_iter().next().unwrap_or(converted_path))
 }
 
+/// Write converted resource as PNG images or text, returning written files
 fn write_resource(
     resource: ResourceType,
     entry: &FileEntry,
     file_name: &Path,
-) -> anyhow::Result<()> {
-    match resource {
-        ResourceType::SpriteSheet { mut sprites } => {
-            if sprites.len() == 1 {
-                let image = sprites.remove(0);
-                let mut new_file_name = file_name.to_path_buf();
-                new_file_name.set_extension("png");
-                image.save(new_file_name)?;
-            } else {
-                for (i, sprite) in sprites.iter().enumerate() {
-                    let mut new_file_name = file_name.to_path_buf();
-                    new_file_name.set_file_name(format!(
-                        "{}_{}",
-                        new_file_name
-                            .file_stem()
-                            .context("Could not get file name")?
-                            .to_str()
-                            .context("Not valid UTF-8")?,
-                        i
-                    ));
-                    new_file_name.set_extension("png");
-                    sprite.save(&new_file_name)?;
-                }
-            }
-            Ok(())
-        }
-        ResourceType::RgbaImage { image } => {
-            let mut new_file_name = file_name.to_path_buf();
-            new_file_name.set_extension("png");
-            image.save(new_file_name)?;
-            Ok(())
-        }
-        ResourceType::Text(s) => {
-            let mut new_file_name = file_name.to_path_buf();
-            new_file_name.set_extension("txt");
-            File::create(new_file_name)?.write_all(s.as_bytes())?;
-            Ok(())
-        }
-        ResourceType::Other => Err(akaibu::error::AkaibuError::Custom(
-            format!("Convert not available for: {}", entry.file_name),
-        )
-        .into()),
+) -> anyhow::Result<Vec<PathBuf>> {
+    if let ResourceType::Other = resource {
+        return Err(akaibu::error::AkaibuError::Custom(i18n::tr_args(
+            "convert_not_available",
+            &[entry.file_name.clone()],
+        ))
+        .into());
     }
+    resource.write_resource(file_name)
 }
 
 pub fn write_resource_with_format(
@@ -131,61 +101,10 @@ pub fn write_resource_with_format(
             )?;
             Ok(file_name)
         }
-        _ => Err(akaibu::error::AkaibuError::Custom(format!(
-            "Convert not available for: {:?}",
-            file_name
+        _ => Err(akaibu::error::AkaibuError::Custom(i18n::tr_args(
+            "convert_not_available",
+            &[format!("{:?}", file_name)],
         ))
         .into()),
     }
 }
-
-fn write_resource_entry(
-    resource: ResourceType,
-    entry: &FileEntry,
-    file_path: &Path,
-) -> anyhow::Result<()> {
-    match resource {
-        ResourceType::SpriteSheet { mut sprites } => {
-            if sprites.len() == 1 {
-                let image = sprites.remove(0);
-                let mut new_file_name = file_path.to_path_buf();
-                new_file_name.set_extension("png");
-                image.save(new_file_name)?;
-            } else {
-                for (i, sprite) in sprites.iter().enumerate() {
-                    let mut new_file_name = file_path.to_path_buf();
-                    new_file_name.set_file_name(format!(
-                        "{}_{}",
-                        new_file_name
-                            .file_stem()
-                            .context("Could not get file name")?
-                            .to_str()
-                            .context("Not valid UTF-8")?,
-                        i
-                    ));
-                    new_file_name.set_extension("png");
-                    sprite.save(&new_file_name)?;
-                }
-            }
-            Ok(())
-        }
-        ResourceType::RgbaImage { image } => {
-            let mut new_file_name = file_path.to_path_buf();
-            new_file_name.push(entry.full_path.clone());
-            new_file_name.set_extension("png");
-            image.save(new_file_name)?;
-            Ok(())
-        }
-        ResourceType::Text(s) => {
-            let mut new_file_name = file_path.to_path_buf();
-            new_file_name.push(entry.full_path.clone());
-            new_file_name.set_extension("txt");
-            File::create(new_file_name)?.write_all(s.as_bytes())?;
-            Ok(())
-        }
-        ResourceType::Other => Err(akaibu::error::AkaibuError::Unimplemented(
-            format!("Convert not available for: {}", entry.file_name),
-        )
-        .into()),
-    }
-}
@@ -1,108 +1,153 @@
-use super::convert;
-use akaibu::archive::{Archive, FileEntry};
+use super::{convert, jobs::JobContext};
+use crate::{i18n, message::Status};
+use akaibu::{
+    archive::{Archive, FileEntry},
+    error::AkaibuError,
+    pipeline::{self, ExtractOptions},
+    progress::{Progress, ProgressTracker},
+};
 use anyhow::Context;
 use rayon::iter::{IntoParallelRefIterator, ParallelIterator};
-use std::{path::PathBuf, sync::Arc};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+/// Output directory created next to archive
+const OUTPUT_DIR: &str = "ext";
 
 pub async fn extract_single_file(
     archive: Arc<Box<dyn Archive>>,
     entry: FileEntry,
     file_path: PathBuf,
 ) -> anyhow::Result<PathBuf> {
-    let file_contents = archive.extract(&entry)?;
-    let mut output_file_name = PathBuf::from(
-        file_path
-            .parent()
-            .context("Could not get parent directory")?,
-    );
+    let output_dir = file_path
+        .parent()
+        .context("Could not get parent directory")?;
+    write_single_file(&archive, &entry, output_dir)
+}
+
+/// Ask user for output directory, None when dialog was cancelled
+pub async fn extract_single_file_to(
+    archive: Arc<Box<dyn Archive>>,
+    entry: FileEntry,
+) -> anyhow::Result<Option<PathBuf>> {
+    match rfd::AsyncFileDialog::new()
+        .set_title(&i18n::tr("extract_to_title"))
+        .pick_folder()
+        .await
+    {
+        Some(output_dir) => {
+            write_single_file(&archive, &entry, output_dir.path()).map(Some)
+        }
+        None => Ok(None),
+    }
+}
+
+fn write_single_file(
+    archive: &Arc<Box<dyn Archive>>,
+    entry: &FileEntry,
+    output_dir: &Path,
+) -> anyhow::Result<PathBuf> {
+    let file_contents = archive.extract(entry)?;
+    let mut output_file_name = PathBuf::from(output_dir);
     output_file_name.push(&entry.file_name);
     log::info!("Extracting resource: {:?} {:X?}", output_file_name, entry);
-    file_contents.write_contents(&output_file_name, Some(&archive))?;
+    file_contents.write_contents(&output_file_name, Some(archive))?;
     Ok(output_file_name)
 }
 
-pub async fn extract_all(
+/// Same layout as command line extraction with default output directory,
+/// `ext/<archive_stem>/` next to archive
+fn archive_output_dir(file_path: &Path) -> anyhow::Result<PathBuf> {
+    let output_path = file_path
+        .parent()
+        .context("Could not get parent directory")?
+        .join(OUTPUT_DIR);
+    pipeline::archive_output_dir(&output_path, file_path, true)
+}
+
+/// Tracker reporting count of extracted entries to footer of job's tab,
+/// about every percent of them so UI is not flooded with messages
+fn job_progress(files: &[FileEntry], context: &JobContext) -> ProgressTracker {
+    let context = context.clone();
+    let step = (files.len() as u64 / 100).max(1);
+    ProgressTracker::new(files).with_callback(move |progress| {
+        if progress.entries_done % step == 0 {
+            context.report(Status::Normal(i18n::tr_args(
+                "extracting_progress",
+                &[
+                    progress.entries_done.to_string(),
+                    progress.entries_total.to_string(),
+                ],
+            )));
+        }
+    })
+}
+
+pub fn extract_all(
     archive: Arc<Box<dyn Archive>>,
     files: Vec<FileEntry>,
     file_path: PathBuf,
-) -> anyhow::Result<PathBuf> {
-    let mut extract_path = file_path
-        .file_name()
-        .context("Could not get file name")?
-        .to_os_string();
-    extract_path.push("_ext");
-    let mut output_path = PathBuf::from(
-        file_path
-            .parent()
-            .context("Could not get parent directory")?,
-    );
-    output_path.push(extract_path);
-    files
-        .par_iter()
-        .try_for_each::<_, anyhow::Result<()>>(|entry| {
-            let file_contents = archive.extract(entry)?;
-            let mut output_file_path = output_path.clone();
-            output_file_path.push(&entry.full_path);
-            std::fs::create_dir_all(
-                &output_file_path
-                    .parent()
-                    .context("Could not get parent directory")?,
-            )?;
-            log::info!(
-                "Extracting resource: {:?} {:X?}",
-                output_file_path,
-                entry
-            );
-            file_contents.write_contents(&output_file_path, Some(&archive))?;
-            Ok(())
-        })?;
-    Ok(output_path)
+    context: &JobContext,
+) -> anyhow::Result<(PathBuf, Progress)> {
+    let output_path = archive_output_dir(&file_path)?;
+    let progress = job_progress(&files, context);
+    pipeline::extract_entries(
+        &archive,
+        &files,
+        &output_path,
+        &progress,
+        None,
+        &ExtractOptions {
+            keep_going: true,
+            ..Default::default()
+        },
+    )?;
+    Ok((output_path, progress.progress()))
 }
 
-pub async fn extract_all_with_convert(
+/// Conversion checks job between entries, so cancelling it stops
+/// remaining entries from being written
+pub fn extract_all_with_convert(
     archive: Arc<Box<dyn Archive>>,
     files: Vec<FileEntry>,
     file_path: PathBuf,
+    context: &JobContext,
 ) -> anyhow::Result<PathBuf> {
-    let mut extract_path = file_path
-        .file_name()
-        .context("Could not get file name")?
-        .to_os_string();
-    extract_path.push("_ext");
-    let mut output_path = PathBuf::from(
-        file_path
-            .parent()
-            .context("Could not get parent directory")?,
-    );
-    output_path.push(extract_path);
+    let output_path = archive_output_dir(&file_path)?;
+    let progress = job_progress(&files, context);
     files
         .par_iter()
         .try_for_each::<_, anyhow::Result<()>>(|entry| {
-            match convert::convert_resource_blocking(
+            if context.is_cancelled() {
+                return Err(AkaibuError::Cancelled.into());
+            }
+            if convert::convert_resource_blocking(
                 &archive,
                 &entry,
                 &output_path,
-            ) {
-                Ok(_) => Ok(()),
-                Err(_) => {
-                    let file_contents = archive.extract(entry)?;
-                    let mut output_file_path = output_path.clone();
-                    output_file_path.push(&entry.full_path);
-                    std::fs::create_dir_all(
-                        &output_file_path
-                            .parent()
-                            .context("Could not get parent directory")?,
-                    )?;
-                    log::info!(
-                        "Extracting resource: {:?} {:X?}",
-                        output_file_path,
-                        entry
-                    );
-                    file_contents
-                        .write_contents(&output_file_path, Some(&archive))?;
-                    Ok(())
-                }
+            )
+            .is_err()
+            {
+                let file_contents = archive.extract(entry)?;
+                let mut output_file_path = output_path.clone();
+                output_file_path.push(entry.output_path());
+                std::fs::create_dir_all(
+                    &output_file_path
+                        .parent()
+                        .context("Could not get parent directory")?,
+                )?;
+                log::info!(
+                    "Extracting resource: {:?} {:X?}",
+                    output_file_path,
+                    entry
+                );
+                file_contents
+                    .write_contents(&output_file_path, Some(&archive))?;
             }
+            progress.entry_done(entry.file_size, 0);
+            Ok(())
         })?;
     Ok(output_path)
 }
@@ -0,0 +1,174 @@
+use crate::message::{Message, Status};
+use akaibu::cancel::{self, CancellationToken};
+use iced::{
+    futures::{
+        channel::mpsc::{self, UnboundedReceiver, UnboundedSender},
+        stream::{self, BoxStream, StreamExt},
+    },
+    Command, Subscription,
+};
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+/// Identifies running job until its result comes back
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct JobId(u64);
+
+/// Background operation of tab of given file
+#[derive(Debug)]
+pub struct Job {
+    pub file: PathBuf,
+    pub label: String,
+    token: CancellationToken,
+}
+
+/// Handle given to running job, reports its progress to tab it was started
+/// for and tells whether it was cancelled
+#[derive(Debug, Clone)]
+pub struct JobContext {
+    file: PathBuf,
+    token: CancellationToken,
+    sender: UnboundedSender<Message>,
+}
+
+impl JobContext {
+    pub fn is_cancelled(&self) -> bool {
+        self.token.is_cancelled()
+    }
+    /// Show status in footer of job's tab while it runs
+    pub fn report(&self, status: Status) {
+        // Receiver is gone only when app is closing
+        let _ = self
+            .sender
+            .unbounded_send(Message::JobProgress(self.file.clone(), status));
+    }
+}
+
+/// Owns every background job of app. Jobs run on worker threads, their
+/// progress comes back through channel drained by [`JobManager::subscription`]
+/// and their results as [`Message::JobFinished`], so closing or switching
+/// tabs never loses track of them.
+pub struct JobManager {
+    next_id: u64,
+    jobs: HashMap<JobId, Job>,
+    sender: UnboundedSender<Message>,
+    /// Taken by first subscription, iced keeps its stream while hash of
+    /// subscription stays the same
+    receiver: Arc<Mutex<Option<UnboundedReceiver<Message>>>>,
+}
+
+impl JobManager {
+    pub fn new() -> Self {
+        let (sender, receiver) = mpsc::unbounded();
+        Self {
+            next_id: 0,
+            jobs: HashMap::new(),
+            sender,
+            receiver: Arc::new(Mutex::new(Some(receiver))),
+        }
+    }
+    /// Run operation on worker thread, its result is turned into message by
+    /// `on_finished` once job ends or is cancelled
+    pub fn spawn<T, F, M>(
+        &mut self,
+        file: &Path,
+        label: String,
+        operation: F,
+        on_finished: M,
+    ) -> Command<Message>
+    where
+        T: Send + 'static,
+        F: FnOnce(&JobContext) -> anyhow::Result<T> + Send + 'static,
+        M: Fn(anyhow::Result<T>) -> Message + Send + 'static,
+    {
+        let id = JobId(self.next_id);
+        self.next_id += 1;
+        let context = JobContext {
+            file: file.to_path_buf(),
+            token: CancellationToken::new(),
+            sender: self.sender.clone(),
+        };
+        log::debug!("Starting job {:?}: {}", id, label);
+        self.jobs.insert(
+            id,
+            Job {
+                file: file.to_path_buf(),
+                label,
+                token: context.token.clone(),
+            },
+        );
+        Command::perform(
+            async move {
+                let token = context.token.clone();
+                cancel::run_cancellable(&token, move || operation(&context))
+            },
+            move |result| {
+                Message::JobFinished(id, Box::new(on_finished(result)))
+            },
+        )
+    }
+    /// Forget finished job, returning it so its tab can be updated
+    pub fn finish(&mut self, id: JobId) -> Option<Job> {
+        self.jobs.remove(&id)
+    }
+    pub fn is_busy(&self, file: &Path) -> bool {
+        self.jobs.values().any(|job| job.file == file)
+    }
+    /// Ask jobs of file to stop, they are finished when their results come
+    /// back. Returns whether any job was running.
+    pub fn cancel(&self, file: &Path) -> bool {
+        let mut cancelled = false;
+        for job in self.jobs.values().filter(|job| job.file == file) {
+            log::debug!("Cancelling job: {}", job.label);
+            job.token.cancel();
+            cancelled = true;
+        }
+        cancelled
+    }
+    /// Progress messages reported by running jobs
+    pub fn subscription(&self) -> Subscription<Message> {
+        Subscription::from_recipe(JobEvents(self.receiver.clone()))
+    }
+}
+
+impl Default for JobManager {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for JobManager {
+    fn drop(&mut self) {
+        for job in self.jobs.values() {
+            job.token.cancel();
+        }
+    }
+}
+
+/// Stream of messages sent through [`JobContext::report`]
+struct JobEvents(Arc<Mutex<Option<UnboundedReceiver<Message>>>>);
+
+impl<H, I> iced_native::subscription::Recipe<H, I> for JobEvents
+where
+    H: Hasher,
+{
+    type Output = Message;
+
+    fn hash(&self, state: &mut H) {
+        std::any::TypeId::of::<Self>().hash(state);
+    }
+
+    fn stream(
+        self: Box<Self>,
+        _input: BoxStream<'static, I>,
+    ) -> BoxStream<'static, Self::Output> {
+        match self.0.lock().ok().and_then(|mut receiver| receiver.take()) {
+            Some(receiver) => receiver.boxed(),
+            None => stream::empty().boxed(),
+        }
+    }
+}
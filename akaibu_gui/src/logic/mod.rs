@@ -1,3 +1,4 @@
 pub mod convert;
 pub mod extract;
+pub mod jobs;
 pub mod preview;
@@ -1,21 +1,35 @@
-use std::sync::Arc;
+use std::{sync::Arc, time::Instant};
 
-use akaibu::{archive::Archive, archive::FileEntry, resource::ResourceType};
+use akaibu::{
+    archive::Archive,
+    archive::FileEntry,
+    resource::{ConvertedMeta, ResourceType},
+};
 use anyhow::Context;
 
-pub async fn get_resource_type(
+/// Convert entry for preview, returning details shown below it
+pub fn convert_entry(
     archive: Arc<Box<dyn Archive>>,
     entry: FileEntry,
-) -> anyhow::Result<ResourceType> {
+) -> anyhow::Result<(ResourceType, ConvertedMeta)> {
     let file_contents = archive.extract(&entry)?;
-    file_contents
-        .get_resource_type()
+    let resource_magic = file_contents.get_resource_type();
+    let scheme = resource_magic
         .get_schemes()
-        .get(0)
-        .context("Unknown resource format")?
-        .convert_from_bytes(
-            &entry.full_path,
-            file_contents.contents.to_vec(),
-            Some(&archive),
-        )
+        .into_iter()
+        .next()
+        .context("Unknown resource format")?;
+    let start = Instant::now();
+    let resource = scheme.convert_from_bytes(
+        &entry.full_path,
+        file_contents.contents.to_vec(),
+        Some(&archive),
+    )?;
+    let meta = ConvertedMeta::new(
+        scheme.as_ref(),
+        &resource_magic,
+        &resource,
+        start.elapsed(),
+    );
+    Ok((resource, meta))
 }
@@ -8,6 +8,7 @@
 #![windows_subsystem = "windows"]
 
 mod app;
+mod i18n;
 mod logic;
 mod message;
 mod style;
@@ -15,6 +16,7 @@ mod ui;
 mod update;
 
 use app::App;
+use i18n::Language;
 use iced::{window, Application, Settings};
 use std::path::PathBuf;
 use structopt::StructOpt;
@@ -22,9 +24,13 @@ use structopt::StructOpt;
 #[derive(StructOpt, Debug)]
 #[structopt()]
 pub(crate) struct Opt {
-    /// File to process
+    /// Files to process, each is opened in its own tab
     #[structopt(required = true, name = "ARCHIVE", parse(from_os_str))]
-    pub(crate) file: PathBuf,
+    pub(crate) files: Vec<PathBuf>,
+
+    /// Interface language: en or ja. Defaults to system locale
+    #[structopt(long)]
+    pub(crate) lang: Option<Language>,
 }
 
 fn main() -> Result<(), iced::Error> {
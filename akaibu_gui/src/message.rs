@@ -1,7 +1,7 @@
-use crate::ui::resource::ConvertFormat;
+use crate::{logic::jobs::JobId, ui::resource::ConvertFormat};
 use akaibu::{
     archive::FileEntry,
-    resource::{ResourceScheme, ResourceType},
+    resource::{ConvertedMeta, ResourceScheme, ResourceType},
     scheme::Scheme,
 };
 use std::path::PathBuf;
@@ -17,16 +17,42 @@ pub enum Message {
     ConvertFile(FileEntry),
     ExtractFile(FileEntry),
     PreviewFile(FileEntry),
+    ExtractFileTo(FileEntry),
+    CopyPath(FileEntry),
+    OpenContextMenu(FileEntry),
+    CloseContextMenu,
     SetStatus(Status),
-    OpenPreview(ResourceType, String),
+    /// Preview converted for tab of given file
+    OpenPreview(PathBuf, ResourceType, ConvertedMeta, String),
+    /// Loose resource converted for tab of given file
+    ResourceConverted(PathBuf, ResourceType),
+    /// Job of tab of given file ended with status
+    ConversionFinished(PathBuf, Status),
+    /// Status reported by running job of tab of given file
+    JobProgress(PathBuf, Status),
+    /// Job ended, its result is handled as wrapped message
+    JobFinished(JobId, Box<Message>),
+    CancelConversion,
     ClosePreview,
     ConvertAllToggle(bool),
     PatternChanged(String),
+    PasswordChanged(String),
+    /// Open archive of current tab with entered password
+    SubmitPassword,
     FormatChanged(ConvertFormat),
     SaveResource,
     NextSprite,
     PrevSprite,
     SaveSprite(usize),
+    ToggleLanguage,
+    ReloadKeys,
+    /// Show or hide version and scheme database info
+    ToggleAbout,
+    CopyBuildInfo,
+    SelectTab(usize),
+    CloseTab(usize),
+    OpenArchives,
+    AddTabs(Vec<PathBuf>),
     Error(String),
 }
 
@@ -110,6 +110,38 @@ impl button::StyleSheet for Dark {
     }
 }
 
+/// Tab bar button, selected tab blends into content below
+pub struct TabButton {
+    pub selected: bool,
+}
+
+impl button::StyleSheet for TabButton {
+    fn active(&self) -> button::Style {
+        button::Style {
+            shadow_offset: Vector::new(0.0, 0.0),
+            background: Some(Background::Color(if self.selected {
+                DARK_BUTTON_FOCUSED
+            } else {
+                DARK
+            })),
+            border_radius: 0.0,
+            border_width: 0.0,
+            border_color: BORDER_COLOR,
+            text_color: if self.selected {
+                DARK_SELECTION
+            } else {
+                TEXT_COLOR
+            },
+        }
+    }
+    fn hovered(&self) -> button::Style {
+        button::Style {
+            background: Some(Background::Color(DARK_BUTTON_FOCUSED)),
+            ..self.active()
+        }
+    }
+}
+
 impl text_input::StyleSheet for Dark {
     fn active(&self) -> text_input::Style {
         text_input::Style {
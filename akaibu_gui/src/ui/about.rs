@@ -0,0 +1,70 @@
+use crate::{i18n, message::Message, style};
+use akaibu::build_info::BuildInfo;
+use iced::{
+    button, scrollable, Button, Column, Container, Element, Length, Row,
+    Scrollable, Space, Text,
+};
+
+/// Version and scheme database state, copied into issue reports
+pub struct AboutContent {
+    info: BuildInfo,
+    scroll_state: scrollable::State,
+    copy_button_state: button::State,
+    close_button_state: button::State,
+}
+
+impl AboutContent {
+    pub fn new() -> Self {
+        Self {
+            info: akaibu::build_info(),
+            scroll_state: scrollable::State::new(),
+            copy_button_state: button::State::new(),
+            close_button_state: button::State::new(),
+        }
+    }
+    pub fn view(&mut self) -> Element<'_, Message> {
+        let info = self
+            .info
+            .to_string()
+            .lines()
+            .fold(Scrollable::new(&mut self.scroll_state), |column, line| {
+                column.push(Text::new(line).size(14))
+            })
+            .width(Length::Fill)
+            .height(Length::Fill);
+        let buttons = Row::new()
+            .spacing(5)
+            .push(Space::new(Length::Fill, Length::Units(0)))
+            .push(
+                Button::new(
+                    &mut self.copy_button_state,
+                    Text::new(i18n::tr("copy_build_info")).size(14),
+                )
+                .on_press(Message::CopyBuildInfo)
+                .style(style::Dark::default()),
+            )
+            .push(
+                Button::new(
+                    &mut self.close_button_state,
+                    Text::new(i18n::tr("close")).size(14),
+                )
+                .on_press(Message::ToggleAbout)
+                .style(style::Dark::default()),
+            );
+        Container::new(
+            Column::new()
+                .spacing(5)
+                .padding(10)
+                .push(Text::new(i18n::tr("about")).size(30))
+                .push(info)
+                .push(buttons),
+        )
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(style::Dark {
+            border_width: 0.0,
+            ..Default::default()
+        })
+        .into()
+    }
+}
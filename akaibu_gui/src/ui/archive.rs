@@ -1,6 +1,7 @@
 use crate::{
-    message::Message, message::Status, style, ui::footer::Footer,
-    ui::preview::Preview,
+    i18n, message::Message, message::Status, style,
+    ui::context_area::ContextArea, ui::context_menu::ContextMenu,
+    ui::footer::Footer, ui::preview::Preview,
 };
 use akaibu::archive;
 use anyhow::Context;
@@ -79,7 +80,7 @@ impl ArchiveContent {
                     .push(
                         Button::new(
                             &mut self.extract_all_button_state,
-                            Text::new("Extract all"),
+                            Text::new(i18n::tr("extract_all")),
                         )
                         .on_press(Message::ExtractAll)
                         .style(style::Dark::default()),
@@ -88,7 +89,7 @@ impl ArchiveContent {
                         Container::new(
                             Checkbox::new(
                                 self.convert_all,
-                                "Convert all",
+                                i18n::tr("convert_all"),
                                 Message::ConvertAllToggle,
                             )
                             .text_size(16)
@@ -102,7 +103,7 @@ impl ArchiveContent {
                     .push({
                         let back_button = Button::new(
                             &mut self.back_dir_button_state,
-                            Text::new("Back dir"),
+                            Text::new(i18n::tr("back_dir")),
                         )
                         .style(style::Dark::default());
                         if self.navigable_dir.has_parent() {
@@ -114,7 +115,7 @@ impl ArchiveContent {
                     .push(
                         TextInput::new(
                             &mut self.pattern_text_input,
-                            "Search...",
+                            &i18n::tr("search"),
                             &self.pattern,
                             Message::PatternChanged,
                         )
@@ -128,15 +129,15 @@ impl ArchiveContent {
                 Row::new()
                     .push(Space::new(Length::Units(5), Length::Units(0)))
                     .push(
-                        Container::new(Text::new("Name").size(18))
+                        Container::new(Text::new(i18n::tr("name")).size(18))
                             .width(Length::FillPortion(1)),
                     )
                     .push(
-                        Container::new(Text::new("Size").size(18))
+                        Container::new(Text::new(i18n::tr("size")).size(18))
                             .width(Length::Units(80)),
                     )
                     .push(
-                        Container::new(Text::new("Actions").size(18))
+                        Container::new(Text::new(i18n::tr("actions")).size(18))
                             .width(Length::Units(210)),
                     ),
             )
@@ -196,9 +197,34 @@ impl ArchiveContent {
         self.pattern = String::new();
         Ok(())
     }
+    /// Show context menu under given file, closing any other
+    pub fn open_context_menu(&mut self, file_entry: &archive::FileEntry) {
+        for entry in self.entries.iter_mut() {
+            if let Entry::File {
+                file, context_menu, ..
+            } = entry
+            {
+                *context_menu = if file.full_path == file_entry.full_path {
+                    Some(ContextMenu::new())
+                } else {
+                    None
+                };
+            }
+        }
+    }
+    pub fn close_context_menu(&mut self) {
+        for entry in self.entries.iter_mut() {
+            if let Entry::File { context_menu, .. } = entry {
+                *context_menu = None;
+            }
+        }
+    }
     pub fn set_status(&mut self, status: Status) {
         self.footer.set_status(status);
     }
+    pub fn set_busy(&mut self, busy: bool) {
+        self.footer.set_busy(busy);
+    }
     pub fn set_progress(&mut self, progress: f32) {
         self.footer.set_progress(progress);
     }
@@ -217,6 +243,7 @@ impl ArchiveContent {
                 convert_button_state: button::State::new(),
                 extract_button_state: button::State::new(),
                 preview_button_state: button::State::new(),
+                context_menu: None,
             }))
             .collect()
     }
@@ -233,6 +260,7 @@ enum Entry {
         convert_button_state: button::State,
         extract_button_state: button::State,
         preview_button_state: button::State,
+        context_menu: Option<ContextMenu>,
     },
 }
 
@@ -287,9 +315,11 @@ impl Entry {
                         Container::new(
                             Button::new(
                                 open_button_state,
-                                Container::new(Text::new("Open").size(16))
-                                    .center_y()
-                                    .center_x(),
+                                Container::new(
+                                    Text::new(i18n::tr("open")).size(16),
+                                )
+                                .center_y()
+                                .center_x(),
                             )
                             .on_press(Message::OpenDirectory(dir_name.clone()))
                             .width(Length::Units(65))
@@ -311,6 +341,7 @@ impl Entry {
                 convert_button_state,
                 extract_button_state,
                 preview_button_state,
+                context_menu,
             } => {
                 let content = Row::new()
                     .push(Space::new(Length::Units(5), Length::Units(0)))
@@ -353,9 +384,11 @@ impl Entry {
                         Container::new(
                             Button::new(
                                 convert_button_state,
-                                Container::new(Text::new("Convert").size(16))
-                                    .center_y()
-                                    .center_x(),
+                                Container::new(
+                                    Text::new(i18n::tr("convert")).size(16),
+                                )
+                                .center_y()
+                                .center_x(),
                             )
                             .on_press(Message::ConvertFile(file.clone()))
                             .width(Length::Units(65))
@@ -372,9 +405,11 @@ impl Entry {
                         Container::new(
                             Button::new(
                                 extract_button_state,
-                                Container::new(Text::new("Extract").size(16))
-                                    .center_y()
-                                    .center_x(),
+                                Container::new(
+                                    Text::new(i18n::tr("extract")).size(16),
+                                )
+                                .center_y()
+                                .center_x(),
                             )
                             .on_press(Message::ExtractFile(file.clone()))
                             .width(Length::Units(65))
@@ -391,9 +426,11 @@ impl Entry {
                         Container::new(
                             Button::new(
                                 preview_button_state,
-                                Container::new(Text::new("Preview").size(16))
-                                    .center_y()
-                                    .center_x(),
+                                Container::new(
+                                    Text::new(i18n::tr("preview")).size(16),
+                                )
+                                .center_y()
+                                .center_x(),
                             )
                             .on_press(Message::PreviewFile(file.clone()))
                             .width(Length::Units(65))
@@ -408,7 +445,17 @@ impl Entry {
                     )
                     .push(Space::new(Length::Units(5), Length::Units(0)))
                     .height(Length::Units(30));
-                Container::new(content).into()
+                let row = ContextArea::new(
+                    Container::new(content),
+                    Message::OpenContextMenu(file.clone()),
+                );
+                match context_menu {
+                    Some(context_menu) => Column::new()
+                        .push(row)
+                        .push(context_menu.view(file))
+                        .into(),
+                    None => row.into(),
+                }
             }
         }
     }
@@ -1,11 +1,18 @@
 use crate::{
     message::Message,
     ui::{
-        archive::ArchiveContent, resource::ResourceContent,
-        scheme::SchemeContent,
+        archive::ArchiveContent, password::PasswordContent,
+        resource::ResourceContent, scheme::SchemeContent,
     },
 };
+use akaibu::{
+    error, magic,
+    resource::{ResourceMagic, ResourceScheme},
+    scheme::Scheme,
+};
+use anyhow::Context;
 use iced::Element;
+use std::path::Path;
 
 use super::resource_scheme::ResourceSchemeContent;
 
@@ -14,15 +21,104 @@ pub enum Content {
     ResourceSchemeView(ResourceSchemeContent),
     ArchiveView(Box<ArchiveContent>),
     ResourceView(ResourceContent),
+    PasswordView(PasswordContent),
 }
 
 impl Content {
+    /// Open archive or resource with universal scheme, or let user pick
+    /// scheme when there is more than one. Resources are not converted
+    /// yet, see [`Content::universal_resource_scheme`].
+    pub fn open(file: &Path) -> anyhow::Result<Self> {
+        let sniffed = magic::sniff(file)?;
+        let archive = magic::Archive::detect(file, &sniffed);
+
+        if let magic::Archive::NotRecognized = archive {
+            let mut resource = ResourceMagic::parse_magic(&sniffed.head);
+            if let ResourceMagic::Unrecognized = resource {
+                resource = ResourceMagic::parse_file_extension(file);
+            }
+            if let ResourceMagic::Unrecognized = resource {
+                return Ok(Content::SchemeView(SchemeContent::new(
+                    magic::Archive::get_all_schemes(),
+                    "archive_not_recognized".to_string(),
+                )));
+            } else if resource.is_universal() {
+                return Ok(Content::ResourceSchemeView(
+                    ResourceSchemeContent::converting(
+                        resource
+                            .get_schemes()
+                            .into_iter()
+                            .next()
+                            .context("Unknown resource format")?,
+                        file.to_path_buf(),
+                    ),
+                ));
+            } else {
+                return Ok(Content::ResourceSchemeView(
+                    ResourceSchemeContent::new(
+                        resource.get_schemes(),
+                        "select_convert_scheme".to_string(),
+                        file.to_path_buf(),
+                    ),
+                ));
+            }
+        }
+
+        let schemes = archive.get_schemes();
+
+        if archive.is_universal() {
+            let scheme = schemes
+                .into_iter()
+                .next()
+                .expect("Expected universal scheme");
+            Content::extract(scheme, file, None)
+        } else {
+            Ok(Content::SchemeView(SchemeContent::new(
+                schemes,
+                "select_extract_scheme".to_string(),
+            )))
+        }
+    }
+    /// Archive extracted with scheme, or password prompt when scheme needs
+    /// password that was not given or is wrong
+    pub fn extract(
+        scheme: Box<dyn Scheme>,
+        file: &Path,
+        password: Option<&str>,
+    ) -> anyhow::Result<Self> {
+        let extracted = match password {
+            Some(password) => scheme.extract_with_password(file, password),
+            None => scheme.extract(file),
+        };
+        match extracted {
+            Ok((archive, dir)) => Ok(Content::ArchiveView(Box::new(
+                ArchiveContent::new(archive, dir),
+            ))),
+            Err(err) if error::password_required(&err) => {
+                log::debug!("{:?}: {}", file, err);
+                Ok(Content::PasswordView(PasswordContent::new(
+                    scheme,
+                    password.is_some(),
+                )))
+            }
+            Err(err) => Err(err),
+        }
+    }
+    /// Scheme resource opened with universal scheme should be converted
+    /// with in background
+    pub fn universal_resource_scheme(&self) -> Option<Box<dyn ResourceScheme>> {
+        match self {
+            Content::ResourceSchemeView(content) => content.universal_scheme(),
+            _ => None,
+        }
+    }
     pub fn view(&mut self) -> Element<'_, Message> {
         match self {
             Content::ArchiveView(content) => content.view(),
             Content::SchemeView(content) => content.view(),
             Content::ResourceView(content) => content.view(),
             Content::ResourceSchemeView(content) => content.view(),
+            Content::PasswordView(content) => content.view(),
         }
     }
 }
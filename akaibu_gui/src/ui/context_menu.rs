@@ -0,0 +1,77 @@
+use crate::{i18n, message::Message, style};
+use akaibu::archive::FileEntry;
+use iced::{button, Button, Container, Element, Length, Row, Space, Text};
+
+/// Actions for single archive entry shown after right clicking it
+#[derive(Default)]
+pub struct ContextMenu {
+    copy_path_button_state: button::State,
+    extract_button_state: button::State,
+    extract_to_button_state: button::State,
+    convert_button_state: button::State,
+    preview_button_state: button::State,
+    close_button_state: button::State,
+}
+
+impl ContextMenu {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn view(&mut self, file: &FileEntry) -> Element<'_, Message> {
+        let content = Row::new()
+            .spacing(5)
+            .push(Space::new(Length::Units(30), Length::Units(0)))
+            .push(Self::menu_button(
+                &mut self.copy_path_button_state,
+                &i18n::tr("copy_full_path"),
+                Message::CopyPath(file.clone()),
+            ))
+            .push(Self::menu_button(
+                &mut self.extract_button_state,
+                &i18n::tr("extract"),
+                Message::ExtractFile(file.clone()),
+            ))
+            .push(Self::menu_button(
+                &mut self.extract_to_button_state,
+                &i18n::tr("extract_to"),
+                Message::ExtractFileTo(file.clone()),
+            ))
+            .push(Self::menu_button(
+                &mut self.convert_button_state,
+                &i18n::tr("convert"),
+                Message::ConvertFile(file.clone()),
+            ))
+            .push(Self::menu_button(
+                &mut self.preview_button_state,
+                &i18n::tr("preview"),
+                Message::PreviewFile(file.clone()),
+            ))
+            .push(Space::new(Length::Fill, Length::Units(0)))
+            .push(Self::menu_button(
+                &mut self.close_button_state,
+                &i18n::tr("close"),
+                Message::CloseContextMenu,
+            ))
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .height(Length::Units(30));
+        Container::new(content)
+            .center_y()
+            .style(style::Dark::default())
+            .into()
+    }
+    fn menu_button<'a>(
+        state: &'a mut button::State,
+        label: &str,
+        message: Message,
+    ) -> Button<'a, Message> {
+        Button::new(
+            state,
+            Container::new(Text::new(label).size(16))
+                .center_y()
+                .center_x(),
+        )
+        .on_press(message)
+        .height(Length::Units(25))
+        .style(style::Dark::default())
+    }
+}
@@ -1,13 +1,18 @@
-use crate::{message::Message, message::Status, style};
+use crate::{i18n, message::Message, message::Status, style};
 use iced::{
-    Background, Container, Element, Length, ProgressBar, Row, Space, Text,
-    VerticalAlignment,
+    button, Background, Button, Container, Element, Length, ProgressBar, Row,
+    Space, Text, VerticalAlignment,
 };
 
 pub struct Footer {
     current_dir: String,
     progress: f32,
     status: Status,
+    /// Conversion is running and can be cancelled
+    busy: bool,
+    cancel_button_state: button::State,
+    language_button_state: button::State,
+    reload_keys_button_state: button::State,
 }
 
 impl Footer {
@@ -16,10 +21,14 @@ impl Footer {
             current_dir: String::from("/"),
             progress: 0.0,
             status: Status::Normal(String::new()),
+            busy: false,
+            cancel_button_state: button::State::new(),
+            language_button_state: button::State::new(),
+            reload_keys_button_state: button::State::new(),
         }
     }
     pub fn view(&mut self) -> Element<'_, Message> {
-        let content = Row::new()
+        let mut content = Row::new()
             .push(Space::new(Length::Units(5), Length::Units(0)))
             .push(
                 Text::new(&self.current_dir)
@@ -60,7 +69,52 @@ impl Footer {
                     .height(Length::Fill)
                     .vertical_alignment(VerticalAlignment::Center),
                 Status::Empty => Text::new(""),
-            })
+            });
+        if self.busy {
+            content = content
+                .push(Space::new(Length::Units(15), Length::Units(0)))
+                .push(
+                    Button::new(
+                        &mut self.cancel_button_state,
+                        Text::new(i18n::tr("cancel")).size(14),
+                    )
+                    .on_press(Message::CancelConversion)
+                    .padding(0)
+                    .style(style::Dark {
+                        border_width: 0.0,
+                        background: Background::Color(
+                            style::DARK_BUTTON_FOCUSED,
+                        ),
+                    }),
+                );
+        }
+        let content = content
+            .push(Space::new(Length::Units(15), Length::Units(0)))
+            .push(
+                Button::new(
+                    &mut self.reload_keys_button_state,
+                    Text::new(i18n::tr("reload_keys")).size(14),
+                )
+                .on_press(Message::ReloadKeys)
+                .padding(0)
+                .style(style::Dark {
+                    border_width: 0.0,
+                    background: Background::Color(style::DARK_BUTTON_FOCUSED),
+                }),
+            )
+            .push(Space::new(Length::Units(15), Length::Units(0)))
+            .push(
+                Button::new(
+                    &mut self.language_button_state,
+                    Text::new(i18n::tr("switch_language")).size(14),
+                )
+                .on_press(Message::ToggleLanguage)
+                .padding(0)
+                .style(style::Dark {
+                    border_width: 0.0,
+                    background: Background::Color(style::DARK_BUTTON_FOCUSED),
+                }),
+            )
             .push(Space::new(Length::Units(5), Length::Units(0)));
         Container::new(content)
             .height(Length::Units(20))
@@ -77,6 +131,9 @@ impl Footer {
     pub fn set_status(&mut self, status: Status) {
         self.status = status;
     }
+    pub fn set_busy(&mut self, busy: bool) {
+        self.busy = busy;
+    }
     pub fn set_progress(&mut self, progress: f32) {
         self.progress = progress;
     }
@@ -1,7 +1,12 @@
+pub mod about;
 pub mod archive;
 pub mod content;
+pub mod context_area;
+pub mod context_menu;
 pub mod footer;
+pub mod password;
 pub mod preview;
 pub mod resource;
 pub mod resource_scheme;
 pub mod scheme;
+pub mod tabs;
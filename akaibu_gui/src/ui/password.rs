@@ -0,0 +1,97 @@
+use crate::{
+    i18n,
+    message::{Message, Status},
+    style,
+    ui::footer::Footer,
+};
+use akaibu::scheme::Scheme;
+use iced::{
+    button, text_input, Button, Column, Container, Element, Length, Row, Space,
+    Text, TextInput,
+};
+
+/// Asks for password of archive whose scheme reported it is required
+pub struct PasswordContent {
+    pub scheme: Box<dyn Scheme>,
+    pub password: String,
+    password_text_input: text_input::State,
+    open_button_state: button::State,
+    footer: Footer,
+}
+
+impl PasswordContent {
+    /// Prompt shown again after `wrong` password was given
+    pub fn new(scheme: Box<dyn Scheme>, wrong: bool) -> Self {
+        let mut footer = Footer::new();
+        if wrong {
+            footer.set_status(Status::Error(i18n::tr("wrong_password")));
+        }
+        Self {
+            scheme,
+            password: String::new(),
+            password_text_input: text_input::State::focused(),
+            open_button_state: button::State::new(),
+            footer,
+        }
+    }
+    pub fn view(&mut self) -> Element<'_, Message> {
+        let prompt = Container::new(
+            Row::new()
+                .spacing(5)
+                .width(Length::Units(400))
+                .push(
+                    TextInput::new(
+                        &mut self.password_text_input,
+                        &i18n::tr("password"),
+                        &self.password,
+                        Message::PasswordChanged,
+                    )
+                    .password()
+                    .padding(5)
+                    .on_submit(Message::SubmitPassword)
+                    .style(style::Dark::default()),
+                )
+                .push(
+                    Button::new(
+                        &mut self.open_button_state,
+                        Text::new(i18n::tr("open")),
+                    )
+                    .on_press(Message::SubmitPassword)
+                    .style(style::Dark::default()),
+                ),
+        )
+        .center_x()
+        .center_y()
+        .width(Length::Fill)
+        .height(Length::Fill)
+        .style(style::Dark {
+            border_width: 0.0,
+            ..Default::default()
+        });
+        let header = Container::new(
+            Column::new()
+                .push(Text::new(i18n::tr("password_required")).size(30))
+                .push(Space::new(Length::Units(0), Length::Units(5)))
+                .push(Text::new(self.scheme.get_name()).size(16)),
+        )
+        .center_x()
+        .center_y()
+        .width(Length::Fill)
+        .height(Length::Units(60))
+        .style(style::Dark {
+            border_width: 0.0,
+            ..Default::default()
+        });
+        Column::new()
+            .push(header)
+            .push(prompt)
+            .push(self.footer.view())
+            .into()
+    }
+    pub fn set_status(&mut self, status: Status) {
+        self.footer.set_status(status);
+    }
+    pub fn set_busy(&mut self, busy: bool) {
+        self.footer.set_busy(busy);
+    }
+}
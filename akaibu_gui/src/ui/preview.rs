@@ -1,5 +1,5 @@
-use crate::{message::Message, style};
-use akaibu::resource::{self, ResourceType};
+use crate::{i18n, message::Message, style};
+use akaibu::resource::{self, ConvertedMeta, ResourceType};
 use iced::{
     button,
     image::{viewer, Viewer},
@@ -19,6 +19,7 @@ static X_IMAGE_HANDLE: Lazy<iced::image::Handle> = Lazy::new(|| {
 
 pub struct Preview {
     resource: resource::ResourceType,
+    meta: Option<ConvertedMeta>,
     is_visible: bool,
     file_name: String,
     close_button_state: button::State,
@@ -32,6 +33,7 @@ impl Preview {
     pub fn new() -> Self {
         Self {
             resource: resource::ResourceType::Other,
+            meta: None,
             is_visible: false,
             file_name: String::new(),
             close_button_state: button::State::new(),
@@ -46,17 +48,17 @@ impl Preview {
             .push(Space::new(Length::Units(5), Length::Units(0)))
             .push(Text::new(&self.file_name));
         let preview = match &self.resource {
-            resource::ResourceType::SpriteSheet { sprites } => {
+            resource::ResourceType::SpriteSheet { sprites }
+            | resource::ResourceType::Animation { frames: sprites } => {
                 let bgra: ImageBuffer<image::Bgra<u8>, Vec<u8>> = sprites
                     .get(self.sprite_index)
                     .expect("Could not get sprite")
                     .convert();
                 header = header
                     .push(Space::new(Length::Units(5), Length::Units(0)))
-                    .push(Text::new(format!(
-                        "Sprite {}x{}px",
-                        bgra.width(),
-                        bgra.height()
+                    .push(Text::new(i18n::tr_args(
+                        "sprite_size",
+                        &[bgra.width().to_string(), bgra.height().to_string()],
                     )));
                 Container::new(Viewer::new(
                     &mut self.image_viewer_state,
@@ -76,10 +78,9 @@ impl Preview {
                     image.convert();
                 header = header
                     .push(Space::new(Length::Units(5), Length::Units(0)))
-                    .push(Text::new(format!(
-                        "Image {}x{}px",
-                        bgra.width(),
-                        bgra.height()
+                    .push(Text::new(i18n::tr_args(
+                        "image_size",
+                        &[bgra.width().to_string(), bgra.height().to_string()],
                     )));
                 Container::new(Viewer::new(
                     &mut self.image_viewer_state,
@@ -104,7 +105,7 @@ impl Preview {
             .width(Length::Fill)
             .height(Length::Fill),
             resource::ResourceType::Other => Container::new(
-                Text::new("No preview available...")
+                Text::new(i18n::tr("no_preview"))
                     .width(Length::Fill)
                     .height(Length::Fill)
                     .vertical_alignment(VerticalAlignment::Center)
@@ -113,8 +114,15 @@ impl Preview {
             .width(Length::Fill)
             .height(Length::Fill),
         };
+        if let Some(meta) = &self.meta {
+            header = header
+                .push(Space::new(Length::Units(10), Length::Units(0)))
+                .push(Text::new(Self::meta_text(meta)));
+        }
         header = header.push(Space::new(Length::Fill, Length::Units(0)));
-        if let ResourceType::SpriteSheet { sprites } = &self.resource {
+        if let ResourceType::SpriteSheet { sprites }
+        | ResourceType::Animation { frames: sprites } = &self.resource
+        {
             let mut prev = Button::new(
                 &mut self.prev_sprite_button_state,
                 Container::new(Text::new(" < ").size(16))
@@ -165,9 +173,11 @@ impl Preview {
     pub fn set_resource(
         &mut self,
         resource: resource::ResourceType,
+        meta: ConvertedMeta,
         file_name: String,
     ) {
         self.resource = resource;
+        self.meta = Some(meta);
         self.file_name = file_name;
         self.sprite_index = 0;
     }
@@ -177,4 +187,23 @@ impl Preview {
     pub fn dec_sprite_index(&mut self) {
         self.sprite_index -= 1;
     }
+    /// Format, pixel depth, decode time and scheme of previewed resource
+    fn meta_text(meta: &ConvertedMeta) -> String {
+        let decode_time = format!("{}", meta.decode_time.as_millis());
+        match meta.bits_per_pixel {
+            Some(bits_per_pixel) => i18n::tr_args(
+                "image_meta",
+                &[
+                    meta.format.clone(),
+                    bits_per_pixel.to_string(),
+                    decode_time,
+                    meta.scheme.clone(),
+                ],
+            ),
+            None => i18n::tr_args(
+                "resource_meta",
+                &[meta.format.clone(), decode_time, meta.scheme.clone()],
+            ),
+        }
+    }
 }
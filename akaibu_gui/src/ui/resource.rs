@@ -1,5 +1,6 @@
 use super::footer::Footer;
 use crate::{
+    i18n,
     message::{Message, Status},
     style,
 };
@@ -80,17 +81,17 @@ impl ResourceContent {
         let mut header =
             Row::new().push(Space::new(Length::Units(5), Length::Units(0)));
         let resource = match &self.resource {
-            ResourceType::SpriteSheet { sprites } => {
+            ResourceType::SpriteSheet { sprites }
+            | ResourceType::Animation { frames: sprites } => {
                 let bgra: ImageBuffer<image::Bgra<u8>, Vec<u8>> = sprites
                     .get(self.sprite_index)
                     .expect("Could not get sprite")
                     .convert();
                 header = header
                     .push(Space::new(Length::Units(5), Length::Units(0)))
-                    .push(Text::new(format!(
-                        "Sprite {}x{}px",
-                        bgra.width(),
-                        bgra.height()
+                    .push(Text::new(i18n::tr_args(
+                        "sprite_size",
+                        &[bgra.width().to_string(), bgra.height().to_string()],
                     )));
                 Container::new(Viewer::new(
                     &mut self.image_viewer_state,
@@ -110,10 +111,9 @@ impl ResourceContent {
                     image.convert();
                 header = header
                     .push(Space::new(Length::Units(5), Length::Units(0)))
-                    .push(Text::new(format!(
-                        "Image {}x{}px",
-                        bgra.width(),
-                        bgra.height()
+                    .push(Text::new(i18n::tr_args(
+                        "image_size",
+                        &[bgra.width().to_string(), bgra.height().to_string()],
                     )));
                 Container::new(Viewer::new(
                     &mut self.image_viewer_state,
@@ -138,7 +138,7 @@ impl ResourceContent {
             .width(Length::Fill)
             .height(Length::Fill),
             ResourceType::Other => Container::new(
-                Text::new("No preview available...")
+                Text::new(i18n::tr("no_preview"))
                     .width(Length::Fill)
                     .height(Length::Fill)
                     .vertical_alignment(VerticalAlignment::Center)
@@ -153,7 +153,7 @@ impl ResourceContent {
                 .push(
                     Button::new(
                         &mut self.convert_button_state,
-                        Container::new(Text::new("Save as").size(16))
+                        Container::new(Text::new(i18n::tr("save_as")).size(16))
                             .center_x()
                             .center_y(),
                     )
@@ -175,7 +175,9 @@ impl ResourceContent {
                     .text_size(16),
                 )
                 .push(Space::new(Length::Units(5), Length::Units(0)));
-        } else if let ResourceType::SpriteSheet { sprites } = &self.resource {
+        } else if let ResourceType::SpriteSheet { sprites }
+        | ResourceType::Animation { frames: sprites } = &self.resource
+        {
             let mut prev = Button::new(
                 &mut self.prev_sprite_button_state,
                 Container::new(Text::new(" < ").size(16))
@@ -205,7 +207,7 @@ impl ResourceContent {
                 .push(
                     Button::new(
                         &mut self.convert_button_state,
-                        Container::new(Text::new("Save as").size(16))
+                        Container::new(Text::new(i18n::tr("save_as")).size(16))
                             .center_x()
                             .center_y(),
                     )
@@ -248,6 +250,9 @@ impl ResourceContent {
     pub fn set_status(&mut self, status: Status) {
         self.footer.set_status(status);
     }
+    pub fn set_busy(&mut self, busy: bool) {
+        self.footer.set_busy(busy);
+    }
     pub fn inc_sprite_index(&mut self) {
         self.sprite_index += 1;
     }
@@ -1,4 +1,5 @@
 use crate::{
+    i18n,
     message::{Message, Scene, Status},
     style,
     ui::footer::Footer,
@@ -13,15 +14,18 @@ use std::path::PathBuf;
 pub struct ResourceSchemeContent {
     schemes: Vec<(Box<dyn ResourceScheme>, button::State)>,
     scrollable_state: scrollable::State,
-    message: String,
+    /// Translation key of header message
+    message_key: String,
     footer: Footer,
     file_path: PathBuf,
+    /// Only scheme is converted with right after opening
+    universal: bool,
 }
 
 impl ResourceSchemeContent {
     pub fn new(
         schemes: Vec<Box<dyn ResourceScheme>>,
-        message: String,
+        message_key: String,
         file_path: PathBuf,
     ) -> Self {
         let schemes = schemes
@@ -32,11 +36,34 @@ impl ResourceSchemeContent {
         Self {
             schemes,
             scrollable_state: scrollable::State::new(),
-            message,
+            message_key,
             footer,
             file_path,
+            universal: false,
         }
     }
+    /// Resource of universal scheme, converted in background. Scheme stays
+    /// listed so conversion can be run again after it was cancelled.
+    pub fn converting(
+        scheme: Box<dyn ResourceScheme>,
+        file_path: PathBuf,
+    ) -> Self {
+        Self {
+            universal: true,
+            ..Self::new(
+                vec![scheme],
+                "converting_resource".to_string(),
+                file_path,
+            )
+        }
+    }
+    /// Scheme to start converting with when content is opened
+    pub fn universal_scheme(&self) -> Option<Box<dyn ResourceScheme>> {
+        self.schemes
+            .first()
+            .filter(|_| self.universal)
+            .map(|(scheme, _)| scheme.clone())
+    }
     pub fn view(&mut self) -> Element<'_, Message> {
         let file_path = self.file_path.clone();
         let schemes =
@@ -70,15 +97,16 @@ impl ResourceSchemeContent {
                 border_width: 0.0,
                 ..Default::default()
             });
-        let header = Container::new(Text::new(&self.message).size(30))
-            .center_x()
-            .center_y()
-            .width(Length::Fill)
-            .height(Length::Units(40))
-            .style(style::Dark {
-                border_width: 0.0,
-                ..Default::default()
-            });
+        let header =
+            Container::new(Text::new(i18n::tr(&self.message_key)).size(30))
+                .center_x()
+                .center_y()
+                .width(Length::Fill)
+                .height(Length::Units(40))
+                .style(style::Dark {
+                    border_width: 0.0,
+                    ..Default::default()
+                });
         Column::new()
             .push(header)
             .push(schemes)
@@ -88,4 +116,7 @@ impl ResourceSchemeContent {
     pub fn set_status(&mut self, status: Status) {
         self.footer.set_status(status);
     }
+    pub fn set_busy(&mut self, busy: bool) {
+        self.footer.set_busy(busy);
+    }
 }
@@ -1,4 +1,5 @@
 use crate::{
+    i18n,
     message::{Message, Scene, Status},
     style,
     ui::footer::Footer,
@@ -12,12 +13,13 @@ use iced::{
 pub struct SchemeContent {
     schemes: Vec<(Box<dyn Scheme>, button::State)>,
     scrollable_state: scrollable::State,
-    message: String,
+    /// Translation key of header message
+    message_key: String,
     footer: Footer,
 }
 
 impl SchemeContent {
-    pub fn new(schemes: Vec<Box<dyn Scheme>>, message: String) -> Self {
+    pub fn new(schemes: Vec<Box<dyn Scheme>>, message_key: String) -> Self {
         let schemes = schemes
             .into_iter()
             .map(|scheme| (scheme, button::State::new()))
@@ -26,7 +28,7 @@ impl SchemeContent {
         Self {
             schemes,
             scrollable_state: scrollable::State::new(),
-            message,
+            message_key,
             footer,
         }
     }
@@ -59,15 +61,16 @@ impl SchemeContent {
                 border_width: 0.0,
                 ..Default::default()
             });
-        let header = Container::new(Text::new(&self.message).size(30))
-            .center_x()
-            .center_y()
-            .width(Length::Fill)
-            .height(Length::Units(40))
-            .style(style::Dark {
-                border_width: 0.0,
-                ..Default::default()
-            });
+        let header =
+            Container::new(Text::new(i18n::tr(&self.message_key)).size(30))
+                .center_x()
+                .center_y()
+                .width(Length::Fill)
+                .height(Length::Units(40))
+                .style(style::Dark {
+                    border_width: 0.0,
+                    ..Default::default()
+                });
         Column::new()
             .push(header)
             .push(schemes)
@@ -77,4 +80,7 @@ impl SchemeContent {
     pub fn set_status(&mut self, status: Status) {
         self.footer.set_status(status);
     }
+    pub fn set_busy(&mut self, busy: bool) {
+        self.footer.set_busy(busy);
+    }
 }
@@ -0,0 +1,198 @@
+use crate::{
+    i18n,
+    message::{Message, Status},
+    style,
+    ui::{about::AboutContent, content::Content},
+};
+use iced::{
+    button, Button, Column, Container, Element, Length, Row, Space, Text,
+};
+use std::path::{Path, PathBuf};
+
+/// Archive or resource opened in its own tab
+pub struct Tab {
+    pub file: PathBuf,
+    pub content: Content,
+    select_button_state: button::State,
+    close_button_state: button::State,
+}
+
+impl Tab {
+    pub fn new(file: PathBuf, content: Content) -> Self {
+        Self {
+            file,
+            content,
+            select_button_state: button::State::new(),
+            close_button_state: button::State::new(),
+        }
+    }
+    pub fn title(&self) -> String {
+        self.file
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_else(|| self.file.to_string_lossy().into_owned())
+    }
+    pub fn set_status(&mut self, status: Status) {
+        match self.content {
+            Content::ArchiveView(ref mut content) => {
+                content.set_status(status);
+            }
+            Content::SchemeView(ref mut content) => {
+                content.set_status(status);
+            }
+            Content::ResourceView(ref mut content) => {
+                content.set_status(status);
+            }
+            Content::ResourceSchemeView(ref mut content) => {
+                content.set_status(status);
+            }
+            Content::PasswordView(ref mut content) => {
+                content.set_status(status);
+            }
+        }
+    }
+    /// Disable actions starting new jobs while one of tab's jobs runs
+    pub fn set_busy(&mut self, busy: bool) {
+        match self.content {
+            Content::ArchiveView(ref mut content) => content.set_busy(busy),
+            Content::SchemeView(ref mut content) => content.set_busy(busy),
+            Content::ResourceView(ref mut content) => content.set_busy(busy),
+            Content::ResourceSchemeView(ref mut content) => {
+                content.set_busy(busy)
+            }
+            Content::PasswordView(ref mut content) => content.set_busy(busy),
+        }
+    }
+}
+
+/// Opened tabs with tab bar, always holds at least one tab
+pub struct Tabs {
+    tabs: Vec<Tab>,
+    active: usize,
+    /// Shown in place of active tab while open
+    pub about: Option<AboutContent>,
+    open_button_state: button::State,
+    about_button_state: button::State,
+}
+
+impl Tabs {
+    pub fn new(tabs: Vec<Tab>) -> Self {
+        assert!(!tabs.is_empty(), "Expected at least one tab");
+        Self {
+            tabs,
+            active: 0,
+            about: None,
+            open_button_state: button::State::new(),
+            about_button_state: button::State::new(),
+        }
+    }
+    pub fn active(&self) -> &Tab {
+        &self.tabs[self.active]
+    }
+    pub fn active_mut(&mut self) -> &mut Tab {
+        &mut self.tabs[self.active]
+    }
+    /// Tab of given file, results of conversions are routed by it as
+    /// user may switch tabs while they run
+    pub fn by_file_mut(&mut self, file: &Path) -> Option<&mut Tab> {
+        self.tabs.iter_mut().find(|tab| tab.file == file)
+    }
+    /// Add tab and switch to it
+    pub fn push(&mut self, tab: Tab) {
+        self.tabs.push(tab);
+        self.active = self.tabs.len() - 1;
+    }
+    pub fn select(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.active = index;
+        }
+    }
+    /// Last tab is never closed, returns closed tab
+    pub fn close(&mut self, index: usize) -> Option<Tab> {
+        if index >= self.tabs.len() || self.tabs.len() == 1 {
+            return None;
+        }
+        let tab = self.tabs.remove(index);
+        if self.active > index || self.active == self.tabs.len() {
+            self.active -= 1;
+        }
+        Some(tab)
+    }
+    pub fn toggle_about(&mut self) {
+        self.about = match self.about {
+            Some(_) => None,
+            None => Some(AboutContent::new()),
+        };
+    }
+    pub fn view(&mut self) -> Element<'_, Message> {
+        let active = self.active;
+        let closable = self.tabs.len() > 1;
+        let mut tab_bar = Row::new().spacing(2);
+        let mut content = None;
+        for (index, tab) in self.tabs.iter_mut().enumerate() {
+            let title = tab.title();
+            let Tab {
+                content: tab_content,
+                select_button_state,
+                close_button_state,
+                ..
+            } = tab;
+            let mut tab_row = Row::new().push(
+                Button::new(select_button_state, Text::new(title).size(16))
+                    .on_press(Message::SelectTab(index))
+                    .style(style::TabButton {
+                        selected: index == active,
+                    }),
+            );
+            if closable {
+                tab_row = tab_row.push(
+                    Button::new(close_button_state, Text::new("×").size(16))
+                        .on_press(Message::CloseTab(index))
+                        .style(style::TabButton {
+                            selected: index == active,
+                        }),
+                );
+            }
+            tab_bar = tab_bar.push(tab_row);
+            if index == active && self.about.is_none() {
+                content = Some(tab_content.view());
+            }
+        }
+        let tab_bar = Row::new()
+            .push(Container::new(tab_bar).width(Length::Fill))
+            .push(
+                Button::new(
+                    &mut self.open_button_state,
+                    Text::new(i18n::tr("open_archive")).size(16),
+                )
+                .on_press(Message::OpenArchives)
+                .style(style::Dark::default()),
+            )
+            .push(Space::new(Length::Units(5), Length::Units(0)))
+            .push(
+                Button::new(
+                    &mut self.about_button_state,
+                    Text::new(i18n::tr("about")).size(16),
+                )
+                .on_press(Message::ToggleAbout)
+                .style(style::Dark::default()),
+            )
+            .push(Space::new(Length::Units(5), Length::Units(0)));
+        let content = match &mut self.about {
+            Some(about) => about.view(),
+            None => content.expect("Active tab out of range"),
+        };
+        Column::new()
+            .push(
+                Container::new(tab_bar)
+                    .width(Length::Fill)
+                    .height(Length::Units(26))
+                    .style(style::Dark {
+                        border_width: 0.0,
+                        ..Default::default()
+                    }),
+            )
+            .push(content)
+            .into()
+    }
+}
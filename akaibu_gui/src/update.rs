@@ -1,207 +1,418 @@
 use crate::{
     app::App,
+    i18n,
     logic::convert,
     logic::extract,
+    logic::jobs::{JobContext, JobManager},
     logic::preview,
     message::Status,
     message::{Message, Scene},
-    ui::archive::ArchiveContent,
-    ui::{content::Content, resource::ResourceContent},
+    ui::{content::Content, resource::ResourceContent, tabs::Tab},
+};
+use akaibu::{
+    error::{self, AkaibuError},
+    pipeline,
+    resource::{ResourceScheme, ResourceType},
+    scheme::registry::SchemeRegistry,
 };
-use akaibu::{error::AkaibuError, resource::ResourceType};
 use anyhow::Context;
-use extract::extract_all;
-use iced::Command;
+use iced::{Clipboard, Command};
 use image::buffer::ConvertBuffer;
 
 pub(crate) fn handle_message(
     app: &mut App,
     message: Message,
+    clipboard: &mut Clipboard,
 ) -> anyhow::Result<Command<Message>> {
     log::info!("{:?}", message);
+    match message {
+        Message::SelectTab(index) => app.tabs.select(index),
+        Message::CloseTab(index) => {
+            // Jobs of closed tab are not needed anymore, unless same file is
+            // open in another tab
+            if let Some(tab) = app.tabs.close(index) {
+                if app.tabs.by_file_mut(&tab.file).is_none() {
+                    app.jobs.cancel(&tab.file);
+                }
+            }
+        }
+        Message::ToggleAbout => app.tabs.toggle_about(),
+        Message::CopyBuildInfo => {
+            clipboard.write(akaibu::build_info().to_string());
+            app.tabs
+                .active_mut()
+                .set_status(Status::Normal(i18n::tr("build_info_copied")));
+        }
+        Message::OpenArchives => {
+            return Ok(Command::perform(
+                rfd::AsyncFileDialog::new()
+                    .set_title(&i18n::tr("open_archive_title"))
+                    .pick_files(),
+                |files| {
+                    Message::AddTabs(
+                        files
+                            .unwrap_or_default()
+                            .iter()
+                            .map(|file| file.path().to_path_buf())
+                            .collect(),
+                    )
+                },
+            ));
+        }
+        Message::AddTabs(files) => {
+            let mut commands = Vec::new();
+            for file in files {
+                match Content::open(&file) {
+                    Ok(content) => {
+                        app.tabs.push(Tab::new(file, content));
+                        commands.push(convert_opened_resource(
+                            app.tabs.active_mut(),
+                            &mut app.jobs,
+                        ));
+                    }
+                    Err(err) => {
+                        log::error!("{:?}: {:?}", file, err);
+                        app.tabs.active_mut().set_status(Status::Error(
+                            format!("{:?}: {}", file, error_text(&err)),
+                        ));
+                    }
+                }
+            }
+            return Ok(Command::batch(commands));
+        }
+        Message::JobFinished(id, message) => {
+            if let Some(job) = app.jobs.finish(id) {
+                if !app.jobs.is_busy(&job.file) {
+                    if let Some(tab) = app.tabs.by_file_mut(&job.file) {
+                        tab.set_busy(false);
+                    }
+                }
+            }
+            return handle_message(app, *message, clipboard);
+        }
+        Message::JobProgress(file, status) => {
+            // Progress sent just before job finished may come after its
+            // result and must not replace it
+            if app.jobs.is_busy(&file) {
+                if let Some(tab) = app.tabs.by_file_mut(&file) {
+                    tab.set_status(status);
+                }
+            }
+        }
+        Message::ResourceConverted(file, resource) => {
+            if let Some(tab) = app.tabs.by_file_mut(&file) {
+                tab.content =
+                    Content::ResourceView(ResourceContent::new(resource, file));
+            }
+        }
+        Message::ConversionFinished(file, status) => {
+            if let Some(tab) = app.tabs.by_file_mut(&file) {
+                tab.set_status(status);
+            }
+        }
+        Message::OpenPreview(file, resource, meta, file_name) => {
+            if let Some(tab) = app.tabs.by_file_mut(&file) {
+                if let Content::ArchiveView(ref mut content) = tab.content {
+                    content.preview.set_resource(resource, meta, file_name);
+                    content.preview.set_visible(true);
+                }
+            }
+        }
+        message => {
+            return handle_tab_message(
+                app.tabs.active_mut(),
+                &mut app.jobs,
+                message,
+                clipboard,
+            )
+        }
+    };
+    Ok(Command::none())
+}
+
+/// Handle message of currently selected tab
+fn handle_tab_message(
+    tab: &mut Tab,
+    jobs: &mut JobManager,
+    message: Message,
+    clipboard: &mut Clipboard,
+) -> anyhow::Result<Command<Message>> {
     match message {
         Message::OpenDirectory(dir_name) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+            if let Content::ArchiveView(ref mut content) = tab.content {
                 content.move_dir(dir_name)?;
             }
         }
         Message::BackDirectory => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+            if let Content::ArchiveView(ref mut content) = tab.content {
                 content.back_dir()?;
             }
         }
         Message::ConvertFile(file_entry) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                content.close_context_menu();
+                let archive = content.archive.clone();
+                if jobs.is_busy(&tab.file) {
+                    return Ok(Command::none());
+                }
+                let file = tab.file.clone();
+                let file_path = file.clone();
+                return Ok(start_job(
+                    tab,
+                    jobs,
+                    i18n::tr_args(
+                        "converting",
+                        &[file_entry.file_name.clone()],
+                    ),
+                    move |_| {
+                        convert::convert_resource_next_to(
+                            archive, file_entry, file_path,
+                        )
+                    },
+                    move |result| {
+                        Message::ConversionFinished(
+                            file.clone(),
+                            match result {
+                                Ok(path) => Status::Success(i18n::tr_args(
+                                    "converted",
+                                    &[format!("{:?}", path)],
+                                )),
+                                Err(err) => conversion_error(&err),
+                            },
+                        )
+                    },
+                ));
+            };
+        }
+        Message::ExtractFile(file_entry) => {
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                content.close_context_menu();
                 return Ok(Command::perform(
-                    convert::convert_resource(
+                    extract::extract_single_file(
                         content.archive.clone(),
                         file_entry,
-                        app.opt.file.clone(),
+                        tab.file.clone(),
                     ),
                     |result| match result {
-                        Ok(path) => Message::SetStatus(Status::Success(
-                            format!("Converted: {:?}", path),
-                        )),
-                        Err(err) => {
-                            Message::SetStatus(Status::Error(err.to_string()))
+                        Ok(path) => {
+                            Message::SetStatus(Status::Success(i18n::tr_args(
+                                "extracted",
+                                &[format!("{:?}", path)],
+                            )))
                         }
+                        Err(err) => Message::SetStatus(Status::Error(
+                            i18n::tr_args("extract_error", &[error_text(&err)]),
+                        )),
                     },
                 ));
             };
         }
-        Message::ExtractFile(file_entry) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+        Message::ExtractFileTo(file_entry) => {
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                content.close_context_menu();
                 return Ok(Command::perform(
-                    extract::extract_single_file(
+                    extract::extract_single_file_to(
                         content.archive.clone(),
                         file_entry,
-                        app.opt.file.clone(),
                     ),
                     |result| match result {
-                        Ok(path) => Message::SetStatus(Status::Success(
-                            format!("Extracted: {:?}", path),
+                        Ok(Some(path)) => {
+                            Message::SetStatus(Status::Success(i18n::tr_args(
+                                "extracted",
+                                &[format!("{:?}", path)],
+                            )))
+                        }
+                        Ok(None) => Message::SetStatus(Status::Empty),
+                        Err(err) => Message::SetStatus(Status::Error(
+                            i18n::tr_args("extract_error", &[error_text(&err)]),
                         )),
-                        Err(err) => Message::SetStatus(Status::Error(format!(
-                            "Error while extracting: {}",
-                            err
-                        ))),
                     },
                 ));
             };
         }
+        Message::CopyPath(file_entry) => {
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                content.close_context_menu();
+                let full_path =
+                    file_entry.full_path.to_string_lossy().into_owned();
+                clipboard.write(full_path.clone());
+                content.set_status(Status::Normal(i18n::tr_args(
+                    "copied",
+                    &[full_path],
+                )));
+            }
+        }
+        Message::OpenContextMenu(file_entry) => {
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                content.open_context_menu(&file_entry);
+            }
+        }
+        Message::CloseContextMenu => {
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                content.close_context_menu();
+            }
+        }
         Message::PreviewFile(file_entry) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
-                return Ok(Command::perform(
-                    preview::get_resource_type(
-                        content.archive.clone(),
-                        file_entry.clone(),
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                content.close_context_menu();
+                let archive = content.archive.clone();
+                if jobs.is_busy(&tab.file) {
+                    return Ok(Command::none());
+                }
+                let file = tab.file.clone();
+                let entry = file_entry.clone();
+                return Ok(start_job(
+                    tab,
+                    jobs,
+                    i18n::tr_args(
+                        "converting",
+                        &[file_entry.file_name.clone()],
                     ),
+                    move |_| preview::convert_entry(archive, entry),
                     move |result| match result {
-                        Ok(resource) => Message::OpenPreview(
+                        Ok((resource, meta)) => Message::OpenPreview(
+                            file.clone(),
                             resource,
+                            meta,
                             file_entry.file_name.clone(),
                         ),
-                        Err(err) => {
-                            Message::SetStatus(Status::Error(err.to_string()))
-                        }
+                        Err(err) => Message::ConversionFinished(
+                            file.clone(),
+                            conversion_error(&err),
+                        ),
                     },
                 ));
             }
         }
         Message::ExtractAll => {
-            if let Content::ArchiveView(ref mut content) = app.content {
-                let mut commands = vec![Command::perform(async {}, |_| {
-                    Message::SetStatus(Status::Normal(
-                        "Extracting...".to_string(),
-                    ))
-                })];
-                if content.convert_all {
-                    commands.push(Command::perform(
-                        extract::extract_all_with_convert(
-                            content.archive.clone(),
-                            content
-                                .navigable_dir
-                                .get_root_dir()
-                                .get_all_files()
-                                .cloned()
-                                .collect(),
-                            app.opt.file.clone(),
-                        ),
-                        |result| match result {
-                            Ok(path) => Message::SetStatus(Status::Success(
-                                format!("Extracted all! {:?}", path),
-                            )),
-                            Err(err) => Message::SetStatus(Status::Error(
-                                format!("Error while extracting: {}", err),
-                            )),
+            if let Content::ArchiveView(ref mut content) = tab.content {
+                if jobs.is_busy(&tab.file) {
+                    return Ok(Command::none());
+                }
+                let archive = content.archive.clone();
+                let files =
+                    content.navigable_dir.files().cloned().collect::<Vec<_>>();
+                let convert_all = content.convert_all;
+                let file = tab.file.clone();
+                let file_path = file.clone();
+                let status = i18n::tr("extracting");
+                return Ok(if convert_all {
+                    start_job(
+                        tab,
+                        jobs,
+                        status,
+                        move |context| {
+                            extract::extract_all_with_convert(
+                                archive, files, file_path, context,
+                            )
+                        },
+                        move |result| {
+                            Message::ConversionFinished(
+                                file.clone(),
+                                match result {
+                                    Ok(path) => Status::Success(i18n::tr_args(
+                                        "extracted_all",
+                                        &[format!("{:?}", path)],
+                                    )),
+                                    Err(err) => extraction_error(&err),
+                                },
+                            )
                         },
-                    ));
+                    )
                 } else {
-                    commands.push(Command::perform(
-                        extract_all(
-                            content.archive.clone(),
-                            content
-                                .navigable_dir
-                                .get_root_dir()
-                                .get_all_files()
-                                .cloned()
-                                .collect(),
-                            app.opt.file.clone(),
-                        ),
-                        |result| match result {
-                            Ok(path) => Message::SetStatus(Status::Success(
-                                format!("Extracted all! {:?}", path),
-                            )),
-                            Err(err) => Message::SetStatus(Status::Error(
-                                format!("Error while extracting: {}", err),
-                            )),
+                    start_job(
+                        tab,
+                        jobs,
+                        status,
+                        move |context| {
+                            extract::extract_all(
+                                archive, files, file_path, context,
+                            )
                         },
-                    ));
-                }
-                return Ok(Command::batch(commands));
+                        move |result| {
+                            Message::ConversionFinished(
+                                file.clone(),
+                                match result {
+                                    Ok((path, progress)) => {
+                                        Status::Success(i18n::tr_args(
+                                            "extracted_all_stats",
+                                            &[
+                                                format!("{:?}", path),
+                                                bytesize::to_string(
+                                                    progress.bytes_written,
+                                                    false,
+                                                ),
+                                                format!(
+                                                    "{:.1}",
+                                                    progress
+                                                        .elapsed
+                                                        .as_secs_f32()
+                                                ),
+                                            ],
+                                        ))
+                                    }
+                                    Err(err) => extraction_error(&err),
+                                },
+                            )
+                        },
+                    )
+                });
             };
         }
         Message::UpdateScrollbar(progress) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+            if let Content::ArchiveView(ref mut content) = tab.content {
                 content.set_progress(progress);
             }
         }
         Message::MoveScene(scene) => match scene {
             Scene::ArchiveView(scheme) => {
-                let (archive, dir) = scheme.extract(&app.opt.file)?;
-                app.content = Content::ArchiveView(Box::new(
-                    ArchiveContent::new(archive, dir),
-                ));
+                tab.content = Content::extract(scheme, &tab.file, None)?;
             }
-            Scene::ResourceView(scheme, file_path) => {
-                let resource = scheme.convert(&app.opt.file)?;
-                app.content = Content::ResourceView(ResourceContent::new(
-                    resource, file_path,
-                ));
+            Scene::ResourceView(scheme, _) => {
+                return Ok(convert_resource_file(tab, jobs, scheme));
             }
         },
-        Message::SetStatus(status) => match app.content {
-            Content::ArchiveView(ref mut content) => {
-                content.set_status(status);
-            }
-            Content::SchemeView(ref mut content) => {
-                content.set_status(status);
-            }
-            Content::ResourceView(ref mut content) => {
-                content.set_status(status);
-            }
-            Content::ResourceSchemeView(ref mut content) => {
-                content.set_status(status);
-            }
-        },
-        Message::OpenPreview(resource, file_name) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
-                content.preview.set_resource(resource, file_name);
-                content.preview.set_visible(true);
+        Message::SetStatus(status) => tab.set_status(status),
+        Message::CancelConversion => {
+            if jobs.cancel(&tab.file) {
+                tab.set_status(Status::Normal(i18n::tr("cancelling")));
             }
         }
         Message::ClosePreview => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+            if let Content::ArchiveView(ref mut content) = tab.content {
                 content.preview.set_visible(false);
             }
         }
         Message::ConvertAllToggle(convert_all) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+            if let Content::ArchiveView(ref mut content) = tab.content {
                 content.convert_all = convert_all;
             }
         }
+        Message::PasswordChanged(password) => {
+            if let Content::PasswordView(ref mut content) = tab.content {
+                content.password = password;
+            }
+        }
+        Message::SubmitPassword => {
+            if let Content::PasswordView(ref content) = tab.content {
+                let scheme = content.scheme.clone();
+                let password = content.password.clone();
+                tab.content =
+                    Content::extract(scheme, &tab.file, Some(&password))?;
+            }
+        }
         Message::PatternChanged(pattern) => {
-            if let Content::ArchiveView(ref mut content) = app.content {
+            if let Content::ArchiveView(ref mut content) = tab.content {
                 content.pattern = pattern;
             }
         }
         Message::FormatChanged(format) => {
-            if let Content::ResourceView(ref mut content) = app.content {
+            if let Content::ResourceView(ref mut content) = tab.content {
                 content.format = format;
             }
         }
         Message::SaveResource => {
-            if let Content::ResourceView(ref mut content) = app.content {
+            if let Content::ResourceView(ref mut content) = tab.content {
                 return Ok(Command::perform(
                     iced::futures::future::ready(
                         convert::write_resource_with_format(
@@ -212,16 +423,16 @@ pub(crate) fn handle_message(
                     ),
                     |result| match result {
                         Ok(path) => Message::SetStatus(Status::Success(
-                            format!("Saved: {:?}", path),
+                            i18n::tr_args("saved", &[format!("{:?}", path)]),
                         )),
                         Err(err) => {
-                            Message::SetStatus(Status::Error(err.to_string()))
+                            Message::SetStatus(Status::Error(error_text(&err)))
                         }
                     },
                 ));
             }
         }
-        Message::PrevSprite => match app.content {
+        Message::PrevSprite => match tab.content {
             Content::ResourceView(ref mut content) => {
                 content.dec_sprite_index()
             }
@@ -230,7 +441,7 @@ pub(crate) fn handle_message(
             }
             _ => (),
         },
-        Message::NextSprite => match app.content {
+        Message::NextSprite => match tab.content {
             Content::ResourceView(ref mut content) => {
                 content.inc_sprite_index()
             }
@@ -240,10 +451,12 @@ pub(crate) fn handle_message(
             _ => (),
         },
         Message::SaveSprite(sprite_index) => {
-            if let Content::ResourceView(ref mut content) = app.content {
+            if let Content::ResourceView(ref mut content) = tab.content {
                 let resource =
-                    if let ResourceType::SpriteSheet { ref sprites } =
-                        content.resource
+                    if let ResourceType::SpriteSheet { ref sprites }
+                    | ResourceType::Animation {
+                        frames: ref sprites,
+                    } = content.resource
                     {
                         ResourceType::RgbaImage {
                             image: sprites
@@ -269,29 +482,148 @@ pub(crate) fn handle_message(
                     ),
                     |result| match result {
                         Ok(path) => Message::SetStatus(Status::Success(
-                            format!("Saved: {:?}", path),
+                            i18n::tr_args("saved", &[format!("{:?}", path)]),
                         )),
                         Err(err) => {
-                            Message::SetStatus(Status::Error(err.to_string()))
+                            Message::SetStatus(Status::Error(error_text(&err)))
                         }
                     },
                 ));
             }
         }
-        Message::Error(err) => match app.content {
-            Content::ArchiveView(ref mut content) => {
-                content.set_status(Status::Error(err));
-            }
-            Content::SchemeView(ref mut content) => {
-                content.set_status(Status::Error(err));
-            }
-            Content::ResourceView(ref mut content) => {
-                content.set_status(Status::Error(err));
-            }
-            Content::ResourceSchemeView(ref mut content) => {
-                content.set_status(Status::Error(err));
-            }
-        },
+        Message::ToggleLanguage => {
+            i18n::set_language(i18n::language().other());
+        }
+        Message::ReloadKeys => {
+            let status = match SchemeRegistry::global().reload() {
+                Ok(count) => Status::Success(i18n::tr_args(
+                    "keys_reloaded",
+                    &[count.to_string()],
+                )),
+                Err(err) => Status::Error(err.to_string()),
+            };
+            return Ok(Command::perform(async {}, move |_| {
+                Message::SetStatus(status.clone())
+            }));
+        }
+        Message::Error(err) => tab.set_status(Status::Error(err)),
+        // Handled for whole app
+        Message::SelectTab(_)
+        | Message::CloseTab(_)
+        | Message::ToggleAbout
+        | Message::CopyBuildInfo
+        | Message::OpenArchives
+        | Message::AddTabs(_)
+        | Message::ResourceConverted(..)
+        | Message::ConversionFinished(..)
+        | Message::JobProgress(..)
+        | Message::JobFinished(..)
+        | Message::OpenPreview(..) => (),
     };
     Ok(Command::none())
 }
+
+/// Start job for tab. Footer shows status and actions of tab stay disabled
+/// until all of its jobs finish.
+fn start_job<T, F, M>(
+    tab: &mut Tab,
+    jobs: &mut JobManager,
+    status: String,
+    operation: F,
+    on_finished: M,
+) -> Command<Message>
+where
+    T: Send + 'static,
+    F: FnOnce(&JobContext) -> anyhow::Result<T> + Send + 'static,
+    M: Fn(anyhow::Result<T>) -> Message + Send + 'static,
+{
+    tab.set_busy(true);
+    tab.set_status(Status::Normal(status.clone()));
+    jobs.spawn(&tab.file, status, operation, on_finished)
+}
+
+/// Start converting resource of tab opened with universal scheme
+pub(crate) fn convert_opened_resource(
+    tab: &mut Tab,
+    jobs: &mut JobManager,
+) -> Command<Message> {
+    match tab.content.universal_resource_scheme() {
+        Some(scheme) => convert_resource_file(tab, jobs, scheme),
+        None => Command::none(),
+    }
+}
+
+/// Convert loose resource of tab on worker thread, so huge images do not
+/// freeze UI
+fn convert_resource_file(
+    tab: &mut Tab,
+    jobs: &mut JobManager,
+    scheme: Box<dyn ResourceScheme>,
+) -> Command<Message> {
+    if jobs.is_busy(&tab.file) {
+        return Command::none();
+    }
+    let file = tab.file.clone();
+    let file_path = file.clone();
+    let status = i18n::tr_args("converting", &[tab.title()]);
+    start_job(
+        tab,
+        jobs,
+        status,
+        move |_| pipeline::convert_file(scheme.as_ref(), &file_path),
+        move |result| match result {
+            Ok(resource) => Message::ResourceConverted(file.clone(), resource),
+            Err(err) => Message::ConversionFinished(
+                file.clone(),
+                conversion_error(&err),
+            ),
+        },
+    )
+}
+
+/// Status of failed conversion, cancelling it is not an error
+fn conversion_error(err: &anyhow::Error) -> Status {
+    if error::is_cancelled(err) {
+        Status::Normal(i18n::tr("conversion_cancelled"))
+    } else {
+        Status::Error(error_text(err))
+    }
+}
+
+/// Status of failed extraction, cancelling it is not an error
+fn extraction_error(err: &anyhow::Error) -> Status {
+    if error::is_cancelled(err) {
+        Status::Normal(i18n::tr("extraction_cancelled"))
+    } else if let Some(failures) = error::entry_failures(err) {
+        // Remaining entries were extracted, first failure is shown as
+        // example and all are logged
+        let first = failures
+            .first()
+            .map(|failure| {
+                format!("{}: {}", failure.path.display(), failure.reason)
+            })
+            .unwrap_or_default();
+        Status::Error(i18n::tr_args(
+            "entries_failed",
+            &[failures.len().to_string(), first],
+        ))
+    } else {
+        Status::Error(i18n::tr_args("extract_error", &[error_text(err)]))
+    }
+}
+
+/// Status text of failed operation. Unsupported versions are spelled out in
+/// current language, as they tell which version of file would be needed.
+fn error_text(err: &anyhow::Error) -> String {
+    match error::unsupported_version(err) {
+        Some(AkaibuError::UnsupportedVersion {
+            scheme,
+            found,
+            expected,
+        }) => i18n::tr_args(
+            "unsupported_version",
+            &[scheme.clone(), found.clone(), expected.clone()],
+        ),
+        _ => err.to_string(),
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{resource, to_py_err};
+use akaibu::{
+    archive::FileEntry, pipeline::ArchiveHandle, util::text::display_name,
+};
+use pyo3::{exceptions::PyKeyError, prelude::*, types::PyBytes};
+use std::path::Path;
+
+/// Opened archive, entries are looked up by their path inside archive
+#[pyclass(name = "Archive")]
+#[derive(Debug)]
+pub(crate) struct PyArchive {
+    handle: ArchiveHandle,
+}
+
+/// Entry of archive
+#[pyclass(name = "Entry")]
+#[derive(Debug, Clone)]
+pub(crate) struct PyEntry {
+    /// Path inside archive with `/` separators, names that are not valid
+    /// UTF-8 are decoded as SHIFT_JIS
+    #[pyo3(get)]
+    path: String,
+    /// Size of entry as stored in archive
+    #[pyo3(get)]
+    size: u64,
+    /// Offset of entry data in archive
+    #[pyo3(get)]
+    offset: u64,
+}
+
+/// Path of entry as given to Python, entries are found by it too
+fn py_path(path: &Path) -> String {
+    display_name(path).to_string().replace('\\', "/")
+}
+
+impl PyArchive {
+    pub(crate) fn new(handle: ArchiveHandle) -> Self {
+        Self { handle }
+    }
+    fn find(&self, path: &str) -> PyResult<&FileEntry> {
+        // Compared the same way path is given by `list`
+        let normalized = py_path(Path::new(path));
+        self.handle
+            .dir()
+            .files()
+            .find(|entry| py_path(&entry.full_path) == normalized)
+            .ok_or_else(|| PyKeyError::new_err(path.to_string()))
+    }
+}
+
+#[pymethods]
+impl PyArchive {
+    /// All entries of archive
+    #[pyo3(text_signature = "($self)")]
+    fn list(&self) -> Vec<PyEntry> {
+        self.handle
+            .dir()
+            .files()
+            .map(|entry| PyEntry {
+                path: py_path(&entry.full_path),
+                size: entry.file_size,
+                offset: entry.file_offset,
+            })
+            .collect()
+    }
+    /// Entry contents after decryption, as bytes
+    #[pyo3(text_signature = "($self, path)")]
+    fn extract(&self, py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let entry = self.find(path)?;
+        let contents = py
+            .allow_threads(|| self.handle.archive().extract(entry))
+            .map_err(to_py_err)?
+            .contents;
+        Ok(PyBytes::new(py, &contents).into())
+    }
+    /// Entry converted like `akaibu_py.convert` does, entries of unknown
+    /// type are returned as bytes
+    #[pyo3(text_signature = "($self, path)")]
+    fn convert(&self, py: Python<'_>, path: &str) -> PyResult<PyObject> {
+        let entry = self.find(path)?;
+        let converted = py
+            .allow_threads(|| self.handle.convert(entry))
+            .map_err(to_py_err)?;
+        resource::converted_to_python(py, converted.contents)
+    }
+    fn __len__(&self) -> usize {
+        self.handle.dir().files().count()
+    }
+}
+
+#[pymethods]
+impl PyEntry {
+    fn __repr__(&self) -> String {
+        format!("Entry(path={:?}, size={})", self.path, self.size)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use akaibu::archive::{Directory, DirectoryArchive, NavigableDirectory};
+    use std::path::PathBuf;
+
+    fn archive(paths: Vec<PathBuf>) -> PyArchive {
+        let root_dir = Directory::from_entries(
+            paths.into_iter().map(|path| FileEntry::new(path, 0, 0)),
+        );
+        PyArchive::new(ArchiveHandle::new(
+            Box::new(DirectoryArchive::new(Path::new("."))),
+            NavigableDirectory::new(root_dir),
+        ))
+    }
+
+    /// Every path returned by `list` finds its own entry
+    fn assert_paths_found(archive: &PyArchive) {
+        for entry in archive.list() {
+            let found = archive.find(&entry.path).expect("Entry not found");
+            assert_eq!(py_path(&found.full_path), entry.path);
+        }
+    }
+
+    #[test]
+    fn find_entry_by_path_of_entry() {
+        let archive = archive(vec![
+            PathBuf::from("bg/BG01.png"),
+            PathBuf::from("script\\start.txt"),
+        ]);
+        assert_paths_found(&archive);
+        let found = archive.find("script\\start.txt").unwrap();
+        assert_eq!(found.full_path, Path::new("script\\start.txt"));
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn find_entry_with_shift_jis_name() {
+        use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+        // 背景.png in SHIFT_JIS
+        let name = OsStr::from_bytes(b"\x94\x77\x8C\x69.png");
+        let archive = archive(vec![PathBuf::from(name)]);
+        assert_eq!(archive.list()[0].path, "背景.png");
+        assert_paths_found(&archive);
+    }
+}
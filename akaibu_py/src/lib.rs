@@ -0,0 +1,108 @@
+#![deny(
+    rust_2018_idioms,
+    unreachable_pub,
+    unused_imports,
+    unused_mut,
+    missing_debug_implementations
+)]
+
+mod archive;
+mod resource;
+
+use akaibu::{
+    key_file::KeyFile,
+    magic,
+    pipeline::{self, ArchiveHandle},
+    resource::ResourceMagic,
+};
+use pyo3::{exceptions::PyRuntimeError, prelude::*, wrap_pyfunction};
+use std::path::PathBuf;
+
+/// Errors of core library are raised as RuntimeError with their context
+pub(crate) fn to_py_err(err: anyhow::Error) -> PyErr {
+    PyRuntimeError::new_err(format!("{:#}", err))
+}
+
+/// Open archive. Scheme is detected from archive contents unless given by
+/// name, as listed by `schemes`.
+#[pyfunction]
+#[pyo3(text_signature = "(path, scheme=None, key_file=None)")]
+fn open(
+    py: Python<'_>,
+    path: PathBuf,
+    scheme: Option<String>,
+    key_file: Option<PathBuf>,
+) -> PyResult<archive::PyArchive> {
+    let sniffed = magic::sniff(&path).map_err(to_py_err)?;
+    let archive_magic = magic::Archive::detect(&path, &sniffed);
+    let scheme = archive_magic
+        .get_schemes()
+        .into_iter()
+        .find(|s| scheme.as_ref().map_or(true, |name| &s.get_name() == name))
+        .ok_or_else(|| {
+            PyRuntimeError::new_err(match &scheme {
+                Some(name) => {
+                    format!("Unknown scheme for {:?}: {}", path, name)
+                }
+                None => format!("Archive type of {:?} not recognized", path),
+            })
+        })?;
+    let key_file = key_file
+        .map(|key_file| KeyFile::load(&key_file))
+        .transpose()
+        .map_err(to_py_err)?;
+    let (archive, dir) = py
+        .allow_threads(move || match &key_file {
+            Some(key_file) => scheme.extract_with_key(&path, key_file),
+            None => scheme.extract(&path),
+        })
+        .map_err(to_py_err)?;
+    Ok(archive::PyArchive::new(ArchiveHandle::new(archive, dir)))
+}
+
+/// Names of schemes that can be passed to `open` for given archive
+#[pyfunction]
+#[pyo3(text_signature = "(path)")]
+fn schemes(path: PathBuf) -> PyResult<Vec<String>> {
+    let sniffed = magic::sniff(&path).map_err(to_py_err)?;
+    Ok(magic::Archive::detect(&path, &sniffed)
+        .get_schemes()
+        .iter()
+        .map(|scheme| scheme.get_name())
+        .collect())
+}
+
+/// Convert loose resource file. Images are returned as `height x width x 4`
+/// RGBA numpy arrays, animations and sprite sheets as lists of them and
+/// scripts as str.
+#[pyfunction]
+#[pyo3(text_signature = "(path)")]
+fn convert(py: Python<'_>, path: PathBuf) -> PyResult<PyObject> {
+    let sniffed = magic::sniff(&path).map_err(to_py_err)?;
+    let mut resource_magic = ResourceMagic::parse_magic(&sniffed.head);
+    if let ResourceMagic::Unrecognized = resource_magic {
+        resource_magic = ResourceMagic::parse_file_extension(&path);
+    }
+    let scheme = resource_magic.get_schemes().into_iter().next();
+    let scheme = scheme.ok_or_else(|| {
+        PyRuntimeError::new_err(format!(
+            "Resource type of {:?} not recognized",
+            path
+        ))
+    })?;
+    let resource = py
+        .allow_threads(|| pipeline::convert_file(scheme.as_ref(), &path))
+        .map_err(to_py_err)?;
+    resource::to_python(py, resource)
+}
+
+/// Python bindings of akaibu
+#[pymodule]
+fn akaibu_py(_py: Python<'_>, m: &PyModule) -> PyResult<()> {
+    m.add_class::<archive::PyArchive>()?;
+    m.add_class::<archive::PyEntry>()?;
+    m.add_function(wrap_pyfunction!(open, m)?)?;
+    m.add_function(wrap_pyfunction!(schemes, m)?)?;
+    m.add_function(wrap_pyfunction!(convert, m)?)?;
+    Ok(())
+}
@@ -0,0 +1,45 @@
+use akaibu::{pipeline::Converted, resource::ResourceType};
+use image::RgbaImage;
+use numpy::PyArray;
+use pyo3::{prelude::*, types::PyBytes};
+
+/// Converted resource as Python object, `None` for resources without
+/// contents
+pub(crate) fn to_python(
+    py: Python<'_>,
+    resource: ResourceType,
+) -> PyResult<PyObject> {
+    match resource {
+        ResourceType::RgbaImage { image } => image_to_array(py, image),
+        ResourceType::SpriteSheet { sprites: images }
+        | ResourceType::Animation { frames: images } => Ok(images
+            .into_iter()
+            .map(|image| image_to_array(py, image))
+            .collect::<PyResult<Vec<PyObject>>>()?
+            .into_py(py)),
+        ResourceType::Text(text) => Ok(text.into_py(py)),
+        ResourceType::Other => Ok(py.None()),
+    }
+}
+
+/// Entry converted when recognized, raw entries are returned as bytes
+pub(crate) fn converted_to_python(
+    py: Python<'_>,
+    converted: Converted,
+) -> PyResult<PyObject> {
+    match converted {
+        Converted::Resource(resource) => to_python(py, resource),
+        Converted::Raw(contents) => Ok(PyBytes::new(py, &contents).into()),
+    }
+}
+
+/// `height x width x 4` array of RGBA pixels, pixels are moved not copied
+fn image_to_array(py: Python<'_>, image: RgbaImage) -> PyResult<PyObject> {
+    let (width, height) = image.dimensions();
+    let array = PyArray::from_vec(py, image.into_raw()).reshape([
+        height as usize,
+        width as usize,
+        4,
+    ])?;
+    Ok(array.into_py(py))
+}
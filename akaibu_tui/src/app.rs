@@ -0,0 +1,326 @@
+use akaibu::{
+    archive::{Archive, FileEntry, NavigableDirectory},
+    error,
+    pipeline::{self, ExtractOptions},
+    progress::ProgressTracker,
+};
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
+use ratatui::widgets::ListState;
+use std::{
+    collections::BTreeSet,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+/// Rows moved by page up and page down
+const PAGE_SIZE: usize = 20;
+
+/// Line of entry list
+#[derive(Debug, Clone)]
+pub(crate) enum Row {
+    Parent,
+    Directory(String),
+    File(FileEntry),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Mode {
+    Browse,
+    /// Typed keys edit search pattern
+    Search,
+}
+
+pub(crate) struct App {
+    archive: Box<dyn Archive>,
+    dir: NavigableDirectory,
+    pub(crate) archive_path: PathBuf,
+    output_dir: PathBuf,
+    pub(crate) rows: Vec<Row>,
+    pub(crate) list_state: ListState,
+    /// Full paths of files marked for extraction
+    pub(crate) marked: BTreeSet<PathBuf>,
+    pub(crate) mode: Mode,
+    /// Files of whole archive are listed instead of current directory
+    /// while pattern is not empty
+    pub(crate) pattern: String,
+    matcher: SkimMatcherV2,
+    pending_extraction: Option<Vec<FileEntry>>,
+    pub(crate) status: String,
+    pub(crate) quit: bool,
+}
+
+impl fmt::Debug for App {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("App")
+            .field("archive_path", &self.archive_path)
+            .field("current_dir", &self.dir.get_current_full_path())
+            .field("mode", &self.mode)
+            .field("pattern", &self.pattern)
+            .finish()
+    }
+}
+
+impl App {
+    pub(crate) fn new(
+        archive: Box<dyn Archive>,
+        dir: NavigableDirectory,
+        archive_path: &Path,
+        output_dir: PathBuf,
+    ) -> Self {
+        let mut app = Self {
+            archive,
+            dir,
+            archive_path: archive_path.to_path_buf(),
+            output_dir,
+            rows: Vec::new(),
+            list_state: ListState::default(),
+            marked: BTreeSet::new(),
+            mode: Mode::Browse,
+            pattern: String::new(),
+            matcher: SkimMatcherV2::default(),
+            pending_extraction: None,
+            status: String::from("Press ? for help"),
+            quit: false,
+        };
+        app.refresh_rows();
+        app
+    }
+    pub(crate) fn current_dir(&self) -> String {
+        self.dir.get_current_full_path()
+    }
+    pub(crate) fn has_pending_extraction(&self) -> bool {
+        self.pending_extraction.is_some()
+    }
+    pub(crate) fn handle_key(&mut self, key: KeyEvent) {
+        if key.modifiers.contains(KeyModifiers::CONTROL)
+            && key.code == KeyCode::Char('c')
+        {
+            self.quit = true;
+            return;
+        }
+        match self.mode {
+            Mode::Browse => self.handle_browse_key(key.code),
+            Mode::Search => self.handle_search_key(key.code),
+        }
+    }
+    fn handle_browse_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Char('q') => self.quit = true,
+            KeyCode::Esc if !self.pattern.is_empty() => {
+                self.pattern.clear();
+                self.refresh_rows();
+            }
+            KeyCode::Esc => self.quit = true,
+            KeyCode::Down | KeyCode::Char('j') => self.move_selection(1),
+            KeyCode::Up | KeyCode::Char('k') => self.move_selection(-1),
+            KeyCode::PageDown => self.move_selection(PAGE_SIZE as isize),
+            KeyCode::PageUp => self.move_selection(-(PAGE_SIZE as isize)),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => {
+                self.select(self.rows.len().saturating_sub(1))
+            }
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                self.open_selected()
+            }
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => {
+                self.back_dir()
+            }
+            KeyCode::Char(' ') => self.toggle_mark(),
+            KeyCode::Char('/') => {
+                self.mode = Mode::Search;
+                self.status = String::from(
+                    "Search: Enter to keep results, Esc to cancel",
+                );
+            }
+            KeyCode::Char('e') => self.extract_marked_or_selected(),
+            KeyCode::Char('a') => {
+                self.pending_extraction =
+                    Some(self.dir.files().cloned().collect());
+                self.status = String::from("Extracting all entries...");
+            }
+            KeyCode::Char('?') => {
+                self.status = String::from(
+                    "j/k move, Enter open, Backspace back, Space mark, \
+                     / search, e extract marked or selected, a extract all, \
+                     q quit",
+                )
+            }
+            _ => (),
+        }
+    }
+    fn handle_search_key(&mut self, code: KeyCode) {
+        match code {
+            KeyCode::Enter => {
+                self.mode = Mode::Browse;
+                self.status = format!("{} matching entries", self.rows.len());
+            }
+            KeyCode::Esc => {
+                self.mode = Mode::Browse;
+                self.pattern.clear();
+                self.status.clear();
+                self.refresh_rows();
+            }
+            KeyCode::Backspace => {
+                self.pattern.pop();
+                self.refresh_rows();
+            }
+            KeyCode::Down => self.move_selection(1),
+            KeyCode::Up => self.move_selection(-1),
+            KeyCode::Char(c) => {
+                self.pattern.push(c);
+                self.refresh_rows();
+            }
+            _ => (),
+        }
+    }
+    /// Rebuild rows from current directory or search results
+    fn refresh_rows(&mut self) {
+        self.rows = if self.pattern.is_empty() {
+            let current = self.dir.get_current();
+            let parent = Some(Row::Parent).filter(|_| self.dir.has_parent());
+            parent
+                .into_iter()
+                .chain(current.directories.keys().cloned().map(Row::Directory))
+                .chain(current.files.iter().cloned().map(Row::File))
+                .collect()
+        } else {
+            let mut matches = self
+                .dir
+                .files()
+                .filter_map(|entry| {
+                    self.matcher
+                        .fuzzy_match(
+                            &entry.full_path.to_string_lossy(),
+                            &self.pattern,
+                        )
+                        .map(|score| (score, entry))
+                })
+                .collect::<Vec<_>>();
+            matches.sort_by(|a, b| b.0.cmp(&a.0));
+            matches
+                .into_iter()
+                .map(|(_, entry)| Row::File(entry.clone()))
+                .collect()
+        };
+        self.select(0);
+    }
+    fn select(&mut self, index: usize) {
+        self.list_state.select(if self.rows.is_empty() {
+            None
+        } else {
+            Some(index.min(self.rows.len() - 1))
+        });
+    }
+    fn move_selection(&mut self, delta: isize) {
+        let current = self.list_state.selected().unwrap_or(0) as isize;
+        self.select((current + delta).max(0) as usize);
+    }
+    fn selected_row(&self) -> Option<&Row> {
+        self.rows.get(self.list_state.selected()?)
+    }
+    fn open_selected(&mut self) {
+        match self.selected_row().cloned() {
+            Some(Row::Parent) => self.back_dir(),
+            Some(Row::Directory(name)) => {
+                self.dir.move_dir(&name);
+                self.refresh_rows();
+            }
+            Some(Row::File(entry)) => {
+                self.pending_extraction = Some(vec![entry]);
+                self.status = String::from("Extracting...");
+            }
+            None => (),
+        }
+    }
+    fn back_dir(&mut self) {
+        if self.pattern.is_empty() && self.dir.back_dir().is_some() {
+            self.refresh_rows();
+        }
+    }
+    /// Mark file, or every file under directory, or unmark them when all
+    /// already were marked
+    fn toggle_mark(&mut self) {
+        let paths = match self.selected_row() {
+            Some(row) => self.row_files(row),
+            None => return,
+        };
+        if paths
+            .iter()
+            .all(|entry| self.marked.contains(&entry.full_path))
+        {
+            for entry in &paths {
+                self.marked.remove(&entry.full_path);
+            }
+        } else {
+            self.marked
+                .extend(paths.into_iter().map(|entry| entry.full_path));
+        }
+        self.status = format!("{} entries marked", self.marked.len());
+        self.move_selection(1);
+    }
+    /// Files extracted for row, directories include all of their files
+    fn row_files(&self, row: &Row) -> Vec<FileEntry> {
+        match row {
+            Row::Parent => Vec::new(),
+            Row::Directory(name) => {
+                let mut prefix =
+                    PathBuf::from(self.dir.get_current_full_path());
+                prefix.push(name);
+                self.dir.files_with_prefix(&prefix).cloned().collect()
+            }
+            Row::File(entry) => vec![entry.clone()],
+        }
+    }
+    fn extract_marked_or_selected(&mut self) {
+        let entries = if self.marked.is_empty() {
+            match self.selected_row() {
+                Some(row) => self.row_files(row),
+                None => Vec::new(),
+            }
+        } else {
+            self.dir
+                .files()
+                .filter(|entry| self.marked.contains(&entry.full_path))
+                .cloned()
+                .collect()
+        };
+        if entries.is_empty() {
+            self.status = String::from("Nothing to extract");
+            return;
+        }
+        self.status = format!("Extracting {} entries...", entries.len());
+        self.pending_extraction = Some(entries);
+    }
+    /// Extract entries requested by last key, blocking until they are
+    /// written
+    pub(crate) fn run_extraction(&mut self) {
+        let entries = match self.pending_extraction.take() {
+            Some(entries) => entries,
+            None => return,
+        };
+        let progress = ProgressTracker::new(&entries);
+        let result = pipeline::extract_entries(
+            &self.archive,
+            &entries,
+            &self.output_dir,
+            &progress,
+            None,
+            &ExtractOptions::default(),
+        );
+        self.status = match result {
+            Ok(()) => {
+                let progress = progress.progress();
+                self.marked.clear();
+                format!(
+                    "Extracted {} entries ({} in {:.1}s) to {:?}",
+                    entries.len(),
+                    bytesize::to_string(progress.bytes_written, false),
+                    progress.elapsed.as_secs_f32(),
+                    self.output_dir
+                )
+            }
+            Err(err) => format!("Error: {}", error::describe(&err)),
+        };
+    }
+}
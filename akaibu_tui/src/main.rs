@@ -0,0 +1,160 @@
+#![deny(
+    rust_2018_idioms,
+    unsafe_code,
+    unused_imports,
+    unused_mut,
+    missing_debug_implementations
+)]
+
+mod app;
+mod ui;
+
+use akaibu::{
+    archive::{Archive, NavigableDirectory},
+    error::AkaibuError,
+    key_file::KeyFile,
+    magic, pipeline,
+    scheme::Scheme,
+};
+use app::App;
+use crossterm::{
+    event::{self, Event, KeyEventKind},
+    execute,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use ratatui::{
+    backend::{Backend, CrosstermBackend},
+    Terminal,
+};
+use std::path::{Path, PathBuf};
+use structopt::StructOpt;
+
+#[derive(StructOpt, Debug)]
+#[structopt()]
+pub(crate) struct Opt {
+    /// Archive to browse
+    #[structopt(name = "ARCHIVE", parse(from_os_str))]
+    archive: PathBuf,
+
+    /// Scheme to open archive with, by its name as listed by akaibu_cli probe. Required when archive type can not be detected
+    #[structopt(long)]
+    scheme: Option<String>,
+
+    /// Key file for archives that need per-install key. Defaults to key.dat next to archive
+    #[structopt(long, parse(from_os_str))]
+    key_file: Option<PathBuf>,
+
+    /// Password for archives protected by user supplied password
+    #[structopt(long)]
+    password: Option<String>,
+
+    /// Extracted entries are written to <OUTPUT_DIR>/<archive_stem>/
+    #[structopt(short, long, parse(from_os_str), default_value = "ext/")]
+    output_dir: PathBuf,
+}
+
+fn main() -> anyhow::Result<()> {
+    let opt = Opt::from_args();
+    // Archive is opened before taking over terminal, so errors are printed
+    // as usual
+    let (archive, dir) = open_archive(&opt)?;
+    let output_dir =
+        pipeline::archive_output_dir(&opt.output_dir, &opt.archive, true)?;
+    let mut app = App::new(archive, dir, &opt.archive, output_dir);
+
+    enable_raw_mode()?;
+    let mut stdout = std::io::stdout();
+    execute!(stdout, EnterAlternateScreen)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+    let result = run(&mut terminal, &mut app);
+
+    disable_raw_mode()?;
+    execute!(terminal.backend_mut(), LeaveAlternateScreen)?;
+    terminal.show_cursor()?;
+    result
+}
+
+fn run<B: Backend>(
+    terminal: &mut Terminal<B>,
+    app: &mut App,
+) -> anyhow::Result<()> {
+    while !app.quit {
+        terminal.draw(|f| ui::draw(f, app))?;
+        // Status telling extraction started is drawn before it runs
+        if app.has_pending_extraction() {
+            app.run_extraction();
+            continue;
+        }
+        if let Event::Key(key) = event::read()? {
+            if key.kind == KeyEventKind::Press {
+                app.handle_key(key);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Open archive with scheme given by name, or with universal scheme of
+/// detected archive type
+fn open_archive(
+    opt: &Opt,
+) -> anyhow::Result<(Box<dyn Archive>, NavigableDirectory)> {
+    let file = &opt.archive;
+    let archive_magic = magic::Archive::detect(file, &magic::sniff(file)?);
+    let scheme = match &opt.scheme {
+        Some(name) => magic::Archive::get_all_schemes()
+            .into_iter()
+            .find(|scheme| &scheme.get_name() == name)
+            .ok_or_else(|| {
+                AkaibuError::Custom(format!("Unknown scheme: {}", name))
+            })?,
+        None if archive_magic.is_universal() => archive_magic
+            .get_schemes()
+            .into_iter()
+            .next()
+            .ok_or_else(|| {
+                AkaibuError::Custom("Expected universal scheme".to_string())
+            })?,
+        None => {
+            return Err(AkaibuError::Custom(format!(
+                "Could not pick scheme for {:?}, pass one of them with \
+                 --scheme:\n{}",
+                file,
+                scheme_names(&archive_magic)
+            ))
+            .into())
+        }
+    };
+    open_with_scheme(opt, scheme.as_ref(), file)
+}
+
+fn scheme_names(archive_magic: &magic::Archive) -> String {
+    let schemes = match archive_magic {
+        magic::Archive::NotRecognized => magic::Archive::get_all_schemes(),
+        _ => archive_magic.get_schemes(),
+    };
+    schemes
+        .iter()
+        .map(|scheme| scheme.get_name())
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn open_with_scheme(
+    opt: &Opt,
+    scheme: &dyn Scheme,
+    file: &Path,
+) -> anyhow::Result<(Box<dyn Archive>, NavigableDirectory)> {
+    if scheme.needs_key_file() {
+        let key_file =
+            KeyFile::load_or_companion(opt.key_file.as_deref(), file)?;
+        scheme.extract_with_key(file, &key_file)
+    } else if let Some(password) = &opt.password {
+        scheme.extract_with_password(file, password)
+    } else {
+        scheme.extract(file)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::app::{App, Mode, Row};
+use ratatui::{
+    backend::Backend,
+    layout::{Constraint, Direction, Layout},
+    style::{Color, Modifier, Style},
+    text::{Span, Spans},
+    widgets::{Block, Borders, List, ListItem, Paragraph},
+    Frame,
+};
+
+const HELP: &str = "Enter open  Backspace back  Space mark  / search  \
+                    e extract  a extract all  q quit";
+
+pub(crate) fn draw<B: Backend>(f: &mut Frame<'_, B>, app: &mut App) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Length(1),
+            Constraint::Min(1),
+            Constraint::Length(1),
+            Constraint::Length(1),
+        ])
+        .split(f.size());
+
+    let header = match app.mode {
+        Mode::Search => Spans::from(vec![
+            Span::styled("/", Style::default().fg(Color::Yellow)),
+            Span::raw(app.pattern.clone()),
+        ]),
+        Mode::Browse if !app.pattern.is_empty() => Spans::from(format!(
+            "{} matching {:?}",
+            app.archive_path.display(),
+            app.pattern
+        )),
+        Mode::Browse => Spans::from(format!(
+            "{}:{}",
+            app.archive_path.display(),
+            app.current_dir()
+        )),
+    };
+    f.render_widget(Paragraph::new(header), chunks[0]);
+
+    let items = app
+        .rows
+        .iter()
+        .map(|row| ListItem::new(row_label(app, row)))
+        .collect::<Vec<_>>();
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL).title(format!(
+            "{} entries, {} marked",
+            app.rows.len(),
+            app.marked.len()
+        )))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+    f.render_stateful_widget(list, chunks[1], &mut app.list_state);
+
+    f.render_widget(Paragraph::new(app.status.clone()), chunks[2]);
+    f.render_widget(
+        Paragraph::new(HELP).style(Style::default().fg(Color::DarkGray)),
+        chunks[3],
+    );
+}
+
+fn row_label(app: &App, row: &Row) -> String {
+    match row {
+        Row::Parent => String::from("    ../"),
+        Row::Directory(name) => format!("    {}/", name),
+        Row::File(entry) => {
+            let mark = if app.marked.contains(&entry.full_path) {
+                '*'
+            } else {
+                ' '
+            };
+            // Search results come from whole archive, so full path is shown
+            let name = if app.pattern.is_empty() {
+                entry.file_name.clone()
+            } else {
+                entry.full_path.display().to_string()
+            };
+            format!(
+                "{}   {:<40} {:>10}",
+                mark,
+                name,
+                bytesize::to_string(entry.file_size, false)
+            )
+        }
+    }
+}